@@ -1,11 +1,20 @@
 use {
     anyhow::Result,
-    clap::{Args, Parser, Subcommand},
+    clap::{error::ErrorKind, Args, CommandFactory, FromArgMatches, Parser, Subcommand},
     log::error,
+    xtask::{
+        config::Config,
+        utils::{aliases, audit::audited, telemetry},
+    },
 };
 
 #[derive(Parser)]
-#[command(name = "xtask", about = "Build tasks", version)]
+#[command(
+    name = "xtask",
+    about = "Build tasks",
+    version,
+    after_long_help = xtask::failure::exit_codes_help()
+)]
 struct Xtask {
     #[command(flatten)]
     pub global: GlobalOptions,
@@ -16,20 +25,124 @@ struct Xtask {
 
 #[derive(Subcommand)]
 enum Commands {
-    #[command(about = "Hello")]
-    Hello,
+    #[command(about = "Summarize the workspace")]
+    Info(xtask::commands::info::CommandArgs),
     #[command(about = "Bump version")]
     BumpVersion(xtask::commands::bump_version::CommandArgs),
     #[command(about = "Update crate version")]
     UpdateCrate(xtask::commands::update_crate::CommandArgs),
     #[command(about = "Publish crates")]
-    Publish(xtask::commands::publish::CommandArgs),
+    Publish(Box<xtask::commands::publish::CommandArgs>),
+    #[command(about = "Yank or un-yank a version of every workspace crate")]
+    Yank(xtask::commands::yank::CommandArgs),
+    #[command(about = "Acquire, release or inspect release locks")]
+    Lock(xtask::commands::lock::CommandArgs),
+    #[command(about = "Check tools, credentials and workspace layout")]
+    Doctor(xtask::commands::doctor::CommandArgs),
+    #[command(about = "Run workspace lints")]
+    Lint(xtask::commands::lint::CommandArgs),
+    #[command(about = "Run the combined workspace checks")]
+    Check(xtask::commands::check::CommandArgs),
+    #[command(about = "Bump and publish a release")]
+    Release(xtask::commands::release::CommandArgs),
+    #[command(about = "Generate a dev container pinned to the workspace toolchain")]
+    GenerateDevcontainer(xtask::commands::generate_devcontainer::CommandArgs),
+    #[command(about = "Write deny.toml and audit.toml from the policy in xtask.toml")]
+    GeneratePolicy(xtask::commands::generate_policy::CommandArgs),
+    #[command(about = "Docker image helpers")]
+    Docker(xtask::commands::docker::CommandArgs),
+    #[command(about = "Build on-chain programs into dist/programs")]
+    BuildSbf(xtask::commands::build_sbf::CommandArgs),
+    #[command(about = "Rebuild a program hermetically and compare it to a deployment or artifact")]
+    VerifyBuild(xtask::commands::verify_build::CommandArgs),
+    #[command(about = "Download a GitHub release and verify its artifacts")]
+    VerifyRelease(xtask::commands::verify_release::CommandArgs),
+    #[command(about = "Package release binaries for distribution")]
+    Package(xtask::commands::package::CommandArgs),
+    #[command(about = "Run integration tests against a local test validator")]
+    Itest(xtask::commands::itest::CommandArgs),
+    #[command(about = "Test the workspace against the declared dependency version range")]
+    CheckCompat(xtask::commands::check_compat::CommandArgs),
+    #[command(about = "Report external dependencies behind the latest crates.io release")]
+    Outdated(xtask::commands::outdated::CommandArgs),
+    #[command(about = "Security advisory reports")]
+    Audit(xtask::commands::audit::CommandArgs),
+    #[command(about = "Dependency license reports")]
+    Licenses(xtask::commands::licenses::CommandArgs),
+    #[command(about = "Dependency graph reports")]
+    Deps(xtask::commands::deps::CommandArgs),
+    #[command(about = "Build every crate across its feature combinations")]
+    CheckFeatures(xtask::commands::check_features::CommandArgs),
+    #[command(about = "Build no_std crates for a bare target to catch std leakage")]
+    CheckNoStd(xtask::commands::check_no_std::CommandArgs),
+    #[command(about = "Build the crates that claim wasm support for wasm32")]
+    CheckWasm(xtask::commands::check_wasm::CommandArgs),
+    #[command(about = "Diff a crate's package against its latest published version")]
+    PackageDiff(xtask::commands::package_diff::CommandArgs),
+    #[command(
+        about = "Scan the files going into .crate packages and release tarballs for secrets"
+    )]
+    ScanSecrets(xtask::commands::scan_secrets::CommandArgs),
+    #[command(about = "Record the dependency versions compiled into released binaries")]
+    PinReport(xtask::commands::pin_report::CommandArgs),
+    #[command(about = "Run the tests and report slow or regressed ones")]
+    Test(xtask::commands::test::CommandArgs),
+    #[command(about = "Find the commit that broke a test command with git bisect")]
+    Bisect(xtask::commands::bisect::CommandArgs),
+    #[command(about = "Query the recorded release history")]
+    History(xtask::commands::history::CommandArgs),
+    #[command(about = "Report release branches the support policy no longer covers")]
+    EolCheck(xtask::commands::eol_check::CommandArgs),
+    #[command(about = "Estimate .crate sizes and flag crates close to the crates.io limit")]
+    CheckPackageSize(xtask::commands::check_package_size::CommandArgs),
+    #[command(about = "Promote a soaked release candidate to stable")]
+    Promote(xtask::commands::promote::CommandArgs),
+    #[command(about = "Check the release train schedule allows a release")]
+    Schedule(xtask::commands::schedule::CommandArgs),
+    #[command(about = "List published prereleases and flag the ones left unpromoted")]
+    PrereleaseReport(xtask::commands::prerelease_report::CommandArgs),
+    #[command(about = "Check workspace versions are new and not below crates.io")]
+    CheckVersionMonotonic(xtask::commands::check_version_monotonic::CommandArgs),
+    #[command(about = "Move the workspace to a new edition with cargo fix --edition")]
+    BumpEdition(xtask::commands::bump_edition::CommandArgs),
+    #[command(about = "Record or verify crate versions and content hashes of a release")]
+    VersionManifest(xtask::commands::version_manifest::CommandArgs),
+    #[command(about = "Generate a new workspace skeleton from a template")]
+    InitWorkspace(xtask::commands::init_workspace::CommandArgs),
+    #[command(about = "Run the configured code generators or check their output is committed")]
+    Codegen(xtask::commands::codegen::CommandArgs),
+    #[command(about = "Compare binaries' CLI output against checked-in snapshots")]
+    CliSnapshots(xtask::commands::cli_snapshots::CommandArgs),
+    #[command(about = "Report which team owns each crate, from maintainers.toml")]
+    Owners(xtask::commands::owners::CommandArgs),
+    #[command(about = "Document each crate's cargo features")]
+    Features(xtask::commands::features::CommandArgs),
+    #[command(about = "Apply the retention policy to nightly artifacts")]
+    Artifacts(xtask::commands::artifacts::CommandArgs),
+    #[command(about = "Prune stale files from shared target and sccache directories")]
+    CleanCache(xtask::commands::clean_cache::CommandArgs),
+    #[command(about = "Replace this binary with a verified prebuilt release")]
+    SelfUpdate(xtask::commands::self_update::CommandArgs),
 }
 
 #[derive(Args, Debug)]
 pub struct GlobalOptions {
     #[arg(short, long, global = true)]
     pub verbose: bool,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Restrict discovery to this subtree of the repository"
+    )]
+    pub scope: Option<std::path::PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Search git submodules for manifests and lockfiles, skipped by default"
+    )]
+    pub include_submodules: bool,
 }
 
 fn main() {
@@ -38,12 +151,30 @@ fn main() {
         for (i, cause) in err.chain().skip(1).enumerate() {
             error!("  {}: {}", i.saturating_add(1), cause);
         }
-        std::process::exit(1);
+        std::process::exit(xtask::failure::exit_code(&err));
     }
 }
 
+/// Parses the command line with the `[aliases]` of `xtask.toml` applied; a
+/// broken config is reported by the command that loads it. Returns the
+/// canonical name of the subcommand with the parsed arguments.
+fn parse() -> (String, Xtask) {
+    let aliases = Config::load().map(|c| c.aliases).unwrap_or_default();
+    let cmd = match aliases::register(Xtask::command(), &aliases) {
+        Ok(cmd) => cmd,
+        Err(err) => Xtask::command()
+            .error(ErrorKind::InvalidValue, format!("invalid [aliases]: {err}"))
+            .exit(),
+    };
+    let args = aliases::expand(&cmd, std::env::args_os().collect(), &aliases);
+    let mut matches = cmd.get_matches_from(args);
+    let name = matches.subcommand_name().unwrap_or_default().to_string();
+    let xtask = Xtask::from_arg_matches_mut(&mut matches).unwrap_or_else(|err| err.exit());
+    (name, xtask)
+}
+
 fn try_main() -> Result<()> {
-    let xtask = Xtask::parse();
+    let (name, xtask) = parse();
 
     if xtask.global.verbose {
         std::env::set_var("RUST_LOG", "debug");
@@ -52,16 +183,201 @@ fn try_main() -> Result<()> {
     }
     env_logger::init();
 
-    match xtask.command {
-        Commands::Hello => xtask::commands::hello::run()?,
+    if let Some(scope) = &xtask.global.scope {
+        xtask::utils::scope::set_scope(scope)?;
+    }
+    if xtask.global.include_submodules {
+        xtask::utils::discovery::include_submodules();
+    }
+
+    telemetry::timed(&name, || run(xtask.command))
+}
+
+fn run(command: Commands) -> Result<()> {
+    match command {
+        Commands::Info(args) => xtask::commands::info::run(args)?,
         Commands::BumpVersion(args) => {
-            xtask::commands::bump_version::run(args)?;
+            audited("bump-version", || xtask::commands::bump_version::run(args))?;
         }
         Commands::UpdateCrate(args) => {
-            xtask::commands::update_crate::run(args)?;
+            audited("update-crate", || xtask::commands::update_crate::run(args))?;
         }
         Commands::Publish(args) => {
-            xtask::commands::publish::run(args)?;
+            if args.subcommand.is_mutating() {
+                audited("publish", || xtask::commands::publish::run(*args))?;
+            } else {
+                xtask::commands::publish::run(*args)?;
+            }
+        }
+        Commands::Yank(args) => {
+            audited("yank", || xtask::commands::yank::run(args))?;
+        }
+        Commands::Lock(args) => {
+            if args.is_mutating() {
+                audited("lock", || xtask::commands::lock::run(args))?;
+            } else {
+                xtask::commands::lock::run(args)?;
+            }
+        }
+        Commands::Doctor(args) => {
+            xtask::commands::doctor::run(args)?;
+        }
+        Commands::Lint(args) => {
+            if args.is_mutating() {
+                audited("lint", || xtask::commands::lint::run(args))?;
+            } else {
+                xtask::commands::lint::run(args)?;
+            }
+        }
+        Commands::Check(args) => {
+            xtask::commands::check::run(args)?;
+        }
+        Commands::Release(args) => {
+            if args.is_mutating() {
+                audited("release", || xtask::commands::release::run(args))?;
+            } else {
+                xtask::commands::release::run(args)?;
+            }
+        }
+        Commands::GenerateDevcontainer(args) => {
+            xtask::commands::generate_devcontainer::run(args)?;
+        }
+        Commands::GeneratePolicy(args) => {
+            if args.is_mutating() {
+                audited("generate-policy", || {
+                    xtask::commands::generate_policy::run(args)
+                })?;
+            } else {
+                xtask::commands::generate_policy::run(args)?;
+            }
+        }
+        Commands::Docker(args) => {
+            audited("docker", || xtask::commands::docker::run(args))?;
+        }
+        Commands::BuildSbf(args) => {
+            xtask::commands::build_sbf::run(args)?;
+        }
+        Commands::VerifyBuild(args) => {
+            xtask::commands::verify_build::run(args)?;
+        }
+        Commands::VerifyRelease(args) => {
+            xtask::commands::verify_release::run(args)?;
+        }
+        Commands::Package(args) => {
+            audited("package", || xtask::commands::package::run(args))?;
+        }
+        Commands::Itest(args) => {
+            xtask::commands::itest::run(args)?;
+        }
+        Commands::CheckCompat(args) => {
+            xtask::commands::check_compat::run(args)?;
+        }
+        Commands::Outdated(args) => {
+            xtask::commands::outdated::run(args)?;
+        }
+        Commands::Audit(args) => {
+            xtask::commands::audit::run(args)?;
+        }
+        Commands::Licenses(args) => {
+            xtask::commands::licenses::run(args)?;
+        }
+        Commands::Deps(args) => {
+            xtask::commands::deps::run(args)?;
+        }
+        Commands::CheckFeatures(args) => {
+            xtask::commands::check_features::run(args)?;
+        }
+        Commands::CheckNoStd(args) => {
+            xtask::commands::check_no_std::run(args)?;
+        }
+        Commands::CheckWasm(args) => {
+            xtask::commands::check_wasm::run(args)?;
+        }
+        Commands::PackageDiff(args) => {
+            xtask::commands::package_diff::run(args)?;
+        }
+        Commands::Test(args) => {
+            xtask::commands::test::run(args)?;
+        }
+        Commands::Bisect(args) => {
+            xtask::commands::bisect::run(args)?;
+        }
+        Commands::History(args) => {
+            xtask::commands::history::run(args)?;
+        }
+        Commands::EolCheck(args) => {
+            xtask::commands::eol_check::run(args)?;
+        }
+        Commands::PinReport(args) => {
+            xtask::commands::pin_report::run(args)?;
+        }
+        Commands::ScanSecrets(args) => {
+            xtask::commands::scan_secrets::run(args)?;
+        }
+        Commands::CheckPackageSize(args) => {
+            xtask::commands::check_package_size::run(args)?;
+        }
+        Commands::Promote(args) => {
+            audited("promote", || xtask::commands::promote::run(args))?;
+        }
+        Commands::Schedule(args) => {
+            xtask::commands::schedule::run(args)?;
+        }
+        Commands::PrereleaseReport(args) => {
+            xtask::commands::prerelease_report::run(args)?;
+        }
+        Commands::CheckVersionMonotonic(args) => {
+            xtask::commands::check_version_monotonic::run(args)?;
+        }
+        Commands::BumpEdition(args) => {
+            audited("bump-edition", || xtask::commands::bump_edition::run(args))?;
+        }
+        Commands::VersionManifest(args) => {
+            if args.is_mutating() {
+                audited("version-manifest", || {
+                    xtask::commands::version_manifest::run(args)
+                })?;
+            } else {
+                xtask::commands::version_manifest::run(args)?;
+            }
+        }
+        Commands::InitWorkspace(args) => {
+            audited("init-workspace", || {
+                xtask::commands::init_workspace::run(args)
+            })?;
+        }
+        Commands::Codegen(args) => {
+            if args.is_mutating() {
+                audited("codegen", || xtask::commands::codegen::run(args))?;
+            } else {
+                xtask::commands::codegen::run(args)?;
+            }
+        }
+        Commands::CliSnapshots(args) => {
+            xtask::commands::cli_snapshots::run(args)?;
+        }
+        Commands::Owners(args) => {
+            xtask::commands::owners::run(args)?;
+        }
+        Commands::Features(args) => {
+            xtask::commands::features::run(args)?;
+        }
+        Commands::Artifacts(args) => {
+            if args.is_mutating() {
+                audited("artifacts", || xtask::commands::artifacts::run(args))?;
+            } else {
+                xtask::commands::artifacts::run(args)?;
+            }
+        }
+        Commands::CleanCache(args) => {
+            if args.is_mutating() {
+                audited("clean-cache", || xtask::commands::clean_cache::run(args))?;
+            } else {
+                xtask::commands::clean_cache::run(args)?;
+            }
+        }
+        Commands::SelfUpdate(args) => {
+            audited("self-update", || xtask::commands::self_update::run(args))?;
         }
     }
 
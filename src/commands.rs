@@ -1,4 +1,48 @@
+pub mod artifacts;
+pub mod audit;
+pub mod bisect;
+pub mod build_sbf;
+pub mod bump_edition;
 pub mod bump_version;
-pub mod hello;
+pub mod check;
+pub mod check_compat;
+pub mod check_features;
+pub mod check_no_std;
+pub mod check_package_size;
+pub mod check_version_monotonic;
+pub mod check_wasm;
+pub mod clean_cache;
+pub mod cli_snapshots;
+pub mod codegen;
+pub mod deps;
+pub mod docker;
+pub mod doctor;
+pub mod eol_check;
+pub mod features;
+pub mod generate_devcontainer;
+pub mod generate_policy;
+pub mod history;
+pub mod info;
+pub mod init_workspace;
+pub mod itest;
+pub mod licenses;
+pub mod lint;
+pub mod lock;
+pub mod outdated;
+pub mod owners;
+pub mod package;
+pub mod package_diff;
+pub mod pin_report;
+pub mod prerelease_report;
+pub mod promote;
 pub mod publish;
+pub mod release;
+pub mod scan_secrets;
+pub mod schedule;
+pub mod self_update;
+pub mod test;
 pub mod update_crate;
+pub mod verify_build;
+pub mod verify_release;
+pub mod version_manifest;
+pub mod yank;
@@ -0,0 +1,349 @@
+use {
+    crate::{
+        config::{ArtifactsConfig, Config},
+        utils::cargo::wildcard_match,
+    },
+    anyhow::{anyhow, Context, Result},
+    clap::{Args, Subcommand},
+    log::{info, warn},
+    std::{collections::BTreeMap, process::Command},
+};
+
+#[derive(Subcommand)]
+pub enum ArtifactsSubcommand {
+    #[command(about = "Delete all but the newest nightly releases, S3 entries and image tags")]
+    Prune {
+        #[arg(
+            long,
+            help = "Artifacts to keep per store [default: artifacts.keep-last]"
+        )]
+        keep_last: Option<usize>,
+        #[arg(long, help = "Only print what would be deleted")]
+        dry_run: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[command(subcommand)]
+    pub subcommand: ArtifactsSubcommand,
+}
+
+impl CommandArgs {
+    pub fn is_mutating(&self) -> bool {
+        matches!(
+            self.subcommand,
+            ArtifactsSubcommand::Prune { dry_run: false, .. }
+        )
+    }
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    match args.subcommand {
+        ArtifactsSubcommand::Prune { keep_last, dry_run } => {
+            let policy = &config.artifacts;
+            let keep = keep_last.unwrap_or(policy.keep_last);
+            let mut deleted = 0usize;
+            for store in stores(policy) {
+                let items = store.list()?;
+                let names: Vec<String> = items.iter().map(|(name, _)| name.clone()).collect();
+                let prune = to_prune(items, &policy.pattern, keep);
+                let kept: Vec<&str> = names
+                    .iter()
+                    .filter(|name| !prune.contains(name))
+                    .map(String::as_str)
+                    .collect();
+                info!(
+                    "{}: {} artifact(s) past the newest {keep}",
+                    store.describe(),
+                    prune.len()
+                );
+                for name in prune.iter() {
+                    if dry_run {
+                        info!("  would delete {name}");
+                        continue;
+                    }
+                    info!("  🗑️ deleting {name}");
+                    if store.delete(name, &kept)? {
+                        deleted = deleted.saturating_add(1);
+                    }
+                }
+            }
+            if !dry_run {
+                info!("✅ deleted {deleted} artifact(s)");
+            }
+            Ok(())
+        }
+    }
+}
+
+enum Store {
+    GithubReleases,
+    S3(String),
+    Image(String),
+}
+
+fn stores(policy: &ArtifactsConfig) -> Vec<Store> {
+    let mut stores = vec![];
+    if policy.github_releases {
+        stores.push(Store::GithubReleases);
+    }
+    if let Some(prefix) = &policy.s3 {
+        stores.push(Store::S3(prefix.clone()));
+    }
+    for image in policy.images.iter() {
+        stores.push(Store::Image(image.clone()));
+    }
+    stores
+}
+
+impl Store {
+    fn describe(&self) -> String {
+        match self {
+            Store::GithubReleases => String::from("GitHub releases"),
+            Store::S3(prefix) => prefix.clone(),
+            Store::Image(image) => image.clone(),
+        }
+    }
+
+    /// Names with their creation time (RFC 3339, or `YYYY-MM-DD HH:MM:SS`
+    /// for S3), which orders them by age.
+    fn list(&self) -> Result<Vec<(String, String)>> {
+        match self {
+            Store::GithubReleases => {
+                let output = tool(
+                    "gh",
+                    &[
+                        "release",
+                        "list",
+                        "--limit",
+                        "1000",
+                        "--json",
+                        "tagName,createdAt",
+                    ],
+                )?;
+                parse_releases(&output)
+            }
+            Store::S3(prefix) => {
+                // without the trailing `/` the listing is of the prefix
+                // itself, not of what it contains
+                let prefix = format!("{}/", prefix.trim_end_matches('/'));
+                let output = tool("aws", &["s3", "ls", "--recursive", &prefix])?;
+                Ok(parse_s3_ls(&output, s3_key_prefix(&prefix)))
+            }
+            Store::Image(image) => {
+                let mut tags = vec![];
+                for tag in tool("crane", &["ls", image])?
+                    .lines()
+                    .map(str::trim)
+                    .filter(|tag| !tag.is_empty())
+                {
+                    let config = tool("crane", &["config", &format!("{image}:{tag}")])?;
+                    tags.push((tag.to_string(), image_created(&config)?));
+                }
+                Ok(tags)
+            }
+        }
+    }
+
+    /// Deletes `name`, unless it is an image tag sharing its manifest with
+    /// one of the `kept` tags: registries only delete manifests, which would
+    /// take the kept tag with it. Returns whether anything was deleted.
+    fn delete(&self, name: &str, kept: &[&str]) -> Result<bool> {
+        match self {
+            Store::GithubReleases => {
+                tool("gh", &["release", "delete", name, "--yes", "--cleanup-tag"])?;
+            }
+            Store::S3(prefix) => {
+                let url = format!("{}/{name}", prefix.trim_end_matches('/'));
+                if name.ends_with('/') {
+                    tool("aws", &["s3", "rm", "--recursive", &url])?;
+                } else {
+                    tool("aws", &["s3", "rm", &url])?;
+                }
+            }
+            Store::Image(image) => {
+                let digest = |tag: &str| -> Result<String> {
+                    Ok(tool("crane", &["digest", &format!("{image}:{tag}")])?
+                        .trim()
+                        .to_string())
+                };
+                let pruned = digest(name)?;
+                for tag in kept {
+                    if digest(tag)? == pruned {
+                        warn!("  ⏭️ keeping {name}, its image is also tagged {tag}");
+                        return Ok(false);
+                    }
+                }
+                tool("crane", &["delete", &format!("{image}@{pruned}")])?;
+            }
+        }
+        Ok(true)
+    }
+}
+
+fn tool(program: &str, args: &[&str]) -> Result<String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|e| anyhow!("failed to run {program}: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{program} {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn parse_releases(json: &str) -> Result<Vec<(String, String)>> {
+    let releases: Vec<serde_json::Value> =
+        serde_json::from_str(json).context("failed to parse gh release list")?;
+    Ok(releases
+        .iter()
+        .filter_map(|release| {
+            Some((
+                release["tagName"].as_str()?.to_string(),
+                release["createdAt"].as_str()?.to_string(),
+            ))
+        })
+        .collect())
+}
+
+/// The key prefix of an `s3://bucket/key/prefix/` URL.
+fn s3_key_prefix(url: &str) -> &str {
+    url.trim_start_matches("s3://")
+        .split_once('/')
+        .map(|(_, key)| key)
+        .unwrap_or_default()
+}
+
+/// Entries directly under `key_prefix` in `aws s3 ls --recursive`, with the
+/// time of their oldest object; directories keep their trailing `/`.
+pub fn parse_s3_ls(output: &str, key_prefix: &str) -> Vec<(String, String)> {
+    let mut entries: BTreeMap<String, String> = BTreeMap::new();
+    for line in output.lines() {
+        // 2024-01-01 12:00:00       1234 nightly-2024-01-01/agave.tar.gz
+        let mut fields = line.trim();
+        let mut parts = vec![];
+        for _ in 0..3 {
+            let Some((part, rest)) = fields.split_once(char::is_whitespace) else {
+                break;
+            };
+            parts.push(part);
+            fields = rest.trim_start();
+        }
+        let [date, time, _size] = parts[..] else {
+            continue;
+        };
+        let Some(relative) = fields.strip_prefix(key_prefix) else {
+            continue;
+        };
+        let name = match relative.split_once('/') {
+            Some((dir, _)) => format!("{dir}/"),
+            None => relative.to_string(),
+        };
+        if name.is_empty() || name == "/" {
+            continue;
+        }
+        let created = format!("{date} {time}");
+        let oldest = entries.entry(name).or_insert_with(|| created.clone());
+        if created < *oldest {
+            *oldest = created;
+        }
+    }
+    entries.into_iter().collect()
+}
+
+/// `created` of an image config, as printed by `crane config`.
+fn image_created(config: &str) -> Result<String> {
+    let config: serde_json::Value =
+        serde_json::from_str(config).context("failed to parse the image config")?;
+    Ok(config["created"].as_str().unwrap_or_default().to_string())
+}
+
+/// Items matching `pattern` beyond the newest `keep`, newest first.
+pub fn to_prune(mut items: Vec<(String, String)>, pattern: &str, keep: usize) -> Vec<String> {
+    items.retain(|(name, _)| wildcard_match(pattern, name.trim_end_matches('/')));
+    // images built reproducibly share one creation time, fall back to names
+    items.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| b.0.cmp(&a.0)));
+    items.into_iter().skip(keep).map(|(name, _)| name).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_to_prune() {
+        let items = |names: &[&str]| {
+            names
+                .iter()
+                .map(|n| (n.to_string(), n.to_string()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            to_prune(
+                items(&[
+                    "nightly-2024-01-02",
+                    "v2.0.0",
+                    "nightly-2024-01-04/",
+                    "nightly-2024-01-01",
+                    "nightly-2024-01-03",
+                ]),
+                "nightly-*",
+                2
+            ),
+            vec!["nightly-2024-01-02", "nightly-2024-01-01"]
+        );
+        assert_eq!(
+            to_prune(items(&["nightly-1"]), "nightly-*", 10),
+            Vec::<String>::new()
+        );
+
+        let releases = parse_releases(
+            r#"[{"tagName":"nightly-b","createdAt":"2024-01-01T00:00:00Z"},
+                {"tagName":"nightly-a","createdAt":"2024-01-02T00:00:00Z"}]"#,
+        )
+        .unwrap();
+        assert_eq!(to_prune(releases, "nightly-*", 1), vec!["nightly-b"]);
+    }
+
+    #[test]
+    fn test_parse_s3_ls() {
+        let output = "2024-01-02 03:04:05      12345 nightlies/nightly-2024-01-01/agave.tar.gz
+2024-01-01 23:00:00      12345 nightlies/nightly-2024-01-01/SHA256SUMS
+2024-01-03 03:04:05      12345 nightlies/nightly-2024-01-02.tar.gz
+2024-01-03 03:04:05      12345 nightlies/name with spaces.txt
+";
+        assert_eq!(
+            parse_s3_ls(output, s3_key_prefix("s3://releases/nightlies/")),
+            vec![
+                (
+                    String::from("name with spaces.txt"),
+                    String::from("2024-01-03 03:04:05")
+                ),
+                (
+                    String::from("nightly-2024-01-01/"),
+                    String::from("2024-01-01 23:00:00")
+                ),
+                (
+                    String::from("nightly-2024-01-02.tar.gz"),
+                    String::from("2024-01-03 03:04:05")
+                ),
+            ]
+        );
+        assert_eq!(s3_key_prefix("s3://releases/"), "");
+    }
+
+    #[test]
+    fn test_image_created() {
+        assert_eq!(
+            image_created(r#"{"created":"2024-01-02T03:04:05Z","config":{}}"#).unwrap(),
+            "2024-01-02T03:04:05Z"
+        );
+        assert_eq!(image_created("{}").unwrap(), "");
+    }
+}
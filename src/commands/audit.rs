@@ -0,0 +1,401 @@
+use {
+    crate::{
+        config::Config,
+        utils::{
+            discovery::Discovery, get_git_root_path, git::with_worktree_in, notify,
+            recursive_find_files,
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::MetadataCommand,
+    clap::{Args, Subcommand},
+    log::info,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        path::Path,
+        process::Command,
+    },
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[command(subcommand)]
+    pub subcommand: AuditSubcommand,
+}
+
+#[derive(Subcommand)]
+pub enum AuditSubcommand {
+    #[command(about = "Report advisories introduced or resolved between two refs")]
+    Diff {
+        #[arg(long)]
+        from: String,
+        #[arg(long, default_value = "HEAD")]
+        to: String,
+        #[arg(long, help = "Print the delta as JSON")]
+        json: bool,
+        #[arg(
+            long,
+            help = "Notify the teams owning the affected crates of introduced advisories"
+        )]
+        notify: bool,
+    },
+}
+
+/// An advisory affecting a locked package.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Finding {
+    pub id: String,
+    pub package: String,
+    pub version: String,
+    pub title: String,
+    /// `vulnerability`, or the warning kind (`unmaintained`, `unsound`, `yanked`).
+    pub kind: String,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct AdvisoryDelta {
+    pub from: String,
+    pub to: String,
+    pub introduced: Vec<Finding>,
+    pub resolved: Vec<Finding>,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    match args.subcommand {
+        AuditSubcommand::Diff {
+            from,
+            to,
+            json,
+            notify,
+        } => {
+            let repo = get_git_root_path()?;
+            info!("🔎 auditing {from}");
+            let before = with_worktree_in(&repo, &from, audit_tree)?;
+            info!("🔎 auditing {to}");
+            let after = with_worktree_in(&repo, &to, audit_tree)?;
+            let delta = diff(&from, &to, &before, &after);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&delta)?);
+            } else {
+                print!("{}", render_markdown(&delta));
+            }
+            if notify && !delta.introduced.is_empty() {
+                notify_introduced(&repo, &delta)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Sends each introduced advisory to the owners of the workspace crates
+/// depending on the affected package at `delta.to`.
+fn notify_introduced(repo: &Path, delta: &AdvisoryDelta) -> Result<()> {
+    let config = Config::load()?;
+    let (graph, members) = with_worktree_in(repo, &delta.to, dependency_graph)?;
+    for finding in delta.introduced.iter() {
+        let crates = dependents(&graph, &members, &finding.package);
+        let crates: Vec<&str> = crates.iter().map(String::as_str).collect();
+        let text = format!(
+            "🚨 {} introduced in {}: `{}` {}: {}",
+            finding.id, delta.to, finding.package, finding.version, finding.title
+        );
+        notify::send_to_owners(&config, &crates, &text)?;
+    }
+    Ok(())
+}
+
+/// Package name to the names of its dependencies.
+type Graph = BTreeMap<String, BTreeSet<String>>;
+
+/// The [`Graph`] of the workspace at `tree`, with the names of its members.
+fn dependency_graph(tree: &Path) -> Result<(Graph, Vec<String>)> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(tree.join("Cargo.toml"))
+        .exec()
+        .context("failed to run cargo metadata")?;
+    let mut graph = Graph::new();
+    if let Some(resolve) = &metadata.resolve {
+        for node in resolve.nodes.iter() {
+            graph
+                .entry(metadata[&node.id].name.to_string())
+                .or_default()
+                .extend(node.deps.iter().map(|d| metadata[&d.pkg].name.to_string()));
+        }
+    }
+    let members: Vec<String> = metadata
+        .workspace_packages()
+        .iter()
+        .map(|p| p.name.to_string())
+        .collect();
+    Ok((graph, members))
+}
+
+/// Workspace `members` depending on `package`, directly or not, in `graph`
+/// (package name to the names of its dependencies).
+pub fn dependents(
+    graph: &BTreeMap<String, BTreeSet<String>>,
+    members: &[String],
+    package: &str,
+) -> Vec<String> {
+    members
+        .iter()
+        .filter(|member| {
+            let mut seen = BTreeSet::new();
+            let mut pending = vec![member.as_str()];
+            while let Some(name) = pending.pop() {
+                if name == package {
+                    return true;
+                }
+                if seen.insert(name) {
+                    if let Some(dependencies) = graph.get(name) {
+                        pending.extend(dependencies.iter().map(String::as_str));
+                    }
+                }
+            }
+            false
+        })
+        .cloned()
+        .collect()
+}
+
+/// Audits every lockfile in the checkout at `dir`.
+fn audit_tree(dir: &Path) -> Result<Vec<Finding>> {
+    let mut findings = vec![];
+    for lockfile in recursive_find_files(dir, "Cargo.lock", &Discovery::for_tree(dir)?, |_| true)? {
+        findings.extend(audit_lockfile(&lockfile)?);
+    }
+    findings.sort();
+    findings.dedup();
+    Ok(findings)
+}
+
+fn audit_lockfile(lockfile: &Path) -> Result<Vec<Finding>> {
+    let output = Command::new("cargo")
+        .args(["audit", "--json", "--file"])
+        .arg(lockfile)
+        .output()
+        .map_err(|e| anyhow!("failed to run cargo audit (is cargo-audit installed?): {e}"))?;
+    // cargo audit exits non-zero when it finds vulnerabilities, the report is still on stdout
+    if output.stdout.is_empty() {
+        return Err(anyhow!(
+            "cargo audit failed for {}: {}",
+            lockfile.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    parse_report(&String::from_utf8_lossy(&output.stdout)).context(format!(
+        "failed to parse cargo audit report for {}",
+        lockfile.display()
+    ))
+}
+
+#[derive(Deserialize)]
+struct Report {
+    vulnerabilities: Vulnerabilities,
+    #[serde(default)]
+    warnings: BTreeMap<String, Vec<ReportEntry>>,
+}
+
+#[derive(Deserialize)]
+struct Vulnerabilities {
+    list: Vec<ReportEntry>,
+}
+
+#[derive(Deserialize)]
+struct ReportEntry {
+    advisory: Option<Advisory>,
+    package: ReportPackage,
+}
+
+#[derive(Deserialize)]
+struct Advisory {
+    id: String,
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct ReportPackage {
+    name: String,
+    version: String,
+}
+
+pub fn parse_report(json: &str) -> Result<Vec<Finding>> {
+    let report: Report = serde_json::from_str(json)?;
+    let vulnerabilities = report
+        .vulnerabilities
+        .list
+        .into_iter()
+        .map(|entry| (String::from("vulnerability"), entry));
+    let warnings = report
+        .warnings
+        .into_iter()
+        .flat_map(|(kind, entries)| entries.into_iter().map(move |entry| (kind.clone(), entry)));
+    Ok(vulnerabilities
+        .chain(warnings)
+        .map(|(kind, entry)| {
+            let (id, title) = match entry.advisory {
+                Some(advisory) => (advisory.id, advisory.title),
+                // yanked crates have no advisory
+                None => (kind.clone(), format!("{} is {kind}", entry.package.name)),
+            };
+            Finding {
+                id,
+                package: entry.package.name,
+                version: entry.package.version,
+                title,
+                kind,
+            }
+        })
+        .collect())
+}
+
+/// Advisories are matched by id and package, so a version bump that keeps a
+/// package vulnerable is neither introduced nor resolved.
+pub fn diff(from: &str, to: &str, before: &[Finding], after: &[Finding]) -> AdvisoryDelta {
+    let key = |f: &Finding| (f.id.clone(), f.package.clone());
+    let before_keys: Vec<_> = before.iter().map(key).collect();
+    let after_keys: Vec<_> = after.iter().map(key).collect();
+    AdvisoryDelta {
+        from: from.to_string(),
+        to: to.to_string(),
+        introduced: after
+            .iter()
+            .filter(|f| !before_keys.contains(&key(f)))
+            .cloned()
+            .collect(),
+        resolved: before
+            .iter()
+            .filter(|f| !after_keys.contains(&key(f)))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// Security section for release notes.
+pub fn render_markdown(delta: &AdvisoryDelta) -> String {
+    let mut out = format!("## Security ({}..{})\n\n", delta.from, delta.to);
+    if delta.introduced.is_empty() && delta.resolved.is_empty() {
+        out.push_str("No advisories introduced or resolved.\n");
+        return out;
+    }
+    for (heading, findings) in [
+        ("Introduced", &delta.introduced),
+        ("Resolved", &delta.resolved),
+    ] {
+        if findings.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("### {heading}\n\n"));
+        for finding in findings {
+            out.push_str(&format!(
+                "- {} `{}` {}: {}\n",
+                finding.id, finding.package, finding.version, finding.title
+            ));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn finding(id: &str, package: &str, version: &str) -> Finding {
+        Finding {
+            id: id.to_string(),
+            package: package.to_string(),
+            version: version.to_string(),
+            title: String::from("bad"),
+            kind: String::from("vulnerability"),
+        }
+    }
+
+    #[test]
+    fn test_parse_report() {
+        let findings = parse_report(
+            r#"{
+  "vulnerabilities": {"found": true, "count": 1, "list": [
+    {"advisory": {"id": "RUSTSEC-2024-0001", "title": "Overflow"}, "package": {"name": "foo", "version": "1.0.0"}}
+  ]},
+  "warnings": {
+    "unmaintained": [{"advisory": {"id": "RUSTSEC-2023-0002", "title": "Unmaintained"}, "package": {"name": "bar", "version": "0.1.0"}}],
+    "yanked": [{"advisory": null, "package": {"name": "baz", "version": "2.0.0"}}]
+  }
+}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            findings,
+            vec![
+                Finding {
+                    id: String::from("RUSTSEC-2024-0001"),
+                    package: String::from("foo"),
+                    version: String::from("1.0.0"),
+                    title: String::from("Overflow"),
+                    kind: String::from("vulnerability"),
+                },
+                Finding {
+                    id: String::from("RUSTSEC-2023-0002"),
+                    package: String::from("bar"),
+                    version: String::from("0.1.0"),
+                    title: String::from("Unmaintained"),
+                    kind: String::from("unmaintained"),
+                },
+                Finding {
+                    id: String::from("yanked"),
+                    package: String::from("baz"),
+                    version: String::from("2.0.0"),
+                    title: String::from("baz is yanked"),
+                    kind: String::from("yanked"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = vec![finding("A", "foo", "1.0.0"), finding("B", "bar", "1.0.0")];
+        let after = vec![finding("A", "foo", "1.0.1"), finding("C", "qux", "0.1.0")];
+        let delta = diff("v2.1.0", "HEAD", &before, &after);
+        assert_eq!(delta.introduced, vec![finding("C", "qux", "0.1.0")]);
+        assert_eq!(delta.resolved, vec![finding("B", "bar", "1.0.0")]);
+        assert_eq!(
+            render_markdown(&delta),
+            "## Security (v2.1.0..HEAD)\n\n### Introduced\n\n- C `qux` 0.1.0: bad\n\n### Resolved\n\n- B `bar` 1.0.0: bad\n\n"
+        );
+    }
+
+    #[test]
+    fn test_dependents() {
+        let graph: BTreeMap<String, BTreeSet<String>> = [
+            ("solana-runtime", vec!["solana-svm", "tokio"]),
+            ("solana-svm", vec!["curve25519-dalek"]),
+            ("solana-cli", vec!["tokio"]),
+            ("tokio", vec!["mio"]),
+            ("curve25519-dalek", vec![]),
+        ]
+        .into_iter()
+        .map(|(name, deps)| {
+            (
+                name.to_string(),
+                deps.into_iter().map(str::to_string).collect(),
+            )
+        })
+        .collect();
+        let members: Vec<String> = ["solana-cli", "solana-runtime", "solana-svm"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            dependents(&graph, &members, "curve25519-dalek"),
+            vec!["solana-runtime", "solana-svm"]
+        );
+        assert_eq!(
+            dependents(&graph, &members, "mio"),
+            vec!["solana-cli", "solana-runtime"]
+        );
+        assert_eq!(dependents(&graph, &members, "serde"), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,174 @@
+use {
+    crate::utils::{get_git_root_path, git::git_in, portable::shell, with_worktree},
+    anyhow::{anyhow, Context, Result},
+    clap::Args,
+    log::{info, warn},
+    std::{fs, path::Path, process::Command},
+};
+
+/// Exit code `git bisect run` reads as "this commit cannot be tested".
+const SKIP_EXIT_CODE: i32 = 125;
+
+/// Target dir shared by the steps, relative to the worktree.
+const BISECT_TARGET_DIR: &str = "target";
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(
+        long,
+        help = "Shell command deciding each step: exit 0 good, 125 skip, anything else up to 127 bad"
+    )]
+    pub test: String,
+    #[arg(long, help = "Known good ref, e.g. v2.1.0")]
+    pub good: String,
+    #[arg(long, default_value = "HEAD", help = "Known bad ref")]
+    pub bad: String,
+    #[arg(
+        long,
+        default_value = "Cargo.toml",
+        help = "Workspace manifest, relative to the repository root"
+    )]
+    pub manifest_path: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+    Good,
+    Bad,
+    Skip,
+}
+
+impl Verdict {
+    /// Reads the test's exit code the way `git bisect run` does. `None`
+    /// (killed by a signal) and codes above 127 abort the bisect.
+    pub fn from_exit_code(code: Option<i32>) -> Result<Self> {
+        match code {
+            Some(0) => Ok(Verdict::Good),
+            Some(SKIP_EXIT_CODE) => Ok(Verdict::Skip),
+            Some(1..=127) => Ok(Verdict::Bad),
+            Some(code) => Err(anyhow!("test exited with {code}, aborting the bisect")),
+            None => Err(anyhow!("test was killed by a signal, aborting the bisect")),
+        }
+    }
+
+    fn as_arg(self) -> &'static str {
+        match self {
+            Verdict::Good => "good",
+            Verdict::Bad => "bad",
+            Verdict::Skip => "skip",
+        }
+    }
+}
+
+/// Bisects in a throwaway worktree so the checkout is left alone. Each step
+/// gets a freshly synced lockfile, since version bumps between commits leave
+/// `Cargo.lock` out of date. The steps share one target dir so they build
+/// incrementally, it goes away with the worktree.
+pub fn run(args: CommandArgs) -> Result<()> {
+    let repo = get_git_root_path()?;
+    let good = git_in(&repo, &["rev-parse", "--verify", &args.good])?;
+    let bad = git_in(&repo, &["rev-parse", "--verify", &args.bad])?;
+    let first_bad = with_worktree(&bad, |worktree| {
+        let result = bisect(worktree, &good, &bad, &args);
+        let log = git_in(worktree, &["bisect", "log"]).unwrap_or_default();
+        let _ = git_in(worktree, &["bisect", "reset"]);
+        let _ = fs::remove_dir_all(worktree.join(BISECT_TARGET_DIR));
+        result.map_err(|e| anyhow!("{e}\n\nbisect log:\n{log}"))
+    })?;
+
+    let summary = git_in(
+        &repo,
+        &["show", "--no-patch", "--format=%h %s (%an)", &first_bad],
+    )?;
+    info!("🎯 first bad commit: {summary}");
+    println!("{first_bad}");
+    Ok(())
+}
+
+fn bisect(worktree: &Path, good: &str, bad: &str, args: &CommandArgs) -> Result<String> {
+    let mut output = git_in(worktree, &["bisect", "start", bad, good])?;
+    loop {
+        if let Some(commit) = first_bad_commit(&output) {
+            return Ok(commit);
+        }
+        let commit = git_in(worktree, &["rev-parse", "HEAD"])?;
+        let verdict = step(worktree, &commit, args)?;
+        // undo the lockfile sync and anything the test wrote, but keep the
+        // build cache
+        git_in(worktree, &["reset", "--hard", "--quiet"])?;
+        git_in(
+            worktree,
+            &["clean", "-fdx", "--quiet", "-e", BISECT_TARGET_DIR],
+        )?;
+        info!("🔎 {} is {}", &commit[..12], verdict.as_arg());
+        output = git_in(worktree, &["bisect", verdict.as_arg()])?;
+    }
+}
+
+fn step(worktree: &Path, commit: &str, args: &CommandArgs) -> Result<Verdict> {
+    let manifest_path = worktree.join(&args.manifest_path);
+    if !sync_lockfile(&manifest_path) {
+        warn!(
+            "failed to sync the lockfile of {}, skipping it",
+            &commit[..12]
+        );
+        return Ok(Verdict::Skip);
+    }
+    let status = shell(&args.test)
+        .current_dir(worktree)
+        .env("CARGO_TARGET_DIR", worktree.join(BISECT_TARGET_DIR))
+        .status()
+        .context(format!("failed to run {}", args.test))?;
+    Verdict::from_exit_code(status.code())
+}
+
+/// Brings the lockfile in line with the workspace manifests, keeping
+/// external dependencies where they are pinned when possible.
+fn sync_lockfile(manifest_path: &Path) -> bool {
+    let cargo = |args: &[&str]| {
+        Command::new("cargo")
+            .args(args)
+            .arg("--manifest-path")
+            .arg(manifest_path)
+            .output()
+            .is_ok_and(|output| output.status.success())
+    };
+    cargo(&["update", "--workspace"]) || cargo(&["generate-lockfile"])
+}
+
+/// The commit `git bisect` names once it has narrowed the range down.
+pub fn first_bad_commit(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        line.strip_suffix(" is the first bad commit")
+            .map(|commit| commit.trim().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_verdict_from_exit_code() {
+        assert_eq!(Verdict::from_exit_code(Some(0)).unwrap(), Verdict::Good);
+        assert_eq!(Verdict::from_exit_code(Some(1)).unwrap(), Verdict::Bad);
+        assert_eq!(Verdict::from_exit_code(Some(101)).unwrap(), Verdict::Bad);
+        assert_eq!(Verdict::from_exit_code(Some(125)).unwrap(), Verdict::Skip);
+        assert!(Verdict::from_exit_code(Some(128)).is_err());
+        assert!(Verdict::from_exit_code(None).is_err());
+    }
+
+    #[test]
+    fn test_first_bad_commit() {
+        let output = "3f2a9c1e4b5d6a7f8e9d0c1b2a3f4e5d6c7b8a9f is the first bad commit\n\
+                      commit 3f2a9c1e4b5d6a7f8e9d0c1b2a3f4e5d6c7b8a9f\n";
+        assert_eq!(
+            first_bad_commit(output).as_deref(),
+            Some("3f2a9c1e4b5d6a7f8e9d0c1b2a3f4e5d6c7b8a9f")
+        );
+        assert_eq!(
+            first_bad_commit("Bisecting: 3 revisions left to test after this (roughly 2 steps)"),
+            None
+        );
+    }
+}
@@ -0,0 +1,227 @@
+use {
+    crate::{
+        config::Config,
+        utils::{
+            fs::sha256_file, get_git_root_path, http::apply_cargo_network_env,
+            scope::resolve_manifest_path,
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::{MetadataCommand, Package},
+    clap::Args,
+    log::info,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+};
+
+/// Dependencies that mark a `cdylib` crate as an on-chain program.
+const PROGRAM_DEPENDENCIES: &[&str] = &[
+    "solana-program",
+    "solana-program-entrypoint",
+    "pinocchio",
+    "anchor-lang",
+];
+
+const EM_BPF: u16 = 247;
+const EM_SBF: u16 = 263;
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, help = "Only build this program crate (repeatable)")]
+    pub package: Vec<String>,
+    #[arg(long, default_value = "dist/programs")]
+    pub out_dir: PathBuf,
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Program {
+    pub package: String,
+    pub manifest_path: PathBuf,
+    /// file stem of the produced `.so`
+    pub lib_name: String,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let git_root = get_git_root_path()?;
+    let config = Config::load()?;
+
+    let manifest_path = resolve_manifest_path(&args.manifest_path)?;
+    let mut programs = find_programs(Path::new(&manifest_path))?;
+    if !args.package.is_empty() {
+        for name in args.package.iter() {
+            if !programs.iter().any(|p| &p.package == name) {
+                return Err(anyhow!("{name} is not a program crate in this workspace"));
+            }
+        }
+        programs.retain(|p| args.package.contains(&p.package));
+    }
+    if programs.is_empty() {
+        return Err(anyhow!("no program crates found"));
+    }
+
+    let out_dir = git_root.join(&args.out_dir);
+    fs::create_dir_all(&out_dir).context(format!("failed to create {}", out_dir.display()))?;
+
+    let staging = tempfile::tempdir()?;
+    let mut sums = vec![];
+    for program in programs.iter() {
+        info!("🔨 building {}", program.package);
+        let artifact = build_program(program, staging.path(), &config)?;
+        verify_program_elf(&fs::read(&artifact)?)
+            .context(format!("{} is not a valid program", artifact.display()))?;
+
+        let file_name = format!("{}.so", program.lib_name);
+        let destination = out_dir.join(&file_name);
+        fs::copy(&artifact, &destination)
+            .context(format!("failed to copy to {}", destination.display()))?;
+        sums.push((file_name, sha256_file(&destination)?));
+        info!("  {}", destination.display());
+    }
+
+    // merge with programs built by earlier (filtered) runs
+    let sums_path = out_dir.join("SHA256SUMS");
+    if sums_path.exists() {
+        for line in fs::read_to_string(&sums_path)?.lines() {
+            if let Some((hash, file_name)) = line.split_once("  ") {
+                if !sums.iter().any(|(name, _)| name == file_name) {
+                    sums.push((file_name.to_string(), hash.to_string()));
+                }
+            }
+        }
+    }
+    fs::write(&sums_path, render_sums(sums))?;
+    info!("✅ {} program(s) in {}", programs.len(), out_dir.display());
+    Ok(())
+}
+
+/// Finds workspace crates that build a `cdylib` and depend on a program entrypoint crate.
+pub fn find_programs(manifest_path: &Path) -> Result<Vec<Program>> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+    let mut programs: Vec<Program> = metadata
+        .workspace_packages()
+        .into_iter()
+        .filter_map(program_of)
+        .collect();
+    programs.sort_by(|a, b| a.package.cmp(&b.package));
+    Ok(programs)
+}
+
+fn program_of(package: &Package) -> Option<Program> {
+    let lib = package
+        .targets
+        .iter()
+        .find(|t| t.crate_types.iter().any(|c| c.to_string() == "cdylib"))?;
+    if !package
+        .dependencies
+        .iter()
+        .any(|d| PROGRAM_DEPENDENCIES.contains(&d.name.as_str()))
+    {
+        return None;
+    }
+    Some(Program {
+        package: package.name.to_string(),
+        manifest_path: package.manifest_path.clone().into_std_path_buf(),
+        lib_name: lib.name.replace('-', "_"),
+    })
+}
+
+fn build_program(program: &Program, sbf_out_dir: &Path, config: &Config) -> Result<PathBuf> {
+    let mut cmd = Command::new("cargo");
+    cmd.arg("build-sbf")
+        .arg("--manifest-path")
+        .arg(&program.manifest_path)
+        .arg("--sbf-out-dir")
+        .arg(sbf_out_dir);
+    apply_cargo_network_env(&mut cmd, &config.network);
+    let status = cmd.status().map_err(|e| {
+        anyhow!("failed to run cargo build-sbf (are the platform tools installed?): {e}")
+    })?;
+    if !status.success() {
+        return Err(anyhow!("cargo build-sbf failed for {}", program.package));
+    }
+    let artifact = sbf_out_dir.join(format!("{}.so", program.lib_name));
+    if !artifact.exists() {
+        return Err(anyhow!(
+            "cargo build-sbf did not produce {}",
+            artifact.display()
+        ));
+    }
+    Ok(artifact)
+}
+
+/// Checks that `bytes` is a 64-bit little-endian shared object for the BPF/SBF machine.
+pub fn verify_program_elf(bytes: &[u8]) -> Result<()> {
+    if bytes.len() < 64 || &bytes[..4] != b"\x7fELF" {
+        return Err(anyhow!("not an ELF file"));
+    }
+    if bytes[4] != 2 || bytes[5] != 1 {
+        return Err(anyhow!("not a 64-bit little-endian ELF"));
+    }
+    let e_type = u16::from_le_bytes([bytes[16], bytes[17]]);
+    if e_type != 3 {
+        return Err(anyhow!("ELF type {e_type} is not a shared object"));
+    }
+    let e_machine = u16::from_le_bytes([bytes[18], bytes[19]]);
+    if e_machine != EM_BPF && e_machine != EM_SBF {
+        return Err(anyhow!("ELF machine {e_machine} is not BPF/SBF"));
+    }
+    Ok(())
+}
+
+fn render_sums(mut sums: Vec<(String, String)>) -> String {
+    sums.sort();
+    sums.iter()
+        .map(|(file_name, hash)| format!("{hash}  {file_name}\n"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn elf_header(e_type: u16, e_machine: u16) -> Vec<u8> {
+        let mut bytes = vec![0u8; 64];
+        bytes[..4].copy_from_slice(b"\x7fELF");
+        bytes[4] = 2;
+        bytes[5] = 1;
+        bytes[16..18].copy_from_slice(&e_type.to_le_bytes());
+        bytes[18..20].copy_from_slice(&e_machine.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn test_verify_program_elf() {
+        verify_program_elf(&elf_header(3, EM_SBF)).unwrap();
+        verify_program_elf(&elf_header(3, EM_BPF)).unwrap();
+        // x86-64 shared object
+        assert!(verify_program_elf(&elf_header(3, 62)).is_err());
+        // executable instead of shared object
+        assert!(verify_program_elf(&elf_header(2, EM_SBF)).is_err());
+        assert!(verify_program_elf(b"#!/bin/sh").is_err());
+    }
+
+    #[test]
+    fn test_render_sums() {
+        let sums = vec![
+            (String::from("token.so"), String::from("bb")),
+            (String::from("memo.so"), String::from("aa")),
+        ];
+        assert_eq!(render_sums(sums), "aa  memo.so\nbb  token.so\n");
+    }
+
+    #[test]
+    fn test_find_programs_skips_non_programs() {
+        let programs =
+            find_programs(Path::new("tests/dummy-workspace-publish-test/Cargo.toml")).unwrap();
+        assert_eq!(programs, vec![]);
+    }
+}
@@ -0,0 +1,249 @@
+use {
+    crate::{
+        config::Config,
+        utils::{
+            cargo::read_manifest,
+            http::apply_cargo_network_env,
+            scope::resolve_manifest_path,
+            vfs::{FileSystem, RealFs},
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::{DependencyKind, MetadataCommand, Package},
+    clap::Args,
+    log::{info, warn},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        path::Path,
+        process::Command,
+    },
+    toml_edit::{value, DocumentMut},
+};
+
+const EDITIONS: [&str; 4] = ["2015", "2018", "2021", "2024"];
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(help = "Edition to move the workspace to, e.g. 2024")]
+    pub edition: String,
+
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let target = args.edition.as_str();
+    let previous = EDITIONS
+        .iter()
+        .position(|e| *e == target)
+        .ok_or_else(|| anyhow!("unknown edition {target}, expected one of {EDITIONS:?}"))?
+        .checked_sub(1)
+        .map(|i| EDITIONS[i])
+        .ok_or_else(|| anyhow!("{target} is the first edition, there is nothing to migrate"))?;
+
+    let manifest_path = resolve_manifest_path(&args.manifest_path)?;
+    let metadata = MetadataCommand::new()
+        .manifest_path(&manifest_path)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+    let config = Config::load()?;
+    let root_manifest = metadata.workspace_root.join("Cargo.toml");
+    let members = metadata.workspace_packages();
+
+    let order = dependency_order(&members);
+    let skipped: Vec<String> = order
+        .iter()
+        .filter(|package| package.edition.as_str() != target)
+        .filter(|package| package.edition.as_str() != previous)
+        .map(|package| {
+            format!(
+                "{} is on edition {}, migrate it one edition at a time",
+                package.name,
+                package.edition.as_str()
+            )
+        })
+        .collect();
+    if !skipped.is_empty() {
+        for failure in skipped.iter() {
+            warn!("❌ {failure}");
+        }
+        return Err(anyhow!(
+            "{} crate(s) need a manual migration to edition {target}",
+            skipped.len()
+        ));
+    }
+
+    // cargo fix migrates from the edition in the manifest, so fix every
+    // crate before touching an edition, and stop at the first failure so no
+    // manifest claims an edition its code was not migrated to
+    for package in order {
+        if package.edition.as_str() == target {
+            continue;
+        }
+        info!("🔧 migrating {} to edition {target}", package.name);
+        cargo_fix(root_manifest.as_std_path(), &package.name, &config).context(format!(
+            "cargo fix --edition failed for {}, no edition was changed; fix it by hand and run bump-edition again",
+            package.name
+        ))?;
+    }
+
+    let mut manifests = vec![root_manifest.into_std_path_buf()];
+    manifests.extend(
+        members
+            .iter()
+            .map(|p| p.manifest_path.clone().into_std_path_buf()),
+    );
+    manifests.dedup();
+    for manifest in manifests {
+        let mut doc = read_manifest(&manifest)?;
+        if set_edition(&mut doc, target) {
+            RealFs.write(&manifest, &doc.to_string())?;
+            info!("  set edition {target} in {}", manifest.display());
+        }
+    }
+    info!("✅ workspace moved to edition {target}");
+    Ok(())
+}
+
+/// Members ordered so every crate comes after the members it depends on,
+/// letting `cargo fix` see already migrated dependencies.
+pub fn dependency_order<'a>(members: &[&'a Package]) -> Vec<&'a Package> {
+    let names: BTreeSet<&str> = members.iter().map(|p| p.name.as_str()).collect();
+    let mut pending: BTreeMap<&str, (&'a Package, BTreeSet<&str>)> = members
+        .iter()
+        .map(|package| {
+            let dependencies = package
+                .dependencies
+                .iter()
+                .filter(|d| d.kind != DependencyKind::Development)
+                .map(|d| d.name.as_str())
+                .filter(|name| names.contains(name) && *name != package.name.as_str())
+                .collect();
+            (package.name.as_str(), (*package, dependencies))
+        })
+        .collect();
+
+    let mut ordered = vec![];
+    while !pending.is_empty() {
+        let ready: Vec<&str> = pending
+            .iter()
+            .filter(|(_, (_, dependencies))| dependencies.is_empty())
+            .map(|(name, _)| *name)
+            .collect();
+        // a cycle through build dependencies, keep the remaining order stable
+        let ready = if ready.is_empty() {
+            pending.keys().copied().collect()
+        } else {
+            ready
+        };
+        for name in ready {
+            if let Some((package, _)) = pending.remove(name) {
+                ordered.push(package);
+            }
+            for (_, dependencies) in pending.values_mut() {
+                dependencies.remove(name);
+            }
+        }
+    }
+    ordered
+}
+
+/// Sets `package.edition` and `workspace.package.edition` where they are
+/// spelled out, leaving `edition.workspace = true` alone. Returns whether
+/// anything changed.
+pub fn set_edition(doc: &mut DocumentMut, edition: &str) -> bool {
+    let mut changed = false;
+    for path in [&["package"][..], &["workspace", "package"]] {
+        let mut table = Some(doc.as_item_mut());
+        for key in path {
+            table = table.and_then(|t| t.get_mut(key));
+        }
+        let Some(table) = table else {
+            continue;
+        };
+        let Some(current) = table.get("edition").and_then(|e| e.as_str()) else {
+            continue;
+        };
+        if current != edition {
+            table["edition"] = value(edition);
+            changed = true;
+        }
+    }
+    changed
+}
+
+fn cargo_fix(manifest_path: &Path, package: &str, config: &Config) -> Result<()> {
+    let mut cmd = Command::new("cargo");
+    cmd.args([
+        "fix",
+        "--edition",
+        "--allow-dirty",
+        "--allow-staged",
+        "--all-targets",
+        "--all-features",
+        "--package",
+        package,
+        "--manifest-path",
+    ])
+    .arg(manifest_path);
+    apply_cargo_network_env(&mut cmd, &config.network);
+    let output = cmd
+        .output()
+        .map_err(|e| anyhow!("failed to run cargo fix: {e}"))?;
+    if !output.status.success() {
+        // the tail holds the errors, the rest is compilation progress
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let lines: Vec<&str> = stderr.lines().collect();
+        return Err(anyhow!(
+            "{}",
+            lines[lines.len().saturating_sub(5)..].join("\n")
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_set_edition() {
+        let mut doc: DocumentMut =
+            "[workspace.package]\nedition = \"2021\"\n\n[package]\nname = \"a\"\nedition.workspace = true\n"
+                .parse()
+                .unwrap();
+        assert!(set_edition(&mut doc, "2024"));
+        assert_eq!(
+            doc.to_string(),
+            "[workspace.package]\nedition = \"2024\"\n\n[package]\nname = \"a\"\nedition.workspace = true\n"
+        );
+        assert!(!set_edition(&mut doc, "2024"));
+    }
+
+    #[test]
+    fn test_dependency_order() {
+        let metadata = MetadataCommand::new()
+            .manifest_path("tests/dummy-workspace/Cargo.toml")
+            .no_deps()
+            .exec()
+            .unwrap();
+        let members = metadata.workspace_packages();
+        let ordered: Vec<&str> = dependency_order(&members)
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        for package in members.iter() {
+            let position = |name: &str| ordered.iter().position(|n| *n == name).unwrap();
+            for dependency in package
+                .dependencies
+                .iter()
+                .filter(|d| d.kind != DependencyKind::Development)
+                .filter(|d| ordered.contains(&d.name.as_str()))
+            {
+                assert!(position(&dependency.name) < position(&package.name));
+            }
+        }
+        assert_eq!(ordered.len(), members.len());
+    }
+}
@@ -1,10 +1,11 @@
 use {
+    crate::utils::vfs::{FileSystem, RealFs},
     anyhow::{anyhow, Context, Result},
     clap::{Args, ValueEnum},
-    log::{debug, info},
     semver::Version,
-    std::{fs, process::Command},
+    std::{path::PathBuf, process::Command},
     toml_edit::{value, DocumentMut},
+    tracing::{debug, debug_span, info},
 };
 
 #[derive(Args)]
@@ -47,11 +48,54 @@ pub fn run(args: CommandArgs) -> Result<()> {
     let all_cargo_tomls =
         crate::utils::find_all_cargo_tomls().context("failed to find all cargo.toml files")?;
     info!("found {} cargo.toml files", all_cargo_tomls.len());
-    for cargo_toml in all_cargo_tomls {
+    bump_manifests(
+        &RealFs,
+        &all_cargo_tomls,
+        &all_crates,
+        &current_version,
+        &new_version,
+    )?;
+
+    let config = crate::config::Config::load().context("failed to load xtask.toml")?;
+    let all_cargo_locks =
+        crate::utils::find_all_cargo_locks().context("failed to find all Cargo.lock files")?;
+    info!("found {} Cargo.lock files", all_cargo_locks.len());
+    for cargo_lock in all_cargo_locks {
+        let dir = cargo_lock.parent().context(format!(
+            "failed to get {}'s parent directory",
+            cargo_lock.display()
+        ))?;
+
+        info!("running `cargo tree` in {}", dir.display());
+        let mut cmd = Command::new("cargo");
+        cmd.arg("tree").current_dir(dir);
+        crate::utils::http::apply_cargo_network_env(&mut cmd, &config.network);
+        let output = cmd
+            .output()
+            .context(format!("failed to run `cargo tree` in {}", dir.display()))?;
+        if !output.status.success() {
+            return Err(anyhow!("{}", String::from_utf8_lossy(&output.stderr)));
+        }
+    }
+
+    Ok(())
+}
+
+/// Rewrites `manifests` from `current_version` to `new_version`: the
+/// workspace and package versions, and the version requirements on the crates
+/// in `all_crates`.
+pub fn bump_manifests(
+    fs: &impl FileSystem,
+    manifests: &[PathBuf],
+    all_crates: &[String],
+    current_version: &Version,
+    new_version: &Version,
+) -> Result<()> {
+    for cargo_toml in manifests {
+        let _span = debug_span!("manifest", path = %cargo_toml.display()).entered();
         info!("processing {}", cargo_toml.display());
 
-        let content = fs::read_to_string(&cargo_toml)
-            .context(format!("failed to read {}", cargo_toml.display()))?;
+        let content = fs.read_to_string(cargo_toml)?;
         let mut doc = content
             .parse::<DocumentMut>()
             .context(format!("failed to parse {}", cargo_toml.display()))?;
@@ -112,30 +156,8 @@ pub fn run(args: CommandArgs) -> Result<()> {
 
         // write the updated document back to the file
         debug!("writing {}", cargo_toml.display());
-        fs::write(&cargo_toml, doc.to_string())
-            .context(format!("failed to write {}", cargo_toml.display()))?;
+        fs.write(cargo_toml, &doc.to_string())?;
     }
-
-    let all_cargo_locks =
-        crate::utils::find_all_cargo_locks().context("failed to find all Cargo.lock files")?;
-    info!("found {} Cargo.lock files", all_cargo_locks.len());
-    for cargo_lock in all_cargo_locks {
-        let dir = cargo_lock.parent().context(format!(
-            "failed to get {}'s parent directory",
-            cargo_lock.display()
-        ))?;
-
-        info!("running `cargo tree` in {}", dir.display());
-        let output = Command::new("cargo")
-            .arg("tree")
-            .current_dir(dir)
-            .output()
-            .context(format!("failed to run `cargo tree` in {}", dir.display()))?;
-        if !output.status.success() {
-            return Err(anyhow!("{}", String::from_utf8_lossy(&output.stderr)));
-        }
-    }
-
     Ok(())
 }
 
@@ -202,7 +224,7 @@ pub fn bump_version(level: &BumpLevel, current: &Version) -> Result<Version> {
 
 #[cfg(test)]
 mod tests {
-    use super::*;
+    use {super::*, crate::utils::vfs::MemoryFs};
 
     #[test]
     fn test_bump_version_major() {
@@ -376,4 +398,39 @@ mod tests {
             Version::parse("1.2.4").unwrap()
         );
     }
+
+    #[test]
+    fn test_bump_manifests() {
+        let fs = MemoryFs::new([
+            (
+                "Cargo.toml",
+                "[workspace.package]\nversion = \"1.2.3\"\n\n[workspace.dependencies]\na = { path = \"a\", version = \"=1.2.3\" }\ncc = \"1.2.3\"\n",
+            ),
+            (
+                "a/Cargo.toml",
+                "[package]\nname = \"a\"\nversion.workspace = true\n",
+            ),
+            ("d/Cargo.toml", "[package]\nname = \"d\"\nversion = \"1.2.3\"\n"),
+        ]);
+        bump_manifests(
+            &fs,
+            &fs.paths_named("Cargo.toml"),
+            &[String::from("a"), String::from("d")],
+            &Version::parse("1.2.3").unwrap(),
+            &Version::parse("1.2.4").unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            fs.get("Cargo.toml").unwrap(),
+            "[workspace.package]\nversion = \"1.2.4\"\n\n[workspace.dependencies]\na = { path = \"a\", version = \"=1.2.4\" }\ncc = \"1.2.3\"\n"
+        );
+        assert_eq!(
+            fs.get("a/Cargo.toml").unwrap(),
+            "[package]\nname = \"a\"\nversion.workspace = true\n"
+        );
+        assert_eq!(
+            fs.get("d/Cargo.toml").unwrap(),
+            "[package]\nname = \"d\"\nversion = \"1.2.4\"\n"
+        );
+    }
 }
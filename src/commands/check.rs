@@ -0,0 +1,113 @@
+use {
+    super::lint::{self, Finding, LINTS},
+    crate::utils::{discovery::Discovery, get_scope_root, recursive_find_files, telemetry},
+    anyhow::{anyhow, Result},
+    clap::Args,
+    log::{info, warn},
+    std::{
+        path::{Path, PathBuf},
+        thread,
+        time::{Duration, Instant, SystemTime},
+    },
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, help = "Only run the cheap checks suitable for pre-commit hooks")]
+    pub fast: bool,
+
+    #[arg(
+        long,
+        help = "Re-run the checks whenever a manifest or lockfile changes"
+    )]
+    pub watch: bool,
+
+    #[arg(
+        long,
+        default_value_t = 500,
+        help = "Watch poll interval in milliseconds"
+    )]
+    pub poll_interval_ms: u64,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let root = get_scope_root()?;
+    if !args.watch {
+        let findings = run_checks(&root, args.fast)?;
+        return lint::report(&findings);
+    }
+
+    let mut last_fingerprint = None;
+    loop {
+        let fingerprint = watched_files_fingerprint(&root)?;
+        if last_fingerprint.as_ref() != Some(&fingerprint) {
+            last_fingerprint = Some(fingerprint);
+            if let Err(e) = run_checks(&root, args.fast).and_then(|f| lint::report(&f)) {
+                warn!("{e}");
+            }
+            info!("👀 watching for changes (ctrl-c to stop)");
+        }
+        thread::sleep(Duration::from_millis(args.poll_interval_ms));
+    }
+}
+
+/// Runs the registered lints, returning all findings prefixed by lint name.
+pub fn run_checks(root: &Path, fast_only: bool) -> Result<Vec<Finding>> {
+    let mut findings = vec![];
+    for lint in LINTS.iter().filter(|lint| lint.fast || !fast_only) {
+        let started = Instant::now();
+        let lint_findings = telemetry::phase(lint.name, || (lint.run)(root))
+            .map_err(|e| anyhow!("{} failed to run: {e:#}", lint.name))?;
+        info!(
+            "{} {} ({} ms)",
+            if lint_findings.is_empty() {
+                "✅"
+            } else {
+                "❌"
+            },
+            lint.name,
+            started.elapsed().as_millis()
+        );
+        findings.extend(lint_findings.into_iter().map(|finding| Finding {
+            message: format!("[{}] {}", lint.name, finding.message),
+            ..finding
+        }));
+    }
+    Ok(findings)
+}
+
+fn watched_files_fingerprint(root: &Path) -> Result<Vec<(PathBuf, SystemTime)>> {
+    let mut fingerprint = vec![];
+    let discovery = Discovery::for_tree(root)?;
+    for name in ["Cargo.toml", "Cargo.lock"] {
+        for path in recursive_find_files(root, name, &discovery, |_| true)? {
+            let modified = path
+                .metadata()
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            fingerprint.push((path, modified));
+        }
+    }
+    fingerprint.sort();
+    Ok(fingerprint)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_run_fast_checks() {
+        let findings = run_checks(Path::new("tests/dummy-workspace-crates-update"), true).unwrap();
+        assert!(findings
+            .iter()
+            .all(|finding| finding.message.starts_with('[')));
+        assert_eq!(
+            findings
+                .iter()
+                .filter(|f| f.message.starts_with("[workspace-deps]"))
+                .count(),
+            1
+        );
+    }
+}
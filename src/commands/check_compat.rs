@@ -0,0 +1,227 @@
+use {
+    crate::{
+        config::Config,
+        utils::{get_git_root_path, http::apply_cargo_network_env},
+    },
+    anyhow::{anyhow, Context, Result},
+    clap::Args,
+    log::{info, warn},
+    scopeguard::defer,
+    serde::Deserialize,
+    std::{fmt, fs, io, path::Path, process::Command},
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, default_value = "compat.toml")]
+    pub matrix: String,
+    #[arg(
+        long,
+        help = "Only build each combination instead of running the tests"
+    )]
+    pub build_only: bool,
+}
+
+/// `compat.toml`: the range of versions we claim to support for key dependencies.
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Matrix {
+    #[serde(rename = "dependency")]
+    pub dependencies: Vec<DependencyRange>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DependencyRange {
+    pub name: String,
+    pub min: String,
+    pub max: String,
+}
+
+/// One pinned version per dependency.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Combination(pub Vec<(String, String)>);
+
+impl fmt::Display for Combination {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pins: Vec<_> = self
+            .0
+            .iter()
+            .map(|(name, version)| format!("{name}={version}"))
+            .collect();
+        write!(f, "{}", pins.join(" "))
+    }
+}
+
+impl Matrix {
+    pub fn read(path: &Path) -> Result<Self> {
+        let content =
+            fs::read_to_string(path).context(format!("failed to read {}", path.display()))?;
+        toml_edit::de::from_str(&content).context(format!("failed to parse {}", path.display()))
+    }
+
+    /// Every min/max combination across the declared dependencies.
+    pub fn combinations(&self) -> Vec<Combination> {
+        let mut combinations = vec![Combination(vec![])];
+        for dependency in self.dependencies.iter() {
+            let mut versions = vec![dependency.min.clone()];
+            if dependency.max != dependency.min {
+                versions.push(dependency.max.clone());
+            }
+            combinations = combinations
+                .into_iter()
+                .flat_map(|combination| {
+                    versions.iter().map(move |version| {
+                        let mut pins = combination.0.clone();
+                        pins.push((dependency.name.clone(), version.clone()));
+                        Combination(pins)
+                    })
+                })
+                .collect();
+        }
+        combinations
+    }
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let git_root = get_git_root_path()?;
+    let config = Config::load()?;
+    let matrix = Matrix::read(&git_root.join(&args.matrix))?;
+    let combinations = matrix.combinations();
+
+    // every combination rewrites Cargo.lock, put the committed one back (or
+    // remove the generated one) at the end
+    let lock_path = git_root.join("Cargo.lock");
+    let original_lock = fs::read(&lock_path).ok();
+    defer! {
+        if let Err(e) = reset_lock(&lock_path, original_lock.as_deref()) {
+            warn!("failed to restore {}: {e}", lock_path.display());
+        }
+    }
+
+    let mut failures = vec![];
+    for combination in combinations.iter() {
+        info!("🧪 {combination}");
+        reset_lock(&lock_path, original_lock.as_deref())
+            .context(format!("failed to reset {}", lock_path.display()))?;
+        if let Err(e) = check_combination(&git_root, &config, combination, args.build_only) {
+            warn!("❌ {combination}: {e:#}");
+            failures.push(combination);
+        }
+    }
+
+    println!(
+        "{} of {} combination(s) passed",
+        combinations.len().saturating_sub(failures.len()),
+        combinations.len()
+    );
+    for combination in failures.iter() {
+        println!("  ❌ {combination}");
+    }
+    if !failures.is_empty() {
+        return Err(anyhow!("{} combination(s) failed", failures.len()));
+    }
+    Ok(())
+}
+
+/// Puts `original` back at `path`, or removes the lockfile a previous
+/// combination generated when there was none.
+fn reset_lock(path: &Path, original: Option<&[u8]>) -> io::Result<()> {
+    match original {
+        Some(original) => fs::write(path, original),
+        None => match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        },
+    }
+}
+
+fn check_combination(
+    git_root: &Path,
+    config: &Config,
+    combination: &Combination,
+    build_only: bool,
+) -> Result<()> {
+    for (name, version) in combination.0.iter() {
+        cargo(
+            git_root,
+            config,
+            &["update", "--package", name, "--precise", version],
+        )
+        .context(format!("cannot resolve {name}={version}"))?;
+    }
+    let step = if build_only { "build" } else { "test" };
+    cargo(git_root, config, &[step, "--workspace", "--locked"])
+}
+
+fn cargo(git_root: &Path, config: &Config, args: &[&str]) -> Result<()> {
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(git_root).args(args);
+    apply_cargo_network_env(&mut cmd, &config.network);
+    let output = cmd
+        .output()
+        .map_err(|e| anyhow!("failed to run cargo: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // the tail holds the actual error, the rest is compile progress
+        let tail: Vec<_> = stderr.lines().rev().take(10).collect();
+        let tail: Vec<_> = tail.into_iter().rev().collect();
+        return Err(anyhow!("cargo {} failed:\n{}", args[0], tail.join("\n")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_combinations() {
+        let matrix: Matrix = toml_edit::de::from_str(
+            r#"
+[[dependency]]
+name = "solana-sdk"
+min = "2.0.0"
+max = "2.2.1"
+
+[[dependency]]
+name = "solana-program"
+min = "2.1.0"
+max = "2.1.0"
+"#,
+        )
+        .unwrap();
+        let combinations: Vec<_> = matrix
+            .combinations()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            combinations,
+            vec![
+                "solana-sdk=2.0.0 solana-program=2.1.0",
+                "solana-sdk=2.2.1 solana-program=2.1.0",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matrix_rejects_unknown_keys() {
+        let result: Result<Matrix, _> = toml_edit::de::from_str(
+            "[[dependency]]\nname = \"a\"\nmin = \"1.0.0\"\nmax = \"1.1.0\"\nlatest = true\n",
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_reset_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Cargo.lock");
+        fs::write(&path, "generated").unwrap();
+        reset_lock(&path, None).unwrap();
+        assert!(!path.exists());
+        reset_lock(&path, None).unwrap();
+        reset_lock(&path, Some(b"committed")).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "committed");
+    }
+}
@@ -0,0 +1,293 @@
+use {
+    crate::{
+        commands::publish::compute_publish_order_data,
+        config::{Config, NetworkConfig},
+        utils::{http::apply_cargo_network_env, scope::resolve_manifest_path},
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::{DependencyKind, MetadataCommand, Package},
+    clap::Args,
+    log::{info, warn},
+    std::{collections::BTreeSet, fmt, path::Path, process::Command, sync::Mutex, thread},
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(
+        long,
+        default_value_t = 2,
+        help = "Largest number of features combined"
+    )]
+    pub depth: usize,
+    #[arg(long, help = "Only check these crates")]
+    pub package: Vec<String>,
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+    #[arg(
+        long,
+        help = "Feature sets checked in parallel [default: available cores]"
+    )]
+    pub jobs: Option<usize>,
+}
+
+/// One crate built with `--no-default-features` plus a set of features.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureSet {
+    pub package: String,
+    pub features: Vec<String>,
+}
+
+impl fmt::Display for FeatureSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.features.is_empty() {
+            write!(f, "{} (no features)", self.package)
+        } else {
+            write!(f, "{} --features {}", self.package, self.features.join(","))
+        }
+    }
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let manifest_path = resolve_manifest_path(&args.manifest_path)?;
+    let config = Config::load()?;
+    let metadata = MetadataCommand::new()
+        .manifest_path(&manifest_path)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+    let target_root = metadata
+        .target_directory
+        .join("check-features")
+        .into_std_path_buf();
+    let packages: Vec<&Package> = metadata
+        .workspace_packages()
+        .into_iter()
+        .filter(|p| args.package.is_empty() || args.package.contains(&p.name.to_string()))
+        .collect();
+    if packages.is_empty() {
+        return Err(anyhow!("no crates to check"));
+    }
+
+    let levels = group_by_publish_level(&manifest_path, &packages)?;
+    let jobs = args
+        .jobs
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()))
+        .max(1);
+    let mut checked = 0usize;
+    let mut failures = vec![];
+    for (idx, level) in levels.iter().enumerate() {
+        info!(
+            "🧪 level {}: {}",
+            idx.saturating_add(1),
+            level
+                .iter()
+                .map(|p| p.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let sets: Vec<FeatureSet> = level
+            .iter()
+            .flat_map(|package| {
+                feature_combinations(&powerset_features(package), args.depth)
+                    .into_iter()
+                    .map(|features| FeatureSet {
+                        package: package.name.to_string(),
+                        features,
+                    })
+            })
+            .collect();
+        checked = checked.saturating_add(sets.len());
+        failures.extend(check_in_parallel(
+            &manifest_path,
+            &config.network,
+            &target_root,
+            sets,
+            jobs,
+        ));
+    }
+
+    println!(
+        "{} of {checked} feature set(s) passed",
+        checked.saturating_sub(failures.len())
+    );
+    for (set, error) in failures.iter() {
+        println!("  ❌ {set}\n{}", indent(error));
+    }
+    if !failures.is_empty() {
+        return Err(anyhow!("{} feature set(s) failed", failures.len()));
+    }
+    Ok(())
+}
+
+/// Orders `packages` by publish level so a broken dependency shows up before
+/// its dependents; unpublished crates come last.
+fn group_by_publish_level<'a>(
+    manifest_path: &str,
+    packages: &[&'a Package],
+) -> Result<Vec<Vec<&'a Package>>> {
+    let order = compute_publish_order_data(manifest_path)?;
+    let mut remaining: Vec<&Package> = packages.to_vec();
+    let mut levels = vec![];
+    for level in order.levels.iter() {
+        let (current, rest): (Vec<&Package>, Vec<&Package>) =
+            remaining.into_iter().partition(|p| level.contains(&p.id));
+        remaining = rest;
+        if !current.is_empty() {
+            levels.push(current);
+        }
+    }
+    if !remaining.is_empty() {
+        levels.push(remaining);
+    }
+    Ok(levels)
+}
+
+/// Each worker gets its own target directory so cargo's build lock does not
+/// serialize them; a worker reuses its directory across feature sets.
+fn check_in_parallel(
+    manifest_path: &str,
+    network: &NetworkConfig,
+    target_root: &Path,
+    sets: Vec<FeatureSet>,
+    jobs: usize,
+) -> Vec<(FeatureSet, String)> {
+    let queue = Mutex::new(sets);
+    let failures = Mutex::new(vec![]);
+    thread::scope(|scope| {
+        for worker in 0..jobs {
+            let (queue, failures) = (&queue, &failures);
+            let target_dir = target_root.join(worker.to_string());
+            scope.spawn(move || {
+                // the guard moves into the closure, so the queue is unlocked while checking
+                while let Some(set) = queue.lock().ok().and_then(|mut q| q.pop()) {
+                    if let Err(e) = cargo_check(manifest_path, network, &target_dir, &set) {
+                        warn!("❌ {set}");
+                        if let Ok(mut failures) = failures.lock() {
+                            failures.push((set, format!("{e:#}")));
+                        }
+                    }
+                }
+            });
+        }
+    });
+    let mut failures = failures.into_inner().unwrap_or_default();
+    failures.sort_by(|a, b| {
+        a.0.package
+            .cmp(&b.0.package)
+            .then(a.0.features.cmp(&b.0.features))
+    });
+    failures
+}
+
+fn cargo_check(
+    manifest_path: &str,
+    network: &NetworkConfig,
+    target_dir: &Path,
+    set: &FeatureSet,
+) -> Result<()> {
+    let mut cmd = Command::new("cargo");
+    cmd.args(["check", "--manifest-path", manifest_path])
+        .args(["--package", &set.package, "--no-default-features"])
+        .arg("--target-dir")
+        .arg(target_dir);
+    if !set.features.is_empty() {
+        cmd.args(["--features", &set.features.join(",")]);
+    }
+    apply_cargo_network_env(&mut cmd, network);
+    let output = cmd
+        .output()
+        .map_err(|e| anyhow!("failed to run cargo: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // the first error is the useful one, the rest tends to cascade from it
+        let errors: Vec<_> = stderr
+            .lines()
+            .skip_while(|line| !line.starts_with("error"))
+            .take(10)
+            .collect();
+        return Err(anyhow!("{}", errors.join("\n")));
+    }
+    Ok(())
+}
+
+/// Features worth combining: everything except `default` and the implicit
+/// features cargo creates for optional dependencies.
+pub fn powerset_features(package: &Package) -> Vec<String> {
+    let optional: BTreeSet<&str> = package
+        .dependencies
+        .iter()
+        .filter(|d| d.optional && d.kind == DependencyKind::Normal)
+        .map(|d| d.rename.as_deref().unwrap_or(d.name.as_str()))
+        .collect();
+    package
+        .features
+        .iter()
+        .filter(|(name, _)| name.as_str() != "default")
+        .filter(|(name, enables)| {
+            let implicit = optional.contains(name.as_str())
+                && enables.len() == 1
+                && enables[0] == format!("dep:{name}");
+            !implicit
+        })
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Every subset of `features` with at most `depth` elements, smallest first.
+pub fn feature_combinations(features: &[String], depth: usize) -> Vec<Vec<String>> {
+    let mut combinations = vec![vec![]];
+    let mut frontier: Vec<(usize, Vec<String>)> = vec![(0, vec![])];
+    for _ in 0..depth.min(features.len()) {
+        let mut next = vec![];
+        for (start, combination) in frontier {
+            for (idx, feature) in features.iter().enumerate().skip(start) {
+                let mut extended = combination.clone();
+                extended.push(feature.clone());
+                next.push((idx.saturating_add(1), extended));
+            }
+        }
+        combinations.extend(next.iter().map(|(_, c)| c.clone()));
+        frontier = next;
+    }
+    combinations
+}
+
+fn indent(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("      {line}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_feature_combinations() {
+        let features: Vec<String> = ["a", "b", "c"].iter().map(|s| s.to_string()).collect();
+        let names = |combinations: Vec<Vec<String>>| -> Vec<String> {
+            combinations.into_iter().map(|c| c.join("+")).collect()
+        };
+        assert_eq!(
+            names(feature_combinations(&features, 2)),
+            vec!["", "a", "b", "c", "a+b", "a+c", "b+c"]
+        );
+        assert_eq!(names(feature_combinations(&features, 0)), vec![""]);
+        assert_eq!(feature_combinations(&features, 5).len(), 8);
+    }
+
+    #[test]
+    fn test_powerset_features() {
+        let metadata = MetadataCommand::new()
+            .manifest_path("Cargo.toml")
+            .no_deps()
+            .exec()
+            .unwrap();
+        let package = metadata.workspace_packages()[0];
+        assert_eq!(
+            powerset_features(package),
+            vec!["agave-unstable-api", "dummy-for-ci-check", "frozen-abi"]
+        );
+    }
+}
@@ -0,0 +1,122 @@
+use {
+    crate::{
+        config::Config,
+        utils::{
+            cargo::{build_lib_for_target, ensure_target_installed},
+            scope::resolve_manifest_path,
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::{MetadataCommand, Package},
+    clap::Args,
+    log::{info, warn},
+    std::fs,
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(
+        long,
+        help = "Bare target to build for [default: no-std.target in xtask.toml]"
+    )]
+    pub target: Option<String>,
+    #[arg(long, help = "Only check these crates")]
+    pub package: Vec<String>,
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let manifest_path = resolve_manifest_path(&args.manifest_path)?;
+    let config = Config::load()?;
+    let target = args.target.unwrap_or(config.no_std.target.clone());
+    let metadata = MetadataCommand::new()
+        .manifest_path(&manifest_path)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+
+    let mut crates = vec![];
+    for package in metadata.workspace_packages() {
+        if !args.package.is_empty() && !args.package.contains(&package.name.to_string()) {
+            continue;
+        }
+        if supports_no_std(package)? {
+            crates.push(package.name.to_string());
+        }
+    }
+    if crates.is_empty() {
+        info!("no crates declare no_std support");
+        return Ok(());
+    }
+
+    ensure_target_installed(metadata.workspace_root.as_std_path(), &target)?;
+    let mut failures = vec![];
+    for name in crates.iter() {
+        info!("🧪 {name} for {target}");
+        match build_lib_for_target(
+            &manifest_path,
+            &config.network,
+            name,
+            &target,
+            &["--no-default-features"],
+        ) {
+            Ok(()) => println!("  ✅ {name}"),
+            Err(e) => {
+                warn!("❌ {name}");
+                println!("  ❌ {name}\n{e:#}");
+                failures.push(name);
+            }
+        }
+    }
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "{} of {} no_std crate(s) failed to build for {target}",
+            failures.len(),
+            crates.len()
+        ));
+    }
+    Ok(())
+}
+
+/// A crate declares no_std support with `#![no_std]` or a
+/// `#![cfg_attr(..., no_std)]` in its library root.
+fn supports_no_std(package: &Package) -> Result<bool> {
+    let Some(lib) = package.targets.iter().find(|t| t.is_lib()) else {
+        return Ok(false);
+    };
+    let content =
+        fs::read_to_string(&lib.src_path).context(format!("failed to read {}", lib.src_path))?;
+    Ok(declares_no_std(&content))
+}
+
+pub fn declares_no_std(source: &str) -> bool {
+    source
+        .lines()
+        .map(str::trim)
+        .filter(|line| line.starts_with("#!["))
+        .any(|line| {
+            let attribute = line.split("//").next().unwrap_or_default();
+            attribute
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .any(|word| word == "no_std")
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_declares_no_std() {
+        assert!(declares_no_std("//! Docs\n#![no_std]\n"));
+        assert!(declares_no_std(
+            "#![cfg_attr(not(feature = \"std\"), no_std)]\n"
+        ));
+        assert!(!declares_no_std(
+            "#![deny(missing_docs)] // no_std someday\n"
+        ));
+        assert!(!declares_no_std("// #![no_std]\npub fn f() {}\n"));
+        assert!(!declares_no_std("#![no_std_compat]\n"));
+    }
+}
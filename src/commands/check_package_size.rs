@@ -0,0 +1,309 @@
+use {
+    crate::{
+        config::Config,
+        utils::{
+            http::apply_cargo_network_env, portable::slash_path, scope::resolve_manifest_path,
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::{MetadataCommand, Package},
+    clap::Args,
+    log::info,
+    std::{
+        cmp::Reverse,
+        fs,
+        io::Write,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+};
+
+/// crates.io rejects `.crate` files above this size.
+pub const CRATES_IO_LIMIT: u64 = 10 * 1024 * 1024;
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, help = "Only check these crates")]
+    pub package: Vec<String>,
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+    #[arg(
+        long,
+        help = "Compressed size to flag in KiB [default: publish.max-package-size-kib in xtask.toml]"
+    )]
+    pub max_kib: Option<u64>,
+    #[arg(
+        long,
+        default_value_t = 5,
+        help = "Largest files listed per flagged crate"
+    )]
+    pub top: usize,
+}
+
+/// Estimated contents of one crate's `.crate` file.
+#[derive(Debug)]
+pub struct PackageSize {
+    pub name: String,
+    /// Gzipped tarball of the packaged files, close to what cargo uploads.
+    pub compressed: u64,
+    /// Packaged files with their uncompressed size, largest first.
+    pub files: Vec<(PathBuf, u64)>,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let manifest_path = resolve_manifest_path(&args.manifest_path)?;
+    let config = Config::load()?;
+    let threshold = args
+        .max_kib
+        .unwrap_or(config.publish.max_package_size_kib)
+        .saturating_mul(1024);
+    let metadata = MetadataCommand::new()
+        .manifest_path(&manifest_path)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+
+    let mut sizes = vec![];
+    for package in metadata.workspace_packages() {
+        if !args.package.is_empty() && !args.package.contains(&package.name.to_string()) {
+            continue;
+        }
+        if package.publish.as_ref().is_some_and(|r| r.is_empty()) {
+            continue;
+        }
+        info!("📦 {}", package.name);
+        sizes.push(package_size(package, &config)?);
+    }
+    sizes.sort_by_key(|size| Reverse(size.compressed));
+
+    let mut flagged = 0usize;
+    for size in sizes.iter() {
+        let problem = if size.compressed > CRATES_IO_LIMIT {
+            Some("over the crates.io limit")
+        } else if size.compressed > threshold {
+            Some("over the configured threshold")
+        } else {
+            None
+        };
+        println!(
+            "{:<40} {:>10} {:>6} file(s){}",
+            size.name,
+            format_size(size.compressed),
+            size.files.len(),
+            problem.map(|p| format!("  ❌ {p}")).unwrap_or_default()
+        );
+        if problem.is_some() {
+            flagged = flagged.saturating_add(1);
+            for (path, bytes) in size.files.iter().take(args.top) {
+                println!("    {:>10}  {}", format_size(*bytes), path.display());
+            }
+        }
+    }
+    if flagged > 0 {
+        return Err(anyhow!(
+            "{flagged} crate(s) over {}, exclude large files with package.exclude",
+            format_size(threshold.min(CRATES_IO_LIMIT))
+        ));
+    }
+    Ok(())
+}
+
+fn package_size(package: &Package, config: &Config) -> Result<PackageSize> {
+    let dir = package
+        .manifest_path
+        .parent()
+        .map(|p| p.as_std_path().to_path_buf())
+        .unwrap_or_default();
+    // cargo generates a few entries (Cargo.toml.orig, .cargo_vcs_info.json)
+    // that do not exist on disk yet, they are tiny
+    let sources: Vec<(PathBuf, PathBuf, u64)> = packaged_sources(package, config)?
+        .into_iter()
+        .filter_map(|(name, source)| {
+            let size = fs::metadata(&source).ok()?.len();
+            Some((PathBuf::from(name), source, size))
+        })
+        .collect();
+    let compressed = compressed_size(
+        &dir,
+        &sources
+            .iter()
+            .map(|(_, source, _)| source)
+            .collect::<Vec<_>>(),
+    )?;
+    let mut files: Vec<(PathBuf, u64)> = sources
+        .into_iter()
+        .map(|(name, _, size)| (name, size))
+        .collect();
+    files.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+    Ok(PackageSize {
+        name: package.name.to_string(),
+        compressed,
+        files,
+    })
+}
+
+/// Files `cargo package` would put in the `.crate`, relative to the package
+/// directory.
+pub fn packaged_files(package: &Package, config: &Config) -> Result<Vec<PathBuf>> {
+    let mut cmd = Command::new("cargo");
+    cmd.args(["package", "--list", "--allow-dirty", "--manifest-path"])
+        .arg(package.manifest_path.as_std_path());
+    apply_cargo_network_env(&mut cmd, &config.network);
+    let output = cmd
+        .output()
+        .map_err(|e| anyhow!("failed to run cargo package: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "cargo package --list failed for {}: {}",
+            package.name,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// Pairs each file of `cargo package --list`, by its path in the `.crate`,
+/// with the file it is packaged from. A `readme` or `license-file` outside
+/// the package (`../README.md`) is packaged at the root under its file name.
+pub fn packaged_sources(package: &Package, config: &Config) -> Result<Vec<(String, PathBuf)>> {
+    let dir = package
+        .manifest_path
+        .parent()
+        .map(|p| p.as_std_path().to_path_buf())
+        .unwrap_or_default();
+    let extra: Vec<PathBuf> = [&package.readme, &package.license_file]
+        .into_iter()
+        .flatten()
+        .map(|path| dir.join(path))
+        .collect();
+    package_sources(&dir, packaged_files(package, config)?, &extra)
+}
+
+/// [`packaged_sources`] for the `listed` files of the package in `dir`.
+/// Files cargo generates while packaging map to where they would be in `dir`.
+fn package_sources(
+    dir: &Path,
+    listed: Vec<PathBuf>,
+    extra: &[PathBuf],
+) -> Result<Vec<(String, PathBuf)>> {
+    let dir = fs::canonicalize(dir).context(format!("failed to resolve {}", dir.display()))?;
+    let outside: Vec<PathBuf> = extra
+        .iter()
+        .filter_map(|path| fs::canonicalize(path).ok())
+        .filter(|path| !path.starts_with(&dir))
+        .collect();
+    Ok(listed
+        .into_iter()
+        .map(|packaged| {
+            let source = match outside
+                .iter()
+                .find(|source| source.file_name() == Some(packaged.as_os_str()))
+            {
+                Some(source) => source.clone(),
+                None => {
+                    let source = dir.join(&packaged);
+                    fs::canonicalize(&source).unwrap_or(source)
+                }
+            };
+            (slash_path(&packaged), source)
+        })
+        .collect())
+}
+
+/// Size of the files as a gzipped tarball, the format of a `.crate`.
+fn compressed_size(dir: &Path, files: &[&PathBuf]) -> Result<u64> {
+    let mut list = tempfile::NamedTempFile::new()?;
+    for path in files {
+        writeln!(list, "{}", path.display())?;
+    }
+    list.flush()?;
+    let output = Command::new("tar")
+        .arg("-czf")
+        .arg("-")
+        .arg("-C")
+        .arg(dir)
+        .arg("-T")
+        .arg(list.path())
+        .output()
+        .map_err(|e| anyhow!("failed to run tar: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "tar failed in {}: {}",
+            dir.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout.len() as u64)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 3] = ["KiB", "MiB", "GiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len().saturating_sub(1) {
+        size /= 1024.0;
+        unit = unit.saturating_add(1);
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_package_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        fs::create_dir_all(root.join("foo/src")).unwrap();
+        for file in ["README.md", "LICENSE", "foo/README.md", "foo/src/lib.rs"] {
+            fs::write(root.join(file), file).unwrap();
+        }
+        let listed = ["LICENSE", "README.md", "src/lib.rs"].map(PathBuf::from);
+        let sources = package_sources(
+            &root.join("foo"),
+            listed.to_vec(),
+            &[root.join("foo/../README.md"), root.join("foo/../LICENSE")],
+        )
+        .unwrap();
+        assert_eq!(
+            sources,
+            [
+                (String::from("LICENSE"), root.join("LICENSE")),
+                (String::from("README.md"), root.join("README.md")),
+                (String::from("src/lib.rs"), root.join("foo/src/lib.rs")),
+            ]
+        );
+        // a readme inside the package is packaged where it is
+        let sources = package_sources(
+            &root.join("foo"),
+            vec![PathBuf::from("README.md")],
+            &[root.join("foo/README.md")],
+        )
+        .unwrap();
+        assert_eq!(sources[0].1, root.join("foo/README.md"));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(2048), "2.0 KiB");
+        assert_eq!(format_size(CRATES_IO_LIMIT), "10.0 MiB");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+
+    #[test]
+    fn test_compressed_size() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("zeros.bin"), vec![0u8; 1024 * 1024]).unwrap();
+        let file = PathBuf::from("zeros.bin");
+        let compressed = compressed_size(dir.path(), &[&file]).unwrap();
+        assert!(compressed > 0 && compressed < 16 * 1024, "{compressed}");
+    }
+}
@@ -0,0 +1,177 @@
+use {
+    super::publish::{PlannedPackage, PublishPlan},
+    crate::{
+        config::Config,
+        utils::{
+            registry::{fetch_versions, IndexEntry, CRATES_IO_INDEX},
+            scope::resolve_manifest_path,
+        },
+    },
+    anyhow::{anyhow, Result},
+    clap::Args,
+    log::info,
+    semver::Version,
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+}
+
+/// How a local version relates to what crates.io already has.
+#[derive(Debug, PartialEq)]
+pub enum Verdict {
+    /// Higher than anything published in its release line.
+    Ok(Option<Version>),
+    AlreadyPublished {
+        yanked: bool,
+    },
+    /// Lower than a version already published in its release line.
+    Regression(Version),
+    /// Below the newest release, on a line `publish.maintained-lines` does
+    /// not list.
+    Unmaintained(Version),
+}
+
+impl Verdict {
+    fn message(&self) -> String {
+        match self {
+            Verdict::Ok(Some(highest)) => format!("✅ above {highest}"),
+            Verdict::Ok(None) => String::from("✅ first release of this line"),
+            Verdict::AlreadyPublished { yanked: false } => String::from("❌ already published"),
+            Verdict::AlreadyPublished { yanked: true } => {
+                String::from("❌ already published and yanked, versions cannot be reused")
+            }
+            Verdict::Regression(highest) => format!("❌ {highest} is already published"),
+            Verdict::Unmaintained(highest) => {
+                format!("❌ below {highest} on a line publish.maintained-lines does not list")
+            }
+        }
+    }
+
+    fn is_ok(&self) -> bool {
+        matches!(self, Verdict::Ok(_))
+    }
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    let plan = PublishPlan::compute(&resolve_manifest_path(&args.manifest_path)?, &config)?;
+    verify(plan.packages(), CRATES_IO_INDEX, &config)
+}
+
+/// Fails unless every package's version is new, not below a published
+/// version of the same `major.minor` line, and not below the newest release
+/// at all unless its line is one of `publish.maintained-lines`, which
+/// maintenance branches ship patch releases from.
+pub fn verify<'a>(
+    packages: impl IntoIterator<Item = &'a PlannedPackage>,
+    index_url: &str,
+    config: &Config,
+) -> Result<()> {
+    let mut failures = 0usize;
+    for package in packages {
+        let version = Version::parse(&package.version)?;
+        let entries = fetch_versions(&config.network, index_url, None, &package.name)?;
+        let verdict = compare(&version, &entries, &config.publish.maintained_lines);
+        println!(
+            "{:<40} {:>16}  {}",
+            package.name,
+            package.version,
+            verdict.message()
+        );
+        if !verdict.is_ok() {
+            failures = failures.saturating_add(1);
+        }
+    }
+    if failures > 0 {
+        return Err(anyhow!(
+            "{failures} crate(s) would republish or regress a version on crates.io"
+        ));
+    }
+    info!("✅ every version is new");
+    Ok(())
+}
+
+pub fn compare(version: &Version, entries: &[IndexEntry], maintained_lines: &[String]) -> Verdict {
+    let published: Vec<(Version, bool)> = entries
+        .iter()
+        .filter_map(|entry| Some((entry.version()?, entry.yanked)))
+        .collect();
+    if let Some((_, yanked)) = published.iter().find(|(v, _)| v == version) {
+        return Verdict::AlreadyPublished { yanked: *yanked };
+    }
+    let live: Vec<&Version> = published
+        .iter()
+        .filter(|(_, yanked)| !yanked)
+        .map(|(v, _)| v)
+        .collect();
+    let highest_in_line = live
+        .iter()
+        .filter(|v| v.major == version.major && v.minor == version.minor)
+        .max()
+        .map(|v| (*v).clone());
+    if let Some(highest) = highest_in_line.as_ref().filter(|h| *h > version) {
+        return Verdict::Regression(highest.clone());
+    }
+    // prereleases of the next line do not make the current one old
+    let newest_release = live.iter().filter(|v| v.pre.is_empty()).max();
+    let line = format!("{}.{}", version.major, version.minor);
+    match newest_release {
+        Some(newest) if *newest > version && !maintained_lines.contains(&line) => {
+            Verdict::Unmaintained((*newest).clone())
+        }
+        _ => Verdict::Ok(highest_in_line),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_compare() {
+        let entry = |vers: &str, yanked: bool| IndexEntry {
+            name: String::from("foo"),
+            vers: vers.to_string(),
+            yanked,
+        };
+        let entries = vec![
+            entry("2.1.3", false),
+            entry("2.2.0-rc.1", false),
+            entry("2.2.0", false),
+            entry("2.2.1", true),
+        ];
+        let maintained = [String::from("2.1")];
+        let verdict =
+            |version: &str| compare(&Version::parse(version).unwrap(), &entries, &maintained);
+
+        assert_eq!(
+            verdict("2.2.2"),
+            Verdict::Ok(Some(Version::parse("2.2.0").unwrap()))
+        );
+        assert_eq!(
+            verdict("2.1.4"),
+            Verdict::Ok(Some(Version::parse("2.1.3").unwrap()))
+        );
+        assert_eq!(
+            compare(&Version::parse("2.1.4").unwrap(), &entries, &[]),
+            Verdict::Unmaintained(Version::parse("2.2.0").unwrap())
+        );
+        assert_eq!(
+            verdict("2.0.9"),
+            Verdict::Unmaintained(Version::parse("2.2.0").unwrap())
+        );
+        assert_eq!(verdict("2.3.0"), Verdict::Ok(None));
+        assert_eq!(
+            verdict("2.2.0"),
+            Verdict::AlreadyPublished { yanked: false }
+        );
+        assert_eq!(verdict("2.2.1"), Verdict::AlreadyPublished { yanked: true });
+        assert_eq!(
+            verdict("2.2.0-rc.2"),
+            Verdict::Regression(Version::parse("2.2.0").unwrap())
+        );
+    }
+}
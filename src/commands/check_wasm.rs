@@ -0,0 +1,101 @@
+use {
+    crate::{
+        config::Config,
+        utils::{
+            cargo::{build_lib_for_target, ensure_target_installed},
+            scope::resolve_manifest_path,
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::MetadataCommand,
+    clap::Args,
+    log::{info, warn},
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Crates to build [default: wasm.packages in xtask.toml]"
+    )]
+    pub package: Vec<String>,
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let manifest_path = resolve_manifest_path(&args.manifest_path)?;
+    let config = Config::load()?;
+    let target = &config.wasm.target;
+    let packages = if args.package.is_empty() {
+        config.wasm.packages.clone()
+    } else {
+        args.package
+    };
+    if packages.is_empty() {
+        info!("no wasm crates configured, set wasm.packages in xtask.toml");
+        return Ok(());
+    }
+
+    let metadata = MetadataCommand::new()
+        .manifest_path(&manifest_path)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+    let members: Vec<String> = metadata
+        .workspace_packages()
+        .iter()
+        .map(|p| p.name.to_string())
+        .collect();
+    let unknown = unknown_packages(&packages, &members);
+    if !unknown.is_empty() {
+        return Err(anyhow!("not workspace members: {}", unknown.join(", ")));
+    }
+
+    ensure_target_installed(metadata.workspace_root.as_std_path(), target)?;
+    let mut failures = vec![];
+    for name in packages.iter() {
+        info!("🧪 {name} for {target}");
+        match build_lib_for_target(&manifest_path, &config.network, name, target, &[]) {
+            Ok(()) => println!("  ✅ {name}"),
+            Err(e) => {
+                warn!("❌ {name}");
+                println!("  ❌ {name}\n{e:#}");
+                failures.push(name);
+            }
+        }
+    }
+    println!(
+        "{} of {} crate(s) built for {target}",
+        packages.len().saturating_sub(failures.len()),
+        packages.len()
+    );
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "{} crate(s) failed to build for {target}",
+            failures.len()
+        ));
+    }
+    Ok(())
+}
+
+fn unknown_packages<'a>(packages: &'a [String], members: &[String]) -> Vec<&'a str> {
+    packages
+        .iter()
+        .filter(|p| !members.contains(p))
+        .map(String::as_str)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_unknown_packages() {
+        let members = vec![String::from("solana-pubkey"), String::from("solana-hash")];
+        let packages = vec![String::from("solana-hash"), String::from("solana-typo")];
+        assert_eq!(unknown_packages(&packages, &members), vec!["solana-typo"]);
+    }
+}
@@ -0,0 +1,259 @@
+use {
+    super::check_package_size::format_size,
+    crate::utils::scope::resolve_manifest_path,
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::MetadataCommand,
+    clap::Args,
+    log::{info, warn},
+    std::{
+        env, fs,
+        path::{Path, PathBuf},
+        time::{Duration, SystemTime},
+    },
+    walkdir::WalkDir,
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(
+        long,
+        value_parser = parse_size,
+        help = "Shrink each directory to this size, least recently used files first (e.g. 50G)"
+    )]
+    pub max_size: Option<u64>,
+
+    #[arg(long, help = "Delete files not used for this many days")]
+    pub max_age_days: Option<u64>,
+
+    #[arg(
+        long,
+        help = "Directories to prune [default: the workspace target directory and the sccache directory]"
+    )]
+    pub dir: Vec<PathBuf>,
+
+    #[arg(long, help = "Only report what would be reclaimed")]
+    pub dry_run: bool,
+
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+}
+
+impl CommandArgs {
+    pub fn is_mutating(&self) -> bool {
+        !self.dry_run
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CacheFile {
+    pub path: PathBuf,
+    pub size: u64,
+    /// Later of the access and modification times; filesystems mounted
+    /// `noatime` only update the latter.
+    pub last_used: SystemTime,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    if args.max_size.is_none() && args.max_age_days.is_none() {
+        return Err(anyhow!("pass --max-size and/or --max-age-days"));
+    }
+    let dirs = if args.dir.is_empty() {
+        default_dirs(&args.manifest_path)?
+    } else {
+        args.dir
+    };
+    let max_age = args
+        .max_age_days
+        .map(|days| Duration::from_secs(days.saturating_mul(24 * 60 * 60)));
+
+    let mut reclaimed = 0u64;
+    for dir in dirs.iter() {
+        if !dir.is_dir() {
+            warn!("skipping {}, it does not exist", dir.display());
+            continue;
+        }
+        let files = cache_files(dir)?;
+        let total: u64 = files.iter().map(|f| f.size).sum();
+        let stale = select(files, args.max_size, max_age, SystemTime::now());
+        let size: u64 = stale.iter().map(|f| f.size).sum();
+        info!(
+            "{}: {} used, {} in {} stale file(s)",
+            dir.display(),
+            format_size(total),
+            format_size(size),
+            stale.len()
+        );
+        if args.dry_run {
+            continue;
+        }
+        for file in stale.iter() {
+            // a build may have removed it since the scan
+            if fs::remove_file(&file.path).is_ok() {
+                reclaimed = reclaimed.saturating_add(file.size);
+            }
+        }
+        remove_empty_dirs(dir);
+    }
+    if args.dry_run {
+        info!("dry run, nothing deleted");
+    } else {
+        info!("✅ reclaimed {}", format_size(reclaimed));
+    }
+    Ok(())
+}
+
+fn default_dirs(manifest_path: &str) -> Result<Vec<PathBuf>> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(resolve_manifest_path(manifest_path)?)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+    let mut dirs = vec![metadata.target_directory.into_std_path_buf()];
+    let sccache = env::var_os("SCCACHE_DIR")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".cache/sccache")));
+    if let Some(sccache) = sccache.filter(|dir| dir.is_dir()) {
+        dirs.push(sccache);
+    }
+    Ok(dirs)
+}
+
+fn cache_files(dir: &Path) -> Result<Vec<CacheFile>> {
+    let mut files = vec![];
+    for entry in WalkDir::new(dir) {
+        let entry = entry.context(format!("failed to walk {}", dir.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        let modified = metadata.modified()?;
+        let last_used = metadata
+            .accessed()
+            .map(|accessed| accessed.max(modified))
+            .unwrap_or(modified);
+        files.push(CacheFile {
+            path: entry.into_path(),
+            size: metadata.len(),
+            last_used,
+        });
+    }
+    Ok(files)
+}
+
+/// Files to delete: those unused for longer than `max_age`, then the least
+/// recently used ones until the rest fits in `max_size`.
+pub fn select(
+    mut files: Vec<CacheFile>,
+    max_size: Option<u64>,
+    max_age: Option<Duration>,
+    now: SystemTime,
+) -> Vec<CacheFile> {
+    files.sort_by_key(|f| f.last_used);
+    let mut total: u64 = files.iter().map(|f| f.size).sum();
+    let mut stale = vec![];
+    for file in files {
+        let expired = max_age.is_some_and(|max_age| {
+            now.duration_since(file.last_used)
+                .is_ok_and(|age| age > max_age)
+        });
+        let over = max_size.is_some_and(|max_size| total > max_size);
+        if !expired && !over {
+            break;
+        }
+        total = total.saturating_sub(file.size);
+        stale.push(file);
+    }
+    stale
+}
+
+fn remove_empty_dirs(dir: &Path) {
+    for entry in WalkDir::new(dir)
+        .min_depth(1)
+        .contents_first(true)
+        .into_iter()
+        .flatten()
+    {
+        if entry.file_type().is_dir() {
+            // fails for directories that still have files, which is fine
+            let _ = fs::remove_dir(entry.path());
+        }
+    }
+}
+
+/// Sizes like `50G`, `512M` or `1024` (bytes), in powers of 1024.
+pub fn parse_size(value: &str) -> Result<u64> {
+    let value = value.trim();
+    let (number, unit) = value
+        .find(|c: char| !c.is_ascii_digit())
+        .map(|at| value.split_at(at))
+        .unwrap_or((value, ""));
+    let number: u64 = number
+        .parse()
+        .map_err(|_| anyhow!("invalid size {value:?}"))?;
+    let shift = match unit.trim().to_ascii_uppercase().trim_end_matches("IB") {
+        "" | "B" => 0,
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        "T" => 40,
+        _ => return Err(anyhow!("invalid size unit in {value:?}, use K, M, G or T")),
+    };
+    number
+        .checked_mul(1u64 << shift)
+        .ok_or_else(|| anyhow!("size {value:?} is too large"))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1024").unwrap(), 1024);
+        assert_eq!(parse_size("50G").unwrap(), 50 << 30);
+        assert_eq!(parse_size("512MiB").unwrap(), 512 << 20);
+        assert_eq!(parse_size("2 k").unwrap(), 2048);
+        assert!(parse_size("G").is_err());
+        assert!(parse_size("5X").is_err());
+    }
+
+    #[test]
+    fn test_select() {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(100 * 24 * 60 * 60);
+        let day = Duration::from_secs(24 * 60 * 60);
+        let file = |name: &str, size: u64, days_ago: u32| CacheFile {
+            path: PathBuf::from(name),
+            size,
+            last_used: now - day * days_ago,
+        };
+        let files = vec![
+            file("new", 10, 0),
+            file("old", 10, 30),
+            file("older", 10, 40),
+            file("recent", 10, 2),
+        ];
+        let names = |stale: Vec<CacheFile>| {
+            stale
+                .into_iter()
+                .map(|f| f.path.display().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            names(select(files.clone(), None, Some(day * 7), now)),
+            vec!["older", "old"]
+        );
+        assert_eq!(
+            names(select(files.clone(), Some(15), None, now)),
+            vec!["older", "old", "recent"]
+        );
+        assert_eq!(
+            names(select(files.clone(), Some(35), Some(day * 35), now)),
+            vec!["older"]
+        );
+        assert_eq!(
+            names(select(files, Some(100), None, now)),
+            Vec::<String>::new()
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use {
+    crate::{
+        config::{CliBinary, Config},
+        utils::{http::apply_cargo_network_env, scope::resolve_manifest_path},
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::MetadataCommand,
+    clap::Args,
+    log::{info, warn},
+    std::{collections::BTreeSet, fs, path::Path, process::Command},
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, help = "Rewrite the snapshots from the current output")]
+    pub update: bool,
+
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    let binaries = &config.cli_snapshots.binaries;
+    if binaries.is_empty() {
+        info!("no binaries configured under cli-snapshots.binaries in xtask.toml");
+        return Ok(());
+    }
+    let manifest_path = resolve_manifest_path(&args.manifest_path)?;
+    let metadata = MetadataCommand::new()
+        .manifest_path(&manifest_path)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+
+    let mut cmd = Command::new("cargo");
+    cmd.args([
+        "build",
+        "--quiet",
+        "--workspace",
+        "--manifest-path",
+        &manifest_path,
+    ]);
+    for binary in binaries.iter() {
+        cmd.args(["--bin", &binary.name]);
+    }
+    apply_cargo_network_env(&mut cmd, &config.network);
+    let status = cmd
+        .status()
+        .map_err(|e| anyhow!("failed to run cargo build: {e}"))?;
+    if !status.success() {
+        return Err(anyhow!("cargo build failed with {status}"));
+    }
+    let bin_dir = metadata.target_directory.as_std_path().join("debug");
+
+    let dir = config.resolve(&config.cli_snapshots.dir);
+    fs::create_dir_all(&dir).context(format!("failed to create {}", dir.display()))?;
+    let mut expected_files = BTreeSet::new();
+    let mut mismatches = 0usize;
+    for binary in binaries.iter() {
+        for arguments in invocations(binary) {
+            let name = snapshot_name(&binary.name, &arguments);
+            let path = dir.join(&name);
+            expected_files.insert(name);
+            let actual = render(&bin_dir, &binary.name, &arguments)?;
+            if args.update {
+                fs::write(&path, &actual).context(format!("failed to write {}", path.display()))?;
+                continue;
+            }
+            let Ok(expected) = fs::read_to_string(&path) else {
+                mismatches = mismatches.saturating_add(1);
+                warn!("❌ {} is missing", path.display());
+                continue;
+            };
+            if let Some((line, want, got)) = first_difference(&expected, &actual) {
+                mismatches = mismatches.saturating_add(1);
+                warn!(
+                    "❌ {} differs at line {line}:\n  - {want}\n  + {got}",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    // snapshots of invocations that were removed from the config
+    for stale in snapshot_files(&dir)?.difference(&expected_files) {
+        let path = dir.join(stale);
+        if args.update {
+            fs::remove_file(&path).context(format!("failed to remove {}", path.display()))?;
+        } else {
+            mismatches = mismatches.saturating_add(1);
+            warn!("❌ {} is no longer configured", path.display());
+        }
+    }
+
+    if args.update {
+        info!(
+            "✅ wrote {} snapshot(s) to {}",
+            expected_files.len(),
+            dir.display()
+        );
+        return Ok(());
+    }
+    if mismatches > 0 {
+        return Err(anyhow!(
+            "{mismatches} CLI snapshot(s) out of date, review the change and run `xtask cli-snapshots --update`"
+        ));
+    }
+    info!("✅ {} CLI snapshot(s) match", expected_files.len());
+    Ok(())
+}
+
+fn invocations(binary: &CliBinary) -> Vec<Vec<String>> {
+    if binary.invocations.is_empty() {
+        vec![vec![String::from("--help")]]
+    } else {
+        binary.invocations.clone()
+    }
+}
+
+/// `<bin>_<args>.txt`, e.g. `solana_account_help.txt` for `solana account --help`.
+pub fn snapshot_name(binary: &str, arguments: &[String]) -> String {
+    let mut name = binary.to_string();
+    for argument in arguments {
+        let argument: String = argument
+            .trim_start_matches('-')
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '-'
+                }
+            })
+            .collect();
+        name.push('_');
+        name.push_str(&argument);
+    }
+    name.push_str(".txt");
+    name
+}
+
+/// The command line followed by what the binary printed; a non-zero exit
+/// status is part of the snapshot.
+fn render(bin_dir: &Path, binary: &str, arguments: &[String]) -> Result<String> {
+    let output = Command::new(bin_dir.join(binary))
+        .args(arguments)
+        .output()
+        .map_err(|e| anyhow!("failed to run {binary}: {e}"))?;
+    let mut rendered = format!("$ {binary}");
+    for argument in arguments {
+        rendered.push(' ');
+        rendered.push_str(argument);
+    }
+    rendered.push('\n');
+    rendered.push_str(&String::from_utf8_lossy(&output.stdout));
+    rendered.push_str(&String::from_utf8_lossy(&output.stderr));
+    if !output.status.success() {
+        rendered.push_str(&format!("[{}]\n", output.status));
+    }
+    Ok(rendered)
+}
+
+fn snapshot_files(dir: &Path) -> Result<BTreeSet<String>> {
+    let mut files = BTreeSet::new();
+    for entry in fs::read_dir(dir).context(format!("failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|e| e == "txt") {
+            if let Some(name) = path.file_name() {
+                files.insert(name.to_string_lossy().to_string());
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// First differing line (1-based) with the expected and actual text,
+/// `None` when both match.
+pub fn first_difference<'a>(
+    expected: &'a str,
+    actual: &'a str,
+) -> Option<(usize, &'a str, &'a str)> {
+    if expected == actual {
+        return None;
+    }
+    let mut expected_lines = expected.lines();
+    let mut actual_lines = actual.lines();
+    let mut line = 1usize;
+    loop {
+        match (expected_lines.next(), actual_lines.next()) {
+            (Some(want), Some(got)) if want == got => line = line.saturating_add(1),
+            (want, got) => return Some((line, want.unwrap_or(""), got.unwrap_or(""))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_snapshot_name() {
+        let arguments = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            snapshot_name("solana", &arguments(&["--help"])),
+            "solana_help.txt"
+        );
+        assert_eq!(
+            snapshot_name("solana", &arguments(&["account", "--help"])),
+            "solana_account_help.txt"
+        );
+        assert_eq!(
+            snapshot_name("agave-validator", &arguments(&["--url=x/y"])),
+            "agave-validator_url-x-y.txt"
+        );
+        assert_eq!(snapshot_name("foo", &[]), "foo.txt");
+    }
+
+    #[test]
+    fn test_first_difference() {
+        assert_eq!(first_difference("a\nb\n", "a\nb\n"), None);
+        assert_eq!(first_difference("a\nb\n", "a\nc\n"), Some((2, "b", "c")));
+        assert_eq!(first_difference("a\n", "a\nb\n"), Some((2, "", "b")));
+        assert_eq!(first_difference("a\n", "a"), Some((2, "", "")));
+    }
+}
@@ -0,0 +1,215 @@
+use {
+    crate::{
+        config::{Config, Generator},
+        utils::{git::git_in, scope::resolve_manifest_path},
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::MetadataCommand,
+    clap::Args,
+    log::{info, warn},
+    std::{path::Path, process::Command},
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(
+        long,
+        help = "Fail if the committed outputs differ from regenerated ones, leaving them untouched"
+    )]
+    pub check: bool,
+
+    #[arg(long, help = "Only run these generators")]
+    pub generator: Vec<String>,
+
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+}
+
+impl CommandArgs {
+    pub fn is_mutating(&self) -> bool {
+        !self.check
+    }
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    let generators: Vec<&Generator> = config
+        .codegen
+        .generators
+        .iter()
+        .filter(|g| args.generator.is_empty() || args.generator.contains(&g.name))
+        .collect();
+    if generators.is_empty() {
+        info!("no generators configured under codegen.generators in xtask.toml");
+        return Ok(());
+    }
+    let metadata = MetadataCommand::new()
+        .manifest_path(resolve_manifest_path(&args.manifest_path)?)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+
+    let mut stale = vec![];
+    for generator in generators {
+        let dir = match &generator.package {
+            Some(package) => metadata
+                .workspace_packages()
+                .into_iter()
+                .find(|p| p.name.as_str() == package)
+                .and_then(|p| p.manifest_path.parent())
+                .map(|p| p.as_std_path().to_path_buf())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "generator {} names {package}, which is not in the workspace",
+                        generator.name
+                    )
+                })?,
+            None => config.root.clone(),
+        };
+        info!("⚙️ {}", generator.name);
+        if !args.check {
+            run_generator(generator, &dir)?;
+            continue;
+        }
+        let changed = check_generator(&config.root, generator, &dir)?;
+        if !changed.is_empty() {
+            for line in changed.iter() {
+                warn!("  {line}");
+            }
+            stale.push(generator.name.clone());
+        }
+    }
+    if !stale.is_empty() {
+        return Err(anyhow!(
+            "generated code is out of date for {}, run `xtask codegen` and commit the result",
+            stale.join(", ")
+        ));
+    }
+    if args.check {
+        info!("✅ generated code is up to date");
+    }
+    Ok(())
+}
+
+fn run_generator(generator: &Generator, dir: &Path) -> Result<()> {
+    let (program, arguments) = generator
+        .command
+        .split_first()
+        .ok_or_else(|| anyhow!("generator {} has an empty command", generator.name))?;
+    let status = Command::new(program)
+        .args(arguments)
+        .current_dir(dir)
+        .status()
+        .map_err(|e| anyhow!("failed to run {program}: {e}"))?;
+    if !status.success() {
+        return Err(anyhow!("generator {} failed with {status}", generator.name));
+    }
+    Ok(())
+}
+
+/// Regenerates the outputs of `generator` in the `root` checkout and returns
+/// the `git status` lines of what changed, restoring the committed files
+/// afterwards.
+pub fn check_generator(root: &Path, generator: &Generator, dir: &Path) -> Result<Vec<String>> {
+    let outputs: Vec<String> = generator
+        .outputs
+        .iter()
+        .map(|p| p.display().to_string())
+        .collect();
+    if outputs.is_empty() {
+        return Err(anyhow!(
+            "generator {} declares no outputs to check",
+            generator.name
+        ));
+    }
+    // uncommitted edits would be reported as stale, and lost on restore
+    if !output_status(root, &outputs)?.is_empty() {
+        return Err(anyhow!(
+            "outputs of {} have uncommitted changes, commit or stash them first",
+            generator.name
+        ));
+    }
+
+    let result = run_generator(generator, dir).and_then(|_| output_status(root, &outputs));
+    for output in outputs.iter() {
+        git_in(root, &["clean", "-fdq", "--", output])?;
+        if !git_in(root, &["ls-files", "--", output])?.is_empty() {
+            git_in(root, &["checkout", "HEAD", "--", output])?;
+        }
+    }
+    result
+}
+
+fn output_status(root: &Path, outputs: &[String]) -> Result<Vec<String>> {
+    // not git_in, trimming would eat the status column of the first line
+    let output = Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=all", "--"])
+        .args(outputs)
+        .current_dir(root)
+        .output()
+        .map_err(|e| anyhow!("failed to run git: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git status failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        pretty_assertions::assert_eq,
+        std::{fs, path::PathBuf},
+    };
+
+    #[test]
+    #[cfg(unix)] // the generators are `sh` scripts
+    fn test_check_generator() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@localhost"])
+                .args(args)
+                .current_dir(dir)
+                .output()
+                .unwrap()
+        };
+        git(&["init"]);
+        fs::create_dir(dir.join("gen")).unwrap();
+        fs::write(dir.join("gen/out.rs"), "a\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-m", "init"]);
+
+        let generator = |script: &str| Generator {
+            name: String::from("test"),
+            package: None,
+            command: vec![String::from("sh"), String::from("-c"), script.to_string()],
+            outputs: vec![PathBuf::from("gen")],
+        };
+        assert_eq!(
+            check_generator(dir, &generator("echo a > gen/out.rs"), dir).unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            check_generator(
+                dir,
+                &generator("echo b > gen/out.rs && echo c > gen/new.rs"),
+                dir
+            )
+            .unwrap(),
+            vec![" M gen/out.rs", "?? gen/new.rs"]
+        );
+        assert_eq!(fs::read_to_string(dir.join("gen/out.rs")).unwrap(), "a\n");
+        assert!(!dir.join("gen/new.rs").exists());
+
+        fs::write(dir.join("gen/out.rs"), "edited\n").unwrap();
+        assert!(check_generator(dir, &generator("true"), dir).is_err());
+    }
+}
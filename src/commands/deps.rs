@@ -0,0 +1,347 @@
+use {
+    crate::utils::{
+        discovery::Discovery, get_git_root_path, git::with_worktree_in, recursive_find_files,
+    },
+    anyhow::{Context, Result},
+    clap::{Args, Subcommand},
+    log::info,
+    semver::Version,
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[command(subcommand)]
+    pub subcommand: DepsSubcommand,
+}
+
+#[derive(Subcommand)]
+pub enum DepsSubcommand {
+    #[command(about = "Show locked dependencies added, removed or changed between two refs")]
+    Diff {
+        #[arg(long, default_value = "origin/main")]
+        base: String,
+        #[arg(
+            long,
+            help = "Ref to compare against the base [default: the working tree]"
+        )]
+        head: Option<String>,
+        #[arg(long, help = "Print the changes as JSON")]
+        json: bool,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct LockFile {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+    source: Option<String>,
+    #[serde(default)]
+    dependencies: Vec<String>,
+}
+
+/// The registry and git packages of one lockfile.
+#[derive(Debug, Default, PartialEq)]
+pub struct LockedDependencies {
+    /// Versions locked per crate; a crate can be locked at several versions.
+    pub versions: BTreeMap<String, BTreeSet<String>>,
+    /// Crates a local (workspace) package depends on directly.
+    pub direct: BTreeSet<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Upgraded,
+    Downgraded,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DependencyChange {
+    pub kind: ChangeKind,
+    pub name: String,
+    pub before: Vec<String>,
+    pub after: Vec<String>,
+    pub direct: bool,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct LockfileDiff {
+    pub lockfile: PathBuf,
+    pub changes: Vec<DependencyChange>,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    match args.subcommand {
+        DepsSubcommand::Diff { base, head, json } => {
+            let repo = get_git_root_path()?;
+            info!("🔎 reading lockfiles at {base}");
+            let before = with_worktree_in(&repo, &base, read_lockfiles)?;
+            let head_label = head.clone().unwrap_or_else(|| String::from("working tree"));
+            info!("🔎 reading lockfiles at {head_label}");
+            let after = match &head {
+                Some(head) => with_worktree_in(&repo, head, read_lockfiles)?,
+                None => read_lockfiles(&repo)?,
+            };
+            let diffs = diff(&before, &after);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&diffs)?);
+            } else {
+                print!("{}", render_markdown(&base, &head_label, &diffs));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Every lockfile in the checkout at `dir`, keyed by its relative path.
+fn read_lockfiles(dir: &Path) -> Result<BTreeMap<PathBuf, LockedDependencies>> {
+    let mut lockfiles = BTreeMap::new();
+    for path in recursive_find_files(dir, "Cargo.lock", &Discovery::for_tree(dir)?, |_| true)? {
+        let content =
+            fs::read_to_string(&path).context(format!("failed to read {}", path.display()))?;
+        let relative = path.strip_prefix(dir).unwrap_or(&path).to_path_buf();
+        lockfiles.insert(
+            relative,
+            parse_lockfile(&content).context(format!("failed to parse {}", path.display()))?,
+        );
+    }
+    Ok(lockfiles)
+}
+
+pub fn parse_lockfile(content: &str) -> Result<LockedDependencies> {
+    let lockfile: LockFile = toml_edit::de::from_str(content)?;
+    let mut locked = LockedDependencies::default();
+    for package in lockfile.package {
+        if package.source.is_none() {
+            // entries are `name`, `name version` or `name version (source)`
+            locked.direct.extend(
+                package
+                    .dependencies
+                    .iter()
+                    .filter_map(|dep| dep.split_whitespace().next())
+                    .map(str::to_string),
+            );
+            continue;
+        }
+        locked
+            .versions
+            .entry(package.name)
+            .or_default()
+            .insert(package.version);
+    }
+    Ok(locked)
+}
+
+/// Compares lockfiles by path; a lockfile missing on one side counts as empty.
+pub fn diff(
+    before: &BTreeMap<PathBuf, LockedDependencies>,
+    after: &BTreeMap<PathBuf, LockedDependencies>,
+) -> Vec<LockfileDiff> {
+    let empty = LockedDependencies::default();
+    let paths: BTreeSet<&PathBuf> = before.keys().chain(after.keys()).collect();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let changes = diff_lockfile(
+                before.get(path).unwrap_or(&empty),
+                after.get(path).unwrap_or(&empty),
+            );
+            (!changes.is_empty()).then(|| LockfileDiff {
+                lockfile: path.clone(),
+                changes,
+            })
+        })
+        .collect()
+}
+
+fn diff_lockfile(before: &LockedDependencies, after: &LockedDependencies) -> Vec<DependencyChange> {
+    let names: BTreeSet<&String> = before
+        .versions
+        .keys()
+        .chain(after.versions.keys())
+        .collect();
+    let mut changes = vec![];
+    for name in names {
+        let old = before.versions.get(name);
+        let new = after.versions.get(name);
+        if old == new {
+            continue;
+        }
+        let kind = match (old, new) {
+            (None, _) => ChangeKind::Added,
+            (_, None) => ChangeKind::Removed,
+            (Some(old), Some(new)) if highest(new) < highest(old) => ChangeKind::Downgraded,
+            _ => ChangeKind::Upgraded,
+        };
+        let direct = match kind {
+            ChangeKind::Removed => before.direct.contains(name),
+            _ => after.direct.contains(name),
+        };
+        changes.push(DependencyChange {
+            kind,
+            name: name.clone(),
+            before: old.map(|v| v.iter().cloned().collect()).unwrap_or_default(),
+            after: new.map(|v| v.iter().cloned().collect()).unwrap_or_default(),
+            direct,
+        });
+    }
+    // direct changes first, they are the ones a reviewer asked for
+    changes.sort_by(|a, b| b.direct.cmp(&a.direct).then(a.name.cmp(&b.name)));
+    changes
+}
+
+fn highest(versions: &BTreeSet<String>) -> Option<Version> {
+    versions.iter().filter_map(|v| Version::parse(v).ok()).max()
+}
+
+/// Markdown suitable for a pull request comment.
+pub fn render_markdown(base: &str, head: &str, diffs: &[LockfileDiff]) -> String {
+    let mut out = format!("## Dependency changes ({base}..{head})\n\n");
+    if diffs.is_empty() {
+        out.push_str("No locked dependencies changed.\n");
+        return out;
+    }
+    for lockfile in diffs {
+        let count = |kind| lockfile.changes.iter().filter(|c| c.kind == kind).count();
+        out.push_str(&format!(
+            "### {}\n\n{} added, {} removed, {} upgraded, {} downgraded\n\n",
+            lockfile.lockfile.display(),
+            count(ChangeKind::Added),
+            count(ChangeKind::Removed),
+            count(ChangeKind::Upgraded),
+            count(ChangeKind::Downgraded)
+        ));
+        out.push_str("| Change | Crate | Before | After | Direct |\n|---|---|---|---|---|\n");
+        for change in lockfile.changes.iter() {
+            let label = match change.kind {
+                ChangeKind::Added => "➕ added",
+                ChangeKind::Removed => "➖ removed",
+                ChangeKind::Upgraded => "⬆️ upgraded",
+                ChangeKind::Downgraded => "⬇️ downgraded",
+            };
+            out.push_str(&format!(
+                "| {label} | `{}` | {} | {} | {} |\n",
+                change.name,
+                versions_cell(&change.before),
+                versions_cell(&change.after),
+                if change.direct { "yes" } else { "" }
+            ));
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn versions_cell(versions: &[String]) -> String {
+    if versions.is_empty() {
+        String::from("-")
+    } else {
+        versions.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    const REGISTRY: &str = "registry+https://github.com/rust-lang/crates.io-index";
+
+    fn lockfile(packages: &[(&str, &str, bool, &[&str])]) -> String {
+        let mut content = String::from("version = 4\n");
+        for (name, version, local, dependencies) in packages {
+            content.push_str(&format!(
+                "\n[[package]]\nname = \"{name}\"\nversion = \"{version}\"\n"
+            ));
+            if !local {
+                content.push_str(&format!("source = \"{REGISTRY}\"\n"));
+            }
+            if !dependencies.is_empty() {
+                let deps: Vec<String> = dependencies.iter().map(|d| format!("\"{d}\"")).collect();
+                content.push_str(&format!("dependencies = [{}]\n", deps.join(", ")));
+            }
+        }
+        content
+    }
+
+    #[test]
+    fn test_parse_lockfile() {
+        let locked = parse_lockfile(&lockfile(&[
+            ("a", "0.1.0", true, &["serde", "syn 2.0.1"]),
+            ("serde", "1.0.0", false, &["syn 1.0.0"]),
+            ("syn", "1.0.0", false, &[]),
+            ("syn", "2.0.1", false, &[]),
+        ]))
+        .unwrap();
+        assert_eq!(
+            locked.versions,
+            BTreeMap::from([
+                (
+                    String::from("serde"),
+                    BTreeSet::from([String::from("1.0.0")])
+                ),
+                (
+                    String::from("syn"),
+                    BTreeSet::from([String::from("1.0.0"), String::from("2.0.1")])
+                ),
+            ])
+        );
+        assert_eq!(
+            locked.direct,
+            BTreeSet::from([String::from("serde"), String::from("syn")])
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = BTreeMap::from([(
+            PathBuf::from("Cargo.lock"),
+            parse_lockfile(&lockfile(&[
+                ("a", "0.1.0", true, &["serde", "log"]),
+                ("serde", "1.0.0", false, &[]),
+                ("log", "0.4.0", false, &[]),
+                ("itoa", "1.0.2", false, &[]),
+            ]))
+            .unwrap(),
+        )]);
+        let after = BTreeMap::from([(
+            PathBuf::from("Cargo.lock"),
+            parse_lockfile(&lockfile(&[
+                ("a", "0.2.0", true, &["serde"]),
+                ("serde", "1.0.5", false, &["ryu"]),
+                ("ryu", "1.0.0", false, &[]),
+                ("itoa", "1.0.1", false, &[]),
+            ]))
+            .unwrap(),
+        )]);
+        let diffs = diff(&before, &after);
+        assert_eq!(
+            render_markdown("origin/main", "HEAD", &diffs),
+            "## Dependency changes (origin/main..HEAD)\n\n\
+             ### Cargo.lock\n\n\
+             1 added, 1 removed, 1 upgraded, 1 downgraded\n\n\
+             | Change | Crate | Before | After | Direct |\n|---|---|---|---|---|\n\
+             | ➖ removed | `log` | 0.4.0 | - | yes |\n\
+             | ⬆️ upgraded | `serde` | 1.0.0 | 1.0.5 | yes |\n\
+             | ⬇️ downgraded | `itoa` | 1.0.2 | 1.0.1 |  |\n\
+             | ➕ added | `ryu` | - | 1.0.0 |  |\n\n"
+        );
+        assert_eq!(
+            render_markdown("origin/main", "HEAD", &diff(&before, &before)),
+            "## Dependency changes (origin/main..HEAD)\n\nNo locked dependencies changed.\n"
+        );
+    }
+}
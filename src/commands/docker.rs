@@ -0,0 +1,238 @@
+use {
+    crate::{
+        config::Config,
+        utils::{
+            docker::{push_with_retry, run_checked},
+            get_current_version,
+            release_metadata::ReleaseMetadata,
+        },
+    },
+    anyhow::{anyhow, Result},
+    clap::{Args, Subcommand, ValueEnum},
+    log::info,
+    semver::Version,
+    std::{process::Command, time::Duration},
+};
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum TagScheme {
+    #[value(
+        help = "x.y.z, x.y, x and latest for stable; x.y.z-<pre> and <channel> for prereleases"
+    )]
+    Semver,
+}
+
+#[derive(Subcommand)]
+pub enum DockerSubcommand {
+    #[command(about = "Tag (and push) an image following the version tagging scheme")]
+    Tag {
+        #[arg(long, help = "Local image to tag, e.g. the output of `docker build`")]
+        source: String,
+        #[arg(long, help = "Target repository, e.g. anzaxyz/agave")]
+        image: String,
+        #[arg(long, value_enum, default_value = "semver")]
+        scheme: TagScheme,
+        #[arg(
+            long,
+            help = "Version to derive tags from, defaults to the workspace version"
+        )]
+        version: Option<Version>,
+        #[arg(long)]
+        push: bool,
+        #[arg(long, default_value_t = 5, help = "Push attempts before giving up")]
+        push_attempts: u32,
+    },
+}
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[command(subcommand)]
+    pub subcommand: DockerSubcommand,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    match args.subcommand {
+        DockerSubcommand::Tag {
+            source,
+            image,
+            scheme,
+            version,
+            push,
+            push_attempts,
+        } => {
+            let version = match version {
+                Some(version) => version,
+                None => Version::parse(&get_current_version()?)?,
+            };
+            let published = published_versions(&image)?;
+            let tags = derive_tags(scheme, &version, &published)?;
+            for tag in tags.iter() {
+                let target = format!("{image}:{tag}");
+                info!("tagging {source} as {target}");
+                run_checked(
+                    Command::new("docker").args(["tag", &source, &target]),
+                    "docker tag",
+                )?;
+            }
+            if push {
+                let config = Config::load()?;
+                let metadata_path = config.resolve(&config.release.metadata);
+                let mut metadata = ReleaseMetadata::load(&metadata_path)?;
+                metadata.version = Some(version.to_string());
+                for tag in tags.iter() {
+                    let target = format!("{image}:{tag}");
+                    info!("pushing {target}");
+                    let digest = push_with_retry(&target, push_attempts, Duration::from_secs(5))?;
+                    info!("  {target}@{digest}");
+                    metadata.record_image(&target, &digest);
+                    // save after every push so a later failure keeps earlier digests
+                    metadata.save(&metadata_path)?;
+                }
+                info!("recorded image digests in {}", metadata_path.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Versions `image` already has an exact tag for, empty for a repository
+/// that does not exist yet.
+fn published_versions(image: &str) -> Result<Vec<Version>> {
+    let output = Command::new("crane")
+        .args(["ls", image])
+        .output()
+        .map_err(|e| anyhow!("failed to run crane: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("NAME_UNKNOWN") {
+            return Ok(vec![]);
+        }
+        return Err(anyhow!(
+            "failed to list the tags of {image}: {}",
+            stderr.trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|tag| Version::parse(tag.trim()).ok())
+        .collect())
+}
+
+/// Derives the image tags for `version`, given the versions already
+/// `published` to the repository.
+///
+/// Stable releases move the floating `x.y`, `x` and `latest` tags, each only
+/// when no newer stable release of its line exists, so a backport never
+/// moves them back. Prereleases only get their exact tag plus a channel tag
+/// (`alpha`, `beta`, `rc`) under the same rule, and can never move `latest`.
+pub fn derive_tags(
+    scheme: TagScheme,
+    version: &Version,
+    published: &[Version],
+) -> Result<Vec<String>> {
+    match scheme {
+        TagScheme::Semver => {
+            if !version.build.is_empty() {
+                return Err(anyhow!(
+                    "build metadata is not allowed in image tags: {version}"
+                ));
+            }
+            let newest = |in_line: &dyn Fn(&Version) -> bool| {
+                !published.iter().any(|v| in_line(v) && v > version)
+            };
+            let mut tags = vec![version.to_string()];
+            if version.pre.is_empty() {
+                let stable = |v: &Version| v.pre.is_empty();
+                if newest(&|v| stable(v) && v.major == version.major && v.minor == version.minor) {
+                    tags.push(format!("{}.{}", version.major, version.minor));
+                }
+                if newest(&|v| stable(v) && v.major == version.major) {
+                    tags.push(version.major.to_string());
+                }
+                if newest(&stable) {
+                    tags.push(String::from("latest"));
+                }
+            } else {
+                let channel = channel(version);
+                if channel == "latest" {
+                    return Err(anyhow!("refusing to move latest to prerelease {version}"));
+                }
+                // `2.3.0-2` has no channel, a numeric tag would clash with
+                // the major tags
+                if channel.is_empty() || channel.chars().all(|c| c.is_ascii_digit()) {
+                    return Ok(tags);
+                }
+                if newest(&|v| !v.pre.is_empty() && self::channel(v) == channel) {
+                    tags.push(channel.to_string());
+                }
+            }
+            Ok(tags)
+        }
+    }
+}
+
+fn channel(version: &Version) -> &str {
+    version.pre.as_str().split('.').next().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn versions(versions: &[&str]) -> Vec<Version> {
+        versions
+            .iter()
+            .map(|v| Version::parse(v).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_derive_tags_stable() {
+        let derive = |version: &str, published: &[&str]| {
+            derive_tags(
+                TagScheme::Semver,
+                &Version::parse(version).unwrap(),
+                &versions(published),
+            )
+            .unwrap()
+        };
+        assert_eq!(
+            derive("2.2.0", &["2.1.9", "2.2.0-rc.1"]),
+            ["2.2.0", "2.2", "2", "latest"]
+        );
+        // a backport only moves its own line
+        assert_eq!(
+            derive("1.18.5", &["1.18.4", "2.0.0", "2.0.1"]),
+            ["1.18.5", "1.18", "1"]
+        );
+        assert_eq!(
+            derive("1.17.9", &["1.17.8", "1.18.4", "2.0.0"]),
+            ["1.17.9", "1.17"]
+        );
+    }
+
+    #[test]
+    fn test_derive_tags_prerelease() {
+        let derive = |version: &str, published: &[&str]| {
+            derive_tags(
+                TagScheme::Semver,
+                &Version::parse(version).unwrap(),
+                &versions(published),
+            )
+        };
+        assert_eq!(
+            derive("2.3.0-rc.1", &["2.2.0", "2.3.0-beta.0"]).unwrap(),
+            ["2.3.0-rc.1", "rc"]
+        );
+        assert_eq!(
+            derive("2.3.0-beta.0", &[]).unwrap(),
+            ["2.3.0-beta.0", "beta"]
+        );
+        assert_eq!(
+            derive("2.2.1-rc.1", &["2.3.0-rc.0"]).unwrap(),
+            ["2.2.1-rc.1"]
+        );
+        assert_eq!(derive("2.3.0-2", &[]).unwrap(), ["2.3.0-2"]);
+        assert!(derive("2.3.0-latest.0", &[]).is_err());
+    }
+}
@@ -0,0 +1,341 @@
+use {
+    crate::{
+        commands::publish::CRATES_IO,
+        config::{Config, CONFIG_FILE_NAME},
+        utils::{credentials, get_git_root_path, get_toolchain_channel, http},
+    },
+    anyhow::{anyhow, Result},
+    clap::Args,
+    std::{fmt, path::Path, process::Command},
+    toml_edit::DocumentMut,
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, help = "Skip checks that need network access")]
+    pub offline: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Check {
+    pub name: &'static str,
+    pub status: CheckStatus,
+    pub detail: String,
+    pub fix: Option<String>,
+}
+
+impl Check {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Check {
+            name,
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Check {
+            name,
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Check {
+            name,
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+}
+
+impl fmt::Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let icon = match self.status {
+            CheckStatus::Pass => "✅",
+            CheckStatus::Warn => "⚠️ ",
+            CheckStatus::Fail => "❌",
+        };
+        write!(f, "{icon} {}: {}", self.name, self.detail)?;
+        if let Some(fix) = &self.fix {
+            write!(f, "\n     fix: {fix}")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let checks = run_checks(args.offline);
+    for check in checks.iter() {
+        println!("{check}");
+    }
+
+    let failures = checks
+        .iter()
+        .filter(|check| check.status == CheckStatus::Fail)
+        .count();
+    if failures > 0 {
+        return Err(anyhow!("{failures} check(s) failed"));
+    }
+    Ok(())
+}
+
+pub fn run_checks(offline: bool) -> Vec<Check> {
+    let mut checks = vec![
+        check_tool("git", &["--version"], "install git"),
+        check_tool(
+            "cargo",
+            &["--version"],
+            "install rust via https://rustup.rs",
+        ),
+        check_tool(
+            "docker",
+            &["--version"],
+            "install docker (needed by `publish test`)",
+        ),
+    ];
+
+    let git_root = get_git_root_path()
+        .ok()
+        .filter(|root| !root.as_os_str().is_empty());
+    match &git_root {
+        Some(root) => {
+            checks.push(Check::pass("git repository", root.display().to_string()));
+            checks.push(check_workspace_layout(root));
+            checks.push(check_toolchain(root));
+            checks.push(check_config(root));
+        }
+        None => checks.push(Check::fail(
+            "git repository",
+            "not inside a git repository",
+            "run xtask from within the repository checkout",
+        )),
+    }
+
+    if !offline {
+        let config = Config::load().unwrap_or_default();
+        checks.push(check_registry_token(&config));
+        checks.push(check_github_token(&config.network));
+    }
+    checks
+}
+
+fn check_tool(name: &'static str, args: &[&str], fix: &str) -> Check {
+    match Command::new(name).args(args).output() {
+        Ok(output) if output.status.success() => Check::pass(
+            name,
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ),
+        Ok(output) => Check::fail(
+            name,
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            fix,
+        ),
+        Err(e) => Check::fail(name, format!("not found ({e})"), fix),
+    }
+}
+
+pub fn check_workspace_layout(root: &Path) -> Check {
+    const NAME: &str = "workspace layout";
+    let manifest = root.join("Cargo.toml");
+    let Ok(content) = std::fs::read_to_string(&manifest) else {
+        return Check::fail(
+            NAME,
+            format!("{} is missing", manifest.display()),
+            "run xtask from the root of a cargo workspace",
+        );
+    };
+    let Ok(doc) = content.parse::<DocumentMut>() else {
+        return Check::fail(
+            NAME,
+            format!("{} is not valid TOML", manifest.display()),
+            "fix the syntax errors reported by `cargo metadata`",
+        );
+    };
+    let Some(workspace) = doc.get("workspace") else {
+        return Check::fail(
+            NAME,
+            "root Cargo.toml has no [workspace] section",
+            "add a [workspace] section listing the member crates",
+        );
+    };
+    if workspace
+        .get("package")
+        .and_then(|package| package.get("version"))
+        .is_none()
+    {
+        return Check::warn(
+            NAME,
+            "workspace.package.version is not set",
+            "declare the shared version in [workspace.package] so bump-version can find it",
+        );
+    }
+    Check::pass(NAME, "root Cargo.toml declares workspace.package.version")
+}
+
+fn check_toolchain(root: &Path) -> Check {
+    const NAME: &str = "toolchain";
+    let channel = match get_toolchain_channel(root) {
+        Ok(Some(channel)) => channel,
+        Ok(None) => return Check::pass(NAME, "no rust-toolchain pin, using the default"),
+        Err(e) => {
+            return Check::fail(
+                NAME,
+                format!("failed to read rust-toolchain: {e}"),
+                "fix rust-toolchain.toml",
+            )
+        }
+    };
+    match Command::new("rustup")
+        .args(["run", &channel, "rustc", "--version"])
+        .output()
+    {
+        Ok(output) if output.status.success() => Check::pass(
+            NAME,
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ),
+        Ok(_) => Check::fail(
+            NAME,
+            format!("pinned toolchain {channel} is not installed"),
+            format!("rustup toolchain install {channel}"),
+        ),
+        Err(_) => Check::warn(
+            NAME,
+            format!("rustup not found, cannot verify pinned toolchain {channel}"),
+            "install rustup so the pinned toolchain is used",
+        ),
+    }
+}
+
+fn check_config(root: &Path) -> Check {
+    const NAME: &str = "xtask.toml";
+    let path = root.join(CONFIG_FILE_NAME);
+    if !path.exists() {
+        return Check::pass(NAME, "not present, using defaults");
+    }
+    match Config::load_from(&path) {
+        Ok(_) => Check::pass(NAME, path.display().to_string()),
+        Err(e) => Check::fail(NAME, format!("{e:#}"), "fix the reported key in xtask.toml"),
+    }
+}
+
+fn check_registry_token(config: &Config) -> Check {
+    const NAME: &str = "crates.io token";
+    let token = match credentials::token(CRATES_IO, config) {
+        Ok(Some(token)) => token,
+        Ok(None) => match std::env::var("CARGO_REGISTRY_TOKEN") {
+            Ok(token) => token,
+            Err(_) => return Check::warn(
+                NAME,
+                "CARGO_REGISTRY_TOKEN is not set",
+                "export CARGO_REGISTRY_TOKEN or set publish.credentials.crates-io in xtask.toml",
+            ),
+        },
+        Err(e) => {
+            return Check::fail(
+                NAME,
+                format!("{e:#}"),
+                "fix publish.credentials.crates-io in xtask.toml",
+            )
+        }
+    };
+    match http::get(
+        &config.network,
+        "https://crates.io/api/v1/me",
+        &[("Authorization", &token), ("User-Agent", "anza-xtask")],
+    ) {
+        Ok(_) => Check::pass(NAME, "token accepted by crates.io"),
+        Err(e) => Check::fail(
+            NAME,
+            format!("token rejected: {e}"),
+            "generate a new token with the publish-update scope at https://crates.io/settings/tokens",
+        ),
+    }
+}
+
+fn check_github_token(network: &crate::config::NetworkConfig) -> Check {
+    const NAME: &str = "GitHub token";
+    let Some(token) = ["GITHUB_TOKEN", "GH_TOKEN"]
+        .iter()
+        .find_map(|key| std::env::var(key).ok().filter(|v| !v.is_empty()))
+    else {
+        return Check::warn(
+            NAME,
+            "neither GITHUB_TOKEN nor GH_TOKEN is set",
+            "export GITHUB_TOKEN for release and PR automation",
+        );
+    };
+    let headers = match http::head(
+        network,
+        "https://api.github.com/user",
+        &[
+            ("Authorization", &format!("Bearer {token}")),
+            ("User-Agent", "anza-xtask"),
+        ],
+    ) {
+        Ok(headers) => headers,
+        Err(e) => {
+            return Check::fail(
+                NAME,
+                format!("token rejected: {e}"),
+                "generate a new token with the repo scope",
+            )
+        }
+    };
+    // fine-grained tokens don't report scopes, classic tokens must carry `repo`
+    match headers.iter().find(|(name, _)| name == "x-oauth-scopes") {
+        Some((_, scopes)) if !scopes.split(',').any(|s| s.trim() == "repo") => Check::fail(
+            NAME,
+            format!("token scopes are [{scopes}]"),
+            "add the repo scope to the token",
+        ),
+        Some((_, scopes)) => Check::pass(NAME, format!("token scopes are [{scopes}]")),
+        None => Check::pass(NAME, "fine-grained token accepted"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::fs};
+
+    #[test]
+    fn test_check_workspace_layout() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let root = root_dir.path();
+        assert_eq!(check_workspace_layout(root).status, CheckStatus::Fail);
+
+        fs::write(root.join("Cargo.toml"), "[package]\nname = \"foo\"\n").unwrap();
+        assert_eq!(check_workspace_layout(root).status, CheckStatus::Fail);
+
+        fs::write(root.join("Cargo.toml"), "[workspace]\nmembers = []\n").unwrap();
+        assert_eq!(check_workspace_layout(root).status, CheckStatus::Warn);
+
+        fs::write(
+            root.join("Cargo.toml"),
+            "[workspace]\nmembers = []\n\n[workspace.package]\nversion = \"1.0.0\"\n",
+        )
+        .unwrap();
+        assert_eq!(check_workspace_layout(root).status, CheckStatus::Pass);
+    }
+
+    #[test]
+    fn test_check_display() {
+        let check = Check::fail("docker", "not found", "install docker");
+        assert_eq!(
+            check.to_string(),
+            "❌ docker: not found\n     fix: install docker"
+        );
+        assert_eq!(Check::pass("git", "2.43").to_string(), "✅ git: 2.43");
+    }
+}
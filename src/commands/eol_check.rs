@@ -0,0 +1,278 @@
+use {
+    crate::{
+        config::Config,
+        utils::{
+            cargo::wildcard_match,
+            get_git_root_path,
+            git::git_in,
+            release_history::{ReleaseHistory, ReleaseRecord},
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    clap::Args,
+    log::info,
+    semver::Version,
+    serde::Serialize,
+    std::{path::Path, process::Command},
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(
+        long,
+        help = "Open a tracking issue for each branch due for EOL that has none"
+    )]
+    pub open_issues: bool,
+    #[arg(long, help = "Print the branches due for EOL as JSON")]
+    pub json: bool,
+}
+
+/// A release branch whose minor line fell out of the support window.
+#[derive(Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct EolBranch {
+    pub branch: String,
+    /// `major.minor` the branch maintains.
+    pub line: String,
+    /// Newest release cut from the line, if any was recorded.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_release: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_release_date: Option<String>,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    let repo = get_git_root_path()?;
+    let history = ReleaseHistory::load(&config.resolve(&config.release.history))?;
+    let branches: Vec<String> = remote_branches(&repo, &config.release.remote)?
+        .into_iter()
+        .filter(|branch| wildcard_match(&config.eol.branch_pattern, branch))
+        .collect();
+    let supported = supported_lines(&history, config.eol.supported_lines);
+    info!(
+        "🔎 supported lines: {}",
+        supported
+            .iter()
+            .map(|(major, minor)| format!("{major}.{minor}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    let due = due_for_eol(&branches, &history, config.eol.supported_lines);
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&due)?);
+    }
+    if due.is_empty() {
+        info!("✅ every release branch is within the support window");
+        return Ok(());
+    }
+    for branch in due.iter() {
+        let last = match (&branch.last_release, &branch.last_release_date) {
+            (Some(version), Some(date)) => format!(", last release v{version} on {date}"),
+            _ => String::new(),
+        };
+        info!("🪦 {} is due for EOL{last}", branch.branch);
+    }
+    if args.open_issues {
+        let open = open_issue_titles(&config)?;
+        for branch in due.iter() {
+            let title = issue_title(branch);
+            if open.contains(&title) {
+                info!("⏭️ {title} is already tracked");
+                continue;
+            }
+            open_issue(branch, &title, &config)?;
+            info!("📬 opened {title}");
+        }
+    }
+    Ok(())
+}
+
+/// Branch names on `remote`, without the `refs/heads/` prefix.
+pub fn remote_branches(repo: &Path, remote: &str) -> Result<Vec<String>> {
+    let listing = git_in(repo, &["ls-remote", "--heads", remote])?;
+    Ok(listing
+        .lines()
+        .filter_map(|line| line.split_once("refs/heads/"))
+        .map(|(_, branch)| branch.trim().to_string())
+        .collect())
+}
+
+/// The newest `count` `(major, minor)` lines with a recorded release,
+/// newest first.
+pub fn supported_lines(history: &ReleaseHistory, count: usize) -> Vec<(u64, u64)> {
+    let mut lines: Vec<(u64, u64)> = history
+        .releases
+        .iter()
+        .filter_map(|release| Version::parse(&release.version).ok())
+        .map(|version| (version.major, version.minor))
+        .collect();
+    lines.sort_unstable_by(|a, b| b.cmp(a));
+    lines.dedup();
+    lines.truncate(count);
+    lines
+}
+
+/// `(major, minor)` of a branch like `v2.1.x` or `release/2.1`.
+pub fn branch_line(branch: &str) -> Option<(u64, u64)> {
+    let start = branch.find(|c: char| c.is_ascii_digit())?;
+    let mut parts = branch[start..].split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next()?.parse().ok()?;
+    Some((major, minor))
+}
+
+/// Branches maintaining a line older than every supported one. Branches of
+/// lines newer than the last release, e.g. cut ahead of a `.0`, are kept.
+pub fn due_for_eol(
+    branches: &[String],
+    history: &ReleaseHistory,
+    supported: usize,
+) -> Vec<EolBranch> {
+    let lines = supported_lines(history, supported);
+    let Some(oldest) = lines.last().filter(|_| lines.len() == supported) else {
+        return vec![];
+    };
+    let mut due: Vec<(u64, u64, EolBranch)> = branches
+        .iter()
+        .filter_map(|branch| Some((branch, branch_line(branch)?)))
+        .filter(|(_, line)| line < oldest)
+        .map(|(branch, (major, minor))| {
+            let last = last_release(history, major, minor);
+            (
+                major,
+                minor,
+                EolBranch {
+                    branch: branch.clone(),
+                    line: format!("{major}.{minor}"),
+                    last_release: last.map(|release| release.version.clone()),
+                    last_release_date: last.map(|release| release.date.clone()),
+                },
+            )
+        })
+        .collect();
+    due.sort_by(|a, b| (a.0, a.1, &a.2.branch).cmp(&(b.0, b.1, &b.2.branch)));
+    due.into_iter().map(|(_, _, branch)| branch).collect()
+}
+
+fn last_release(history: &ReleaseHistory, major: u64, minor: u64) -> Option<&ReleaseRecord> {
+    history
+        .releases
+        .iter()
+        .filter_map(|release| Some((Version::parse(&release.version).ok()?, release)))
+        .filter(|(version, _)| version.major == major && version.minor == minor)
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, release)| release)
+}
+
+fn issue_title(branch: &EolBranch) -> String {
+    format!("EOL {} ({}.x)", branch.branch, branch.line)
+}
+
+fn open_issue_titles(config: &Config) -> Result<Vec<String>> {
+    let output = Command::new("gh")
+        .args(["issue", "list", "--state", "open", "--limit", "200"])
+        .args(["--search", "EOL in:title", "--json", "title"])
+        .args(["--repo", &config.packaging.repository])
+        .output()
+        .map_err(|e| anyhow!("failed to run gh: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "gh issue list failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let issues: Vec<serde_json::Value> =
+        serde_json::from_slice(&output.stdout).context("failed to parse gh issue list")?;
+    Ok(issues
+        .iter()
+        .filter_map(|issue| issue["title"].as_str().map(str::to_string))
+        .collect())
+}
+
+fn open_issue(branch: &EolBranch, title: &str, config: &Config) -> Result<()> {
+    let last = match (&branch.last_release, &branch.last_release_date) {
+        (Some(version), Some(date)) => format!(" Its last release was v{version} on {date}."),
+        _ => String::new(),
+    };
+    let body = format!(
+        "The {}.x line is older than the {} supported release line(s).{last}\n\nAnnounce the end of support, then archive `{}`.",
+        branch.line, config.eol.supported_lines, branch.branch
+    );
+    let mut cmd = Command::new("gh");
+    cmd.args(["issue", "create", "--title", title, "--body", &body])
+        .args(["--repo", &config.packaging.repository]);
+    if let Some(label) = &config.eol.issue_label {
+        cmd.args(["--label", label]);
+    }
+    let output = cmd.output().map_err(|e| anyhow!("failed to run gh: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "gh issue create failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn history(versions: &[(&str, &str)]) -> ReleaseHistory {
+        ReleaseHistory {
+            releases: versions
+                .iter()
+                .map(|(version, date)| ReleaseRecord {
+                    version: version.to_string(),
+                    date: date.to_string(),
+                    ..ReleaseRecord::default()
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_branch_line() {
+        assert_eq!(branch_line("v2.1.x"), Some((2, 1)));
+        assert_eq!(branch_line("release/1.18"), Some((1, 18)));
+        assert_eq!(branch_line("v2.x"), None);
+        assert_eq!(branch_line("master"), None);
+    }
+
+    #[test]
+    fn test_due_for_eol() {
+        let history = history(&[
+            ("2.2.0", "2025-03-01"),
+            ("2.1.3", "2025-02-20"),
+            ("2.0.9", "2025-02-01"),
+            ("2.0.10", "2025-02-15"),
+            ("1.18.26", "2024-10-01"),
+        ]);
+        let branches: Vec<String> = ["v1.18.x", "v2.0.x", "v2.1.x", "v2.2.x", "v2.3.x"]
+            .iter()
+            .map(|b| b.to_string())
+            .collect();
+        assert_eq!(supported_lines(&history, 2), vec![(2, 2), (2, 1)]);
+        assert_eq!(
+            due_for_eol(&branches, &history, 2),
+            vec![
+                EolBranch {
+                    branch: String::from("v1.18.x"),
+                    line: String::from("1.18"),
+                    last_release: Some(String::from("1.18.26")),
+                    last_release_date: Some(String::from("2024-10-01")),
+                },
+                EolBranch {
+                    branch: String::from("v2.0.x"),
+                    line: String::from("2.0"),
+                    last_release: Some(String::from("2.0.10")),
+                    last_release_date: Some(String::from("2025-02-15")),
+                },
+            ]
+        );
+        // Fewer released lines than the window covers nothing
+        assert_eq!(due_for_eol(&branches, &history, 5), vec![]);
+    }
+}
@@ -0,0 +1,202 @@
+use {
+    crate::utils::{cargo::read_manifest, scope::resolve_manifest_path},
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::MetadataCommand,
+    clap::{Args, Subcommand},
+    log::info,
+    serde::Serialize,
+    std::{fs, path::PathBuf},
+    toml_edit::DocumentMut,
+};
+
+#[derive(Subcommand)]
+pub enum FeaturesSubcommand {
+    #[command(about = "Extract every crate's features and their `##` doc comments")]
+    Doc {
+        #[arg(long, default_value = "features.json")]
+        out: PathBuf,
+        #[arg(
+            long,
+            help = "Only check every feature has a description, write nothing"
+        )]
+        check: bool,
+    },
+}
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+
+    #[command(subcommand)]
+    pub subcommand: FeaturesSubcommand,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CrateFeatures {
+    pub name: String,
+    pub version: String,
+    pub features: Vec<Feature>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Feature {
+    pub name: String,
+    /// Features and dependencies it turns on.
+    pub enables: Vec<String>,
+    /// The `##` comment lines above the feature, empty when undocumented.
+    pub doc: String,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let manifest_path = resolve_manifest_path(&args.manifest_path)?;
+    match args.subcommand {
+        FeaturesSubcommand::Doc { out, check } => {
+            let metadata = MetadataCommand::new()
+                .manifest_path(&manifest_path)
+                .no_deps()
+                .exec()
+                .context("failed to run cargo metadata")?;
+            let mut crates = vec![];
+            for package in metadata.workspace_packages() {
+                let doc = read_manifest(package.manifest_path.as_std_path())?;
+                crates.push(CrateFeatures {
+                    name: package.name.to_string(),
+                    version: package.version.to_string(),
+                    features: features(&doc),
+                });
+            }
+            crates.sort_by(|a, b| a.name.cmp(&b.name));
+
+            if check {
+                let undocumented = undocumented(&crates);
+                if !undocumented.is_empty() {
+                    return Err(anyhow!(
+                        "{} feature(s) have no `##` description:\n{}",
+                        undocumented.len(),
+                        undocumented
+                            .iter()
+                            .map(|f| format!("  - {f}"))
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    ));
+                }
+                info!("✅ every feature is documented");
+                return Ok(());
+            }
+            fs::write(
+                &out,
+                format!("{}\n", serde_json::to_string_pretty(&crates)?),
+            )
+            .context(format!("failed to write {}", out.display()))?;
+            info!(
+                "✅ wrote the features of {} crate(s) to {}",
+                crates.len(),
+                out.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+/// The `[features]` of a manifest in declaration order, documented with the
+/// `## ` comment lines right above each feature (the `document-features`
+/// convention, `#!` section comments are ignored).
+pub fn features(doc: &DocumentMut) -> Vec<Feature> {
+    let Some(table) = doc.get("features").and_then(|f| f.as_table()) else {
+        return vec![];
+    };
+    table
+        .iter()
+        .map(|(name, value)| {
+            let enables = value
+                .as_array()
+                .map(|array| {
+                    array
+                        .iter()
+                        .filter_map(|v| v.as_str().map(str::to_string))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let prefix = table
+                .key(name)
+                .and_then(|key| key.leaf_decor().prefix())
+                .and_then(|prefix| prefix.as_str())
+                .unwrap_or_default();
+            let doc = prefix
+                .lines()
+                .filter_map(|line| line.trim().strip_prefix("##"))
+                .map(str::trim)
+                .collect::<Vec<_>>()
+                .join("\n");
+            Feature {
+                name: name.to_string(),
+                enables,
+                doc,
+            }
+        })
+        .collect()
+}
+
+/// `crate/feature` for every feature without a description; `default` only
+/// lists other features and needs none.
+pub fn undocumented(crates: &[CrateFeatures]) -> Vec<String> {
+    crates
+        .iter()
+        .flat_map(|c| {
+            c.features
+                .iter()
+                .filter(|f| f.name != "default" && f.doc.is_empty())
+                .map(move |f| format!("{}/{}", c.name, f.name))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_features() {
+        let doc: DocumentMut = r#"[package]
+name = "foo"
+
+[features]
+default = ["std"]
+#! ### Runtime
+## Use the standard library.
+std = []
+## Serialize with serde,
+## including the account types.
+serde = ["dep:serde", "std"]
+# not a doc comment
+frozen-abi = []
+"#
+        .parse()
+        .unwrap();
+        let features = features(&doc);
+        assert_eq!(
+            features
+                .iter()
+                .map(|f| (f.name.as_str(), f.doc.as_str()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("default", ""),
+                ("std", "Use the standard library."),
+                (
+                    "serde",
+                    "Serialize with serde,\nincluding the account types."
+                ),
+                ("frozen-abi", ""),
+            ]
+        );
+        assert_eq!(features[2].enables, vec!["dep:serde", "std"]);
+
+        let crates = vec![CrateFeatures {
+            name: String::from("foo"),
+            version: String::from("1.0.0"),
+            features,
+        }];
+        assert_eq!(undocumented(&crates), vec!["foo/frozen-abi"]);
+    }
+}
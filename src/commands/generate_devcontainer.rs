@@ -0,0 +1,188 @@
+use {
+    crate::{
+        config::Config,
+        utils::{find_all_cargo_locks, get_git_root_path, get_toolchain_channel},
+    },
+    anyhow::{Context, Result},
+    clap::Args,
+    log::info,
+    std::{collections::BTreeSet, fs, path::PathBuf},
+    toml_edit::DocumentMut,
+};
+
+/// System packages needed to build well-known `-sys` crates (and their
+/// build-script helpers) on Debian.
+const SYSTEM_DEPENDENCIES: &[(&str, &[&str])] = &[
+    ("openssl-sys", &["libssl-dev", "pkg-config"]),
+    ("libudev-sys", &["libudev-dev", "pkg-config"]),
+    ("hidapi", &["libudev-dev", "pkg-config"]),
+    ("clang-sys", &["clang", "libclang-dev"]),
+    ("bindgen", &["clang", "libclang-dev"]),
+    ("librocksdb-sys", &["clang", "libclang-dev", "cmake"]),
+    ("cmake", &["cmake"]),
+    ("prost-build", &["protobuf-compiler"]),
+    ("protobuf-src", &["cmake"]),
+    ("tonic-build", &["protobuf-compiler"]),
+    ("libz-sys", &["zlib1g-dev", "pkg-config"]),
+    ("bzip2-sys", &["libbz2-dev"]),
+    ("lz4-sys", &["liblz4-dev"]),
+    ("zstd-sys", &["libzstd-dev", "pkg-config"]),
+];
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, default_value = ".devcontainer")]
+    pub output_dir: PathBuf,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let git_root = get_git_root_path()?;
+    let config = Config::load()?;
+
+    let channel = get_toolchain_channel(&git_root)?.unwrap_or_else(|| String::from("stable"));
+    let mut packages: BTreeSet<String> = detect_system_packages()?;
+    packages.extend(config.devcontainer.packages.iter().cloned());
+
+    let output_dir = git_root.join(&args.output_dir);
+    fs::create_dir_all(&output_dir)
+        .context(format!("failed to create {}", output_dir.display()))?;
+
+    let dockerfile = render_dockerfile(
+        config.devcontainer.base_image.as_deref(),
+        &channel,
+        &packages,
+    );
+    fs::write(output_dir.join("Dockerfile"), dockerfile)?;
+
+    let name = git_root
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| String::from("workspace"));
+    fs::write(
+        output_dir.join("devcontainer.json"),
+        render_devcontainer_json(&name)?,
+    )?;
+
+    info!(
+        "generated {} for toolchain {channel} with {} system package(s)",
+        output_dir.display(),
+        packages.len()
+    );
+    Ok(())
+}
+
+/// Maps the crates in every Cargo.lock to the system packages they need.
+fn detect_system_packages() -> Result<BTreeSet<String>> {
+    let mut crates = BTreeSet::new();
+    for cargo_lock in find_all_cargo_locks()? {
+        let content = fs::read_to_string(&cargo_lock)
+            .context(format!("failed to read {}", cargo_lock.display()))?;
+        let Ok(doc) = content.parse::<DocumentMut>() else {
+            continue;
+        };
+        let Some(packages) = doc.get("package").and_then(|p| p.as_array_of_tables()) else {
+            continue;
+        };
+        for package in packages.iter() {
+            if let Some(name) = package.get("name").and_then(|n| n.as_str()) {
+                crates.insert(name.to_string());
+            }
+        }
+    }
+    Ok(system_packages_for(&crates))
+}
+
+fn system_packages_for(crates: &BTreeSet<String>) -> BTreeSet<String> {
+    SYSTEM_DEPENDENCIES
+        .iter()
+        .filter(|(crate_name, _)| crates.contains(*crate_name))
+        .flat_map(|(_, packages)| packages.iter().map(|p| p.to_string()))
+        .collect()
+}
+
+fn render_dockerfile(
+    base_image: Option<&str>,
+    channel: &str,
+    packages: &BTreeSet<String>,
+) -> String {
+    let is_release = semver::Version::parse(channel).is_ok();
+    let base_image = match base_image {
+        Some(image) => image.to_string(),
+        // release channels have a matching official image, everything else
+        // (nightly-YYYY-MM-DD, beta) is installed through rustup
+        None if is_release => format!("rust:{channel}-bookworm"),
+        None => String::from("rust:bookworm"),
+    };
+
+    let mut dockerfile =
+        String::from("# Generated by `xtask generate-devcontainer`, do not edit.\n");
+    dockerfile.push_str(&format!("FROM {base_image}\n\n"));
+    if !packages.is_empty() {
+        dockerfile.push_str(
+            "RUN apt-get update \\\n    && apt-get install -y --no-install-recommends \\\n",
+        );
+        for package in packages {
+            dockerfile.push_str(&format!("        {package} \\\n"));
+        }
+        dockerfile.push_str("    && rm -rf /var/lib/apt/lists/*\n\n");
+    }
+    dockerfile.push_str(&format!(
+        "RUN rustup toolchain install {channel} --profile minimal --component clippy,rustfmt \\\n    && rustup default {channel}\n"
+    ));
+    dockerfile
+}
+
+fn render_devcontainer_json(name: &str) -> Result<String> {
+    let devcontainer = serde_json::json!({
+        "name": format!("{name} dev"),
+        "build": { "dockerfile": "Dockerfile" },
+        "customizations": {
+            "vscode": { "extensions": ["rust-lang.rust-analyzer"] }
+        }
+    });
+    Ok(format!(
+        "{}\n",
+        serde_json::to_string_pretty(&devcontainer)?
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_system_packages_for() {
+        let crates: BTreeSet<String> = ["openssl-sys", "serde", "prost-build"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert_eq!(
+            system_packages_for(&crates).into_iter().collect::<Vec<_>>(),
+            ["libssl-dev", "pkg-config", "protobuf-compiler"]
+        );
+    }
+
+    #[test]
+    fn test_render_dockerfile() {
+        let packages: BTreeSet<String> = [String::from("libudev-dev")].into_iter().collect();
+        assert_eq!(
+            render_dockerfile(None, "1.86.0", &packages),
+            r#"# Generated by `xtask generate-devcontainer`, do not edit.
+FROM rust:1.86.0-bookworm
+
+RUN apt-get update \
+    && apt-get install -y --no-install-recommends \
+        libudev-dev \
+    && rm -rf /var/lib/apt/lists/*
+
+RUN rustup toolchain install 1.86.0 --profile minimal --component clippy,rustfmt \
+    && rustup default 1.86.0
+"#
+        );
+        assert!(
+            render_dockerfile(None, "nightly-2025-01-01", &BTreeSet::new()).starts_with(
+                "# Generated by `xtask generate-devcontainer`, do not edit.\nFROM rust:bookworm\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,282 @@
+use {
+    crate::{
+        config::{AdvisoryIgnore, Config, PolicyConfig},
+        utils::time::{format_rfc3339, unix_now, SECONDS_PER_DAY},
+    },
+    anyhow::{anyhow, Context, Result},
+    clap::Args,
+    log::{info, warn},
+    std::{fs, path::Path},
+    toml_edit::{value, Array, DocumentMut, InlineTable, Item, Table, Value},
+};
+
+/// Starting point when the repo has no deny.toml yet.
+const BASE_DENY: &str = r#"[graph]
+all-features = true
+
+[advisories]
+version = 2
+
+[licenses]
+version = 2
+
+[bans]
+multiple-versions = "warn"
+wildcards = "deny"
+"#;
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(
+        long,
+        help = "Fail if deny.toml or audit.toml differ from the policy, leaving them untouched"
+    )]
+    pub check: bool,
+}
+
+impl CommandArgs {
+    pub fn is_mutating(&self) -> bool {
+        !self.check
+    }
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    let policy = &config.policy;
+    if policy.licenses.is_empty() && policy.banned.is_empty() && policy.advisory_ignores.is_empty()
+    {
+        info!("no policy configured under policy in xtask.toml");
+        return Ok(());
+    }
+    let now = unix_now();
+    let (ignores, expired) = active_ignores(&policy.advisory_ignores, now - now % SECONDS_PER_DAY);
+    for ignore in expired.iter() {
+        warn!(
+            "⏰ ignore of {} expired on {}, the advisory is enforced again",
+            ignore.id,
+            &format_rfc3339(ignore.expires)[..10]
+        );
+    }
+
+    let deny_path = config.resolve(&policy.deny);
+    let mut files = vec![(
+        deny_path.clone(),
+        render_deny(read_optional(&deny_path)?.as_deref(), policy, &ignores)?,
+    )];
+    if !policy.advisory_ignores.is_empty() {
+        let audit_path = config.resolve(&policy.audit);
+        let audit = render_audit(read_optional(&audit_path)?.as_deref(), &ignores)?;
+        files.push((audit_path, audit));
+    }
+
+    let mut stale = vec![];
+    for (path, content) in files.iter() {
+        if read_optional(path)?.as_deref() == Some(content) {
+            continue;
+        }
+        if args.check {
+            stale.push(path.display().to_string());
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context(format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(path, content).context(format!("failed to write {}", path.display()))?;
+        info!("📝 updated {}", path.display());
+    }
+    if !stale.is_empty() {
+        return Err(anyhow!(
+            "{} out of sync with the policy in xtask.toml, run `xtask generate-policy` and commit the result",
+            stale.join(", ")
+        ));
+    }
+    if args.check {
+        info!("✅ dependency policy is in sync");
+    }
+    Ok(())
+}
+
+fn read_optional(path: &Path) -> Result<Option<String>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    fs::read_to_string(path)
+        .map(Some)
+        .context(format!("failed to read {}", path.display()))
+}
+
+/// Splits the ignores into those still in force on the day starting at
+/// `today` and those past their expiry.
+fn active_ignores(
+    ignores: &[AdvisoryIgnore],
+    today: u64,
+) -> (Vec<&AdvisoryIgnore>, Vec<&AdvisoryIgnore>) {
+    ignores.iter().partition(|ignore| ignore.expires >= today)
+}
+
+/// Sets the keys of the configured parts of the policy in `current` (or a
+/// fresh deny.toml), keeping everything else as it is.
+fn render_deny(
+    current: Option<&str>,
+    policy: &PolicyConfig,
+    ignores: &[&AdvisoryIgnore],
+) -> Result<String> {
+    let mut doc: DocumentMut = current
+        .unwrap_or(BASE_DENY)
+        .parse()
+        .context("failed to parse deny.toml")?;
+
+    if !policy.licenses.is_empty() {
+        table(&mut doc, "licenses")?.insert(
+            "allow",
+            value(multiline(policy.licenses.iter().map(Value::from))),
+        );
+    }
+    if !policy.banned.is_empty() {
+        table(&mut doc, "bans")?.insert(
+            "deny",
+            value(multiline(policy.banned.iter().map(|banned| {
+                let spec = match &banned.version {
+                    Some(version) => format!("{}@{version}", banned.name),
+                    None => banned.name.clone(),
+                };
+                let mut entry = InlineTable::new();
+                entry.insert("crate", spec.into());
+                if let Some(reason) = &banned.reason {
+                    entry.insert("reason", reason.into());
+                }
+                Value::from(entry)
+            }))),
+        );
+    }
+    // kept when every ignore expired, so the expired ones are dropped
+    if !policy.advisory_ignores.is_empty() {
+        table(&mut doc, "advisories")?.insert(
+            "ignore",
+            value(multiline(ignores.iter().map(|ignore| {
+                let mut entry = InlineTable::new();
+                entry.insert("id", ignore.id.as_str().into());
+                entry.insert("reason", ignore.reason.as_str().into());
+                Value::from(entry)
+            }))),
+        );
+    }
+    Ok(doc.to_string())
+}
+
+/// cargo-audit only knows the advisory ids, without reasons.
+fn render_audit(current: Option<&str>, ignores: &[&AdvisoryIgnore]) -> Result<String> {
+    let mut doc: DocumentMut = current
+        .unwrap_or_default()
+        .parse()
+        .context("failed to parse audit.toml")?;
+    table(&mut doc, "advisories")?.insert(
+        "ignore",
+        value(multiline(
+            ignores.iter().map(|ignore| Value::from(ignore.id.as_str())),
+        )),
+    );
+    Ok(doc.to_string())
+}
+
+fn table<'a>(doc: &'a mut DocumentMut, name: &str) -> Result<&'a mut Table> {
+    doc.entry(name)
+        .or_insert(Item::Table(Table::new()))
+        .as_table_mut()
+        .ok_or_else(|| anyhow!("`{name}` is not a table"))
+}
+
+/// One element per line, the way deny.toml is usually written.
+fn multiline(values: impl Iterator<Item = Value>) -> Array {
+    let mut array = Array::new();
+    for mut value in values {
+        value.decor_mut().set_prefix("\n    ");
+        array.push_formatted(value);
+    }
+    if !array.is_empty() {
+        array.set_trailing("\n");
+        array.set_trailing_comma(true);
+    }
+    array
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{config::BannedCrate, utils::time::parse_rfc3339},
+        pretty_assertions::assert_eq,
+    };
+
+    fn ignore(id: &str, expires: &str) -> AdvisoryIgnore {
+        AdvisoryIgnore {
+            id: id.to_string(),
+            reason: String::from("no fix upstream"),
+            expires: parse_rfc3339(expires).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_active_ignores() {
+        let ignores = [
+            ignore("RUSTSEC-2024-0001", "2026-01-31"),
+            ignore("RUSTSEC-2024-0002", "2026-02-01"),
+        ];
+        let (active, expired) = active_ignores(&ignores, parse_rfc3339("2026-02-01").unwrap());
+        assert_eq!(active, [&ignores[1]]);
+        assert_eq!(expired, [&ignores[0]]);
+    }
+
+    #[test]
+    fn test_render() {
+        let policy = PolicyConfig {
+            licenses: vec![String::from("Apache-2.0"), String::from("MIT")],
+            banned: vec![BannedCrate {
+                name: String::from("openssl"),
+                version: Some(String::from("<0.10")),
+                reason: Some(String::from("use rustls")),
+            }],
+            advisory_ignores: vec![ignore("RUSTSEC-2024-0001", "2026-01-31")],
+            ..PolicyConfig::default()
+        };
+        let ignores: Vec<&AdvisoryIgnore> = policy.advisory_ignores.iter().collect();
+        let current =
+            "[licenses]\nversion = 2\nallow = [\"GPL-3.0\"]\n\n[sources]\nunknown-git = \"deny\"\n";
+        assert_eq!(
+            render_deny(Some(current), &policy, &ignores).unwrap(),
+            r#"[licenses]
+version = 2
+allow = [
+    "Apache-2.0",
+    "MIT",
+]
+
+[sources]
+unknown-git = "deny"
+
+[bans]
+deny = [
+    { crate = "openssl@<0.10", reason = "use rustls" },
+]
+
+[advisories]
+ignore = [
+    { id = "RUSTSEC-2024-0001", reason = "no fix upstream" },
+]
+"#
+        );
+        // only the configured parts of the policy are written
+        let licenses_only = PolicyConfig {
+            licenses: vec![String::from("MIT")],
+            ..PolicyConfig::default()
+        };
+        assert_eq!(
+            render_deny(Some("[bans]\ndeny = [\"openssl\"]\n"), &licenses_only, &[]).unwrap(),
+            "[bans]\ndeny = [\"openssl\"]\n\n[licenses]\nallow = [\n    \"MIT\",\n]\n"
+        );
+        assert_eq!(
+            render_audit(None, &ignores).unwrap(),
+            "[advisories]\nignore = [\n    \"RUSTSEC-2024-0001\",\n]\n"
+        );
+    }
+}
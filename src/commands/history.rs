@@ -0,0 +1,111 @@
+use {
+    crate::{
+        config::Config,
+        utils::{
+            release_history::{ReleaseHistory, ReleaseRecord},
+            time::parse_date,
+        },
+    },
+    anyhow::{anyhow, Result},
+    clap::{Args, Subcommand},
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[command(subcommand)]
+    pub subcommand: HistorySubcommand,
+}
+
+#[derive(Subcommand)]
+pub enum HistorySubcommand {
+    #[command(about = "Show recorded releases, newest first")]
+    Show {
+        #[arg(help = "Release or release line, e.g. 2.1 for every 2.1.x")]
+        version: Option<String>,
+        #[arg(
+            long,
+            value_parser = parse_since,
+            help = "Only releases cut on or after this YYYY-MM-DD date"
+        )]
+        since: Option<String>,
+        #[arg(long, help = "Print the releases as JSON")]
+        json: bool,
+    },
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    let history = ReleaseHistory::load(&config.resolve(&config.release.history))?;
+    match args.subcommand {
+        HistorySubcommand::Show {
+            version,
+            since,
+            json,
+        } => {
+            let releases = history.query(version.as_deref(), since.as_deref());
+            if json {
+                println!("{}", serde_json::to_string_pretty(&releases)?);
+                return Ok(());
+            }
+            if releases.is_empty() {
+                return Err(anyhow!(
+                    "no recorded release matches, see {}",
+                    config.release.history.display()
+                ));
+            }
+            print!("{}", render(&releases));
+        }
+    }
+    Ok(())
+}
+
+/// `--since` is compared to the recorded dates as a string, so it has to be
+/// a well-formed date.
+fn parse_since(value: &str) -> Result<String> {
+    parse_date(value)?;
+    Ok(value.to_string())
+}
+
+pub fn render(releases: &[&ReleaseRecord]) -> String {
+    let mut text = String::new();
+    for release in releases {
+        let commit = &release.git_commit[..release.git_commit.len().min(12)];
+        let publish = release
+            .publish_duration_secs
+            .map(|secs| format!("  published in {}m{:02}s", secs / 60, secs % 60))
+            .unwrap_or_default();
+        text.push_str(&format!(
+            "v{:<12} {}  {commit}{publish}\n",
+            release.version, release.date
+        ));
+        for (artifact, digest) in release.artifacts.iter() {
+            text.push_str(&format!("  {artifact}  {digest}\n"));
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::collections::BTreeMap};
+
+    #[test]
+    fn test_render() {
+        let release = ReleaseRecord {
+            version: String::from("2.1.0"),
+            date: String::from("2025-01-10"),
+            git_commit: String::from("0fbec9e1f2a3b4c5d6e7"),
+            publish_duration_secs: Some(754),
+            artifacts: BTreeMap::from([(
+                String::from("solana-release-x86_64-unknown-linux-gnu.tar.bz2"),
+                String::from("e3b0c442"),
+            )]),
+        };
+        assert_eq!(
+            render(&[&release]),
+            "v2.1.0        2025-01-10  0fbec9e1f2a3  published in 12m34s
+  solana-release-x86_64-unknown-linux-gnu.tar.bz2  e3b0c442
+"
+        );
+    }
+}
@@ -0,0 +1,153 @@
+use {
+    crate::utils::{
+        discovery::Discovery, get_git_root_path, get_last_tag, is_dirty, recursive_find_files,
+    },
+    anyhow::{Context, Result},
+    cargo_metadata::MetadataCommand,
+    clap::Args,
+    log::debug,
+    serde::Serialize,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+    toml_edit::DocumentMut,
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, help = "Print the summary as JSON")]
+    pub json: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WorkspaceInfo {
+    pub root: PathBuf,
+    pub version: Option<String>,
+    pub crates: usize,
+    pub publishable: Vec<String>,
+    pub private: Vec<String>,
+    pub nested_workspaces: Vec<PathBuf>,
+    pub dirty: bool,
+    pub last_release_tag: Option<String>,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let info = collect_info(&get_git_root_path()?)?;
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+        return Ok(());
+    }
+
+    println!("📁 Workspace root: {}", info.root.display());
+    println!(
+        "🏷️  Current version: {}",
+        info.version.as_deref().unwrap_or("(not set)")
+    );
+    println!(
+        "📦 Crates: {} ({} publishable, {} private)",
+        info.crates,
+        info.publishable.len(),
+        info.private.len()
+    );
+    println!("🪆 Nested workspaces: {}", info.nested_workspaces.len());
+    for nested in info.nested_workspaces.iter() {
+        println!("  {}", nested.display());
+    }
+    println!(
+        "🌿 Working tree: {}",
+        if info.dirty { "dirty" } else { "clean" }
+    );
+    println!(
+        "🔖 Last release tag: {}",
+        info.last_release_tag.as_deref().unwrap_or("(none)")
+    );
+    Ok(())
+}
+
+pub fn collect_info(root: &Path) -> Result<WorkspaceInfo> {
+    let root_manifest = root.join("Cargo.toml");
+    let content = fs::read_to_string(&root_manifest)
+        .context(format!("failed to read {}", root_manifest.display()))?;
+    let doc = content
+        .parse::<DocumentMut>()
+        .context(format!("failed to parse {}", root_manifest.display()))?;
+    let version = doc
+        .get("workspace")
+        .and_then(|workspace| workspace.get("package"))
+        .and_then(|package| package.get("version"))
+        .and_then(|version| version.as_str())
+        .map(str::to_string);
+
+    let metadata = MetadataCommand::new()
+        .manifest_path(&root_manifest)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+    let mut publishable = vec![];
+    let mut private = vec![];
+    for package in metadata.workspace_packages() {
+        match &package.publish {
+            Some(registries) if registries.is_empty() => private.push(package.name.to_string()),
+            _ => publishable.push(package.name.to_string()),
+        }
+    }
+    publishable.sort();
+    private.sort();
+
+    Ok(WorkspaceInfo {
+        root: root.to_path_buf(),
+        version,
+        crates: publishable.len().saturating_add(private.len()),
+        publishable,
+        private,
+        nested_workspaces: find_nested_workspaces(root)?,
+        dirty: is_dirty(root)?,
+        last_release_tag: get_last_tag(root)?,
+    })
+}
+
+/// Finds manifests below `root` that declare their own `[workspace]`.
+pub fn find_nested_workspaces(root: &Path) -> Result<Vec<PathBuf>> {
+    let root_manifest = root.join("Cargo.toml");
+    let mut nested = vec![];
+    for manifest in recursive_find_files(root, "Cargo.toml", &Discovery::for_tree(root)?, |_| true)?
+    {
+        if manifest == root_manifest {
+            continue;
+        }
+        let Ok(doc) = fs::read_to_string(&manifest)?.parse::<DocumentMut>() else {
+            debug!("skipping unparsable {}", manifest.display());
+            continue;
+        };
+        if doc.contains_key("workspace") {
+            let dir = manifest.parent().unwrap_or(root);
+            nested.push(dir.strip_prefix(root).unwrap_or(dir).to_path_buf());
+        }
+    }
+    nested.sort();
+    Ok(nested)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_find_nested_workspaces() {
+        let root = fs::canonicalize("tests/dummy-workspace").unwrap();
+        let nested = find_nested_workspaces(&root).unwrap();
+        assert_eq!(nested, vec![PathBuf::from("d"), PathBuf::from("sub")]);
+    }
+
+    #[test]
+    fn test_collect_info() {
+        let root = fs::canonicalize("tests/dummy-workspace-publish-excluded").unwrap();
+        let info = collect_info(&root).unwrap();
+        assert_eq!(info.crates, 2);
+        assert_eq!(info.publishable, vec!["publishable"]);
+        assert_eq!(info.private, vec!["excluded"]);
+        assert!(info.nested_workspaces.is_empty());
+    }
+}
@@ -0,0 +1,260 @@
+use {
+    anyhow::{anyhow, Context, Result},
+    clap::{Args, ValueEnum},
+    log::info,
+    std::{fs, path::PathBuf, process::Command},
+};
+
+const ANZA_CARGO_TOML: &str = r#"[workspace]
+resolver = "2"
+members = []
+# the xtask crate keeps its own lockfile and workspace
+exclude = ["ci/xtask"]
+
+[workspace.package]
+version = "0.1.0"
+authors = ["Anza Maintainers <maintainers@anza.xyz>"]
+repository = "https://github.com/anza-xyz/{{name}}"
+homepage = "https://anza.xyz/"
+license = "Apache-2.0"
+edition = "2021"
+
+[workspace.lints.rust]
+unexpected_cfgs = { level = "warn", check-cfg = ['cfg(feature, values("frozen-abi"))'] }
+
+[workspace.lints.clippy]
+arithmetic_side_effects = "deny"
+default_trait_access = "deny"
+manual_let_else = "deny"
+used_underscore_binding = "deny"
+
+[workspace.dependencies]
+"#;
+
+const ANZA_RUST_TOOLCHAIN: &str = r#"[toolchain]
+channel = "{{toolchain}}"
+components = ["clippy", "rustfmt"]
+"#;
+
+// only stable options, the pinned toolchain ships a stable rustfmt
+const ANZA_RUSTFMT: &str = r#"edition = "2021"
+use_field_init_shorthand = true
+use_try_shorthand = true
+"#;
+
+const ANZA_DENY: &str = r#"[graph]
+all-features = true
+
+[advisories]
+version = 2
+
+[licenses]
+version = 2
+allow = [
+    "Apache-2.0",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "ISC",
+    "MIT",
+    "Unicode-3.0",
+    "Zlib",
+]
+
+[bans]
+multiple-versions = "warn"
+wildcards = "deny"
+
+[sources]
+unknown-registry = "deny"
+unknown-git = "deny"
+"#;
+
+/// Where the alias expects the xtask crate, with its own lockfile.
+const XTASK_MANIFEST: &str = "ci/xtask/Cargo.toml";
+
+const ANZA_CARGO_CONFIG: &str = r#"[alias]
+xtask = "run --quiet --locked --manifest-path ci/xtask/Cargo.toml --"
+"#;
+
+const ANZA_XTASK_TOML: &str = r#"# Configuration of `cargo xtask`.
+
+[lint]
+fixtures = []
+"#;
+
+const ANZA_GITIGNORE: &str = "/target\n/ci/xtask/target\n/.xtask/\n";
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum Template {
+    #[value(help = "Anza monorepo: workspace lints, pinned toolchain, cargo-deny and xtask")]
+    AnzaDefault,
+}
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, value_enum)]
+    pub template: Template,
+
+    #[arg(
+        long,
+        default_value = ".",
+        help = "Directory to create the workspace in"
+    )]
+    pub dir: PathBuf,
+
+    #[arg(long, help = "Repository name [default: the directory name]")]
+    pub name: Option<String>,
+
+    #[arg(long, help = "Toolchain to pin [default: the installed rustc version]")]
+    pub toolchain: Option<String>,
+
+    #[arg(long, help = "Overwrite files that already exist")]
+    pub force: bool,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    fs::create_dir_all(&args.dir).context(format!("failed to create {}", args.dir.display()))?;
+    let dir = args
+        .dir
+        .canonicalize()
+        .context(format!("failed to resolve {}", args.dir.display()))?;
+    let name = match args.name {
+        Some(name) => name,
+        None => dir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .ok_or_else(|| anyhow!("cannot name a workspace at {}, pass --name", dir.display()))?,
+    };
+    let toolchain = match args.toolchain {
+        Some(toolchain) => toolchain,
+        None => installed_rustc_version()?,
+    };
+
+    let has_xtask = dir.join(XTASK_MANIFEST).exists();
+    let files = render(args.template, &name, &toolchain, has_xtask);
+    if !args.force {
+        let existing: Vec<String> = files
+            .iter()
+            .filter(|(path, _)| dir.join(path).exists())
+            .map(|(path, _)| path.to_string())
+            .collect();
+        if !existing.is_empty() {
+            return Err(anyhow!(
+                "{} already has {}, pass --force to overwrite",
+                dir.display(),
+                existing.join(", ")
+            ));
+        }
+    }
+    for (path, content) in files.iter() {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context(format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(&path, content).context(format!("failed to write {}", path.display()))?;
+        info!("  wrote {}", path.display());
+    }
+    if has_xtask {
+        info!("✅ {name} initialized on {toolchain}");
+    } else {
+        info!(
+            "✅ {name} initialized on {toolchain}, add the xtask crate under ci/xtask and rerun with --force to get the `cargo xtask` alias"
+        );
+    }
+    Ok(())
+}
+
+/// Files of `template`, relative to the workspace root. The `cargo xtask`
+/// alias is only written when `has_xtask`, it would fail without the crate.
+pub fn render(
+    template: Template,
+    name: &str,
+    toolchain: &str,
+    has_xtask: bool,
+) -> Vec<(&'static str, String)> {
+    let fill = |content: &str| {
+        content
+            .replace("{{name}}", name)
+            .replace("{{toolchain}}", toolchain)
+    };
+    match template {
+        Template::AnzaDefault => {
+            let mut files = vec![
+                ("Cargo.toml", fill(ANZA_CARGO_TOML)),
+                ("rust-toolchain.toml", fill(ANZA_RUST_TOOLCHAIN)),
+                ("rustfmt.toml", fill(ANZA_RUSTFMT)),
+                ("deny.toml", fill(ANZA_DENY)),
+                ("xtask.toml", fill(ANZA_XTASK_TOML)),
+                (".gitignore", fill(ANZA_GITIGNORE)),
+            ];
+            if has_xtask {
+                files.push((".cargo/config.toml", fill(ANZA_CARGO_CONFIG)));
+            }
+            files
+        }
+    }
+}
+
+fn installed_rustc_version() -> Result<String> {
+    let output = Command::new("rustc")
+        .arg("--version")
+        .output()
+        .map_err(|e| anyhow!("failed to run rustc: {e}"))?;
+    // rustc 1.86.0 (05f9846f8 2025-03-31)
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .nth(1)
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("unexpected rustc --version output, pass --toolchain"))
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::{config::Config, utils::get_toolchain_channel},
+        pretty_assertions::assert_eq,
+        toml_edit::DocumentMut,
+    };
+
+    #[test]
+    fn test_render_anza_default() {
+        let dir = tempfile::tempdir().unwrap();
+        for (path, content) in render(Template::AnzaDefault, "foo", "1.86.0", true) {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let manifest: DocumentMut = fs::read_to_string(dir.path().join("Cargo.toml"))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(
+            manifest["workspace"]["package"]["repository"].as_str(),
+            Some("https://github.com/anza-xyz/foo")
+        );
+        assert!(manifest["workspace"]["lints"]["clippy"].is_table());
+        assert_eq!(
+            get_toolchain_channel(dir.path()).unwrap().as_deref(),
+            Some("1.86.0")
+        );
+        Config::load_from(&dir.path().join("xtask.toml")).unwrap();
+        for path in ["deny.toml", "rustfmt.toml", ".cargo/config.toml"] {
+            fs::read_to_string(dir.path().join(path))
+                .unwrap()
+                .parse::<DocumentMut>()
+                .unwrap();
+        }
+        assert!(fs::read_to_string(dir.path().join(".gitignore"))
+            .unwrap()
+            .lines()
+            .any(|line| line == "/.xtask/"));
+
+        let paths: Vec<_> = render(Template::AnzaDefault, "foo", "1.86.0", false)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        assert!(!paths.contains(&".cargo/config.toml"));
+    }
+}
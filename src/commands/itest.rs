@@ -0,0 +1,249 @@
+use {
+    crate::{
+        config::{Config, ItestConfig, NetworkConfig},
+        utils::{get_git_root_path, http, DockerRun},
+    },
+    anyhow::{anyhow, Context, Result},
+    clap::Args,
+    log::{info, warn},
+    scopeguard::guard,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        process::{Child, Command, Stdio},
+        thread,
+        time::{Duration, Instant},
+    },
+};
+
+const HEALTH_REQUEST: &str = r#"{"jsonrpc":"2.0","id":1,"method":"getHealth"}"#;
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, help = "Validator image, overrides itest.image")]
+    pub image: Option<String>,
+    #[arg(long, help = "Keep the validator running after the tests")]
+    pub keep: bool,
+    #[arg(last = true, help = "Extra arguments passed to cargo test")]
+    pub test_args: Vec<String>,
+}
+
+enum Validator {
+    Local { child: Child, log: PathBuf },
+    Docker { container: String },
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let git_root = get_git_root_path()?;
+    let config = Config::load()?;
+    let itest = ItestConfig {
+        image: args.image.clone().or(config.itest.image.clone()),
+        ..config.itest.clone()
+    };
+    // kept out of a tempdir so `--keep` leaves a usable ledger behind
+    let scratch = git_root.join("target/itest");
+    fs::create_dir_all(&scratch).context(format!("failed to create {}", scratch.display()))?;
+
+    info!("🚀 starting test validator");
+    let keep = args.keep;
+    let validator = guard(start_validator(&itest, &scratch)?, |mut validator| {
+        if !keep {
+            info!("🧹 Cleanup: stopping test validator");
+            stop_validator(&mut validator);
+        }
+    });
+
+    let rpc_url = format!("http://127.0.0.1:{}", itest.rpc_port);
+    let result = wait_for_health(&rpc_url, Duration::from_secs(itest.startup_timeout_secs))
+        .and_then(|_| {
+            info!("🧪 running integration tests against {rpc_url}");
+            run_tests(&git_root, &itest, &args.test_args)
+        });
+    if let Err(e) = result {
+        let log_dir = config.resolve(&itest.log_dir);
+        match collect_logs(&validator, &log_dir) {
+            Ok(path) => warn!("validator logs saved to {}", path.display()),
+            Err(log_err) => warn!("failed to collect validator logs: {log_err:#}"),
+        }
+        return Err(e);
+    }
+    info!("✅ integration tests passed");
+    Ok(())
+}
+
+fn start_validator(itest: &ItestConfig, scratch: &Path) -> Result<Validator> {
+    let rpc_port = itest.rpc_port;
+    match &itest.image {
+        Some(image) => {
+            let mut cmd = validator_docker_run(image, rpc_port).to_command();
+            let output = cmd
+                .output()
+                .map_err(|e| anyhow!("failed to run docker: {e}"))?;
+            if !output.status.success() {
+                return Err(anyhow!(
+                    "failed to start {image}: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+            Ok(Validator::Docker {
+                container: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+            })
+        }
+        None => {
+            let log = scratch.join("validator.log");
+            let log_file =
+                fs::File::create(&log).context(format!("failed to create {}", log.display()))?;
+            let child = Command::new("solana-test-validator")
+                .arg("--reset")
+                .arg("--ledger")
+                .arg(scratch.join("ledger"))
+                .args(["--rpc-port", &rpc_port.to_string()])
+                .stdout(Stdio::from(log_file.try_clone()?))
+                .stderr(Stdio::from(log_file))
+                .spawn()
+                .map_err(|e| anyhow!("failed to start solana-test-validator: {e}"))?;
+            Ok(Validator::Local { child, log })
+        }
+    }
+}
+
+pub fn validator_docker_run(image: &str, rpc_port: u16) -> DockerRun {
+    // the websocket port is always rpc + 1
+    let ws_port = rpc_port.saturating_add(1);
+    DockerRun {
+        extra_args: vec![
+            String::from("--detach"),
+            String::from("--publish"),
+            format!("{rpc_port}:8899"),
+            String::from("--publish"),
+            format!("{ws_port}:8900"),
+        ],
+        ..DockerRun::new(image)
+    }
+}
+
+fn stop_validator(validator: &mut Validator) {
+    match validator {
+        Validator::Local { child, .. } => {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        Validator::Docker { container } => {
+            let _ = Command::new("docker")
+                .args(["stop", container.as_str()])
+                .output();
+        }
+    }
+}
+
+fn wait_for_health(rpc_url: &str, timeout: Duration) -> Result<()> {
+    // the validator is local, never go through a proxy
+    let network = NetworkConfig {
+        no_proxy: Some(String::from("*")),
+        ..NetworkConfig::default()
+    };
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Ok(body) = http::post_json(&network, rpc_url, HEALTH_REQUEST) {
+            if String::from_utf8_lossy(&body).contains(r#""result":"ok""#) {
+                return Ok(());
+            }
+        }
+        if Instant::now() >= deadline {
+            return Err(anyhow!(
+                "validator at {rpc_url} not healthy after {}s",
+                timeout.as_secs()
+            ));
+        }
+        thread::sleep(Duration::from_millis(500));
+    }
+}
+
+fn run_tests(git_root: &Path, itest: &ItestConfig, test_args: &[String]) -> Result<()> {
+    let status = Command::new("cargo")
+        .current_dir(git_root)
+        .args(["test", "--workspace", "--test", "*"])
+        .args(test_args)
+        .envs(test_env(itest.rpc_port))
+        .status()
+        .map_err(|e| anyhow!("failed to run cargo test: {e}"))?;
+    if !status.success() {
+        return Err(anyhow!("integration tests failed"));
+    }
+    Ok(())
+}
+
+pub fn test_env(rpc_port: u16) -> Vec<(String, String)> {
+    vec![
+        (
+            String::from("SOLANA_RPC_URL"),
+            format!("http://127.0.0.1:{rpc_port}"),
+        ),
+        (
+            String::from("SOLANA_WEBSOCKET_URL"),
+            format!("ws://127.0.0.1:{}", rpc_port.saturating_add(1)),
+        ),
+    ]
+}
+
+/// Copies the validator log into `log_dir`, where CI picks it up as an artifact.
+fn collect_logs(validator: &Validator, log_dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(log_dir).context(format!("failed to create {}", log_dir.display()))?;
+    let destination = log_dir.join("validator.log");
+    match validator {
+        Validator::Local { log, .. } => {
+            fs::copy(log, &destination).context(format!("failed to copy {}", log.display()))?;
+        }
+        Validator::Docker { container } => {
+            let output = Command::new("docker")
+                .args(["logs", container.as_str()])
+                .output()
+                .map_err(|e| anyhow!("failed to run docker logs: {e}"))?;
+            let mut logs = output.stdout;
+            logs.extend(output.stderr);
+            fs::write(&destination, logs)?;
+        }
+    }
+    Ok(destination)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_validator_docker_run() {
+        let cmd = validator_docker_run("anzaxyz/agave:v2.2.0", 18899).to_command();
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy()).collect();
+        assert_eq!(
+            args,
+            [
+                "run",
+                "--rm",
+                "--detach",
+                "--publish",
+                "18899:8899",
+                "--publish",
+                "18900:8900",
+                "anzaxyz/agave:v2.2.0"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_test_env() {
+        assert_eq!(
+            test_env(8899),
+            vec![
+                (
+                    String::from("SOLANA_RPC_URL"),
+                    String::from("http://127.0.0.1:8899")
+                ),
+                (
+                    String::from("SOLANA_WEBSOCKET_URL"),
+                    String::from("ws://127.0.0.1:8900")
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,501 @@
+use {
+    crate::utils::get_scope_root,
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::{DependencyKind, Metadata, MetadataCommand, Package},
+    clap::{Args, Subcommand},
+    log::info,
+    serde::Serialize,
+    std::collections::{BTreeMap, BTreeSet},
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[command(subcommand)]
+    pub subcommand: LicensesSubcommand,
+}
+
+#[derive(Subcommand)]
+pub enum LicensesSubcommand {
+    #[command(about = "Classify dependency licenses per shipped artifact")]
+    Matrix {
+        #[arg(long, help = "Print the matrix as JSON")]
+        json: bool,
+    },
+}
+
+/// License families, ordered from least to most restrictive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LicenseClass {
+    Permissive,
+    /// File-level copyleft (MPL, LGPL, EPL): linking is fine, modified files stay open.
+    WeakCopyleft,
+    /// Copyleft that extends to the combined work (GPL, AGPL).
+    Copyleft,
+    /// Missing, custom (`license-file`) or unrecognized licenses; needs a human.
+    Unknown,
+}
+
+const PERMISSIVE: &[&str] = &[
+    "0BSD",
+    "Apache-2.0",
+    "BSD-1-Clause",
+    "BSD-2-Clause",
+    "BSD-3-Clause",
+    "BSL-1.0",
+    "CC0-1.0",
+    "CDLA-Permissive-2.0",
+    "ISC",
+    "MIT",
+    "MIT-0",
+    "NCSA",
+    "OpenSSL",
+    "Unicode-3.0",
+    "Unicode-DFS-2016",
+    "Unlicense",
+    "Zlib",
+];
+const WEAK_COPYLEFT_PREFIXES: &[&str] = &["CDDL-", "EPL-", "LGPL-", "MPL-"];
+const COPYLEFT_PREFIXES: &[&str] = &["AGPL-", "GPL-", "OSL-", "SSPL-"];
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct DependencyLicense {
+    pub name: String,
+    pub version: String,
+    pub license: Option<String>,
+    pub class: LicenseClass,
+}
+
+/// How an artifact reaches its users, which decides what its licenses oblige.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ArtifactKind {
+    /// Release binaries, statically linking every normal dependency.
+    Binary,
+    /// On-chain programs (`cdylib`), also statically linked.
+    Program,
+    /// Crates published as source to crates.io.
+    Library,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Artifact {
+    pub name: String,
+    pub kind: ArtifactKind,
+    pub dependencies: Vec<DependencyLicense>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LicenseMatrix {
+    pub artifacts: Vec<Artifact>,
+    /// Dependencies only used to build or test: dev-dependencies, build
+    /// scripts and proc macros. Nothing here ships.
+    pub tooling: Vec<DependencyLicense>,
+}
+
+/// An edge of the resolved graph; `normal` when it links into the dependent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Edge {
+    pub to: String,
+    pub normal: bool,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    match args.subcommand {
+        LicensesSubcommand::Matrix { json } => {
+            let manifest_path = get_scope_root()?.join("Cargo.toml");
+            info!("🔎 resolving {}", manifest_path.display());
+            let metadata = MetadataCommand::new()
+                .manifest_path(&manifest_path)
+                .exec()
+                .context("failed to run cargo metadata")?;
+            let matrix = build_matrix(&metadata)?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&matrix)?);
+            } else {
+                print!("{}", render_markdown(&matrix));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn build_matrix(metadata: &Metadata) -> Result<LicenseMatrix> {
+    let graph = dependency_graph(metadata)?;
+    let packages: BTreeMap<String, &Package> = metadata
+        .packages
+        .iter()
+        .map(|package| (package.id.to_string(), package))
+        .collect();
+    let members: BTreeSet<String> = metadata
+        .workspace_members
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+    let host_only = host_only(metadata);
+    let describe = |ids: &BTreeSet<String>| -> Vec<DependencyLicense> {
+        ids.iter()
+            .filter(|id| !members.contains(*id))
+            .filter_map(|id| packages.get(id))
+            .map(|package| {
+                let license = package.license.clone();
+                DependencyLicense {
+                    name: package.name.to_string(),
+                    version: package.version.to_string(),
+                    class: classify(license.as_deref()),
+                    license,
+                }
+            })
+            .collect()
+    };
+
+    let mut artifacts = vec![];
+    let mut linked = BTreeSet::new();
+    for package in metadata.workspace_packages() {
+        let Some(kind) = artifact_kind(package) else {
+            continue;
+        };
+        let id = package.id.to_string();
+        let shipped = shipped_dependencies(&graph, &id, &host_only);
+        linked.extend(shipped.iter().cloned());
+        artifacts.push(Artifact {
+            name: package.name.to_string(),
+            kind,
+            dependencies: describe(&shipped),
+        });
+    }
+    artifacts.sort_by(|a, b| a.kind.cmp(&b.kind).then(a.name.cmp(&b.name)));
+
+    let tooling: BTreeSet<String> = graph
+        .keys()
+        .filter(|id| !linked.contains(*id))
+        .cloned()
+        .collect();
+    Ok(LicenseMatrix {
+        artifacts,
+        tooling: describe(&tooling),
+    })
+}
+
+/// Edges of the resolved graph by package id.
+pub fn dependency_graph(metadata: &Metadata) -> Result<BTreeMap<String, Vec<Edge>>> {
+    let resolve = metadata
+        .resolve
+        .as_ref()
+        .ok_or_else(|| anyhow!("cargo metadata did not resolve dependencies"))?;
+    Ok(resolve
+        .nodes
+        .iter()
+        .map(|node| {
+            let edges = node
+                .deps
+                .iter()
+                .map(|dep| Edge {
+                    to: dep.pkg.to_string(),
+                    normal: dep
+                        .dep_kinds
+                        .iter()
+                        .any(|k| k.kind == DependencyKind::Normal),
+                })
+                .collect();
+            (node.id.to_string(), edges)
+        })
+        .collect())
+}
+
+/// Ids of the proc macro packages, which only run in the compiler.
+pub fn host_only(metadata: &Metadata) -> BTreeSet<String> {
+    metadata
+        .packages
+        .iter()
+        .filter(|package| package.targets.iter().any(|t| t.is_proc_macro()))
+        .map(|package| package.id.to_string())
+        .collect()
+}
+
+fn artifact_kind(package: &Package) -> Option<ArtifactKind> {
+    if package.targets.iter().any(|t| t.is_bin()) {
+        return Some(ArtifactKind::Binary);
+    }
+    if package.targets.iter().any(|t| t.is_cdylib()) {
+        return Some(ArtifactKind::Program);
+    }
+    match &package.publish {
+        Some(registries) if registries.is_empty() => None,
+        _ => Some(ArtifactKind::Library),
+    }
+}
+
+/// Packages linked into `root`: everything reachable over normal edges,
+/// stopping at proc macros since they only run in the compiler.
+pub fn shipped_dependencies(
+    graph: &BTreeMap<String, Vec<Edge>>,
+    root: &str,
+    host_only: &BTreeSet<String>,
+) -> BTreeSet<String> {
+    let mut shipped = BTreeSet::new();
+    let mut queue = vec![root.to_string()];
+    while let Some(id) = queue.pop() {
+        for edge in graph.get(&id).into_iter().flatten() {
+            if edge.normal && !host_only.contains(&edge.to) && shipped.insert(edge.to.clone()) {
+                queue.push(edge.to.clone());
+            }
+        }
+    }
+    shipped
+}
+
+/// Classifies an SPDX expression. With `OR` the least restrictive choice
+/// applies, with `AND` the most restrictive one.
+pub fn classify(expression: Option<&str>) -> LicenseClass {
+    let Some(expression) = expression else {
+        return LicenseClass::Unknown;
+    };
+    // crates from before SPDX expressions use `/` for `OR`
+    let spaced = expression
+        .replace('/', " OR ")
+        .replace('(', " ( ")
+        .replace(')', " ) ");
+    let tokens: Vec<&str> = spaced.split_whitespace().collect();
+    let mut pos = 0;
+    match parse_or(&tokens, &mut pos) {
+        Some(class) if pos == tokens.len() => class,
+        _ => LicenseClass::Unknown,
+    }
+}
+
+fn parse_or(tokens: &[&str], pos: &mut usize) -> Option<LicenseClass> {
+    let mut class = parse_and(tokens, pos)?;
+    while tokens
+        .get(*pos)
+        .is_some_and(|t| t.eq_ignore_ascii_case("OR"))
+    {
+        *pos = pos.saturating_add(1);
+        class = class.min(parse_and(tokens, pos)?);
+    }
+    Some(class)
+}
+
+fn parse_and(tokens: &[&str], pos: &mut usize) -> Option<LicenseClass> {
+    let mut class = parse_term(tokens, pos)?;
+    while tokens
+        .get(*pos)
+        .is_some_and(|t| t.eq_ignore_ascii_case("AND"))
+    {
+        *pos = pos.saturating_add(1);
+        class = class.max(parse_term(tokens, pos)?);
+    }
+    Some(class)
+}
+
+fn parse_term(tokens: &[&str], pos: &mut usize) -> Option<LicenseClass> {
+    let token = *tokens.get(*pos)?;
+    *pos = pos.saturating_add(1);
+    let class = if token == "(" {
+        let class = parse_or(tokens, pos)?;
+        if tokens.get(*pos) != Some(&")") {
+            return None;
+        }
+        *pos = pos.saturating_add(1);
+        class
+    } else {
+        classify_id(token)
+    };
+    // exceptions only ever relax a license, keep the base classification
+    if tokens
+        .get(*pos)
+        .is_some_and(|t| t.eq_ignore_ascii_case("WITH"))
+    {
+        tokens.get(pos.saturating_add(1))?;
+        *pos = pos.saturating_add(2);
+    }
+    Some(class)
+}
+
+fn classify_id(id: &str) -> LicenseClass {
+    let id = id.trim_end_matches('+');
+    let id = id
+        .strip_suffix("-only")
+        .or_else(|| id.strip_suffix("-or-later"))
+        .unwrap_or(id);
+    if PERMISSIVE.iter().any(|p| p.eq_ignore_ascii_case(id)) {
+        LicenseClass::Permissive
+    } else if WEAK_COPYLEFT_PREFIXES.iter().any(|p| id.starts_with(p)) {
+        LicenseClass::WeakCopyleft
+    } else if COPYLEFT_PREFIXES.iter().any(|p| id.starts_with(p)) {
+        LicenseClass::Copyleft
+    } else {
+        LicenseClass::Unknown
+    }
+}
+
+fn class_label(class: LicenseClass) -> &'static str {
+    match class {
+        LicenseClass::Permissive => "permissive",
+        LicenseClass::WeakCopyleft => "weak copyleft",
+        LicenseClass::Copyleft => "copyleft",
+        LicenseClass::Unknown => "unknown",
+    }
+}
+
+/// Markdown report: per artifact a summary plus every dependency that is not
+/// permissive, then the same for build and test tooling.
+pub fn render_markdown(matrix: &LicenseMatrix) -> String {
+    let mut out = String::from("# License matrix\n\n");
+    out.push_str("| Artifact | Kind | Permissive | Weak copyleft | Copyleft | Unknown |\n");
+    out.push_str("|---|---|---|---|---|---|\n");
+    for artifact in matrix.artifacts.iter() {
+        let counts = count_by_class(&artifact.dependencies);
+        out.push_str(&format!(
+            "| {} | {:?} | {} | {} | {} | {} |\n",
+            artifact.name, artifact.kind, counts[0], counts[1], counts[2], counts[3]
+        ));
+    }
+    out.push('\n');
+
+    for artifact in matrix.artifacts.iter() {
+        let flagged: Vec<_> = artifact
+            .dependencies
+            .iter()
+            .filter(|d| d.class != LicenseClass::Permissive)
+            .collect();
+        if flagged.is_empty() {
+            continue;
+        }
+        out.push_str(&format!("## {} ({:?})\n\n", artifact.name, artifact.kind));
+        render_table(&mut out, &flagged);
+    }
+
+    let flagged: Vec<_> = matrix
+        .tooling
+        .iter()
+        .filter(|d| d.class != LicenseClass::Permissive)
+        .collect();
+    out.push_str(&format!(
+        "## Build and test tooling\n\n{} dependencies never ship in an artifact",
+        matrix.tooling.len()
+    ));
+    if flagged.is_empty() {
+        out.push_str(", all permissive.\n");
+    } else {
+        out.push_str(":\n\n");
+        render_table(&mut out, &flagged);
+    }
+    out
+}
+
+fn count_by_class(dependencies: &[DependencyLicense]) -> [usize; 4] {
+    let mut counts = [0usize; 4];
+    for dependency in dependencies {
+        let idx = dependency.class as usize;
+        counts[idx] = counts[idx].saturating_add(1);
+    }
+    counts
+}
+
+fn render_table(out: &mut String, dependencies: &[&DependencyLicense]) {
+    out.push_str("| Crate | Version | License | Class |\n|---|---|---|---|\n");
+    for dependency in dependencies {
+        out.push_str(&format!(
+            "| {} | {} | {} | {} |\n",
+            dependency.name,
+            dependency.version,
+            dependency.license.as_deref().unwrap_or("(none)"),
+            class_label(dependency.class)
+        ));
+    }
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_classify() {
+        assert_eq!(
+            classify(Some("MIT OR Apache-2.0")),
+            LicenseClass::Permissive
+        );
+        assert_eq!(classify(Some("MIT/Apache-2.0")), LicenseClass::Permissive);
+        assert_eq!(
+            classify(Some("(MIT OR Apache-2.0) AND Unicode-3.0")),
+            LicenseClass::Permissive
+        );
+        assert_eq!(
+            classify(Some("Apache-2.0 WITH LLVM-exception")),
+            LicenseClass::Permissive
+        );
+        assert_eq!(classify(Some("MPL-2.0")), LicenseClass::WeakCopyleft);
+        assert_eq!(
+            classify(Some("LGPL-2.1-or-later")),
+            LicenseClass::WeakCopyleft
+        );
+        assert_eq!(classify(Some("GPL-3.0+")), LicenseClass::Copyleft);
+        assert_eq!(
+            classify(Some("MIT AND GPL-2.0-only")),
+            LicenseClass::Copyleft
+        );
+        assert_eq!(classify(Some("GPL-2.0 OR MIT")), LicenseClass::Permissive);
+        assert_eq!(classify(Some("Proprietary")), LicenseClass::Unknown);
+        assert_eq!(classify(Some("MIT AND Proprietary")), LicenseClass::Unknown);
+        assert_eq!(classify(Some("(MIT")), LicenseClass::Unknown);
+        assert_eq!(classify(None), LicenseClass::Unknown);
+    }
+
+    #[test]
+    fn test_shipped_dependencies() {
+        let edge = |to: &str, normal: bool| Edge {
+            to: to.to_string(),
+            normal,
+        };
+        let graph = BTreeMap::from([
+            (
+                String::from("validator"),
+                vec![
+                    edge("runtime", true),
+                    edge("test-utils", false),
+                    edge("derive", true),
+                ],
+            ),
+            (String::from("runtime"), vec![edge("serde", true)]),
+            (String::from("test-utils"), vec![edge("gpl-fixture", true)]),
+            (String::from("derive"), vec![edge("syn", true)]),
+        ]);
+        let host_only = BTreeSet::from([String::from("derive")]);
+        assert_eq!(
+            shipped_dependencies(&graph, "validator", &host_only),
+            BTreeSet::from([String::from("runtime"), String::from("serde")])
+        );
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        let dependency = |name: &str, license: &str| DependencyLicense {
+            name: name.to_string(),
+            version: String::from("1.0.0"),
+            license: Some(license.to_string()),
+            class: classify(Some(license)),
+        };
+        let matrix = LicenseMatrix {
+            artifacts: vec![Artifact {
+                name: String::from("agave-validator"),
+                kind: ArtifactKind::Binary,
+                dependencies: vec![dependency("serde", "MIT"), dependency("webpki", "MPL-2.0")],
+            }],
+            tooling: vec![dependency("criterion", "Apache-2.0")],
+        };
+        assert_eq!(
+            render_markdown(&matrix),
+            "# License matrix\n\n\
+             | Artifact | Kind | Permissive | Weak copyleft | Copyleft | Unknown |\n\
+             |---|---|---|---|---|---|\n\
+             | agave-validator | Binary | 1 | 1 | 0 | 0 |\n\n\
+             ## agave-validator (Binary)\n\n\
+             | Crate | Version | License | Class |\n|---|---|---|---|\n\
+             | webpki | 1.0.0 | MPL-2.0 | weak copyleft |\n\n\
+             ## Build and test tooling\n\n\
+             1 dependencies never ship in an artifact, all permissive.\n"
+        );
+    }
+}
@@ -0,0 +1,534 @@
+mod binstall;
+mod build_scripts;
+mod changelog;
+mod codeowners;
+mod dep_sources;
+mod deprecations;
+mod dev_cycles;
+mod docsrs;
+mod git_deps;
+mod lint_config;
+mod lock_committed;
+mod manifest_fmt;
+mod metadata;
+mod orphan_manifests;
+mod pr;
+mod profiles;
+mod version_consistency;
+mod workspace_deps;
+mod workspace_members;
+
+use {
+    crate::{
+        config::Config,
+        utils::{
+            cargo::read_manifest, discovery::Discovery, get_current_version, get_git_root_path,
+            get_scope_root, http, recursive_find_files,
+        },
+    },
+    anyhow::{anyhow, Result},
+    clap::{Args, Subcommand},
+    log::info,
+    std::{
+        fmt, fs,
+        path::{Path, PathBuf},
+    },
+    toml_edit::DocumentMut,
+};
+
+/// A single lint violation, reported relative to the repository root.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Finding {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+impl Finding {
+    pub fn new(path: impl Into<PathBuf>, message: impl Into<String>) -> Self {
+        Finding {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path.display(), self.message)
+    }
+}
+
+pub type LintFn = fn(&Path) -> Result<Vec<Finding>>;
+
+/// A lint that can run as part of `xtask check`.
+pub struct Lint {
+    pub name: &'static str,
+    /// Cheap enough (no cargo invocations, no network) for pre-commit hooks.
+    pub fast: bool,
+    pub run: LintFn,
+}
+
+pub const LINTS: &[Lint] = &[
+    Lint {
+        name: "version-consistency",
+        fast: true,
+        run: version_consistency::lint,
+    },
+    Lint {
+        name: "manifest-fmt",
+        fast: true,
+        run: manifest_fmt::lint,
+    },
+    Lint {
+        name: "workspace-deps",
+        fast: true,
+        run: workspace_deps::lint,
+    },
+    Lint {
+        name: "codeowners",
+        fast: true,
+        run: codeowners::lint,
+    },
+    Lint {
+        name: "orphan-manifests",
+        fast: true,
+        run: orphan_manifests::lint,
+    },
+    Lint {
+        name: "workspace-members",
+        fast: true,
+        run: workspace_members::lint,
+    },
+    Lint {
+        name: "docsrs",
+        fast: true,
+        run: docsrs::lint,
+    },
+    Lint {
+        name: "deprecations",
+        fast: true,
+        run: deprecations::lint,
+    },
+    Lint {
+        name: "metadata",
+        fast: true,
+        run: metadata::lint,
+    },
+    Lint {
+        name: "git-deps",
+        fast: true,
+        run: git_deps::lint,
+    },
+    Lint {
+        name: "dep-sources",
+        fast: true,
+        run: dep_sources::lint,
+    },
+    Lint {
+        name: "lock-committed",
+        fast: true,
+        run: lock_committed::lint,
+    },
+    Lint {
+        name: "lint-config",
+        fast: true,
+        run: lint_config::lint,
+    },
+    Lint {
+        name: "profiles",
+        fast: true,
+        run: profiles::lint,
+    },
+    Lint {
+        name: "build-scripts",
+        fast: true,
+        run: build_scripts::lint,
+    },
+];
+
+#[derive(Subcommand)]
+pub enum LintSubcommand {
+    #[command(about = "Check member and internal dependency versions match the workspace version")]
+    VersionConsistency,
+    #[command(about = "Check manifests parse and keep dependency tables sorted")]
+    ManifestFmt,
+    #[command(about = "Check members inherit dependencies declared in workspace.dependencies")]
+    WorkspaceDeps,
+    #[command(about = "Check CODEOWNERS covers every crate and only points at existing paths")]
+    Codeowners {
+        #[arg(long, help = "Also check @org/team owners exist (needs GITHUB_TOKEN)")]
+        check_teams: bool,
+        #[arg(long, help = "Append entries assigning uncovered crates to this owner")]
+        stub_owner: Option<String>,
+    },
+    #[command(about = "Check every manifest is a workspace root or member, except fixtures")]
+    OrphanManifests,
+    #[command(about = "Check crates under the source roots are members and members globs match")]
+    WorkspaceMembers {
+        #[arg(
+            long,
+            help = "Add missing crates and drop dead entries in the root manifest"
+        )]
+        fix: bool,
+    },
+    #[command(about = "Report dev-dependencies that close a cycle between workspace members")]
+    DevCycles,
+    #[command(about = "Check [package.metadata.docs.rs] names existing features and targets")]
+    Docsrs {
+        #[arg(long, help = "Also build the docs locally the way docs.rs would")]
+        simulate: bool,
+        #[arg(long, help = "Only simulate these crates")]
+        package: Vec<String>,
+        #[arg(long, default_value = "nightly", help = "Toolchain used to simulate")]
+        toolchain: String,
+    },
+    #[command(about = "Check deprecated items are removed within lint.deprecations of xtask.toml")]
+    Deprecations,
+    #[command(about = "Check publishable crates carry complete crates.io metadata")]
+    Metadata {
+        #[arg(long, help = "Inherit fields from [workspace.package] where possible")]
+        fix: bool,
+    },
+    #[command(about = "Check publishable crates have no git dependency without a version")]
+    GitDeps,
+    #[command(
+        about = "Check dependencies only come from crates.io, paths and allowlisted sources"
+    )]
+    DepSources,
+    #[command(about = "Check every workspace commits a Cargo.lock in sync with its manifests")]
+    LockCommitted,
+    #[command(about = "Check members inherit [workspace.lints] and only override allowed lints")]
+    LintConfig {
+        #[arg(long, help = "Add lints.workspace = true to members without [lints]")]
+        fix: bool,
+    },
+    #[command(about = "Check profiles live in workspace roots and nested roots match the root")]
+    Profiles,
+    #[command(
+        about = "Check every build script is allowlisted, flagging network access and stray writes"
+    )]
+    BuildScripts {
+        #[arg(
+            long,
+            help = "Print every build script with its allowlist entry and concerns"
+        )]
+        list: bool,
+    },
+    #[command(about = "Check packaged binary crates carry [package.metadata.binstall]")]
+    Binstall {
+        #[arg(long, help = "Write the expected metadata into the manifests")]
+        fix: bool,
+        #[arg(
+            long,
+            help = "Also check cargo binstall resolves the uploaded release artifacts"
+        )]
+        resolve: bool,
+        #[arg(long, help = "Release to resolve [default: the workspace version]")]
+        version: Option<String>,
+    },
+    #[command(about = "Check changes to publishable crates come with a changelog entry")]
+    Changelog {
+        #[arg(
+            long,
+            default_value = "origin/main",
+            help = "Ref the branch is compared against"
+        )]
+        base: String,
+        #[arg(
+            long,
+            help = "Pull request whose skip label waives the check, defaults to the one of $GITHUB_EVENT_PATH"
+        )]
+        pr: Option<u64>,
+    },
+    #[command(about = "Check the PR title, labels and linked issue from the CI event payload")]
+    Pr {
+        #[arg(long, help = "Event payload, defaults to $GITHUB_EVENT_PATH")]
+        event_path: Option<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[command(subcommand)]
+    pub subcommand: LintSubcommand,
+}
+
+impl CommandArgs {
+    /// Whether this rewrites files (and must be audited) rather than only reporting.
+    pub fn is_mutating(&self) -> bool {
+        match &self.subcommand {
+            LintSubcommand::Codeowners { stub_owner, .. } => stub_owner.is_some(),
+            LintSubcommand::WorkspaceMembers { fix }
+            | LintSubcommand::Metadata { fix }
+            | LintSubcommand::LintConfig { fix }
+            | LintSubcommand::Binstall { fix, .. } => *fix,
+            _ => false,
+        }
+    }
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let root = get_scope_root()?;
+    let findings = match args.subcommand {
+        LintSubcommand::VersionConsistency => version_consistency::lint(&root)?,
+        LintSubcommand::ManifestFmt => manifest_fmt::lint(&root)?,
+        LintSubcommand::WorkspaceDeps => workspace_deps::lint(&root)?,
+        LintSubcommand::Codeowners {
+            check_teams,
+            stub_owner,
+        } => {
+            if let Some(owner) = stub_owner {
+                add_codeowners_stubs(&root, &owner)?;
+            }
+            let mut findings = codeowners::lint(&root)?;
+            if check_teams {
+                findings.extend(check_codeowners_teams(&root)?);
+            }
+            findings
+        }
+        LintSubcommand::OrphanManifests => orphan_manifests::lint(&root)?,
+        LintSubcommand::WorkspaceMembers { fix } => {
+            if fix {
+                let fixed = workspace_members::fix(&root)?;
+                info!("fixed {fixed} workspace.members problem(s)");
+            }
+            workspace_members::lint(&root)?
+        }
+        LintSubcommand::DevCycles => dev_cycles::lint(&root)?,
+        LintSubcommand::Docsrs {
+            simulate,
+            package,
+            toolchain,
+        } => {
+            let mut findings = docsrs::lint(&root)?;
+            if simulate {
+                findings.extend(simulate_docsrs(&root, &package, &toolchain)?);
+            }
+            findings
+        }
+        LintSubcommand::Deprecations => deprecations::lint(&root)?,
+        LintSubcommand::Metadata { fix } => {
+            if fix {
+                let fixed = metadata::fix(&root)?;
+                info!("inherited {fixed} field(s) from workspace.package");
+            }
+            metadata::lint(&root)?
+        }
+        LintSubcommand::GitDeps => git_deps::lint(&root)?,
+        LintSubcommand::DepSources => dep_sources::lint(&root)?,
+        LintSubcommand::LockCommitted => lock_committed::lint(&root)?,
+        LintSubcommand::LintConfig { fix } => {
+            if fix {
+                let fixed = lint_config::fix(&root)?;
+                info!("added lints.workspace = true to {fixed} manifest(s)");
+            }
+            lint_config::lint(&root)?
+        }
+        LintSubcommand::Profiles => profiles::lint(&root)?,
+        LintSubcommand::BuildScripts { list } => {
+            if list {
+                list_build_scripts(&root)?;
+            }
+            build_scripts::lint(&root)?
+        }
+        LintSubcommand::Binstall {
+            fix,
+            resolve,
+            version,
+        } => {
+            if fix {
+                let fixed = binstall::fix(&root)?;
+                info!("wrote package.metadata.binstall into {fixed} manifest(s)");
+            }
+            let mut findings = binstall::lint(&root)?;
+            if resolve {
+                let version = match version {
+                    Some(version) => version,
+                    None => get_current_version()?,
+                };
+                findings.extend(binstall::resolve(&root, &version)?);
+            }
+            findings
+        }
+        LintSubcommand::Changelog { base, pr } => lint_changelog(&root, &base, pr)?,
+        LintSubcommand::Pr { event_path } => return lint_pr(event_path),
+    };
+    report(&findings)
+}
+
+fn list_build_scripts(root: &Path) -> Result<()> {
+    let config = Config::load()?;
+    let fixtures = config.fixture_dirs();
+    let scripts = build_scripts::find_build_scripts(root, &fixtures)?;
+    for line in build_scripts::inventory(root, &scripts, &config.lint.build_scripts) {
+        println!("{line}");
+    }
+    Ok(())
+}
+
+fn add_codeowners_stubs(root: &Path, owner: &str) -> Result<()> {
+    let path = codeowners::find_codeowners(root).unwrap_or_else(|| root.join(".github/CODEOWNERS"));
+    let mut content = fs::read_to_string(&path).unwrap_or_default();
+    let uncovered = codeowners::uncovered_crates(root, &codeowners::parse(&content))?;
+    if uncovered.is_empty() {
+        return Ok(());
+    }
+    if !content.is_empty() && !content.ends_with('\n') {
+        content.push('\n');
+    }
+    content.push_str(&codeowners::stub_entries(&uncovered, owner));
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content)?;
+    info!("added {} entries to {}", uncovered.len(), path.display());
+    Ok(())
+}
+
+fn check_codeowners_teams(root: &Path) -> Result<Vec<Finding>> {
+    let Some(path) = codeowners::find_codeowners(root) else {
+        return Ok(vec![]);
+    };
+    let token = ["GITHUB_TOKEN", "GH_TOKEN"]
+        .iter()
+        .find_map(|key| std::env::var(key).ok().filter(|v| !v.is_empty()))
+        .ok_or_else(|| anyhow!("--check-teams needs GITHUB_TOKEN or GH_TOKEN"))?;
+    let config = Config::load()?;
+    let rules = codeowners::parse(&fs::read_to_string(&path)?);
+    let mut findings = vec![];
+    for (org, team) in codeowners::teams(&rules) {
+        let url = format!("https://api.github.com/orgs/{org}/teams/{team}");
+        let authorization = format!("Bearer {token}");
+        let headers = [
+            ("Authorization", authorization.as_str()),
+            ("User-Agent", "anza-xtask"),
+        ];
+        if http::get(&config.network, &url, &headers).is_err() {
+            findings.push(Finding::new(
+                relative(root, &path),
+                format!("team @{org}/{team} does not exist or is not visible"),
+            ));
+        }
+    }
+    Ok(findings)
+}
+
+fn simulate_docsrs(root: &Path, packages: &[String], toolchain: &str) -> Result<Vec<Finding>> {
+    let mut findings = vec![];
+    for manifest in find_manifests(root)? {
+        let Ok(doc) = read_manifest(&manifest) else {
+            continue;
+        };
+        let Some(name) = doc
+            .get("package")
+            .and_then(|p| p.get("name"))
+            .and_then(|n| n.as_str())
+        else {
+            continue;
+        };
+        if !packages.is_empty() && !packages.iter().any(|p| p == name) {
+            continue;
+        }
+        // invalid sections are already reported by the lint itself
+        let Ok(Some(metadata)) = docsrs::docsrs_metadata(&doc) else {
+            continue;
+        };
+        info!("📚 simulating the docs.rs build of {name}");
+        if let Err(e) = docsrs::simulate(&manifest, &metadata, toolchain) {
+            findings.push(Finding::new(
+                relative(root, &manifest),
+                format!("docs.rs build fails:\n{e}"),
+            ));
+        }
+    }
+    Ok(findings)
+}
+
+fn lint_changelog(root: &Path, base: &str, pr: Option<u64>) -> Result<Vec<Finding>> {
+    let config = Config::load()?;
+    let repo = get_git_root_path()?;
+    let crates = changelog::publishable_crates(&repo, root, &config)?;
+    let findings = changelog::missing_entries(&crates, &changelog::changed_files(&repo, base)?);
+    if findings.is_empty() {
+        return Ok(findings);
+    }
+    let repository = std::env::var("GITHUB_REPOSITORY").ok();
+    if let (Some(pr), Some(repository)) = (pr.or_else(changelog::event_pr_number), repository) {
+        let label = &config.lint.changelog.skip_label;
+        if changelog::has_label(&config.network, &repository, pr, label)? {
+            info!("⏩ pull request #{pr} is labeled {label}, skipping the changelog check");
+            return Ok(vec![]);
+        }
+    }
+    Ok(findings)
+}
+
+fn lint_pr(event_path: Option<PathBuf>) -> Result<()> {
+    let event_path = event_path
+        .or_else(|| std::env::var_os("GITHUB_EVENT_PATH").map(PathBuf::from))
+        .ok_or_else(|| anyhow!("pass --event-path or set GITHUB_EVENT_PATH"))?;
+    let config = Config::load()?;
+    let problems = pr::lint(&pr::read_event(&event_path)?, &config.pr);
+    if problems.is_empty() {
+        info!("✅ pull request looks good");
+        return Ok(());
+    }
+    for problem in problems.iter() {
+        println!("{}", pr::annotation(problem));
+    }
+    Err(anyhow!(
+        "{} problem(s) with the pull request",
+        problems.len()
+    ))
+}
+
+/// Prints the findings and fails when there are any.
+pub fn report(findings: &[Finding]) -> Result<()> {
+    if findings.is_empty() {
+        info!("✅ no findings");
+        return Ok(());
+    }
+    for finding in findings {
+        println!("{finding}");
+    }
+    Err(anyhow!("{} finding(s)", findings.len()))
+}
+
+/// A workspace root manifest found in the repository.
+pub struct Workspace {
+    pub dir: PathBuf,
+    pub manifest: PathBuf,
+    pub doc: DocumentMut,
+}
+
+/// Finds every workspace root under `root`, including nested ones.
+///
+/// Manifests that fail to parse are skipped here, `manifest-fmt` reports them.
+pub fn find_workspaces(root: &Path) -> Result<Vec<Workspace>> {
+    let mut workspaces = vec![];
+    for manifest in find_manifests(root)? {
+        let Ok(doc) = read_manifest(&manifest) else {
+            continue;
+        };
+        if doc.contains_key("workspace") {
+            workspaces.push(Workspace {
+                dir: manifest.parent().unwrap_or(root).to_path_buf(),
+                manifest,
+                doc,
+            });
+        }
+    }
+    Ok(workspaces)
+}
+
+pub fn find_manifests(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut manifests =
+        recursive_find_files(root, "Cargo.toml", &Discovery::for_tree(root)?, |_| true)?;
+    manifests.sort();
+    Ok(manifests)
+}
+
+/// Makes `path` relative to `root` for reporting.
+pub fn relative(root: &Path, path: &Path) -> PathBuf {
+    path.strip_prefix(root).unwrap_or(path).to_path_buf()
+}
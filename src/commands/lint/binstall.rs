@@ -0,0 +1,290 @@
+use {
+    super::{metadata::publishable_members, relative, Finding},
+    crate::{
+        commands::package::RELEASE_DIR,
+        config::{Config, PackagingConfig},
+        utils::{
+            cargo::read_manifest,
+            vfs::{FileSystem, RealFs},
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+    toml_edit::{value, DocumentMut, Item, Table},
+};
+
+/// A publishable crate shipping binaries that `package --profile validator`
+/// packages.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinaryCrate {
+    pub name: String,
+    pub manifest: PathBuf,
+}
+
+/// `[package.metadata.binstall]` pointing `cargo binstall` at the release
+/// tarballs, as `package manifests` lays them out.
+pub fn expected_metadata(packaging: &PackagingConfig) -> [(&'static str, String); 3] {
+    [
+        (
+            "pkg-url",
+            format!(
+                "https://github.com/{}/releases/download/v{{ version }}/{RELEASE_DIR}-{{ target }}.tar.bz2",
+                packaging.repository
+            ),
+        ),
+        ("bin-dir", format!("{RELEASE_DIR}/bin/{{ bin }}{{ binary-ext }}")),
+        ("pkg-fmt", String::from("tbz2")),
+    ]
+}
+
+/// Checks every packaged binary crate carries the expected binstall metadata.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let config = Config::load()?;
+    let expected = expected_metadata(&config.packaging);
+    let mut findings = vec![];
+    for krate in find_binary_crates(root, &config)? {
+        let doc = read_manifest(&krate.manifest)?;
+        for message in check(&doc, &expected) {
+            findings.push(Finding::new(
+                relative(root, &krate.manifest),
+                format!("{message}, fix it with lint binstall --fix"),
+            ));
+        }
+    }
+    Ok(findings)
+}
+
+/// Problems of the `[package.metadata.binstall]` of `doc`.
+pub fn check(doc: &DocumentMut, expected: &[(&str, String)]) -> Vec<String> {
+    let Some(binstall) = doc
+        .get("package")
+        .and_then(|p| p.get("metadata"))
+        .and_then(|m| m.get("binstall"))
+    else {
+        return vec![String::from("[package.metadata.binstall] is missing")];
+    };
+    expected
+        .iter()
+        .filter_map(
+            |(key, expected)| match binstall.get(key).and_then(|v| v.as_str()) {
+                Some(actual) if actual == expected => None,
+                Some(actual) => Some(format!(
+                    "package.metadata.binstall.{key} is {actual:?}, expected {expected:?}"
+                )),
+                None => Some(format!("package.metadata.binstall.{key} is missing")),
+            },
+        )
+        .collect()
+}
+
+/// Writes the expected binstall metadata into every packaged binary crate,
+/// returning how many manifests changed.
+pub fn fix(root: &Path) -> Result<usize> {
+    let config = Config::load()?;
+    let expected = expected_metadata(&config.packaging);
+    let mut fixed = 0usize;
+    for krate in find_binary_crates(root, &config)? {
+        let mut doc = read_manifest(&krate.manifest)?;
+        if check(&doc, &expected).is_empty() {
+            continue;
+        }
+        set_metadata(&mut doc, &expected).context(format!(
+            "[package] in {} is not a table",
+            krate.manifest.display()
+        ))?;
+        RealFs.write(&krate.manifest, &doc.to_string())?;
+        fixed = fixed.saturating_add(1);
+    }
+    Ok(fixed)
+}
+
+fn set_metadata(doc: &mut DocumentMut, expected: &[(&str, String)]) -> Option<()> {
+    let package = doc.get_mut("package")?.as_table_mut()?;
+    let metadata = package.entry("metadata").or_insert_with(|| {
+        let mut table = Table::new();
+        table.set_implicit(true);
+        Item::Table(table)
+    });
+    let binstall = metadata
+        .as_table_mut()?
+        .entry("binstall")
+        .or_insert_with(|| Item::Table(Table::new()))
+        .as_table_mut()?;
+    for (key, expected) in expected {
+        binstall.insert(key, value(expected.as_str()));
+    }
+    Some(())
+}
+
+/// Asks `cargo binstall` to resolve `version` of every packaged binary crate
+/// from the uploaded tarballs, without installing anything.
+pub fn resolve(root: &Path, version: &str) -> Result<Vec<Finding>> {
+    let config = Config::load()?;
+    let mut findings = vec![];
+    for krate in find_binary_crates(root, &config)? {
+        let output = Command::new("cargo")
+            .args(["binstall", "--dry-run", "--no-confirm"])
+            .args(["--strategies", "crate-meta-data"])
+            .args(["--version", version, &krate.name])
+            .output()
+            .map_err(|e| anyhow!("failed to run cargo binstall: {e}"))?;
+        if !output.status.success() {
+            findings.push(Finding::new(
+                relative(root, &krate.manifest),
+                format!(
+                    "cargo binstall cannot resolve {} {version}:\n{}",
+                    krate.name,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ),
+            ));
+        }
+    }
+    Ok(findings)
+}
+
+/// Publishable crates under `root` with a binary listed in
+/// `packaging.validator-binaries`.
+pub fn find_binary_crates(root: &Path, config: &Config) -> Result<Vec<BinaryCrate>> {
+    Ok(publishable_members(root, config)?
+        .into_iter()
+        .filter(|member| {
+            binaries(&member.dir, &member.doc)
+                .iter()
+                .any(|bin| config.packaging.validator_binaries.contains(bin))
+        })
+        .map(|member| BinaryCrate {
+            name: member.name,
+            manifest: member.dir.join("Cargo.toml"),
+        })
+        .collect())
+}
+
+/// Binary targets of the crate in `dir`: its `[[bin]]` entries plus, unless
+/// `autobins = false`, the ones cargo discovers in `src/main.rs` and
+/// `src/bin/` that no `[[bin]]` already claims by name or path.
+pub fn binaries(dir: &Path, doc: &DocumentMut) -> Vec<String> {
+    let declared: Vec<(&str, Option<PathBuf>)> = doc
+        .get("bin")
+        .and_then(|b| b.as_array_of_tables())
+        .into_iter()
+        .flat_map(|bins| bins.iter())
+        .filter_map(|bin| {
+            let name = bin.get("name").and_then(|n| n.as_str())?;
+            let path = bin.get("path").and_then(|p| p.as_str()).map(PathBuf::from);
+            Some((name, path))
+        })
+        .collect();
+    let mut bins: Vec<String> = declared.iter().map(|(name, _)| name.to_string()).collect();
+    let package = doc.get("package");
+    let autobins = package
+        .and_then(|p| p.get("autobins"))
+        .and_then(|a| a.as_bool())
+        .unwrap_or(true);
+    if autobins {
+        let mut discovered = vec![];
+        if dir.join("src/main.rs").exists() {
+            if let Some(name) = package.and_then(|p| p.get("name")).and_then(|n| n.as_str()) {
+                discovered.push((name.to_string(), PathBuf::from("src/main.rs")));
+            }
+        }
+        if let Ok(entries) = fs::read_dir(dir.join("src/bin")) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                let bin = if path.is_dir() && path.join("main.rs").exists() {
+                    path.file_name()
+                        .map(|name| (name, Path::new("src/bin").join(name).join("main.rs")))
+                } else if path.extension().is_some_and(|e| e == "rs") {
+                    path.file_stem()
+                        .zip(path.file_name())
+                        .map(|(stem, file)| (stem, Path::new("src/bin").join(file)))
+                } else {
+                    None
+                };
+                if let Some((name, path)) = bin {
+                    discovered.push((name.to_string_lossy().to_string(), path));
+                }
+            }
+        }
+        bins.extend(
+            discovered
+                .into_iter()
+                .filter(|(name, path)| {
+                    !declared.iter().any(|(declared, declared_path)| {
+                        declared == name || declared_path.as_ref() == Some(path)
+                    })
+                })
+                .map(|(name, _)| name),
+        );
+    }
+    bins.sort();
+    bins
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_binaries() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("src/bin/agave-install")).unwrap();
+        fs::write(dir.path().join("src/main.rs"), "").unwrap();
+        fs::write(dir.path().join("src/bin/solana-keygen.rs"), "").unwrap();
+        fs::write(dir.path().join("src/bin/agave-install/main.rs"), "").unwrap();
+        fs::write(dir.path().join("src/bin/README.md"), "").unwrap();
+        let doc: DocumentMut = "[package]\nname = \"solana-cli\"\n".parse().unwrap();
+        assert_eq!(
+            binaries(dir.path(), &doc),
+            vec!["agave-install", "solana-cli", "solana-keygen"]
+        );
+
+        let doc: DocumentMut =
+            "[package]\nname = \"solana-cli\"\n\n[[bin]]\nname = \"solana\"\npath = \"src/main.rs\"\n"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            binaries(dir.path(), &doc),
+            vec!["agave-install", "solana", "solana-keygen"]
+        );
+
+        let doc: DocumentMut =
+            "[package]\nname = \"solana-cli\"\nautobins = false\n\n[[bin]]\nname = \"solana\"\npath = \"src/main.rs\"\n"
+                .parse()
+                .unwrap();
+        assert_eq!(binaries(dir.path(), &doc), vec!["solana"]);
+    }
+
+    #[test]
+    fn test_check_and_set_metadata() {
+        let expected = expected_metadata(&PackagingConfig::default());
+        let mut doc: DocumentMut =
+            "[package]\nname = \"solana-cli\"\n\n[package.metadata.binstall]\npkg-fmt = \"tgz\"\n"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            check(&doc, &expected),
+            vec![
+                String::from("package.metadata.binstall.pkg-url is missing"),
+                String::from("package.metadata.binstall.bin-dir is missing"),
+                String::from("package.metadata.binstall.pkg-fmt is \"tgz\", expected \"tbz2\""),
+            ]
+        );
+        set_metadata(&mut doc, &expected).unwrap();
+        assert_eq!(check(&doc, &expected), Vec::<String>::new());
+
+        let mut doc: DocumentMut = "[package]\nname = \"solana-cli\"\n".parse().unwrap();
+        assert_eq!(
+            check(&doc, &expected),
+            vec![String::from("[package.metadata.binstall] is missing")]
+        );
+        set_metadata(&mut doc, &expected).unwrap();
+        assert_eq!(
+            doc.to_string(),
+            "[package]\nname = \"solana-cli\"\n\n[package.metadata.binstall]\npkg-url = \"https://github.com/anza-xyz/agave/releases/download/v{ version }/solana-release-{ target }.tar.bz2\"\nbin-dir = \"solana-release/bin/{ bin }{ binary-ext }\"\npkg-fmt = \"tbz2\"\n"
+        );
+    }
+}
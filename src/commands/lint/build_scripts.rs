@@ -0,0 +1,247 @@
+use {
+    super::{find_workspaces, relative, Finding},
+    crate::{
+        config::Config,
+        utils::cargo::{expand_workspace_members, read_manifest},
+    },
+    anyhow::{Context, Result},
+    std::{
+        collections::BTreeMap,
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Source patterns suggesting a build script talks to the network.
+const NETWORK_PATTERNS: &[&str] = &[
+    "std::net",
+    "TcpStream",
+    "UdpSocket",
+    "reqwest",
+    "ureq",
+    "hyper",
+    "\"curl\"",
+    "\"wget\"",
+    "http://",
+    "https://",
+];
+
+/// Source patterns of a build script writing files.
+const WRITE_PATTERNS: &[&str] = &[
+    "fs::write",
+    "File::create",
+    "OpenOptions",
+    "fs::copy",
+    "fs::rename",
+    "create_dir",
+    "remove_file",
+    "remove_dir",
+];
+
+/// A crate's build script and what the heuristic scan found in it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BuildScript {
+    pub name: String,
+    pub path: PathBuf,
+    /// Network patterns found in the source.
+    pub network: Vec<&'static str>,
+    /// Writes files without ever looking at `OUT_DIR`.
+    pub writes_outside_out_dir: bool,
+}
+
+impl BuildScript {
+    pub fn concerns(&self) -> Vec<String> {
+        let mut concerns = vec![];
+        if !self.network.is_empty() {
+            concerns.push(format!(
+                "may access the network ({})",
+                self.network.join(", ")
+            ));
+        }
+        if self.writes_outside_out_dir {
+            concerns.push(String::from("writes files without using OUT_DIR"));
+        }
+        concerns
+    }
+}
+
+/// Checks every build script has a `lint.build-scripts` entry and every
+/// entry still has a build script.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let config = Config::load()?;
+    let fixtures = config.fixture_dirs();
+    let scripts = find_build_scripts(root, &fixtures)?;
+    Ok(check(root, &scripts, &config.lint.build_scripts))
+}
+
+pub fn check(
+    root: &Path,
+    scripts: &[BuildScript],
+    allowed: &BTreeMap<String, String>,
+) -> Vec<Finding> {
+    let mut findings = vec![];
+    for script in scripts.iter() {
+        if allowed.contains_key(&script.name) {
+            continue;
+        }
+        let mut message = format!(
+            "build script of {} needs a lint.build-scripts entry explaining it",
+            script.name
+        );
+        let concerns = script.concerns();
+        if !concerns.is_empty() {
+            message.push_str(&format!(", it {}", concerns.join(" and ")));
+        }
+        findings.push(Finding::new(relative(root, &script.path), message));
+    }
+    for name in allowed.keys() {
+        if !scripts.iter().any(|s| s.name == *name) {
+            findings.push(Finding::new(
+                "xtask.toml",
+                format!("lint.build-scripts.{name} names a crate without a build script"),
+            ));
+        }
+    }
+    findings
+}
+
+/// Build scripts of the members of every workspace under `root`.
+pub fn find_build_scripts(root: &Path, fixtures: &[PathBuf]) -> Result<Vec<BuildScript>> {
+    let mut scripts = vec![];
+    for workspace in find_workspaces(root)? {
+        if fixtures.iter().any(|f| workspace.dir.starts_with(f)) {
+            continue;
+        }
+        for member in expand_workspace_members(&workspace.dir, &workspace.doc) {
+            let Ok(doc) = read_manifest(&member.join("Cargo.toml")) else {
+                continue;
+            };
+            let Some(package) = doc.get("package") else {
+                continue;
+            };
+            let name = package.get("name").and_then(|n| n.as_str()).unwrap_or("");
+            // cargo picks up build.rs unless `build` points elsewhere or is false
+            let path = match package.get("build").and_then(|b| b.as_value()) {
+                Some(build) if build.as_bool() == Some(false) => continue,
+                Some(build) => match build.as_str() {
+                    Some(path) => member.join(path),
+                    None => member.join("build.rs"),
+                },
+                None => member.join("build.rs"),
+            };
+            if !path.exists() {
+                continue;
+            }
+            let source =
+                fs::read_to_string(&path).context(format!("failed to read {}", path.display()))?;
+            scripts.push(scan(name, path, &source));
+        }
+    }
+    scripts.sort_by(|a, b| a.name.cmp(&b.name));
+    scripts.dedup_by(|a, b| a.path == b.path);
+    Ok(scripts)
+}
+
+pub fn scan(name: &str, path: PathBuf, source: &str) -> BuildScript {
+    let network = NETWORK_PATTERNS
+        .iter()
+        .copied()
+        .filter(|pattern| source.contains(pattern))
+        .collect();
+    let writes = WRITE_PATTERNS
+        .iter()
+        .any(|pattern| source.contains(pattern));
+    BuildScript {
+        name: name.to_string(),
+        path,
+        network,
+        writes_outside_out_dir: writes && !source.contains("OUT_DIR"),
+    }
+}
+
+/// One line per build script: crate, path, allowlist reason and concerns.
+pub fn inventory(
+    root: &Path,
+    scripts: &[BuildScript],
+    allowed: &BTreeMap<String, String>,
+) -> Vec<String> {
+    scripts
+        .iter()
+        .map(|script| {
+            let reason = allowed
+                .get(&script.name)
+                .map(|r| format!("✅ {r}"))
+                .unwrap_or_else(|| String::from("❌ not allowed"));
+            let concerns = script.concerns();
+            format!(
+                "{:<40} {:<50} {reason}{}",
+                script.name,
+                relative(root, &script.path).display(),
+                if concerns.is_empty() {
+                    String::new()
+                } else {
+                    format!(" ⚠️ {}", concerns.join(", "))
+                }
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_scan() {
+        let script = scan(
+            "a",
+            PathBuf::from("a/build.rs"),
+            r#"fn main() {
+    let body = reqwest::blocking::get("https://example.com").unwrap();
+    std::fs::write("generated.rs", body.text().unwrap()).unwrap();
+}"#,
+        );
+        assert_eq!(script.network, vec!["reqwest", "https://"]);
+        assert!(script.writes_outside_out_dir);
+
+        let script = scan(
+            "b",
+            PathBuf::from("b/build.rs"),
+            r#"fn main() {
+    let out = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(format!("{out}/gen.rs"), "").unwrap();
+}"#,
+        );
+        assert_eq!(script.concerns(), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_check() {
+        let root = Path::new("/repo");
+        let scripts = vec![
+            scan(
+                "a",
+                PathBuf::from("/repo/a/build.rs"),
+                "use std::net::TcpStream;",
+            ),
+            scan("b", PathBuf::from("/repo/b/build.rs"), ""),
+        ];
+        let allowed = BTreeMap::from([
+            (String::from("b"), String::from("links libfoo")),
+            (String::from("c"), String::from("removed")),
+        ]);
+        assert_eq!(
+            check(root, &scripts, &allowed),
+            vec![
+                Finding::new(
+                    "a/build.rs",
+                    "build script of a needs a lint.build-scripts entry explaining it, it may access the network (std::net, TcpStream)"
+                ),
+                Finding::new(
+                    "xtask.toml",
+                    "lint.build-scripts.c names a crate without a build script"
+                ),
+            ]
+        );
+    }
+}
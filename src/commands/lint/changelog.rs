@@ -0,0 +1,173 @@
+use {
+    super::{metadata::publishable_members, pr::Label, relative, Finding},
+    crate::{
+        config::{Config, NetworkConfig},
+        utils::{git::git_in, http},
+    },
+    anyhow::{anyhow, Context, Result},
+    serde::Deserialize,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// A publishable crate, with paths relative to the repository root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Crate {
+    pub name: String,
+    pub dir: PathBuf,
+    pub changelog: PathBuf,
+}
+
+/// Publishable crates under `root` outside the fixtures, each with its
+/// changelog.
+pub fn publishable_crates(repo: &Path, root: &Path, config: &Config) -> Result<Vec<Crate>> {
+    Ok(publishable_members(root, config)?
+        .into_iter()
+        .map(|member| {
+            let dir = relative(repo, &member.dir);
+            Crate {
+                changelog: config.crate_changelog(&member.name, &dir),
+                name: member.name,
+                dir,
+            }
+        })
+        .collect())
+}
+
+/// Whether `path`, relative to its crate, is part of what gets published as
+/// code: `src/` and the build script.
+pub fn is_source(path: &Path) -> bool {
+    path.starts_with("src") || path == Path::new("build.rs")
+}
+
+/// Crates whose sources changed while their changelog did not.
+pub fn missing_entries(crates: &[Crate], changed: &[PathBuf]) -> Vec<Finding> {
+    crates
+        .iter()
+        .filter(|krate| {
+            changed
+                .iter()
+                .any(|path| path.strip_prefix(&krate.dir).is_ok_and(is_source))
+                && !changed.contains(&krate.changelog)
+        })
+        .map(|krate| {
+            Finding::new(
+                krate.dir.join("Cargo.toml"),
+                format!(
+                    "{} changed without an entry in {}",
+                    krate.name,
+                    krate.changelog.display()
+                ),
+            )
+        })
+        .collect()
+}
+
+/// Files changed on HEAD since it forked from `base`.
+pub fn changed_files(repo: &Path, base: &str) -> Result<Vec<PathBuf>> {
+    let diff = git_in(repo, &["diff", "--name-only", &format!("{base}...HEAD")])
+        .context(format!("failed to diff against {base}"))?;
+    Ok(diff.lines().map(PathBuf::from).collect())
+}
+
+#[derive(Debug, Deserialize)]
+struct PullRequestNumber {
+    number: u64,
+}
+
+/// The pull request of the `pull_request` event at `$GITHUB_EVENT_PATH`.
+pub fn event_pr_number() -> Option<u64> {
+    let path = std::env::var_os("GITHUB_EVENT_PATH")?;
+    let content = fs::read_to_string(path).ok()?;
+    serde_json::from_str::<PullRequestNumber>(&content)
+        .ok()
+        .map(|event| event.number)
+}
+
+/// Whether pull request `pr` of `repository` (`owner/name`) has `label`,
+/// asked from the GitHub API so labels added after the run was triggered
+/// count.
+pub fn has_label(network: &NetworkConfig, repository: &str, pr: u64, label: &str) -> Result<bool> {
+    let url = format!("https://api.github.com/repos/{repository}/issues/{pr}/labels");
+    let authorization = ["GITHUB_TOKEN", "GH_TOKEN"]
+        .iter()
+        .find_map(|key| std::env::var(key).ok().filter(|v| !v.is_empty()))
+        .map(|token| format!("Bearer {token}"));
+    let mut headers = vec![
+        ("Accept", "application/vnd.github+json"),
+        ("User-Agent", "anza-xtask"),
+    ];
+    if let Some(authorization) = &authorization {
+        headers.push(("Authorization", authorization.as_str()));
+    }
+    let body = http::get(network, &url, &headers)?;
+    let labels: Vec<Label> = serde_json::from_slice(&body)
+        .map_err(|e| anyhow!("unexpected labels of pull request #{pr}: {e}"))?;
+    Ok(labels.iter().any(|l| l.name == label))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn krate(name: &str, dir: &str, changelog: &str) -> Crate {
+        Crate {
+            name: name.to_string(),
+            dir: PathBuf::from(dir),
+            changelog: PathBuf::from(changelog),
+        }
+    }
+
+    #[test]
+    fn test_missing_entries() {
+        let crates = [
+            krate("solana-sdk", "sdk", "CHANGELOG.md"),
+            krate("solana-program", "sdk/program", "sdk/program/CHANGELOG.md"),
+            krate("solana-cli", "cli", "CHANGELOG.md"),
+        ];
+        let changed = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+
+        assert_eq!(
+            missing_entries(
+                &crates,
+                &changed(&[
+                    "sdk/src/lib.rs",
+                    "sdk/program/src/lib.rs",
+                    "cli/tests/cli.rs",
+                    "README.md"
+                ])
+            ),
+            vec![
+                Finding::new(
+                    "sdk/Cargo.toml",
+                    "solana-sdk changed without an entry in CHANGELOG.md"
+                ),
+                Finding::new(
+                    "sdk/program/Cargo.toml",
+                    "solana-program changed without an entry in sdk/program/CHANGELOG.md"
+                ),
+            ]
+        );
+        assert_eq!(
+            missing_entries(
+                &crates,
+                &changed(&["sdk/program/build.rs", "sdk/program/CHANGELOG.md"])
+            ),
+            vec![]
+        );
+        assert_eq!(
+            missing_entries(&crates, &changed(&["cli/src/main.rs", "CHANGELOG.md"])),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_is_source() {
+        assert!(is_source(Path::new("src/lib.rs")));
+        assert!(is_source(Path::new("build.rs")));
+        assert!(!is_source(Path::new("benches/bench.rs")));
+        assert!(!is_source(Path::new("sources.md")));
+    }
+}
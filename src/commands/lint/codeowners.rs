@@ -0,0 +1,254 @@
+use {
+    super::{find_manifests, relative, Finding},
+    crate::utils::{
+        cargo::{read_manifest, wildcard_match},
+        portable::slash_path,
+    },
+    anyhow::{Context, Result},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Where GitHub looks for CODEOWNERS, in order.
+const CODEOWNERS_LOCATIONS: [&str; 3] = [".github/CODEOWNERS", "CODEOWNERS", "docs/CODEOWNERS"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub line: usize,
+    pub pattern: String,
+    pub owners: Vec<String>,
+}
+
+pub fn find_codeowners(root: &Path) -> Option<PathBuf> {
+    CODEOWNERS_LOCATIONS
+        .iter()
+        .map(|location| root.join(location))
+        .find(|path| path.exists())
+}
+
+pub fn parse(content: &str) -> Vec<Rule> {
+    content
+        .lines()
+        .enumerate()
+        .filter_map(|(idx, line)| {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let mut parts = line.split_whitespace();
+            let pattern = parts.next()?;
+            Some(Rule {
+                line: idx.saturating_add(1),
+                pattern: pattern.to_string(),
+                owners: parts.map(str::to_string).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Whether a CODEOWNERS `pattern` matches the repository-relative `path`,
+/// following the gitignore-style rules GitHub uses.
+pub fn matches(pattern: &str, path: &str) -> bool {
+    let pattern = pattern.trim_end_matches('/');
+    if pattern == "*" || pattern.is_empty() {
+        return true;
+    }
+    let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+    let prefixes = (1..=components.len()).map(|len| components[..len].join("/"));
+    if pattern.starts_with('/') || pattern.contains('/') {
+        // anchored: matches a directory (and everything below it) or the file itself
+        let pattern = pattern.trim_start_matches('/').replace("**", "*");
+        prefixes
+            .into_iter()
+            .any(|prefix| wildcard_match(&pattern, &prefix))
+    } else {
+        components.iter().any(|c| wildcard_match(pattern, c))
+    }
+}
+
+/// Checks every crate is owned and every rule points at something.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let Some(codeowners) = find_codeowners(root) else {
+        return Ok(vec![]);
+    };
+    let content = fs::read_to_string(&codeowners)
+        .context(format!("failed to read {}", codeowners.display()))?;
+    let rules = parse(&content);
+    let codeowners_path = relative(root, &codeowners);
+    let mut findings = vec![];
+
+    for rule in rules.iter() {
+        if rule.owners.is_empty() {
+            continue;
+        }
+        let literal = rule.pattern.trim_start_matches('/').trim_end_matches('/');
+        let is_anchored = rule.pattern.starts_with('/') || literal.contains('/');
+        if is_anchored && !literal.contains(['*', '?']) && !root.join(literal).exists() {
+            findings.push(Finding::new(
+                &codeowners_path,
+                format!("line {}: {} does not exist", rule.line, rule.pattern),
+            ));
+        }
+        for owner in rule.owners.iter() {
+            if !owner.starts_with('@') && !owner.contains('@') {
+                findings.push(Finding::new(
+                    &codeowners_path,
+                    format!("line {}: {owner} is not a user, team or email", rule.line),
+                ));
+            }
+        }
+    }
+
+    for dir in uncovered_crates(root, &rules)? {
+        findings.push(Finding::new(
+            &codeowners_path,
+            format!("{} has no owner", dir.display()),
+        ));
+    }
+    Ok(findings)
+}
+
+/// Crate directories no rule assigns an owner to.
+pub fn uncovered_crates(root: &Path, rules: &[Rule]) -> Result<Vec<PathBuf>> {
+    let mut uncovered = vec![];
+    for manifest in find_manifests(root)? {
+        let Ok(doc) = read_manifest(&manifest) else {
+            continue;
+        };
+        if !doc.contains_key("package") {
+            continue;
+        }
+        let path = slash_path(&relative(root, &manifest));
+        // the last matching rule wins, a rule without owners un-assigns
+        let owned = rules
+            .iter()
+            .rev()
+            .find(|rule| matches(&rule.pattern, &path))
+            .is_some_and(|rule| !rule.owners.is_empty());
+        if !owned {
+            let dir = manifest.parent().unwrap_or(root);
+            uncovered.push(relative(root, dir));
+        }
+    }
+    Ok(uncovered)
+}
+
+/// CODEOWNERS lines assigning `owner` to each uncovered crate directory.
+pub fn stub_entries(uncovered: &[PathBuf], owner: &str) -> String {
+    uncovered
+        .iter()
+        .map(|dir| format!("/{}/ {owner}\n", dir.display()))
+        .collect()
+}
+
+/// Distinct `@org/team` owners, for checking the teams exist.
+pub fn teams(rules: &[Rule]) -> Vec<(String, String)> {
+    let mut teams: Vec<(String, String)> = rules
+        .iter()
+        .flat_map(|rule| rule.owners.iter())
+        .filter_map(|owner| owner.strip_prefix('@')?.split_once('/'))
+        .map(|(org, team)| (org.to_string(), team.to_string()))
+        .collect();
+    teams.sort();
+    teams.dedup();
+    teams
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_parse() {
+        let rules = parse(
+            "# owners\n* @anza-xyz/core\n\n/programs/ @anza-xyz/programs # onchain\n/vendored/\n",
+        );
+        assert_eq!(
+            rules,
+            vec![
+                Rule {
+                    line: 2,
+                    pattern: String::from("*"),
+                    owners: vec![String::from("@anza-xyz/core")],
+                },
+                Rule {
+                    line: 4,
+                    pattern: String::from("/programs/"),
+                    owners: vec![String::from("@anza-xyz/programs")],
+                },
+                Rule {
+                    line: 5,
+                    pattern: String::from("/vendored/"),
+                    owners: vec![],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("*", "a/Cargo.toml"));
+        assert!(matches("/programs/", "programs/token/Cargo.toml"));
+        assert!(matches("programs/token", "programs/token/Cargo.toml"));
+        assert!(!matches("/programs/", "sdk/programs/Cargo.toml"));
+        assert!(matches("programs", "sdk/programs/Cargo.toml"));
+        assert!(matches("/sdk/**", "sdk/a/b/Cargo.toml"));
+        assert!(matches("*.toml", "a/Cargo.toml"));
+        assert!(!matches("/a/", "ab/Cargo.toml"));
+    }
+
+    #[test]
+    fn test_lint() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let root = root_dir.path();
+        for name in ["a", "b", "vendored/c"] {
+            fs::create_dir_all(root.join(name)).unwrap();
+            fs::write(
+                root.join(name).join("Cargo.toml"),
+                format!("[package]\nname = \"{}\"\n", name.replace('/', "-")),
+            )
+            .unwrap();
+        }
+        fs::create_dir_all(root.join(".github")).unwrap();
+        fs::write(
+            root.join(".github/CODEOWNERS"),
+            "/a/ @anza-xyz/a\n/vendored/ @anza-xyz/vendor\n/vendored/c/\n/gone/ @anza-xyz/gone bob\n",
+        )
+        .unwrap();
+
+        let findings: Vec<_> = lint(root)
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                ".github/CODEOWNERS: line 4: /gone/ does not exist",
+                ".github/CODEOWNERS: line 4: bob is not a user, team or email",
+                ".github/CODEOWNERS: b has no owner",
+                ".github/CODEOWNERS: vendored/c has no owner",
+            ]
+        );
+
+        let rules = parse(&fs::read_to_string(root.join(".github/CODEOWNERS")).unwrap());
+        let uncovered = uncovered_crates(root, &rules).unwrap();
+        assert_eq!(
+            stub_entries(&uncovered, "@anza-xyz/core"),
+            "/b/ @anza-xyz/core\n/vendored/c/ @anza-xyz/core\n"
+        );
+        assert_eq!(
+            teams(&rules),
+            vec![
+                (String::from("anza-xyz"), String::from("a")),
+                (String::from("anza-xyz"), String::from("gone")),
+                (String::from("anza-xyz"), String::from("vendor")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_without_codeowners() {
+        let root_dir = tempfile::tempdir().unwrap();
+        assert_eq!(lint(root_dir.path()).unwrap(), vec![]);
+    }
+}
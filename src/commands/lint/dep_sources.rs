@@ -0,0 +1,117 @@
+use {
+    super::{find_manifests, relative, Finding},
+    crate::{
+        config::{Config, DepSourcesConfig},
+        utils::cargo::{dependency_tables, read_manifest, wildcard_match},
+    },
+    anyhow::Result,
+    std::path::Path,
+    toml_edit::DocumentMut,
+};
+
+/// Checks every dependency, including `[workspace.dependencies]` and
+/// `[patch]` entries, comes from crates.io, a local path or a source
+/// allowlisted under `lint.dep-sources`.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let config = Config::load()?;
+    let fixtures = config.fixture_dirs();
+    let mut findings = vec![];
+    for manifest in find_manifests(root)? {
+        if fixtures.iter().any(|f| manifest.starts_with(f)) {
+            continue;
+        }
+        // manifest-fmt reports manifests that do not parse
+        let Ok(doc) = read_manifest(&manifest) else {
+            continue;
+        };
+        for message in unapproved_sources(&doc, &config.lint.dep_sources) {
+            findings.push(Finding::new(relative(root, &manifest), message));
+        }
+    }
+    findings.sort();
+    Ok(findings)
+}
+
+fn unapproved_sources(doc: &DocumentMut, allowed: &DepSourcesConfig) -> Vec<String> {
+    let mut messages = vec![];
+    for table in dependency_tables(doc) {
+        let (table, item) = (&table.name, table.item);
+        let Some(dependencies) = item.as_table_like() else {
+            continue;
+        };
+        for (key, spec) in dependencies.iter() {
+            if let Some(url) = spec.get("git").and_then(|g| g.as_str()) {
+                if !is_allowed_git(url, &allowed.git) {
+                    messages.push(format!(
+                        "[{table}] {key} comes from git repository {url}, which lint.dep-sources.git does not allow"
+                    ));
+                }
+            }
+            for field in ["registry", "registry-index"] {
+                let Some(registry) = spec.get(field).and_then(|r| r.as_str()) else {
+                    continue;
+                };
+                if !allowed.registries.iter().any(|r| r == registry) {
+                    messages.push(format!(
+                        "[{table}] {key} comes from registry {registry}, which lint.dep-sources.registries does not allow"
+                    ));
+                }
+            }
+        }
+    }
+    messages
+}
+
+/// Whether `url` matches an allowed pattern, with or without a trailing
+/// `.git` or `/`.
+fn is_allowed_git(url: &str, patterns: &[String]) -> bool {
+    let bare = url.trim_end_matches('/').trim_end_matches(".git");
+    patterns
+        .iter()
+        .any(|pattern| wildcard_match(pattern, url) || wildcard_match(pattern, bare))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_unapproved_sources() {
+        let doc: DocumentMut = r#"[workspace.dependencies]
+ours = { git = "https://github.com/anza-xyz/ours.git", branch = "main" }
+theirs = { git = "https://github.com/someone/theirs", rev = "abc" }
+internal = { version = "1.0", registry = "internal" }
+
+[dependencies]
+local = { path = "../local" }
+serde = "1.0"
+mirror = { version = "1.0", registry-index = "sparse+https://mirror.example.com/" }
+
+[target.'cfg(unix)'.dev-dependencies]
+fork = { git = "https://gitlab.com/fork/fork" }
+
+[patch.crates-io]
+curve25519-dalek = { git = "https://github.com/someone/curve25519-dalek" }
+ours-patched = { git = "https://github.com/anza-xyz/ours" }
+"#
+        .parse()
+        .unwrap();
+        let allowed = DepSourcesConfig {
+            registries: vec![String::from("internal")],
+            git: vec![String::from("https://github.com/anza-xyz/*")],
+        };
+        assert_eq!(
+            unapproved_sources(&doc, &allowed),
+            vec![
+                "[dependencies] mirror comes from registry sparse+https://mirror.example.com/, which lint.dep-sources.registries does not allow",
+                "[workspace.dependencies] theirs comes from git repository https://github.com/someone/theirs, which lint.dep-sources.git does not allow",
+                "[target.cfg(unix).dev-dependencies] fork comes from git repository https://gitlab.com/fork/fork, which lint.dep-sources.git does not allow",
+                "[patch.crates-io] curve25519-dalek comes from git repository https://github.com/someone/curve25519-dalek, which lint.dep-sources.git does not allow",
+            ]
+        );
+        assert!(is_allowed_git(
+            "https://github.com/anza-xyz/ours.git",
+            &[String::from("https://github.com/anza-xyz/ours")]
+        ));
+    }
+}
@@ -0,0 +1,221 @@
+use {
+    super::{find_workspaces, relative, Finding},
+    crate::{
+        config::{Config, DeprecationsConfig},
+        utils::cargo::{expand_workspace_members, read_manifest},
+    },
+    anyhow::Result,
+    ignore::WalkBuilder,
+    semver::Version,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+    toml_edit::DocumentMut,
+};
+
+/// A `#[deprecated(since = "...")]` attribute found in a source file.
+#[derive(Debug, PartialEq)]
+pub struct Deprecation {
+    pub line: usize,
+    pub since: String,
+}
+
+/// Checks no deprecated item outlived the release window in
+/// `lint.deprecations` of `xtask.toml`.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let config = Config::load()?;
+    let fixtures = config.fixture_dirs();
+    let policy = &config.lint.deprecations;
+
+    let mut findings = vec![];
+    for workspace in find_workspaces(root)? {
+        if fixtures.iter().any(|f| workspace.dir.starts_with(f)) {
+            continue;
+        }
+        let members = expand_workspace_members(&workspace.dir, &workspace.doc);
+        for member in members.iter() {
+            let Ok(doc) = read_manifest(&member.join("Cargo.toml")) else {
+                continue;
+            };
+            let Some(current) = package_version(&doc, &workspace.doc) else {
+                continue;
+            };
+            for file in source_files(member, &members) {
+                let Ok(source) = fs::read_to_string(&file) else {
+                    continue;
+                };
+                for deprecation in find_deprecations(&source) {
+                    let Some(problem) = overdue(&deprecation.since, &current, policy) else {
+                        continue;
+                    };
+                    findings.push(Finding::new(
+                        format!("{}:{}", relative(root, &file).display(), deprecation.line),
+                        problem,
+                    ));
+                }
+            }
+        }
+    }
+    findings.sort();
+    Ok(findings)
+}
+
+/// The version of the crate, following `version.workspace = true`.
+fn package_version(doc: &DocumentMut, workspace_doc: &DocumentMut) -> Option<Version> {
+    let version = doc.get("package")?.get("version")?;
+    let version = match version.as_str() {
+        Some(version) => version,
+        None => workspace_doc
+            .get("workspace")?
+            .get("package")?
+            .get("version")?
+            .as_str()?,
+    };
+    Version::parse(version).ok()
+}
+
+/// Rust sources of the member at `dir`, leaving out other members nested in it.
+fn source_files(dir: &Path, members: &[PathBuf]) -> Vec<PathBuf> {
+    let nested: Vec<PathBuf> = members
+        .iter()
+        .filter(|m| m.as_path() != dir && m.starts_with(dir))
+        .cloned()
+        .collect();
+    let mut files: Vec<PathBuf> = WalkBuilder::new(dir)
+        .git_ignore(true)
+        .filter_entry(move |entry| !nested.iter().any(|n| entry.path() == n.as_path()))
+        .build()
+        .filter_map(Result::ok)
+        .map(|entry| entry.into_path())
+        .filter(|path| path.extension().is_some_and(|e| e == "rs"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Every `#[deprecated]` attribute with a `since`, including ones spread
+/// over several lines.
+pub fn find_deprecations(source: &str) -> Vec<Deprecation> {
+    let mut deprecations = vec![];
+    let mut offset = 0;
+    while let Some(start) = source[offset..].find("#[deprecated") {
+        let start = offset.saturating_add(start);
+        offset = start.saturating_add(1);
+        let line_start = source[..start]
+            .rfind('\n')
+            .map_or(0, |i| i.saturating_add(1));
+        if source[line_start..start].contains("//") {
+            continue;
+        }
+        let attribute = match source[start..].find(']') {
+            Some(end) => &source[start..start.saturating_add(end)],
+            None => &source[start..],
+        };
+        let Some(since) = attribute
+            .split_once("since")
+            .and_then(|(_, rest)| rest.trim_start().strip_prefix('='))
+            .and_then(|rest| rest.trim_start().strip_prefix('"'))
+            .and_then(|rest| rest.split_once('"'))
+            .map(|(since, _)| since.to_string())
+        else {
+            continue;
+        };
+        deprecations.push(Deprecation {
+            line: source[..start].matches('\n').count().saturating_add(1),
+            since,
+        });
+    }
+    deprecations
+}
+
+/// Explains why an item deprecated in `since` should be gone by `current`,
+/// `None` while it is still within the policy. Versions that do not parse,
+/// like `since = "TBD"`, are left alone.
+pub fn overdue(since: &str, current: &Version, policy: &DeprecationsConfig) -> Option<String> {
+    let since_version = parse_lenient(since)?;
+    if since_version.major < current.major {
+        let majors = current.major.saturating_sub(since_version.major);
+        return (majors >= policy.major_releases).then(|| {
+            format!(
+                "deprecated since {since}, {majors} major release(s) ago at {current}, remove it"
+            )
+        });
+    }
+    let minors = current.minor.saturating_sub(since_version.minor);
+    let limit = policy.minor_releases?;
+    (since_version.major == current.major && minors >= limit).then(|| {
+        format!("deprecated since {since}, {minors} minor release(s) ago at {current}, remove it")
+    })
+}
+
+/// Accepts the `1.2` shorthand common in `since` values.
+fn parse_lenient(version: &str) -> Option<Version> {
+    let version = version.trim().trim_start_matches('v');
+    Version::parse(version)
+        .or_else(|_| Version::parse(&format!("{version}.0")))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_find_deprecations() {
+        let source = r#"
+#[deprecated(since = "1.2.0", note = "use `b`")]
+pub fn a() {}
+
+#[deprecated(
+    note = "use `d`",
+    since = "2.0"
+)]
+pub fn c() {}
+
+// #[deprecated(since = "0.1.0")]
+#[deprecated]
+pub fn e() {}
+"#;
+        assert_eq!(
+            find_deprecations(source),
+            vec![
+                Deprecation {
+                    line: 2,
+                    since: String::from("1.2.0")
+                },
+                Deprecation {
+                    line: 5,
+                    since: String::from("2.0")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_overdue() {
+        let current = Version::parse("3.4.0").unwrap();
+        let default = DeprecationsConfig::default();
+        assert_eq!(
+            overdue("2.9.0", &current, &default),
+            Some(String::from(
+                "deprecated since 2.9.0, 1 major release(s) ago at 3.4.0, remove it"
+            ))
+        );
+        assert_eq!(overdue("3.0", &current, &default), None);
+        assert_eq!(overdue("TBD", &current, &default), None);
+
+        let strict = DeprecationsConfig {
+            major_releases: 2,
+            minor_releases: Some(3),
+        };
+        assert_eq!(overdue("2.9.0", &current, &strict), None);
+        assert_eq!(overdue("3.2.0", &current, &strict), None);
+        assert_eq!(
+            overdue("v3.1", &current, &strict),
+            Some(String::from(
+                "deprecated since v3.1, 3 minor release(s) ago at 3.4.0, remove it"
+            ))
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use {
+    super::{find_workspaces, relative, Finding},
+    crate::utils::cargo::{dependency_tables, expand_workspace_members, read_manifest, TableScope},
+    anyhow::Result,
+    std::{
+        collections::{BTreeMap, BTreeSet, VecDeque},
+        path::{Path, PathBuf},
+    },
+    toml_edit::DocumentMut,
+};
+
+/// A dependency between two members of the same workspace.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Edge {
+    pub to: String,
+    pub dev: bool,
+}
+
+/// Reports dev-dependencies that close a cycle between workspace members.
+///
+/// Such cycles are legal, the publish order ignores dev edges, but a
+/// dev-dependency that carries a version must already be on the registry
+/// when its dependent is published.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let mut findings = vec![];
+    for workspace in find_workspaces(root)? {
+        let graph = member_graph(&workspace.dir, &workspace.doc);
+        for (from, (manifest, edges)) in graph.iter() {
+            for edge in edges.iter().filter(|edge| edge.dev) {
+                let Some(path) = path_between(&graph, &edge.to, from) else {
+                    continue;
+                };
+                findings.push(Finding::new(
+                    relative(root, manifest),
+                    format!(
+                        "dev-dependency on {} forms a cycle: {from} -> {}",
+                        edge.to,
+                        path.join(" -> ")
+                    ),
+                ));
+            }
+        }
+    }
+    findings.sort();
+    Ok(findings)
+}
+
+/// Member name to its manifest and its edges to other members.
+pub fn member_graph(
+    workspace_dir: &Path,
+    workspace_doc: &DocumentMut,
+) -> BTreeMap<String, (PathBuf, BTreeSet<Edge>)> {
+    let inherited = |key: &str| -> Option<String> {
+        workspace_doc
+            .get("workspace")?
+            .get("dependencies")?
+            .get(key)?
+            .get("package")?
+            .as_str()
+            .map(str::to_string)
+    };
+
+    let mut members = BTreeMap::new();
+    for member in expand_workspace_members(workspace_dir, workspace_doc) {
+        let manifest = member.join("Cargo.toml");
+        let Ok(doc) = read_manifest(&manifest) else {
+            continue;
+        };
+        let Some(name) = doc
+            .get("package")
+            .and_then(|p| p.get("name"))
+            .and_then(|n| n.as_str())
+        else {
+            continue;
+        };
+        let mut dependencies = vec![];
+        for table in dependency_tables(&doc) {
+            if !matches!(table.scope, TableScope::Package | TableScope::Target) {
+                continue;
+            }
+            let Some(item) = table.item.as_table_like() else {
+                continue;
+            };
+            for (key, spec) in item.iter() {
+                let package = spec
+                    .get("package")
+                    .and_then(|p| p.as_str())
+                    .map(str::to_string)
+                    .or_else(|| spec.get("workspace").and_then(|_| inherited(key)))
+                    .unwrap_or_else(|| key.to_string());
+                dependencies.push((package, table.kind == "dev-dependencies"));
+            }
+        }
+        members.insert(name.to_string(), (manifest, dependencies));
+    }
+
+    let names: BTreeSet<String> = members.keys().cloned().collect();
+    members
+        .into_iter()
+        .map(|(name, (manifest, dependencies))| {
+            let edges = dependencies
+                .into_iter()
+                .filter(|(to, _)| names.contains(to) && *to != name)
+                .map(|(to, dev)| Edge { to, dev })
+                .collect();
+            (name, (manifest, edges))
+        })
+        .collect()
+}
+
+/// Shortest path from `from` to `to` over any kind of edge, both ends included.
+fn path_between(
+    graph: &BTreeMap<String, (PathBuf, BTreeSet<Edge>)>,
+    from: &str,
+    to: &str,
+) -> Option<Vec<String>> {
+    let mut previous: BTreeMap<&str, &str> = BTreeMap::new();
+    let mut queue = VecDeque::from([from]);
+    while let Some(current) = queue.pop_front() {
+        if current == to {
+            let mut path = vec![to.to_string()];
+            let mut node = to;
+            while let Some(prev) = previous.get(node) {
+                path.push(prev.to_string());
+                node = prev;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for edge in graph.get(current).map(|(_, e)| e).into_iter().flatten() {
+            if edge.to != from && !previous.contains_key(edge.to.as_str()) {
+                previous.insert(&edge.to, current);
+                queue.push_back(&edge.to);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_dev_cycles() {
+        let findings = lint(Path::new("tests/dummy-workspace-dev-cycle")).unwrap();
+        assert_eq!(
+            findings,
+            vec![Finding::new(
+                "a/Cargo.toml",
+                "dev-dependency on b forms a cycle: a -> b -> a"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_no_dev_cycles() {
+        let findings = lint(Path::new("tests/dummy-workspace-dev-deps")).unwrap();
+        assert_eq!(findings, vec![]);
+    }
+}
@@ -0,0 +1,274 @@
+use {
+    super::{find_manifests, relative, Finding},
+    crate::utils::cargo::{read_manifest, DEPENDENCY_TABLES},
+    anyhow::{anyhow, Result},
+    serde::Deserialize,
+    std::{collections::BTreeSet, path::Path, process::Command},
+    toml_edit::DocumentMut,
+};
+
+/// `[package.metadata.docs.rs]`, see <https://docs.rs/about/metadata>.
+#[derive(Debug, Default, PartialEq, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DocsRsMetadata {
+    pub features: Vec<String>,
+    pub all_features: bool,
+    pub no_default_features: bool,
+    pub default_target: Option<String>,
+    pub targets: Vec<String>,
+    pub rustc_args: Vec<String>,
+    pub rustdoc_args: Vec<String>,
+    pub cargo_args: Vec<String>,
+}
+
+/// Checks docs.rs metadata only names existing features and valid targets.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let mut findings = vec![];
+    // only ask rustc for its targets when a manifest actually names some
+    let mut known_targets: Option<Vec<String>> = None;
+    for manifest in find_manifests(root)? {
+        let Ok(doc) = read_manifest(&manifest) else {
+            continue;
+        };
+        let metadata = match docsrs_metadata(&doc) {
+            Ok(Some(metadata)) => metadata,
+            Ok(None) => continue,
+            Err(e) => {
+                findings.push(Finding::new(
+                    relative(root, &manifest),
+                    format!("invalid [package.metadata.docs.rs]: {e}"),
+                ));
+                continue;
+            }
+        };
+        let targets = if metadata.targets.is_empty() && metadata.default_target.is_none() {
+            vec![]
+        } else {
+            if known_targets.is_none() {
+                known_targets = Some(rustc_targets()?);
+            }
+            known_targets.clone().unwrap_or_default()
+        };
+        for problem in validate(&doc, &metadata, &targets) {
+            findings.push(Finding::new(relative(root, &manifest), problem));
+        }
+    }
+    Ok(findings)
+}
+
+pub fn docsrs_metadata(doc: &DocumentMut) -> Result<Option<DocsRsMetadata>> {
+    let Some(table) = doc
+        .get("package")
+        .and_then(|p| p.get("metadata"))
+        .and_then(|m| m.get("docs"))
+        .and_then(|d| d.get("rs"))
+        .and_then(|r| r.as_table_like())
+    else {
+        return Ok(None);
+    };
+    let mut section = DocumentMut::new();
+    for (key, item) in table.iter() {
+        section.insert(key, item.clone());
+    }
+    let metadata =
+        toml_edit::de::from_str(&section.to_string()).map_err(|e| anyhow!("{}", e.message()))?;
+    Ok(Some(metadata))
+}
+
+/// Problems with `metadata` for the crate described by `doc`; `known_targets`
+/// empty skips the target check.
+pub fn validate(
+    doc: &DocumentMut,
+    metadata: &DocsRsMetadata,
+    known_targets: &[String],
+) -> Vec<String> {
+    let mut problems = vec![];
+    let features = crate_features(doc);
+    let dependencies = dependency_names(doc);
+    for feature in metadata.features.iter() {
+        let exists = match feature.split_once('/') {
+            Some((dependency, _)) => dependencies.contains(dependency.trim_end_matches('?')),
+            None => features.contains(feature.as_str()),
+        };
+        if !exists {
+            problems.push(format!("docs.rs feature `{feature}` does not exist"));
+        }
+    }
+    if metadata.all_features && !metadata.features.is_empty() {
+        problems.push(String::from(
+            "docs.rs `features` has no effect together with `all-features`",
+        ));
+    }
+    if !known_targets.is_empty() {
+        for target in metadata
+            .default_target
+            .iter()
+            .chain(metadata.targets.iter())
+        {
+            if !known_targets.contains(target) {
+                problems.push(format!("docs.rs target `{target}` is not a rustc target"));
+            }
+        }
+    }
+    problems
+}
+
+/// Declared features plus the implicit ones of optional dependencies.
+fn crate_features(doc: &DocumentMut) -> BTreeSet<String> {
+    let mut features: BTreeSet<String> = doc
+        .get("features")
+        .and_then(|f| f.as_table_like())
+        .map(|f| f.iter().map(|(name, _)| name.to_string()).collect())
+        .unwrap_or_default();
+    if let Some(dependencies) = doc.get("dependencies").and_then(|d| d.as_table_like()) {
+        for (name, spec) in dependencies.iter() {
+            if spec.get("optional").and_then(|o| o.as_bool()) == Some(true) {
+                features.insert(name.to_string());
+            }
+        }
+    }
+    features
+}
+
+fn dependency_names(doc: &DocumentMut) -> BTreeSet<String> {
+    DEPENDENCY_TABLES
+        .iter()
+        .filter_map(|table| doc.get(table).and_then(|t| t.as_table_like()))
+        .flat_map(|table| table.iter().map(|(name, _)| name.to_string()))
+        .collect()
+}
+
+fn rustc_targets() -> Result<Vec<String>> {
+    let output = Command::new("rustc")
+        .args(["--print", "target-list"])
+        .output()
+        .map_err(|e| anyhow!("failed to run rustc: {e}"))?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Reproduces the docs.rs build of the crate at `manifest` for its default
+/// target with the given toolchain.
+pub fn simulate(manifest: &Path, metadata: &DocsRsMetadata, toolchain: &str) -> Result<()> {
+    let mut cmd = Command::new("cargo");
+    cmd.arg(format!("+{toolchain}"))
+        .args(doc_args(metadata))
+        .arg("--manifest-path")
+        .arg(manifest)
+        .env("DOCS_RS", "1")
+        .env("RUSTDOCFLAGS", rustdoc_flags(metadata))
+        .env("RUSTFLAGS", metadata.rustc_args.join(" "));
+    let output = cmd
+        .output()
+        .map_err(|e| anyhow!("failed to run cargo doc: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let errors: Vec<_> = stderr
+            .lines()
+            .skip_while(|line| !line.starts_with("error"))
+            .take(10)
+            .collect();
+        return Err(anyhow!("{}", errors.join("\n")));
+    }
+    Ok(())
+}
+
+/// `cargo` arguments docs.rs derives from the metadata.
+pub fn doc_args(metadata: &DocsRsMetadata) -> Vec<String> {
+    let mut args = vec![
+        String::from("doc"),
+        String::from("--no-deps"),
+        String::from("--lib"),
+    ];
+    if metadata.all_features {
+        args.push(String::from("--all-features"));
+    } else if !metadata.features.is_empty() {
+        args.push(String::from("--features"));
+        args.push(metadata.features.join(","));
+    }
+    if metadata.no_default_features {
+        args.push(String::from("--no-default-features"));
+    }
+    if let Some(target) = &metadata.default_target {
+        args.push(String::from("--target"));
+        args.push(target.clone());
+    }
+    args.extend(metadata.cargo_args.iter().cloned());
+    args
+}
+
+fn rustdoc_flags(metadata: &DocsRsMetadata) -> String {
+    let mut flags = vec![String::from("--cfg docsrs")];
+    flags.extend(metadata.rustdoc_args.iter().cloned());
+    flags.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    const MANIFEST: &str = r#"[package]
+name = "a"
+
+[package.metadata.docs.rs]
+features = ["serde", "extra", "log/std"]
+rustdoc-args = ["--generate-link-to-definition"]
+default-target = "x86_64-unknown-linux-gnu"
+targets = ["wasm32-unknown-unknown", "x86_64-apple-darwin-typo"]
+
+[features]
+serde = []
+
+[dependencies]
+log = "0.4"
+"#;
+
+    #[test]
+    fn test_validate() {
+        let doc: DocumentMut = MANIFEST.parse().unwrap();
+        let metadata = docsrs_metadata(&doc).unwrap().unwrap();
+        let targets = vec![
+            String::from("x86_64-unknown-linux-gnu"),
+            String::from("wasm32-unknown-unknown"),
+        ];
+        assert_eq!(
+            validate(&doc, &metadata, &targets),
+            vec![
+                "docs.rs feature `extra` does not exist",
+                "docs.rs target `x86_64-apple-darwin-typo` is not a rustc target",
+            ]
+        );
+        assert_eq!(
+            doc_args(&metadata),
+            vec![
+                "doc",
+                "--no-deps",
+                "--lib",
+                "--features",
+                "serde,extra,log/std",
+                "--target",
+                "x86_64-unknown-linux-gnu"
+            ]
+        );
+        assert_eq!(
+            rustdoc_flags(&metadata),
+            "--cfg docsrs --generate-link-to-definition"
+        );
+    }
+
+    #[test]
+    fn test_docsrs_metadata_rejects_unknown_keys() {
+        let doc: DocumentMut =
+            "[package]\nname = \"a\"\n\n[package.metadata.docs.rs]\nall_features = true\n"
+                .parse()
+                .unwrap();
+        let e = docsrs_metadata(&doc).unwrap_err();
+        assert!(e.to_string().contains("all_features"), "{e}");
+        assert_eq!(
+            docsrs_metadata(&"[package]\nname = \"a\"\n".parse().unwrap()).unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,154 @@
+use {
+    super::{find_workspaces, metadata::is_publishable, relative, Finding},
+    crate::{
+        config::Config,
+        utils::cargo::{dependency_tables, expand_workspace_members, read_manifest, TableScope},
+    },
+    anyhow::Result,
+    std::path::{Path, PathBuf},
+    toml_edit::Item,
+};
+
+/// Tables cargo keeps when publishing; git-only dev-dependencies are stripped.
+const PUBLISHED_TABLES: [&str; 2] = ["dependencies", "build-dependencies"];
+
+/// Checks publishable crates have no git dependency without a registry
+/// version, which crates.io rejects.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let config = Config::load()?;
+    let fixtures = config.fixture_dirs();
+    find_git_deps(root, &fixtures, &config.lint.unpublished)
+}
+
+pub fn find_git_deps(
+    root: &Path,
+    fixtures: &[PathBuf],
+    unpublished: &[String],
+) -> Result<Vec<Finding>> {
+    let mut findings = vec![];
+    for workspace in find_workspaces(root)? {
+        if fixtures.iter().any(|f| workspace.dir.starts_with(f)) {
+            continue;
+        }
+        let workspace_deps = workspace
+            .doc
+            .get("workspace")
+            .and_then(|w| w.get("dependencies"));
+        let shared = workspace
+            .doc
+            .get("workspace")
+            .and_then(|w| w.get("package"));
+        for member in expand_workspace_members(&workspace.dir, &workspace.doc) {
+            let manifest = member.join("Cargo.toml");
+            let Ok(doc) = read_manifest(&manifest) else {
+                continue;
+            };
+            let Some(package) = doc.get("package") else {
+                continue;
+            };
+            let name = package.get("name").and_then(|n| n.as_str()).unwrap_or("");
+            if !is_publishable(package, shared) || unpublished.iter().any(|u| u == name) {
+                continue;
+            }
+
+            for table in dependency_tables(&doc) {
+                if !matches!(table.scope, TableScope::Package | TableScope::Target)
+                    || !PUBLISHED_TABLES.contains(&table.kind)
+                {
+                    continue;
+                }
+                let (table, item) = (&table.name, table.item);
+                let Some(dependencies) = item.as_table_like() else {
+                    continue;
+                };
+                for (key, spec) in dependencies.iter() {
+                    let inherits = spec.get("workspace").and_then(|w| w.as_bool()) == Some(true);
+                    let message = if inherits {
+                        let inherited = workspace_deps.and_then(|deps| deps.get(key));
+                        if !inherited.is_some_and(is_git_only) {
+                            continue;
+                        }
+                        format!(
+                            "[{table}] {key} inherits a git dependency without a version from workspace.dependencies, crates.io rejects it"
+                        )
+                    } else if is_git_only(spec) {
+                        format!(
+                            "[{table}] {key} is a git dependency without a version, crates.io rejects it"
+                        )
+                    } else {
+                        continue;
+                    };
+                    findings.push(Finding::new(relative(root, &manifest), message));
+                }
+            }
+        }
+    }
+    findings.sort();
+    Ok(findings)
+}
+
+/// A `git = "..."` dependency cargo cannot fall back to a registry version for.
+fn is_git_only(spec: &Item) -> bool {
+    spec.get("git").is_some() && spec.get("version").is_none()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::fs};
+
+    #[test]
+    fn test_find_git_deps() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            r#"[workspace]
+members = ["a", "b", "c", "d"]
+
+[workspace.dependencies]
+patched = { git = "https://github.com/anza-xyz/patched" }
+pinned = { git = "https://github.com/anza-xyz/pinned", version = "1.0" }
+"#,
+        )
+        .unwrap();
+        let members = [
+            (
+                "a",
+                "[package]\nname = \"a\"\n\n[dependencies]\npatched = { workspace = true }\npinned = { workspace = true }\nfoo = { git = \"https://github.com/foo/foo\", branch = \"main\" }\n\n[dev-dependencies]\nbar = { git = \"https://github.com/foo/bar\" }\n",
+            ),
+            (
+                "b",
+                "[package]\nname = \"b\"\npublish = false\n\n[dependencies]\npatched.workspace = true\n",
+            ),
+            (
+                "c",
+                "[package]\nname = \"c\"\n\n[target.'cfg(unix)'.build-dependencies]\nbaz = { git = \"https://github.com/foo/baz\" }\n",
+            ),
+            (
+                "d",
+                "[package]\nname = \"d\"\n\n[dependencies]\npatched.workspace = true\n",
+            ),
+        ];
+        for (name, manifest) in members {
+            fs::create_dir(dir.path().join(name)).unwrap();
+            fs::write(dir.path().join(name).join("Cargo.toml"), manifest).unwrap();
+        }
+
+        assert_eq!(
+            find_git_deps(dir.path(), &[], &[String::from("d")]).unwrap(),
+            vec![
+                Finding::new(
+                    "a/Cargo.toml",
+                    "[dependencies] foo is a git dependency without a version, crates.io rejects it"
+                ),
+                Finding::new(
+                    "a/Cargo.toml",
+                    "[dependencies] patched inherits a git dependency without a version from workspace.dependencies, crates.io rejects it"
+                ),
+                Finding::new(
+                    "c/Cargo.toml",
+                    "[target.cfg(unix).build-dependencies] baz is a git dependency without a version, crates.io rejects it"
+                ),
+            ]
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use {
+    super::{find_workspaces, relative, Finding},
+    crate::{
+        config::Config,
+        utils::{
+            cargo::{expand_workspace_members, read_manifest},
+            vfs::{FileSystem, RealFs},
+        },
+    },
+    anyhow::{Context, Result},
+    std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    },
+    toml_edit::{value, DocumentMut, Item, Table},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub manifest: PathBuf,
+    pub message: String,
+    /// Whether `--fix` adds `lints.workspace = true`; crates with local lints
+    /// are left for a human, dropping them could loosen the policy.
+    pub fixable: bool,
+}
+
+/// Checks members of workspaces with `[workspace.lints]` inherit them and
+/// only override lints listed in `lint.lint-overrides`.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let config = Config::load()?;
+    let problems = find_problems(root, &config.fixture_dirs(), &config.lint.lint_overrides)?;
+    Ok(problems
+        .iter()
+        .map(|problem| Finding::new(relative(root, &problem.manifest), &problem.message))
+        .collect())
+}
+
+/// Adds `lints.workspace = true` to members without a `[lints]` table,
+/// returning how many manifests were changed.
+pub fn fix(root: &Path) -> Result<usize> {
+    let config = Config::load()?;
+    let problems = find_problems(root, &config.fixture_dirs(), &config.lint.lint_overrides)?;
+    apply_fixes(&RealFs, &problems)
+}
+
+pub fn apply_fixes(fs: &impl FileSystem, problems: &[Problem]) -> Result<usize> {
+    let mut fixed = 0usize;
+    for problem in problems.iter().filter(|p| p.fixable) {
+        let mut doc: DocumentMut = fs
+            .read_to_string(&problem.manifest)?
+            .parse()
+            .context(format!("failed to parse {}", problem.manifest.display()))?;
+        let mut lints = Table::new();
+        lints.insert("workspace", value(true));
+        doc.insert("lints", Item::Table(lints));
+        fs.write(&problem.manifest, &doc.to_string())?;
+        fixed = fixed.saturating_add(1);
+    }
+    Ok(fixed)
+}
+
+pub fn find_problems(
+    root: &Path,
+    fixtures: &[PathBuf],
+    overrides: &BTreeMap<String, Vec<String>>,
+) -> Result<Vec<Problem>> {
+    let mut problems = vec![];
+    for workspace in find_workspaces(root)? {
+        if fixtures.iter().any(|f| workspace.dir.starts_with(f)) {
+            continue;
+        }
+        let has_lints = workspace
+            .doc
+            .get("workspace")
+            .and_then(|w| w.get("lints"))
+            .is_some();
+        if !has_lints {
+            continue;
+        }
+        for member in expand_workspace_members(&workspace.dir, &workspace.doc) {
+            let manifest = member.join("Cargo.toml");
+            let Ok(doc) = read_manifest(&manifest) else {
+                continue;
+            };
+            let Some(name) = doc
+                .get("package")
+                .and_then(|p| p.get("name"))
+                .and_then(|n| n.as_str())
+            else {
+                continue;
+            };
+            let Some(lints) = doc.get("lints").and_then(|l| l.as_table_like()) else {
+                problems.push(Problem {
+                    manifest,
+                    message: String::from(
+                        "does not inherit [workspace.lints], add lints.workspace = true",
+                    ),
+                    fixable: true,
+                });
+                continue;
+            };
+            if lints.get("workspace").and_then(|w| w.as_bool()) == Some(true) {
+                continue;
+            }
+            let allowed = overrides.get(name).map(Vec::as_slice).unwrap_or_default();
+            for (tool, table) in lints.iter() {
+                let Some(table) = table.as_table_like() else {
+                    continue;
+                };
+                for (lint, _) in table.iter() {
+                    let key = format!("{tool}::{lint}");
+                    if allowed.iter().any(|a| a == "*" || *a == key) {
+                        continue;
+                    }
+                    problems.push(Problem {
+                        manifest: manifest.clone(),
+                        message: format!(
+                            "sets lints.{tool}.{lint} instead of inheriting [workspace.lints], allow it with lint.lint-overrides.{name} = [\"{key}\"]"
+                        ),
+                        fixable: false,
+                    });
+                }
+            }
+        }
+    }
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::utils::vfs::MemoryFs, pretty_assertions::assert_eq, std::fs};
+
+    #[test]
+    fn test_find_problems() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"a\", \"b\", \"c\", \"d\"]\n\n[workspace.lints.clippy]\narithmetic_side_effects = \"deny\"\n",
+        );
+        write(
+            "a/Cargo.toml",
+            "[package]\nname = \"a\"\n\n[lints]\nworkspace = true\n",
+        );
+        write("b/Cargo.toml", "[package]\nname = \"b\"\n");
+        write(
+            "c/Cargo.toml",
+            "[package]\nname = \"c\"\n\n[lints.clippy]\narithmetic_side_effects = \"allow\"\n\n[lints.rust]\nunsafe_code = \"deny\"\n",
+        );
+        write(
+            "d/Cargo.toml",
+            "[package]\nname = \"d\"\n\n[lints.rust]\nunsafe_code = \"allow\"\n",
+        );
+        let overrides = BTreeMap::from([
+            (String::from("c"), vec![String::from("rust::unsafe_code")]),
+            (String::from("d"), vec![String::from("*")]),
+        ]);
+
+        let problems = find_problems(root, &[], &overrides).unwrap();
+        assert_eq!(
+            problems
+                .iter()
+                .map(|p| (relative(root, &p.manifest), p.fixable))
+                .collect::<Vec<_>>(),
+            vec![
+                (PathBuf::from("b/Cargo.toml"), true),
+                (PathBuf::from("c/Cargo.toml"), false),
+            ]
+        );
+        assert_eq!(
+            problems[1].message,
+            "sets lints.clippy.arithmetic_side_effects instead of inheriting [workspace.lints], allow it with lint.lint-overrides.c = [\"clippy::arithmetic_side_effects\"]"
+        );
+    }
+
+    #[test]
+    fn test_apply_fixes() {
+        let fs = MemoryFs::new([
+            ("b/Cargo.toml", "[package]\nname = \"b\"\n"),
+            ("c/Cargo.toml", "[package]\nname = \"c\"\n"),
+        ]);
+        let problem = |manifest: &str, fixable: bool| Problem {
+            manifest: PathBuf::from(manifest),
+            message: String::new(),
+            fixable,
+        };
+        let fixed = apply_fixes(
+            &fs,
+            &[
+                problem("b/Cargo.toml", true),
+                problem("c/Cargo.toml", false),
+            ],
+        )
+        .unwrap();
+        assert_eq!(fixed, 1);
+        assert_eq!(
+            fs.get("b/Cargo.toml").unwrap(),
+            "[package]\nname = \"b\"\n\n[lints]\nworkspace = true\n"
+        );
+        assert_eq!(fs.get("c/Cargo.toml").unwrap(), "[package]\nname = \"c\"\n");
+    }
+}
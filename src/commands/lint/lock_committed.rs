@@ -0,0 +1,264 @@
+use {
+    super::{find_workspaces, relative, Finding},
+    crate::{
+        config::Config,
+        utils::{
+            cargo::{dependency_tables, expand_workspace_members, read_manifest, TableScope},
+            git::git_in,
+        },
+    },
+    anyhow::{Context, Result},
+    semver::{Version, VersionReq},
+    std::{collections::HashMap, fs, path::Path},
+    toml_edit::DocumentMut,
+};
+
+/// Checks every workspace has a committed Cargo.lock that is in sync with
+/// its manifests.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let config = Config::load()?;
+    let fixtures = config.fixture_dirs();
+
+    let mut findings = vec![];
+    for workspace in find_workspaces(root)? {
+        if fixtures.iter().any(|f| workspace.dir.starts_with(f)) {
+            continue;
+        }
+        let lock = workspace.dir.join("Cargo.lock");
+        let path = relative(root, &lock);
+        if !lock.exists() {
+            findings.push(Finding::new(
+                path,
+                "Cargo.lock is missing, run `cargo generate-lockfile` and commit it",
+            ));
+            continue;
+        }
+        if git_in(&workspace.dir, &["check-ignore", "--quiet", "Cargo.lock"]).is_ok() {
+            findings.push(Finding::new(
+                &path,
+                "Cargo.lock is ignored by git, drop it from .gitignore and commit it",
+            ));
+        } else if git_in(
+            &workspace.dir,
+            &["ls-files", "--error-unmatch", "Cargo.lock"],
+        )
+        .is_err()
+        {
+            findings.push(Finding::new(&path, "Cargo.lock is not committed"));
+        }
+
+        let content =
+            fs::read_to_string(&lock).context(format!("failed to read {}", lock.display()))?;
+        let Ok(lockfile) = content.parse::<DocumentMut>() else {
+            findings.push(Finding::new(path, "Cargo.lock does not parse"));
+            continue;
+        };
+        for problem in stale_entries(&workspace.dir, &workspace.doc, &lockfile) {
+            findings.push(Finding::new(
+                &path,
+                format!("stale, {problem}, run `cargo update --workspace`"),
+            ));
+        }
+    }
+    findings.sort();
+    Ok(findings)
+}
+
+/// Manifest contents the lockfile does not reflect: members at another
+/// version and dependencies with no locked version matching the requirement.
+pub fn stale_entries(
+    workspace_dir: &Path,
+    workspace_doc: &DocumentMut,
+    lockfile: &DocumentMut,
+) -> Vec<String> {
+    let mut locked: HashMap<&str, Vec<(&str, bool)>> = HashMap::new();
+    if let Some(packages) = lockfile.get("package").and_then(|p| p.as_array_of_tables()) {
+        for package in packages.iter() {
+            let (Some(name), Some(version)) = (
+                package.get("name").and_then(|n| n.as_str()),
+                package.get("version").and_then(|v| v.as_str()),
+            ) else {
+                continue;
+            };
+            let is_local = package.get("source").is_none();
+            locked.entry(name).or_default().push((version, is_local));
+        }
+    }
+    let shared = workspace_doc.get("workspace");
+    let workspace_version = shared
+        .and_then(|w| w.get("package"))
+        .and_then(|p| p.get("version"))
+        .and_then(|v| v.as_str());
+    let workspace_deps = shared.and_then(|w| w.get("dependencies"));
+
+    let mut problems = vec![];
+    for member in expand_workspace_members(workspace_dir, workspace_doc) {
+        let Ok(doc) = read_manifest(&member.join("Cargo.toml")) else {
+            continue;
+        };
+        let Some(package) = doc.get("package") else {
+            continue;
+        };
+        let Some(name) = package.get("name").and_then(|n| n.as_str()) else {
+            continue;
+        };
+        let version = match package.get("version") {
+            Some(version) if version.get("workspace").is_some() => workspace_version,
+            Some(version) => version.as_str(),
+            // cargo locks unversioned packages as 0.0.0
+            None => Some("0.0.0"),
+        };
+        if let Some(version) = version {
+            let entries = locked.get(name).map(Vec::as_slice).unwrap_or_default();
+            if !entries.iter().any(|(v, local)| *local && *v == version) {
+                problems.push(format!("{name} {version} is not locked"));
+            }
+        }
+
+        for table in dependency_tables(&doc) {
+            // the workspace tables only matter through the members inheriting them
+            if !matches!(table.scope, TableScope::Package | TableScope::Target) {
+                continue;
+            }
+            let (table, item) = (&table.name, table.item);
+            let Some(dependencies) = item.as_table_like() else {
+                continue;
+            };
+            for (key, spec) in dependencies.iter() {
+                let spec = if spec.get("workspace").and_then(|w| w.as_bool()) == Some(true) {
+                    match workspace_deps.and_then(|deps| deps.get(key)) {
+                        Some(spec) => spec,
+                        None => continue,
+                    }
+                } else {
+                    spec
+                };
+                let dependency = spec.get("package").and_then(|p| p.as_str()).unwrap_or(key);
+                let requirement = spec
+                    .as_str()
+                    .or_else(|| spec.get("version").and_then(|v| v.as_str()));
+                let Some(entries) = locked.get(dependency) else {
+                    problems.push(format!("{name} [{table}] {dependency} is not locked"));
+                    continue;
+                };
+                let Some(req) = requirement.and_then(|r| VersionReq::parse(r).ok()) else {
+                    continue;
+                };
+                let matches = entries.iter().any(|(version, _)| {
+                    Version::parse(version).is_ok_and(|version| req.matches(&version))
+                });
+                if !matches {
+                    problems.push(format!(
+                        "{name} [{table}] {dependency} {req} matches no locked version"
+                    ));
+                }
+            }
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    const LOCKFILE: &str = r#"version = 4
+
+[[package]]
+name = "a"
+version = "1.0.0"
+dependencies = ["b", "serde"]
+
+[[package]]
+name = "b"
+version = "1.0.0"
+
+[[package]]
+name = "serde"
+version = "1.0.200"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    #[test]
+    fn test_stale_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = r#"[workspace]
+members = ["a", "b"]
+
+[workspace.package]
+version = "1.1.0"
+
+[workspace.dependencies]
+serde = "1.0.210"
+"#;
+        fs::write(dir.path().join("Cargo.toml"), workspace).unwrap();
+        for (name, manifest) in [
+            (
+                "a",
+                "[package]\nname = \"a\"\nversion = \"1.0.0\"\n\n[dependencies]\nb = { path = \"../b\" }\nserde.workspace = true\n\n[dev-dependencies]\ntempfile = \"3\"\n",
+            ),
+            ("b", "[package]\nname = \"b\"\nversion.workspace = true\n"),
+        ] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+            fs::write(dir.path().join(name).join("Cargo.toml"), manifest).unwrap();
+        }
+        assert_eq!(
+            stale_entries(
+                dir.path(),
+                &workspace.parse().unwrap(),
+                &LOCKFILE.parse().unwrap()
+            ),
+            vec![
+                "a [dependencies] serde ^1.0.210 matches no locked version",
+                "a [dev-dependencies] tempfile is not locked",
+                "b 1.1.0 is not locked",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lock_tracking() {
+        let dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| git_in(dir.path(), args).unwrap();
+        git(&["init", "--quiet"]);
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"a\"]\n",
+        )
+        .unwrap();
+        fs::create_dir(dir.path().join("a")).unwrap();
+        fs::write(
+            dir.path().join("a/Cargo.toml"),
+            "[package]\nname = \"a\"\nversion = \"1.0.0\"\n",
+        )
+        .unwrap();
+        let lint_messages = || -> Vec<String> {
+            lint(dir.path())
+                .unwrap()
+                .into_iter()
+                .map(|f| f.message)
+                .collect()
+        };
+        assert_eq!(
+            lint_messages(),
+            ["Cargo.lock is missing, run `cargo generate-lockfile` and commit it"]
+        );
+
+        fs::write(
+            dir.path().join("Cargo.lock"),
+            "version = 4\n\n[[package]]\nname = \"a\"\nversion = \"1.0.0\"\n",
+        )
+        .unwrap();
+        assert_eq!(lint_messages(), ["Cargo.lock is not committed"]);
+
+        fs::write(dir.path().join(".gitignore"), "Cargo.lock\n").unwrap();
+        assert_eq!(
+            lint_messages(),
+            ["Cargo.lock is ignored by git, drop it from .gitignore and commit it"]
+        );
+
+        fs::remove_file(dir.path().join(".gitignore")).unwrap();
+        git(&["add", "Cargo.lock"]);
+        assert_eq!(lint_messages(), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,70 @@
+use {
+    super::{find_manifests, relative, Finding},
+    crate::utils::cargo::{dependency_tables, read_manifest, TableScope},
+    anyhow::Result,
+    std::path::Path,
+    toml_edit::DocumentMut,
+};
+
+/// Checks every manifest parses and keeps its dependency tables sorted.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let mut findings = vec![];
+    for manifest in find_manifests(root)? {
+        let path = relative(root, &manifest);
+        match read_manifest(&manifest) {
+            Ok(doc) => {
+                for (table, unsorted) in unsorted_dependency_tables(&doc) {
+                    findings.push(Finding::new(
+                        path.clone(),
+                        format!("[{table}] is not sorted, `{unsorted}` is out of order"),
+                    ));
+                }
+            }
+            Err(e) => findings.push(Finding::new(path, format!("{:#}", e.root_cause()))),
+        }
+    }
+    Ok(findings)
+}
+
+fn unsorted_dependency_tables(doc: &DocumentMut) -> Vec<(String, String)> {
+    dependency_tables(doc)
+        .into_iter()
+        // patch tables are keyed by source, not sorted by convention
+        .filter(|table| table.scope != TableScope::Patch)
+        .filter_map(|table| {
+            let keys: Vec<&str> = table.item.as_table_like()?.iter().map(|(k, _)| k).collect();
+            keys.windows(2)
+                .find(|pair| pair[0] > pair[1])
+                .map(|pair| (table.name, pair[1].to_string()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::fs};
+
+    #[test]
+    fn test_manifest_fmt() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let root = root_dir.path();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[package]\nname = \"a\"\n\n[dependencies]\nserde = \"1\"\nanyhow = \"1\"\n\n[target.'cfg(unix)'.dev-dependencies]\nlibc = \"0.2\"\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("broken")).unwrap();
+        fs::write(root.join("broken/Cargo.toml"), "[package\n").unwrap();
+
+        let findings = lint(root).unwrap();
+        assert_eq!(findings.len(), 2);
+        assert_eq!(
+            findings[0],
+            Finding::new(
+                "Cargo.toml",
+                "[dependencies] is not sorted, `anyhow` is out of order"
+            )
+        );
+        assert_eq!(findings[1].path, Path::new("broken/Cargo.toml"));
+    }
+}
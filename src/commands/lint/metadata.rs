@@ -0,0 +1,442 @@
+use {
+    super::{find_workspaces, relative, Finding},
+    crate::{
+        config::Config,
+        utils::{
+            cargo::{expand_workspace_members, read_manifest},
+            vfs::{FileSystem, RealFs},
+        },
+    },
+    anyhow::{Context, Result},
+    std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    },
+    toml_edit::{DocumentMut, Item, Table, Value},
+};
+
+/// Fields every published crate needs for a useful crates.io page.
+pub const REQUIRED_FIELDS: &[&str] = &["description", "license", "repository", "rust-version"];
+
+/// `[package]` fields that can come from `[workspace.package]`.
+pub const INHERITABLE_FIELDS: &[&str] = &[
+    "authors",
+    "categories",
+    "edition",
+    "homepage",
+    "keywords",
+    "license",
+    "repository",
+    "rust-version",
+];
+
+/// crates.io limits, see <https://doc.rust-lang.org/cargo/reference/manifest.html>.
+const MAX_KEYWORDS: usize = 5;
+const MAX_KEYWORD_LENGTH: usize = 20;
+const MAX_CATEGORIES: usize = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub manifest: PathBuf,
+    pub message: String,
+    /// Field `--fix` replaces with `<field>.workspace = true`.
+    pub inherit: Option<String>,
+}
+
+/// Checks publishable crates carry complete crates.io metadata.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let problems = find_problems(root, &Config::load()?.fixture_dirs())?;
+    Ok(problems
+        .iter()
+        .map(|problem| Finding::new(relative(root, &problem.manifest), &problem.message))
+        .collect())
+}
+
+/// Inherits fields from `[workspace.package]` where that fixes a problem,
+/// returning how many fields were changed.
+pub fn fix(root: &Path) -> Result<usize> {
+    let problems = find_problems(root, &Config::load()?.fixture_dirs())?;
+    let mut by_manifest: BTreeMap<&PathBuf, Vec<&str>> = BTreeMap::new();
+    for problem in problems.iter() {
+        if let Some(field) = &problem.inherit {
+            by_manifest
+                .entry(&problem.manifest)
+                .or_default()
+                .push(field);
+        }
+    }
+    let mut fixed = 0usize;
+    for (manifest, fields) in by_manifest {
+        let mut doc = read_manifest(manifest)?;
+        let package = doc["package"].as_table_like_mut().context(format!(
+            "[package] in {} is not a table",
+            manifest.display()
+        ))?;
+        for field in fields {
+            package.insert(field, inherit_item());
+            fixed = fixed.saturating_add(1);
+        }
+        RealFs.write(manifest, &doc.to_string())?;
+    }
+    Ok(fixed)
+}
+
+/// `<field>.workspace = true`
+fn inherit_item() -> Item {
+    let mut table = Table::new();
+    table.set_dotted(true);
+    table.insert("workspace", toml_edit::value(true));
+    Item::Table(table)
+}
+
+pub fn find_problems(root: &Path, fixtures: &[PathBuf]) -> Result<Vec<Problem>> {
+    let mut problems = vec![];
+    for workspace in find_workspaces(root)? {
+        if fixtures.iter().any(|f| workspace.dir.starts_with(f)) {
+            continue;
+        }
+        let shared = workspace
+            .doc
+            .get("workspace")
+            .and_then(|w| w.get("package"));
+        let mut homepages = vec![];
+        for member in expand_workspace_members(&workspace.dir, &workspace.doc) {
+            let manifest = member.join("Cargo.toml");
+            let Ok(doc) = read_manifest(&manifest) else {
+                continue;
+            };
+            let Some(package) = doc.get("package") else {
+                continue;
+            };
+            if !is_publishable(package, shared) {
+                continue;
+            }
+            for (message, inherit) in check_package(package, shared) {
+                problems.push(Problem {
+                    manifest: manifest.clone(),
+                    message,
+                    inherit,
+                });
+            }
+            if let Some(homepage) = resolve(package, shared, "homepage").and_then(|h| h.as_str()) {
+                homepages.push((manifest, homepage.to_string()));
+            }
+        }
+        problems.extend(check_homepages(shared, &homepages));
+    }
+    Ok(problems)
+}
+
+/// A publishable workspace member.
+pub struct PublishableMember {
+    pub name: String,
+    pub dir: PathBuf,
+    pub doc: DocumentMut,
+}
+
+/// Publishable members of every workspace under `root`, skipping the
+/// fixtures and `lint.unpublished`, sorted by name.
+pub fn publishable_members(root: &Path, config: &Config) -> Result<Vec<PublishableMember>> {
+    let fixtures = config.fixture_dirs();
+    let mut members = vec![];
+    for workspace in find_workspaces(root)? {
+        if fixtures.iter().any(|f| workspace.dir.starts_with(f)) {
+            continue;
+        }
+        let shared = workspace
+            .doc
+            .get("workspace")
+            .and_then(|w| w.get("package"));
+        for dir in expand_workspace_members(&workspace.dir, &workspace.doc) {
+            let Ok(doc) = read_manifest(&dir.join("Cargo.toml")) else {
+                continue;
+            };
+            let Some(package) = doc.get("package") else {
+                continue;
+            };
+            let Some(name) = package.get("name").and_then(|n| n.as_str()) else {
+                continue;
+            };
+            if !is_publishable(package, shared) || config.lint.unpublished.iter().any(|u| u == name)
+            {
+                continue;
+            }
+            members.push(PublishableMember {
+                name: name.to_string(),
+                dir,
+                doc,
+            });
+        }
+    }
+    members.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.dir.cmp(&b.dir)));
+    members.dedup_by(|a, b| a.name == b.name && a.dir == b.dir);
+    Ok(members)
+}
+
+pub fn is_publishable(package: &Item, shared: Option<&Item>) -> bool {
+    match resolve(package, shared, "publish") {
+        Some(Value::Boolean(publish)) => *publish.value(),
+        Some(Value::Array(registries)) => !registries.is_empty(),
+        _ => true,
+    }
+}
+
+/// The value of `field`, looked up in `[workspace.package]` when inherited.
+fn resolve<'a>(package: &'a Item, shared: Option<&'a Item>, field: &str) -> Option<&'a Value> {
+    let item = package.get(field)?;
+    if is_inherited(item) {
+        return shared?.get(field)?.as_value();
+    }
+    item.as_value()
+}
+
+fn is_inherited(item: &Item) -> bool {
+    item.get("workspace").and_then(|w| w.as_bool()) == Some(true)
+}
+
+/// Problems of a single `[package]`, each with the field inheriting would fix.
+pub fn check_package(package: &Item, shared: Option<&Item>) -> Vec<(String, Option<String>)> {
+    let mut problems = vec![];
+    let shared_value = |field: &str| shared.and_then(|s| s.get(field)).and_then(|v| v.as_value());
+
+    for field in REQUIRED_FIELDS {
+        let present = package.get(field).is_some()
+            || (*field == "license" && package.get("license-file").is_some());
+        if present {
+            continue;
+        }
+        if shared_value(field).is_some() {
+            problems.push((
+                format!("{field} is missing, inherit it with {field}.workspace = true"),
+                Some(field.to_string()),
+            ));
+        } else {
+            problems.push((format!("{field} is missing"), None));
+        }
+    }
+
+    for field in INHERITABLE_FIELDS {
+        let (Some(own), Some(shared)) = (
+            package.get(field).filter(|i| !is_inherited(i)),
+            shared_value(field),
+        ) else {
+            continue;
+        };
+        if own.as_value().is_some_and(|own| same_value(own, shared)) {
+            problems.push((
+                format!(
+                    "{field} repeats workspace.package.{field}, inherit it with {field}.workspace = true"
+                ),
+                Some(field.to_string()),
+            ));
+        }
+    }
+
+    let keywords = strings(resolve(package, shared, "keywords"));
+    if keywords.len() > MAX_KEYWORDS {
+        problems.push((
+            format!(
+                "{} keywords, crates.io allows at most {MAX_KEYWORDS}",
+                keywords.len()
+            ),
+            None,
+        ));
+    }
+    for keyword in keywords.iter().filter(|k| !is_valid_keyword(k)) {
+        problems.push((
+            format!(
+                "keyword `{keyword}` is rejected by crates.io, use at most {MAX_KEYWORD_LENGTH} ASCII alphanumerics, `_`, `-` or `+` starting with an alphanumeric"
+            ),
+            None,
+        ));
+    }
+    let categories = strings(resolve(package, shared, "categories"));
+    if categories.len() > MAX_CATEGORIES {
+        problems.push((
+            format!(
+                "{} categories, crates.io allows at most {MAX_CATEGORIES}",
+                categories.len()
+            ),
+            None,
+        ));
+    }
+    problems
+}
+
+/// Homepages must agree with `workspace.package.homepage`, or with each other
+/// when the workspace does not set one.
+fn check_homepages(shared: Option<&Item>, homepages: &[(PathBuf, String)]) -> Vec<Problem> {
+    let workspace_homepage = shared
+        .and_then(|s| s.get("homepage"))
+        .and_then(|h| h.as_str());
+    let expected = match workspace_homepage {
+        Some(homepage) => homepage.to_string(),
+        None => {
+            let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+            for (_, homepage) in homepages {
+                let count = counts.entry(homepage).or_default();
+                *count = count.saturating_add(1);
+            }
+            let Some((most_common, _)) = counts.into_iter().max_by_key(|(_, count)| *count) else {
+                return vec![];
+            };
+            most_common.to_string()
+        }
+    };
+    homepages
+        .iter()
+        .filter(|(_, homepage)| *homepage != expected)
+        .map(|(manifest, homepage)| Problem {
+            manifest: manifest.clone(),
+            message: format!("homepage {homepage} differs from {expected} used by the workspace"),
+            inherit: workspace_homepage.map(|_| String::from("homepage")),
+        })
+        .collect()
+}
+
+fn strings(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(|v| v.as_array())
+        .map(|a| {
+            a.iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn same_value(a: &Value, b: &Value) -> bool {
+    match (a.as_str(), b.as_str()) {
+        (Some(a), Some(b)) => a == b,
+        _ => a.is_array() && b.is_array() && strings(Some(a)) == strings(Some(b)),
+    }
+}
+
+pub fn is_valid_keyword(keyword: &str) -> bool {
+    keyword.len() <= MAX_KEYWORD_LENGTH
+        && keyword
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphanumeric())
+        && keyword
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+'))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::fs, toml_edit::DocumentMut};
+
+    const WORKSPACE: &str = r#"[workspace]
+members = ["a", "b", "c"]
+
+[workspace.package]
+license = "Apache-2.0"
+repository = "https://github.com/anza-xyz/agave"
+homepage = "https://anza.xyz/"
+rust-version = "1.80"
+"#;
+
+    #[test]
+    fn test_check_package() {
+        let workspace: DocumentMut = WORKSPACE.parse().unwrap();
+        let shared = workspace["workspace"].get("package");
+        let doc: DocumentMut = r#"[package]
+name = "a"
+license = "Apache-2.0"
+repository.workspace = true
+keywords = ["solana", "a-keyword-far-too-long", "-dash", "b", "c", "d"]
+"#
+        .parse()
+        .unwrap();
+        let messages: Vec<(String, Option<String>)> = check_package(&doc["package"], shared);
+        assert_eq!(
+            messages,
+            vec![
+                (String::from("description is missing"), None),
+                (
+                    String::from(
+                        "rust-version is missing, inherit it with rust-version.workspace = true"
+                    ),
+                    Some(String::from("rust-version"))
+                ),
+                (
+                    String::from(
+                        "license repeats workspace.package.license, inherit it with license.workspace = true"
+                    ),
+                    Some(String::from("license"))
+                ),
+                (
+                    String::from("6 keywords, crates.io allows at most 5"),
+                    None
+                ),
+                (
+                    String::from("keyword `a-keyword-far-too-long` is rejected by crates.io, use at most 20 ASCII alphanumerics, `_`, `-` or `+` starting with an alphanumeric"),
+                    None
+                ),
+                (
+                    String::from("keyword `-dash` is rejected by crates.io, use at most 20 ASCII alphanumerics, `_`, `-` or `+` starting with an alphanumeric"),
+                    None
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fix_inherits_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Cargo.toml"), WORKSPACE).unwrap();
+        let members = [
+            (
+                "a",
+                "[package]\nname = \"a\"\ndescription = \"A\"\nlicense = \"Apache-2.0\"\nrepository.workspace = true\n",
+            ),
+            (
+                "b",
+                "[package]\nname = \"b\"\npublish = false\n",
+            ),
+            (
+                "c",
+                "[package]\nname = \"c\"\ndescription = \"C\"\nlicense-file = \"LICENSE\"\nhomepage = \"https://example.com/\"\nrepository.workspace = true\nrust-version.workspace = true\n",
+            ),
+        ];
+        for (name, manifest) in members {
+            fs::create_dir(dir.path().join(name)).unwrap();
+            fs::write(dir.path().join(name).join("Cargo.toml"), manifest).unwrap();
+        }
+
+        let problems = find_problems(dir.path(), &[]).unwrap();
+        let messages: Vec<(PathBuf, &str)> = problems
+            .iter()
+            .map(|p| (relative(dir.path(), &p.manifest), p.message.as_str()))
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                (
+                    PathBuf::from("a/Cargo.toml"),
+                    "rust-version is missing, inherit it with rust-version.workspace = true"
+                ),
+                (
+                    PathBuf::from("a/Cargo.toml"),
+                    "license repeats workspace.package.license, inherit it with license.workspace = true"
+                ),
+                (
+                    PathBuf::from("c/Cargo.toml"),
+                    "homepage https://example.com/ differs from https://anza.xyz/ used by the workspace"
+                ),
+            ]
+        );
+
+        assert_eq!(fix(dir.path()).unwrap(), 3);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("a/Cargo.toml")).unwrap(),
+            "[package]\nname = \"a\"\ndescription = \"A\"\nlicense.workspace = true\nrepository.workspace = true\nrust-version.workspace = true\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("c/Cargo.toml")).unwrap(),
+            "[package]\nname = \"c\"\ndescription = \"C\"\nlicense-file = \"LICENSE\"\nhomepage.workspace = true\nrepository.workspace = true\nrust-version.workspace = true\n"
+        );
+        assert_eq!(find_problems(dir.path(), &[]).unwrap(), vec![]);
+    }
+}
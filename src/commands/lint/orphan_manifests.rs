@@ -0,0 +1,87 @@
+use {
+    super::{find_manifests, find_workspaces, relative, Finding},
+    crate::{
+        config::Config,
+        utils::cargo::{expand_workspace_members, read_manifest},
+    },
+    anyhow::Result,
+    std::path::{Path, PathBuf},
+};
+
+/// Checks every manifest is a workspace root or a member of one.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let config = Config::load()?;
+    let fixtures = config.fixture_dirs();
+    find_orphans(root, &fixtures)
+}
+
+pub fn find_orphans(root: &Path, fixtures: &[PathBuf]) -> Result<Vec<Finding>> {
+    let members: Vec<PathBuf> = find_workspaces(root)?
+        .iter()
+        .flat_map(|workspace| expand_workspace_members(&workspace.dir, &workspace.doc))
+        .collect();
+
+    let mut findings = vec![];
+    for manifest in find_manifests(root)? {
+        if fixtures.iter().any(|fixture| manifest.starts_with(fixture)) {
+            continue;
+        }
+        // unparsable manifests are reported by manifest-fmt
+        let Ok(doc) = read_manifest(&manifest) else {
+            continue;
+        };
+        if doc.contains_key("workspace") {
+            continue;
+        }
+        let dir = manifest.parent().unwrap_or(root);
+        if members.iter().any(|member| member == dir) {
+            continue;
+        }
+        findings.push(Finding::new(
+            relative(root, &manifest),
+            "neither a workspace root nor a workspace member, so bump, publish and lint skip it",
+        ));
+    }
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::fs};
+
+    #[test]
+    fn test_find_orphans() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let root = root_dir.path();
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"crates/*\", \"tools\"]\n",
+        );
+        write("crates/a/Cargo.toml", "[package]\nname = \"a\"\n");
+        write("tools/Cargo.toml", "[package]\nname = \"tools\"\n");
+        write("scripts/Cargo.toml", "[package]\nname = \"scripts\"\n");
+        write(
+            "standalone/Cargo.toml",
+            "[package]\nname = \"standalone\"\n\n[workspace]\n",
+        );
+        write("fixtures/x/Cargo.toml", "[package]\nname = \"x\"\n");
+
+        let findings: Vec<_> = find_orphans(root, &[root.join("fixtures")])
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                "scripts/Cargo.toml: neither a workspace root nor a workspace member, so bump, publish and lint skip it"
+            ]
+        );
+        assert_eq!(find_orphans(root, &[]).unwrap().len(), 2);
+    }
+}
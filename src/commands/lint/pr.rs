@@ -0,0 +1,191 @@
+use {
+    crate::config::PrConfig,
+    anyhow::{anyhow, Context, Result},
+    serde::Deserialize,
+    std::{fs, path::Path},
+};
+
+/// The parts of a GitHub `pull_request` event payload the PR lint looks at.
+#[derive(Debug, Deserialize)]
+pub struct PullRequestEvent {
+    pub pull_request: PullRequest,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PullRequest {
+    pub title: String,
+    #[serde(default)]
+    pub body: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<Label>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Label {
+    pub name: String,
+}
+
+const CLOSING_KEYWORDS: &[&str] = &[
+    "close", "closes", "closed", "fix", "fixes", "fixed", "resolve", "resolves", "resolved",
+];
+
+pub fn read_event(path: &Path) -> Result<PullRequest> {
+    let content = fs::read_to_string(path).context(format!("failed to read {}", path.display()))?;
+    let event: PullRequestEvent = serde_json::from_str(&content)
+        .context(format!("{} is not a pull_request event", path.display()))?;
+    Ok(event.pull_request)
+}
+
+/// Validates the title, labels and linked issue of a pull request.
+pub fn lint(pr: &PullRequest, config: &PrConfig) -> Vec<String> {
+    let mut problems = vec![];
+    if let Err(e) = check_title(&pr.title, config) {
+        problems.push(e.to_string());
+    }
+
+    for prefix in config.required_label_prefixes.iter() {
+        if !pr.labels.iter().any(|label| label.name.starts_with(prefix)) {
+            problems.push(format!("missing a `{prefix}*` label"));
+        }
+    }
+
+    if config.require_linked_issue && !has_linked_issue(pr.body.as_deref().unwrap_or_default()) {
+        problems.push(String::from(
+            "no linked issue, reference one with e.g. `Fixes #123` in the description",
+        ));
+    }
+    problems
+}
+
+fn check_title(title: &str, config: &PrConfig) -> Result<()> {
+    if title.chars().count() > config.max_title_length {
+        return Err(anyhow!(
+            "title is longer than {} characters",
+            config.max_title_length
+        ));
+    }
+    if config.title_types.is_empty() {
+        return Ok(());
+    }
+    let expected = || {
+        anyhow!(
+            "title must look like `<type>(<scope>): <summary>` with type one of {}",
+            config.title_types.join(", ")
+        )
+    };
+    let (prefix, summary) = title.split_once(": ").ok_or_else(expected)?;
+    let prefix = prefix.strip_suffix('!').unwrap_or(prefix);
+    let kind = match prefix.split_once('(') {
+        Some((kind, scope)) if scope.ends_with(')') && scope.len() > 1 => kind,
+        Some(_) => return Err(expected()),
+        None => prefix,
+    };
+    if !config.title_types.iter().any(|t| t == kind) || summary.trim().is_empty() {
+        return Err(expected());
+    }
+    Ok(())
+}
+
+/// Whether `body` links an issue with a closing keyword (`Fixes #1`,
+/// `Closes org/repo#2`, `Resolves https://github.com/org/repo/issues/3`).
+pub fn has_linked_issue(body: &str) -> bool {
+    let words: Vec<&str> = body.split_whitespace().collect();
+    words.windows(2).any(|pair| {
+        let keyword = pair[0].trim_end_matches(':').to_lowercase();
+        CLOSING_KEYWORDS.contains(&keyword.as_str()) && is_issue_reference(pair[1])
+    })
+}
+
+fn is_issue_reference(word: &str) -> bool {
+    let word = word.trim_end_matches(['.', ',', ')']);
+    let number = match word.split_once("/issues/") {
+        Some((_, number)) => number,
+        None => match word.rsplit_once('#') {
+            Some((_, number)) => number,
+            None => return false,
+        },
+    };
+    !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+}
+
+/// Formats a problem as a GitHub Actions error annotation.
+pub fn annotation(problem: &str) -> String {
+    format!("::error title=PR lint::{problem}")
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn pr(title: &str, body: &str, labels: &[&str]) -> PullRequest {
+        PullRequest {
+            title: title.to_string(),
+            body: Some(body.to_string()),
+            labels: labels
+                .iter()
+                .map(|name| Label {
+                    name: name.to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_check_title() {
+        let config = PrConfig::default();
+        check_title("fix: handle empty plans", &config).unwrap();
+        check_title("feat(publish)!: drop legacy registry", &config).unwrap();
+        assert!(check_title("Handle empty plans", &config).is_err());
+        assert!(check_title("wip: handle empty plans", &config).is_err());
+        assert!(check_title("fix(: broken scope", &config).is_err());
+        assert!(check_title(&format!("fix: {}", "x".repeat(80)), &config).is_err());
+    }
+
+    #[test]
+    fn test_has_linked_issue() {
+        assert!(has_linked_issue("Fixes #123"));
+        assert!(has_linked_issue(
+            "This change\n\ncloses: anza-xyz/agave#42."
+        ));
+        assert!(has_linked_issue(
+            "Resolves https://github.com/anza-xyz/agave/issues/7"
+        ));
+        assert!(!has_linked_issue("Related to #123"));
+        assert!(!has_linked_issue("Fixes the bug"));
+    }
+
+    #[test]
+    fn test_lint() {
+        let config = PrConfig::default();
+        let ok = pr(
+            "fix: handle empty plans",
+            "Fixes #1",
+            &["semver:patch", "changelog:skip"],
+        );
+        assert_eq!(lint(&ok, &config), Vec::<String>::new());
+
+        let bad = pr("fix: handle empty plans", "", &["semver:patch"]);
+        assert_eq!(
+            lint(&bad, &config),
+            vec![
+                "missing a `changelog:*` label",
+                "no linked issue, reference one with e.g. `Fixes #123` in the description",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_event() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("event.json");
+        fs::write(
+            &path,
+            r#"{"action":"opened","pull_request":{"title":"fix: x","body":null,"labels":[{"name":"semver:patch"}]}}"#,
+        )
+        .unwrap();
+        let pr = read_event(&path).unwrap();
+        assert_eq!(pr.title, "fix: x");
+        assert_eq!(pr.body, None);
+        assert_eq!(pr.labels[0].name, "semver:patch");
+    }
+}
@@ -0,0 +1,179 @@
+use {
+    super::{find_workspaces, relative, Finding},
+    crate::{
+        config::Config,
+        utils::cargo::{expand_workspace_members, read_manifest},
+    },
+    anyhow::Result,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        path::{Path, PathBuf},
+    },
+    toml_edit::Item,
+};
+
+/// Checks `[profile.*]` only appears in workspace roots and nested roots
+/// build releases like the root workspace.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let config = Config::load()?;
+    let resolve =
+        |dirs: &[PathBuf]| -> Vec<PathBuf> { dirs.iter().map(|dir| config.resolve(dir)).collect() };
+    find_problems(
+        root,
+        &config.fixture_dirs(),
+        &resolve(&config.lint.independent_profiles),
+    )
+}
+
+pub fn find_problems(
+    root: &Path,
+    fixtures: &[PathBuf],
+    independent: &[PathBuf],
+) -> Result<Vec<Finding>> {
+    let workspaces: Vec<_> = find_workspaces(root)?
+        .into_iter()
+        .filter(|w| !fixtures.iter().any(|f| w.dir.starts_with(f)))
+        .collect();
+    let mut findings = vec![];
+
+    let mut members = BTreeSet::new();
+    for workspace in workspaces.iter() {
+        members.extend(
+            expand_workspace_members(&workspace.dir, &workspace.doc)
+                .into_iter()
+                .filter(|dir| *dir != workspace.dir),
+        );
+    }
+    for member in members {
+        let manifest = member.join("Cargo.toml");
+        let Ok(doc) = read_manifest(&manifest) else {
+            continue;
+        };
+        if doc.contains_key("workspace") {
+            continue;
+        }
+        if let Some(profiles) = doc.get("profile").and_then(|p| p.as_table_like()) {
+            for (profile, _) in profiles.iter() {
+                findings.push(Finding::new(
+                    relative(root, &manifest),
+                    format!(
+                        "[profile.{profile}] is ignored outside the workspace root, move it there"
+                    ),
+                ));
+            }
+        }
+    }
+
+    let Some(main) = workspaces.iter().find(|w| w.dir == root) else {
+        return Ok(findings);
+    };
+    let expected = release_profile(main.doc.as_item());
+    for workspace in workspaces.iter().filter(|w| w.dir != root) {
+        if independent.iter().any(|dir| workspace.dir.starts_with(dir)) {
+            continue;
+        }
+        let differences = differences(&expected, &release_profile(workspace.doc.as_item()));
+        if !differences.is_empty() {
+            findings.push(Finding::new(
+                relative(root, &workspace.manifest),
+                format!(
+                    "[profile.release] differs from {}: {}, align it or list the workspace in lint.independent-profiles",
+                    relative(root, &main.manifest).display(),
+                    differences.join(", ")
+                ),
+            ));
+        }
+    }
+    Ok(findings)
+}
+
+/// `[profile.release]` flattened to dotted keys and their TOML values.
+fn release_profile(doc: &Item) -> BTreeMap<String, String> {
+    let mut settings = BTreeMap::new();
+    if let Some(release) = doc.get("profile").and_then(|p| p.get("release")) {
+        flatten("", release, &mut settings);
+    }
+    settings
+}
+
+fn flatten(prefix: &str, item: &Item, settings: &mut BTreeMap<String, String>) {
+    if let Some(table) = item.as_table_like() {
+        for (key, value) in table.iter() {
+            let key = if prefix.is_empty() {
+                key.to_string()
+            } else {
+                format!("{prefix}.{key}")
+            };
+            flatten(&key, value, settings);
+        }
+    } else if let Some(value) = item.as_value() {
+        settings.insert(prefix.to_string(), value.to_string().trim().to_string());
+    }
+}
+
+/// `key (expected vs actual)` for every setting that differs.
+fn differences(
+    expected: &BTreeMap<String, String>,
+    actual: &BTreeMap<String, String>,
+) -> Vec<String> {
+    let keys: BTreeSet<&String> = expected.keys().chain(actual.keys()).collect();
+    keys.into_iter()
+        .filter(|key| expected.get(*key) != actual.get(*key))
+        .map(|key| {
+            let show = |settings: &BTreeMap<String, String>| {
+                settings
+                    .get(key)
+                    .cloned()
+                    .unwrap_or_else(|| String::from("unset"))
+            };
+            format!("{key} ({} vs {})", show(expected), show(actual))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::fs};
+
+    #[test]
+    fn test_find_problems() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"a\"]\n\n[profile.release]\nlto = \"thin\"\ncodegen-units = 1\n",
+        );
+        write(
+            "a/Cargo.toml",
+            "[package]\nname = \"a\"\n\n[profile.dev]\nopt-level = 1\n",
+        );
+        write(
+            "programs/Cargo.toml",
+            "[workspace]\nmembers = []\n\n[profile.release]\nlto = \"fat\"\ncodegen-units = 1\noverflow-checks = true\n",
+        );
+        write(
+            "platform-tools/Cargo.toml",
+            "[workspace]\nmembers = []\n\n[profile.release]\nopt-level = \"z\"\n",
+        );
+
+        let findings = find_problems(root, &[], &[root.join("platform-tools")]).unwrap();
+        assert_eq!(
+            findings,
+            vec![
+                Finding::new(
+                    "a/Cargo.toml",
+                    "[profile.dev] is ignored outside the workspace root, move it there"
+                ),
+                Finding::new(
+                    "programs/Cargo.toml",
+                    "[profile.release] differs from Cargo.toml: lto (\"thin\" vs \"fat\"), overflow-checks (unset vs true), align it or list the workspace in lint.independent-profiles"
+                ),
+            ]
+        );
+    }
+}
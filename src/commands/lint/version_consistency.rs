@@ -0,0 +1,131 @@
+use {
+    super::{find_workspaces, relative, Finding},
+    crate::utils::cargo::{expand_workspace_members, read_manifest},
+    anyhow::Result,
+    std::path::Path,
+};
+
+/// Checks that members and internal `workspace.dependencies` agree with
+/// `workspace.package.version`.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let mut findings = vec![];
+    for workspace in find_workspaces(root)? {
+        let Some(version) = workspace
+            .doc
+            .get("workspace")
+            .and_then(|ws| ws.get("package"))
+            .and_then(|package| package.get("version"))
+            .and_then(|version| version.as_str())
+        else {
+            continue;
+        };
+
+        if let Some(dependencies) = workspace
+            .doc
+            .get("workspace")
+            .and_then(|ws| ws.get("dependencies"))
+            .and_then(|deps| deps.as_table_like())
+        {
+            for (name, spec) in dependencies.iter() {
+                // only path dependencies are internal crates sharing the version
+                if spec.get("path").is_none() {
+                    continue;
+                }
+                let Some(requirement) = spec.get("version").and_then(|v| v.as_str()) else {
+                    continue;
+                };
+                if requirement.trim_start_matches(['=', '^', '~']) != version {
+                    findings.push(Finding::new(
+                        relative(root, &workspace.manifest),
+                        format!(
+                            "workspace.dependencies.{name} requires {requirement} but the workspace version is {version}"
+                        ),
+                    ));
+                }
+            }
+        }
+
+        for member in expand_workspace_members(&workspace.dir, &workspace.doc) {
+            let manifest = member.join("Cargo.toml");
+            let Ok(doc) = read_manifest(&manifest) else {
+                continue;
+            };
+            if let Some(member_version) = doc
+                .get("package")
+                .and_then(|package| package.get("version"))
+                .and_then(|version| version.as_str())
+            {
+                if member_version != version {
+                    findings.push(Finding::new(
+                        relative(root, &manifest),
+                        format!(
+                            "package.version is {member_version} but the workspace version is {version}"
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+    findings.sort();
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::fs};
+
+    #[test]
+    fn test_version_consistency() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let root = root_dir.path();
+        fs::write(
+            root.join("Cargo.toml"),
+            r#"[workspace]
+members = ["a", "b"]
+
+[workspace.package]
+version = "1.2.3"
+
+[workspace.dependencies]
+a = { path = "a", version = "=1.2.3" }
+b = { path = "b", version = "=1.2.2" }
+serde = "1.0"
+"#,
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::write(
+            root.join("a/Cargo.toml"),
+            "[package]\nname = \"a\"\nversion = { workspace = true }\n",
+        )
+        .unwrap();
+        fs::create_dir_all(root.join("b")).unwrap();
+        fs::write(
+            root.join("b/Cargo.toml"),
+            "[package]\nname = \"b\"\nversion = \"1.0.0\"\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            lint(root).unwrap(),
+            vec![
+                Finding::new(
+                    "Cargo.toml",
+                    "workspace.dependencies.b requires =1.2.2 but the workspace version is 1.2.3"
+                ),
+                Finding::new(
+                    "b/Cargo.toml",
+                    "package.version is 1.0.0 but the workspace version is 1.2.3"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_version_consistency_fixture_is_clean() {
+        assert_eq!(
+            lint(Path::new("tests/dummy-workspace-publish-test")).unwrap(),
+            vec![]
+        );
+    }
+}
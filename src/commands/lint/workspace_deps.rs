@@ -0,0 +1,73 @@
+use {
+    super::{find_workspaces, relative, Finding},
+    crate::utils::cargo::{expand_workspace_members, read_manifest, DEPENDENCY_TABLES},
+    anyhow::Result,
+    std::{collections::HashSet, path::Path},
+};
+
+/// Checks members use `{ workspace = true }` for dependencies the workspace declares.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let mut findings = vec![];
+    for workspace in find_workspaces(root)? {
+        let declared: HashSet<String> = workspace
+            .doc
+            .get("workspace")
+            .and_then(|ws| ws.get("dependencies"))
+            .and_then(|deps| deps.as_table_like())
+            .map(|deps| deps.iter().map(|(name, _)| name.to_string()).collect())
+            .unwrap_or_default();
+        if declared.is_empty() {
+            continue;
+        }
+
+        for member in expand_workspace_members(&workspace.dir, &workspace.doc) {
+            let manifest = member.join("Cargo.toml");
+            let Ok(doc) = read_manifest(&manifest) else {
+                continue;
+            };
+            for table in DEPENDENCY_TABLES {
+                let Some(dependencies) = doc.get(table).and_then(|t| t.as_table_like()) else {
+                    continue;
+                };
+                for (name, spec) in dependencies.iter() {
+                    // renamed dependencies are keyed by the alias
+                    let package = spec.get("package").and_then(|p| p.as_str()).unwrap_or(name);
+                    if !declared.contains(package) {
+                        continue;
+                    }
+                    let inherits = spec
+                        .get("workspace")
+                        .and_then(|w| w.as_bool())
+                        .unwrap_or(false);
+                    if !inherits {
+                        findings.push(Finding::new(
+                            relative(root, &manifest),
+                            format!(
+                                "[{table}] {name} is declared in workspace.dependencies, use `{name} = {{ workspace = true }}`"
+                            ),
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    findings.sort();
+    Ok(findings)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_workspace_deps() {
+        let findings = lint(Path::new("tests/dummy-workspace-crates-update")).unwrap();
+        assert_eq!(
+            findings,
+            vec![Finding::new(
+                "b/Cargo.toml",
+                "[dependencies] solana-frozen-abi is declared in workspace.dependencies, use `solana-frozen-abi = { workspace = true }`"
+            )]
+        );
+    }
+}
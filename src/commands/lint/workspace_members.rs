@@ -0,0 +1,255 @@
+use {
+    super::{find_manifests, find_workspaces, relative, Finding, Workspace},
+    crate::{
+        config::Config,
+        utils::{
+            cargo::{
+                expand_member_pattern, expand_workspace_members, read_manifest, workspace_strings,
+            },
+            portable::slash_path,
+            vfs::{FileSystem, RealFs},
+        },
+    },
+    anyhow::{Context, Result},
+    std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    },
+    toml_edit::Value,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Problem {
+    /// A crate under a source root its enclosing workspace neither lists nor excludes.
+    Unlisted { workspace: PathBuf, dir: PathBuf },
+    /// A `members` entry that matches no crate.
+    DeadPattern { workspace: PathBuf, pattern: String },
+}
+
+/// Checks crates under the configured source roots are workspace members and
+/// every `members` entry points at a crate.
+pub fn lint(root: &Path) -> Result<Vec<Finding>> {
+    let config = Config::load()?;
+    let problems = find_problems(root, &source_roots(&config), &config.fixture_dirs())?;
+    Ok(problems
+        .iter()
+        .map(|problem| to_finding(root, problem))
+        .collect())
+}
+
+/// Adds unlisted crates to and removes dead entries from `workspace.members`.
+pub fn fix(root: &Path) -> Result<usize> {
+    let config = Config::load()?;
+    let problems = find_problems(root, &source_roots(&config), &config.fixture_dirs())?;
+    apply_fixes(&problems)?;
+    Ok(problems.len())
+}
+
+fn source_roots(config: &Config) -> Vec<PathBuf> {
+    config
+        .lint
+        .source_roots
+        .iter()
+        .map(|dir| config.resolve(dir))
+        .collect()
+}
+
+pub fn find_problems(
+    root: &Path,
+    source_roots: &[PathBuf],
+    fixtures: &[PathBuf],
+) -> Result<Vec<Problem>> {
+    let workspaces = find_workspaces(root)?;
+    let mut problems = vec![];
+
+    for workspace in workspaces.iter() {
+        for pattern in workspace_strings(&workspace.doc, "members") {
+            let dirs = expand_member_pattern(&workspace.dir, &pattern);
+            if !dirs.iter().any(|dir| dir.join("Cargo.toml").exists()) {
+                problems.push(Problem::DeadPattern {
+                    workspace: workspace.manifest.clone(),
+                    pattern,
+                });
+            }
+        }
+    }
+
+    for source_root in source_roots.iter().filter(|dir| dir.is_dir()) {
+        for manifest in find_manifests(source_root)? {
+            if fixtures.iter().any(|fixture| manifest.starts_with(fixture)) {
+                continue;
+            }
+            let Ok(doc) = read_manifest(&manifest) else {
+                continue;
+            };
+            if !doc.contains_key("package") || doc.contains_key("workspace") {
+                continue;
+            }
+            let dir = manifest.parent().unwrap_or(source_root).to_path_buf();
+            // like cargo, the closest workspace above the crate claims it
+            let Some(workspace) = workspaces
+                .iter()
+                .filter(|workspace| dir.starts_with(&workspace.dir) && dir != workspace.dir)
+                .max_by_key(|workspace| workspace.dir.components().count())
+            else {
+                continue;
+            };
+            if !is_listed(workspace, &dir) {
+                problems.push(Problem::Unlisted {
+                    workspace: workspace.manifest.clone(),
+                    dir,
+                });
+            }
+        }
+    }
+    Ok(problems)
+}
+
+fn is_listed(workspace: &Workspace, dir: &Path) -> bool {
+    let excluded = workspace_strings(&workspace.doc, "exclude")
+        .iter()
+        .any(|exclude| dir.starts_with(workspace.dir.join(exclude)));
+    excluded
+        || expand_workspace_members(&workspace.dir, &workspace.doc).contains(&dir.to_path_buf())
+}
+
+fn to_finding(root: &Path, problem: &Problem) -> Finding {
+    match problem {
+        Problem::Unlisted { workspace, dir } => Finding::new(
+            relative(root, &dir.join("Cargo.toml")),
+            format!(
+                "not listed in workspace.members of {}, add it or list it in workspace.exclude",
+                relative(root, workspace).display()
+            ),
+        ),
+        Problem::DeadPattern { workspace, pattern } => Finding::new(
+            relative(root, workspace),
+            format!("workspace.members entry `{pattern}` matches no crate"),
+        ),
+    }
+}
+
+fn apply_fixes(problems: &[Problem]) -> Result<()> {
+    let mut by_workspace: BTreeMap<&PathBuf, Vec<&Problem>> = BTreeMap::new();
+    for problem in problems {
+        let (Problem::Unlisted { workspace, .. } | Problem::DeadPattern { workspace, .. }) =
+            problem;
+        by_workspace.entry(workspace).or_default().push(problem);
+    }
+
+    for (manifest, problems) in by_workspace {
+        let workspace_dir = manifest.parent().unwrap_or(Path::new("."));
+        let mut doc = read_manifest(manifest)?;
+        let members = doc["workspace"]["members"]
+            .or_insert(toml_edit::value(toml_edit::Array::new()))
+            .as_array_mut()
+            .context(format!(
+                "workspace.members in {} is not an array",
+                manifest.display()
+            ))?;
+        let entries = |members: &toml_edit::Array| -> Vec<String> {
+            members
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        };
+        let was_sorted = entries(members).windows(2).all(|pair| pair[0] <= pair[1]);
+        // one entry per line stays that way, anything else is normalized inline
+        let multiline_decor = members.get(0).map(|v| v.decor().clone()).filter(|decor| {
+            decor
+                .prefix()
+                .and_then(|p| p.as_str())
+                .is_some_and(|p| p.contains('\n'))
+        });
+
+        for problem in problems {
+            match problem {
+                Problem::DeadPattern { pattern, .. } => {
+                    members.retain(|v| v.as_str() != Some(pattern.as_str()));
+                }
+                Problem::Unlisted { dir, .. } => {
+                    let entry = slash_path(&relative(workspace_dir, dir));
+                    if !entries(members).contains(&entry) {
+                        members.push(entry);
+                    }
+                }
+            }
+        }
+        if was_sorted {
+            members.sort_by(|a: &Value, b: &Value| a.as_str().cmp(&b.as_str()));
+        }
+        match multiline_decor {
+            Some(decor) => {
+                for value in members.iter_mut() {
+                    *value.decor_mut() = decor.clone();
+                }
+            }
+            None => members.fmt(),
+        }
+        RealFs.write(manifest, &doc.to_string())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::fs};
+
+    fn setup(root: &Path) {
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\n    \"crates/a\",\n    \"crates/gone\",\n    \"programs/*\",\n]\nexclude = [\"crates/vendored\"]\n",
+        );
+        write("crates/a/Cargo.toml", "[package]\nname = \"a\"\n");
+        write("crates/b/Cargo.toml", "[package]\nname = \"b\"\n");
+        write("crates/vendored/Cargo.toml", "[package]\nname = \"v\"\n");
+        write("crates/fixtures/x/Cargo.toml", "[package]\nname = \"x\"\n");
+        write("programs/README.md", "");
+    }
+
+    #[test]
+    fn test_find_problems() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let root = root_dir.path();
+        setup(root);
+
+        let problems = find_problems(
+            root,
+            &[root.join("crates")],
+            &[root.join("crates/fixtures")],
+        )
+        .unwrap();
+        let findings: Vec<_> = problems
+            .iter()
+            .map(|problem| to_finding(root, problem).to_string())
+            .collect();
+        assert_eq!(
+            findings,
+            vec![
+                "Cargo.toml: workspace.members entry `crates/gone` matches no crate",
+                "Cargo.toml: workspace.members entry `programs/*` matches no crate",
+                "crates/b/Cargo.toml: not listed in workspace.members of Cargo.toml, add it or list it in workspace.exclude",
+            ]
+        );
+
+        apply_fixes(&problems).unwrap();
+        assert_eq!(
+            fs::read_to_string(root.join("Cargo.toml")).unwrap(),
+            "[workspace]\nmembers = [\n    \"crates/a\",\n    \"crates/b\",\n]\nexclude = [\"crates/vendored\"]\n"
+        );
+        assert_eq!(
+            find_problems(
+                root,
+                &[root.join("crates")],
+                &[root.join("crates/fixtures")]
+            )
+            .unwrap(),
+            vec![]
+        );
+    }
+}
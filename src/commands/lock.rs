@@ -0,0 +1,81 @@
+use {
+    crate::{config::Config, utils::lock},
+    anyhow::{anyhow, Result},
+    clap::{Args, Subcommand},
+    log::info,
+};
+
+#[derive(Subcommand)]
+pub enum LockSubcommand {
+    #[command(about = "Acquire a lock, failing if it is already held")]
+    Acquire,
+    #[command(about = "Release a lock taken by `lock acquire`")]
+    Release {
+        #[arg(
+            long,
+            help = "Token printed by `lock acquire` [default: $XTASK_LOCK_TOKEN]"
+        )]
+        token: Option<String>,
+        #[arg(
+            long,
+            conflicts_with = "token",
+            help = "Release the lock regardless of who holds it"
+        )]
+        force: bool,
+    },
+    #[command(about = "Show who holds a lock")]
+    Status,
+}
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, default_value = "release")]
+    pub scope: String,
+
+    #[command(subcommand)]
+    pub subcommand: LockSubcommand,
+}
+
+impl CommandArgs {
+    pub fn is_mutating(&self) -> bool {
+        !matches!(self.subcommand, LockSubcommand::Status)
+    }
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    match args.subcommand {
+        LockSubcommand::Acquire => {
+            // the lock outlives this process, `lock release` drops it later
+            let lock = lock::acquire(&config, &args.scope)?;
+            info!(
+                "export {}={} so the commands run under the lock can take it",
+                lock::LOCK_TOKEN_ENV,
+                lock.holder().token
+            );
+            println!("{}", serde_json::to_string(lock.holder())?);
+        }
+        LockSubcommand::Release { token, force } => {
+            if force {
+                lock::force_release(&config, &args.scope)?;
+            } else {
+                let token = token
+                    .or_else(|| std::env::var(lock::LOCK_TOKEN_ENV).ok())
+                    .filter(|token| !token.is_empty())
+                    .ok_or_else(|| {
+                        anyhow!(
+                            "pass --token or set {}, or --force to release a lock held by someone else",
+                            lock::LOCK_TOKEN_ENV
+                        )
+                    })?;
+                lock::release(&config, &args.scope, &token)?;
+            }
+            info!("🔓 released lock '{}'", args.scope);
+        }
+        LockSubcommand::Status => match lock::status(&config, &args.scope)? {
+            Some(holder) => println!("{}", serde_json::to_string(&holder)?),
+            None => info!("lock '{}' is free", args.scope),
+        },
+    }
+    Ok(())
+}
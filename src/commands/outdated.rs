@@ -0,0 +1,276 @@
+use {
+    crate::{
+        commands::lint::{find_manifests, relative},
+        config::Config,
+        utils::{
+            cargo::{dependency_tables, read_manifest, TableScope},
+            get_scope_root,
+            registry::{fetch_versions, latest_version, CRATES_IO_INDEX},
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    clap::Args,
+    log::{info, warn},
+    semver::{Version, VersionReq},
+    serde::Serialize,
+    std::{
+        collections::BTreeMap,
+        path::{Path, PathBuf},
+    },
+    toml_edit::DocumentMut,
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, help = "Print the report as JSON")]
+    pub json: bool,
+}
+
+/// How far a requirement is behind the newest release.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Freshness {
+    /// The requirement accepts the newest release.
+    UpToDate,
+    /// A newer release is semver compatible but excluded by a tight requirement.
+    Compatible,
+    /// Reaching the newest release needs a semver-breaking update.
+    Breaking,
+    /// crates.io has no stable release of the crate to compare against.
+    Unknown,
+}
+
+/// One external requirement, shared by every manifest that declares it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutdatedDependency {
+    pub name: String,
+    pub requirement: String,
+    pub manifests: Vec<PathBuf>,
+    pub latest_compatible: Option<String>,
+    pub latest: Option<String>,
+    pub freshness: Freshness,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let root = get_scope_root()?;
+    let config = Config::load()?;
+    let requirements = collect_requirements(&root)?;
+
+    let mut report = vec![];
+    let mut versions = BTreeMap::new();
+    for ((name, requirement), manifests) in requirements {
+        if !versions.contains_key(&name) {
+            let entries = fetch_versions(&config.network, CRATES_IO_INDEX, None, &name)
+                .context(format!("failed to fetch {name} from crates.io"))?;
+            versions.insert(name.clone(), entries);
+        }
+        let Ok(req) = VersionReq::parse(&requirement) else {
+            warn!("skipping {name}: unparsable requirement {requirement:?}");
+            continue;
+        };
+        let entries = &versions[&name];
+        let latest = latest_version(entries, None);
+        let latest_compatible = latest_version(entries, Some(&req));
+        report.push(OutdatedDependency {
+            freshness: classify(&req, latest_compatible.as_ref(), latest.as_ref()),
+            name,
+            requirement,
+            manifests,
+            latest_compatible: latest_compatible.map(|v| v.to_string()),
+            latest: latest.map(|v| v.to_string()),
+        });
+    }
+    report.sort_by(|a, b| b.freshness.cmp(&a.freshness).then(a.name.cmp(&b.name)));
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+    for (freshness, title) in [
+        (Freshness::Unknown, "❓ no stable release on crates.io"),
+        (Freshness::Breaking, "🔴 needs a breaking update"),
+        (Freshness::Compatible, "🟡 held back by the requirement"),
+    ] {
+        let group: Vec<_> = report.iter().filter(|d| d.freshness == freshness).collect();
+        if group.is_empty() {
+            continue;
+        }
+        println!("{title} ({})", group.len());
+        for dependency in group {
+            println!(
+                "  {} {} -> {} (compatible: {}, used in {} manifest(s))",
+                dependency.name,
+                dependency.requirement,
+                dependency.latest.as_deref().unwrap_or("?"),
+                dependency.latest_compatible.as_deref().unwrap_or("none"),
+                dependency.manifests.len()
+            );
+        }
+    }
+    let up_to_date = report
+        .iter()
+        .filter(|d| d.freshness == Freshness::UpToDate)
+        .count();
+    info!(
+        "✅ {up_to_date} of {} requirement(s) up to date",
+        report.len()
+    );
+    let unknown = report
+        .iter()
+        .filter(|d| d.freshness == Freshness::Unknown)
+        .count();
+    if unknown > 0 {
+        return Err(anyhow!(
+            "{unknown} requirement(s) could not be checked against crates.io"
+        ));
+    }
+    Ok(())
+}
+
+pub fn classify(
+    req: &VersionReq,
+    latest_compatible: Option<&Version>,
+    latest: Option<&Version>,
+) -> Freshness {
+    let Some(latest) = latest else {
+        return Freshness::Unknown;
+    };
+    if req.matches(latest) {
+        return Freshness::UpToDate;
+    }
+    match latest_compatible {
+        Some(compatible) if is_semver_compatible(compatible, latest) => Freshness::Compatible,
+        _ => Freshness::Breaking,
+    }
+}
+
+fn is_semver_compatible(a: &Version, b: &Version) -> bool {
+    match (a.major, b.major) {
+        (0, 0) if a.minor == 0 && b.minor == 0 => a.patch == b.patch,
+        (0, 0) => a.minor == b.minor,
+        (a_major, b_major) => a_major == b_major,
+    }
+}
+
+/// crates.io requirements across every manifest under `root`,
+/// keyed by `(crate, requirement)`.
+pub fn collect_requirements(root: &Path) -> Result<BTreeMap<(String, String), Vec<PathBuf>>> {
+    let mut requirements: BTreeMap<(String, String), Vec<PathBuf>> = BTreeMap::new();
+    for manifest in find_manifests(root)? {
+        let Ok(doc) = read_manifest(&manifest) else {
+            continue;
+        };
+        for (name, requirement) in external_requirements(&doc) {
+            requirements
+                .entry((name, requirement))
+                .or_default()
+                .push(relative(root, &manifest));
+        }
+    }
+    Ok(requirements)
+}
+
+fn external_requirements(doc: &DocumentMut) -> Vec<(String, String)> {
+    let mut requirements = vec![];
+    for table in dependency_tables(doc) {
+        if !matches!(table.scope, TableScope::Package | TableScope::Workspace) {
+            continue;
+        }
+        let Some(table) = table.item.as_table_like() else {
+            continue;
+        };
+        for (key, spec) in table.iter() {
+            if let Some(requirement) = spec.as_str() {
+                requirements.push((key.to_string(), requirement.to_string()));
+                continue;
+            }
+            // path, git and inherited dependencies are not on crates.io, nor
+            // are the ones from another registry
+            if ["path", "git", "workspace", "registry", "registry-index"]
+                .iter()
+                .any(|k| spec.get(k).is_some())
+            {
+                continue;
+            }
+            let name = spec.get("package").and_then(|p| p.as_str()).unwrap_or(key);
+            if let Some(requirement) = spec.get("version").and_then(|v| v.as_str()) {
+                requirements.push((name.to_string(), requirement.to_string()));
+            }
+        }
+    }
+    requirements
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_classify() {
+        let req = VersionReq::parse("1.2").unwrap();
+        let v = |s: &str| Version::parse(s).unwrap();
+        assert_eq!(
+            classify(&req, Some(&v("1.5.0")), Some(&v("1.5.0"))),
+            Freshness::UpToDate
+        );
+        assert_eq!(
+            classify(
+                &VersionReq::parse("=1.2.0").unwrap(),
+                Some(&v("1.2.0")),
+                Some(&v("1.5.0"))
+            ),
+            Freshness::Compatible
+        );
+        assert_eq!(
+            classify(&req, Some(&v("1.5.0")), Some(&v("2.0.0"))),
+            Freshness::Breaking
+        );
+        assert_eq!(
+            classify(
+                &VersionReq::parse("0.3").unwrap(),
+                Some(&v("0.3.9")),
+                Some(&v("0.4.0"))
+            ),
+            Freshness::Breaking
+        );
+        assert_eq!(classify(&req, None, None), Freshness::Unknown);
+    }
+
+    #[test]
+    fn test_external_requirements() {
+        let doc: DocumentMut = r#"
+[dependencies]
+serde = "1"
+internal = { version = "2", registry = "corp" }
+log = { version = "0.4", package = "log" }
+"#
+        .parse()
+        .unwrap();
+        assert_eq!(
+            external_requirements(&doc),
+            vec![
+                (String::from("serde"), String::from("1")),
+                (String::from("log"), String::from("0.4")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collect_requirements() {
+        let requirements =
+            collect_requirements(Path::new("tests/dummy-workspace-crates-update")).unwrap();
+        let frozen_abi = requirements
+            .get(&(String::from("solana-frozen-abi"), String::from("3.0.0")))
+            .unwrap();
+        assert_eq!(
+            frozen_abi,
+            &vec![
+                PathBuf::from("Cargo.toml"),
+                PathBuf::from("b/Cargo.toml"),
+                PathBuf::from("sub/f/Cargo.toml"),
+            ]
+        );
+        // path dependencies between members are not external
+        assert!(requirements.keys().all(|(name, _)| name != "a"));
+    }
+}
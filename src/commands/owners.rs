@@ -0,0 +1,83 @@
+use {
+    crate::{
+        config::Config,
+        utils::{maintainers::Maintainers, scope::resolve_manifest_path},
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::MetadataCommand,
+    clap::{Args, Subcommand},
+    log::{info, warn},
+};
+
+#[derive(Subcommand)]
+pub enum OwnersSubcommand {
+    #[command(about = "List the owning teams of every crate and fail on unowned ones")]
+    Report,
+}
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+
+    #[command(subcommand)]
+    pub subcommand: OwnersSubcommand,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    match args.subcommand {
+        OwnersSubcommand::Report => report(&resolve_manifest_path(&args.manifest_path)?, &config),
+    }
+}
+
+fn report(manifest_path: &str, config: &Config) -> Result<()> {
+    let path = config.resolve(&config.owners.maintainers);
+    if !path.exists() {
+        return Err(anyhow!(
+            "{} does not exist, map teams to their crates there",
+            path.display()
+        ));
+    }
+    let maintainers = Maintainers::load(&path)?;
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+    let mut crates: Vec<String> = metadata
+        .workspace_packages()
+        .iter()
+        .map(|p| p.name.to_string())
+        .collect();
+    crates.sort();
+
+    let mut unowned = vec![];
+    for name in crates.iter() {
+        let owners = maintainers.owners(name);
+        if owners.is_empty() {
+            unowned.push(name.as_str());
+        }
+        println!(
+            "{name:<50} {}",
+            if owners.is_empty() {
+                String::from("❌ no owner")
+            } else {
+                owners.join(", ")
+            }
+        );
+    }
+    for (team, pattern) in maintainers.unused_patterns(&crates) {
+        warn!("⚠️ teams.{team}.crates entry {pattern} matches no crate");
+    }
+    if !unowned.is_empty() {
+        return Err(anyhow!(
+            "{} crate(s) have no owner in {}: {}",
+            unowned.len(),
+            path.display(),
+            unowned.join(", ")
+        ));
+    }
+    info!("✅ all {} crate(s) have an owner", crates.len());
+    Ok(())
+}
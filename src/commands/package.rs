@@ -0,0 +1,248 @@
+mod manifests;
+
+use {
+    crate::{
+        config::Config,
+        utils::{
+            cargo::host_target, docker::run_checked, get_current_version, get_git_root_path,
+            get_head_commit, http::apply_cargo_network_env,
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::MetadataCommand,
+    clap::{Args, Subcommand, ValueEnum},
+    log::info,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+};
+
+/// Top-level directory inside the installer tarball, as `agave-install` expects.
+pub const RELEASE_DIR: &str = "solana-release";
+
+#[derive(Subcommand)]
+pub enum PackageSubcommand {
+    #[command(about = "Generate a Homebrew formula, scoop manifest and install script")]
+    Manifests {
+        #[arg(long, help = "Released version [default: the workspace version]")]
+        version: Option<String>,
+        #[arg(long, default_value = "dist", help = "Directory holding the tarballs")]
+        dir: PathBuf,
+        #[arg(long, help = "Commit the formula and manifest to the tap and bucket")]
+        push: bool,
+    },
+}
+
+#[derive(Args)]
+#[command(subcommand_negates_reqs = true)]
+pub struct CommandArgs {
+    #[arg(long, value_enum, required = true)]
+    pub profile: Option<PackageProfile>,
+    #[arg(
+        long,
+        default_value = "stable",
+        help = "Release channel recorded in version.yml"
+    )]
+    pub channel: String,
+    #[arg(long, help = "Target triple, defaults to the host")]
+    pub target: Option<String>,
+    #[arg(long, default_value = "dist")]
+    pub out_dir: PathBuf,
+
+    #[command(subcommand)]
+    pub subcommand: Option<PackageSubcommand>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub enum PackageProfile {
+    /// Validator binaries in the `agave-install` tarball layout, debuginfo split out.
+    Validator,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    if let Some(PackageSubcommand::Manifests { version, dir, push }) = &args.subcommand {
+        let config = Config::load()?;
+        let version = match version {
+            Some(version) => version.clone(),
+            None => get_current_version()?,
+        };
+        return manifests::manifests(&get_git_root_path()?.join(dir), &version, *push, &config);
+    }
+    match args.profile {
+        Some(PackageProfile::Validator) => package_validator(&args),
+        None => Err(anyhow!("--profile is required")),
+    }
+}
+
+fn package_validator(args: &CommandArgs) -> Result<()> {
+    let git_root = get_git_root_path()?;
+    let config = Config::load()?;
+    let binaries = &config.packaging.validator_binaries;
+    if binaries.is_empty() {
+        return Err(anyhow!("packaging.validator-binaries is empty"));
+    }
+
+    let version = get_current_version()?;
+    let commit = get_head_commit(&git_root)?;
+    let target = match &args.target {
+        Some(target) => target.clone(),
+        None => host_target()?,
+    };
+
+    info!("🔨 building {} binaries for {target}", binaries.len());
+    let mut cmd = Command::new("cargo");
+    cmd.current_dir(&git_root)
+        .args(["build", "--release", "--target", &target])
+        // read by the version crates' build scripts
+        .env("CI_COMMIT", &commit)
+        .env("CI_TAG", format!("v{version}"));
+    for binary in binaries.iter() {
+        cmd.args(["--bin", binary]);
+    }
+    apply_cargo_network_env(&mut cmd, &config.network);
+    run_checked(&mut cmd, "cargo build")?;
+
+    let out_dir = git_root.join(&args.out_dir);
+    let release_dir = out_dir.join(RELEASE_DIR);
+    if release_dir.exists() {
+        fs::remove_dir_all(&release_dir)
+            .context(format!("failed to remove {}", release_dir.display()))?;
+    }
+    let bin_dir = release_dir.join("bin");
+    let debug_dir = out_dir.join(format!("{RELEASE_DIR}-debug"));
+    fs::create_dir_all(&bin_dir).context(format!("failed to create {}", bin_dir.display()))?;
+    fs::create_dir_all(&debug_dir).context(format!("failed to create {}", debug_dir.display()))?;
+
+    // honours CARGO_TARGET_DIR and build.target-dir the way the build did
+    let metadata = MetadataCommand::new()
+        .manifest_path(git_root.join("Cargo.toml"))
+        .current_dir(&git_root)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+    let build_dir = metadata
+        .target_directory
+        .as_std_path()
+        .join(&target)
+        .join("release");
+    let windows = target.contains("windows");
+    for binary in binaries.iter() {
+        let file_name = executable_name(binary, windows);
+        let packaged = bin_dir.join(&file_name);
+        fs::copy(build_dir.join(&file_name), &packaged)
+            .context(format!("failed to copy {file_name}"))?;
+        if windows {
+            // MSVC already writes the debuginfo to a separate pdb
+            let pdb = pdb_name(binary);
+            fs::copy(build_dir.join(&pdb), debug_dir.join(&pdb))
+                .context(format!("failed to copy {pdb}"))?;
+        } else {
+            split_debuginfo(&packaged, &debug_dir.join(format!("{binary}.debug")))?;
+        }
+        check_version_output(&packaged, &version)?;
+    }
+    fs::write(
+        release_dir.join("version.yml"),
+        render_version_yml(&args.channel, &commit, &target),
+    )?;
+
+    let tarball_name = format!("{RELEASE_DIR}-{target}.tar.bz2");
+    // relative, so GNU tar never reads `C:` in a Windows path as a remote host
+    run_checked(
+        Command::new("tar")
+            .current_dir(&out_dir)
+            .args(["-cjf", &tarball_name, RELEASE_DIR]),
+        "tar",
+    )?;
+    let tarball = out_dir.join(tarball_name);
+    info!("✅ {}", tarball.display());
+    Ok(())
+}
+
+/// Moves debuginfo into `debug_file` and links it back so debuggers still find it.
+fn split_debuginfo(binary: &Path, debug_file: &Path) -> Result<()> {
+    run_checked(
+        Command::new("objcopy")
+            .arg("--only-keep-debug")
+            .arg(binary)
+            .arg(debug_file),
+        "objcopy --only-keep-debug",
+    )?;
+    run_checked(
+        Command::new("objcopy").arg("--strip-debug").arg(binary),
+        "objcopy --strip-debug",
+    )?;
+    run_checked(
+        Command::new("objcopy")
+            .arg(format!("--add-gnu-debuglink={}", debug_file.display()))
+            .arg(binary),
+        "objcopy --add-gnu-debuglink",
+    )
+}
+
+/// File name of `binary` as cargo builds it for the target.
+fn executable_name(binary: &str, windows: bool) -> String {
+    if windows {
+        format!("{binary}.exe")
+    } else {
+        binary.to_string()
+    }
+}
+
+/// The pdb cargo writes next to a Windows binary, named after the crate
+/// rather than the binary.
+fn pdb_name(binary: &str) -> String {
+    format!("{}.pdb", binary.replace('-', "_"))
+}
+
+fn check_version_output(binary: &Path, version: &str) -> Result<()> {
+    let output = Command::new(binary)
+        .arg("--version")
+        .output()
+        .map_err(|e| anyhow!("failed to run {} --version: {e}", binary.display()))?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    if !output.status.success() || !stdout.contains(version) {
+        return Err(anyhow!(
+            "{} --version printed {:?}, expected version {version}",
+            binary.display(),
+            stdout.trim()
+        ));
+    }
+    info!("  {}", stdout.trim());
+    Ok(())
+}
+
+pub fn render_version_yml(channel: &str, commit: &str, target: &str) -> String {
+    format!("channel: {channel}\ncommit: {commit}\ntarget: {target}\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_render_version_yml() {
+        assert_eq!(
+            render_version_yml("beta", "0fbec9e", "x86_64-unknown-linux-gnu"),
+            "channel: beta\ncommit: 0fbec9e\ntarget: x86_64-unknown-linux-gnu\n"
+        );
+    }
+
+    #[test]
+    fn test_windows_file_names() {
+        assert_eq!(
+            executable_name("agave-validator", true),
+            "agave-validator.exe"
+        );
+        assert_eq!(executable_name("agave-validator", false), "agave-validator");
+        assert_eq!(pdb_name("agave-validator"), "agave_validator.pdb");
+    }
+
+    #[test]
+    fn test_check_version_output() {
+        // `true --version` never reports the expected version
+        assert!(check_version_output(Path::new("true"), "1.2.3").is_err());
+    }
+}
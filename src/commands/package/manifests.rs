@@ -0,0 +1,475 @@
+use {
+    super::RELEASE_DIR,
+    crate::{
+        config::{Config, PackagingConfig},
+        utils::{fs::sha256_file, git::git_in},
+    },
+    anyhow::{anyhow, Context, Result},
+    log::info,
+    serde_json::json,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+};
+
+/// A release tarball built by `package --profile validator`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Artifact {
+    pub target: String,
+    pub url: String,
+    pub sha256: String,
+}
+
+impl Artifact {
+    /// Operating system and architecture of the target: `("Darwin", "arm64")`,
+    /// `("Linux", "x86_64")`, `("Windows", ...)`. ARM is `arm64` everywhere,
+    /// see [`uname_machine`] for what `uname -m` reports.
+    fn platform(&self) -> Option<(&'static str, &'static str)> {
+        let arch = match self.target.split('-').next()? {
+            "x86_64" => "x86_64",
+            "aarch64" => "arm64",
+            _ => return None,
+        };
+        let os = if self.target.contains("apple-darwin") {
+            "Darwin"
+        } else if self.target.contains("linux") {
+            "Linux"
+        } else if self.target.contains("windows") {
+            "Windows"
+        } else {
+            return None;
+        };
+        Some((os, arch))
+    }
+}
+
+/// `uname -m` on `os` for `arch` of [`Artifact::platform`]: Linux reports
+/// 64-bit ARM as `aarch64`, macOS as `arm64`.
+fn uname_machine(os: &str, arch: &'static str) -> &'static str {
+    match (os, arch) {
+        ("Linux", "arm64") => "aarch64",
+        _ => arch,
+    }
+}
+
+/// `value` as a double-quoted Ruby string literal, with `#{` escaped so it
+/// is not interpolated.
+fn ruby_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '#' if chars.peek() == Some(&'{') => quoted.push_str("\\#"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The tarballs of `version` in `dir`, with their download URLs.
+pub fn find_artifacts(dir: &Path, version: &str, repository: &str) -> Result<Vec<Artifact>> {
+    let prefix = format!("{RELEASE_DIR}-");
+    let mut artifacts = vec![];
+    for entry in fs::read_dir(dir).context(format!("failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let Some(target) = name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".tar.bz2"))
+        else {
+            continue;
+        };
+        artifacts.push(Artifact {
+            target: target.to_string(),
+            url: format!("https://github.com/{repository}/releases/download/v{version}/{name}"),
+            sha256: sha256_file(&path)?,
+        });
+    }
+    if artifacts.is_empty() {
+        return Err(anyhow!(
+            "no {RELEASE_DIR}-<target>.tar.bz2 in {}, run package --profile validator first",
+            dir.display()
+        ));
+    }
+    artifacts.sort_by(|a, b| a.target.cmp(&b.target));
+    Ok(artifacts)
+}
+
+/// `agave-cli` -> `AgaveCli`, the class name Homebrew expects.
+fn formula_class(name: &str) -> String {
+    name.split(['-', '_'])
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+pub fn homebrew_formula(
+    packaging: &PackagingConfig,
+    version: &str,
+    artifacts: &[Artifact],
+) -> String {
+    let mut formula = format!(
+        "class {} < Formula\n  desc {}\n  homepage {}\n  version {}\n  license {}\n",
+        formula_class(&packaging.name),
+        ruby_string(&packaging.description),
+        ruby_string(&packaging.homepage),
+        ruby_string(version),
+        ruby_string(&packaging.license)
+    );
+    for (os, block) in [("Darwin", "on_macos"), ("Linux", "on_linux")] {
+        let archs: Vec<(&str, &Artifact)> = artifacts
+            .iter()
+            .filter_map(|artifact| match artifact.platform()? {
+                (artifact_os, "arm64") if artifact_os == os => Some(("on_arm", artifact)),
+                (artifact_os, _) if artifact_os == os => Some(("on_intel", artifact)),
+                _ => None,
+            })
+            .collect();
+        if archs.is_empty() {
+            continue;
+        }
+        formula.push_str(&format!("\n  {block} do\n"));
+        for (arch, artifact) in archs {
+            formula.push_str(&format!(
+                "    {arch} do\n      url \"{}\"\n      sha256 \"{}\"\n    end\n",
+                artifact.url, artifact.sha256
+            ));
+        }
+        formula.push_str("  end\n");
+    }
+    formula.push_str("\n  def install\n    bin.install Dir[\"bin/*\"]\n  end\nend\n");
+    formula
+}
+
+pub fn scoop_manifest(
+    packaging: &PackagingConfig,
+    version: &str,
+    artifacts: &[Artifact],
+) -> String {
+    let mut architecture = serde_json::Map::new();
+    for artifact in artifacts {
+        let arch = match artifact.platform() {
+            Some(("Windows", "x86_64")) => "64bit",
+            Some(("Windows", "arm64")) => "arm64",
+            _ => continue,
+        };
+        architecture.insert(
+            arch.to_string(),
+            json!({ "url": artifact.url, "hash": artifact.sha256 }),
+        );
+    }
+    let bin: Vec<String> = packaging
+        .validator_binaries
+        .iter()
+        .map(|binary| format!("bin\\{binary}.exe"))
+        .collect();
+    let manifest = json!({
+        "version": version,
+        "description": packaging.description,
+        "homepage": packaging.homepage,
+        "license": packaging.license,
+        "architecture": architecture,
+        "extract_dir": RELEASE_DIR,
+        "bin": bin,
+    });
+    format!(
+        "{}\n",
+        serde_json::to_string_pretty(&manifest).unwrap_or_default()
+    )
+}
+
+pub fn install_script(
+    packaging: &PackagingConfig,
+    version: &str,
+    artifacts: &[Artifact],
+) -> String {
+    let mut cases = String::new();
+    for artifact in artifacts {
+        match artifact.platform() {
+            Some((os @ ("Darwin" | "Linux"), arch)) => cases.push_str(&format!(
+                "  {os}-{})\n    url=\"{}\"\n    sha256=\"{}\"\n    ;;\n",
+                uname_machine(os, arch),
+                artifact.url,
+                artifact.sha256
+            )),
+            _ => continue,
+        }
+    }
+    format!(
+        r#"#!/bin/sh
+# Installs {name} v{version}.
+set -eu
+
+case "$(uname -s)-$(uname -m)" in
+{cases}  *)
+    echo "no {name} release for $(uname -s) $(uname -m)" >&2
+    exit 1
+    ;;
+esac
+
+dir="${{{env}_INSTALL_DIR:-$HOME/.local/share/{name}}}"
+tmp="$(mktemp -d)"
+trap 'rm -rf "$tmp"' EXIT
+
+curl --silent --show-error --location --fail --output "$tmp/release.tar.bz2" "$url"
+if command -v sha256sum >/dev/null; then
+  actual="$(sha256sum "$tmp/release.tar.bz2" | cut -d ' ' -f 1)"
+else
+  actual="$(shasum -a 256 "$tmp/release.tar.bz2" | cut -d ' ' -f 1)"
+fi
+if [ "$actual" != "$sha256" ]; then
+  echo "checksum mismatch for $url" >&2
+  exit 1
+fi
+
+tar -xjf "$tmp/release.tar.bz2" -C "$tmp"
+rm -rf "$dir"
+mkdir -p "$(dirname "$dir")"
+mv "$tmp/{release_dir}" "$dir"
+echo "installed {name} v{version} to $dir, add $dir/bin to PATH"
+"#,
+        name = packaging.name,
+        env = packaging.name.to_uppercase().replace('-', "_"),
+        release_dir = RELEASE_DIR,
+    )
+}
+
+/// Writes the Homebrew formula, scoop manifest and install script for the
+/// tarballs of `version` in `dir` next to them, and commits them to the tap
+/// and bucket with `push`.
+pub fn manifests(dir: &Path, version: &str, push: bool, config: &Config) -> Result<()> {
+    let packaging = &config.packaging;
+    let artifacts = find_artifacts(dir, version, &packaging.repository)?;
+    let formula = format!("{}.rb", packaging.name);
+    let scoop = format!("{}.json", packaging.name);
+    let files = [
+        (
+            formula.clone(),
+            homebrew_formula(packaging, version, &artifacts),
+        ),
+        (
+            scoop.clone(),
+            scoop_manifest(packaging, version, &artifacts),
+        ),
+        (
+            String::from("install.sh"),
+            install_script(packaging, version, &artifacts),
+        ),
+    ];
+    for (name, content) in files.iter() {
+        let path = dir.join(name);
+        fs::write(&path, content).context(format!("failed to write {}", path.display()))?;
+        info!("📝 wrote {}", path.display());
+    }
+    if !push {
+        return Ok(());
+    }
+    let targets = [
+        (
+            packaging.tap.as_deref(),
+            "packaging.tap",
+            "Formula",
+            &formula,
+        ),
+        (
+            packaging.scoop_bucket.as_deref(),
+            "packaging.scoop-bucket",
+            "bucket",
+            &scoop,
+        ),
+    ];
+    if targets.iter().all(|(repository, ..)| repository.is_none()) {
+        return Err(anyhow!(
+            "--push needs packaging.tap or packaging.scoop-bucket in xtask.toml"
+        ));
+    }
+    for (repository, _, subdir, name) in targets {
+        let Some(repository) = repository else {
+            continue;
+        };
+        push_file(
+            repository,
+            &Path::new(subdir).join(name),
+            &dir.join(name),
+            &format!("{} {version}", packaging.name),
+        )?;
+    }
+    Ok(())
+}
+
+/// Commits `source` as `destination` in GitHub `repository` and pushes it.
+fn push_file(repository: &str, destination: &Path, source: &Path, message: &str) -> Result<()> {
+    let checkout = tempfile::tempdir()?;
+    let output = Command::new("gh")
+        .args(["repo", "clone", repository])
+        .arg(checkout.path())
+        .args(["--", "--depth", "1"])
+        .output()
+        .map_err(|e| anyhow!("failed to run gh: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "gh repo clone {repository} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let path: PathBuf = checkout.path().join(destination);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context(format!("failed to create {}", parent.display()))?;
+    }
+    fs::copy(source, &path).context(format!("failed to copy {}", source.display()))?;
+    let destination = destination.to_string_lossy();
+    git_in(checkout.path(), &["add", "--", &destination])?;
+    if git_in(checkout.path(), &["status", "--porcelain"])?.is_empty() {
+        info!("{repository} already has {destination}");
+        return Ok(());
+    }
+    git_in(checkout.path(), &["commit", "-m", message])?;
+    git_in(checkout.path(), &["push"])?;
+    info!("🚀 pushed {destination} to {repository}");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn artifact(target: &str) -> Artifact {
+        Artifact {
+            target: target.to_string(),
+            url: format!("https://example.com/v2.2.0/{RELEASE_DIR}-{target}.tar.bz2"),
+            sha256: format!("sha-{target}"),
+        }
+    }
+
+    fn artifacts() -> Vec<Artifact> {
+        vec![
+            artifact("aarch64-apple-darwin"),
+            artifact("aarch64-unknown-linux-gnu"),
+            artifact("x86_64-pc-windows-msvc"),
+            artifact("x86_64-unknown-linux-gnu"),
+        ]
+    }
+
+    fn packaging() -> PackagingConfig {
+        PackagingConfig {
+            validator_binaries: vec![String::from("solana"), String::from("agave-validator")],
+            ..PackagingConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_formula_class() {
+        assert_eq!(formula_class("solana"), "Solana");
+        assert_eq!(formula_class("agave-cli"), "AgaveCli");
+    }
+
+    #[test]
+    fn test_homebrew_formula() {
+        assert_eq!(
+            homebrew_formula(&packaging(), "2.2.0", &artifacts()),
+            r#"class Solana < Formula
+  desc "Command-line tools and validator of the Solana network"
+  homepage "https://anza.xyz/"
+  version "2.2.0"
+  license "Apache-2.0"
+
+  on_macos do
+    on_arm do
+      url "https://example.com/v2.2.0/solana-release-aarch64-apple-darwin.tar.bz2"
+      sha256 "sha-aarch64-apple-darwin"
+    end
+  end
+
+  on_linux do
+    on_arm do
+      url "https://example.com/v2.2.0/solana-release-aarch64-unknown-linux-gnu.tar.bz2"
+      sha256 "sha-aarch64-unknown-linux-gnu"
+    end
+    on_intel do
+      url "https://example.com/v2.2.0/solana-release-x86_64-unknown-linux-gnu.tar.bz2"
+      sha256 "sha-x86_64-unknown-linux-gnu"
+    end
+  end
+
+  def install
+    bin.install Dir["bin/*"]
+  end
+end
+"#
+        );
+    }
+
+    #[test]
+    fn test_ruby_string() {
+        assert_eq!(
+            ruby_string(r#"The "fast" #{chain} \ #1"#),
+            r#""The \"fast\" \#{chain} \\ #1""#
+        );
+    }
+
+    #[test]
+    fn test_scoop_manifest() {
+        let manifest: serde_json::Value =
+            serde_json::from_str(&scoop_manifest(&packaging(), "2.2.0", &artifacts())).unwrap();
+        assert_eq!(
+            manifest["architecture"],
+            json!({
+                "64bit": {
+                    "url": "https://example.com/v2.2.0/solana-release-x86_64-pc-windows-msvc.tar.bz2",
+                    "hash": "sha-x86_64-pc-windows-msvc"
+                }
+            })
+        );
+        assert_eq!(
+            manifest["bin"],
+            json!(["bin\\solana.exe", "bin\\agave-validator.exe"])
+        );
+        assert_eq!(manifest["extract_dir"], "solana-release");
+    }
+
+    #[test]
+    fn test_install_script() {
+        let script = install_script(&packaging(), "2.2.0", &artifacts());
+        assert!(script.contains(
+            "  Darwin-arm64)\n    url=\"https://example.com/v2.2.0/solana-release-aarch64-apple-darwin.tar.bz2\"\n    sha256=\"sha-aarch64-apple-darwin\"\n    ;;\n"
+        ));
+        assert!(script.contains("  Linux-x86_64)\n"));
+        assert!(script.contains("  Linux-aarch64)\n"));
+        assert!(!script.contains("windows"));
+        assert!(script.contains("dir=\"${SOLANA_INSTALL_DIR:-$HOME/.local/share/solana}\""));
+    }
+
+    #[test]
+    fn test_find_artifacts() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(
+            dir.path()
+                .join("solana-release-x86_64-unknown-linux-gnu.tar.bz2"),
+            "",
+        )
+        .unwrap();
+        fs::write(dir.path().join("solana.rb"), "").unwrap();
+        assert_eq!(
+            find_artifacts(dir.path(), "2.2.0", "anza-xyz/agave").unwrap(),
+            vec![Artifact {
+                target: String::from("x86_64-unknown-linux-gnu"),
+                url: String::from("https://github.com/anza-xyz/agave/releases/download/v2.2.0/solana-release-x86_64-unknown-linux-gnu.tar.bz2"),
+                sha256: String::from("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"),
+            }]
+        );
+        assert!(find_artifacts(&dir.path().join("missing"), "2.2.0", "a/b").is_err());
+    }
+}
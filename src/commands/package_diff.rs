@@ -0,0 +1,324 @@
+use {
+    super::{check_package_size::format_size, version_manifest::crate_files},
+    crate::{
+        config::Config,
+        utils::{
+            http::{self, apply_cargo_network_env},
+            registry::{fetch_versions, latest_version, CRATES_IO_INDEX},
+            scope::resolve_manifest_path,
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::MetadataCommand,
+    clap::Args,
+    log::info,
+    std::{
+        collections::BTreeMap,
+        fs,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+};
+
+/// File names that hint at credentials or keys packaged by accident.
+const SECRET_FILE_NAMES: &[&str] = &[
+    ".env",
+    ".netrc",
+    ".npmrc",
+    ".pypirc",
+    "credentials",
+    "credentials.toml",
+    "id_dsa",
+    "id_ecdsa",
+    "id_ed25519",
+    "id_rsa",
+    "keypair.json",
+];
+
+/// Extensions of key and certificate stores.
+const SECRET_EXTENSIONS: &[&str] = &["key", "p12", "pem", "pfx"];
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(help = "Crate to compare")]
+    pub package: String,
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+    #[arg(
+        long,
+        help = "Published version to compare against [default: the latest stable one]"
+    )]
+    pub against: Option<String>,
+    #[arg(
+        long,
+        default_value_t = 256,
+        help = "Size in KiB above which an added file is flagged"
+    )]
+    pub large_kib: u64,
+    #[arg(long, help = "Print the changes of every modified file")]
+    pub contents: bool,
+}
+
+/// Differences between the files of two `.crate`s, by path in the package.
+#[derive(Debug, Default, PartialEq)]
+pub struct CrateDiff {
+    /// Added files with their size.
+    pub added: Vec<(String, u64)>,
+    pub removed: Vec<String>,
+    pub modified: Vec<String>,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let manifest_path = resolve_manifest_path(&args.manifest_path)?;
+    let config = Config::load()?;
+    let metadata = MetadataCommand::new()
+        .manifest_path(&manifest_path)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+    let package = metadata
+        .workspace_packages()
+        .into_iter()
+        .find(|p| p.name.as_str() == args.package)
+        .ok_or_else(|| anyhow!("{} is not a workspace member", args.package))?;
+
+    let against = match &args.against {
+        Some(version) => version.clone(),
+        None => latest_version(
+            &fetch_versions(&config.network, CRATES_IO_INDEX, None, &args.package)?,
+            None,
+        )
+        .ok_or_else(|| anyhow!("{} has no published stable version", args.package))?
+        .to_string(),
+    };
+
+    let work = tempfile::tempdir()?;
+    let published = work
+        .path()
+        .join(format!("{}-{against}.crate", args.package));
+    info!("⬇️ downloading {}@{against}", args.package);
+    http::download(
+        &config.network,
+        &format!(
+            "https://static.crates.io/crates/{name}/{name}-{against}.crate",
+            name = args.package
+        ),
+        &published,
+    )?;
+    let old_root = extract(&published, &work.path().join("published"))?;
+
+    info!("📦 packaging {}@{}", args.package, package.version);
+    let mut cmd = Command::new("cargo");
+    cmd.args(["package", "--no-verify", "--allow-dirty", "--manifest-path"])
+        .arg(package.manifest_path.as_std_path());
+    apply_cargo_network_env(&mut cmd, &config.network);
+    let output = cmd
+        .output()
+        .map_err(|e| anyhow!("failed to run cargo package: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "cargo package failed for {}: {}",
+            args.package,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let packaged = metadata
+        .target_directory
+        .as_std_path()
+        .join("package")
+        .join(format!("{}-{}.crate", args.package, package.version));
+    let new_root = extract(&packaged, &work.path().join("current"))?;
+
+    let old = crate_files(&old_root)?;
+    let new = crate_files(&new_root)?;
+    let diff = diff_files(&old, &new)?;
+    println!(
+        "{} {against} -> {}: {} added, {} removed, {} modified",
+        args.package,
+        package.version,
+        diff.added.len(),
+        diff.removed.len(),
+        diff.modified.len()
+    );
+    let large = args.large_kib.saturating_mul(1024);
+    let mut flagged = 0usize;
+    for (path, size) in diff.added.iter() {
+        let concern = concern(path, *size, large);
+        println!(
+            "  + {path} ({}){}",
+            format_size(*size),
+            concern.map(|c| format!("  ❌ {c}")).unwrap_or_default()
+        );
+        if concern.is_some() {
+            flagged = flagged.saturating_add(1);
+        }
+    }
+    for path in diff.removed.iter() {
+        println!("  - {path}");
+    }
+    for path in diff.modified.iter() {
+        println!("  ~ {path}");
+    }
+    if args.contents {
+        let old: BTreeMap<String, PathBuf> = old.into_iter().collect();
+        let new: BTreeMap<String, PathBuf> = new.into_iter().collect();
+        for path in diff.modified.iter() {
+            let output = Command::new("diff")
+                .args(["-u", "--label", &format!("{against}/{path}")])
+                .args(["--label", &format!("{}/{path}", package.version)])
+                .arg(&old[path])
+                .arg(&new[path])
+                .output()
+                .map_err(|e| anyhow!("failed to run diff: {e}"))?;
+            print!("{}", String::from_utf8_lossy(&output.stdout));
+        }
+    }
+    if flagged > 0 {
+        return Err(anyhow!(
+            "{flagged} added file(s) look unintended, exclude them with package.exclude"
+        ));
+    }
+    Ok(())
+}
+
+/// Extracts `crate_file` into `dir`, returning the package root inside it.
+fn extract(crate_file: &Path, dir: &Path) -> Result<PathBuf> {
+    fs::create_dir_all(dir).context(format!("failed to create {}", dir.display()))?;
+    let output = Command::new("tar")
+        .arg("-xzf")
+        .arg(crate_file)
+        .arg("-C")
+        .arg(dir)
+        .output()
+        .map_err(|e| anyhow!("failed to run tar: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "failed to extract {}: {}",
+            crate_file.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    // a `.crate` holds a single `<name>-<version>/` directory
+    let stem = crate_file
+        .file_stem()
+        .ok_or_else(|| anyhow!("{} has no file name", crate_file.display()))?;
+    Ok(dir.join(stem))
+}
+
+/// Compares files by path and content.
+pub fn diff_files(old: &[(String, PathBuf)], new: &[(String, PathBuf)]) -> Result<CrateDiff> {
+    let old: BTreeMap<&str, &Path> = old.iter().map(|(n, p)| (n.as_str(), p.as_path())).collect();
+    let new: BTreeMap<&str, &Path> = new.iter().map(|(n, p)| (n.as_str(), p.as_path())).collect();
+    let mut diff = CrateDiff::default();
+    for (name, path) in new.iter() {
+        match old.get(name) {
+            Some(old_path) => {
+                if !same_contents(old_path, path)? {
+                    diff.modified.push(name.to_string());
+                }
+            }
+            None => {
+                let size = fs::metadata(path)
+                    .context(format!("failed to read {}", path.display()))?
+                    .len();
+                diff.added.push((name.to_string(), size));
+            }
+        }
+    }
+    diff.removed = old
+        .keys()
+        .filter(|name| !new.contains_key(*name))
+        .map(|name| name.to_string())
+        .collect();
+    Ok(diff)
+}
+
+/// Whether two files hold the same bytes, without reading them when their
+/// sizes differ.
+fn same_contents(a: &Path, b: &Path) -> Result<bool> {
+    let read = |path: &Path| fs::read(path).context(format!("failed to read {}", path.display()));
+    let size = |path: &Path| {
+        fs::metadata(path)
+            .context(format!("failed to read {}", path.display()))
+            .map(|m| m.len())
+    };
+    Ok(size(a)? == size(b)? && read(a)? == read(b)?)
+}
+
+/// Why an added file deserves a second look before it is published.
+pub fn concern(path: &str, size: u64, large: u64) -> Option<&'static str> {
+    let name = path.rsplit('/').next().unwrap_or(path).to_lowercase();
+    let extension = name.rsplit_once('.').map(|(_, extension)| extension);
+    let is_secret = SECRET_FILE_NAMES.contains(&name.as_str())
+        // .env.local, validator-keypair.json
+        || name.starts_with(".env.")
+        || name.ends_with("-keypair.json")
+        || extension.is_some_and(|e| SECRET_EXTENSIONS.contains(&e));
+    if is_secret {
+        Some("may hold a secret")
+    } else if size > large {
+        Some("large file")
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_diff_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = |name: &str, content: &str| {
+            let path = dir.path().join(name);
+            fs::write(&path, content).unwrap();
+            path
+        };
+        let old = vec![
+            (String::from("src/lib.rs"), file("old-lib", "fn a() {}")),
+            (String::from("README.md"), file("old-readme", "# a")),
+            (String::from("build.rs"), file("old-build", "fn main() {}")),
+        ];
+        let new = vec![
+            (String::from("src/lib.rs"), file("new-lib", "fn b() {}")),
+            (String::from("README.md"), file("new-readme", "# a")),
+            (
+                String::from("tests/fixture.bin"),
+                file("new-fixture", "0123"),
+            ),
+        ];
+        assert_eq!(
+            diff_files(&old, &new).unwrap(),
+            CrateDiff {
+                added: vec![(String::from("tests/fixture.bin"), 4)],
+                removed: vec![String::from("build.rs")],
+                modified: vec![String::from("src/lib.rs")],
+            }
+        );
+    }
+
+    #[test]
+    fn test_concern() {
+        assert_eq!(concern("src/lib.rs", 10, 1024), None);
+        assert_eq!(
+            concern("tests/fixtures/big.json", 2048, 1024),
+            Some("large file")
+        );
+        assert_eq!(concern(".env", 10, 1024), Some("may hold a secret"));
+        assert_eq!(
+            concern("tests/validator-keypair.json", 10, 1024),
+            Some("may hold a secret")
+        );
+        assert_eq!(
+            concern("certs/server.PEM", 10, 1024),
+            Some("may hold a secret")
+        );
+        assert_eq!(concern(".env.local", 10, 1024), Some("may hold a secret"));
+        assert_eq!(concern("src/secret_sharing.rs", 10, 1024), None);
+        assert_eq!(concern("src/keypair.rs", 10, 1024), None);
+        assert_eq!(concern("src/environment.rs", 10, 1024), None);
+        assert_eq!(concern("src/monkey.rs", 10, 1024), None);
+        assert_eq!(concern("docs/credentials.md", 10, 1024), None);
+    }
+}
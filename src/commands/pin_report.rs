@@ -0,0 +1,193 @@
+use {
+    super::licenses::{dependency_graph, host_only, shipped_dependencies},
+    crate::{
+        config::Config,
+        utils::{get_head_commit, scope::resolve_manifest_path},
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::{Metadata, MetadataCommand},
+    clap::Args,
+    log::info,
+    serde::Serialize,
+    std::{fs, path::PathBuf, process::Command},
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(
+        long,
+        required = true,
+        help = "Released binary to report, e.g. agave-validator"
+    )]
+    pub bin: Vec<String>,
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+    #[arg(long, default_value = "dist/pin-report.json")]
+    pub output: PathBuf,
+    #[arg(
+        long,
+        help = "Attach the report to this GitHub release of packaging.repository"
+    )]
+    pub upload: Option<String>,
+}
+
+/// Exact dependency versions compiled into the released binaries.
+#[derive(Debug, PartialEq, Serialize)]
+pub struct PinReport {
+    pub git_commit: String,
+    pub binaries: Vec<BinaryPins>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct BinaryPins {
+    pub bin: String,
+    pub package: String,
+    pub version: String,
+    pub dependencies: Vec<Pin>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct Pin {
+    pub name: String,
+    pub version: String,
+    /// Registry or git source with the locked commit, `None` for path crates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let manifest_path = resolve_manifest_path(&args.manifest_path)?;
+    let config = Config::load()?;
+    info!("🔎 resolving {manifest_path}");
+    // `--locked` so the report is exactly what Cargo.lock pins
+    let metadata = MetadataCommand::new()
+        .manifest_path(&manifest_path)
+        .other_options(vec![String::from("--locked")])
+        .exec()
+        .context("failed to run cargo metadata")?;
+    let report = PinReport {
+        git_commit: get_head_commit(metadata.workspace_root.as_std_path())?,
+        binaries: args
+            .bin
+            .iter()
+            .map(|bin| binary_pins(&metadata, bin))
+            .collect::<Result<_>>()?,
+    };
+    if let Some(parent) = args.output.parent() {
+        fs::create_dir_all(parent).context(format!("failed to create {}", parent.display()))?;
+    }
+    fs::write(&args.output, serde_json::to_string_pretty(&report)?)
+        .context(format!("failed to write {}", args.output.display()))?;
+    for binary in report.binaries.iter() {
+        info!(
+            "📌 {} pins {} crate(s)",
+            binary.bin,
+            binary.dependencies.len()
+        );
+    }
+    info!("✅ wrote {}", args.output.display());
+
+    if let Some(tag) = &args.upload {
+        info!("⬆️ attaching the report to {tag}");
+        let output = Command::new("gh")
+            .args(["release", "upload", tag, "--clobber"])
+            .args(["--repo", &config.packaging.repository])
+            .arg(&args.output)
+            .output()
+            .map_err(|e| anyhow!("failed to run gh: {e}"))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "gh release upload {tag} failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// The packages linked into the workspace binary `bin`, for every platform
+/// the lockfile resolves.
+pub fn binary_pins(metadata: &Metadata, bin: &str) -> Result<BinaryPins> {
+    let package = metadata
+        .workspace_packages()
+        .into_iter()
+        .find(|package| {
+            package
+                .targets
+                .iter()
+                .any(|target| target.is_bin() && target.name == bin)
+        })
+        .ok_or_else(|| anyhow!("no workspace crate has a binary named {bin}"))?;
+    let graph = dependency_graph(metadata)?;
+    let shipped = shipped_dependencies(&graph, &package.id.to_string(), &host_only(metadata));
+    let mut dependencies: Vec<Pin> = metadata
+        .packages
+        .iter()
+        .filter(|p| shipped.contains(&p.id.to_string()))
+        .map(|p| Pin {
+            name: p.name.to_string(),
+            version: p.version.to_string(),
+            source: p.source.as_ref().map(|s| s.to_string()),
+        })
+        .collect();
+    dependencies.sort();
+    Ok(BinaryPins {
+        bin: bin.to_string(),
+        package: package.name.to_string(),
+        version: package.version.to_string(),
+        dependencies,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_binary_pins() {
+        let dir = tempfile::tempdir().unwrap();
+        let write = |path: &str, content: &str| {
+            let path = dir.path().join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        };
+        write(
+            "Cargo.toml",
+            "[workspace]\nmembers = [\"validator\", \"runtime\", \"test-utils\"]\nresolver = \"2\"\n",
+        );
+        write(
+            "validator/Cargo.toml",
+            "[package]\nname = \"agave-validator\"\nversion = \"2.3.0\"\nedition = \"2021\"\n\n[dependencies]\nruntime = { path = \"../runtime\" }\n\n[dev-dependencies]\ntest-utils = { path = \"../test-utils\" }\n",
+        );
+        write("validator/src/main.rs", "fn main() {}\n");
+        write(
+            "runtime/Cargo.toml",
+            "[package]\nname = \"runtime\"\nversion = \"2.3.1\"\nedition = \"2021\"\n",
+        );
+        write("runtime/src/lib.rs", "");
+        write(
+            "test-utils/Cargo.toml",
+            "[package]\nname = \"test-utils\"\nversion = \"2.3.0\"\nedition = \"2021\"\n",
+        );
+        write("test-utils/src/lib.rs", "");
+        let metadata = MetadataCommand::new()
+            .manifest_path(dir.path().join("Cargo.toml"))
+            .other_options(vec![String::from("--offline")])
+            .exec()
+            .unwrap();
+        assert_eq!(
+            binary_pins(&metadata, "agave-validator").unwrap(),
+            BinaryPins {
+                bin: String::from("agave-validator"),
+                package: String::from("agave-validator"),
+                version: String::from("2.3.0"),
+                dependencies: vec![Pin {
+                    name: String::from("runtime"),
+                    version: String::from("2.3.1"),
+                    source: None,
+                }],
+            }
+        );
+        assert!(binary_pins(&metadata, "solana").is_err());
+    }
+}
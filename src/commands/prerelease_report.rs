@@ -0,0 +1,172 @@
+use {
+    crate::{
+        commands::publish::PublishPlan,
+        config::Config,
+        utils::{
+            registry::{fetch_published_versions, PublishedVersion},
+            scope::resolve_manifest_path,
+            time::{parse_rfc3339, unix_now, SECONDS_PER_DAY},
+        },
+    },
+    anyhow::{anyhow, Result},
+    clap::Args,
+    log::info,
+    semver::Version,
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+
+    #[arg(
+        long,
+        help = "Age in days to flag [default: promote.stale-prerelease-days in xtask.toml]"
+    )]
+    pub max_age_days: Option<u64>,
+}
+
+/// The newest prerelease of one crate.
+#[derive(Debug, PartialEq)]
+pub struct Prerelease {
+    pub name: String,
+    pub version: Version,
+    /// Days it has been (or was, until superseded) the newest version.
+    pub latest_for_days: u64,
+    /// Stable release at or above the prerelease.
+    pub superseded_by: Option<Version>,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    let max_age_days = args
+        .max_age_days
+        .unwrap_or(config.promote.stale_prerelease_days);
+    let plan = PublishPlan::compute(&resolve_manifest_path(&args.manifest_path)?, &config)?;
+
+    let mut prereleases = vec![];
+    for package in plan.packages() {
+        let versions = fetch_published_versions(&config.network, &package.name)?;
+        if let Some(prerelease) = newest_prerelease(&package.name, &versions, unix_now())? {
+            prereleases.push(prerelease);
+        }
+    }
+    if prereleases.is_empty() {
+        info!("no published prereleases");
+        return Ok(());
+    }
+
+    let mut stale = 0usize;
+    for prerelease in prereleases.iter() {
+        let state = match &prerelease.superseded_by {
+            Some(stable) => format!("superseded by {stable}"),
+            None if prerelease.latest_for_days > max_age_days => {
+                stale = stale.saturating_add(1);
+                String::from("⚠️ stale, promote or abandon it")
+            }
+            None => String::from("current"),
+        };
+        println!(
+            "{:<40} {:>16} {:>6} day(s)  {state}",
+            prerelease.name,
+            prerelease.version.to_string(),
+            prerelease.latest_for_days
+        );
+    }
+    if stale > 0 {
+        return Err(anyhow!(
+            "{stale} prerelease(s) older than {max_age_days} day(s)"
+        ));
+    }
+    Ok(())
+}
+
+/// The highest non-yanked prerelease of `name`, if any.
+pub fn newest_prerelease(
+    name: &str,
+    versions: &[PublishedVersion],
+    now: u64,
+) -> Result<Option<Prerelease>> {
+    let mut published = vec![];
+    for version in versions.iter().filter(|v| !v.yanked) {
+        let Ok(parsed) = Version::parse(&version.num) else {
+            continue;
+        };
+        published.push((parsed, parse_rfc3339(&version.created_at)?));
+    }
+    let Some((version, created_at)) = published
+        .iter()
+        .filter(|(v, _)| !v.pre.is_empty())
+        .max_by(|a, b| a.0.cmp(&b.0))
+    else {
+        return Ok(None);
+    };
+    // the prerelease stopped being the newest once anything higher came out
+    let replaced_at = published
+        .iter()
+        .filter(|(v, _)| v > version)
+        .map(|(_, at)| *at)
+        .min()
+        .unwrap_or(now);
+    let superseded_by = published
+        .iter()
+        .filter(|(v, _)| {
+            v.pre.is_empty()
+                && (v.major, v.minor, v.patch) >= (version.major, version.minor, version.patch)
+        })
+        .map(|(v, _)| v.clone())
+        .max();
+    Ok(Some(Prerelease {
+        name: name.to_string(),
+        version: version.clone(),
+        latest_for_days: replaced_at.saturating_sub(*created_at) / SECONDS_PER_DAY,
+        superseded_by,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn published(num: &str, created_at: &str, yanked: bool) -> PublishedVersion {
+        PublishedVersion {
+            num: num.to_string(),
+            created_at: created_at.to_string(),
+            yanked,
+        }
+    }
+
+    #[test]
+    fn test_newest_prerelease() {
+        let now = parse_rfc3339("2024-06-01T00:00:00Z").unwrap();
+        let versions = vec![
+            published("2.1.0", "2024-01-01T00:00:00Z", false),
+            published("2.2.0-beta.1", "2024-04-01T00:00:00Z", false),
+            published("2.2.0-rc.1", "2024-05-01T00:00:00Z", false),
+            published("2.2.0-rc.2", "2024-05-20T00:00:00Z", true),
+        ];
+        assert_eq!(
+            newest_prerelease("foo", &versions, now).unwrap(),
+            Some(Prerelease {
+                name: String::from("foo"),
+                version: Version::parse("2.2.0-rc.1").unwrap(),
+                latest_for_days: 31,
+                superseded_by: None,
+            })
+        );
+
+        let mut promoted = versions.clone();
+        promoted.push(published("2.2.0", "2024-05-11T00:00:00Z", false));
+        assert_eq!(
+            newest_prerelease("foo", &promoted, now).unwrap(),
+            Some(Prerelease {
+                name: String::from("foo"),
+                version: Version::parse("2.2.0-rc.1").unwrap(),
+                latest_for_days: 10,
+                superseded_by: Some(Version::parse("2.2.0").unwrap()),
+            })
+        );
+
+        assert_eq!(newest_prerelease("foo", &versions[..1], now).unwrap(), None);
+    }
+}
@@ -0,0 +1,216 @@
+use {
+    super::bump_version::{self, BumpLevel},
+    crate::{
+        commands::publish::PublishPlan,
+        config::{Config, NetworkConfig},
+        utils::{
+            cargo::{dependency_tables, read_manifest, TableScope},
+            find_all_cargo_tomls, get_all_crates, get_current_version, http,
+            scope::resolve_manifest_path,
+            time::{parse_rfc3339, unix_now},
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    clap::{Args, ValueEnum},
+    log::info,
+    semver::{Version, VersionReq},
+    serde::Deserialize,
+    toml_edit::DocumentMut,
+};
+
+#[derive(ValueEnum, Clone, Debug)]
+pub enum PromoteTarget {
+    #[value(help = "Strip the rc prerelease: x.y.z-rc.n -> x.y.z")]
+    Stable,
+}
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, value_enum)]
+    pub to: PromoteTarget,
+
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+}
+
+#[derive(Deserialize)]
+struct VersionResponse {
+    version: PublishedVersion,
+}
+
+#[derive(Deserialize)]
+struct PublishedVersion {
+    created_at: String,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    let current = Version::parse(&get_current_version()?)?;
+    if !current.pre.starts_with("rc.") {
+        return Err(anyhow!(
+            "only an rc can be promoted to stable, the workspace is at {current}"
+        ));
+    }
+
+    let workspace_crates = get_all_crates()?;
+    let mut problems = vec![];
+    for manifest in find_all_cargo_tomls()? {
+        let doc = read_manifest(&manifest)?;
+        for problem in prerelease_dependencies(&doc, &workspace_crates, &current) {
+            problems.push(format!("{}: {problem}", manifest.display()));
+        }
+    }
+
+    let soak_secs = config.promote.soak_hours.saturating_mul(3600);
+    let plan = PublishPlan::compute(&resolve_manifest_path(&args.manifest_path)?, &config)?;
+    for package in plan.packages() {
+        let published =
+            published_at(&config.network, &package.name, &package.version).context(format!(
+                "failed to look up {}@{} on crates.io",
+                package.name, package.version
+            ))?;
+        if let Some(remaining) = soak_remaining(published, unix_now(), soak_secs) {
+            problems.push(format!(
+                "{}@{} has been live for less than promote.soak-hours ({}), {}h to go",
+                package.name,
+                package.version,
+                config.promote.soak_hours,
+                remaining.div_ceil(3600)
+            ));
+        }
+    }
+
+    if !problems.is_empty() {
+        return Err(anyhow!(
+            "{current} cannot be promoted to stable:\n{}",
+            problems
+                .iter()
+                .map(|p| format!("  - {p}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+    info!("✅ {current} is ready for stable, promoting");
+    bump_version::run(bump_version::CommandArgs {
+        level: BumpLevel::PromotePreRelease,
+    })
+}
+
+/// Dependencies in `doc` still requiring a prerelease. Workspace crates pinned
+/// to `current` in `workspace.dependencies` are exempt, the bump rewrites them.
+pub fn prerelease_dependencies(
+    doc: &DocumentMut,
+    workspace_crates: &[String],
+    current: &Version,
+) -> Vec<String> {
+    let mut problems = vec![];
+    for table in dependency_tables(doc) {
+        if table.scope == TableScope::Patch {
+            continue;
+        }
+        let Some(dependencies) = table.item.as_table_like() else {
+            continue;
+        };
+        for (key, spec) in dependencies.iter() {
+            let requirement = match spec.as_str() {
+                Some(requirement) => requirement,
+                None => match spec.get("version").and_then(|v| v.as_str()) {
+                    Some(requirement) => requirement,
+                    None => continue,
+                },
+            };
+            let is_prerelease = VersionReq::parse(requirement)
+                .is_ok_and(|req| req.comparators.iter().any(|c| !c.pre.is_empty()));
+            if !is_prerelease {
+                continue;
+            }
+            let name = spec.get("package").and_then(|p| p.as_str()).unwrap_or(key);
+            let bumped = table.scope == TableScope::Workspace
+                && workspace_crates.iter().any(|c| c == name)
+                && pins(requirement, current);
+            if !bumped {
+                problems.push(format!(
+                    "{}.{key} requires prerelease {requirement}",
+                    table.name
+                ));
+            }
+        }
+    }
+    problems
+}
+
+/// Whether `requirement` names exactly `version`, e.g. `=2.0.0-rc.1` or
+/// `2.0.0-rc.1`, which `2.0.0-rc.10` does not.
+fn pins(requirement: &str, version: &Version) -> bool {
+    VersionReq::parse(requirement).is_ok_and(|req| {
+        req.comparators.iter().any(|c| {
+            c.major == version.major
+                && c.minor == Some(version.minor)
+                && c.patch == Some(version.patch)
+                && c.pre == version.pre
+        })
+    })
+}
+
+/// When `name@version` was published, in unix seconds.
+fn published_at(network: &NetworkConfig, name: &str, version: &str) -> Result<u64> {
+    let url = format!("https://crates.io/api/v1/crates/{name}/{version}");
+    let body = http::get(network, &url, &[("User-Agent", "anza-xtask")])?;
+    let response: VersionResponse = serde_json::from_slice(&body)?;
+    parse_rfc3339(&response.version.created_at)
+}
+
+/// Seconds left until a release published at `published` has soaked for
+/// `soak_secs`, `None` once it has.
+pub fn soak_remaining(published: u64, now: u64, soak_secs: u64) -> Option<u64> {
+    let soaked_at = published.saturating_add(soak_secs);
+    (now < soaked_at).then(|| soaked_at.saturating_sub(now))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_prerelease_dependencies() {
+        let doc: DocumentMut = r#"
+[workspace.dependencies]
+foo = { path = "foo", version = "=2.2.0-rc.1" }
+bar = { path = "bar", version = "=2.1.0-rc.3" }
+baz = { path = "baz", version = "=2.2.0-rc.10" }
+serde = "1.0"
+
+[dependencies]
+tokio = "1.40.0-alpha.1"
+renamed = { package = "foo", version = "2.2.0-rc.1" }
+
+[target.'cfg(unix)'.dev-dependencies]
+nix = { version = "0.30.0-beta" }
+"#
+        .parse()
+        .unwrap();
+        let workspace_crates = vec![
+            String::from("foo"),
+            String::from("bar"),
+            String::from("baz"),
+        ];
+        let current = Version::parse("2.2.0-rc.1").unwrap();
+        assert_eq!(
+            prerelease_dependencies(&doc, &workspace_crates, &current),
+            vec![
+                "dependencies.tokio requires prerelease 1.40.0-alpha.1",
+                "dependencies.renamed requires prerelease 2.2.0-rc.1",
+                "workspace.dependencies.bar requires prerelease =2.1.0-rc.3",
+                "workspace.dependencies.baz requires prerelease =2.2.0-rc.10",
+                "target.cfg(unix).dev-dependencies.nix requires prerelease 0.30.0-beta",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_soak_remaining() {
+        assert_eq!(soak_remaining(1_000, 1_000, 3_600), Some(3_600));
+        assert_eq!(soak_remaining(1_000, 4_000, 3_600), Some(600));
+        assert_eq!(soak_remaining(1_000, 4_600, 3_600), None);
+    }
+}
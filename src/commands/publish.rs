@@ -1,9 +1,36 @@
+mod changelog;
+mod checkpoint;
+mod dry_run;
+mod execute;
+mod guard;
+mod plan;
+mod readme;
+mod registry;
+mod rehearse;
+mod select;
+mod status;
+
+pub use {
+    checkpoint::PublishState,
+    execute::{ExecuteArgs, IndexWait},
+    guard::Violation,
+    plan::{PlannedPackage, PublishPlan},
+    registry::{Registry, CRATES_IO},
+    select::Selection,
+};
+
 use {
-    crate::utils::{check_docker_available, get_git_root_path},
+    crate::{
+        config::Config,
+        utils::{
+            check_docker_available, get_git_root_path,
+            git::{tag_commit, with_worktree_in},
+            scope::resolve_manifest_path,
+        },
+    },
     anyhow::{anyhow, Result},
-    cargo_metadata::{MetadataCommand, PackageId},
+    cargo_metadata::{MetadataCommand, NodeDep, PackageId},
     clap::{Args, Subcommand},
-    log::info,
     scopeguard::defer,
     std::{
         collections::{HashMap, HashSet},
@@ -12,18 +39,27 @@ use {
         process::Command,
         sync::{Arc, RwLock},
         thread,
+        time::Duration,
     },
     toml_edit::{value, DocumentMut},
+    tracing::{info, info_span},
 };
 
+/// Sparse index of the kellnr container started by `publish test`.
+const KELLNR_INDEX: &str = "sparse+http://127.0.0.1:8000/api/v1/crates/";
+/// Token of kellnr's default admin user.
+const KELLNR_TOKEN: &str = "Zy9HhJ02RJmg0GCrgLfaCVfU6IwDfhXD";
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct PackageInfo {
     pub name: String,
+    pub version: String,
     pub path: std::path::PathBuf,
     pub dependencies: HashSet<PackageId>,
 }
 
 pub struct PublishOrderData {
+    pub workspace_root: PathBuf,
     pub levels: Vec<Vec<PackageId>>,
     pub id_to_level: std::collections::HashMap<PackageId, usize>,
     pub id_to_package_info: HashMap<PackageId, PackageInfo>,
@@ -35,6 +71,13 @@ pub enum OutputFormat {
     Tree,
 }
 
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum PlanFormat {
+    Json,
+    Dot,
+    Mermaid,
+}
+
 #[derive(Subcommand)]
 pub enum PublishSubcommand {
     #[command(about = "Print the publish order")]
@@ -44,34 +87,185 @@ pub enum PublishSubcommand {
     },
     #[command(about = "Test the publish process")]
     Test,
+    #[command(about = "Write the publish plan for review and signing")]
+    Plan {
+        #[arg(long, help = "Write the plan to a file instead of stdout")]
+        output: Option<PathBuf>,
+        #[arg(
+            long,
+            value_enum,
+            default_value = "json",
+            help = "dot and mermaid render the graph for review; only json can be executed"
+        )]
+        format: PlanFormat,
+    },
+    #[command(about = "Publish the workspace crates in order")]
+    Execute(ExecuteArgs),
+    #[command(about = "List crates whose local version is not on crates.io yet")]
+    Status,
 }
 
+impl PublishSubcommand {
+    /// Whether the subcommand publishes crates (and must be audited).
+    pub fn is_mutating(&self) -> bool {
+        matches!(
+            self,
+            PublishSubcommand::Test | PublishSubcommand::Execute(_)
+        )
+    }
+}
+
+/// How long published crates get to show up in the crates.io index.
+pub const DEFAULT_INDEX_TIMEOUT_SECS: u64 = 600;
+pub const DEFAULT_POLL_INTERVAL_SECS: u64 = 10;
+
 #[derive(Args)]
 pub struct CommandArgs {
     #[arg(long, default_value = "Cargo.toml")]
     pub manifest_path: String,
 
+    /// Seconds a published level gets to show up in the index before the
+    /// next level starts.
+    #[arg(long, global = true, default_value_t = DEFAULT_INDEX_TIMEOUT_SECS)]
+    pub index_timeout: u64,
+
+    /// Seconds between index checks while waiting for a level.
+    #[arg(long, global = true, default_value_t = DEFAULT_POLL_INTERVAL_SECS)]
+    pub poll_interval: u64,
+
+    /// Registry from the cargo config or `publish.registries` to publish to
+    /// [default: crates.io].
+    #[arg(long, global = true)]
+    pub registry: Option<String>,
+
+    /// Tag or commit to publish from, checked out into a clean temporary
+    /// worktree so local changes and untracked files never end up in a
+    /// release [default: the working tree].
+    #[arg(long = "ref", global = true)]
+    pub git_ref: Option<String>,
+
+    #[command(flatten)]
+    pub selection: Selection,
+
     #[command(subcommand)]
     pub subcommand: PublishSubcommand,
 }
 
-pub fn run(args: CommandArgs) -> Result<()> {
+pub fn run(mut args: CommandArgs) -> Result<()> {
+    let manifest_path = resolve_manifest_path(&args.manifest_path)?;
+    let Some(git_ref) = args.git_ref.clone() else {
+        return run_from(&manifest_path, args);
+    };
+    if matches!(args.subcommand, PublishSubcommand::Test) {
+        return Err(anyhow!(
+            "--ref is not supported by `publish test`, which edits the local .cargo/config.toml"
+        ));
+    }
+    let repo = get_git_root_path()?;
+    let manifest = fs::canonicalize(&manifest_path)
+        .map_err(|e| anyhow!("failed to resolve {manifest_path}: {e}"))?;
+    let relative = manifest.strip_prefix(&repo).map_err(|_| {
+        anyhow!(
+            "{} is outside the repository at {}",
+            manifest.display(),
+            repo.display()
+        )
+    })?;
+    // a tag given as `--ref` is the release tag unless `--tag` says otherwise
+    if let PublishSubcommand::Execute(execute) = &mut args.subcommand {
+        if execute.tag.is_none() && tag_commit(&repo, &git_ref)?.is_some() {
+            execute.tag = Some(git_ref.clone());
+        }
+    }
+    with_worktree_in(&repo, &git_ref, |worktree| {
+        info!("📌 publishing from {git_ref} in {}", worktree.display());
+        run_from(&worktree.join(relative).to_string_lossy(), args)
+    })
+}
+
+fn run_from(manifest_path: &str, args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    let registry = match &args.registry {
+        Some(name) => {
+            let workspace_dir = std::env::current_dir()?
+                .join(Path::new(manifest_path).parent().unwrap_or(Path::new("")));
+            Registry::resolve(Some(name), &config, &workspace_dir)?
+        }
+        None => Registry::crates_io(),
+    };
     match args.subcommand {
         PublishSubcommand::Order { format } => match format {
-            OutputFormat::Json => publish_order_json(&args.manifest_path)?,
-            OutputFormat::Tree => publish_order_tree(&args.manifest_path)?,
+            OutputFormat::Json => publish_order_json(
+                manifest_path,
+                registry.display_name(),
+                &args.selection,
+                &config,
+            )?,
+            OutputFormat::Tree => publish_order_tree(
+                manifest_path,
+                registry.display_name(),
+                &args.selection,
+                &config,
+            )?,
         },
         PublishSubcommand::Test => {
-            publish_test(&args.manifest_path)?;
+            publish_test(manifest_path)?;
+        }
+        PublishSubcommand::Plan { output, format } => {
+            publish_plan(
+                manifest_path,
+                registry.display_name(),
+                &args.selection,
+                format,
+                output.as_deref(),
+                &config,
+            )?;
+        }
+        PublishSubcommand::Execute(execute_args) => {
+            let index_wait = IndexWait {
+                timeout: Duration::from_secs(args.index_timeout),
+                poll_interval: Duration::from_secs(args.poll_interval),
+            };
+            execute::publish_execute(
+                manifest_path,
+                &execute_args,
+                &index_wait,
+                &registry,
+                &args.selection,
+                &config,
+            )?;
+        }
+        PublishSubcommand::Status => {
+            status::publish_status(manifest_path, &registry, &args.selection, &config)?;
         }
     }
     Ok(())
 }
 
 pub fn compute_publish_order_data(manifest_path: &str) -> Result<PublishOrderData> {
+    compute_publish_order_data_for(
+        manifest_path,
+        CRATES_IO,
+        &Selection::default(),
+        &Config::load()?,
+    )
+}
+
+/// Publish order of the selected workspace crates that may be published to
+/// `registry`.
+pub fn compute_publish_order_data_for(
+    manifest_path: &str,
+    registry: &str,
+    selection: &Selection,
+    config: &Config,
+) -> Result<PublishOrderData> {
+    let _span = info_span!("graph_build", manifest_path, registry).entered();
     let mut cmd = MetadataCommand::new();
     cmd.features(cargo_metadata::CargoOpt::AllFeatures);
     cmd.manifest_path(manifest_path);
+    for (key, value) in registry::index_env(config) {
+        cmd.env(key, value);
+    }
     let metadata = cmd.exec()?;
 
     let workspace_member_ids: HashSet<&PackageId> = metadata.workspace_members.iter().collect();
@@ -83,11 +277,9 @@ pub fn compute_publish_order_data(manifest_path: &str) -> Result<PublishOrderDat
             continue;
         }
 
-        // skip packages that no need to be published
-        if let Some(registries) = &pkg.publish {
-            if registries.is_empty() {
-                continue;
-            }
+        // skip packages that are not published, or not to this registry
+        if !registry::publishes_to(pkg.publish.as_deref(), registry) {
+            continue;
         }
 
         let path = Path::new(&pkg.manifest_path)
@@ -99,6 +291,7 @@ pub fn compute_publish_order_data(manifest_path: &str) -> Result<PublishOrderDat
             pkg.id.clone(),
             PackageInfo {
                 name: pkg.name.clone().to_string(),
+                version: pkg.version.to_string(),
                 path,
                 dependencies: HashSet::new(),
             },
@@ -115,12 +308,7 @@ pub fn compute_publish_order_data(manifest_path: &str) -> Result<PublishOrderDat
                     if dep.pkg == node.id {
                         continue;
                     }
-                    // skip dev-only dependencies - they don't affect publish order
-                    if dep
-                        .dep_kinds
-                        .iter()
-                        .all(|dk| dk.kind == cargo_metadata::DependencyKind::Development)
-                    {
+                    if !is_publish_edge(dep) {
                         continue;
                     }
                     if id_to_package_info.contains_key(&dep.pkg) {
@@ -132,6 +320,20 @@ pub fn compute_publish_order_data(manifest_path: &str) -> Result<PublishOrderDat
         }
     }
 
+    let (levels, id_to_level) = order_levels(&id_to_package_info)?;
+    selection.apply(PublishOrderData {
+        workspace_root: metadata.workspace_root.clone().into_std_path_buf(),
+        levels,
+        id_to_level,
+        id_to_package_info,
+    })
+}
+
+/// Groups packages into levels whose dependencies are all in earlier levels.
+#[allow(clippy::type_complexity)]
+fn order_levels(
+    id_to_package_info: &HashMap<PackageId, PackageInfo>,
+) -> Result<(Vec<Vec<PackageId>>, HashMap<PackageId, usize>)> {
     let mut levels: Vec<Vec<PackageId>> = Vec::new();
     let mut processed: HashSet<PackageId> = HashSet::new();
     let mut id_to_level: HashMap<PackageId, usize> = HashMap::new();
@@ -184,15 +386,31 @@ pub fn compute_publish_order_data(manifest_path: &str) -> Result<PublishOrderDat
         ));
     }
 
-    Ok(PublishOrderData {
-        levels,
-        id_to_level,
-        id_to_package_info,
-    })
+    Ok((levels, id_to_level))
 }
 
-pub fn publish_order_json(manifest_path: &str) -> Result<()> {
-    let publish_order_data = compute_publish_order_data(manifest_path)?;
+/// Whether `dep` constrains the publish order.
+///
+/// Dev-only edges are stripped: dev-dependencies are not needed to build the
+/// published crate, and keeping them would turn test-only cycles (`a`
+/// dev-depends on `b` which depends on `a`) into an unsatisfiable order.
+/// `xtask lint dev-cycles` reports those cycles, since a versioned
+/// dev-dependency must still exist on the registry when its dependent is
+/// published.
+fn is_publish_edge(dep: &NodeDep) -> bool {
+    dep.dep_kinds
+        .iter()
+        .any(|dk| dk.kind != cargo_metadata::DependencyKind::Development)
+}
+
+pub fn publish_order_json(
+    manifest_path: &str,
+    registry: &str,
+    selection: &Selection,
+    config: &Config,
+) -> Result<()> {
+    let publish_order_data =
+        compute_publish_order_data_for(manifest_path, registry, selection, config)?;
 
     let mut output = vec![];
     for level in publish_order_data.levels.iter() {
@@ -213,8 +431,14 @@ pub fn publish_order_json(manifest_path: &str) -> Result<()> {
     Ok(())
 }
 
-pub fn publish_order_tree(manifest_path: &str) -> Result<()> {
-    let publish_order_data = compute_publish_order_data(manifest_path)?;
+pub fn publish_order_tree(
+    manifest_path: &str,
+    registry: &str,
+    selection: &Selection,
+    config: &Config,
+) -> Result<()> {
+    let publish_order_data =
+        compute_publish_order_data_for(manifest_path, registry, selection, config)?;
 
     let total_packages = publish_order_data
         .levels
@@ -282,6 +506,31 @@ pub fn publish_order_tree(manifest_path: &str) -> Result<()> {
     Ok(())
 }
 
+pub fn publish_plan(
+    manifest_path: &str,
+    registry: &str,
+    selection: &Selection,
+    format: PlanFormat,
+    output: Option<&Path>,
+    config: &Config,
+) -> Result<()> {
+    let plan = PublishPlan::compute_for(manifest_path, registry, selection, config)?;
+    let rendered = match format {
+        PlanFormat::Json => serde_json::to_string_pretty(&plan)?,
+        PlanFormat::Dot => plan.to_dot(),
+        PlanFormat::Mermaid => plan.to_mermaid(),
+    };
+    match output {
+        Some(path) => {
+            fs::write(path, format!("{rendered}\n"))
+                .map_err(|e| anyhow!("Failed to write plan to {}: {e}", path.display()))?;
+            info!("publish plan written to {}", path.display());
+        }
+        None => println!("{rendered}"),
+    }
+    Ok(())
+}
+
 fn write_custom_registry_config() -> Result<()> {
     let git_root = get_git_root_path()?;
     let config_file_path = git_root.join(".cargo/config.toml");
@@ -294,9 +543,9 @@ fn write_custom_registry_config() -> Result<()> {
     let mut credential_provider = toml_edit::Array::new();
     credential_provider.push("cargo:token");
 
-    doc["registries"]["kellnr"]["index"] = value("sparse+http://127.0.0.1:8000/api/v1/crates/");
+    doc["registries"]["kellnr"]["index"] = value(KELLNR_INDEX);
     doc["registries"]["kellnr"]["credential-provider"] = value(credential_provider);
-    doc["registries"]["kellnr"]["token"] = value("Zy9HhJ02RJmg0GCrgLfaCVfU6IwDfhXD");
+    doc["registries"]["kellnr"]["token"] = value(KELLNR_TOKEN);
 
     fs::write(&config_file_path, doc.to_string())
         .map_err(|e| anyhow!("Failed to write config file: {e}"))?;
@@ -384,6 +633,7 @@ fn publish_test(manifest_path: &str) -> Result<()> {
 
             info!("  publishing package: {package_name}");
             let handle = thread::spawn(move || -> Result<String> {
+                let _span = info_span!("publish_crate", name = %package_name).entered();
                 publish_package_with_docker(package_name.clone(), &package_path)
                     .map_err(|e| anyhow!("Failed to publish package {package_name}: {e}"))?;
                 info!("    ✅ {package_name} published");
@@ -525,7 +775,12 @@ mod tests {
     #[test]
     fn test_publish_order_json_output() {
         let manifest = "tests/dummy-workspace/Cargo.toml";
-        let result = publish_order_json(manifest);
+        let result = publish_order_json(
+            manifest,
+            CRATES_IO,
+            &Selection::default(),
+            &Config::default(),
+        );
         assert!(result.is_ok(), "JSON output should succeed");
     }
 
@@ -539,6 +794,11 @@ mod tests {
     fn test_run_with_json_format() {
         let args = CommandArgs {
             manifest_path: "tests/dummy-workspace/Cargo.toml".to_string(),
+            index_timeout: DEFAULT_INDEX_TIMEOUT_SECS,
+            poll_interval: DEFAULT_POLL_INTERVAL_SECS,
+            registry: None,
+            git_ref: None,
+            selection: Selection::default(),
             subcommand: PublishSubcommand::Order {
                 format: OutputFormat::Json,
             },
@@ -551,6 +811,11 @@ mod tests {
     fn test_run_with_tree_format() {
         let args = CommandArgs {
             manifest_path: "tests/dummy-workspace/Cargo.toml".to_string(),
+            index_timeout: DEFAULT_INDEX_TIMEOUT_SECS,
+            poll_interval: DEFAULT_POLL_INTERVAL_SECS,
+            registry: None,
+            git_ref: None,
+            selection: Selection::default(),
             subcommand: PublishSubcommand::Order {
                 format: OutputFormat::Tree,
             },
@@ -610,11 +875,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_publish_order_strips_dev_cycles() {
+        // a dev-depends on b, which depends on a; c depends on b and dev-depends on a
+        let data =
+            compute_publish_order_data("tests/dummy-workspace-dev-cycle/Cargo.toml").unwrap();
+        let names: Vec<Vec<&str>> = data
+            .levels
+            .iter()
+            .map(|level| {
+                level
+                    .iter()
+                    .map(|id| data.id_to_package_info[id].name.as_str())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(names, vec![vec!["a"], vec!["b"], vec!["c"]]);
+    }
+
     #[test]
     fn test_publish_order_tree_with_dependencies() {
         // uses a workspace with inter-dependencies to exercise the dependency display path
         let manifest = "tests/dummy-workspace-publish-test/Cargo.toml";
-        let result = publish_order_tree(manifest);
+        let result = publish_order_tree(
+            manifest,
+            CRATES_IO,
+            &Selection::default(),
+            &Config::default(),
+        );
         assert!(
             result.is_ok(),
             "Tree output with dependencies should succeed"
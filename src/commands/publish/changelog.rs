@@ -0,0 +1,201 @@
+use {
+    super::plan::PlannedPackage,
+    crate::config::Config,
+    anyhow::{Context, Result},
+    log::{info, warn},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// First line of an injected changelog, which tells a copy left behind by a
+/// killed run apart from a changelog of the crate.
+const MARKER: &str = "<!-- injected by xtask publish --include-changelog, do not commit -->";
+
+/// A crate changelog copied into the crate for the duration of its publish.
+pub struct InjectedChangelog(PathBuf);
+
+impl Drop for InjectedChangelog {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.0) {
+            warn!("failed to remove {}: {e}", self.0.display());
+        }
+    }
+}
+
+/// Writes the section of the changelog of `package` for the version being
+/// published into its directory when the changelog is kept elsewhere
+/// (`lint.changelog.paths`); the file is removed again when the returned
+/// value is dropped. Crates without a section for the version are skipped.
+///
+/// Crates with a `CHANGELOG.md` of their own are packaged with it already,
+/// and the repository changelog is never copied, it covers every crate.
+pub fn inject(
+    repo: &Path,
+    workspace_root: &Path,
+    package: &PlannedPackage,
+    config: &Config,
+) -> Result<Option<InjectedChangelog>> {
+    let crate_dir = workspace_root.join(&package.path);
+    let changelog = config.crate_changelog(
+        &package.name,
+        crate_dir.strip_prefix(repo).unwrap_or(&crate_dir),
+    );
+    if changelog == config.release.changelog {
+        return Ok(None);
+    }
+    let source = repo.join(&changelog);
+    let destination = crate_dir.join("CHANGELOG.md");
+    if !source.exists() || destination.exists() {
+        return Ok(None);
+    }
+    let content =
+        fs::read_to_string(&source).context(format!("failed to read {}", source.display()))?;
+    let Some(section) = version_section(&content, &package.version) else {
+        warn!(
+            "{} has no section for {} {}, not including it",
+            changelog.display(),
+            package.name,
+            package.version
+        );
+        return Ok(None);
+    };
+    fs::write(&destination, format!("{MARKER}\n\n{section}"))
+        .context(format!("failed to write {}", destination.display()))?;
+    info!(
+        "  📝 included the {} section of {} as CHANGELOG.md of {}",
+        package.version,
+        changelog.display(),
+        package.name
+    );
+    Ok(Some(InjectedChangelog(destination)))
+}
+
+/// Removes changelogs a killed run injected into the crates of `packages`.
+pub fn remove_leftovers<'a>(
+    workspace_root: &Path,
+    packages: impl IntoIterator<Item = &'a PlannedPackage>,
+) -> Result<()> {
+    for package in packages {
+        let path = workspace_root.join(&package.path).join("CHANGELOG.md");
+        let is_leftover = fs::read_to_string(&path).is_ok_and(|c| c.starts_with(MARKER));
+        if is_leftover {
+            fs::remove_file(&path).context(format!("failed to remove {}", path.display()))?;
+            info!("  🧹 removed {} left by an interrupted run", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// The `## ` section of `content` whose heading names `version`, as in
+/// `## v1.2.0 - 2024-05-01` or `## [1.2.0]`, up to the next heading of the
+/// same or a higher level.
+pub fn version_section(content: &str, version: &str) -> Option<String> {
+    let names_version = |heading: &str| {
+        heading.split_whitespace().any(|word| {
+            word.trim_matches(['[', ']', '(', ')'])
+                .trim_start_matches('v')
+                == version
+        })
+    };
+    let mut lines = content.lines();
+    let heading = lines.find(|line| line.strip_prefix("## ").is_some_and(names_version))?;
+    let mut section = vec![heading];
+    section.extend(lines.take_while(|line| !line.starts_with("## ") && !line.starts_with("# ")));
+    while section.last().is_some_and(|line| line.trim().is_empty()) {
+        section.pop();
+    }
+    Some(format!("{}\n", section.join("\n")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inject() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        for crate_dir in ["sdk/hash", "sdk/program", "cli"] {
+            fs::create_dir_all(repo.join(crate_dir)).unwrap();
+        }
+        fs::create_dir_all(repo.join("docs")).unwrap();
+        fs::write(
+            repo.join("docs/hash.md"),
+            "# solana-hash\n\n## v1.0.0 - 2024-05-01\n\n- Add hashv\n\n## v0.9.0 - 2024-01-01\n\n- Old\n",
+        )
+        .unwrap();
+        fs::write(repo.join("CHANGELOG.md"), "# Changelog\n").unwrap();
+
+        let mut config = Config::default();
+        config
+            .lint
+            .changelog
+            .paths
+            .insert(String::from("solana-hash"), PathBuf::from("docs/hash.md"));
+        let package = |name: &str, path: &str| PlannedPackage {
+            name: name.to_string(),
+            version: String::from("1.0.0"),
+            path: PathBuf::from(path),
+            dependencies: vec![],
+        };
+        let workspace_root = repo.join("sdk");
+
+        let injected = repo.join("sdk/hash/CHANGELOG.md");
+        {
+            let changelog = inject(
+                repo,
+                &workspace_root,
+                &package("solana-hash", "hash"),
+                &config,
+            )
+            .unwrap();
+            assert!(changelog.is_some());
+            assert_eq!(
+                fs::read_to_string(&injected).unwrap(),
+                format!("{MARKER}\n\n## v1.0.0 - 2024-05-01\n\n- Add hashv\n")
+            );
+        }
+        assert!(!injected.exists());
+
+        // a copy left by a killed run is swept, the crate's own is kept
+        fs::write(&injected, format!("{MARKER}\n\nstale\n")).unwrap();
+        fs::write(repo.join("cli/CHANGELOG.md"), "# cli\n").unwrap();
+        remove_leftovers(
+            &workspace_root,
+            &[
+                package("solana-hash", "hash"),
+                package("solana-cli", "../cli"),
+            ],
+        )
+        .unwrap();
+        assert!(!injected.exists());
+        assert!(repo.join("cli/CHANGELOG.md").exists());
+
+        // the repository changelog covers every crate
+        assert!(inject(
+            repo,
+            &workspace_root,
+            &package("solana-program", "program"),
+            &config
+        )
+        .unwrap()
+        .is_none());
+        assert!(!repo.join("sdk/program/CHANGELOG.md").exists());
+    }
+
+    #[test]
+    fn test_version_section() {
+        let content = "# Changelog\n\n## [Unreleased]\n\n## [1.0.10] - 2024-06-01\n\n- Newer\n\n## [1.0.1] - 2024-05-01\n\n### Fixed\n\n- Bug\n\n## [1.0.0]\n\n- First\n";
+        assert_eq!(
+            version_section(content, "1.0.1").unwrap(),
+            "## [1.0.1] - 2024-05-01\n\n### Fixed\n\n- Bug\n"
+        );
+        assert_eq!(
+            version_section(content, "1.0.0").unwrap(),
+            "## [1.0.0]\n\n- First\n"
+        );
+        assert_eq!(version_section(content, "2.0.0"), None);
+    }
+}
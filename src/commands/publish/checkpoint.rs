@@ -0,0 +1,203 @@
+use {
+    super::plan::PlannedPackage,
+    anyhow::{anyhow, Context, Result},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        fs,
+        path::Path,
+    },
+};
+
+/// Checkpoint of a publish run, written after every published crate.
+///
+/// The checkpoint file holds one per registry, so a run against a private
+/// registry neither resumes from nor clobbers one against crates.io.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct PublishState {
+    /// Commit of the plan being published.
+    pub git_commit: String,
+    /// `<name>@<version>` of every crate published so far.
+    pub published: BTreeSet<String>,
+}
+
+impl PublishState {
+    pub fn load(path: &Path, registry: &str) -> Result<Option<Self>> {
+        Ok(load_all(path)?.remove(registry))
+    }
+
+    pub fn save(&self, path: &Path, registry: &str) -> Result<()> {
+        let mut states = load_all(path)?;
+        states.insert(registry.to_string(), self.clone());
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context(format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(path, serde_json::to_string_pretty(&states)?)
+            .context(format!("failed to write {}", path.display()))
+    }
+
+    /// Drops the checkpoint of `registry` once its run finished, removing the
+    /// file when no other registry has one left.
+    pub fn clear(path: &Path, registry: &str) -> Result<()> {
+        let mut states = load_all(path)?;
+        if states.remove(registry).is_none() {
+            return Ok(());
+        }
+        if states.is_empty() {
+            return fs::remove_file(path).context(format!("failed to remove {}", path.display()));
+        }
+        fs::write(path, serde_json::to_string_pretty(&states)?)
+            .context(format!("failed to write {}", path.display()))
+    }
+
+    /// Whether `path` holds an unfinished run of `git_commit` to
+    /// `registry`, which a re-run of the same release continues.
+    pub fn resumable(path: &Path, registry: &str, git_commit: &str) -> Result<bool> {
+        Ok(Self::load(path, registry)?
+            .is_some_and(|state| state.git_commit == git_commit && !state.published.is_empty()))
+    }
+
+    pub fn is_published(&self, package: &PlannedPackage) -> bool {
+        self.published.contains(&id(package))
+    }
+
+    pub fn record(&mut self, package: &PlannedPackage) {
+        self.published.insert(id(package));
+    }
+}
+
+fn load_all(path: &Path) -> Result<BTreeMap<String, PublishState>> {
+    if !path.exists() {
+        return Ok(BTreeMap::new());
+    }
+    let content = fs::read_to_string(path).context(format!("failed to read {}", path.display()))?;
+    serde_json::from_str(&content).context(format!("failed to parse {}", path.display()))
+}
+
+fn id(package: &PlannedPackage) -> String {
+    format!("{}@{}", package.name, package.version)
+}
+
+/// The state a run of the plan at `git_commit` starts from.
+///
+/// Without `resume` an unfinished run of the same plan is an error, so
+/// crates are never skipped by accident; a checkpoint of another commit is
+/// stale and dropped.
+pub fn starting_state(
+    existing: Option<PublishState>,
+    git_commit: &str,
+    resume: bool,
+) -> Result<PublishState> {
+    let fresh = PublishState {
+        git_commit: git_commit.to_string(),
+        published: BTreeSet::new(),
+    };
+    match existing {
+        Some(state) if state.git_commit == git_commit => {
+            if resume {
+                Ok(state)
+            } else if state.published.is_empty() {
+                Ok(fresh)
+            } else {
+                Err(anyhow!(
+                    "an unfinished publish of {git_commit} is checkpointed with {} crate(s) published, continue it with --resume",
+                    state.published.len()
+                ))
+            }
+        }
+        Some(state) if resume => Err(anyhow!(
+            "the publish checkpoint is for commit {}, not {git_commit}",
+            state.git_commit
+        )),
+        None if resume => Err(anyhow!(
+            "no publish checkpoint to resume from, run without --resume"
+        )),
+        _ => Ok(fresh),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::path::PathBuf};
+
+    fn package(name: &str) -> PlannedPackage {
+        PlannedPackage {
+            name: name.to_string(),
+            version: String::from("2.2.0"),
+            path: PathBuf::from(name),
+            dependencies: vec![],
+        }
+    }
+
+    #[test]
+    fn test_state_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".xtask/publish-state.json");
+        assert_eq!(PublishState::load(&path, "crates-io").unwrap(), None);
+        let mut state = PublishState {
+            git_commit: String::from("0fbec9e"),
+            ..PublishState::default()
+        };
+        state.record(&package("solana-hash"));
+        state.save(&path, "crates-io").unwrap();
+        let other = PublishState {
+            git_commit: String::from("1234567"),
+            ..PublishState::default()
+        };
+        other.save(&path, "internal").unwrap();
+
+        let loaded = PublishState::load(&path, "crates-io").unwrap().unwrap();
+        assert!(loaded.is_published(&package("solana-hash")));
+        assert!(!loaded.is_published(&package("solana-sdk")));
+        assert_eq!(loaded, state);
+        assert_eq!(
+            PublishState::load(&path, "internal").unwrap(),
+            Some(other.clone())
+        );
+
+        assert!(PublishState::resumable(&path, "crates-io", "0fbec9e").unwrap());
+        assert!(!PublishState::resumable(&path, "crates-io", "1234567").unwrap());
+        // nothing published yet, so there is nothing to resume
+        assert!(!PublishState::resumable(&path, "internal", "1234567").unwrap());
+        assert!(!PublishState::resumable(&path, "mirror", "0fbec9e").unwrap());
+
+        PublishState::clear(&path, "crates-io").unwrap();
+        assert_eq!(PublishState::load(&path, "crates-io").unwrap(), None);
+        assert_eq!(PublishState::load(&path, "internal").unwrap(), Some(other));
+        PublishState::clear(&path, "mirror").unwrap();
+        PublishState::clear(&path, "internal").unwrap();
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_starting_state() {
+        let mut checkpoint = PublishState {
+            git_commit: String::from("0fbec9e"),
+            ..PublishState::default()
+        };
+        checkpoint.record(&package("solana-hash"));
+        let checkpoint = || Some(checkpoint.clone());
+
+        let resumed = starting_state(checkpoint(), "0fbec9e", true).unwrap();
+        assert!(resumed.is_published(&package("solana-hash")));
+        assert_eq!(
+            starting_state(checkpoint(), "0fbec9e", false)
+                .unwrap_err()
+                .to_string(),
+            "an unfinished publish of 0fbec9e is checkpointed with 1 crate(s) published, continue it with --resume"
+        );
+        assert!(starting_state(checkpoint(), "1234567", true).is_err());
+        assert_eq!(
+            starting_state(checkpoint(), "1234567", false).unwrap(),
+            PublishState {
+                git_commit: String::from("1234567"),
+                published: BTreeSet::new(),
+            }
+        );
+        assert!(starting_state(None, "0fbec9e", true).is_err());
+        assert_eq!(
+            starting_state(None, "0fbec9e", false).unwrap().git_commit,
+            "0fbec9e"
+        );
+    }
+}
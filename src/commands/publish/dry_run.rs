@@ -0,0 +1,320 @@
+use {
+    super::{
+        changelog,
+        plan::{PlannedPackage, PublishPlan},
+        readme::ReadmeTemplate,
+        registry::Registry,
+    },
+    crate::{
+        config::Config,
+        utils::{git::git_root_of, http::apply_cargo_network_env},
+    },
+    anyhow::{anyhow, Result},
+    serde::Serialize,
+    std::{
+        any::Any,
+        collections::{BTreeMap, BTreeSet},
+        ops::Range,
+        path::Path,
+        process::Command,
+    },
+    tracing::info,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Outcome {
+    Packaged,
+    Failed,
+    /// Not verified because cargo stopped at another crate failing first.
+    Blocked,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CrateResult {
+    pub name: String,
+    pub version: String,
+    pub outcome: Outcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Outcome of packaging every crate of the plan, by level.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct DryRunReport {
+    pub levels: Vec<Vec<CrateResult>>,
+}
+
+impl DryRunReport {
+    pub fn failed(&self) -> usize {
+        self.levels
+            .iter()
+            .flatten()
+            .filter(|result| result.outcome == Outcome::Failed)
+            .count()
+    }
+
+    /// One line per level and per crate, failures with their first error line.
+    pub fn render(&self) -> String {
+        let mut text = String::new();
+        for (level, results) in self.levels.iter().enumerate() {
+            text.push_str(&format!("level {}\n", level.saturating_add(1)));
+            for result in results {
+                let (icon, note) = match result.outcome {
+                    Outcome::Packaged => ("✅", String::new()),
+                    Outcome::Blocked => (
+                        "⏸️",
+                        String::from(": not verified, another crate failed first"),
+                    ),
+                    Outcome::Failed => (
+                        "❌",
+                        format!(
+                            ": {}",
+                            result
+                                .error
+                                .as_deref()
+                                .and_then(|e| e.lines().find(|l| l.contains("error")))
+                                .unwrap_or("packaging failed")
+                                .trim()
+                        ),
+                    ),
+                };
+                text.push_str(&format!(
+                    "  {icon} {}@{}{note}\n",
+                    result.name, result.version
+                ));
+            }
+        }
+        text
+    }
+}
+
+/// What a dry run covers besides packaging.
+pub struct DryRun<'a> {
+    /// Levels of the plan a real run with the same flags would publish.
+    pub levels: Range<usize>,
+    pub readmes: Option<&'a ReadmeTemplate>,
+    pub include_changelog: bool,
+}
+
+/// Packages and verifies the selected crates of `plan` without uploading
+/// anything, printing the report as JSON on stdout. Fails when a crate does
+/// not package.
+///
+/// All crates go through a single `cargo package`, which verifies each one
+/// against the tarballs of its workspace dependencies rather than crates.io,
+/// so crates depending on unreleased versions are verified too.
+pub fn dry_run(
+    workspace_root: &Path,
+    plan: &PublishPlan,
+    options: &DryRun,
+    registry: &Registry,
+    config: &Config,
+) -> Result<()> {
+    let selected: Vec<&PlannedPackage> = plan.levels[options.levels.clone()]
+        .iter()
+        .flatten()
+        .collect();
+    let repo = git_root_of(workspace_root)?;
+    changelog::remove_leftovers(workspace_root, plan.levels.iter().flatten())?;
+    // dropping the guards removes the generated files again
+    let mut injected = vec![];
+    for package in selected.iter() {
+        if let Some(readmes) = options.readmes {
+            injected.push(
+                readmes
+                    .inject(workspace_root, package, &plan.git_commit)?
+                    .map(|readme| Box::new(readme) as Box<dyn Any>),
+            );
+        }
+        if options.include_changelog {
+            injected.push(
+                changelog::inject(&repo, workspace_root, package, config)?
+                    .map(|changelog| Box::new(changelog) as Box<dyn Any>),
+            );
+        }
+    }
+
+    info!("packaging {} crate(s)", selected.len());
+    let mut cmd = Command::new("cargo");
+    cmd.args(["package", "--allow-dirty", "--manifest-path"])
+        .arg(workspace_root.join("Cargo.toml"));
+    for package in selected.iter() {
+        cmd.args(["--package", &package.name]);
+    }
+    registry.apply(&mut cmd, config)?;
+    apply_cargo_network_env(&mut cmd, &config.network);
+    let output = cmd
+        .output()
+        .map_err(|e| anyhow!("failed to run cargo package: {e}"))?;
+    drop(injected);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let mut results = outcomes(output.status.success(), &stderr, &selected);
+
+    let mut report = DryRunReport::default();
+    for packages in plan.levels[options.levels.clone()].iter() {
+        let mut level: Vec<CrateResult> = packages
+            .iter()
+            .filter_map(|package| results.remove(&package.name))
+            .collect();
+        level.sort_by(|a, b| a.name.cmp(&b.name));
+        report.levels.push(level);
+    }
+    info!("\n{}", report.render());
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    let failed = report.failed();
+    if failed > 0 {
+        return Err(anyhow!("{failed} crate(s) would fail to package"));
+    }
+    Ok(())
+}
+
+/// Splits the output of a multi-crate `cargo package` by crate. cargo stops
+/// at the first crate failing to package or verify, which is the last one it
+/// announced; the crates it verified before it passed, the rest were never
+/// verified.
+pub fn outcomes(
+    success: bool,
+    stderr: &str,
+    packages: &[&PlannedPackage],
+) -> BTreeMap<String, CrateResult> {
+    let mut verified = BTreeSet::new();
+    let mut verifying: Option<&str> = None;
+    let mut current: Option<(&str, usize)> = None;
+    for (offset, line) in line_offsets(stderr) {
+        let line = line.trim_start();
+        for (prefix, is_verify) in [("Packaging ", false), ("Verifying ", true)] {
+            let Some(name) = line
+                .strip_prefix(prefix)
+                .and_then(|rest| rest.split_whitespace().next())
+            else {
+                continue;
+            };
+            if is_verify {
+                verified.extend(verifying.replace(name));
+            }
+            current = Some((name, offset));
+        }
+    }
+    packages
+        .iter()
+        .map(|package| {
+            let (outcome, error) = match current {
+                _ if success => (Outcome::Packaged, None),
+                Some((name, offset)) if name == package.name => {
+                    (Outcome::Failed, Some(stderr[offset..].to_string()))
+                }
+                // cargo failed before naming any crate, e.g. on the manifest
+                None => (Outcome::Failed, Some(stderr.to_string())),
+                _ if verified.contains(package.name.as_str()) => (Outcome::Packaged, None),
+                _ => (Outcome::Blocked, None),
+            };
+            (
+                package.name.clone(),
+                CrateResult {
+                    name: package.name.clone(),
+                    version: package.version.clone(),
+                    outcome,
+                    error,
+                },
+            )
+        })
+        .collect()
+}
+
+/// Lines of `text` with the byte offset each starts at.
+fn line_offsets(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_inclusive('\n').scan(0, |offset, line| {
+        let start = *offset;
+        *offset += line.len();
+        Some((start, line))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_outcomes() {
+        let package = |name: &str| PlannedPackage {
+            name: name.to_string(),
+            version: String::from("2.3.0"),
+            path: name.into(),
+            dependencies: vec![],
+        };
+        let packages = [package("a"), package("b"), package("c")];
+        let packages: Vec<&PlannedPackage> = packages.iter().collect();
+        let stderr = "   Packaging a v2.3.0 (/w/a)
+   Packaging b v2.3.0 (/w/b)
+   Packaging c v2.3.0 (/w/c)
+   Verifying a v2.3.0 (/w/a)
+    Finished `dev` profile
+   Verifying b v2.3.0 (/w/b)
+   Unpacking a v2.3.0 (registry `/w/target/package/tmp-registry`)
+error: could not compile `b` (lib) due to 1 previous error
+error: failed to verify package tarball
+";
+        let outcome = |results: &BTreeMap<String, CrateResult>, name: &str| results[name].outcome;
+        let results = outcomes(false, stderr, &packages);
+        assert_eq!(outcome(&results, "a"), Outcome::Packaged);
+        assert_eq!(outcome(&results, "b"), Outcome::Failed);
+        assert!(results["b"]
+            .error
+            .as_deref()
+            .unwrap()
+            .starts_with("   Verifying b v2.3.0"));
+        assert_eq!(outcome(&results, "c"), Outcome::Blocked);
+
+        let results = outcomes(true, stderr, &packages);
+        assert!(results.values().all(|r| r.outcome == Outcome::Packaged));
+
+        // failing while packaging, before anything was verified
+        let results = outcomes(false, "error: failed to load manifest\n", &packages);
+        assert!(results.values().all(|r| r.outcome == Outcome::Failed));
+        let results = outcomes(
+            false,
+            "   Packaging a v2.3.0 (/w/a)\nerror: missing field `description`\n",
+            &packages,
+        );
+        assert_eq!(outcome(&results, "a"), Outcome::Failed);
+        assert_eq!(outcome(&results, "b"), Outcome::Blocked);
+    }
+
+    #[test]
+    fn test_report() {
+        let result = |name: &str, outcome: Outcome, error: Option<&str>| CrateResult {
+            name: name.to_string(),
+            version: String::from("2.3.0"),
+            outcome,
+            error: error.map(str::to_string),
+        };
+        let report = DryRunReport {
+            levels: vec![
+                vec![result("solana-hash", Outcome::Packaged, None)],
+                vec![
+                    result(
+                        "solana-sdk",
+                        Outcome::Failed,
+                        Some("   Packaging solana-sdk\nerror: could not compile `solana-sdk`\n"),
+                    ),
+                    result("solana-program", Outcome::Blocked, Some("...")),
+                ],
+            ],
+        };
+        assert_eq!(report.failed(), 1);
+        assert_eq!(
+            report.render(),
+            "level 1
+  ✅ solana-hash@2.3.0
+level 2
+  ❌ solana-sdk@2.3.0: error: could not compile `solana-sdk`
+  ⏸️ solana-program@2.3.0: not verified, another crate failed first
+"
+        );
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["levels"][0][0]["outcome"], "packaged");
+        assert!(json["levels"][0][0].get("error").is_none());
+        assert_eq!(json["levels"][1][1]["outcome"], "blocked");
+    }
+}
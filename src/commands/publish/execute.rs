@@ -0,0 +1,721 @@
+use {
+    super::{
+        changelog,
+        checkpoint::{starting_state, PublishState},
+        compute_publish_order_data_for,
+        dry_run::{dry_run, DryRun},
+        guard::{preflight, Overrides},
+        plan::{PlannedPackage, PublishPlan},
+        readme::ReadmeTemplate,
+        registry::Registry,
+        rehearse::rehearse,
+        select::Selection,
+    },
+    crate::{
+        commands::check_version_monotonic,
+        config::{Config, NetworkConfig, PublishConfig},
+        failure::Failure,
+        utils::{
+            get_head_commit,
+            git::git_root_of,
+            http::apply_cargo_network_env,
+            lock::LockGuard,
+            notify,
+            registry::{fetch_versions, wait_for_versions},
+            signature, telemetry,
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    clap::Args,
+    std::{
+        collections::VecDeque,
+        fs,
+        io::{self, BufRead, BufReader},
+        ops::Range,
+        path::{Path, PathBuf},
+        process::{Command, ExitStatus, Stdio},
+        sync::Mutex,
+        thread,
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    },
+    tracing::{info, info_span, warn},
+};
+
+/// Lock scope held for the duration of a publish run.
+pub const RELEASE_LOCK_SCOPE: &str = "release";
+
+#[derive(Args, Debug, Default)]
+pub struct ExecuteArgs {
+    /// Publish plan produced by `publish plan`. Computed on the fly when omitted.
+    #[arg(long)]
+    pub plan: Option<PathBuf>,
+
+    /// Public key (minisign `.pub` or GPG) the plan signature must verify against.
+    #[arg(long, requires = "plan")]
+    pub require_signature: Option<PathBuf>,
+
+    /// Detached signature of the plan. Defaults to `<plan>.minisig`, `<plan>.asc` or `<plan>.sig`.
+    #[arg(long, requires = "require_signature")]
+    pub signature: Option<PathBuf>,
+
+    /// Skip the levels before this one (1-based, as numbered in the plan).
+    #[arg(long, requires = "plan", conflicts_with = "only_level")]
+    pub from_level: Option<usize>,
+
+    /// Publish only this level (1-based, as numbered in the plan).
+    #[arg(long, requires = "plan")]
+    pub only_level: Option<usize>,
+
+    /// Publish to a throwaway local registry and build a consumer against it
+    /// instead of publishing to crates.io.
+    #[arg(long)]
+    pub rehearse: bool,
+
+    /// Registry from the cargo config to rehearse against instead of
+    /// starting a kellnr container.
+    #[arg(long, requires = "rehearse")]
+    pub rehearse_registry: Option<String>,
+
+    /// Package every crate of the plan level by level without publishing,
+    /// reporting which would fail as text and as JSON on stdout.
+    #[arg(long, conflicts_with = "rehearse")]
+    pub dry_run: bool,
+
+    /// Generate a README from `publish.readme-template` for crates without
+    /// one. The file only exists while the crate is packaged.
+    #[arg(long)]
+    pub generate_readme: bool,
+
+    /// Copy the section for the published version of crate changelogs kept
+    /// outside their crate (`lint.changelog.paths`) into the package. The file
+    /// only exists while the crate is packaged.
+    #[arg(long)]
+    pub include_changelog: bool,
+
+    /// Crates of one level published concurrently, 1 publishes them one at a
+    /// time [default: publish.jobs in xtask.toml].
+    #[arg(long)]
+    pub jobs: Option<usize>,
+
+    /// Seconds to wait after each crate [default: publish.delay-between-crates-secs].
+    #[arg(long)]
+    pub delay_between_crates: Option<u64>,
+
+    /// Seconds to wait between levels [default: publish.delay-between-levels-secs].
+    #[arg(long)]
+    pub delay_between_levels: Option<u64>,
+
+    /// Tag being released, which must point at HEAD [default: v<workspace version>].
+    #[arg(long)]
+    pub tag: Option<String>,
+
+    /// Publish from a working tree with uncommitted changes; recorded in the audit log.
+    /// Not allowed with --require-signature, the signed plan does not cover them.
+    #[arg(long, conflicts_with = "require_signature")]
+    pub allow_dirty: bool,
+
+    /// Publish from a branch outside publish.allowed-branches; recorded in the audit log.
+    #[arg(long)]
+    pub allow_branch: bool,
+
+    /// Publish a HEAD the release tag does not point at; recorded in the audit log.
+    #[arg(long)]
+    pub allow_untagged: bool,
+
+    /// Skip the crates an interrupted run recorded in publish.state and
+    /// continue from the level that failed.
+    #[arg(long)]
+    pub resume: bool,
+}
+
+/// The packages whose version the index at `index_url` already lists.
+pub fn already_published<'a>(
+    network: &NetworkConfig,
+    index_url: &str,
+    token: Option<&str>,
+    packages: &[&'a PlannedPackage],
+) -> Result<Vec<&'a PlannedPackage>> {
+    let mut listed = vec![];
+    for package in packages {
+        let entries = fetch_versions(network, index_url, token, &package.name)?;
+        if entries.iter().any(|entry| entry.vers == package.version) {
+            listed.push(*package);
+        }
+    }
+    Ok(listed)
+}
+
+/// Polling of the index for a published level before the next one starts,
+/// since its crates only resolve for dependents once the index lists them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexWait {
+    pub timeout: Duration,
+    pub poll_interval: Duration,
+}
+
+/// How fast crates are pushed to the registry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pacing {
+    pub jobs: usize,
+    pub between_crates: Duration,
+    pub between_levels: Duration,
+}
+
+impl Pacing {
+    /// Command line options win over `[publish]` in `xtask.toml`.
+    pub fn new(args: &ExecuteArgs, config: &PublishConfig) -> Self {
+        Pacing {
+            jobs: args.jobs.unwrap_or(config.jobs).max(1),
+            between_crates: Duration::from_secs(
+                args.delay_between_crates
+                    .unwrap_or(config.delay_between_crates_secs),
+            ),
+            between_levels: Duration::from_secs(
+                args.delay_between_levels
+                    .unwrap_or(config.delay_between_levels_secs),
+            ),
+        }
+    }
+}
+
+pub fn publish_execute(
+    manifest_path: &str,
+    args: &ExecuteArgs,
+    index_wait: &IndexWait,
+    registry: &Registry,
+    selection: &Selection,
+    config: &Config,
+) -> Result<()> {
+    let data =
+        compute_publish_order_data_for(manifest_path, registry.display_name(), selection, config)?;
+    let current_plan = PublishPlan::from_order_data(&data, get_head_commit(&data.workspace_root)?);
+
+    let plan = match &args.plan {
+        Some(plan_path) => {
+            // the bytes verified are the bytes executed, even if the file
+            // changes in between
+            let content =
+                fs::read(plan_path).context(format!("failed to read {}", plan_path.display()))?;
+            if let Some(public_key) = &args.require_signature {
+                let signature = match &args.signature {
+                    Some(signature) => signature.clone(),
+                    None => signature::find_detached_signature(plan_path).ok_or_else(|| {
+                        anyhow!("no detached signature found for {}", plan_path.display())
+                    })?,
+                };
+                let verified = tempfile::NamedTempFile::new()?;
+                fs::write(verified.path(), &content)?;
+                signature::verify_detached_signature(verified.path(), &signature, public_key)?;
+                info!("✅ plan signature verified with {}", public_key.display());
+            }
+            let plan = PublishPlan::parse(&content, plan_path)?;
+            plan.verify_matches(&current_plan).map_err(|e| {
+                Failure::Verification.error(format!("plan does not match the workspace: {e}"))
+            })?;
+            plan
+        }
+        None => current_plan,
+    };
+
+    let levels = selected_levels(plan.levels.len(), args.from_level, args.only_level)?;
+    let readmes = if args.generate_readme {
+        Some(ReadmeTemplate::load(manifest_path, config)?)
+    } else {
+        None
+    };
+    if args.rehearse {
+        return rehearse(
+            &data.workspace_root,
+            &plan,
+            levels,
+            args.rehearse_registry.as_deref(),
+            config,
+        );
+    }
+    if args.dry_run {
+        return dry_run(
+            &data.workspace_root,
+            &plan,
+            &DryRun {
+                levels,
+                readmes: readmes.as_ref(),
+                include_changelog: args.include_changelog,
+            },
+            registry,
+            config,
+        );
+    }
+    telemetry::phase("preflight", || {
+        preflight(
+            &data.workspace_root,
+            config,
+            args.tag.as_deref(),
+            &Overrides {
+                allow_dirty: args.allow_dirty,
+                allow_branch: args.allow_branch,
+                allow_untagged: args.allow_untagged,
+            },
+        )
+    })?;
+    let _lock = LockGuard::acquire(config, RELEASE_LOCK_SCOPE)?;
+
+    if levels.start > 0 || levels.end < plan.levels.len() {
+        info!(
+            "publishing level(s) {} to {} of {}",
+            levels.start.saturating_add(1),
+            levels.end,
+            plan.levels.len()
+        );
+    }
+    let selected: Vec<&PlannedPackage> = plan.levels[levels.clone()].iter().flatten().collect();
+    let index_token = registry.index_token(config)?;
+    let listed = already_published(
+        &config.network,
+        &registry.index_url,
+        index_token.as_deref(),
+        &selected,
+    )?;
+    for package in listed.iter() {
+        info!(
+            "  ⏭️ {}@{} already published",
+            package.name, package.version
+        );
+    }
+    check_version_monotonic::verify(
+        selected.iter().copied().filter(|p| !listed.contains(p)),
+        &registry.index_url,
+        config,
+    )?;
+    let repo = git_root_of(&data.workspace_root)?;
+    changelog::remove_leftovers(&data.workspace_root, plan.levels.iter().flatten())?;
+    let state_path = config.resolve(&config.publish.state);
+    let mut state = starting_state(
+        PublishState::load(&state_path, registry.display_name())?,
+        &plan.git_commit,
+        args.resume,
+    )?;
+    // a re-run of a partially published release skips what is on the index
+    for package in listed.iter() {
+        state.record(package);
+    }
+    let state = Mutex::new(state);
+    let pacing = Pacing::new(args, &config.publish);
+    let mut first = true;
+    let mut published = 0usize;
+    for (level, packages) in plan.levels.iter().enumerate() {
+        if !levels.contains(&level) {
+            continue;
+        }
+        let packages: Vec<PlannedPackage> = {
+            let state = state
+                .lock()
+                .map_err(|_| anyhow!("publish checkpoint lock poisoned"))?;
+            packages
+                .iter()
+                .filter(|package| !state.is_published(package))
+                .cloned()
+                .collect()
+        };
+        if packages.is_empty() {
+            info!("level {} already published", level.saturating_add(1));
+            continue;
+        }
+        if !first && !pacing.between_levels.is_zero() {
+            info!("waiting {:?} before the next level", pacing.between_levels);
+            thread::sleep(pacing.between_levels);
+        }
+        first = false;
+        info!("publishing level: {}", level.saturating_add(1));
+        let level_started = Instant::now();
+        let errors = publish_level(&packages, &pacing, |package| {
+            let _span = info_span!(
+                "publish_crate",
+                name = %package.name,
+                version = %package.version
+            )
+            .entered();
+            let readme = match &readmes {
+                Some(readmes) => readmes.inject(&data.workspace_root, package, &plan.git_commit)?,
+                None => None,
+            };
+            let changelog = if args.include_changelog {
+                changelog::inject(&repo, &data.workspace_root, package, config)?
+            } else {
+                None
+            };
+            // the generated README and copied changelog are untracked, so the
+            // tree is dirty
+            let allow_dirty = readme.is_some() || changelog.is_some();
+            if let Err(e) =
+                publish_package(&data.workspace_root, package, registry, config, allow_dirty)
+            {
+                let text = format!("❌ {e:#}");
+                if let Err(notify_error) = notify::send_to_owners(config, &[&package.name], &text) {
+                    warn!(
+                        "failed to notify the owners of {}: {notify_error:#}",
+                        package.name
+                    );
+                }
+                return Err(e);
+            }
+            info!("  ✅ {}@{} published", package.name, package.version);
+            let mut state = state
+                .lock()
+                .map_err(|_| anyhow!("publish checkpoint lock poisoned"))?;
+            state.record(package);
+            state.save(&state_path, registry.display_name())
+        });
+        telemetry::record(
+            &format!("level-{}", level.saturating_add(1)),
+            level_started.elapsed(),
+            errors.is_empty(),
+        );
+        published = published.saturating_add(packages.len().saturating_sub(errors.len()));
+        if !errors.is_empty() {
+            let message = format!(
+                "failed to publish {} crate(s) in level {}:\n{}",
+                errors.len(),
+                level.saturating_add(1),
+                errors
+                    .iter()
+                    .map(|e| format!("  - {e:#}"))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            );
+            return Err(if published > 0 {
+                Failure::PartialPublish.error(message)
+            } else {
+                anyhow!(message)
+            });
+        }
+        let crates: Vec<(String, String)> = packages
+            .iter()
+            .map(|p| (p.name.clone(), p.version.clone()))
+            .collect();
+        info!(
+            "⏳ waiting for level {} to reach the index",
+            level.saturating_add(1)
+        );
+        wait_for_versions(
+            &config.network,
+            &registry.index_url,
+            index_token.as_deref(),
+            &crates,
+            index_wait.timeout,
+            index_wait.poll_interval,
+        )
+        .map_err(|e| Failure::PartialPublish.error(format!("{e:#}")))?;
+    }
+    // a run restricted to some levels leaves the checkpoint for the rest
+    if levels == (0..plan.levels.len()) {
+        PublishState::clear(&state_path, registry.display_name())?;
+    }
+    Ok(())
+}
+
+/// Publishes the crates of one level with `pacing.jobs` workers, each pausing
+/// `pacing.between_crates` after a crate. A failure does not stop the other
+/// crates of the level, they do not depend on each other.
+pub fn publish_level(
+    packages: &[PlannedPackage],
+    pacing: &Pacing,
+    publish: impl Fn(&PlannedPackage) -> Result<()> + Sync,
+) -> Vec<anyhow::Error> {
+    let queue = Mutex::new(packages.iter().collect::<VecDeque<_>>());
+    let errors = Mutex::new(vec![]);
+    thread::scope(|scope| {
+        for _ in 0..pacing.jobs.min(packages.len()) {
+            let (queue, errors, publish) = (&queue, &errors, &publish);
+            scope.spawn(move || {
+                while let Some(package) = queue.lock().ok().and_then(|mut q| q.pop_front()) {
+                    if let Err(e) = publish(package) {
+                        if let Ok(mut errors) = errors.lock() {
+                            errors.push(e);
+                        }
+                    }
+                    if !pacing.between_crates.is_zero() {
+                        thread::sleep(pacing.between_crates);
+                    }
+                }
+            });
+        }
+    });
+    errors.into_inner().unwrap_or_default()
+}
+
+/// Zero-based range of plan levels to publish for the 1-based `--from-level`
+/// or `--only-level`.
+pub fn selected_levels(
+    level_count: usize,
+    from_level: Option<usize>,
+    only_level: Option<usize>,
+) -> Result<Range<usize>> {
+    let (start, end) = match (from_level, only_level) {
+        (_, Some(only)) => (only, only),
+        (Some(from), None) => (from, level_count),
+        (None, None) => return Ok(0..level_count),
+    };
+    if start == 0 || start > level_count {
+        return Err(anyhow!(
+            "level {start} does not exist, the plan has levels 1 to {level_count}"
+        ));
+    }
+    Ok(start.saturating_sub(1)..end)
+}
+
+fn publish_package(
+    workspace_root: &Path,
+    package: &PlannedPackage,
+    registry: &Registry,
+    config: &Config,
+    allow_dirty: bool,
+) -> Result<()> {
+    let manifest_path = workspace_root.join(&package.path).join("Cargo.toml");
+    let mut cmd = Command::new("cargo");
+    cmd.arg("publish")
+        .arg("--manifest-path")
+        .arg(&manifest_path);
+    if allow_dirty {
+        cmd.arg("--allow-dirty");
+    }
+    registry.apply(&mut cmd, config)?;
+    apply_cargo_network_env(&mut cmd, &config.network);
+    let mut attempt = 0;
+    loop {
+        let (status, stderr) = run_prefixed(&mut cmd, &format!("[{}]", package.name))
+            .map_err(|e| anyhow!("failed to run cargo publish: {e}"))?;
+        if status.success() {
+            return Ok(());
+        }
+        if !is_rate_limited(&stderr) || attempt >= config.publish.rate_limit_retries {
+            return Err(anyhow!(
+                "failed to publish {}@{} to {}: {stderr}",
+                package.name,
+                package.version,
+                registry.display_name(),
+            ));
+        }
+        attempt = attempt.saturating_add(1);
+        let delay = with_jitter(
+            rate_limit_backoff(attempt, &config.publish),
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.subsec_nanos())
+                .unwrap_or_default(),
+        );
+        warn!(
+            "  [{}] rate limited by {}, retry {attempt}/{} in {}s",
+            package.name,
+            registry.display_name(),
+            config.publish.rate_limit_retries,
+            delay.as_secs()
+        );
+        thread::sleep(delay);
+    }
+}
+
+/// Whether `cargo publish` failed on the registry's rate limit rather than
+/// on the crate.
+fn is_rate_limited(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    stderr.contains("429 too many requests")
+        || stderr.contains("status 429")
+        || stderr.contains("published too many")
+}
+
+/// Wait before rate-limited `attempt` (1-based): the configured backoff,
+/// doubled per attempt up to the configured maximum.
+fn rate_limit_backoff(attempt: u32, config: &PublishConfig) -> Duration {
+    let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+    Duration::from_secs(
+        config
+            .rate_limit_backoff_secs
+            .saturating_mul(factor)
+            .min(config.rate_limit_max_backoff_secs),
+    )
+}
+
+/// Adds up to a quarter of `delay`, picked by `seed`, so crates published
+/// side by side do not retry in lockstep.
+fn with_jitter(delay: Duration, seed: u32) -> Duration {
+    delay.saturating_add(delay.mul_f64(f64::from(seed % 1000) / 4000.0))
+}
+
+/// Runs `cmd`, logging each line of its stderr behind `prefix` as it comes
+/// so the progress of crates published side by side stays apart. Returns
+/// the exit status and the stderr.
+fn run_prefixed(cmd: &mut Command, prefix: &str) -> io::Result<(ExitStatus, String)> {
+    let mut child = cmd.stdout(Stdio::null()).stderr(Stdio::piped()).spawn()?;
+    let mut stderr = String::new();
+    // the child is reaped even when reading its output fails
+    let read = child.stderr.take().map_or(Ok(()), |pipe| {
+        let mut reader = BufReader::new(pipe);
+        let mut buffer = vec![];
+        loop {
+            buffer.clear();
+            if reader.read_until(b'\n', &mut buffer)? == 0 {
+                return Ok(());
+            }
+            // build scripts and registries may print non UTF-8 output
+            let line = String::from_utf8_lossy(&buffer);
+            let line = line.trim_end_matches(['\n', '\r']);
+            if !line.trim().is_empty() {
+                info!("  {prefix} {}", line.trim());
+            }
+            stderr.push_str(line);
+            stderr.push('\n');
+        }
+    });
+    let status = child.wait()?;
+    read.map(|()| (status, stderr))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn package(name: &str) -> PlannedPackage {
+        PlannedPackage {
+            name: name.to_string(),
+            version: String::from("1.0.0"),
+            path: PathBuf::from(name),
+            dependencies: vec![],
+        }
+    }
+
+    #[test]
+    fn test_already_published() {
+        let index = tempfile::tempdir().unwrap();
+        let entry = |name: &str, version: &str| {
+            format!("{{\"name\":\"{name}\",\"vers\":\"{version}\",\"yanked\":false}}\n")
+        };
+        for (name, versions) in [
+            ("solana-hash", ["0.9.0", "1.0.0"]),
+            ("solana-sdk", ["0.8.0", "0.9.0"]),
+        ] {
+            let path = index.path().join(crate::utils::registry::index_path(name));
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, versions.map(|v| entry(name, v)).concat()).unwrap();
+        }
+        let hash = package("solana-hash");
+        let sdk = package("solana-sdk");
+        assert_eq!(
+            already_published(
+                &NetworkConfig::default(),
+                &format!("file://{}", index.path().display()),
+                None,
+                &[&hash, &sdk],
+            )
+            .unwrap(),
+            vec![&hash]
+        );
+    }
+
+    #[test]
+    fn test_pacing() {
+        let config = PublishConfig {
+            jobs: 4,
+            delay_between_levels_secs: 30,
+            ..PublishConfig::default()
+        };
+        let args = ExecuteArgs {
+            jobs: Some(0),
+            delay_between_crates: Some(2),
+            ..ExecuteArgs::default()
+        };
+        assert_eq!(
+            Pacing::new(&args, &config),
+            Pacing {
+                jobs: 1,
+                between_crates: Duration::from_secs(2),
+                between_levels: Duration::from_secs(30),
+            }
+        );
+        assert_eq!(Pacing::new(&ExecuteArgs::default(), &config).jobs, 4);
+    }
+
+    #[test]
+    fn test_publish_level() {
+        let packages: Vec<PlannedPackage> = ["a", "b", "c", "d"].map(package).to_vec();
+        let pacing = Pacing {
+            jobs: 3,
+            between_crates: Duration::ZERO,
+            between_levels: Duration::ZERO,
+        };
+        let published = Mutex::new(vec![]);
+        let errors = publish_level(&packages, &pacing, |package| {
+            if package.name == "c" {
+                return Err(anyhow!("c is broken"));
+            }
+            published.lock().unwrap().push(package.name.clone());
+            Ok(())
+        });
+        let mut published = published.into_inner().unwrap();
+        published.sort();
+        assert_eq!(published, vec!["a", "b", "d"]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].to_string(), "c is broken");
+    }
+
+    #[test]
+    #[cfg(unix)] // the child is an `sh` script
+    fn test_run_prefixed() {
+        let (status, stderr) = run_prefixed(
+            Command::new("sh").args(["-c", "echo out; echo '   Packaging a' >&2; exit 3"]),
+            "[a]",
+        )
+        .unwrap();
+        assert_eq!(status.code(), Some(3));
+        assert_eq!(stderr, "   Packaging a\n");
+
+        let (status, stderr) = run_prefixed(
+            Command::new("sh").args(["-c", "printf 'bad \\377 byte\\nnext\\n' >&2; exit 4"]),
+            "[a]",
+        )
+        .unwrap();
+        assert_eq!(status.code(), Some(4));
+        assert_eq!(stderr, "bad \u{fffd} byte\nnext\n");
+    }
+
+    #[test]
+    fn test_rate_limit_backoff() {
+        assert!(is_rate_limited(
+            "error: failed to publish to registry at https://crates.io\n\nCaused by:\n  the remote server responded with an error (status 429 Too Many Requests): You have published too many new crates in a short period of time."
+        ));
+        assert!(!is_rate_limited(
+            "error: crate version `1.0.0` is already uploaded"
+        ));
+
+        let config = PublishConfig {
+            rate_limit_backoff_secs: 60,
+            rate_limit_max_backoff_secs: 300,
+            ..PublishConfig::default()
+        };
+        let delays: Vec<u64> = (1..=5)
+            .map(|attempt| rate_limit_backoff(attempt, &config).as_secs())
+            .collect();
+        assert_eq!(delays, vec![60, 120, 240, 300, 300]);
+        assert_eq!(
+            with_jitter(Duration::from_secs(60), 0),
+            Duration::from_secs(60)
+        );
+        assert_eq!(
+            with_jitter(Duration::from_secs(60), 1_500),
+            Duration::from_millis(67_500)
+        );
+        assert!(with_jitter(Duration::from_secs(60), 999) < Duration::from_secs(75));
+    }
+
+    #[test]
+    fn test_selected_levels() {
+        assert_eq!(selected_levels(4, None, None).unwrap(), 0..4);
+        assert_eq!(selected_levels(4, Some(2), None).unwrap(), 1..4);
+        assert_eq!(selected_levels(4, None, Some(3)).unwrap(), 2..3);
+        assert_eq!(selected_levels(4, None, Some(4)).unwrap(), 3..4);
+        assert_eq!(
+            selected_levels(4, Some(5), None).unwrap_err().to_string(),
+            "level 5 does not exist, the plan has levels 1 to 4"
+        );
+        assert!(selected_levels(4, None, Some(0)).is_err());
+    }
+}
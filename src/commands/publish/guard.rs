@@ -0,0 +1,260 @@
+use {
+    crate::{
+        commands::scan_secrets::{scan_crates, unallowed},
+        config::Config,
+        failure::Failure,
+        utils::{
+            audit::{self, AuditEntry, AuditOutcome},
+            cargo::wildcard_match,
+            cargo::workspace_version,
+            get_head_commit,
+            git::{branches_containing_head, current_branch, status_lines, tag_commit},
+        },
+    },
+    anyhow::Result,
+    log::{info, warn},
+    std::path::Path,
+};
+
+/// Preflight checks the operator explicitly chose to skip.
+#[derive(Debug, Clone, Default)]
+pub struct Overrides {
+    pub allow_dirty: bool,
+    pub allow_branch: bool,
+    pub allow_untagged: bool,
+}
+
+/// What the preflight checks look at.
+#[derive(Debug)]
+pub struct RepoState {
+    /// `git status --porcelain` lines.
+    pub changes: Vec<String>,
+    /// Checked out branch, `None` on a detached HEAD.
+    pub branch: Option<String>,
+    /// Branches containing HEAD, consulted on a detached HEAD as CI has.
+    pub branches_containing_head: Vec<String>,
+    pub head: String,
+    pub tag: Option<String>,
+    pub tag_commit: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Violation {
+    Dirty(Vec<String>),
+    Branch(Vec<String>),
+    Untagged(Option<String>),
+    TagMismatch { tag: String, commit: String },
+}
+
+impl Violation {
+    pub fn message(&self) -> String {
+        match self {
+            Violation::Dirty(changes) => format!(
+                "the working tree has {} uncommitted change(s): {}",
+                changes.len(),
+                changes
+                    .iter()
+                    .take(5)
+                    .map(|c| c.trim())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Violation::Branch(branches) if branches.is_empty() => {
+                String::from("HEAD is on no branch allowed by publish.allowed-branches")
+            }
+            Violation::Branch(branches) => format!(
+                "{} not allowed by publish.allowed-branches",
+                branches.join(", ")
+            ),
+            Violation::Untagged(Some(tag)) => format!("tag {tag} does not exist"),
+            Violation::Untagged(None) => {
+                String::from("no workspace version to derive the release tag from, pass --tag")
+            }
+            Violation::TagMismatch { tag, commit } => {
+                format!("tag {tag} points at {commit}, not at HEAD")
+            }
+        }
+    }
+
+    /// The flag that skips this check, when given.
+    fn overridden_by(&self, overrides: &Overrides) -> Option<&'static str> {
+        let (flag, set) = match self {
+            Violation::Dirty(_) => ("--allow-dirty", overrides.allow_dirty),
+            Violation::Branch(_) => ("--allow-branch", overrides.allow_branch),
+            Violation::Untagged(_) | Violation::TagMismatch { .. } => {
+                ("--allow-untagged", overrides.allow_untagged)
+            }
+        };
+        set.then_some(flag)
+    }
+}
+
+/// Refuses to publish from a dirty tree, a branch outside
+/// `publish.allowed-branches` or a HEAD the release tag does not point at.
+/// Every check skipped through an override is recorded in the audit log.
+pub fn preflight(
+    workspace_root: &Path,
+    config: &Config,
+    tag: Option<&str>,
+    overrides: &Overrides,
+) -> Result<()> {
+    let tag = match tag {
+        Some(tag) => Some(tag.to_string()),
+        // the workspace being published, which with `--ref` is a worktree
+        // rather than the checkout xtask runs in
+        None => workspace_version(&workspace_root.join("Cargo.toml"))
+            .ok()
+            .map(|v| format!("v{v}")),
+    };
+    let state = RepoState {
+        changes: status_lines(workspace_root, &config.state_paths())?,
+        branch: current_branch(workspace_root)?,
+        branches_containing_head: branches_containing_head(workspace_root)?,
+        head: get_head_commit(workspace_root)?,
+        tag_commit: match &tag {
+            Some(tag) => tag_commit(workspace_root, tag)?,
+            None => None,
+        },
+        tag,
+    };
+
+    let mut failures = vec![];
+    for violation in check(&state, &config.publish.allowed_branches) {
+        match violation.overridden_by(overrides) {
+            Some(flag) => {
+                let message = format!("{flag}: {}", violation.message());
+                warn!("⚠️ preflight check skipped with {message}");
+                let entry = AuditEntry::new(
+                    "publish-preflight-override",
+                    vec![message],
+                    AuditOutcome::Success,
+                );
+                audit::record(config, &entry)?;
+            }
+            None => failures.push(violation.message()),
+        }
+    }
+    if config.scan_secrets.preflight {
+        let findings = scan_crates(&workspace_root.join("Cargo.toml"), &[], config)?;
+        failures.extend(
+            unallowed(findings, &config.scan_secrets.allow)
+                .iter()
+                .map(|finding| format!("possible secret in {finding}, see scan-secrets")),
+        );
+    }
+    if !failures.is_empty() {
+        return Err(Failure::Preflight.error(format!(
+            "publish preflight failed:\n{}",
+            failures
+                .iter()
+                .map(|f| format!("  - {f}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )));
+    }
+    info!("✅ publish preflight passed");
+    Ok(())
+}
+
+pub fn check(state: &RepoState, allowed_branches: &[String]) -> Vec<Violation> {
+    let mut violations = vec![];
+    if !state.changes.is_empty() {
+        violations.push(Violation::Dirty(state.changes.clone()));
+    }
+
+    if !allowed_branches.is_empty() {
+        let candidates = match &state.branch {
+            Some(branch) => vec![branch.clone()],
+            None => state.branches_containing_head.clone(),
+        };
+        let allowed = candidates.iter().any(|branch| {
+            allowed_branches
+                .iter()
+                .any(|pattern| wildcard_match(pattern, branch))
+        });
+        if !allowed {
+            violations.push(Violation::Branch(candidates));
+        }
+    }
+
+    match (&state.tag, &state.tag_commit) {
+        (Some(tag), Some(commit)) if *commit != state.head => {
+            violations.push(Violation::TagMismatch {
+                tag: tag.clone(),
+                commit: commit.clone(),
+            })
+        }
+        (tag, None) => violations.push(Violation::Untagged(tag.clone())),
+        _ => {}
+    }
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn clean_state() -> RepoState {
+        RepoState {
+            changes: vec![],
+            branch: Some(String::from("master")),
+            branches_containing_head: vec![String::from("master")],
+            head: String::from("abc"),
+            tag: Some(String::from("v1.2.3")),
+            tag_commit: Some(String::from("abc")),
+        }
+    }
+
+    #[test]
+    fn test_check() {
+        let allowed = vec![String::from("master"), String::from("v*.*")];
+        assert_eq!(check(&clean_state(), &allowed), vec![]);
+
+        let state = RepoState {
+            changes: vec![String::from(" M Cargo.toml")],
+            branch: Some(String::from("feature")),
+            tag_commit: Some(String::from("def")),
+            ..clean_state()
+        };
+        assert_eq!(
+            check(&state, &allowed),
+            vec![
+                Violation::Dirty(vec![String::from(" M Cargo.toml")]),
+                Violation::Branch(vec![String::from("feature")]),
+                Violation::TagMismatch {
+                    tag: String::from("v1.2.3"),
+                    commit: String::from("def")
+                },
+            ]
+        );
+        assert_eq!(check(&state, &[]).len(), 2);
+    }
+
+    #[test]
+    fn test_check_detached_head() {
+        let allowed = vec![String::from("v*.*")];
+        let state = RepoState {
+            branch: None,
+            branches_containing_head: vec![String::from("master"), String::from("v2.1")],
+            tag_commit: None,
+            ..clean_state()
+        };
+        assert_eq!(
+            check(&state, &allowed),
+            vec![Violation::Untagged(Some(String::from("v1.2.3")))]
+        );
+    }
+
+    #[test]
+    fn test_overrides() {
+        let overrides = Overrides {
+            allow_dirty: true,
+            ..Overrides::default()
+        };
+        assert_eq!(
+            Violation::Dirty(vec![]).overridden_by(&overrides),
+            Some("--allow-dirty")
+        );
+        assert_eq!(Violation::Untagged(None).overridden_by(&overrides), None);
+    }
+}
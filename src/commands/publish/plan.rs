@@ -0,0 +1,282 @@
+use {
+    super::{compute_publish_order_data_for, registry::CRATES_IO, PublishOrderData, Selection},
+    crate::{config::Config, utils::get_head_commit},
+    anyhow::{anyhow, Context, Result},
+    serde::{Deserialize, Serialize},
+    std::{fs, path::Path, path::PathBuf},
+};
+
+/// A serialized, reviewable snapshot of the publish order.
+///
+/// The plan pins the git commit it was computed from so an approver's
+/// signature covers exactly one workspace state.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PublishPlan {
+    pub git_commit: String,
+    pub levels: Vec<Vec<PlannedPackage>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlannedPackage {
+    pub name: String,
+    pub version: String,
+    /// Package directory relative to the workspace root.
+    pub path: PathBuf,
+    pub dependencies: Vec<String>,
+}
+
+impl PublishPlan {
+    pub fn from_order_data(data: &PublishOrderData, git_commit: String) -> Self {
+        let levels = data
+            .levels
+            .iter()
+            .map(|level| {
+                level
+                    .iter()
+                    .map(|package_id| {
+                        let package_info = &data.id_to_package_info[package_id];
+                        let mut dependencies: Vec<String> = package_info
+                            .dependencies
+                            .iter()
+                            .map(|dep| data.id_to_package_info[dep].name.clone())
+                            .collect();
+                        dependencies.sort();
+                        PlannedPackage {
+                            name: package_info.name.clone(),
+                            version: package_info.version.clone(),
+                            path: package_info
+                                .path
+                                .strip_prefix(&data.workspace_root)
+                                .unwrap_or(&package_info.path)
+                                .to_path_buf(),
+                            dependencies,
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        PublishPlan { git_commit, levels }
+    }
+
+    pub fn compute(manifest_path: &str, config: &Config) -> Result<Self> {
+        Self::compute_for(manifest_path, CRATES_IO, &Selection::default(), config)
+    }
+
+    /// The plan of the selected crates that may be published to `registry`.
+    pub fn compute_for(
+        manifest_path: &str,
+        registry: &str,
+        selection: &Selection,
+        config: &Config,
+    ) -> Result<Self> {
+        let data = compute_publish_order_data_for(manifest_path, registry, selection, config)?;
+        let git_commit = get_head_commit(&data.workspace_root)?;
+        Ok(Self::from_order_data(&data, git_commit))
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        let content = fs::read(path).context(format!("failed to read {}", path.display()))?;
+        Self::parse(&content, path)
+    }
+
+    /// Parses the plan `path` was read into.
+    pub fn parse(content: &[u8], path: &Path) -> Result<Self> {
+        serde_json::from_slice(content).context(format!("failed to parse {}", path.display()))
+    }
+
+    pub fn packages(&self) -> impl Iterator<Item = &PlannedPackage> {
+        self.levels.iter().flatten()
+    }
+
+    /// Graphviz digraph with one cluster per level and an edge from each
+    /// dependency to its dependents, i.e. in publish order.
+    pub fn to_dot(&self) -> String {
+        let mut dot =
+            String::from("digraph publish_plan {\n    rankdir=LR;\n    node [shape=box];\n");
+        for (level, packages) in self.levels.iter().enumerate() {
+            let level = level.saturating_add(1);
+            dot.push_str(&format!(
+                "    subgraph cluster_level_{level} {{\n        label=\"level {level}\";\n"
+            ));
+            for package in packages {
+                dot.push_str(&format!(
+                    "        \"{}\" [label=\"{}\\n{}\", tooltip=\"{}\"];\n",
+                    package.name,
+                    package.name,
+                    package.version,
+                    package.path.display()
+                ));
+            }
+            dot.push_str("    }\n");
+        }
+        for package in self.packages() {
+            for dependency in package.dependencies.iter() {
+                dot.push_str(&format!("    \"{dependency}\" -> \"{}\";\n", package.name));
+            }
+        }
+        dot.push('}');
+        dot
+    }
+
+    /// Mermaid flowchart laid out like [`PublishPlan::to_dot`], for CI job
+    /// summaries and PR descriptions.
+    pub fn to_mermaid(&self) -> String {
+        // mermaid node ids cannot contain `-`
+        let id = |name: &str| name.replace('-', "_");
+        let mut mermaid = String::from("flowchart LR\n");
+        for (level, packages) in self.levels.iter().enumerate() {
+            let level = level.saturating_add(1);
+            mermaid.push_str(&format!("    subgraph level_{level}[\"level {level}\"]\n"));
+            for package in packages {
+                mermaid.push_str(&format!(
+                    "        {}[\"{}<br/>{}\"]\n",
+                    id(&package.name),
+                    package.name,
+                    package.version
+                ));
+            }
+            mermaid.push_str("    end\n");
+        }
+        for package in self.packages() {
+            for dependency in package.dependencies.iter() {
+                mermaid.push_str(&format!(
+                    "    {} --> {}\n",
+                    id(dependency),
+                    id(&package.name)
+                ));
+            }
+        }
+        mermaid.trim_end().to_string()
+    }
+
+    /// Fails when `current` (freshly computed from the workspace) differs
+    /// from this plan.
+    pub fn verify_matches(&self, current: &PublishPlan) -> Result<()> {
+        if self.git_commit != current.git_commit {
+            return Err(anyhow!(
+                "plan was computed at commit {} but HEAD is {}",
+                self.git_commit,
+                current.git_commit
+            ));
+        }
+        if self.levels.len() != current.levels.len() {
+            return Err(anyhow!(
+                "plan has {} level(s) but the workspace has {}",
+                self.levels.len(),
+                current.levels.len()
+            ));
+        }
+        for (level, (planned, actual)) in self.levels.iter().zip(&current.levels).enumerate() {
+            if planned != actual {
+                let names = |packages: &[PlannedPackage]| {
+                    packages
+                        .iter()
+                        .map(|p| format!("{}@{}", p.name, p.version))
+                        .collect::<Vec<_>>()
+                };
+                return Err(anyhow!(
+                    "level {} differs: plan has {:?}, workspace has {:?}",
+                    level.saturating_add(1),
+                    names(planned),
+                    names(actual)
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{super::compute_publish_order_data, *},
+        pretty_assertions::assert_eq,
+    };
+
+    fn package(name: &str, version: &str) -> PlannedPackage {
+        PlannedPackage {
+            name: name.to_string(),
+            version: version.to_string(),
+            path: PathBuf::from(name),
+            dependencies: vec![],
+        }
+    }
+
+    #[test]
+    fn test_plan_from_workspace() {
+        let manifest = "tests/dummy-workspace-publish-test/Cargo.toml";
+        let data = compute_publish_order_data(manifest).unwrap();
+        let plan = PublishPlan::from_order_data(&data, String::from("abc"));
+
+        let names: Vec<Vec<&str>> = plan
+            .levels
+            .iter()
+            .map(|level| level.iter().map(|p| p.name.as_str()).collect())
+            .collect();
+        assert_eq!(names, vec![vec!["a"], vec!["b"], vec!["c"], vec!["d"]]);
+        assert_eq!(plan.levels[3][0].path, PathBuf::from("d"));
+        assert_eq!(plan.levels[3][0].dependencies, vec!["a", "c"]);
+        assert_eq!(plan.levels[0][0].version, "1.0.0");
+    }
+
+    #[test]
+    fn test_render_graph() {
+        let mut dependent = package("solana-sdk", "2.1.0");
+        dependent.dependencies = vec![String::from("solana-pubkey")];
+        let plan = PublishPlan {
+            git_commit: String::from("abc"),
+            levels: vec![vec![package("solana-pubkey", "2.1.0")], vec![dependent]],
+        };
+        assert_eq!(
+            plan.to_dot(),
+            r#"digraph publish_plan {
+    rankdir=LR;
+    node [shape=box];
+    subgraph cluster_level_1 {
+        label="level 1";
+        "solana-pubkey" [label="solana-pubkey\n2.1.0", tooltip="solana-pubkey"];
+    }
+    subgraph cluster_level_2 {
+        label="level 2";
+        "solana-sdk" [label="solana-sdk\n2.1.0", tooltip="solana-sdk"];
+    }
+    "solana-pubkey" -> "solana-sdk";
+}"#
+        );
+        assert_eq!(
+            plan.to_mermaid(),
+            r#"flowchart LR
+    subgraph level_1["level 1"]
+        solana_pubkey["solana-pubkey<br/>2.1.0"]
+    end
+    subgraph level_2["level 2"]
+        solana_sdk["solana-sdk<br/>2.1.0"]
+    end
+    solana_pubkey --> solana_sdk"#
+        );
+    }
+
+    #[test]
+    fn test_verify_matches() {
+        let plan = PublishPlan {
+            git_commit: String::from("abc"),
+            levels: vec![vec![package("a", "1.0.0")], vec![package("b", "1.0.0")]],
+        };
+        assert!(plan.verify_matches(&plan.clone()).is_ok());
+
+        let mut other_commit = plan.clone();
+        other_commit.git_commit = String::from("def");
+        assert_eq!(
+            plan.verify_matches(&other_commit).unwrap_err().to_string(),
+            "plan was computed at commit abc but HEAD is def"
+        );
+
+        let mut other_version = plan.clone();
+        other_version.levels[1][0].version = String::from("1.0.1");
+        assert_eq!(
+            plan.verify_matches(&other_version).unwrap_err().to_string(),
+            r#"level 2 differs: plan has ["b@1.0.0"], workspace has ["b@1.0.1"]"#
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use {
+    super::plan::PlannedPackage,
+    crate::{
+        config::Config,
+        utils::{git::git_root_of, portable::slash_path, template::render},
+    },
+    anyhow::{Context, Result},
+    cargo_metadata::MetadataCommand,
+    log::{info, warn},
+    std::{
+        collections::HashMap,
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Used when `publish.readme-template` is not set.
+pub const DEFAULT_TEMPLATE: &str = "# {{name}}
+
+[![Crates.io](https://img.shields.io/crates/v/{{name}}.svg)](https://crates.io/crates/{{name}})
+[![Docs.rs](https://docs.rs/{{name}}/badge.svg)](https://docs.rs/{{name}})
+
+{{description}}
+
+This crate is developed in [{{repository}}]({{repository}}/tree/{{commit}}/{{path}}).
+";
+
+/// Fills in READMEs for crates that ship without one.
+pub struct ReadmeTemplate {
+    template: String,
+    /// Directory of the workspace inside the repository, for links.
+    workspace_dir: PathBuf,
+    details: HashMap<String, CrateDetails>,
+}
+
+struct CrateDetails {
+    description: String,
+    repository: String,
+    has_readme: bool,
+}
+
+/// A README written into a crate for the duration of its publish.
+pub struct GeneratedReadme(PathBuf);
+
+impl Drop for GeneratedReadme {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.0) {
+            warn!("failed to remove {}: {e}", self.0.display());
+        }
+    }
+}
+
+impl ReadmeTemplate {
+    pub fn load(manifest_path: &str, config: &Config) -> Result<Self> {
+        let template = match &config.publish.readme_template {
+            Some(path) => {
+                let path = config.resolve(path);
+                fs::read_to_string(&path).context(format!("failed to read {}", path.display()))?
+            }
+            None => DEFAULT_TEMPLATE.to_string(),
+        };
+        let metadata = MetadataCommand::new()
+            .manifest_path(manifest_path)
+            .no_deps()
+            .exec()
+            .context("failed to run cargo metadata")?;
+        let details = metadata
+            .workspace_packages()
+            .into_iter()
+            .map(|package| {
+                let dir = package.manifest_path.parent().map(|p| p.as_std_path());
+                let has_readme = package.readme.is_some()
+                    || dir.is_some_and(|dir| {
+                        ["README.md", "README.txt", "README"]
+                            .iter()
+                            .any(|name| dir.join(name).exists())
+                    });
+                let details = CrateDetails {
+                    description: package.description.clone().unwrap_or_default(),
+                    repository: package.repository.clone().unwrap_or_default(),
+                    has_readme,
+                };
+                (package.name.to_string(), details)
+            })
+            .collect();
+        let workspace_dir = metadata
+            .workspace_root
+            .as_std_path()
+            .strip_prefix(git_root_of(metadata.workspace_root.as_std_path())?)
+            .unwrap_or(Path::new(""))
+            .to_path_buf();
+        Ok(ReadmeTemplate {
+            template,
+            workspace_dir,
+            details,
+        })
+    }
+
+    /// Writes a README into the crate directory unless it has one already;
+    /// the file is removed again when the returned value is dropped.
+    pub fn inject(
+        &self,
+        workspace_root: &Path,
+        package: &PlannedPackage,
+        commit: &str,
+    ) -> Result<Option<GeneratedReadme>> {
+        let Some(details) = self.details.get(&package.name) else {
+            return Ok(None);
+        };
+        if details.has_readme {
+            return Ok(None);
+        }
+        let path = slash_path(&self.workspace_dir.join(&package.path));
+        let readme = render(
+            &self.template,
+            &[
+                ("name", &package.name),
+                ("version", &package.version),
+                ("description", &details.description),
+                ("repository", details.repository.trim_end_matches('/')),
+                ("path", &path),
+                ("commit", commit),
+            ],
+        );
+        let destination = workspace_root.join(&package.path).join("README.md");
+        fs::write(&destination, readme)
+            .context(format!("failed to write {}", destination.display()))?;
+        info!("  📝 generated README.md for {}", package.name);
+        Ok(Some(GeneratedReadme(destination)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_render_default_template() {
+        let readme = render(
+            DEFAULT_TEMPLATE,
+            &[
+                ("name", "solana-hash"),
+                ("description", "Hashes."),
+                ("repository", "https://github.com/anza-xyz/solana-sdk"),
+                ("path", "hash"),
+                ("commit", "abc"),
+            ],
+        );
+        assert_eq!(
+            readme,
+            "# solana-hash
+
+[![Crates.io](https://img.shields.io/crates/v/solana-hash.svg)](https://crates.io/crates/solana-hash)
+[![Docs.rs](https://docs.rs/solana-hash/badge.svg)](https://docs.rs/solana-hash)
+
+Hashes.
+
+This crate is developed in [https://github.com/anza-xyz/solana-sdk](https://github.com/anza-xyz/solana-sdk/tree/abc/hash).
+"
+        );
+    }
+
+    #[test]
+    fn test_inject_skips_crates_with_readme() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir_all(dir.path().join("a")).unwrap();
+        let detail = |has_readme| CrateDetails {
+            description: String::from("A crate."),
+            repository: String::from("https://github.com/anza-xyz/agave/"),
+            has_readme,
+        };
+        let template = ReadmeTemplate {
+            template: String::from("{{name}} {{version}}: {{description}} {{repository}}/{{path}}"),
+            workspace_dir: PathBuf::from("sdk"),
+            details: HashMap::from([
+                (String::from("a"), detail(false)),
+                (String::from("b"), detail(true)),
+            ]),
+        };
+        let package = |name: &str| PlannedPackage {
+            name: name.to_string(),
+            version: String::from("1.0.0"),
+            path: PathBuf::from(name),
+            dependencies: vec![],
+        };
+        let readme_path = dir.path().join("a/README.md");
+        {
+            let generated = template.inject(dir.path(), &package("a"), "abc").unwrap();
+            assert!(generated.is_some());
+            assert_eq!(
+                fs::read_to_string(&readme_path).unwrap(),
+                "a 1.0.0: A crate. https://github.com/anza-xyz/agave/sdk/a"
+            );
+        }
+        assert!(!readme_path.exists());
+        assert!(template
+            .inject(dir.path(), &package("b"), "abc")
+            .unwrap()
+            .is_none());
+    }
+}
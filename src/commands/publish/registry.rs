@@ -0,0 +1,273 @@
+use {
+    crate::{
+        config::Config,
+        utils::{credentials, registry::CRATES_IO_INDEX},
+    },
+    anyhow::{anyhow, Context, Result},
+    std::{
+        env, fs,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+    toml_edit::DocumentMut,
+};
+
+/// Name cargo gives crates.io in `publish = [...]` lists.
+pub const CRATES_IO: &str = "crates-io";
+
+/// Registry a publish run targets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Registry {
+    /// Name from the cargo config or `publish.registries`, `None` for crates.io.
+    pub name: Option<String>,
+    /// Base URL of its sparse index.
+    pub index_url: String,
+}
+
+impl Registry {
+    pub fn crates_io() -> Self {
+        Registry {
+            name: None,
+            index_url: CRATES_IO_INDEX.to_string(),
+        }
+    }
+
+    /// Looks the index of `name` up in `publish.registries`, then in the
+    /// `CARGO_REGISTRIES_<NAME>_INDEX` environment variable, then in the
+    /// cargo config files that apply to `workspace_dir`.
+    pub fn resolve(name: Option<&str>, config: &Config, workspace_dir: &Path) -> Result<Self> {
+        let name = match name {
+            None | Some(CRATES_IO) => return Ok(Self::crates_io()),
+            Some(name) => name,
+        };
+        let index = match config
+            .publish
+            .registries
+            .get(name)
+            .and_then(|registry| registry.index.clone())
+        {
+            Some(index) => index,
+            None => match env::var(registry_env(name, "INDEX")) {
+                Ok(index) => index,
+                Err(_) => cargo_config_index(workspace_dir, name)?.ok_or_else(|| {
+                    anyhow!(
+                        "registry {name} has no index, set publish.registries.{name}.index in xtask.toml or registries.{name}.index in the cargo config"
+                    )
+                })?,
+            },
+        };
+        Ok(Registry {
+            name: Some(name.to_string()),
+            index_url: sparse_index_url(&index)
+                .ok_or_else(|| anyhow!("registry {name} is not a sparse registry: {index}"))?,
+        })
+    }
+
+    pub fn display_name(&self) -> &str {
+        self.name.as_deref().unwrap_or(CRATES_IO)
+    }
+
+    /// Token authenticating reads of the registry's index: the one from
+    /// `publish.credentials`, else the `CARGO_REGISTRIES_<NAME>_TOKEN` cargo
+    /// would use. `None` for crates.io, whose index is public.
+    pub fn index_token(&self, config: &Config) -> Result<Option<String>> {
+        let Some(name) = &self.name else {
+            return Ok(None);
+        };
+        Ok(
+            credentials::token(name, config)?
+                .or_else(|| env::var(registry_env(name, "TOKEN")).ok()),
+        )
+    }
+
+    /// Points a `cargo publish`, `cargo package` or `cargo yank` at the
+    /// registry, with the index configured in `publish.registries` and the
+    /// token from `publish.credentials`.
+    pub fn apply(&self, cmd: &mut Command, config: &Config) -> Result<()> {
+        let Some(name) = &self.name else {
+            if let Some(token) = credentials::token(CRATES_IO, config)? {
+                cmd.env("CARGO_REGISTRY_TOKEN", token);
+            }
+            return Ok(());
+        };
+        cmd.args(["--registry", name]);
+        if let Some(index) = config
+            .publish
+            .registries
+            .get(name)
+            .and_then(|registry| registry.index.as_ref())
+        {
+            cmd.env(registry_env(name, "INDEX"), index);
+        }
+        if let Some(token) = credentials::token(name, config)? {
+            cmd.env(registry_env(name, "TOKEN"), token);
+        }
+        Ok(())
+    }
+}
+
+/// Environment variable cargo reads `key` of registry `name` from.
+pub fn registry_env(name: &str, key: &str) -> String {
+    format!(
+        "CARGO_REGISTRIES_{}_{key}",
+        name.to_uppercase().replace('-', "_")
+    )
+}
+
+/// Index environment variables of every registry in `publish.registries`,
+/// so manifests naming one with `registry = "..."` resolve without it being
+/// in the cargo config.
+pub fn index_env(config: &Config) -> Vec<(String, String)> {
+    config
+        .publish
+        .registries
+        .iter()
+        .filter_map(|(name, registry)| {
+            let index = registry.index.clone()?;
+            Some((registry_env(name, "INDEX"), index))
+        })
+        .collect()
+}
+
+/// Whether a package whose manifest says `publish` may go to `registry`.
+pub fn publishes_to(publish: Option<&[String]>, registry: &str) -> bool {
+    publish.is_none_or(|registries| registries.iter().any(|r| r == registry))
+}
+
+/// `https://...` base of a `sparse+https://...` index.
+fn sparse_index_url(index: &str) -> Option<String> {
+    index
+        .strip_prefix("sparse+")
+        .map(|url| url.trim_end_matches('/').to_string())
+}
+
+/// `registries.<name>.index` from the nearest cargo config that sets it.
+fn cargo_config_index(workspace_dir: &Path, name: &str) -> Result<Option<String>> {
+    let mut dirs: Vec<PathBuf> = workspace_dir
+        .ancestors()
+        .map(|dir| dir.join(".cargo"))
+        .collect();
+    match env::var_os("CARGO_HOME") {
+        Some(home) => dirs.push(PathBuf::from(home)),
+        None => dirs.extend(env::var_os("HOME").map(|home| PathBuf::from(home).join(".cargo"))),
+    }
+    for dir in dirs {
+        for file in ["config.toml", "config"] {
+            let path = dir.join(file);
+            if !path.is_file() {
+                continue;
+            }
+            let doc: DocumentMut = fs::read_to_string(&path)
+                .context(format!("failed to read {}", path.display()))?
+                .parse()
+                .context(format!("failed to parse {}", path.display()))?;
+            if let Some(index) = doc
+                .get("registries")
+                .and_then(|r| r.get(name))
+                .and_then(|r| r.get("index"))
+                .and_then(|i| i.as_str())
+            {
+                return Ok(Some(index.to_string()));
+            }
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::config::{CredentialSource, RegistryConfig},
+        pretty_assertions::assert_eq,
+    };
+
+    #[test]
+    fn test_resolve() {
+        let dir = tempfile::tempdir().unwrap();
+        let workspace = dir.path().join("workspace");
+        fs::create_dir_all(dir.path().join(".cargo")).unwrap();
+        fs::create_dir_all(&workspace).unwrap();
+        fs::write(
+            dir.path().join(".cargo/config.toml"),
+            "[registries.mirror]\nindex = \"sparse+https://mirror.internal/index/\"\n[registries.git]\nindex = \"https://github.com/org/index\"\n",
+        )
+        .unwrap();
+        let mut config = Config::default();
+        config.publish.registries.insert(
+            String::from("internal"),
+            RegistryConfig {
+                index: Some(String::from("sparse+https://crates.internal/api/v1/index/")),
+                token_env: None,
+            },
+        );
+
+        assert_eq!(
+            Registry::resolve(None, &config, &workspace).unwrap(),
+            Registry::crates_io()
+        );
+        assert_eq!(
+            Registry::resolve(Some("crates-io"), &config, &workspace).unwrap(),
+            Registry::crates_io()
+        );
+        assert_eq!(
+            Registry::resolve(Some("internal"), &config, &workspace).unwrap(),
+            Registry {
+                name: Some(String::from("internal")),
+                index_url: String::from("https://crates.internal/api/v1/index"),
+            }
+        );
+        assert_eq!(
+            Registry::resolve(Some("mirror"), &config, &workspace)
+                .unwrap()
+                .index_url,
+            "https://mirror.internal/index"
+        );
+        assert!(Registry::resolve(Some("git"), &config, &workspace).is_err());
+        assert!(Registry::resolve(Some("xtask-test-unknown"), &config, &workspace).is_err());
+        assert_eq!(
+            index_env(&config),
+            vec![(
+                String::from("CARGO_REGISTRIES_INTERNAL_INDEX"),
+                String::from("sparse+https://crates.internal/api/v1/index/")
+            )]
+        );
+    }
+
+    #[test]
+    fn test_publishes_to() {
+        let internal = [String::from("internal")];
+        assert!(publishes_to(None, CRATES_IO));
+        assert!(publishes_to(None, "internal"));
+        assert!(publishes_to(Some(&internal), "internal"));
+        assert!(!publishes_to(Some(&internal), CRATES_IO));
+        assert!(!publishes_to(Some(&[]), CRATES_IO));
+        assert_eq!(
+            registry_env("my-registry", "TOKEN"),
+            "CARGO_REGISTRIES_MY_REGISTRY_TOKEN"
+        );
+    }
+
+    #[test]
+    fn test_index_token() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("token"), "cio-private\n").unwrap();
+        let mut config = Config {
+            root: dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        config.publish.credentials.insert(
+            String::from("xtask-test-index"),
+            CredentialSource::File("token".into()),
+        );
+        let registry = Registry {
+            name: Some(String::from("xtask-test-index")),
+            index_url: String::from("https://crates.internal/index"),
+        };
+        assert_eq!(
+            registry.index_token(&config).unwrap().as_deref(),
+            Some("cio-private")
+        );
+        assert_eq!(Registry::crates_io().index_token(&config).unwrap(), None);
+    }
+}
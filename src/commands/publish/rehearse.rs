@@ -0,0 +1,326 @@
+use {
+    super::{
+        plan::{PlannedPackage, PublishPlan},
+        start_docker_registry, KELLNR_INDEX, KELLNR_TOKEN,
+    },
+    crate::{
+        config::{Config, NetworkConfig},
+        utils::{
+            cargo::{dependency_tables_mut, read_manifest, TableScope},
+            check_docker_available,
+            git::{git_root_of, with_worktree_in},
+            http::{self, apply_cargo_network_env},
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    log::info,
+    scopeguard::guard,
+    std::{
+        collections::BTreeSet, fs, ops::Range, path::Path, process::Command, thread, time::Duration,
+    },
+    toml_edit::value,
+};
+
+/// Registry name the started kellnr container is configured under.
+const REHEARSAL_REGISTRY: &str = "kellnr";
+
+/// How long kellnr gets to start serving its index.
+const REGISTRY_STARTUP_ATTEMPTS: u32 = 60;
+
+/// Publishes `plan` to a local registry from a throwaway worktree of the
+/// planned commit, then builds a consumer of every published crate.
+///
+/// Only the `levels` a real run would publish are rehearsed. Without
+/// `registry` a kellnr container is started, seeded with the levels before
+/// them, and torn down again; otherwise the named registry from the cargo
+/// config is used as is and must already hold the earlier levels.
+pub fn rehearse(
+    workspace_root: &Path,
+    plan: &PublishPlan,
+    levels: Range<usize>,
+    registry: Option<&str>,
+    config: &Config,
+) -> Result<()> {
+    // the workspace may be a worktree of a `--ref` checkout
+    let repo = git_root_of(workspace_root)?;
+    let workspace_dir = workspace_root
+        .strip_prefix(&repo)
+        .unwrap_or(Path::new(""))
+        .to_path_buf();
+
+    let _kellnr = match registry {
+        Some(_) => None,
+        None => {
+            check_docker_available()?;
+            info!("🐳 starting kellnr");
+            let container = guard(start_docker_registry()?, |container_id| {
+                info!("🧹 stopping kellnr");
+                let _ = Command::new("docker")
+                    .args(["rm", "--force", &container_id])
+                    .output();
+            });
+            wait_for_registry()?;
+            Some(container)
+        }
+    };
+    let (registry, env, seed) = match registry {
+        Some(registry) => (registry, vec![], 0..0),
+        None => (REHEARSAL_REGISTRY, kellnr_env(), 0..levels.start),
+    };
+    with_worktree_in(&repo, &plan.git_commit, |worktree| {
+        rehearse_in(
+            &worktree.join(&workspace_dir),
+            plan,
+            Levels {
+                seed: seed.clone(),
+                rehearsed: levels.clone(),
+            },
+            registry,
+            &env,
+            &config.network,
+        )
+    })
+}
+
+/// Registers the kellnr container with cargo without touching any config file.
+fn kellnr_env() -> Vec<(String, String)> {
+    let key = REHEARSAL_REGISTRY.to_uppercase();
+    vec![
+        (
+            format!("CARGO_REGISTRIES_{key}_INDEX"),
+            KELLNR_INDEX.to_string(),
+        ),
+        (
+            format!("CARGO_REGISTRIES_{key}_TOKEN"),
+            KELLNR_TOKEN.to_string(),
+        ),
+        (
+            format!("CARGO_REGISTRIES_{key}_CREDENTIAL_PROVIDER"),
+            String::from("cargo:token"),
+        ),
+    ]
+}
+
+fn wait_for_registry() -> Result<()> {
+    let url = format!("{}config.json", KELLNR_INDEX.trim_start_matches("sparse+"));
+    for _ in 0..REGISTRY_STARTUP_ATTEMPTS {
+        // loopback, so the configured proxy must not apply
+        if http::get(&NetworkConfig::default(), &url, &[]).is_ok() {
+            return Ok(());
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+    Err(anyhow!("kellnr did not come up at {url}"))
+}
+
+/// Levels published to the rehearsal registry only so the rehearsed ones
+/// resolve, and the rehearsed levels themselves.
+struct Levels {
+    seed: Range<usize>,
+    rehearsed: Range<usize>,
+}
+
+fn rehearse_in(
+    workspace_dir: &Path,
+    plan: &PublishPlan,
+    levels: Levels,
+    registry: &str,
+    env: &[(String, String)],
+    network: &NetworkConfig,
+) -> Result<()> {
+    let names: BTreeSet<String> = plan.packages().map(|p| p.name.clone()).collect();
+    let mut manifests = vec![workspace_dir.join("Cargo.toml")];
+    manifests.extend(
+        plan.packages()
+            .map(|p| workspace_dir.join(&p.path).join("Cargo.toml")),
+    );
+    for manifest in manifests.iter() {
+        point_dependencies_at_registry(manifest, &names, registry)?;
+    }
+
+    for level in levels.seed.start..levels.rehearsed.end {
+        if level < levels.seed.end {
+            info!("seeding level: {}", level.saturating_add(1));
+        } else if level >= levels.rehearsed.start {
+            info!("rehearsing level: {}", level.saturating_add(1));
+        } else {
+            continue;
+        }
+        for package in plan.levels[level].iter() {
+            let mut cmd = Command::new("cargo");
+            cmd.args(["publish", "--allow-dirty", "--registry", registry])
+                .arg("--manifest-path")
+                .arg(workspace_dir.join(&package.path).join("Cargo.toml"))
+                .envs(env.iter().map(|(k, v)| (k, v)));
+            apply_cargo_network_env(&mut cmd, network);
+            run(&mut cmd).context(format!(
+                "failed to publish {}@{} to {registry}",
+                package.name, package.version
+            ))?;
+            info!("  ✅ {}@{}", package.name, package.version);
+        }
+    }
+
+    info!("🧪 building a consumer of every published crate");
+    let consumer = tempfile::tempdir()?;
+    fs::write(
+        consumer.path().join("Cargo.toml"),
+        consumer_manifest(
+            plan.levels[levels.rehearsed.clone()].iter().flatten(),
+            registry,
+        ),
+    )?;
+    fs::create_dir(consumer.path().join("src"))?;
+    fs::write(consumer.path().join("src/lib.rs"), "")?;
+    let mut cmd = Command::new("cargo");
+    cmd.arg("build")
+        .current_dir(consumer.path())
+        .envs(env.iter().map(|(k, v)| (k, v)));
+    apply_cargo_network_env(&mut cmd, network);
+    run(&mut cmd).context("the consumer project failed to build")?;
+    info!(
+        "✅ rehearsal passed, {} crate(s) published",
+        plan.levels[levels.rehearsed].iter().flatten().count()
+    );
+    Ok(())
+}
+
+/// Makes dependencies on crates of the plan resolve from `registry`, which
+/// cargo otherwise assumes to be crates.io when publishing.
+fn point_dependencies_at_registry(
+    manifest: &Path,
+    names: &BTreeSet<String>,
+    registry: &str,
+) -> Result<()> {
+    let mut doc = read_manifest(manifest)?;
+    let tables = dependency_tables_mut(
+        &mut doc,
+        &[
+            TableScope::Package,
+            TableScope::Workspace,
+            TableScope::Target,
+        ],
+    );
+    for table in tables {
+        let Some(table) = table.as_table_like_mut() else {
+            continue;
+        };
+        for (key, spec) in table.iter_mut() {
+            let name = spec
+                .get("package")
+                .and_then(|p| p.as_str())
+                .unwrap_or(key.get())
+                .to_string();
+            if !names.contains(&name) || spec.get("path").is_none() {
+                continue;
+            }
+            if let Some(spec) = spec.as_inline_table_mut() {
+                spec.insert("registry", registry.into());
+                spec.fmt();
+            } else if let Some(spec) = spec.as_table_like_mut() {
+                spec.insert("registry", value(registry));
+            }
+        }
+    }
+    fs::write(manifest, doc.to_string()).context(format!("failed to write {}", manifest.display()))
+}
+
+pub fn consumer_manifest<'a>(
+    packages: impl Iterator<Item = &'a PlannedPackage>,
+    registry: &str,
+) -> String {
+    let mut manifest = String::from(
+        "[package]\nname = \"xtask-rehearsal-consumer\"\nversion = \"0.0.0\"\nedition = \"2021\"\npublish = false\n\n[workspace]\n\n[dependencies]\n",
+    );
+    for package in packages {
+        manifest.push_str(&format!(
+            "{} = {{ version = \"={}\", registry = \"{registry}\" }}\n",
+            package.name, package.version
+        ));
+    }
+    manifest
+}
+
+fn run(cmd: &mut Command) -> Result<()> {
+    let output = cmd
+        .output()
+        .map_err(|e| anyhow!("failed to run cargo: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::path::PathBuf};
+
+    #[test]
+    fn test_point_dependencies_at_registry() {
+        let dir = tempfile::tempdir().unwrap();
+        let manifest = dir.path().join("Cargo.toml");
+        fs::write(
+            &manifest,
+            r#"[workspace]
+members = ["a", "b"]
+
+[workspace.dependencies]
+a = { path = "a", version = "1.0.0" }
+serde = "1"
+
+[package]
+name = "b"
+
+[dependencies]
+a = { workspace = true }
+renamed = { package = "a", path = "a", version = "1.0.0" }
+
+[target.'cfg(unix)'.dev-dependencies]
+a = { path = "a", version = "1.0.0" }
+"#,
+        )
+        .unwrap();
+        let names = BTreeSet::from([String::from("a"), String::from("b")]);
+        point_dependencies_at_registry(&manifest, &names, "kellnr").unwrap();
+        assert_eq!(
+            fs::read_to_string(&manifest).unwrap(),
+            r#"[workspace]
+members = ["a", "b"]
+
+[workspace.dependencies]
+a = { path = "a", version = "1.0.0", registry = "kellnr" }
+serde = "1"
+
+[package]
+name = "b"
+
+[dependencies]
+a = { workspace = true }
+renamed = { package = "a", path = "a", version = "1.0.0", registry = "kellnr" }
+
+[target.'cfg(unix)'.dev-dependencies]
+a = { path = "a", version = "1.0.0", registry = "kellnr" }
+"#
+        );
+    }
+
+    #[test]
+    fn test_consumer_manifest() {
+        let package = |name: &str| PlannedPackage {
+            name: name.to_string(),
+            version: String::from("1.2.3"),
+            path: PathBuf::from(name),
+            dependencies: vec![],
+        };
+        let plan = PublishPlan {
+            git_commit: String::from("abc"),
+            levels: vec![vec![package("a")], vec![package("b")]],
+        };
+        assert!(consumer_manifest(plan.packages(), "kellnr").ends_with(
+            "[dependencies]\na = { version = \"=1.2.3\", registry = \"kellnr\" }\nb = { version = \"=1.2.3\", registry = \"kellnr\" }\n"
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+use {
+    super::{order_levels, PublishOrderData},
+    crate::utils::cargo::wildcard_match,
+    anyhow::{anyhow, Result},
+    cargo_metadata::PackageId,
+    clap::Args,
+    std::collections::HashSet,
+};
+
+/// The subset of the workspace a publish command works on.
+#[derive(Args, Debug, Clone, Default)]
+pub struct Selection {
+    /// Only these crates, `*` and `?` wildcards allowed [default: every
+    /// publishable crate].
+    #[arg(long, global = true)]
+    pub only: Vec<String>,
+
+    /// Also select the workspace crates `--only` crates depend on.
+    #[arg(long, global = true, requires = "only")]
+    pub with_dependencies: bool,
+
+    /// Leave these crates out, `*` and `?` wildcards allowed.
+    #[arg(long, global = true)]
+    pub exclude: Vec<String>,
+}
+
+impl Selection {
+    pub fn is_all(&self) -> bool {
+        self.only.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Narrows `data` to the selected crates and re-levels them. Edges to
+    /// unselected crates are dropped: those are expected on the registry
+    /// already.
+    pub fn apply(&self, mut data: PublishOrderData) -> Result<PublishOrderData> {
+        if self.is_all() {
+            return Ok(data);
+        }
+        let info = &data.id_to_package_info;
+        let matching = |patterns: &[String], flag: &str| -> Result<HashSet<PackageId>> {
+            let mut ids = HashSet::new();
+            for pattern in patterns {
+                let matched: Vec<&PackageId> = info
+                    .iter()
+                    .filter(|(_, package)| wildcard_match(pattern, &package.name))
+                    .map(|(id, _)| id)
+                    .collect();
+                if matched.is_empty() {
+                    return Err(anyhow!("--{flag} {pattern} matches no publishable crate"));
+                }
+                ids.extend(matched.into_iter().cloned());
+            }
+            Ok(ids)
+        };
+
+        let mut selected: HashSet<PackageId> = if self.only.is_empty() {
+            info.keys().cloned().collect()
+        } else {
+            matching(&self.only, "only")?
+        };
+        if self.with_dependencies {
+            let mut pending: Vec<PackageId> = selected.iter().cloned().collect();
+            while let Some(id) = pending.pop() {
+                for dep in info[&id].dependencies.iter() {
+                    if selected.insert(dep.clone()) {
+                        pending.push(dep.clone());
+                    }
+                }
+            }
+        }
+        for id in matching(&self.exclude, "exclude")? {
+            selected.remove(&id);
+        }
+
+        data.id_to_package_info
+            .retain(|id, _| selected.contains(id));
+        for package in data.id_to_package_info.values_mut() {
+            package.dependencies.retain(|dep| selected.contains(dep));
+        }
+        let (levels, id_to_level) = order_levels(&data.id_to_package_info)?;
+        data.levels = levels;
+        data.id_to_level = id_to_level;
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::{super::compute_publish_order_data, *},
+        pretty_assertions::assert_eq,
+    };
+
+    fn levels(selection: Selection) -> Result<Vec<Vec<String>>> {
+        let data = compute_publish_order_data("tests/dummy-workspace-publish-test/Cargo.toml")?;
+        let data = selection.apply(data)?;
+        Ok(data
+            .levels
+            .iter()
+            .map(|level| {
+                level
+                    .iter()
+                    .map(|id| data.id_to_package_info[id].name.clone())
+                    .collect()
+            })
+            .collect())
+    }
+
+    fn names(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn test_apply() {
+        // a <- b <- c <- d, and d depends on a too
+        assert_eq!(
+            levels(Selection {
+                only: names(&["d", "b"]),
+                ..Selection::default()
+            })
+            .unwrap(),
+            vec![names(&["b", "d"])]
+        );
+        assert_eq!(
+            levels(Selection {
+                only: names(&["c"]),
+                with_dependencies: true,
+                ..Selection::default()
+            })
+            .unwrap(),
+            vec![names(&["a"]), names(&["b"]), names(&["c"])]
+        );
+        assert_eq!(
+            levels(Selection {
+                only: names(&["d"]),
+                with_dependencies: true,
+                exclude: names(&["b"]),
+            })
+            .unwrap(),
+            vec![names(&["a", "c"]), names(&["d"])]
+        );
+        assert_eq!(
+            levels(Selection {
+                exclude: names(&["?"]),
+                ..Selection::default()
+            })
+            .unwrap(),
+            Vec::<Vec<String>>::new()
+        );
+        assert_eq!(
+            levels(Selection {
+                exclude: names(&["e"]),
+                ..Selection::default()
+            })
+            .unwrap_err()
+            .to_string(),
+            "--exclude e matches no publishable crate"
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use {
+    super::{PublishPlan, Registry, Selection},
+    crate::{commands::release::CrateStatus, config::Config, utils::registry::fetch_versions},
+    anyhow::Result,
+    log::info,
+};
+
+/// Lists the crates whose local version is not live on `registry`, whatever
+/// release they belong to.
+pub fn publish_status(
+    manifest_path: &str,
+    registry: &Registry,
+    selection: &Selection,
+    config: &Config,
+) -> Result<()> {
+    let plan = PublishPlan::compute_for(manifest_path, registry.display_name(), selection, config)?;
+    let token = registry.index_token(config)?;
+    let mut pending = vec![];
+    let mut total = 0usize;
+    for package in plan.packages() {
+        total = total.saturating_add(1);
+        let entries = fetch_versions(
+            &config.network,
+            &registry.index_url,
+            token.as_deref(),
+            &package.name,
+        )?;
+        let status = CrateStatus::from_entries(&package.name, &package.version, &entries);
+        if !status.live {
+            pending.push(status);
+        }
+    }
+    if pending.is_empty() {
+        info!("✅ all {total} crate(s) are published at their local version");
+        return Ok(());
+    }
+
+    println!("{:<40} {:>12} {:>12}  state", "crate", "local", "latest");
+    for status in pending.iter() {
+        println!(
+            "{:<40} {:>12} {:>12}  {}",
+            status.name,
+            status.version,
+            status.latest.as_deref().unwrap_or("-"),
+            status.state()
+        );
+    }
+    println!();
+    println!("{} of {total} crate(s) need publishing", pending.len());
+    Ok(())
+}
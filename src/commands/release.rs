@@ -0,0 +1,272 @@
+mod crate_changelogs;
+mod notes;
+mod pipeline;
+mod rollback;
+mod status;
+
+pub use {
+    pipeline::{ReleaseState, Stage},
+    status::CrateStatus,
+};
+
+use {
+    super::bump_version::BumpLevel,
+    crate::{
+        config::Config,
+        utils::{
+            check_docker_available, credentials,
+            docker::{current_user_spec, ensure_pinned_image, run_checked},
+            get_git_root_path,
+            scope::resolve_manifest_path,
+            DockerRun,
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    clap::{Args, Subcommand},
+    log::info,
+    std::{fs, path::PathBuf},
+};
+
+/// Set inside the hermetic container so the re-executed release runs directly.
+pub const HERMETIC_ENV: &str = "XTASK_HERMETIC";
+
+/// Credentials forwarded into the hermetic container by default.
+const DEFAULT_PASS_ENV: [&str; 3] = ["CARGO_REGISTRY_TOKEN", "GITHUB_TOKEN", "GH_TOKEN"];
+
+/// Scratch space of the hermetic run under the checkout: the xtask build and
+/// the HOME and CARGO_HOME of the container user.
+const HERMETIC_DIR: &str = "target/hermetic";
+
+#[derive(Subcommand)]
+pub enum ReleaseSubcommand {
+    #[command(about = "Show how far a release got on crates.io, git and GitHub")]
+    Status {
+        #[arg(long, help = "Release to inspect [default: the workspace version]")]
+        version: Option<String>,
+    },
+    #[command(about = "Yank a release, remove its tag and GitHub release and open a revert PR")]
+    Rollback {
+        #[arg(long, help = "Release to roll back")]
+        version: String,
+        #[arg(long, help = "Answer yes to every confirmation")]
+        yes: bool,
+    },
+}
+
+#[derive(Args)]
+#[command(subcommand_negates_reqs = true)]
+pub struct CommandArgs {
+    #[arg(long, value_enum, required = true)]
+    pub level: Option<BumpLevel>,
+
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+
+    #[arg(
+        long,
+        help = "Run the release inside the pinned docker image from xtask.toml"
+    )]
+    pub hermetic: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Continue a checkpointed release from this stage"
+    )]
+    pub resume_from: Option<Stage>,
+
+    #[command(subcommand)]
+    pub subcommand: Option<ReleaseSubcommand>,
+}
+
+impl CommandArgs {
+    /// Whether this changes a release (and must be audited) rather than reporting on it.
+    /// The run re-executed inside the hermetic container is audited by the host.
+    pub fn is_mutating(&self) -> bool {
+        !matches!(self.subcommand, Some(ReleaseSubcommand::Status { .. }))
+            && std::env::var_os(HERMETIC_ENV).is_none()
+    }
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    if let Some(subcommand) = &args.subcommand {
+        let manifest_path = resolve_manifest_path(&args.manifest_path)?;
+        let config = Config::load()?;
+        return match subcommand {
+            ReleaseSubcommand::Status { version } => {
+                status::status(&manifest_path, version.as_deref(), &config)
+            }
+            ReleaseSubcommand::Rollback { version, yes } => {
+                rollback::rollback(&manifest_path, version, *yes, &config)
+            }
+        };
+    }
+
+    if args.hermetic && std::env::var_os(HERMETIC_ENV).is_none() {
+        let config = Config::load()?;
+        return run_hermetic(&config);
+    }
+
+    let config = Config::load()?;
+    pipeline::run(&args, &config)
+}
+
+/// Re-executes the current command line inside the configured image.
+///
+/// The checkout is mounted at the same absolute path so manifest paths stay
+/// valid, and xtask is built from `release.xtask-manifest` inside the image
+/// so the binary links against the image's libc rather than the host's.
+fn run_hermetic(config: &Config) -> Result<()> {
+    let image = config
+        .release
+        .image
+        .clone()
+        .ok_or_else(|| anyhow!("release.image must be set in xtask.toml for --hermetic"))?;
+    ensure_pinned_image(&image)?;
+    check_docker_available()?;
+
+    let xtask_manifest = config.resolve(&config.release.xtask_manifest);
+    if !xtask_manifest.is_file() {
+        return Err(anyhow!(
+            "{} does not exist, point release.xtask-manifest at the xtask crate",
+            xtask_manifest.display()
+        ));
+    }
+    let git_root = get_git_root_path()?;
+    let hermetic_dir = git_root.join(HERMETIC_DIR);
+    for dir in ["home", "cargo-home"] {
+        let dir = hermetic_dir.join(dir);
+        fs::create_dir_all(&dir).context(format!("failed to create {}", dir.display()))?;
+    }
+    let mut docker_run = hermetic_docker_run(
+        &image,
+        git_root,
+        std::env::current_dir()?,
+        xtask_manifest,
+        &config.release.pass_env,
+        std::env::args().skip(1).collect(),
+    );
+    // the keyring or password manager a token comes from is out of reach
+    // inside the container
+    docker_run.secret_env = credentials::forwarded(config);
+    info!("🐳 re-executing release inside {image}");
+    run_checked(&mut docker_run.to_command(), "hermetic release")
+}
+
+fn hermetic_docker_run(
+    image: &str,
+    git_root: PathBuf,
+    current_dir: PathBuf,
+    xtask_manifest: PathBuf,
+    extra_pass_env: &[String],
+    args: Vec<String>,
+) -> DockerRun {
+    let mut pass_env: Vec<String> = DEFAULT_PASS_ENV.iter().map(|s| s.to_string()).collect();
+    pass_env.extend(extra_pass_env.iter().cloned());
+
+    let hermetic_dir = git_root.join(HERMETIC_DIR);
+    let path = |p: PathBuf| p.to_string_lossy().to_string();
+    // --target-dir rather than CARGO_TARGET_DIR, which the release itself
+    // would inherit
+    let mut command: Vec<String> = [
+        "cargo",
+        "run",
+        "--quiet",
+        "--locked",
+        "--release",
+        "--manifest-path",
+    ]
+    .map(String::from)
+    .to_vec();
+    command.push(path(xtask_manifest));
+    command.push(String::from("--target-dir"));
+    command.push(path(hermetic_dir.join("target")));
+    command.push(String::from("--"));
+    command.extend(args.into_iter().filter(|arg| arg != "--hermetic"));
+
+    DockerRun {
+        mounts: vec![(git_root.clone(), git_root)],
+        workdir: Some(current_dir),
+        pass_env,
+        // the --user uid has no home in the image
+        env: vec![
+            (HERMETIC_ENV.to_string(), String::from("1")),
+            (String::from("HOME"), path(hermetic_dir.join("home"))),
+            (
+                String::from("CARGO_HOME"),
+                path(hermetic_dir.join("cargo-home")),
+            ),
+        ],
+        user: current_user_spec(),
+        command,
+        ..DockerRun::new(image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_hermetic_docker_run() {
+        let run = hermetic_docker_run(
+            "rust@sha256:abc",
+            PathBuf::from("/src/agave"),
+            PathBuf::from("/src/agave/sdk"),
+            PathBuf::from("/src/agave/ci/xtask/Cargo.toml"),
+            &[String::from("SLACK_WEBHOOK")],
+            vec![
+                String::from("release"),
+                String::from("--level"),
+                String::from("minor"),
+                String::from("--hermetic"),
+            ],
+        );
+        assert_eq!(
+            run.command,
+            [
+                "cargo",
+                "run",
+                "--quiet",
+                "--locked",
+                "--release",
+                "--manifest-path",
+                "/src/agave/ci/xtask/Cargo.toml",
+                "--target-dir",
+                "/src/agave/target/hermetic/target",
+                "--",
+                "release",
+                "--level",
+                "minor"
+            ]
+        );
+        assert_eq!(
+            run.mounts,
+            [(PathBuf::from("/src/agave"), PathBuf::from("/src/agave"))]
+        );
+        assert_eq!(run.workdir, Some(PathBuf::from("/src/agave/sdk")));
+        assert_eq!(
+            run.pass_env,
+            [
+                "CARGO_REGISTRY_TOKEN",
+                "GITHUB_TOKEN",
+                "GH_TOKEN",
+                "SLACK_WEBHOOK"
+            ]
+        );
+        assert_eq!(
+            run.env,
+            [
+                (String::from(HERMETIC_ENV), String::from("1")),
+                (
+                    String::from("HOME"),
+                    String::from("/src/agave/target/hermetic/home")
+                ),
+                (
+                    String::from("CARGO_HOME"),
+                    String::from("/src/agave/target/hermetic/cargo-home")
+                ),
+            ]
+        );
+    }
+}
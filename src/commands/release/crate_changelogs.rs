@@ -0,0 +1,164 @@
+use {
+    super::{
+        notes::{self, NotesTemplate},
+        pipeline::prepend_changelog,
+    },
+    crate::config::Config,
+    anyhow::{Context, Result},
+    log::info,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Prepends the notes of `version`, limited to the commits in `range` that
+/// touched the crate, to the changelog of every crate in `crates` (name and
+/// directory relative to `repo`), creating missing changelogs. Returns the
+/// changelogs written.
+pub fn update(
+    repo: &Path,
+    crates: &[(String, PathBuf)],
+    config: &Config,
+    template: &NotesTemplate,
+    version: &str,
+    date: &str,
+    range: &str,
+) -> Result<Vec<PathBuf>> {
+    let dirs: Vec<PathBuf> = crates.iter().map(|(_, dir)| dir.clone()).collect();
+    let mut written = vec![];
+    for (name, dir) in crates {
+        let changelog = config.crate_changelog(name, dir);
+        // crates sharing the repository changelog get the full notes there
+        if changelog == config.release.changelog {
+            continue;
+        }
+        let commits = notes::log(repo, range, &pathspecs(dir, &nested_dirs(dir, &dirs)))?;
+        if commits.is_empty() {
+            continue;
+        }
+        let path = repo.join(&changelog);
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(_) => String::from("# Changelog\n"),
+        };
+        let section = template.render(version, date, &commits);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context(format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(&path, prepend_changelog(&content, &section))
+            .context(format!("failed to write {}", path.display()))?;
+        info!("📝 updated {}", changelog.display());
+        written.push(changelog);
+    }
+    Ok(written)
+}
+
+/// Pathspecs of `dir`, leaving out the crates nested in it.
+pub fn pathspecs(dir: &Path, nested: &[PathBuf]) -> Vec<String> {
+    let mut pathspecs = vec![pathspec(dir)];
+    pathspecs.extend(
+        nested
+            .iter()
+            .map(|nested| format!(":(exclude){}", pathspec(nested))),
+    );
+    pathspecs
+}
+
+fn pathspec(dir: &Path) -> String {
+    if dir.as_os_str().is_empty() {
+        String::from(".")
+    } else {
+        dir.to_string_lossy().to_string()
+    }
+}
+
+/// The directories of `dirs` strictly inside `dir`.
+pub fn nested_dirs(dir: &Path, dirs: &[PathBuf]) -> Vec<PathBuf> {
+    dirs.iter()
+        .filter(|other| *other != dir && other.starts_with(dir))
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::process::Command};
+
+    fn git(repo: &Path, args: &[&str]) {
+        let output = Command::new("git")
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .current_dir(repo)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+    }
+
+    fn commit(repo: &Path, file: &str, subject: &str) {
+        let path = repo.join(file);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, subject).unwrap();
+        git(repo, &["add", "."]);
+        git(repo, &["commit", "-m", subject]);
+    }
+
+    #[test]
+    fn test_nested_dirs() {
+        let dirs = [
+            PathBuf::from("sdk"),
+            PathBuf::from("sdk/program"),
+            PathBuf::from("sdkx"),
+        ];
+        assert_eq!(
+            nested_dirs(Path::new("sdk"), &dirs),
+            vec![PathBuf::from("sdk/program")]
+        );
+        assert_eq!(nested_dirs(Path::new(""), &dirs), dirs.to_vec());
+    }
+
+    #[test]
+    fn test_update() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        git(repo, &["init", "--quiet"]);
+        commit(repo, "README.md", "Initial commit");
+        git(repo, &["tag", "v1.0.0"]);
+        commit(repo, "sdk/src/lib.rs", "Add sdk helper");
+        commit(repo, "sdk/program/src/lib.rs", "Fix program entrypoint");
+        commit(repo, "cli/src/main.rs", "Polish cli output");
+
+        let mut config = Config::default();
+        config.release.crate_changelogs = true;
+        let crates = [
+            (String::from("sdk"), PathBuf::from("sdk")),
+            (String::from("program"), PathBuf::from("sdk/program")),
+            (String::from("untouched"), PathBuf::from("untouched")),
+        ];
+        let written = update(
+            repo,
+            &crates,
+            &config,
+            &NotesTemplate::load(&config).unwrap(),
+            "1.1.0",
+            "2024-05-01",
+            "v1.0.0..HEAD",
+        )
+        .unwrap();
+        assert_eq!(
+            written,
+            vec![
+                PathBuf::from("sdk/CHANGELOG.md"),
+                PathBuf::from("sdk/program/CHANGELOG.md")
+            ]
+        );
+        assert_eq!(
+            fs::read_to_string(repo.join("sdk/CHANGELOG.md")).unwrap(),
+            "# Changelog\n\n## v1.1.0 - 2024-05-01\n\n- Add sdk helper\n"
+        );
+        assert_eq!(
+            fs::read_to_string(repo.join("sdk/program/CHANGELOG.md")).unwrap(),
+            "# Changelog\n\n## v1.1.0 - 2024-05-01\n\n- Fix program entrypoint\n"
+        );
+    }
+}
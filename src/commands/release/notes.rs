@@ -0,0 +1,290 @@
+use {
+    crate::{
+        config::{Config, NotesConfig},
+        utils::{git::git_in, template::render},
+    },
+    anyhow::{Context, Result},
+    std::{collections::BTreeSet, fs, path::Path},
+};
+
+/// A commit listed in the release notes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Commit {
+    pub hash: String,
+    pub author: String,
+    pub subject: String,
+}
+
+impl Commit {
+    /// Type, scope and summary of a conventional commit subject such as
+    /// `fix(cli): handle empty input`.
+    fn conventional(&self) -> Option<(&str, &str, &str)> {
+        let (prefix, summary) = self.subject.split_once(": ")?;
+        let prefix = prefix.strip_suffix('!').unwrap_or(prefix);
+        let (kind, scope) = match prefix.split_once('(') {
+            Some((kind, scope)) => (kind, scope.strip_suffix(')')?),
+            None => (prefix, ""),
+        };
+        if kind.is_empty() || !kind.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return None;
+        }
+        Some((kind, scope, summary))
+    }
+}
+
+/// Commits in `range` touching `pathspecs`, all commits without any, newest
+/// first.
+pub fn log(repo: &Path, range: &str, pathspecs: &[String]) -> Result<Vec<Commit>> {
+    let mut args = vec!["log", "--no-merges", "--format=%h%x1f%an%x1f%s", range];
+    if !pathspecs.is_empty() {
+        args.push("--");
+        args.extend(pathspecs.iter().map(String::as_str));
+    }
+    let log = git_in(repo, &args)?;
+    Ok(log
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\u{1f}');
+            let commit = Commit {
+                hash: fields.next()?.to_string(),
+                author: fields.next()?.to_string(),
+                subject: fields.next()?.to_string(),
+            };
+            (!commit.subject.trim().is_empty()).then_some(commit)
+        })
+        .collect())
+}
+
+/// Renders release notes from `release.notes`.
+pub struct NotesTemplate {
+    config: NotesConfig,
+    layout: Option<String>,
+}
+
+impl NotesTemplate {
+    pub fn load(config: &Config) -> Result<Self> {
+        let layout = match &config.release.notes.template {
+            Some(path) => {
+                let path = config.resolve(path);
+                Some(
+                    fs::read_to_string(&path)
+                        .context(format!("failed to read {}", path.display()))?,
+                )
+            }
+            None => None,
+        };
+        Ok(NotesTemplate {
+            config: config.release.notes.clone(),
+            layout,
+        })
+    }
+
+    /// The notes of `version`, released on `date`, ending with a newline.
+    pub fn render(&self, version: &str, date: &str, commits: &[Commit]) -> String {
+        let header = render(&self.config.header, &[("version", version), ("date", date)]);
+        let body = self.body(commits);
+        let credits = self.credits(commits);
+        let notes = match &self.layout {
+            Some(layout) => render(
+                layout,
+                &[
+                    ("header", &header),
+                    ("body", body.trim_end()),
+                    ("credits", credits.as_deref().unwrap_or_default()),
+                    ("version", version),
+                    ("date", date),
+                ],
+            ),
+            None => match credits {
+                Some(credits) => format!("{header}\n\n{body}\n{credits}\n"),
+                None => format!("{header}\n\n{body}"),
+            },
+        };
+        format!("{}\n", notes.trim_end())
+    }
+
+    fn body(&self, commits: &[Commit]) -> String {
+        if commits.is_empty() {
+            return format!("{}\n", self.config.empty);
+        }
+        if self.config.sections.is_empty() {
+            return self.entries(commits.iter());
+        }
+        let kind = |commit: &Commit| commit.conventional().map(|(kind, _, _)| kind.to_string());
+        let mut groups: Vec<(&str, Vec<&Commit>)> = self
+            .config
+            .sections
+            .iter()
+            .map(|section| {
+                let commits = commits
+                    .iter()
+                    .filter(|c| kind(c).is_some_and(|k| section.types.contains(&k)))
+                    .collect();
+                (section.title.as_str(), commits)
+            })
+            .collect();
+        let other = commits
+            .iter()
+            .filter(|c| {
+                !kind(c).is_some_and(|k| self.config.sections.iter().any(|s| s.types.contains(&k)))
+            })
+            .collect();
+        groups.push((self.config.other.as_str(), other));
+        groups
+            .into_iter()
+            .filter(|(_, commits)| !commits.is_empty())
+            .map(|(title, commits)| {
+                format!(
+                    "{}\n\n{}",
+                    render(&self.config.section, &[("title", title)]),
+                    self.entries(commits.into_iter())
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn entries<'a>(&self, commits: impl Iterator<Item = &'a Commit>) -> String {
+        commits
+            .map(|commit| {
+                let (kind, scope, summary) =
+                    commit.conventional().unwrap_or(("", "", &commit.subject));
+                let entry = render(
+                    &self.config.entry,
+                    &[
+                        ("subject", &commit.subject),
+                        ("summary", summary),
+                        ("type", kind),
+                        ("scope", scope),
+                        ("author", &commit.author),
+                        ("hash", &commit.hash),
+                    ],
+                );
+                format!("{entry}\n")
+            })
+            .collect()
+    }
+
+    fn credits(&self, commits: &[Commit]) -> Option<String> {
+        let template = self.config.credits.as_ref()?;
+        let authors: BTreeSet<&str> = commits.iter().map(|c| c.author.as_str()).collect();
+        if authors.is_empty() {
+            return None;
+        }
+        let authors = authors.into_iter().collect::<Vec<_>>().join(", ");
+        Some(render(template, &[("authors", &authors)]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::config::NotesSection, pretty_assertions::assert_eq};
+
+    fn commit(author: &str, subject: &str) -> Commit {
+        Commit {
+            hash: String::from("abc1234"),
+            author: author.to_string(),
+            subject: subject.to_string(),
+        }
+    }
+
+    fn template(config: NotesConfig, layout: Option<&str>) -> NotesTemplate {
+        NotesTemplate {
+            config,
+            layout: layout.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn test_conventional() {
+        assert_eq!(
+            commit("a", "fix(cli): handle empty input").conventional(),
+            Some(("fix", "cli", "handle empty input"))
+        );
+        assert_eq!(
+            commit("a", "feat!: drop old api").conventional(),
+            Some(("feat", "", "drop old api"))
+        );
+        assert_eq!(commit("a", "Add foo").conventional(), None);
+        assert_eq!(
+            commit("a", "Docs: see notes: here").conventional(),
+            Some(("Docs", "", "see notes: here"))
+        );
+        assert_eq!(commit("a", "Bump serde to 1.0: fixes").conventional(), None);
+    }
+
+    #[test]
+    fn test_render_default() {
+        let notes = template(NotesConfig::default(), None);
+        assert_eq!(
+            notes.render(
+                "1.3.0",
+                "2024-05-01",
+                &[commit("Ana", "Add foo"), commit("Bo", "Fix bar")]
+            ),
+            "## v1.3.0 - 2024-05-01\n\n- Add foo\n- Fix bar\n"
+        );
+        assert_eq!(
+            notes.render("1.3.0", "2024-05-01", &[]),
+            "## v1.3.0 - 2024-05-01\n\n- No changes\n"
+        );
+    }
+
+    #[test]
+    fn test_render_sections() {
+        let config = NotesConfig {
+            header: String::from("# Release {{version}}"),
+            entry: String::from("* {{summary}} ({{hash}}, @{{author}})"),
+            sections: vec![
+                NotesSection {
+                    types: vec![String::from("feat")],
+                    title: String::from("Features"),
+                },
+                NotesSection {
+                    types: vec![String::from("fix"), String::from("perf")],
+                    title: String::from("Fixes"),
+                },
+                NotesSection {
+                    types: vec![String::from("docs")],
+                    title: String::from("Documentation"),
+                },
+            ],
+            credits: Some(String::from("Thanks to {{authors}}!")),
+            ..NotesConfig::default()
+        };
+        let commits = [
+            commit("Bo", "fix(cli): handle empty input"),
+            commit("Ana", "feat: add foo"),
+            commit("Bo", "Update README"),
+            commit("Cy", "perf: cache lookups"),
+        ];
+        assert_eq!(
+            template(config.clone(), None).render("1.3.0", "2024-05-01", &commits),
+            "# Release 1.3.0
+
+### Features
+
+* add foo (abc1234, @Ana)
+
+### Fixes
+
+* handle empty input (abc1234, @Bo)
+* cache lookups (abc1234, @Cy)
+
+### Other changes
+
+* Update README (abc1234, @Bo)
+
+Thanks to Ana, Bo, Cy!
+"
+        );
+        assert_eq!(
+            template(
+                config,
+                Some("{{header}}\n\n{{credits}}\n\n{{body}}\n\nFull notes of v{{version}}.\n")
+            )
+            .render("1.3.0", "2024-05-01", &commits[1..2]),
+            "# Release 1.3.0\n\nThanks to Ana!\n\n### Features\n\n* add foo (abc1234, @Ana)\n\nFull notes of v1.3.0.\n"
+        );
+    }
+}
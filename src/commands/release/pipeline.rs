@@ -0,0 +1,598 @@
+use {
+    super::{
+        crate_changelogs,
+        notes::{self, NotesTemplate},
+        CommandArgs,
+    },
+    crate::{
+        commands::{
+            bump_version, check, lint,
+            package::RELEASE_DIR,
+            publish::{
+                self, compute_publish_order_data, ExecuteArgs, PublishPlan, PublishState, Violation,
+            },
+        },
+        config::Config,
+        failure::Failure,
+        utils::{
+            find_all_cargo_locks, find_all_cargo_tomls,
+            fs::sha256_file,
+            get_current_version, get_git_root_path, get_head_commit, get_last_tag, get_scope_root,
+            git::{current_branch, git_in, status_lines, tag_commit},
+            notify,
+            registry::{wait_for_versions, CRATES_IO_INDEX},
+            release_history::{ReleaseHistory, ReleaseRecord},
+            release_metadata::ReleaseMetadata,
+            scope::resolve_manifest_path,
+            telemetry,
+            time::{format_rfc3339, unix_now},
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    clap::ValueEnum,
+    log::{info, warn},
+    serde::{Deserialize, Serialize},
+    std::{
+        collections::BTreeMap,
+        fs,
+        path::{Path, PathBuf},
+        process::Command,
+        time::{Duration, Instant},
+    },
+};
+
+/// Where `package` leaves the release tarballs.
+const ARTIFACTS_DIR: &str = "dist";
+
+#[derive(ValueEnum, Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "kebab-case")]
+pub enum Stage {
+    #[value(help = "Run every lint as the release gate")]
+    Check,
+    #[value(help = "Bump the workspace version")]
+    Bump,
+    #[value(help = "Prepend the release notes to the changelog")]
+    Changelog,
+    #[value(help = "Commit the bumped manifests and changelog")]
+    Commit,
+    #[value(help = "Tag the release commit and push both")]
+    Tag,
+    #[value(help = "Publish the crates and wait for the index")]
+    Publish,
+    #[value(help = "Create the GitHub release")]
+    GithubRelease,
+    #[value(help = "Record the release in the release history and push it")]
+    History,
+    #[value(help = "Announce the release")]
+    Notify,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 9] = [
+        Stage::Check,
+        Stage::Bump,
+        Stage::Changelog,
+        Stage::Commit,
+        Stage::Tag,
+        Stage::Publish,
+        Stage::GithubRelease,
+        Stage::History,
+        Stage::Notify,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Stage::Check => "check",
+            Stage::Bump => "bump",
+            Stage::Changelog => "changelog",
+            Stage::Commit => "commit",
+            Stage::Tag => "tag",
+            Stage::Publish => "publish",
+            Stage::GithubRelease => "github-release",
+            Stage::History => "history",
+            Stage::Notify => "notify",
+        }
+    }
+}
+
+/// Checkpoint of a release run, written after every completed stage.
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq)]
+pub struct ReleaseState {
+    /// Version the bump stage produced.
+    pub version: Option<String>,
+    pub completed: Vec<Stage>,
+    /// Release notes the changelog stage wrote, reused for the GitHub release.
+    pub notes: Option<String>,
+    /// How long the publish stage took, recorded in the release history.
+    pub publish_secs: Option<u64>,
+}
+
+impl ReleaseState {
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content =
+            fs::read_to_string(path).context(format!("failed to read {}", path.display()))?;
+        let state = serde_json::from_str(&content)
+            .context(format!("failed to parse {}", path.display()))?;
+        Ok(Some(state))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context(format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)
+            .context(format!("failed to write {}", path.display()))
+    }
+
+    fn version(&self) -> Result<&str> {
+        self.version
+            .as_deref()
+            .ok_or_else(|| anyhow!("the checkpoint records no version, resume from bump"))
+    }
+}
+
+/// Stages still to run, starting at `resume_from` when given.
+///
+/// Without `resume_from` an unfinished earlier run is an error, so a second
+/// invocation never bumps the version twice. Resuming requires every stage
+/// before `resume_from` to be recorded as completed.
+pub fn stages_to_run(
+    state: Option<&ReleaseState>,
+    resume_from: Option<Stage>,
+) -> Result<Vec<Stage>> {
+    let first = match (state, resume_from) {
+        (Some(state), None) if !state.completed.is_empty() => {
+            let next = Stage::ALL
+                .iter()
+                .find(|stage| !state.completed.contains(stage))
+                .map_or("notify", |stage| stage.name());
+            return Err(anyhow!(
+                "an unfinished release is checkpointed, continue it with --resume-from {next}"
+            ));
+        }
+        (_, None) => Stage::Check,
+        (None, Some(stage)) if stage != Stage::Check => {
+            return Err(anyhow!(
+                "no release checkpoint to resume from, run without --resume-from"
+            ));
+        }
+        (state, Some(stage)) => {
+            let missing: Vec<&str> = Stage::ALL
+                .iter()
+                .filter(|earlier| **earlier < stage)
+                .filter(|earlier| !state.is_some_and(|s| s.completed.contains(earlier)))
+                .map(|earlier| earlier.name())
+                .collect();
+            if !missing.is_empty() {
+                return Err(anyhow!(
+                    "cannot resume from {}, stage(s) not completed: {}",
+                    stage.name(),
+                    missing.join(", ")
+                ));
+            }
+            stage
+        }
+    };
+    Ok(Stage::ALL
+        .into_iter()
+        .filter(|stage| *stage >= first)
+        .collect())
+}
+
+pub fn run(args: &CommandArgs, config: &Config) -> Result<()> {
+    let state_path = config.resolve(&config.release.state);
+    let existing = ReleaseState::load(&state_path)?;
+    let stages = stages_to_run(existing.as_ref(), args.resume_from)?;
+    let mut state = existing.unwrap_or_default();
+
+    for stage in stages {
+        info!("🚦 release stage: {}", stage.name());
+        if let Err(e) =
+            telemetry::phase(stage.name(), || run_stage(stage, args, config, &mut state))
+        {
+            let message = format!(
+                "release stopped at {}, re-run with --resume-from {}",
+                stage.name(),
+                stage.name()
+            );
+            if let Err(notify_error) = notify::send(config, &format!("❌ {message}: {e:#}")) {
+                warn!("failed to send the failure notification: {notify_error:#}");
+            }
+            return Err(e.context(message));
+        }
+        state.completed.push(stage);
+        state.save(&state_path)?;
+    }
+
+    fs::remove_file(&state_path).context(format!("failed to remove {}", state_path.display()))?;
+    info!("🎉 released v{}", state.version()?);
+    Ok(())
+}
+
+fn run_stage(
+    stage: Stage,
+    args: &CommandArgs,
+    config: &Config,
+    state: &mut ReleaseState,
+) -> Result<()> {
+    let repo = get_git_root_path()?;
+    match stage {
+        Stage::Check => {
+            ensure_clean(&repo, config)?;
+            let findings = check::run_checks(&get_scope_root()?, false)?;
+            lint::report(&findings)?;
+        }
+        Stage::Bump => {
+            let level = args
+                .level
+                .clone()
+                .ok_or_else(|| anyhow!("--level is required to bump the version"))?;
+            ensure_clean(&repo, config)?;
+            bump_version::run(bump_version::CommandArgs { level })?;
+            state.version = Some(get_current_version()?);
+        }
+        Stage::Changelog => {
+            let version = state.version()?.to_string();
+            let range = release_range(&repo)?;
+            let template = NotesTemplate::load(config)?;
+            let notes = template.render(&version, &today(), &notes::log(&repo, &range, &[])?);
+            let changelog = repo.join(&config.release.changelog);
+            if changelog.exists() {
+                let content = fs::read_to_string(&changelog)
+                    .context(format!("failed to read {}", changelog.display()))?;
+                fs::write(&changelog, prepend_changelog(&content, &notes))
+                    .context(format!("failed to write {}", changelog.display()))?;
+                git_in(&repo, &["add", "--", &changelog.to_string_lossy()])?;
+                info!("📝 updated {}", changelog.display());
+            }
+            if config.release.crate_changelogs {
+                let data =
+                    compute_publish_order_data(&resolve_manifest_path(&args.manifest_path)?)?;
+                let crates: Vec<(String, PathBuf)> =
+                    PublishPlan::from_order_data(&data, String::new())
+                        .packages()
+                        .map(|p| {
+                            let dir = data.workspace_root.join(&p.path);
+                            (
+                                p.name.clone(),
+                                dir.strip_prefix(&repo).unwrap_or(&dir).to_path_buf(),
+                            )
+                        })
+                        .collect();
+                let written = crate_changelogs::update(
+                    &repo,
+                    &crates,
+                    config,
+                    &template,
+                    &version,
+                    &today(),
+                    &range,
+                )?;
+                for changelog in written {
+                    git_in(&repo, &["add", "--", &changelog.to_string_lossy()])?;
+                }
+            }
+            state.notes = Some(notes);
+        }
+        Stage::Commit => {
+            let version = state.version()?;
+            // the changelogs are staged by their stage, the bump only touched
+            // tracked manifests and lockfiles
+            let bumped: Vec<String> = find_all_cargo_tomls()?
+                .into_iter()
+                .chain(find_all_cargo_locks()?)
+                .map(|path| path.to_string_lossy().to_string())
+                .collect();
+            let mut add = vec!["add", "--update", "--"];
+            add.extend(bumped.iter().map(String::as_str));
+            git_in(&repo, &add)?;
+            git_in(
+                &repo,
+                &["commit", "-m", &format!("Bump version to v{version}")],
+            )?;
+        }
+        Stage::Tag => {
+            let tag = format!("v{}", state.version()?);
+            git_in(&repo, &["tag", "--annotate", &tag, "--message", &tag])?;
+            let branch = push_refspec(&repo, config)?;
+            git_in(
+                &repo,
+                &["push", "--atomic", &config.release.remote, &branch, &tag],
+            )?;
+        }
+        Stage::Publish => {
+            let started = Instant::now();
+            let tag = format!("v{}", state.version()?);
+            publish::run(publish::CommandArgs {
+                manifest_path: args.manifest_path.clone(),
+                index_timeout: publish::DEFAULT_INDEX_TIMEOUT_SECS,
+                poll_interval: publish::DEFAULT_POLL_INTERVAL_SECS,
+                registry: None,
+                git_ref: None,
+                selection: publish::Selection::default(),
+                subcommand: publish::PublishSubcommand::Execute(ExecuteArgs {
+                    tag: Some(tag),
+                    // a checkpoint of this commit is left by this stage
+                    // failing earlier, one of another commit is stale
+                    resume: PublishState::resumable(
+                        &config.resolve(&config.publish.state),
+                        publish::CRATES_IO,
+                        &get_head_commit(&repo)?,
+                    )?,
+                    ..ExecuteArgs::default()
+                }),
+            })?;
+            let plan = PublishPlan::compute(&resolve_manifest_path(&args.manifest_path)?, config)?;
+            let crates: Vec<(String, String)> = plan
+                .packages()
+                .map(|p| (p.name.clone(), p.version.clone()))
+                .collect();
+            info!(
+                "⏳ waiting for {} crate(s) to reach the index",
+                crates.len()
+            );
+            wait_for_versions(
+                &config.network,
+                CRATES_IO_INDEX,
+                None,
+                &crates,
+                Duration::from_secs(publish::DEFAULT_INDEX_TIMEOUT_SECS),
+                Duration::from_secs(publish::DEFAULT_POLL_INTERVAL_SECS),
+            )?;
+            state.publish_secs = Some(started.elapsed().as_secs());
+        }
+        Stage::GithubRelease => {
+            let tag = format!("v{}", state.version()?);
+            let notes_file = tempfile::NamedTempFile::new()?;
+            let mut cmd = Command::new("gh");
+            cmd.args(["release", "create", &tag, "--title", &tag])
+                .current_dir(&repo);
+            match &state.notes {
+                Some(notes) => {
+                    fs::write(notes_file.path(), notes)?;
+                    cmd.arg("--notes-file").arg(notes_file.path());
+                }
+                None => {
+                    cmd.arg("--generate-notes");
+                }
+            }
+            let output = cmd.output().map_err(|e| anyhow!("failed to run gh: {e}"))?;
+            if !output.status.success() {
+                return Err(anyhow!(
+                    "gh release create failed: {}",
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ));
+            }
+        }
+        Stage::History => {
+            let version = state.version()?.to_string();
+            let tag = format!("v{version}");
+            let git_commit = tag_commit(&repo, &tag)?
+                .ok_or_else(|| anyhow!("tag {tag} does not exist, resume from tag"))?;
+            let path = config.resolve(&config.release.history);
+            let mut history = ReleaseHistory::load(&path)?;
+            history.record(ReleaseRecord {
+                artifacts: artifact_digests(&version, config)?,
+                version: version.clone(),
+                date: today(),
+                git_commit,
+                publish_duration_secs: state.publish_secs,
+            });
+            history.save(&path)?;
+            git_in(&repo, &["add", "--", &path.to_string_lossy()])?;
+            git_in(
+                &repo,
+                &[
+                    "commit",
+                    "-m",
+                    &format!("Record v{version} in the release history"),
+                ],
+            )?;
+            let branch = push_refspec(&repo, config)?;
+            git_in(&repo, &["push", &config.release.remote, &branch])?;
+        }
+        Stage::Notify => {
+            notify::send(config, &format!("🚀 released v{}", state.version()?))?;
+        }
+    }
+    Ok(())
+}
+
+/// SHA-256 of the release tarballs in `dist` and digests of the images
+/// pushed for `version`.
+fn artifact_digests(version: &str, config: &Config) -> Result<BTreeMap<String, String>> {
+    let mut digests = BTreeMap::new();
+    let dist = config.resolve(Path::new(ARTIFACTS_DIR));
+    if dist.is_dir() {
+        for entry in fs::read_dir(&dist).context(format!("failed to read {}", dist.display()))? {
+            let path = entry?.path();
+            let name = path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_default();
+            if name.starts_with(RELEASE_DIR) && name.ends_with(".tar.bz2") {
+                digests.insert(name, sha256_file(&path)?);
+            }
+        }
+    }
+    let metadata = ReleaseMetadata::load(&config.resolve(&config.release.metadata))?;
+    if metadata.version.as_deref() == Some(version) {
+        digests.extend(
+            metadata
+                .images
+                .into_iter()
+                .map(|image| (image.reference, image.digest)),
+        );
+    }
+    Ok(digests)
+}
+
+/// Fails on uncommitted changes, which the release commit would otherwise
+/// take along.
+fn ensure_clean(repo: &Path, config: &Config) -> Result<()> {
+    let changes = status_lines(repo, &config.state_paths())?;
+    if changes.is_empty() {
+        return Ok(());
+    }
+    Err(Failure::Preflight.error(format!(
+        "{}, commit or stash them before releasing",
+        Violation::Dirty(changes).message()
+    )))
+}
+
+/// `HEAD:refs/heads/<branch>` for `release.branch` or the checked out branch,
+/// a bare `HEAD` cannot be pushed from a detached checkout.
+fn push_refspec(repo: &Path, config: &Config) -> Result<String> {
+    let branch = match &config.release.branch {
+        Some(branch) => branch.clone(),
+        None => current_branch(repo)?.ok_or_else(|| {
+            anyhow!("HEAD is detached, set release.branch to the branch to push the release to")
+        })?,
+    };
+    Ok(format!("HEAD:refs/heads/{branch}"))
+}
+
+/// Commits since the previous release tag.
+fn release_range(repo: &Path) -> Result<String> {
+    Ok(match get_last_tag(repo)? {
+        Some(tag) => format!("{tag}..HEAD"),
+        None => String::from("HEAD"),
+    })
+}
+
+fn today() -> String {
+    format_rfc3339(unix_now())
+        .split('T')
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Inserts `section` above the newest entry, keeping a leading `# ` title.
+pub fn prepend_changelog(content: &str, section: &str) -> String {
+    let (title, rest) = match content.strip_prefix("# ") {
+        Some(_) => match content.split_once('\n') {
+            Some((title, rest)) => (format!("{title}\n\n"), rest.trim_start_matches('\n')),
+            None => (format!("{content}\n\n"), ""),
+        },
+        None => (String::new(), content),
+    };
+    if rest.is_empty() {
+        return format!("{title}{section}");
+    }
+    format!("{title}{section}\n{rest}")
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn completed(stages: &[Stage]) -> ReleaseState {
+        ReleaseState {
+            version: Some(String::from("1.3.0")),
+            completed: stages.to_vec(),
+            notes: None,
+            publish_secs: None,
+        }
+    }
+
+    #[test]
+    fn test_stages_to_run() {
+        assert_eq!(stages_to_run(None, None).unwrap(), Stage::ALL.to_vec());
+        assert_eq!(
+            stages_to_run(None, Some(Stage::Check)).unwrap(),
+            Stage::ALL.to_vec()
+        );
+        assert!(stages_to_run(None, Some(Stage::Tag)).is_err());
+
+        let state = completed(&[Stage::Check, Stage::Bump, Stage::Changelog, Stage::Commit]);
+        assert_eq!(
+            stages_to_run(Some(&state), None).unwrap_err().to_string(),
+            "an unfinished release is checkpointed, continue it with --resume-from tag"
+        );
+        assert_eq!(
+            stages_to_run(Some(&state), Some(Stage::Tag)).unwrap(),
+            vec![
+                Stage::Tag,
+                Stage::Publish,
+                Stage::GithubRelease,
+                Stage::History,
+                Stage::Notify
+            ]
+        );
+        assert_eq!(
+            stages_to_run(Some(&state), Some(Stage::Publish))
+                .unwrap_err()
+                .to_string(),
+            "cannot resume from publish, stage(s) not completed: tag"
+        );
+        assert_eq!(
+            stages_to_run(Some(&ReleaseState::default()), None).unwrap(),
+            Stage::ALL.to_vec()
+        );
+    }
+
+    #[test]
+    fn test_state_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".xtask/release-state.json");
+        assert_eq!(ReleaseState::load(&path).unwrap(), None);
+        let state = completed(&[Stage::Check, Stage::GithubRelease]);
+        state.save(&path).unwrap();
+        assert!(fs::read_to_string(&path)
+            .unwrap()
+            .contains("\"github-release\""));
+        assert_eq!(ReleaseState::load(&path).unwrap(), Some(state));
+    }
+
+    #[test]
+    fn test_changelog() {
+        let section = "## v1.3.0 - 2024-05-01\n\n- Add foo\n- Fix bar\n";
+        assert_eq!(
+            prepend_changelog("# Changelog\n\n## v1.2.0 - 2024-01-01\n\n- Old\n", section),
+            "# Changelog\n\n## v1.3.0 - 2024-05-01\n\n- Add foo\n- Fix bar\n\n## v1.2.0 - 2024-01-01\n\n- Old\n"
+        );
+        assert_eq!(prepend_changelog("", section), section);
+        assert_eq!(
+            prepend_changelog("# Changelog\n", section),
+            format!("# Changelog\n\n{section}")
+        );
+    }
+
+    #[test]
+    fn test_push_refspec() {
+        let dir = tempfile::tempdir().unwrap();
+        git_in(dir.path(), &["init", "--quiet", "--initial-branch", "main"]).unwrap();
+        git_in(
+            dir.path(),
+            &[
+                "-c",
+                "user.name=xtask",
+                "-c",
+                "user.email=xtask@example.com",
+                "commit",
+                "--quiet",
+                "--allow-empty",
+                "-m",
+                "init",
+            ],
+        )
+        .unwrap();
+        let mut config = Config::default();
+        assert_eq!(
+            push_refspec(dir.path(), &config).unwrap(),
+            "HEAD:refs/heads/main"
+        );
+
+        git_in(dir.path(), &["checkout", "--quiet", "--detach"]).unwrap();
+        assert!(push_refspec(dir.path(), &config).is_err());
+        config.release.branch = Some(String::from("release-2.2"));
+        assert_eq!(
+            push_refspec(dir.path(), &config).unwrap(),
+            "HEAD:refs/heads/release-2.2"
+        );
+    }
+}
@@ -0,0 +1,218 @@
+use {
+    crate::{
+        commands::{
+            publish::{PublishPlan, Registry},
+            yank::{yank, yank_order},
+        },
+        config::Config,
+        utils::{
+            get_git_root_path,
+            git::{current_branch, git_in, tag_commit, with_worktree_in},
+            prompt::confirm,
+            registry::{fetch_versions, CRATES_IO_INDEX},
+            release_history::ReleaseHistory,
+        },
+    },
+    anyhow::{anyhow, Result},
+    log::{info, warn},
+    std::{fs, path::Path, process::Command},
+};
+
+/// Undoes the release of `version`: yanks its crates, removes the GitHub
+/// release and tag and opens a PR reverting the bump commit. Every step asks
+/// for confirmation unless `assume_yes` is set.
+pub fn rollback(
+    manifest_path: &str,
+    version: &str,
+    assume_yes: bool,
+    config: &Config,
+) -> Result<()> {
+    let repo = get_git_root_path()?;
+    let tag = format!("v{version}");
+    // looked up before the tag is deleted
+    let bump_commit = match tag_commit(&repo, &tag)? {
+        Some(commit) => Some(commit),
+        None => {
+            match ReleaseHistory::load(&config.resolve(&config.release.history))?.get(version) {
+                Some(release) => Some(release.git_commit.clone()),
+                None => bump_commit_by_subject(&repo, version)?,
+            }
+        }
+    };
+
+    let mut failures = vec![];
+    // the crates of the release, HEAD may have added or dropped some since
+    let plan = if tag_commit(&repo, &tag)?.is_some() {
+        let manifest = fs::canonicalize(manifest_path)
+            .map_err(|e| anyhow!("failed to resolve {manifest_path}: {e}"))?;
+        let relative = manifest.strip_prefix(&repo).map_err(|_| {
+            anyhow!(
+                "{} is outside the repository at {}",
+                manifest.display(),
+                repo.display()
+            )
+        })?;
+        with_worktree_in(&repo, &tag, |worktree| {
+            PublishPlan::compute(&worktree.join(relative).to_string_lossy(), config)
+        })?
+    } else {
+        warn!("tag {tag} is gone, yanking the crates of HEAD");
+        PublishPlan::compute(manifest_path, config)?
+    };
+    let mut live = vec![];
+    for package in yank_order(&plan) {
+        let entries = fetch_versions(&config.network, CRATES_IO_INDEX, None, &package.name)?;
+        if entries.iter().any(|e| e.vers == version && !e.yanked) {
+            live.push(package.name.as_str());
+        }
+    }
+    if live.is_empty() {
+        info!("no crate is live at {version}, nothing to yank");
+    } else if confirm(
+        &format!("yank {} at {version}?", live.join(", ")),
+        assume_yes,
+    )? {
+        for name in live {
+            match yank(name, version, false, &Registry::crates_io(), config) {
+                Ok(()) => info!("🗑️ yanked {name}@{version}"),
+                Err(e) => failures.push(format!("failed to yank {name}@{version}: {e}")),
+            }
+        }
+    }
+
+    let has_github_release = Command::new("gh")
+        .args(["release", "view", &tag])
+        .current_dir(&repo)
+        .output()
+        .is_ok_and(|output| output.status.success());
+    if has_github_release {
+        let result = if confirm(&format!("delete the GitHub release {tag}?"), assume_yes)? {
+            gh(&repo, &["release", "delete", &tag, "--yes"])
+        } else {
+            info!("marking the GitHub release {tag} as rolled back instead");
+            gh(
+                &repo,
+                &[
+                    "release",
+                    "edit",
+                    &tag,
+                    "--prerelease",
+                    "--title",
+                    &format!("{tag} (rolled back)"),
+                ],
+            )
+        };
+        if let Err(e) = result {
+            failures.push(format!("failed to update the GitHub release {tag}: {e}"));
+        }
+    }
+
+    if tag_commit(&repo, &tag)?.is_some()
+        && confirm(
+            &format!("delete tag {tag} locally and on {}?", config.release.remote),
+            assume_yes,
+        )?
+    {
+        git_in(&repo, &["tag", "--delete", &tag])?;
+        if let Err(e) = git_in(
+            &repo,
+            &[
+                "push",
+                &config.release.remote,
+                "--delete",
+                &format!("refs/tags/{tag}"),
+            ],
+        ) {
+            failures.push(format!("failed to delete the remote tag {tag}: {e}"));
+        }
+    }
+
+    match bump_commit {
+        Some(commit)
+            if confirm(
+                &format!("open a PR reverting the bump commit {commit}?"),
+                assume_yes,
+            )? =>
+        {
+            if let Err(e) = open_revert_pr(&repo, &commit, &tag, config) {
+                failures.push(format!("failed to open the revert PR: {e:#}"));
+            }
+        }
+        Some(_) => {}
+        None => warn!("found no bump commit for {tag}, revert it by hand"),
+    }
+
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "rollback of {tag} incomplete:\n{}",
+            failures
+                .iter()
+                .map(|f| format!("  - {f}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        ));
+    }
+    info!("⏪ rolled back {tag}");
+    Ok(())
+}
+
+fn bump_commit_by_subject(repo: &Path, version: &str) -> Result<Option<String>> {
+    let commit = git_in(
+        repo,
+        &[
+            "log",
+            "--fixed-strings",
+            "--grep",
+            &format!("Bump version to v{version}"),
+            "--format=%H",
+            "-1",
+        ],
+    )?;
+    Ok((!commit.is_empty()).then_some(commit))
+}
+
+/// Reverts `commit` on a fresh branch from a throwaway worktree, leaving the
+/// checkout untouched, and opens a PR against the current branch.
+fn open_revert_pr(repo: &Path, commit: &str, tag: &str, config: &Config) -> Result<()> {
+    let branch = format!("revert-{tag}");
+    let base = current_branch(repo)?;
+    with_worktree_in(repo, "HEAD", |worktree| {
+        git_in(worktree, &["switch", "--create", &branch])?;
+        git_in(worktree, &["revert", "--no-edit", commit])?;
+        git_in(
+            worktree,
+            &[
+                "push",
+                &config.release.remote,
+                &format!("HEAD:refs/heads/{branch}"),
+            ],
+        )?;
+        let title = format!("Revert {tag}");
+        let body = format!("Reverts the version bump of the rolled back release {tag}.");
+        let mut args = vec![
+            "pr", "create", "--head", &branch, "--title", &title, "--body", &body,
+        ];
+        if let Some(base) = base.as_deref() {
+            args.extend(["--base", base]);
+        }
+        gh(worktree, &args)
+    })?;
+    info!("📬 opened a PR reverting {commit}");
+    Ok(())
+}
+
+fn gh(dir: &Path, args: &[&str]) -> Result<()> {
+    let output = Command::new("gh")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .map_err(|e| anyhow!("failed to run gh: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "gh {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,134 @@
+use {
+    crate::{
+        commands::publish::PublishPlan,
+        config::Config,
+        utils::{
+            get_git_root_path,
+            git::tag_commit,
+            registry::{fetch_versions, IndexEntry, CRATES_IO_INDEX},
+        },
+    },
+    anyhow::Result,
+    log::info,
+    std::process::Command,
+};
+
+/// How far `version` of one crate got on crates.io.
+#[derive(Debug, PartialEq)]
+pub struct CrateStatus {
+    pub name: String,
+    pub version: String,
+    /// Highest non-yanked published version, prereleases included.
+    pub latest: Option<String>,
+    pub live: bool,
+    pub yanked: bool,
+}
+
+impl CrateStatus {
+    pub fn from_entries(name: &str, version: &str, entries: &[IndexEntry]) -> Self {
+        let target = entries.iter().find(|entry| entry.vers == version);
+        let latest = entries
+            .iter()
+            .filter(|entry| !entry.yanked)
+            .filter_map(IndexEntry::version)
+            .max()
+            .map(|v| v.to_string());
+        CrateStatus {
+            name: name.to_string(),
+            version: version.to_string(),
+            latest,
+            live: target.is_some_and(|entry| !entry.yanked),
+            yanked: target.is_some_and(|entry| entry.yanked),
+        }
+    }
+
+    pub fn state(&self) -> &'static str {
+        if self.yanked {
+            "⚠️ yanked"
+        } else if self.live {
+            "✅ live"
+        } else {
+            "❌ not published"
+        }
+    }
+}
+
+/// Prints how far the release of `version` (the workspace version when
+/// omitted) got: the crates live on crates.io, the tag and the GitHub release.
+pub fn status(manifest_path: &str, version: Option<&str>, config: &Config) -> Result<()> {
+    let plan = PublishPlan::compute(manifest_path, config)?;
+    let mut crates = vec![];
+    for package in plan.packages() {
+        let target = version.unwrap_or(&package.version);
+        let entries = fetch_versions(&config.network, CRATES_IO_INDEX, None, &package.name)?;
+        crates.push(CrateStatus::from_entries(&package.name, target, &entries));
+    }
+    let Some(first) = crates.first() else {
+        info!("no publishable crates in the workspace");
+        return Ok(());
+    };
+
+    let tag = format!("v{}", version.unwrap_or(&first.version));
+    let repo = get_git_root_path()?;
+    let tagged = tag_commit(&repo, &tag)?.is_some();
+    let github_release = Command::new("gh")
+        .args(["release", "view", &tag])
+        .current_dir(&repo)
+        .output()
+        .is_ok_and(|output| output.status.success());
+
+    println!("{:<40} {:>12} {:>12}  state", "crate", "target", "latest");
+    for status in crates.iter() {
+        println!(
+            "{:<40} {:>12} {:>12}  {}",
+            status.name,
+            status.version,
+            status.latest.as_deref().unwrap_or("-"),
+            status.state()
+        );
+    }
+    println!();
+    println!(
+        "{} of {} crate(s) live",
+        crates.iter().filter(|c| c.live).count(),
+        crates.len()
+    );
+    println!("tag {tag}: {}", if tagged { "✅" } else { "❌" });
+    println!(
+        "GitHub release {tag}: {}",
+        if github_release { "✅" } else { "❌" }
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_from_entries() {
+        let entry = |vers: &str, yanked: bool| IndexEntry {
+            name: String::from("foo"),
+            vers: vers.to_string(),
+            yanked,
+        };
+        let entries = vec![
+            entry("2.1.0", false),
+            entry("2.2.0-rc.1", false),
+            entry("2.2.0", true),
+        ];
+        assert_eq!(
+            CrateStatus::from_entries("foo", "2.2.0", &entries),
+            CrateStatus {
+                name: String::from("foo"),
+                version: String::from("2.2.0"),
+                latest: Some(String::from("2.2.0-rc.1")),
+                live: false,
+                yanked: true,
+            }
+        );
+        let status = CrateStatus::from_entries("foo", "2.1.0", &entries);
+        assert!(status.live && !status.yanked);
+        assert_eq!(CrateStatus::from_entries("foo", "3.0.0", &[]).latest, None);
+    }
+}
@@ -0,0 +1,227 @@
+use {
+    crate::{
+        config::{ChannelSchedule, Config, FreezeWindow},
+        utils::{
+            audit::{self, AuditEntry, AuditOutcome},
+            time::{format_rfc3339, parse_rfc3339, unix_now, SECONDS_PER_DAY},
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    clap::{Args, Subcommand},
+    log::{info, warn},
+};
+
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+#[derive(Subcommand)]
+pub enum ScheduleSubcommand {
+    #[command(about = "Check a release may be cut for the channel today")]
+    Check {
+        #[arg(
+            long,
+            help = "Channel configured under schedule.channels in xtask.toml"
+        )]
+        channel: String,
+        #[arg(long, help = "Check this YYYY-MM-DD date instead of today")]
+        date: Option<String>,
+        #[arg(
+            long = "override",
+            value_name = "REASON",
+            help = "Allow an out-of-window release, recorded in the audit log"
+        )]
+        override_reason: Option<String>,
+    },
+}
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[command(subcommand)]
+    pub subcommand: ScheduleSubcommand,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    match args.subcommand {
+        ScheduleSubcommand::Check {
+            channel,
+            date,
+            override_reason,
+        } => {
+            let day = match date {
+                Some(date) => parse_rfc3339(&date)? / SECONDS_PER_DAY,
+                None => unix_now() / SECONDS_PER_DAY,
+            };
+            check(&config, &channel, day, override_reason.as_deref())
+        }
+    }
+}
+
+fn check(config: &Config, channel: &str, day: u64, override_reason: Option<&str>) -> Result<()> {
+    let schedule = config.schedule.channels.get(channel).ok_or_else(|| {
+        anyhow!(
+            "no schedule.channels.{channel} in xtask.toml, configured: {}",
+            config
+                .schedule
+                .channels
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    })?;
+    let date = format_date(day);
+    let blockers = blockers(schedule, &config.schedule.freezes, day)?;
+    if blockers.is_empty() {
+        info!("✅ {date} is a {channel} release day");
+        return Ok(());
+    }
+
+    let message = format!(
+        "{date} is outside the {channel} release window: {}",
+        blockers.join("; ")
+    );
+    if let Some(reason) = override_reason {
+        warn!("⚠️ {message}, overridden: {reason}");
+        let entry = AuditEntry::new(
+            "schedule-override",
+            vec![channel.to_string(), date, reason.to_string()],
+            AuditOutcome::Success,
+        );
+        return audit::record(config, &entry);
+    }
+    let owners = if schedule.owners.is_empty() {
+        String::new()
+    } else {
+        format!(", ask {} or pass --override", schedule.owners.join(", "))
+    };
+    Err(anyhow!("{message}{owners}"))
+}
+
+/// Why no release may be cut on `day` (days since the unix epoch), empty
+/// when it is a valid day.
+pub fn blockers(
+    schedule: &ChannelSchedule,
+    global_freezes: &[FreezeWindow],
+    day: u64,
+) -> Result<Vec<String>> {
+    let mut blockers = vec![];
+    let weekday = WEEKDAYS[weekday(day)];
+    if !schedule.days.is_empty()
+        && !schedule
+            .days
+            .iter()
+            .any(|d| d.eq_ignore_ascii_case(weekday))
+    {
+        blockers.push(format!(
+            "releases are cut on {}, not {weekday}",
+            schedule.days.join(", ")
+        ));
+    }
+
+    if schedule.every_weeks > 1 {
+        let anchor = schedule
+            .anchor
+            .as_deref()
+            .ok_or_else(|| anyhow!("schedule anchor is required with every-weeks"))?;
+        let anchor_day = parse_day(anchor)?;
+        let week = |day: u64| (day.saturating_add(3)) / 7;
+        let weeks = week(day).abs_diff(week(anchor_day));
+        if weeks % schedule.every_weeks != 0 {
+            blockers.push(format!(
+                "not a release week, releases happen every {} weeks from {anchor}",
+                schedule.every_weeks
+            ));
+        }
+    }
+
+    for freeze in global_freezes.iter().chain(schedule.freezes.iter()) {
+        let from = parse_day(&freeze.from)?;
+        let to = parse_day(&freeze.to)?;
+        if (from..=to).contains(&day) {
+            blockers.push(format!(
+                "frozen from {} to {}{}",
+                freeze.from,
+                freeze.to,
+                freeze
+                    .reason
+                    .as_deref()
+                    .map(|r| format!(" ({r})"))
+                    .unwrap_or_default()
+            ));
+        }
+    }
+    Ok(blockers)
+}
+
+/// Monday is 0; the unix epoch was a Thursday.
+fn weekday(day: u64) -> usize {
+    (day.saturating_add(3) % 7) as usize
+}
+
+fn parse_day(date: &str) -> Result<u64> {
+    Ok(parse_rfc3339(date).context(format!("invalid schedule date {date}"))? / SECONDS_PER_DAY)
+}
+
+fn format_date(day: u64) -> String {
+    format_rfc3339(day.saturating_mul(SECONDS_PER_DAY))[..10].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn day(date: &str) -> u64 {
+        parse_day(date).unwrap()
+    }
+
+    #[test]
+    fn test_weekday() {
+        assert_eq!(WEEKDAYS[weekday(0)], "thu");
+        assert_eq!(WEEKDAYS[weekday(day("2024-05-07"))], "tue");
+        assert_eq!(format_date(day("2024-05-07")), "2024-05-07");
+    }
+
+    #[test]
+    fn test_blockers() {
+        let schedule = ChannelSchedule {
+            days: vec![String::from("tue"), String::from("wed")],
+            every_weeks: 2,
+            anchor: Some(String::from("2024-05-07")),
+            freezes: vec![FreezeWindow {
+                from: String::from("2024-12-20"),
+                to: String::from("2025-01-05"),
+                reason: Some(String::from("holidays")),
+            }],
+            owners: vec![],
+        };
+        assert_eq!(
+            blockers(&schedule, &[], day("2024-05-08")).unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            blockers(&schedule, &[], day("2024-05-21")).unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            blockers(&schedule, &[], day("2024-05-16")).unwrap(),
+            vec![
+                "releases are cut on tue, wed, not thu",
+                "not a release week, releases happen every 2 weeks from 2024-05-07",
+            ]
+        );
+        assert_eq!(
+            blockers(&schedule, &[], day("2024-12-31")).unwrap(),
+            vec!["frozen from 2024-12-20 to 2025-01-05 (holidays)"]
+        );
+
+        let global = [FreezeWindow {
+            from: String::from("2024-05-08"),
+            to: String::from("2024-05-08"),
+            reason: None,
+        }];
+        assert_eq!(
+            blockers(&ChannelSchedule::default(), &global, day("2024-05-08")).unwrap(),
+            vec!["frozen from 2024-05-08 to 2024-05-08"]
+        );
+    }
+}
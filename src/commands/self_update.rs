@@ -0,0 +1,209 @@
+use {
+    crate::{
+        config::{Config, SelfUpdateConfig},
+        failure::Failure,
+        utils::{fs::sha256_file, http, signature},
+    },
+    anyhow::{anyhow, Context, Result},
+    clap::Args,
+    log::{info, warn},
+    semver::Version,
+    std::{env, fs, path::Path, process::Command},
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, help = "Version to install [default: the latest release]")]
+    pub version: Option<Version>,
+
+    #[arg(
+        long,
+        help = "Install without a signature check when self-update.public-key is not configured"
+    )]
+    pub insecure: bool,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    let current = env::current_exe().context("failed to locate the running binary")?;
+    let dir = current
+        .parent()
+        .ok_or_else(|| anyhow!("{} has no parent directory", current.display()))?;
+    let target = target_triple(env::consts::ARCH, env::consts::OS)?;
+    let asset = format!("xtask-{target}");
+    let url = asset_url(&config.self_update, args.version.as_ref(), &asset);
+    if config.self_update.public_key.is_none() && !args.insecure {
+        return Err(Failure::Verification.error(
+            "self-update.public-key is not configured, refusing to install an unsigned binary; pass --insecure to rely on the checksum alone",
+        ));
+    }
+
+    // next to the binary, so the final rename stays on one filesystem
+    let download = tempfile::Builder::new()
+        .prefix(".xtask-update")
+        .tempfile_in(dir)
+        .context(format!(
+            "failed to create a temporary file in {}",
+            dir.display()
+        ))?;
+    info!("⬇️ downloading {url}");
+    http::download(&config.network, &url, download.path())?;
+
+    let checksum = http::get(&config.network, &format!("{url}.sha256"), &[])?;
+    let expected = parse_checksum(&String::from_utf8_lossy(&checksum))?;
+    let actual = sha256_file(download.path())?;
+    if actual != expected {
+        return Err(Failure::Verification.error(format!(
+            "checksum of {asset} is {actual}, the release says {expected}"
+        )));
+    }
+    match &config.self_update.public_key {
+        Some(public_key) => {
+            let public_key = config.resolve(public_key);
+            verify_signature(&config, &url, download.path(), &public_key)?;
+            info!("✅ signature verified with {}", public_key.display());
+        }
+        None => warn!("⚠️ --insecure: only the checksum was verified, not the signature"),
+    }
+
+    make_executable(download.path())?;
+    let reported = installed_version(download.path())?;
+    if let Some(version) = &args.version {
+        if reported != *version {
+            return Err(anyhow!(
+                "downloaded binary reports version {reported}, expected {version}"
+            ));
+        }
+    }
+    download
+        .persist(&current)
+        .map_err(|e| anyhow!("failed to replace {}: {}", current.display(), e.error))?;
+    info!(
+        "✅ {} updated from {} to {reported}",
+        current.display(),
+        env!("CARGO_PKG_VERSION")
+    );
+    Ok(())
+}
+
+/// `releases/latest/download` follows the latest release, so no API call is
+/// needed to resolve it.
+pub fn asset_url(config: &SelfUpdateConfig, version: Option<&Version>, asset: &str) -> String {
+    match version {
+        Some(version) => format!(
+            "https://github.com/{}/releases/download/v{version}/{asset}",
+            config.repository
+        ),
+        None => format!(
+            "https://github.com/{}/releases/latest/download/{asset}",
+            config.repository
+        ),
+    }
+}
+
+/// Release targets; Windows is left out, a running executable cannot be
+/// replaced there.
+pub fn target_triple(arch: &str, os: &str) -> Result<String> {
+    let vendor_os = match os {
+        "linux" => "unknown-linux-gnu",
+        "macos" => "apple-darwin",
+        _ => return Err(anyhow!("no prebuilt xtask for {os}")),
+    };
+    Ok(format!("{arch}-{vendor_os}"))
+}
+
+/// The digest of a `sha256sum` line, `<hex>  <file>`.
+pub fn parse_checksum(content: &str) -> Result<String> {
+    content
+        .split_whitespace()
+        .next()
+        .filter(|digest| digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(str::to_ascii_lowercase)
+        .ok_or_else(|| anyhow!("malformed checksum file: {content:?}"))
+}
+
+fn verify_signature(config: &Config, url: &str, binary: &Path, public_key: &Path) -> Result<()> {
+    // armored GPG keys share the extensions of GPG signatures, anything else
+    // is a minisign `.pub`
+    let extension = match signature::SignatureScheme::from_signature_path(public_key) {
+        Ok(signature::SignatureScheme::Gpg) => "asc",
+        _ => "minisig",
+    };
+    let signature_dir = tempfile::tempdir()?;
+    let signature = signature_dir.path().join(format!("xtask.{extension}"));
+    http::download(&config.network, &format!("{url}.{extension}"), &signature)?;
+    signature::verify_detached_signature(binary, &signature, public_key)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .context(format!("failed to make {} executable", path.display()))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Runs the new binary once, so a build for the wrong platform never
+/// replaces a working one.
+fn installed_version(binary: &Path) -> Result<Version> {
+    let output = Command::new(binary)
+        .arg("--version")
+        .output()
+        .map_err(|e| anyhow!("failed to run the downloaded binary: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "the downloaded binary failed to run: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    // xtask 0.1.0
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = stdout.split_whitespace().nth(1).unwrap_or_default();
+    Version::parse(version).context(format!("unexpected --version output {stdout:?}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_asset_url() {
+        let config = SelfUpdateConfig::default();
+        assert_eq!(
+            asset_url(
+                &config,
+                Some(&Version::new(0, 2, 0)),
+                "xtask-x86_64-unknown-linux-gnu"
+            ),
+            "https://github.com/anza-xyz/xtask/releases/download/v0.2.0/xtask-x86_64-unknown-linux-gnu"
+        );
+        assert_eq!(
+            asset_url(&config, None, "xtask-aarch64-apple-darwin"),
+            "https://github.com/anza-xyz/xtask/releases/latest/download/xtask-aarch64-apple-darwin"
+        );
+        assert_eq!(
+            target_triple("aarch64", "macos").unwrap(),
+            "aarch64-apple-darwin"
+        );
+        assert!(target_triple("x86_64", "freebsd").is_err());
+    }
+
+    #[test]
+    fn test_parse_checksum() {
+        let digest = "a".repeat(64);
+        assert_eq!(
+            parse_checksum(&format!("{digest}  xtask-x86_64-unknown-linux-gnu\n")).unwrap(),
+            digest
+        );
+        assert_eq!(
+            parse_checksum(&format!("{}\n", digest.to_uppercase())).unwrap(),
+            digest
+        );
+        assert!(parse_checksum("").is_err());
+        assert!(parse_checksum("not-a-digest  xtask").is_err());
+    }
+}
@@ -0,0 +1,478 @@
+mod instrumented;
+
+pub use instrumented::{Mode, Sanitizer};
+
+use {
+    crate::{
+        config::{Config, TestConfig},
+        utils::{scope::resolve_manifest_path, test_history::TestHistory},
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::{Message, MetadataCommand},
+    clap::{Args, Subcommand},
+    log::{info, warn},
+    serde::Deserialize,
+    std::{
+        collections::HashMap,
+        io::BufReader,
+        path::{Path, PathBuf},
+        process::{Command, Stdio},
+        str::FromStr,
+    },
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, default_value = "Cargo.toml", global = true)]
+    pub manifest_path: String,
+    #[command(subcommand)]
+    pub subcommand: TestSubcommand,
+}
+
+#[derive(Subcommand)]
+pub enum TestSubcommand {
+    #[command(about = "Run the workspace tests, recording how long each one takes")]
+    Run {
+        #[arg(
+            long,
+            help = "Run only shard INDEX/COUNT (1-based) of the test binaries, balanced by recorded runtime"
+        )]
+        shard: Option<Shard>,
+        #[arg(
+            long,
+            requires = "shard",
+            help = "History the shards are balanced by, only read [default: test.history]"
+        )]
+        weights: Option<PathBuf>,
+        #[arg(
+            long,
+            conflicts_with_all = ["shard", "sanitizer"],
+            help = "Run the crates listed in test.miri under miri"
+        )]
+        miri: bool,
+        #[arg(
+            long,
+            value_enum,
+            conflicts_with = "shard",
+            help = "Run the crates listed in test.sanitizers with this sanitizer"
+        )]
+        sanitizer: Option<Sanitizer>,
+        #[arg(
+            last = true,
+            help = "Arguments passed to every test binary, e.g. a filter"
+        )]
+        test_args: Vec<String>,
+    },
+    #[command(about = "Merge the histories written by sharded runs into test.history")]
+    Merge {
+        #[arg(
+            required = true,
+            help = "Shard histories, as `test run --shard` writes them"
+        )]
+        shards: Vec<PathBuf>,
+    },
+    #[command(about = "Show the slowest tests and the ones whose runtime regressed")]
+    Report {
+        #[arg(long, default_value_t = 20)]
+        slowest: usize,
+    },
+}
+
+/// `INDEX/COUNT` slice of the test binaries, e.g. `2/4`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl FromStr for Shard {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, count) = s
+            .split_once('/')
+            .ok_or_else(|| format!("expected INDEX/COUNT, got {s}"))?;
+        let index: usize = index
+            .parse()
+            .map_err(|_| format!("invalid index {index}"))?;
+        let count: usize = count
+            .parse()
+            .map_err(|_| format!("invalid count {count}"))?;
+        if index == 0 || index > count {
+            return Err(format!("index must be between 1 and {count}"));
+        }
+        Ok(Shard { index, count })
+    }
+}
+
+/// A compiled test executable of the workspace.
+struct TestBinary {
+    /// `<package>/<kind>/<target>`, what durations are recorded under.
+    id: String,
+    executable: PathBuf,
+    package_dir: PathBuf,
+}
+
+/// One line of libtest's `--format json` output.
+#[derive(Deserialize)]
+struct TestEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    event: String,
+    #[serde(default)]
+    name: String,
+    exec_time: Option<f64>,
+    /// Captured output of a failed test.
+    stdout: Option<String>,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let manifest_path = resolve_manifest_path(&args.manifest_path)?;
+    let config = Config::load()?;
+    let history_path = config.resolve(&config.test.history);
+    let mut history = TestHistory::load(&history_path)?;
+    match args.subcommand {
+        TestSubcommand::Run {
+            miri: true,
+            test_args,
+            ..
+        } => instrumented::run_instrumented(&manifest_path, Mode::Miri, &test_args, &config)?,
+        TestSubcommand::Run {
+            sanitizer: Some(sanitizer),
+            test_args,
+            ..
+        } => instrumented::run_instrumented(
+            &manifest_path,
+            Mode::Sanitizer(sanitizer),
+            &test_args,
+            &config,
+        )?,
+        TestSubcommand::Run {
+            shard,
+            weights,
+            test_args,
+            ..
+        } => {
+            let binaries = build_test_binaries(&manifest_path)?;
+            let binaries = match shard {
+                Some(shard) => {
+                    // shards never write what they are split by, so they all
+                    // see the same weights however their runs interleave
+                    let weights = match weights {
+                        Some(path) => TestHistory::load(&path)?,
+                        None => history.clone(),
+                    };
+                    let ids: Vec<&str> = binaries.iter().map(|b| b.id.as_str()).collect();
+                    let selected = shard_binaries(&ids, &weights, shard);
+                    info!(
+                        "🧩 shard {}/{}: {} of {} test binaries",
+                        shard.index,
+                        shard.count,
+                        selected.len(),
+                        binaries.len()
+                    );
+                    binaries
+                        .into_iter()
+                        .filter(|b| selected.contains(&b.id))
+                        .collect()
+                }
+                None => binaries,
+            };
+
+            let mut failed = vec![];
+            for binary in binaries.iter() {
+                info!("🧪 {}", binary.id);
+                failed.extend(run_binary(binary, &test_args, &mut history, &config.test)?);
+            }
+            // `--no-run` builds no doctests, the first shard runs them all
+            if shard.is_none_or(|shard| shard.index == 1) && has_doctests(&manifest_path)? {
+                info!("🧪 doctests");
+                if !run_doctests(&manifest_path, &test_args)? {
+                    failed.push(String::from("doctests"));
+                }
+            }
+            report_regressions(&history, &config.test);
+            match shard {
+                Some(shard) => {
+                    let ran: Vec<&str> = binaries.iter().map(|b| b.id.as_str()).collect();
+                    history.binaries.retain(|id, _| ran.contains(&id.as_str()));
+                    let path = shard_history_path(&history_path, shard);
+                    history.save(&path)?;
+                    info!(
+                        "📝 wrote {}, combine the shards with `xtask test merge`",
+                        path.display()
+                    );
+                }
+                None => history.save(&history_path)?,
+            }
+            if !failed.is_empty() {
+                return Err(anyhow!(
+                    "{} test(s) failed:\n  {}",
+                    failed.len(),
+                    failed.join("\n  ")
+                ));
+            }
+            info!("✅ all tests passed");
+        }
+        TestSubcommand::Merge { shards } => {
+            for path in shards.iter() {
+                history.merge(TestHistory::load(path)?);
+            }
+            history.save(&history_path)?;
+            info!(
+                "✅ merged {} shard(s) into {}",
+                shards.len(),
+                history_path.display()
+            );
+        }
+        TestSubcommand::Report { slowest } => {
+            if history.binaries.is_empty() {
+                return Err(anyhow!(
+                    "no test durations recorded in {}, run `xtask test run` first",
+                    history_path.display()
+                ));
+            }
+            println!("{:>10}  test", "median");
+            for (binary, test, secs) in history.slowest().into_iter().take(slowest) {
+                println!("{secs:>9.2}s  {binary} {test}");
+            }
+            report_regressions(&history, &config.test);
+        }
+    }
+    Ok(())
+}
+
+/// Builds every test target of the workspace, like `cargo test --no-run`.
+fn build_test_binaries(manifest_path: &str) -> Result<Vec<TestBinary>> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+    let names: HashMap<_, _> = metadata
+        .packages
+        .iter()
+        .map(|package| (package.id.clone(), package.name.to_string()))
+        .collect();
+
+    let mut child = Command::new("cargo")
+        .args([
+            "test",
+            "--workspace",
+            "--no-run",
+            "--message-format=json-render-diagnostics",
+        ])
+        .arg("--manifest-path")
+        .arg(manifest_path)
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow!("failed to run cargo test: {e}"))?;
+    let stdout = child.stdout.take().expect("piped stdout");
+    let mut binaries = vec![];
+    for message in Message::parse_stream(BufReader::new(stdout)) {
+        let Message::CompilerArtifact(artifact) = message? else {
+            continue;
+        };
+        let (Some(executable), true) = (artifact.executable, artifact.profile.test) else {
+            continue;
+        };
+        let package = names
+            .get(&artifact.package_id)
+            .cloned()
+            .unwrap_or_else(|| artifact.target.name.to_string());
+        let kind = artifact
+            .target
+            .kind
+            .first()
+            .map(|kind| kind.to_string())
+            .unwrap_or_default();
+        binaries.push(TestBinary {
+            id: format!("{package}/{kind}/{}", artifact.target.name),
+            executable: executable.into_std_path_buf(),
+            package_dir: artifact
+                .manifest_path
+                .parent()
+                .map(|dir| dir.as_std_path().to_path_buf())
+                .unwrap_or_default(),
+        });
+    }
+    if !child.wait()?.success() {
+        return Err(anyhow!("failed to build the tests"));
+    }
+    binaries.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(binaries)
+}
+
+fn has_doctests(manifest_path: &str) -> Result<bool> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+    Ok(metadata
+        .workspace_packages()
+        .iter()
+        .any(|package| package.targets.iter().any(|target| target.doctest)))
+}
+
+/// Runs the doctests of the workspace, which `cargo test --no-run` does not
+/// build. Their durations are not recorded.
+fn run_doctests(manifest_path: &str, test_args: &[String]) -> Result<bool> {
+    let status = Command::new("cargo")
+        .args([
+            "test",
+            "--workspace",
+            "--doc",
+            "--manifest-path",
+            manifest_path,
+        ])
+        .arg("--")
+        .args(test_args)
+        .status()
+        .map_err(|e| anyhow!("failed to run cargo test --doc: {e}"))?;
+    Ok(status.success())
+}
+
+/// Where a sharded run writes the history of the binaries it ran, next to
+/// `history`, e.g. `test-history.shard-2-of-4.json`.
+pub fn shard_history_path(history: &Path, shard: Shard) -> PathBuf {
+    history.with_extension(format!("shard-{}-of-{}.json", shard.index, shard.count))
+}
+
+/// Runs one test binary, recording the duration of every test it ran and
+/// returning the names of the failed ones.
+fn run_binary(
+    binary: &TestBinary,
+    test_args: &[String],
+    history: &mut TestHistory,
+    config: &TestConfig,
+) -> Result<Vec<String>> {
+    // libtest only reports times in its unstable json format, which
+    // RUSTC_BOOTSTRAP unlocks on stable toolchains
+    let output = Command::new(&binary.executable)
+        .args([
+            "-Z",
+            "unstable-options",
+            "--format",
+            "json",
+            "--report-time",
+        ])
+        .args(test_args)
+        .current_dir(&binary.package_dir)
+        .env("RUSTC_BOOTSTRAP", "1")
+        .env("CARGO_MANIFEST_DIR", &binary.package_dir)
+        .stderr(Stdio::inherit())
+        .output()
+        .context(format!("failed to run {}", binary.executable.display()))?;
+
+    let mut failed = vec![];
+    let mut finished = false;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        let Ok(event) = serde_json::from_str::<TestEvent>(line) else {
+            // tests printing to stdout with --nocapture
+            println!("{line}");
+            continue;
+        };
+        match (event.kind.as_str(), event.event.as_str()) {
+            ("test", "ok" | "failed") => {
+                if let Some(secs) = event.exec_time {
+                    history.record(&binary.id, &event.name, secs, config.samples);
+                }
+                if event.event == "failed" {
+                    eprintln!(
+                        "---- {} ----\n{}",
+                        event.name,
+                        event.stdout.as_deref().unwrap_or_default()
+                    );
+                    failed.push(format!("{} {}", binary.id, event.name));
+                }
+            }
+            ("suite", "ok" | "failed") => finished = true,
+            _ => {}
+        }
+    }
+    if !finished && !output.status.success() {
+        return Err(anyhow!("{} exited with {}", binary.id, output.status));
+    }
+    Ok(failed)
+}
+
+fn report_regressions(history: &TestHistory, config: &TestConfig) {
+    for regression in history.regressions(config.regression_ratio, config.regression_min_secs) {
+        warn!(
+            "🐢 {} {} took {:.2}s, up from {:.2}s",
+            regression.binary, regression.test, regression.latest_secs, regression.baseline_secs
+        );
+    }
+}
+
+/// The binaries of `shard`, balancing the recorded runtime across shards.
+/// Binaries without history weigh the average of the known ones. Every
+/// shard computes the same split as long as they are given the same weights.
+pub fn shard_binaries(binaries: &[&str], history: &TestHistory, shard: Shard) -> Vec<String> {
+    let known: Vec<f64> = binaries.iter().filter_map(|b| history.weight(b)).collect();
+    let default_weight = if known.is_empty() {
+        1.0
+    } else {
+        known.iter().sum::<f64>() / known.len() as f64
+    };
+    let mut weighted: Vec<(&str, f64)> = binaries
+        .iter()
+        .map(|b| (*b, history.weight(b).unwrap_or(default_weight)))
+        .collect();
+    weighted.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    let mut loads = vec![0.0_f64; shard.count];
+    let mut selected = vec![];
+    for (binary, weight) in weighted {
+        let lightest = (0..shard.count)
+            .min_by(|a, b| loads[*a].total_cmp(&loads[*b]))
+            .unwrap_or(0);
+        loads[lightest] += weight;
+        if lightest == shard.index.saturating_sub(1) {
+            selected.push(binary.to_string());
+        }
+    }
+    selected.sort();
+    selected
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_parse_shard() {
+        assert_eq!(
+            "2/4".parse::<Shard>().unwrap(),
+            Shard { index: 2, count: 4 }
+        );
+        assert!("0/4".parse::<Shard>().is_err());
+        assert!("5/4".parse::<Shard>().is_err());
+        assert!("2".parse::<Shard>().is_err());
+    }
+
+    #[test]
+    fn test_shard_binaries() {
+        let mut history = TestHistory::default();
+        history.record("a/lib/a", "slow", 10.0, 5);
+        history.record("b/lib/b", "medium", 6.0, 5);
+        history.record("c/lib/c", "quick", 2.0, 5);
+        let binaries = ["a/lib/a", "b/lib/b", "c/lib/c", "d/test/new"];
+        let shard = |index| shard_binaries(&binaries, &history, Shard { index, count: 2 });
+        // a (10) | b (6), then the unknown d (6) and c (2) go to the lighter shard
+        assert_eq!(shard(1), vec!["a/lib/a", "c/lib/c"]);
+        assert_eq!(shard(2), vec!["b/lib/b", "d/test/new"]);
+    }
+
+    #[test]
+    fn test_shard_history_path() {
+        assert_eq!(
+            shard_history_path(
+                Path::new(".xtask/test-history.json"),
+                Shard { index: 2, count: 4 }
+            ),
+            PathBuf::from(".xtask/test-history.shard-2-of-4.json")
+        );
+    }
+}
@@ -0,0 +1,273 @@
+use {
+    crate::{
+        config::{Config, TestConfig},
+        utils::{cargo::host_target, http::apply_cargo_network_env},
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::MetadataCommand,
+    clap::ValueEnum,
+    log::{info, warn},
+    std::{path::Path, process::Command},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, ValueEnum)]
+pub enum Sanitizer {
+    Address,
+    Thread,
+}
+
+impl Sanitizer {
+    pub fn name(self) -> &'static str {
+        match self {
+            Sanitizer::Address => "address",
+            Sanitizer::Thread => "thread",
+        }
+    }
+}
+
+/// A nightly-only way of running the tests.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Mode {
+    Miri,
+    Sanitizer(Sanitizer),
+}
+
+impl Mode {
+    fn name(self) -> &'static str {
+        match self {
+            Mode::Miri => "miri",
+            Mode::Sanitizer(sanitizer) => sanitizer.name(),
+        }
+    }
+
+    /// The crates opted in to the mode in `xtask.toml`.
+    fn crates(self, config: &TestConfig) -> Vec<String> {
+        match self {
+            Mode::Miri => config.miri.clone(),
+            Mode::Sanitizer(sanitizer) => config
+                .sanitizers
+                .get(sanitizer.name())
+                .cloned()
+                .unwrap_or_default(),
+        }
+    }
+
+    fn components(self) -> &'static [&'static str] {
+        match self {
+            Mode::Miri => &["miri", "rust-src"],
+            // -Zbuild-std instruments std too, which tsan needs to avoid
+            // false positives
+            Mode::Sanitizer(_) => &["rust-src"],
+        }
+    }
+}
+
+/// Runs the tests of every crate opted in to `mode` on the nightly
+/// toolchain, reporting all failing crates at the end.
+pub fn run_instrumented(
+    manifest_path: &str,
+    mode: Mode,
+    test_args: &[String],
+    config: &Config,
+) -> Result<()> {
+    let crates = mode.crates(&config.test);
+    if crates.is_empty() {
+        info!(
+            "no crates opted in to {}, list them under test.{} in xtask.toml",
+            mode.name(),
+            match mode {
+                Mode::Miri => String::from("miri"),
+                Mode::Sanitizer(sanitizer) => format!("sanitizers.{}", sanitizer.name()),
+            }
+        );
+        return Ok(());
+    }
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+    let unknown: Vec<&str> = crates
+        .iter()
+        .filter(|name| {
+            !metadata
+                .workspace_packages()
+                .iter()
+                .any(|p| p.name == **name)
+        })
+        .map(String::as_str)
+        .collect();
+    if !unknown.is_empty() {
+        return Err(anyhow!("not workspace members: {}", unknown.join(", ")));
+    }
+
+    let toolchain = &config.test.nightly_toolchain;
+    ensure_components(
+        metadata.workspace_root.as_std_path(),
+        toolchain,
+        mode.components(),
+    )?;
+    let host = host_target()?;
+    // a separate target dir keeps the instrumented build from evicting the
+    // regular one
+    let target_dir = metadata
+        .target_directory
+        .join(format!("test-{}", mode.name()));
+
+    let mut failures = vec![];
+    for name in crates.iter() {
+        info!("🧪 {name} under {}", mode.name());
+        let mut cmd = Command::new("cargo");
+        cmd.arg(format!("+{toolchain}"))
+            .args(cargo_args(mode, manifest_path, name, &host))
+            .arg("--")
+            .args(test_args)
+            .env("CARGO_TARGET_DIR", &target_dir)
+            .envs(mode_env(mode, &config.test));
+        apply_cargo_network_env(&mut cmd, &config.network);
+        let status = cmd
+            .status()
+            .map_err(|e| anyhow!("failed to run cargo: {e}"))?;
+        if status.success() {
+            println!("  ✅ {name}");
+        } else {
+            warn!("❌ {name}");
+            failures.push(name.as_str());
+        }
+    }
+    println!(
+        "{} of {} crate(s) passed under {}",
+        crates.len().saturating_sub(failures.len()),
+        crates.len(),
+        mode.name()
+    );
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "{} crate(s) failed under {}: {}",
+            failures.len(),
+            mode.name(),
+            failures.join(", ")
+        ));
+    }
+    Ok(())
+}
+
+/// Arguments after `cargo +<toolchain>`. Only unit and integration tests
+/// run: doctests and benches are not instrumented. Tests that cannot run
+/// under miri opt out with `#[cfg_attr(miri, ignore)]`.
+fn cargo_args(mode: Mode, manifest_path: &str, package: &str, host: &str) -> Vec<String> {
+    let mut args: Vec<String> = match mode {
+        Mode::Miri => vec![String::from("miri"), String::from("test")],
+        // an explicit --target keeps build scripts and proc macros
+        // uninstrumented
+        Mode::Sanitizer(_) => vec![
+            String::from("test"),
+            String::from("-Zbuild-std"),
+            String::from("--target"),
+            host.to_string(),
+        ],
+    };
+    args.extend(
+        [
+            "--manifest-path",
+            manifest_path,
+            "--package",
+            package,
+            "--lib",
+            "--tests",
+        ]
+        .map(String::from),
+    );
+    args
+}
+
+fn mode_env(mode: Mode, config: &TestConfig) -> Vec<(&'static str, String)> {
+    match mode {
+        Mode::Miri => vec![("MIRIFLAGS", config.miri_flags.join(" "))],
+        Mode::Sanitizer(sanitizer) => {
+            let flags = format!("-Zsanitizer={}", sanitizer.name());
+            vec![("RUSTFLAGS", flags.clone()), ("RUSTDOCFLAGS", flags)]
+        }
+    }
+}
+
+/// Installs `toolchain` with `components` unless they are there already.
+fn ensure_components(dir: &Path, toolchain: &str, components: &[&str]) -> Result<()> {
+    let installed = Command::new("rustup")
+        .current_dir(dir)
+        .args(["component", "list", "--installed", "--toolchain", toolchain])
+        .output()
+        .map_err(|e| anyhow!("failed to run rustup: {e}"))?;
+    let installed = String::from_utf8_lossy(&installed.stdout);
+    let missing: Vec<&str> = components
+        .iter()
+        .copied()
+        .filter(|component| {
+            !installed
+                .lines()
+                .any(|line| line == *component || line.starts_with(&format!("{component}-")))
+        })
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+    info!("📥 installing {} for {toolchain}", missing.join(", "));
+    let mut cmd = Command::new("rustup");
+    cmd.current_dir(dir)
+        .args(["toolchain", "install", toolchain, "--profile", "minimal"]);
+    for component in missing.iter() {
+        cmd.args(["--component", component]);
+    }
+    let status = cmd
+        .status()
+        .map_err(|e| anyhow!("failed to run rustup: {e}"))?;
+    if !status.success() {
+        return Err(anyhow!("failed to install {toolchain}"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_cargo_args() {
+        assert_eq!(
+            cargo_args(
+                Mode::Miri,
+                "Cargo.toml",
+                "solana-pubkey",
+                "x86_64-unknown-linux-gnu"
+            ),
+            [
+                "miri",
+                "test",
+                "--manifest-path",
+                "Cargo.toml",
+                "--package",
+                "solana-pubkey",
+                "--lib",
+                "--tests"
+            ]
+        );
+        assert_eq!(
+            cargo_args(
+                Mode::Sanitizer(Sanitizer::Thread),
+                "Cargo.toml",
+                "solana-pubkey",
+                "x86_64-unknown-linux-gnu"
+            )[..4],
+            [
+                "test",
+                "-Zbuild-std",
+                "--target",
+                "x86_64-unknown-linux-gnu"
+            ]
+        );
+        assert_eq!(
+            mode_env(Mode::Sanitizer(Sanitizer::Address), &TestConfig::default())[0],
+            ("RUSTFLAGS", String::from("-Zsanitizer=address"))
+        );
+    }
+}
@@ -1,10 +1,15 @@
 use {
-    crate::utils,
+    crate::utils::{
+        self,
+        discovery::Discovery,
+        portable::slash_path,
+        vfs::{FileSystem, RealFs},
+    },
     anyhow::Result,
     clap::Args,
-    log::{debug, info},
-    std::{fs, path::PathBuf},
+    std::path::PathBuf,
     toml_edit::{value, DocumentMut, Item},
+    tracing::{debug, debug_span, info},
 };
 
 #[derive(Args)]
@@ -22,22 +27,31 @@ pub struct CommandArgs {
 }
 
 pub fn run(args: CommandArgs) -> Result<()> {
-    let all_cargo_tomls = utils::recursive_find_files(&args.root_path, "Cargo.toml", |_| true)?;
+    let discovery = Discovery::for_tree(&args.root_path)?;
+    let all_cargo_tomls =
+        utils::recursive_find_files(&args.root_path, "Cargo.toml", &discovery, |_| true)?;
+    update_manifests(&RealFs, &all_cargo_tomls, &args)
+}
 
-    'MAIN_LOOP: for cargo_toml in all_cargo_tomls {
+/// Moves the requirements on `args.package` in `manifests` from `args.from`
+/// to `args.to`, leaving manifests under `args.exclude_paths` alone.
+pub fn update_manifests(
+    fs: &impl FileSystem,
+    manifests: &[PathBuf],
+    args: &CommandArgs,
+) -> Result<()> {
+    'MAIN_LOOP: for cargo_toml in manifests {
+        let _span = debug_span!("manifest", path = %cargo_toml.display()).entered();
         info!("[{}]", cargo_toml.display());
 
         for exclude_path in &args.exclude_paths {
-            if cargo_toml
-                .to_string_lossy()
-                .contains(exclude_path.to_string_lossy().as_ref())
-            {
+            if slash_path(cargo_toml).contains(&slash_path(exclude_path)) {
                 info!("  ⏩ skipped (exclude path)");
                 continue 'MAIN_LOOP;
             }
         }
 
-        let content = fs::read_to_string(&cargo_toml)?;
+        let content = fs.read_to_string(cargo_toml)?;
         let mut doc = content.parse::<DocumentMut>()?;
         let mut need_to_write = false;
 
@@ -66,7 +80,7 @@ pub fn run(args: CommandArgs) -> Result<()> {
         }
 
         if need_to_write {
-            fs::write(&cargo_toml, doc.to_string())?;
+            fs.write(cargo_toml, &doc.to_string())?;
         } else {
             info!("  ⏩ skipped (no changes)");
         }
@@ -98,7 +112,7 @@ fn update_dependency_spec(dep_spec: &mut Item, from: &str, to: &str) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use {super::*, toml_edit::Table};
+    use {super::*, crate::utils::vfs::MemoryFs, toml_edit::Table};
 
     #[test]
     fn test_update_dependency_spec_string() {
@@ -141,4 +155,42 @@ mod tests {
         assert!(update_dependency_spec(&mut dep_spec, "1.2.3", "1.2.4"));
         assert_eq!(dep_spec["version"].as_str(), Some("=1.2.4"));
     }
+
+    #[test]
+    fn test_update_manifests() {
+        let fs = MemoryFs::new([
+            (
+                "Cargo.toml",
+                "[workspace.dependencies]\nsolana-frozen-abi = \"3.0.0\"\n",
+            ),
+            (
+                "a/Cargo.toml",
+                "[dependencies]\nsolana-frozen-abi = { version = \"=3.0.0\", optional = true }\n",
+            ),
+            (
+                "f/Cargo.toml",
+                "[dependencies]\nsolana-frozen-abi = \"3.0.0\"\n",
+            ),
+        ]);
+        let args = CommandArgs {
+            root_path: PathBuf::from("."),
+            package: String::from("solana-frozen-abi"),
+            from: String::from("3.0.0"),
+            to: String::from("3.1.0"),
+            exclude_paths: vec![PathBuf::from("f/")],
+        };
+        update_manifests(&fs, &fs.paths_named("Cargo.toml"), &args).unwrap();
+        assert_eq!(
+            fs.get("Cargo.toml").unwrap(),
+            "[workspace.dependencies]\nsolana-frozen-abi = \"3.1.0\"\n"
+        );
+        assert_eq!(
+            fs.get("a/Cargo.toml").unwrap(),
+            "[dependencies]\nsolana-frozen-abi = { version = \"=3.1.0\", optional = true }\n"
+        );
+        assert_eq!(
+            fs.get("f/Cargo.toml").unwrap(),
+            "[dependencies]\nsolana-frozen-abi = \"3.0.0\"\n"
+        );
+    }
 }
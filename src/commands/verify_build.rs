@@ -0,0 +1,290 @@
+use {
+    super::build_sbf::{find_programs, verify_program_elf},
+    crate::{
+        config::Config,
+        failure::Failure,
+        utils::{
+            docker::{current_user_spec, ensure_pinned_image, run_checked},
+            fs::{sha256_bytes, sha256_file},
+            get_git_root_path, DockerRun,
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    clap::Args,
+    log::info,
+    std::{
+        fmt, fs,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, help = "Program crate to build")]
+    pub program: String,
+    #[arg(
+        long,
+        help = "Release artifact (.so path) or cluster (mainnet-beta, devnet, testnet or an RPC URL)"
+    )]
+    pub against: String,
+    #[arg(
+        long,
+        help = "On-chain address, required when verifying against a cluster"
+    )]
+    pub program_id: Option<String>,
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: PathBuf,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Target {
+    Artifact(PathBuf),
+    Cluster(String),
+}
+
+impl Target {
+    pub fn parse(against: &str) -> Self {
+        let path = Path::new(against);
+        if path.extension().is_some_and(|ext| ext == "so") || path.exists() {
+            Target::Artifact(path.to_path_buf())
+        } else {
+            Target::Cluster(against.to_string())
+        }
+    }
+}
+
+/// Hash comparison between the local build and the reference binary.
+#[derive(Debug, PartialEq)]
+pub struct Comparison {
+    pub local_hash: String,
+    pub local_size: usize,
+    pub reference_hash: String,
+    pub reference_size: usize,
+    pub first_difference: Option<usize>,
+}
+
+impl Comparison {
+    pub fn matches(&self) -> bool {
+        self.local_hash == self.reference_hash
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "local:     {} ({} bytes)",
+            self.local_hash, self.local_size
+        )?;
+        write!(
+            f,
+            "reference: {} ({} bytes)",
+            self.reference_hash, self.reference_size
+        )?;
+        if let Some(offset) = self.first_difference {
+            write!(f, "\nfirst difference at byte offset {offset:#x}")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let git_root = get_git_root_path()?;
+    let config = Config::load()?;
+    let image = config.programs.verify_image.as_deref().ok_or_else(|| {
+        anyhow!("verify-build needs programs.verify-image in xtask.toml (a digest-pinned image)")
+    })?;
+    ensure_pinned_image(image)?;
+
+    let program = find_programs(&git_root.join(&args.manifest_path))?
+        .into_iter()
+        .find(|p| p.package == args.program)
+        .ok_or_else(|| anyhow!("{} is not a program crate in this workspace", args.program))?;
+
+    let staging = tempfile::tempdir()?;
+    let reference = match Target::parse(&args.against) {
+        Target::Artifact(path) => path,
+        Target::Cluster(cluster) => {
+            let program_id = args
+                .program_id
+                .as_deref()
+                .ok_or_else(|| anyhow!("--program-id is required to verify against {cluster}"))?;
+            let dumped = staging.path().join("deployed.so");
+            dump_program(&cluster, program_id, &dumped)?;
+            dumped
+        }
+    };
+
+    let out_dir = git_root.join("target/verify-build");
+    fs::create_dir_all(&out_dir).context(format!("failed to create {}", out_dir.display()))?;
+    // a binary left by an earlier run must not pass for this build's output
+    let local = out_dir.join(format!("{}.so", program.lib_name));
+    if local.exists() {
+        fs::remove_file(&local).context(format!("failed to remove {}", local.display()))?;
+    }
+    let cargo_home = out_dir.join("cargo-home");
+    info!(
+        "⬇️  fetching the locked dependencies of {}",
+        program.package
+    );
+    run_checked(
+        &mut fetch_run(image, &git_root, &program.manifest_path, &cargo_home).to_command(),
+        "cargo fetch",
+    )?;
+    info!("🔨 building {} in {image}", program.package);
+    run_checked(
+        &mut verifiable_build_run(
+            image,
+            &git_root,
+            &program.manifest_path,
+            &out_dir,
+            &cargo_home,
+        )
+        .to_command(),
+        "verifiable build",
+    )?;
+    let local_bytes = fs::read(&local).context(format!("failed to read {}", local.display()))?;
+    verify_program_elf(&local_bytes)?;
+    info!("  {} sha256 {}", local.display(), sha256_file(&local)?);
+
+    let reference_bytes =
+        fs::read(&reference).context(format!("failed to read {}", reference.display()))?;
+    let comparison = compare(&local_bytes, &reference_bytes)?;
+    println!("{comparison}");
+    if !comparison.matches() {
+        return Err(Failure::Verification.error(format!(
+            "{} does not match {}",
+            program.package, args.against
+        )));
+    }
+    info!("✅ {} matches {}", program.package, args.against);
+    Ok(())
+}
+
+/// Downloads the locked dependencies into `cargo_home`, the only step with
+/// network access.
+pub fn fetch_run(
+    image: &str,
+    git_root: &Path,
+    manifest_path: &Path,
+    cargo_home: &Path,
+) -> DockerRun {
+    DockerRun {
+        mounts: vec![(git_root.to_path_buf(), git_root.to_path_buf())],
+        workdir: Some(git_root.to_path_buf()),
+        user: current_user_spec(),
+        env: vec![(String::from("CARGO_HOME"), cargo_home.display().to_string())],
+        command: vec![
+            String::from("cargo"),
+            String::from("fetch"),
+            String::from("--locked"),
+            String::from("--manifest-path"),
+            manifest_path.display().to_string(),
+        ],
+        ..DockerRun::new(image)
+    }
+}
+
+pub fn verifiable_build_run(
+    image: &str,
+    git_root: &Path,
+    manifest_path: &Path,
+    out_dir: &Path,
+    cargo_home: &Path,
+) -> DockerRun {
+    DockerRun {
+        mounts: vec![(git_root.to_path_buf(), git_root.to_path_buf())],
+        workdir: Some(git_root.to_path_buf()),
+        user: current_user_spec(),
+        env: vec![
+            (String::from("CARGO_HOME"), cargo_home.display().to_string()),
+            (String::from("CARGO_NET_OFFLINE"), String::from("true")),
+        ],
+        // no network so the build can only use what `fetch_run` downloaded
+        // for the lockfile
+        extra_args: vec![String::from("--network=none")],
+        command: vec![
+            String::from("cargo"),
+            String::from("build-sbf"),
+            String::from("--locked"),
+            String::from("--manifest-path"),
+            manifest_path.display().to_string(),
+            String::from("--sbf-out-dir"),
+            out_dir.display().to_string(),
+        ],
+        ..DockerRun::new(image)
+    }
+}
+
+fn dump_program(cluster: &str, program_id: &str, destination: &Path) -> Result<()> {
+    info!("⬇️  dumping {program_id} from {cluster}");
+    run_checked(
+        Command::new("solana")
+            .args(["program", "dump", "--url", cluster, program_id])
+            .arg(destination),
+        "solana program dump",
+    )
+}
+
+/// Compares program binaries, ignoring the zero padding the loader appends
+/// to deployed program data.
+pub fn compare(local: &[u8], reference: &[u8]) -> Result<Comparison> {
+    let local = trim_trailing_zeros(local);
+    let reference = trim_trailing_zeros(reference);
+    let first_difference = local
+        .iter()
+        .zip(reference.iter())
+        .position(|(a, b)| a != b)
+        .or_else(|| (local.len() != reference.len()).then(|| local.len().min(reference.len())));
+    Ok(Comparison {
+        local_hash: sha256_bytes(local),
+        local_size: local.len(),
+        reference_hash: sha256_bytes(reference),
+        reference_size: reference.len(),
+        first_difference,
+    })
+}
+
+fn trim_trailing_zeros(bytes: &[u8]) -> &[u8] {
+    let end = bytes
+        .iter()
+        .rposition(|b| *b != 0)
+        .map_or(0, |i| i.saturating_add(1));
+    &bytes[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_target_parse() {
+        assert_eq!(
+            Target::parse("dist/programs/spl_memo.so"),
+            Target::Artifact(PathBuf::from("dist/programs/spl_memo.so"))
+        );
+        assert_eq!(
+            Target::parse("mainnet-beta"),
+            Target::Cluster(String::from("mainnet-beta"))
+        );
+    }
+
+    #[test]
+    fn test_compare_ignores_padding() {
+        let comparison = compare(b"\x7fELF\x01\x02", b"\x7fELF\x01\x02\0\0\0\0").unwrap();
+        assert!(comparison.matches());
+        assert_eq!(comparison.first_difference, None);
+        assert_eq!(comparison.reference_size, 6);
+    }
+
+    #[test]
+    fn test_compare_reports_first_difference() {
+        let comparison = compare(b"\x7fELF\x01\x02", b"\x7fELF\x01\x03").unwrap();
+        assert!(!comparison.matches());
+        assert_eq!(comparison.first_difference, Some(5));
+
+        let comparison = compare(b"\x7fELF", b"\x7fELF\x01").unwrap();
+        assert_eq!(comparison.first_difference, Some(4));
+    }
+}
@@ -0,0 +1,347 @@
+use {
+    crate::{
+        commands::{package::RELEASE_DIR, self_update::parse_checksum},
+        config::Config,
+        failure::Failure,
+        utils::{check_docker_available, fs::sha256_file, signature, DockerRun},
+    },
+    anyhow::{anyhow, Context, Result},
+    clap::Args,
+    log::info,
+    std::{
+        collections::BTreeMap,
+        fs,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(help = "Release tag, e.g. v2.2.0")]
+    pub tag: String,
+    #[arg(
+        long,
+        help = "Keep the downloaded artifacts here instead of a temporary directory"
+    )]
+    pub dir: Option<PathBuf>,
+    #[arg(long, help = "Skip running the binaries in a container")]
+    pub skip_run: bool,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    let packaging = &config.packaging;
+    let temp = tempfile::tempdir()?;
+    let dir = args
+        .dir
+        .clone()
+        .unwrap_or_else(|| temp.path().to_path_buf());
+    fs::create_dir_all(&dir).context(format!("failed to create {}", dir.display()))?;
+
+    let public_key = config.resolve(packaging.public_key.as_ref().ok_or_else(|| {
+        Failure::Verification
+            .error("packaging.public-key is not configured, signatures cannot be verified")
+    })?);
+    info!(
+        "⬇️ downloading the assets of {} from {}",
+        args.tag, packaging.repository
+    );
+    let output = Command::new("gh")
+        .args(["release", "download", &args.tag, "--clobber"])
+        .args(["--repo", &packaging.repository, "--dir"])
+        .arg(&dir)
+        .output()
+        .map_err(|e| anyhow!("failed to run gh: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "gh release download {} failed: {}",
+            args.tag,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let tarballs = tarballs(&dir)?;
+    if tarballs.is_empty() {
+        return Err(Failure::Verification.error(format!(
+            "release {} has no {RELEASE_DIR}-<target>.tar.bz2 assets",
+            args.tag
+        )));
+    }
+    let checksums = read_checksums(&dir)?;
+    let version = args.tag.trim_start_matches('v');
+    let mut problems = vec![];
+    for (target, tarball) in tarballs.iter() {
+        info!("🔍 {target}");
+        let name = file_name(tarball);
+        match checksums.get(&name) {
+            Some(expected) => {
+                let actual = sha256_file(tarball)?;
+                if actual != *expected {
+                    problems.push(format!(
+                        "{name}: checksum is {actual}, the release says {expected}"
+                    ));
+                }
+            }
+            None => problems.push(format!("{name}: no checksum in the release")),
+        }
+        match signature::find_detached_signature(tarball) {
+            Some(sig) => {
+                if let Err(e) = signature::verify_detached_signature(tarball, &sig, &public_key) {
+                    problems.push(format!("{name}: {e:#}"));
+                }
+            }
+            None => problems.push(format!("{name}: no signature in the release")),
+        }
+        let entries = list_tarball(tarball)?;
+        problems.extend(
+            check_layout(&entries, &packaging.validator_binaries, target)
+                .into_iter()
+                .map(|problem| format!("{name}: {problem}")),
+        );
+        if !args.skip_run && runs_here(target) {
+            problems.extend(
+                run_binaries(tarball, &packaging.validator_binaries, version, &config)?
+                    .into_iter()
+                    .map(|problem| format!("{name}: {problem}")),
+            );
+        }
+    }
+    if !problems.is_empty() {
+        return Err(Failure::Verification.error(format!(
+            "release {} failed verification:\n{}",
+            args.tag,
+            problems
+                .iter()
+                .map(|p| format!("  - {p}"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        )));
+    }
+    info!("✅ {} verified, {} artifact(s)", args.tag, tarballs.len());
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default()
+}
+
+/// The release tarballs in `dir`, by target.
+fn tarballs(dir: &Path) -> Result<BTreeMap<String, PathBuf>> {
+    let prefix = format!("{RELEASE_DIR}-");
+    let mut tarballs = BTreeMap::new();
+    for entry in fs::read_dir(dir).context(format!("failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let name = file_name(&path);
+        if let Some(target) = name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(".tar.bz2"))
+        {
+            tarballs.insert(target.to_string(), path);
+        }
+    }
+    Ok(tarballs)
+}
+
+/// Digests by file name, from `SHA256SUMS` and `<asset>.sha256` files.
+fn read_checksums(dir: &Path) -> Result<BTreeMap<String, String>> {
+    let mut checksums = BTreeMap::new();
+    for entry in fs::read_dir(dir).context(format!("failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        let name = file_name(&path);
+        if name == "SHA256SUMS" || name == "sha256sums.txt" {
+            checksums.extend(parse_sha256sums(&fs::read_to_string(&path)?));
+        } else if let Some(asset) = name.strip_suffix(".sha256") {
+            checksums.insert(
+                asset.to_string(),
+                parse_checksum(&fs::read_to_string(&path)?)?,
+            );
+        }
+    }
+    Ok(checksums)
+}
+
+/// `<hex>  <file>` lines of `sha256sum` output, `*` marking binary mode.
+pub fn parse_sha256sums(content: &str) -> BTreeMap<String, String> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let (digest, file) = line.trim().split_once(char::is_whitespace)?;
+            let file = file.trim_start().trim_start_matches('*');
+            (digest.len() == 64 && !file.is_empty())
+                .then(|| (file.to_string(), digest.to_ascii_lowercase()))
+        })
+        .collect()
+}
+
+fn list_tarball(tarball: &Path) -> Result<Vec<String>> {
+    let output = Command::new("tar")
+        .arg("-tjf")
+        .arg(tarball)
+        .output()
+        .map_err(|e| anyhow!("failed to run tar: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "failed to list {}: {}",
+            tarball.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+/// Differences between the `entries` of a tarball and the layout
+/// `package --profile validator` produces.
+pub fn check_layout(entries: &[String], binaries: &[String], target: &str) -> Vec<String> {
+    let entries: Vec<&str> = entries
+        .iter()
+        .map(|e| e.trim_start_matches("./").trim_end_matches('/'))
+        .collect();
+    let extension = if target.contains("windows") {
+        ".exe"
+    } else {
+        ""
+    };
+    let mut expected = vec![format!("{RELEASE_DIR}/version.yml")];
+    expected.extend(
+        binaries
+            .iter()
+            .map(|binary| format!("{RELEASE_DIR}/bin/{binary}{extension}")),
+    );
+    let mut problems: Vec<String> = expected
+        .iter()
+        .filter(|path| !entries.contains(&path.as_str()))
+        .map(|path| format!("{path} is missing"))
+        .collect();
+    problems.extend(
+        entries
+            .iter()
+            .filter(|entry| {
+                **entry != RELEASE_DIR && !entry.starts_with(&format!("{RELEASE_DIR}/"))
+            })
+            .map(|entry| format!("{entry} is outside {RELEASE_DIR}/")),
+    );
+    problems
+}
+
+/// Whether the binaries of `target` run in a Linux container on this host.
+fn runs_here(target: &str) -> bool {
+    target.contains("linux") && target.starts_with(std::env::consts::ARCH)
+}
+
+/// Runs `--version` of every binary in `packaging.verify-image`, so the
+/// binaries are checked against a clean system rather than the build host.
+fn run_binaries(
+    tarball: &Path,
+    binaries: &[String],
+    version: &str,
+    config: &Config,
+) -> Result<Vec<String>> {
+    check_docker_available()?;
+    let extracted = tempfile::tempdir()?;
+    let output = Command::new("tar")
+        .arg("-xjf")
+        .arg(tarball)
+        .arg("-C")
+        .arg(extracted.path())
+        .output()
+        .map_err(|e| anyhow!("failed to run tar: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "failed to extract {}: {}",
+            tarball.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let mut problems = vec![];
+    for binary in binaries {
+        let mut docker_run = DockerRun::new(&config.packaging.verify_image);
+        docker_run.mounts.push((
+            extracted.path().join(RELEASE_DIR),
+            PathBuf::from("/release"),
+        ));
+        docker_run.command = vec![format!("/release/bin/{binary}"), String::from("--version")];
+        let output = docker_run
+            .to_command()
+            .output()
+            .map_err(|e| anyhow!("failed to run docker: {e}"))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if !output.status.success() || !stdout.contains(version) {
+            problems.push(format!(
+                "{binary} --version printed {:?}, expected version {version}",
+                format!(
+                    "{}{}",
+                    stdout.trim(),
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )
+            ));
+        } else {
+            info!("  {}", stdout.trim());
+        }
+    }
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_parse_sha256sums() {
+        let digest = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        let content = format!(
+            "{digest}  solana-release-x86_64-unknown-linux-gnu.tar.bz2\n{} *solana-release-aarch64-apple-darwin.tar.bz2\nmalformed line\n",
+            digest.to_uppercase()
+        );
+        assert_eq!(
+            parse_sha256sums(&content),
+            BTreeMap::from([
+                (
+                    String::from("solana-release-aarch64-apple-darwin.tar.bz2"),
+                    digest.to_string()
+                ),
+                (
+                    String::from("solana-release-x86_64-unknown-linux-gnu.tar.bz2"),
+                    digest.to_string()
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_check_layout() {
+        let entries = |paths: &[&str]| paths.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        let binaries = [String::from("solana"), String::from("agave-validator")];
+        assert_eq!(
+            check_layout(
+                &entries(&[
+                    "solana-release/",
+                    "solana-release/bin/",
+                    "solana-release/bin/solana",
+                    "solana-release/bin/agave-validator",
+                    "solana-release/version.yml",
+                ]),
+                &binaries,
+                "x86_64-unknown-linux-gnu"
+            ),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            check_layout(
+                &entries(&["./solana-release/bin/solana.exe", "stray.txt"]),
+                &binaries,
+                "x86_64-pc-windows-msvc"
+            ),
+            vec![
+                "solana-release/version.yml is missing",
+                "solana-release/bin/agave-validator.exe is missing",
+                "stray.txt is outside solana-release/",
+            ]
+        );
+    }
+}
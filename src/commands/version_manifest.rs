@@ -0,0 +1,349 @@
+use {
+    super::check_package_size::packaged_sources,
+    crate::{
+        config::Config,
+        failure::Failure,
+        utils::{
+            fs::{sha256_bytes, sha256_file},
+            get_head_commit,
+            git::git_in,
+            scope::resolve_manifest_path,
+        },
+    },
+    anyhow::{anyhow, Context, Result},
+    cargo_metadata::MetadataCommand,
+    clap::{Args, Subcommand},
+    log::info,
+    serde::{Deserialize, Serialize},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+    walkdir::WalkDir,
+};
+
+/// Files cargo writes into a `.crate` that are not sources: the normalized
+/// manifest (the original ships as `Cargo.toml.orig`), the vcs info and the
+/// generated lockfile.
+const GENERATED: [&str; 3] = ["Cargo.toml.orig", ".cargo_vcs_info.json", "Cargo.lock"];
+
+#[derive(Subcommand)]
+pub enum VersionManifestSubcommand {
+    #[command(about = "Check a checkout or a directory of .crate files against a manifest")]
+    Verify {
+        #[arg(help = "Manifest written by version-manifest")]
+        manifest: PathBuf,
+        #[arg(
+            long,
+            help = "Directory of <name>-<version>.crate files to check instead of the checkout"
+        )]
+        artifacts: Option<PathBuf>,
+    },
+}
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, default_value = "versions.json")]
+    pub out: PathBuf,
+
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+
+    #[command(subcommand)]
+    pub subcommand: Option<VersionManifestSubcommand>,
+}
+
+impl CommandArgs {
+    /// Recording writes the manifest, verifying only reads it.
+    pub fn is_mutating(&self) -> bool {
+        self.subcommand.is_none()
+    }
+}
+
+/// What a release shipped, consumed by deployment tooling to check that what
+/// it deploys is what was released.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VersionManifest {
+    pub git_commit: String,
+    pub crates: Vec<CrateEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CrateEntry {
+    pub name: String,
+    pub version: String,
+    /// Last commit touching the crate's directory.
+    pub git_sha: String,
+    /// SHA-256 over the packaged files and their digests, identical for a
+    /// checkout and the `.crate` built from it.
+    pub content_hash: String,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let config = Config::load()?;
+    let manifest_path = resolve_manifest_path(&args.manifest_path)?;
+    match args.subcommand {
+        None => {
+            let manifest = record(Path::new(&manifest_path), &config)?;
+            fs::write(
+                &args.out,
+                format!("{}\n", serde_json::to_string_pretty(&manifest)?),
+            )
+            .context(format!("failed to write {}", args.out.display()))?;
+            info!(
+                "✅ recorded {} crate(s) in {}",
+                manifest.crates.len(),
+                args.out.display()
+            );
+            Ok(())
+        }
+        Some(VersionManifestSubcommand::Verify {
+            manifest,
+            artifacts,
+        }) => {
+            let content = fs::read_to_string(&manifest)
+                .context(format!("failed to read {}", manifest.display()))?;
+            let expected: VersionManifest = serde_json::from_str(&content)
+                .context(format!("failed to parse {}", manifest.display()))?;
+            let problems = match artifacts {
+                Some(dir) => verify_artifacts(&expected, &dir)?,
+                None => compare(
+                    &expected,
+                    &record(Path::new(&manifest_path), &config)?.crates,
+                ),
+            };
+            if !problems.is_empty() {
+                return Err(Failure::Verification.error(format!(
+                    "{} does not match:\n{}",
+                    manifest.display(),
+                    problems
+                        .iter()
+                        .map(|p| format!("  - {p}"))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                )));
+            }
+            info!(
+                "✅ {} crate(s) match {}",
+                expected.crates.len(),
+                manifest.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+/// Entries for every publishable crate of the workspace.
+pub fn record(manifest_path: &Path, config: &Config) -> Result<VersionManifest> {
+    let metadata = MetadataCommand::new()
+        .manifest_path(manifest_path)
+        .no_deps()
+        .exec()
+        .context("failed to run cargo metadata")?;
+    let root = metadata.workspace_root.as_std_path();
+    let mut crates = vec![];
+    for package in metadata.workspace_packages() {
+        if package.publish.as_ref().is_some_and(|r| r.is_empty()) {
+            continue;
+        }
+        let dir = package
+            .manifest_path
+            .parent()
+            .map(|p| p.as_std_path().to_path_buf())
+            .unwrap_or_default();
+        let files: Vec<(String, PathBuf)> = packaged_sources(package, config)?
+            .into_iter()
+            .filter(|(name, _)| !GENERATED.contains(&name.as_str()))
+            .collect();
+        let dir_arg = dir.display().to_string();
+        crates.push(CrateEntry {
+            name: package.name.to_string(),
+            version: package.version.to_string(),
+            git_sha: git_in(root, &["log", "-1", "--format=%H", "--", &dir_arg])?,
+            content_hash: content_hash(files)?,
+        });
+    }
+    Ok(VersionManifest {
+        git_commit: get_head_commit(root)?,
+        crates,
+    })
+}
+
+/// Checks each crate of `expected` against `<name>-<version>.crate` in `dir`.
+fn verify_artifacts(expected: &VersionManifest, dir: &Path) -> Result<Vec<String>> {
+    let mut present = VersionManifest {
+        git_commit: expected.git_commit.clone(),
+        crates: vec![],
+    };
+    let mut actual = vec![];
+    let mut problems = vec![];
+    for entry in expected.crates.iter() {
+        let path = dir.join(format!("{}-{}.crate", entry.name, entry.version));
+        if !path.exists() {
+            problems.push(format!("{} is missing", path.display()));
+            continue;
+        }
+        present.crates.push(entry.clone());
+        let extracted = tempfile::tempdir()?;
+        let output = Command::new("tar")
+            .arg("-xzf")
+            .arg(&path)
+            .arg("-C")
+            .arg(extracted.path())
+            .output()
+            .map_err(|e| anyhow!("failed to run tar: {e}"))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "failed to extract {}: {}",
+                path.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+        let root = extracted
+            .path()
+            .join(format!("{}-{}", entry.name, entry.version));
+        actual.push(CrateEntry {
+            content_hash: content_hash(crate_files(&root)?)?,
+            ..entry.clone()
+        });
+    }
+    problems.extend(compare(&present, &actual));
+    Ok(problems)
+}
+
+/// Source files of an extracted `.crate`, with the original manifest under
+/// its checkout name.
+pub fn crate_files(root: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let mut files = vec![];
+    for entry in WalkDir::new(root) {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(root)?.display().to_string();
+        let name = match relative.as_str() {
+            "Cargo.toml.orig" => String::from("Cargo.toml"),
+            "Cargo.toml" => continue,
+            name if GENERATED.contains(&name) => continue,
+            _ => relative,
+        };
+        files.push((name, entry.path().to_path_buf()));
+    }
+    Ok(files)
+}
+
+/// SHA-256 of the `<digest>  <name>` lines of every file, sorted by name.
+pub fn content_hash(mut files: Vec<(String, PathBuf)>) -> Result<String> {
+    files.sort();
+    let mut listing = String::new();
+    for (name, path) in files {
+        listing.push_str(&format!("{}  {name}\n", sha256_file(&path)?));
+    }
+    Ok(sha256_bytes(listing.as_bytes()))
+}
+
+/// Differences between the manifest and what was found, empty when they match.
+/// Commits are not compared, a later checkout with the same sources is fine.
+pub fn compare(expected: &VersionManifest, actual: &[CrateEntry]) -> Vec<String> {
+    let mut problems = vec![];
+    for entry in expected.crates.iter() {
+        let Some(found) = actual.iter().find(|a| a.name == entry.name) else {
+            problems.push(format!("{} is not in the workspace", entry.name));
+            continue;
+        };
+        if found.version != entry.version {
+            problems.push(format!(
+                "{} is at {}, expected {}",
+                entry.name, found.version, entry.version
+            ));
+        } else if found.content_hash != entry.content_hash {
+            problems.push(format!(
+                "{}@{} content differs from the release",
+                entry.name, entry.version
+            ));
+        }
+    }
+    for found in actual.iter() {
+        if !expected.crates.iter().any(|e| e.name == found.name) {
+            problems.push(format!("{} is not in the manifest", found.name));
+        }
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn entry(name: &str, version: &str, content_hash: &str) -> CrateEntry {
+        CrateEntry {
+            name: name.to_string(),
+            version: version.to_string(),
+            git_sha: String::from("abc"),
+            content_hash: content_hash.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let expected = VersionManifest {
+            git_commit: String::from("abc"),
+            crates: vec![
+                entry("foo", "1.0.0", "aa"),
+                entry("bar", "1.0.0", "bb"),
+                entry("baz", "1.0.0", "cc"),
+            ],
+        };
+        let actual = vec![
+            CrateEntry {
+                git_sha: String::from("def"),
+                ..entry("foo", "1.0.0", "aa")
+            },
+            entry("bar", "1.0.1", "bb"),
+            entry("qux", "1.0.0", "dd"),
+        ];
+        assert_eq!(
+            compare(&expected, &actual),
+            vec![
+                "bar is at 1.0.1, expected 1.0.0",
+                "baz is not in the workspace",
+                "qux is not in the manifest",
+            ]
+        );
+        assert_eq!(
+            compare(&expected, &[entry("foo", "1.0.0", "ab")])[0],
+            "foo@1.0.0 content differs from the release"
+        );
+    }
+
+    #[test]
+    fn test_crate_files_match_checkout() {
+        let checkout = tempfile::tempdir().unwrap();
+        fs::create_dir(checkout.path().join("src")).unwrap();
+        fs::write(checkout.path().join("Cargo.toml"), "[package]\n").unwrap();
+        fs::write(checkout.path().join("src/lib.rs"), "").unwrap();
+        let source = vec![
+            (
+                String::from("src/lib.rs"),
+                checkout.path().join("src/lib.rs"),
+            ),
+            (
+                String::from("Cargo.toml"),
+                checkout.path().join("Cargo.toml"),
+            ),
+        ];
+
+        let packaged = tempfile::tempdir().unwrap();
+        fs::create_dir(packaged.path().join("src")).unwrap();
+        fs::write(packaged.path().join("Cargo.toml.orig"), "[package]\n").unwrap();
+        fs::write(packaged.path().join("Cargo.toml"), "# normalized\n").unwrap();
+        fs::write(packaged.path().join(".cargo_vcs_info.json"), "{}").unwrap();
+        fs::write(packaged.path().join("src/lib.rs"), "").unwrap();
+
+        assert_eq!(
+            content_hash(crate_files(packaged.path()).unwrap()).unwrap(),
+            content_hash(source).unwrap()
+        );
+    }
+}
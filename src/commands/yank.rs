@@ -0,0 +1,163 @@
+use {
+    crate::{
+        commands::publish::{PlannedPackage, PublishPlan, Registry, Selection},
+        config::Config,
+        utils::{
+            http::apply_cargo_network_env, prompt::confirm, registry::fetch_versions,
+            scope::resolve_manifest_path,
+        },
+    },
+    anyhow::{anyhow, Result},
+    clap::Args,
+    log::info,
+    std::{path::Path, process::Command},
+};
+
+#[derive(Args)]
+pub struct CommandArgs {
+    #[arg(long, help = "Version to yank, e.g. 2.1.3")]
+    pub version: String,
+    #[arg(
+        long,
+        help = "Only these crates, `*` and `?` wildcards allowed [default: every publishable crate]"
+    )]
+    pub package: Vec<String>,
+    #[arg(long, help = "Un-yank the version instead")]
+    pub undo: bool,
+    #[arg(long, help = "Registry to yank from [default: crates.io]")]
+    pub registry: Option<String>,
+    #[arg(long, short = 'y', help = "Do not ask for confirmation")]
+    pub yes: bool,
+    #[arg(long, default_value = "Cargo.toml")]
+    pub manifest_path: String,
+}
+
+pub fn run(args: CommandArgs) -> Result<()> {
+    let manifest_path = resolve_manifest_path(&args.manifest_path)?;
+    let config = Config::load()?;
+    let registry = match &args.registry {
+        Some(name) => {
+            let workspace_dir = std::env::current_dir()?
+                .join(Path::new(&manifest_path).parent().unwrap_or(Path::new("")));
+            Registry::resolve(Some(name), &config, &workspace_dir)?
+        }
+        None => Registry::crates_io(),
+    };
+    let selection = Selection {
+        only: args.package.clone(),
+        ..Selection::default()
+    };
+    let plan =
+        PublishPlan::compute_for(&manifest_path, registry.display_name(), &selection, &config)?;
+    let (action, order) = if args.undo {
+        // dependencies come back before the crates needing them
+        ("un-yank", plan.packages().collect::<Vec<_>>())
+    } else {
+        ("yank", yank_order(&plan))
+    };
+
+    let token = registry.index_token(&config)?;
+    let mut pending = vec![];
+    for package in order {
+        let entries = fetch_versions(
+            &config.network,
+            &registry.index_url,
+            token.as_deref(),
+            &package.name,
+        )?;
+        match entries.iter().find(|e| e.vers == args.version) {
+            Some(entry) if entry.yanked == args.undo => pending.push(package.name.as_str()),
+            Some(_) => info!("⏭️ {}@{} already {action}ed", package.name, args.version),
+            None => info!("⏭️ {}@{} was never published", package.name, args.version),
+        }
+    }
+    if pending.is_empty() {
+        info!("nothing to {action} at {}", args.version);
+        return Ok(());
+    }
+    if !confirm(
+        &format!(
+            "{action} {} at {} on {}?",
+            pending.join(", "),
+            args.version,
+            registry.display_name()
+        ),
+        args.yes,
+    )? {
+        return Ok(());
+    }
+
+    let mut failures = vec![];
+    for name in pending.iter() {
+        match yank(name, &args.version, args.undo, &registry, &config) {
+            Ok(()) => info!("🗑️ {action}ed {name}@{}", args.version),
+            Err(e) => failures.push(format!("  - {name}: {e}")),
+        }
+    }
+    if !failures.is_empty() {
+        return Err(anyhow!(
+            "failed to {action} {} of {} crate(s):\n{}",
+            failures.len(),
+            pending.len(),
+            failures.join("\n")
+        ));
+    }
+    Ok(())
+}
+
+/// Dependents before their dependencies, the reverse of the publish order.
+pub fn yank_order(plan: &PublishPlan) -> Vec<&PlannedPackage> {
+    plan.levels.iter().rev().flatten().collect()
+}
+
+/// Runs `cargo yank` (or `cargo yank --undo`) for one crate.
+pub fn yank(
+    name: &str,
+    version: &str,
+    undo: bool,
+    registry: &Registry,
+    config: &Config,
+) -> Result<()> {
+    let mut cmd = Command::new("cargo");
+    cmd.args(["yank", "--version", version, name]);
+    if undo {
+        cmd.arg("--undo");
+    }
+    registry.apply(&mut cmd, config)?;
+    apply_cargo_network_env(&mut cmd, &config.network);
+    let output = cmd
+        .output()
+        .map_err(|e| anyhow!("failed to run cargo yank: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::path::PathBuf};
+
+    #[test]
+    fn test_yank_order() {
+        let package = |name: &str| PlannedPackage {
+            name: name.to_string(),
+            version: String::from("2.2.1"),
+            path: PathBuf::from(name),
+            dependencies: vec![],
+        };
+        let plan = PublishPlan {
+            git_commit: String::from("abc"),
+            levels: vec![
+                vec![package("a"), package("b")],
+                vec![package("c")],
+                vec![package("d")],
+            ],
+        };
+        let names: Vec<&str> = yank_order(&plan).iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, ["d", "c", "a", "b"]);
+    }
+}
@@ -0,0 +1,1104 @@
+use {
+    crate::{
+        failure::Failure,
+        utils::{get_git_root_path, time::parse_date},
+    },
+    anyhow::{Context, Result},
+    serde::{Deserialize, Deserializer},
+    std::{
+        collections::BTreeMap,
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+pub const CONFIG_FILE_NAME: &str = "xtask.toml";
+
+/// Per-repo configuration read from `xtask.toml` at the git root.
+///
+/// Every section is optional so repos without the file get the defaults.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Config {
+    /// Directory holding `xtask.toml`; relative paths resolve against it.
+    #[serde(skip)]
+    pub root: PathBuf,
+    pub network: NetworkConfig,
+    pub audit: AuditConfig,
+    pub lock: LockConfig,
+    pub release: ReleaseConfig,
+    pub devcontainer: DevcontainerConfig,
+    pub programs: ProgramsConfig,
+    pub packaging: PackagingConfig,
+    pub itest: ItestConfig,
+    pub test: TestConfig,
+    pub pr: PrConfig,
+    pub lint: LintConfig,
+    pub no_std: NoStdConfig,
+    pub wasm: WasmConfig,
+    pub publish: PublishConfig,
+    pub scan_secrets: ScanSecretsConfig,
+    pub notify: NotifyConfig,
+    pub promote: PromoteConfig,
+    pub eol: EolConfig,
+    pub policy: PolicyConfig,
+    pub schedule: ScheduleConfig,
+    pub codegen: CodegenConfig,
+    pub cli_snapshots: CliSnapshotsConfig,
+    pub owners: OwnersConfig,
+    pub artifacts: ArtifactsConfig,
+    pub self_update: SelfUpdateConfig,
+    /// Command shortcuts, e.g. `bv = "bump-version"` or
+    /// `bvp = "bump-version patch"`.
+    pub aliases: BTreeMap<String, String>,
+    pub telemetry: TelemetryConfig,
+    pub discovery: DiscoveryConfig,
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// Proxy used for every outgoing request. Falls back to the standard
+    /// `HTTPS_PROXY`/`HTTP_PROXY`/`ALL_PROXY` environment variables.
+    pub proxy: Option<String>,
+    /// Hosts that bypass the proxy. Falls back to `NO_PROXY`.
+    pub no_proxy: Option<String>,
+    /// PEM bundle trusted instead of the system roots, e.g. the certificate
+    /// of a TLS-intercepting proxy. It replaces them for curl and cargo, so
+    /// it must also hold the roots of any host reached without the proxy.
+    pub ca_bundle: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AuditConfig {
+    pub enabled: bool,
+    /// JSONL file every mutating operation is appended to.
+    pub log: PathBuf,
+    /// Optional HTTP endpoint each entry is POSTed to as JSON.
+    pub endpoint: Option<String>,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        AuditConfig {
+            enabled: true,
+            log: PathBuf::from(".xtask/audit.jsonl"),
+            endpoint: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum LockBackend {
+    /// Lock files in a directory, which only excludes other pipelines when
+    /// they share it (the default lives inside the checkout).
+    File,
+    /// Refs under `refs/xtask/locks/` on a git remote, created atomically,
+    /// so every checkout pushing to the remote sees the lock.
+    #[default]
+    GitRef,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LockConfig {
+    pub backend: LockBackend,
+    /// Directory holding lock files (file backend).
+    pub directory: PathBuf,
+    /// Remote the lock refs are pushed to (git-ref backend).
+    pub remote: String,
+    /// Age after which a lock is taken to be left behind by a crashed
+    /// holder and is taken over; 0 never expires locks.
+    pub ttl_secs: u64,
+}
+
+impl Default for LockConfig {
+    fn default() -> Self {
+        LockConfig {
+            backend: LockBackend::GitRef,
+            directory: PathBuf::from(".xtask/locks"),
+            remote: String::from("origin"),
+            ttl_secs: 24 * 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ReleaseConfig {
+    /// Digest-pinned image `release --hermetic` runs in.
+    pub image: Option<String>,
+    /// Manifest of the xtask crate, built inside the image by
+    /// `release --hermetic`.
+    pub xtask_manifest: PathBuf,
+    /// Extra environment variables forwarded into the hermetic container.
+    pub pass_env: Vec<String>,
+    /// Release metadata (provenance) document pushed image digests are recorded in.
+    pub metadata: PathBuf,
+    /// Checkpoint file recording the stages a release run completed.
+    pub state: PathBuf,
+    /// Committed record of every release, read by `xtask history`.
+    pub history: PathBuf,
+    /// Remote the release commit and tag are pushed to.
+    pub remote: String,
+    /// Branch the release commits are pushed to, the checked out branch when
+    /// unset.
+    pub branch: Option<String>,
+    /// Changelog the release notes are prepended to, skipped when missing.
+    pub changelog: PathBuf,
+    /// Also keep a `CHANGELOG.md` per published crate, listing the commits
+    /// that touched it.
+    pub crate_changelogs: bool,
+    /// Templates the release notes are rendered with.
+    pub notes: NotesConfig,
+}
+
+impl Default for ReleaseConfig {
+    fn default() -> Self {
+        ReleaseConfig {
+            image: None,
+            xtask_manifest: PathBuf::from("ci/xtask/Cargo.toml"),
+            pass_env: vec![],
+            metadata: PathBuf::from("dist/release-metadata.json"),
+            state: PathBuf::from(".xtask/release-state.json"),
+            history: PathBuf::from("releases.json"),
+            remote: String::from("origin"),
+            branch: None,
+            changelog: PathBuf::from("CHANGELOG.md"),
+            crate_changelogs: false,
+            notes: NotesConfig::default(),
+        }
+    }
+}
+
+/// Templates of the release notes, with `{{key}}` placeholders. The defaults
+/// list every commit subject under a `## v<version> - <date>` header.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct NotesConfig {
+    /// File laying out the whole notes from `{{header}}`, `{{body}}` and
+    /// `{{credits}}`; without it they follow each other.
+    pub template: Option<PathBuf>,
+    /// Header line, with `{{version}}` and `{{date}}`.
+    pub header: String,
+    /// Line per commit, with `{{subject}}`, `{{summary}}`, `{{type}}`,
+    /// `{{scope}}`, `{{author}}` and `{{hash}}`; the summary is the subject
+    /// without its conventional commit prefix.
+    pub entry: String,
+    /// Body of a release without commits.
+    pub empty: String,
+    /// Heading of a section, with `{{title}}`.
+    pub section: String,
+    /// Sections grouping the commits by conventional commit type, in order;
+    /// without any the commits are listed as they come.
+    pub sections: Vec<NotesSection>,
+    /// Title of the section collecting commits no other section takes.
+    pub other: String,
+    /// Line crediting the commit authors, with `{{authors}}`.
+    pub credits: Option<String>,
+}
+
+impl Default for NotesConfig {
+    fn default() -> Self {
+        NotesConfig {
+            template: None,
+            header: String::from("## v{{version}} - {{date}}"),
+            entry: String::from("- {{subject}}"),
+            empty: String::from("- No changes"),
+            section: String::from("### {{title}}"),
+            sections: vec![],
+            other: String::from("Other changes"),
+            credits: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct NotesSection {
+    /// Conventional commit types collected here, e.g. `["fix", "perf"]`.
+    pub types: Vec<String>,
+    pub title: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DevcontainerConfig {
+    /// Overrides the `rust:<toolchain>-bookworm` base image.
+    pub base_image: Option<String>,
+    /// System packages installed in addition to the detected ones.
+    pub packages: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ProgramsConfig {
+    /// Digest-pinned image with the platform tools used by `verify-build`.
+    pub verify_image: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PackagingConfig {
+    /// Binaries shipped in the validator installer tarball.
+    pub validator_binaries: Vec<String>,
+    /// GitHub repository whose releases carry the tarballs.
+    pub repository: String,
+    /// Name of the Homebrew formula, scoop manifest and install directory.
+    pub name: String,
+    pub description: String,
+    pub homepage: String,
+    pub license: String,
+    /// Homebrew tap `package manifests --push` commits the formula to.
+    pub tap: Option<String>,
+    /// Scoop bucket `package manifests --push` commits the manifest to.
+    pub scoop_bucket: Option<String>,
+    /// Minisign or GPG key `verify-release` checks the tarball signatures
+    /// against; signatures are not checked when unset.
+    pub public_key: Option<PathBuf>,
+    /// Image `verify-release` runs the released binaries in.
+    pub verify_image: String,
+}
+
+impl Default for PackagingConfig {
+    fn default() -> Self {
+        PackagingConfig {
+            validator_binaries: [
+                "agave-validator",
+                "agave-install",
+                "agave-ledger-tool",
+                "solana",
+                "solana-keygen",
+                "solana-test-validator",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+            repository: String::from("anza-xyz/agave"),
+            name: String::from("solana"),
+            description: String::from("Command-line tools and validator of the Solana network"),
+            homepage: String::from("https://anza.xyz/"),
+            license: String::from("Apache-2.0"),
+            tap: None,
+            scoop_bucket: None,
+            public_key: None,
+            verify_image: String::from("debian:bookworm-slim"),
+        }
+    }
+}
+
+/// Test duration tracking of `xtask test`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TestConfig {
+    /// Per-test durations, kept across runs; CI caches it between jobs.
+    pub history: PathBuf,
+    /// Runs kept per test.
+    pub samples: usize,
+    /// A test regressed when its latest run took this many times its median.
+    pub regression_ratio: f64,
+    /// Tests faster than this are never reported as regressed.
+    pub regression_min_secs: f64,
+    /// Toolchain `test run --miri` and `--sanitizer` install and run on.
+    pub nightly_toolchain: String,
+    /// Crates whose tests `test run --miri` runs.
+    pub miri: Vec<String>,
+    /// `MIRIFLAGS`, e.g. `-Zmiri-disable-isolation`.
+    pub miri_flags: Vec<String>,
+    /// Crates whose tests `test run --sanitizer <name>` runs, by sanitizer
+    /// (`address`, `thread`).
+    pub sanitizers: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for TestConfig {
+    fn default() -> Self {
+        TestConfig {
+            history: PathBuf::from(".xtask/test-history.json"),
+            samples: 10,
+            regression_ratio: 2.0,
+            regression_min_secs: 1.0,
+            nightly_toolchain: String::from("nightly"),
+            miri: vec![],
+            miri_flags: vec![],
+            sanitizers: BTreeMap::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ItestConfig {
+    /// Runs the validator from this image instead of a local `solana-test-validator`.
+    pub image: Option<String>,
+    pub rpc_port: u16,
+    pub startup_timeout_secs: u64,
+    /// Where validator logs are collected when the tests fail.
+    pub log_dir: PathBuf,
+}
+
+impl Default for ItestConfig {
+    fn default() -> Self {
+        ItestConfig {
+            image: None,
+            rpc_port: 8899,
+            startup_timeout_secs: 60,
+            log_dir: PathBuf::from("target/itest-logs"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PrConfig {
+    /// Conventional commit types accepted in PR titles; empty disables the check.
+    pub title_types: Vec<String>,
+    pub max_title_length: usize,
+    /// Every prefix needs at least one matching label, e.g. `semver:patch`.
+    pub required_label_prefixes: Vec<String>,
+    pub require_linked_issue: bool,
+}
+
+impl Default for PrConfig {
+    fn default() -> Self {
+        PrConfig {
+            title_types: [
+                "feat", "fix", "perf", "refactor", "docs", "test", "ci", "build", "chore", "revert",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+            max_title_length: 72,
+            required_label_prefixes: vec![String::from("semver:"), String::from("changelog:")],
+            require_linked_issue: true,
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct LintConfig {
+    /// Directories holding test fixture crates, which deliberately sit
+    /// outside any workspace.
+    pub fixtures: Vec<PathBuf>,
+    /// Directories whose crates must all be listed in (or excluded from)
+    /// `workspace.members` of their enclosing workspace.
+    pub source_roots: Vec<PathBuf>,
+    pub deprecations: DeprecationsConfig,
+    /// Crates never published even though their manifest allows it, skipped
+    /// by `lint git-deps`.
+    pub unpublished: Vec<String>,
+    /// Lints a crate may set in its own `[lints]` instead of inheriting
+    /// `[workspace.lints]`, as `tool::lint` (`rust::` for rustc lints) or
+    /// `*`, keyed by crate name.
+    pub lint_overrides: BTreeMap<String, Vec<String>>,
+    /// Nested workspace roots whose `[profile.release]` may differ from the
+    /// root workspace's.
+    pub independent_profiles: Vec<PathBuf>,
+    /// Crates allowed to have a build script, with why it is needed.
+    pub build_scripts: BTreeMap<String, String>,
+    pub changelog: ChangelogLintConfig,
+    pub dep_sources: DepSourcesConfig,
+}
+
+/// How long `#[deprecated]` items may live before `lint deprecations` asks
+/// for their removal.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DeprecationsConfig {
+    /// Major releases an item may survive after its deprecation.
+    pub major_releases: u64,
+    /// Minor releases within the same major an item may survive, unlimited
+    /// when unset.
+    pub minor_releases: Option<u64>,
+}
+
+impl Default for DeprecationsConfig {
+    fn default() -> Self {
+        DeprecationsConfig {
+            major_releases: 1,
+            minor_releases: None,
+        }
+    }
+}
+
+/// Where `lint changelog` expects the entry for a change to a crate.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ChangelogLintConfig {
+    /// Changelog of a crate, keyed by crate name; crates not listed use
+    /// their own `CHANGELOG.md` with `release.crate-changelogs`, or else
+    /// `release.changelog`.
+    pub paths: BTreeMap<String, PathBuf>,
+    /// Pull request label waiving the check, e.g. for refactors.
+    pub skip_label: String,
+}
+
+impl Default for ChangelogLintConfig {
+    fn default() -> Self {
+        ChangelogLintConfig {
+            paths: BTreeMap::new(),
+            skip_label: String::from("no-changelog"),
+        }
+    }
+}
+
+/// Where `lint dep-sources` lets dependencies come from besides crates.io
+/// and local paths.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DepSourcesConfig {
+    /// Registries by cargo config name or index URL.
+    pub registries: Vec<String>,
+    /// Git repository URLs, `*` and `?` wildcards allowed, e.g.
+    /// `https://github.com/anza-xyz/*`.
+    pub git: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct NoStdConfig {
+    /// Bare-metal target `check-no-std` builds for; it has no `std` to leak into.
+    pub target: String,
+}
+
+impl Default for NoStdConfig {
+    fn default() -> Self {
+        NoStdConfig {
+            target: String::from("thumbv7em-none-eabihf"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct WasmConfig {
+    pub target: String,
+    /// Crates that claim wasm support and `check-wasm` builds.
+    pub packages: Vec<String>,
+}
+
+impl Default for WasmConfig {
+    fn default() -> Self {
+        WasmConfig {
+            target: String::from("wasm32-unknown-unknown"),
+            packages: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PublishConfig {
+    /// Compressed `.crate` size `check-package-size` flags, well below the
+    /// crates.io limit so growth is noticed before it blocks a release.
+    pub max_package_size_kib: u64,
+    /// README template `publish execute --generate-readme` fills in for
+    /// crates without one; `{{name}}`, `{{version}}`, `{{description}}`,
+    /// `{{repository}}`, `{{path}}` and `{{commit}}` are replaced.
+    pub readme_template: Option<PathBuf>,
+    /// Crates of one level published concurrently.
+    pub jobs: usize,
+    /// Pause after each published crate, to stay under registry rate limits.
+    pub delay_between_crates_secs: u64,
+    /// Pause between levels, giving the index time to catch up.
+    pub delay_between_levels_secs: u64,
+    /// Retries of a `cargo publish` the registry rejected with a 429.
+    pub rate_limit_retries: u32,
+    /// First wait after a 429, doubled on every further one.
+    pub rate_limit_backoff_secs: u64,
+    /// Longest wait between rate-limited attempts.
+    pub rate_limit_max_backoff_secs: u64,
+    /// Branches publishing is allowed from, `*` and `?` wildcards allowed.
+    /// Empty disables the check.
+    pub allowed_branches: Vec<String>,
+    /// Checkpoint file recording the crates a publish run published.
+    pub state: PathBuf,
+    /// Registries besides crates.io that `publish --registry` targets.
+    pub registries: BTreeMap<String, RegistryConfig>,
+    /// Where the token of a registry comes from, keyed by registry name
+    /// (`crates-io` for crates.io). Registries not listed use whatever
+    /// cargo finds itself, e.g. `CARGO_REGISTRY_TOKEN`.
+    pub credentials: BTreeMap<String, CredentialSource>,
+    /// Older `major.minor` lines still released from maintenance branches,
+    /// e.g. `["2.1"]`. Versions below the newest published release are
+    /// refused on any other line.
+    pub maintained_lines: Vec<String>,
+}
+
+impl Default for PublishConfig {
+    fn default() -> Self {
+        PublishConfig {
+            max_package_size_kib: 2048,
+            readme_template: None,
+            jobs: 1,
+            delay_between_crates_secs: 0,
+            delay_between_levels_secs: 0,
+            rate_limit_retries: 5,
+            rate_limit_backoff_secs: 60,
+            rate_limit_max_backoff_secs: 600,
+            allowed_branches: ["master", "main", "v*.*"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            state: PathBuf::from(".xtask/publish-state.json"),
+            registries: BTreeMap::new(),
+            credentials: BTreeMap::new(),
+            maintained_lines: vec![],
+        }
+    }
+}
+
+/// A registry token source, written as a single-key table, e.g.
+/// `{ command = ["op", "read", "op://release/crates-io/token"] }`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub enum CredentialSource {
+    /// Environment variable holding the token.
+    Env(String),
+    /// File holding just the token, relative to `xtask.toml`.
+    File(PathBuf),
+    /// Entry of the OS keyring: the macOS keychain or the freedesktop
+    /// secret service through `secret-tool`.
+    Keyring { service: String, account: String },
+    /// Program and arguments printing the token on stdout.
+    Command(Vec<String>),
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RegistryConfig {
+    /// Index, e.g. `sparse+https://crates.internal/index/`; cargo's own
+    /// `registries.<name>.index` is used when unset.
+    pub index: Option<String>,
+    /// Environment variable holding the publish token, forwarded to cargo
+    /// as `CARGO_REGISTRIES_<NAME>_TOKEN`; shorthand for an `env` entry in
+    /// `publish.credentials`.
+    pub token_env: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ScanSecretsConfig {
+    /// Run `scan-secrets` over every publishable crate in publish preflight.
+    pub preflight: bool,
+    /// Findings known to be false positives.
+    pub allow: Vec<AllowedSecret>,
+}
+
+impl Default for ScanSecretsConfig {
+    fn default() -> Self {
+        ScanSecretsConfig {
+            preflight: true,
+            allow: vec![],
+        }
+    }
+}
+
+/// A `scan-secrets` finding that is not a secret, e.g. a test keypair.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AllowedSecret {
+    /// `<crate>/<file>` or `<tarball>/<file>`, `*` and `?` wildcards allowed.
+    pub path: String,
+    /// Rule to allow, every rule when unset.
+    pub rule: Option<String>,
+    /// Why the finding is not a secret.
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct NotifyConfig {
+    /// Slack-compatible incoming webhook. Falls back to `SLACK_WEBHOOK`.
+    pub webhook: Option<String>,
+}
+
+/// Support policy `eol-check` enforces on release branches.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct EolConfig {
+    /// How many of the newest released minor lines are supported.
+    pub supported_lines: usize,
+    /// Release branches on `release.remote`, `*` and `?` wildcards allowed.
+    pub branch_pattern: String,
+    /// Label put on the tracking issues `eol-check --open-issues` opens.
+    pub issue_label: Option<String>,
+}
+
+impl Default for EolConfig {
+    fn default() -> Self {
+        EolConfig {
+            supported_lines: 2,
+            branch_pattern: String::from("v*.x"),
+            issue_label: None,
+        }
+    }
+}
+
+/// Dependency policy `generate-policy` writes into the cargo-deny and
+/// cargo-audit configs, shared by every repo vendoring this file.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PolicyConfig {
+    /// SPDX identifiers of the licenses dependencies may use.
+    pub licenses: Vec<String>,
+    pub banned: Vec<BannedCrate>,
+    pub advisory_ignores: Vec<AdvisoryIgnore>,
+    /// cargo-deny config, relative to `xtask.toml`.
+    pub deny: PathBuf,
+    /// cargo-audit config, relative to `xtask.toml`.
+    pub audit: PathBuf,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        PolicyConfig {
+            licenses: vec![],
+            banned: vec![],
+            advisory_ignores: vec![],
+            deny: PathBuf::from("deny.toml"),
+            audit: PathBuf::from(".cargo/audit.toml"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct BannedCrate {
+    pub name: String,
+    /// Semver requirement; every version is banned without one.
+    pub version: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct AdvisoryIgnore {
+    /// Advisory id, e.g. `RUSTSEC-2024-0344`.
+    pub id: String,
+    pub reason: String,
+    /// `YYYY-MM-DD` after which the ignore is dropped and the advisory
+    /// fails the audit again, as the unix seconds the day starts at.
+    #[serde(deserialize_with = "deserialize_date")]
+    pub expires: u64,
+}
+
+/// A `YYYY-MM-DD` date as the unix seconds of its start, rejecting dates
+/// that do not exist.
+fn deserialize_date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    let date = String::deserialize(deserializer)?;
+    parse_date(&date).map_err(serde::de::Error::custom)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PromoteConfig {
+    /// How long an rc must be live on crates.io before it is promoted to stable.
+    pub soak_hours: u64,
+    /// Age at which `prerelease-report` flags a prerelease that was neither
+    /// promoted nor superseded.
+    pub stale_prerelease_days: u64,
+}
+
+impl Default for PromoteConfig {
+    fn default() -> Self {
+        PromoteConfig {
+            soak_hours: 72,
+            stale_prerelease_days: 30,
+        }
+    }
+}
+
+/// Release train windows checked by `xtask schedule check`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ScheduleConfig {
+    /// Freezes applying to every channel.
+    pub freezes: Vec<FreezeWindow>,
+    pub channels: BTreeMap<String, ChannelSchedule>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ChannelSchedule {
+    /// Weekdays a release may be cut on (`mon` to `sun`), any day when empty.
+    pub days: Vec<String>,
+    /// Release every this many weeks, counted from `anchor`.
+    pub every_weeks: u64,
+    /// A date (`YYYY-MM-DD`) in a release week, required when `every-weeks` > 1.
+    pub anchor: Option<String>,
+    pub freezes: Vec<FreezeWindow>,
+    /// Who to ask for an out-of-window release.
+    pub owners: Vec<String>,
+}
+
+impl Default for ChannelSchedule {
+    fn default() -> Self {
+        ChannelSchedule {
+            days: vec![],
+            every_weeks: 1,
+            anchor: None,
+            freezes: vec![],
+            owners: vec![],
+        }
+    }
+}
+
+/// Inclusive range of `YYYY-MM-DD` dates no release may be cut in.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct FreezeWindow {
+    pub from: String,
+    pub to: String,
+    pub reason: Option<String>,
+}
+
+/// Code generators run by `xtask codegen`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CodegenConfig {
+    pub generators: Vec<Generator>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Generator {
+    pub name: String,
+    /// Crate the generator belongs to; the command runs in its directory,
+    /// otherwise next to `xtask.toml`.
+    pub package: Option<String>,
+    /// Program and arguments, e.g. `["cargo", "run", "-p", "foo-idl"]`.
+    pub command: Vec<String>,
+    /// Files and directories the generator writes, relative to `xtask.toml`.
+    pub outputs: Vec<PathBuf>,
+}
+
+/// Binaries whose CLI output `xtask cli-snapshots` compares to snapshots.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CliSnapshotsConfig {
+    /// Directory holding one snapshot per binary and invocation.
+    pub dir: PathBuf,
+    pub binaries: Vec<CliBinary>,
+}
+
+impl Default for CliSnapshotsConfig {
+    fn default() -> Self {
+        CliSnapshotsConfig {
+            dir: PathBuf::from("tests/cli-snapshots"),
+            binaries: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct CliBinary {
+    /// Name of the `[[bin]]` target.
+    pub name: String,
+    /// Argument lists to run the binary with, only `--help` when empty.
+    #[serde(default)]
+    pub invocations: Vec<Vec<String>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct OwnersConfig {
+    /// Mapping of teams to the crates they own.
+    pub maintainers: PathBuf,
+}
+
+impl Default for OwnersConfig {
+    fn default() -> Self {
+        OwnersConfig {
+            maintainers: PathBuf::from("maintainers.toml"),
+        }
+    }
+}
+
+/// Retention of nightly artifacts, applied by `xtask artifacts prune`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ArtifactsConfig {
+    /// Newest matching artifacts kept in each store.
+    pub keep_last: usize,
+    /// Wildcard pattern of the release tags, S3 entries and image tags to
+    /// prune; anything else is never touched.
+    pub pattern: String,
+    pub github_releases: bool,
+    /// S3 prefix holding one entry per nightly, e.g. `s3://bucket/nightly/`.
+    pub s3: Option<String>,
+    /// Container repositories whose tags are pruned, e.g. `anzaxyz/agave`.
+    pub images: Vec<String>,
+}
+
+impl Default for ArtifactsConfig {
+    fn default() -> Self {
+        ArtifactsConfig {
+            keep_last: 10,
+            pattern: String::from("nightly-*"),
+            github_releases: true,
+            s3: None,
+            images: vec![],
+        }
+    }
+}
+
+/// Where `xtask self-update` downloads prebuilt binaries from.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SelfUpdateConfig {
+    /// GitHub repository publishing `xtask-<target>` release assets.
+    pub repository: String,
+    /// Minisign or GPG key the `.minisig`/`.asc` signature of the binary must
+    /// verify against; only the checksum is checked when unset.
+    pub public_key: Option<PathBuf>,
+}
+
+impl Default for SelfUpdateConfig {
+    fn default() -> Self {
+        SelfUpdateConfig {
+            repository: String::from("anza-xyz/xtask"),
+            public_key: None,
+        }
+    }
+}
+
+/// Opt-in timings of commands and their phases, see `utils::telemetry`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct TelemetryConfig {
+    pub enabled: bool,
+    /// JSON lines file every report is appended to.
+    pub file: Option<PathBuf>,
+    /// StatsD `host:port` receiving the timers over UDP.
+    pub statsd: Option<String>,
+    /// OTLP/HTTP metrics endpoint, e.g. `http://collector:4318/v1/metrics`.
+    pub otlp: Option<String>,
+    /// Prefix of every metric name.
+    pub prefix: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        TelemetryConfig {
+            enabled: false,
+            file: None,
+            statsd: None,
+            otlp: None,
+            prefix: String::from("xtask"),
+        }
+    }
+}
+
+/// Paths, relative to `xtask.toml`, that `Cargo.toml` and `Cargo.lock`
+/// discovery skips or always visits, see `utils::discovery`.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DiscoveryConfig {
+    /// Trees never searched, e.g. vendored crates or test fixtures.
+    pub exclude: Vec<PathBuf>,
+    /// Trees searched even when gitignored or under an excluded path.
+    pub include: Vec<PathBuf>,
+}
+
+impl Config {
+    /// Loads `xtask.toml` from the git root, returning the default
+    /// configuration when the file does not exist.
+    pub fn load() -> Result<Self> {
+        let git_root = get_git_root_path()?;
+        Self::load_from(&git_root.join(CONFIG_FILE_NAME))
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        let root = path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        if !path.exists() {
+            return Ok(Config {
+                root,
+                ..Default::default()
+            });
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|e| Failure::Config.error(e))
+            .context(format!("failed to read {}", path.display()))?;
+        let mut config: Config = toml_edit::de::from_str(&content)
+            .map_err(|e| Failure::Config.error(e))
+            .context(format!("failed to parse {}", path.display()))?;
+
+        config.root = root;
+        config.network.ca_bundle = config.network.ca_bundle.map(|p| config.root.join(p));
+
+        Ok(config)
+    }
+
+    /// Resolves a path from the config against the directory of `xtask.toml`.
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        self.root.join(path)
+    }
+
+    /// The `lint.fixtures` directories, resolved like [`Config::resolve`].
+    pub fn fixture_dirs(&self) -> Vec<PathBuf> {
+        self.lint
+            .fixtures
+            .iter()
+            .map(|dir| self.resolve(dir))
+            .collect()
+    }
+
+    /// Files and directories xtask writes its own state to, which do not
+    /// make the working tree dirty.
+    pub fn state_paths(&self) -> Vec<PathBuf> {
+        [
+            &self.audit.log,
+            &self.lock.directory,
+            &self.release.state,
+            &self.publish.state,
+            &self.test.history,
+        ]
+        .into_iter()
+        .map(|path| self.resolve(path))
+        .collect()
+    }
+
+    /// Changelog of crate `name` in `dir`, both relative to the repository
+    /// root: its `lint.changelog.paths` entry, its own `CHANGELOG.md` with
+    /// `release.crate-changelogs`, or else `release.changelog`.
+    pub fn crate_changelog(&self, name: &str, dir: &Path) -> PathBuf {
+        match self.lint.changelog.paths.get(name) {
+            Some(path) => path.clone(),
+            None if self.release.crate_changelogs => dir.join("CHANGELOG.md"),
+            None => self.release.changelog.clone(),
+        }
+    }
+}
+
+impl NotifyConfig {
+    pub fn webhook(&self) -> Option<String> {
+        self.webhook.clone().or_else(|| {
+            std::env::var("SLACK_WEBHOOK")
+                .ok()
+                .filter(|v| !v.is_empty())
+        })
+    }
+}
+
+impl NetworkConfig {
+    pub fn proxy(&self) -> Option<String> {
+        self.proxy.clone().or_else(|| {
+            [
+                "HTTPS_PROXY",
+                "https_proxy",
+                "HTTP_PROXY",
+                "http_proxy",
+                "ALL_PROXY",
+                "all_proxy",
+            ]
+            .iter()
+            .find_map(|key| std::env::var(key).ok().filter(|v| !v.is_empty()))
+        })
+    }
+
+    pub fn no_proxy(&self) -> Option<String> {
+        self.no_proxy.clone().or_else(|| {
+            ["NO_PROXY", "no_proxy"]
+                .iter()
+                .find_map(|key| std::env::var(key).ok().filter(|v| !v.is_empty()))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_load_missing_config_is_default() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let config = Config::load_from(&temp_dir.path().join(CONFIG_FILE_NAME)).unwrap();
+        assert!(config.network.proxy.is_none());
+        assert!(config.network.ca_bundle.is_none());
+        assert!(config.audit.enabled);
+        assert_eq!(
+            config.resolve(&config.audit.log),
+            temp_dir.path().join(".xtask/audit.jsonl")
+        );
+    }
+
+    #[test]
+    fn test_load_network_config() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(CONFIG_FILE_NAME);
+        fs::write(
+            &path,
+            "[network]\nproxy = \"http://proxy:3128\"\nca-bundle = \"certs/ca.pem\"\n",
+        )
+        .unwrap();
+
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(
+            config.network.proxy(),
+            Some("http://proxy:3128".to_string())
+        );
+        assert_eq!(
+            config.network.ca_bundle,
+            Some(temp_dir.path().join("certs/ca.pem"))
+        );
+    }
+
+    #[test]
+    fn test_crate_changelog() {
+        let mut config = Config::default();
+        config.lint.changelog.paths.insert(
+            String::from("solana-program"),
+            PathBuf::from("docs/program.md"),
+        );
+        let dir = Path::new("sdk/hash");
+        assert_eq!(
+            config.crate_changelog("solana-program", Path::new("sdk/program")),
+            PathBuf::from("docs/program.md")
+        );
+        assert_eq!(
+            config.crate_changelog("solana-hash", dir),
+            PathBuf::from("CHANGELOG.md")
+        );
+        config.release.crate_changelogs = true;
+        assert_eq!(
+            config.crate_changelog("solana-hash", dir),
+            PathBuf::from("sdk/hash/CHANGELOG.md")
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_unknown_keys() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(CONFIG_FILE_NAME);
+        fs::write(&path, "[network]\nproxxy = \"http://proxy:3128\"\n").unwrap();
+        assert!(Config::load_from(&path).is_err());
+    }
+
+    #[test]
+    fn test_load_advisory_ignore_expiry() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join(CONFIG_FILE_NAME);
+        let ignore = |expires: &str| {
+            format!("[[policy.advisory-ignores]]\nid = \"RUSTSEC-2024-0001\"\nreason = \"none\"\nexpires = \"{expires}\"\n")
+        };
+        fs::write(&path, ignore("2026-02-01")).unwrap();
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.policy.advisory_ignores[0].expires, 1_769_904_000);
+        for invalid in [
+            "2026-02-30",
+            "2026-2-1",
+            "next week",
+            "2026-02-01T10:00:00Z",
+        ] {
+            fs::write(&path, ignore(invalid)).unwrap();
+            assert!(Config::load_from(&path).is_err(), "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,137 @@
+//! Failure classes and the process exit codes they map to, so CI pipelines
+//! can branch on the kind of failure instead of grepping logs.
+
+use std::fmt;
+
+/// Exit code of unclassified failures.
+pub const GENERIC_EXIT_CODE: i32 = 1;
+/// Exit code clap uses for an invalid command line.
+pub const USAGE_EXIT_CODE: i32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// `xtask.toml` (or another config file) is unreadable or invalid.
+    Config,
+    /// A check refused to start the operation, nothing was changed.
+    Preflight,
+    /// Some crates were published before the run failed; resume it rather
+    /// than starting over.
+    PartialPublish,
+    /// A request to a registry or another remote service failed.
+    Network,
+    /// A signature, checksum or artifact did not match what was expected.
+    Verification,
+}
+
+impl Failure {
+    pub const ALL: [Failure; 5] = [
+        Failure::Config,
+        Failure::Preflight,
+        Failure::PartialPublish,
+        Failure::Network,
+        Failure::Verification,
+    ];
+
+    pub const fn exit_code(self) -> i32 {
+        match self {
+            Failure::Config => 3,
+            Failure::Preflight => 4,
+            Failure::PartialPublish => 5,
+            Failure::Network => 6,
+            Failure::Verification => 7,
+        }
+    }
+
+    fn describe(self) -> &'static str {
+        match self {
+            Failure::Config => "invalid configuration",
+            Failure::Preflight => "preflight check failed, nothing was changed",
+            Failure::PartialPublish => "publish failed after some crates were published",
+            Failure::Network => "network request failed",
+            Failure::Verification => "signature, checksum or artifact verification failed",
+        }
+    }
+
+    /// An error of this class, displayed as `message`; context added on top
+    /// keeps the class.
+    pub fn error(self, message: impl fmt::Display) -> anyhow::Error {
+        anyhow::Error::new(Classified {
+            failure: self,
+            message: message.to_string(),
+        })
+    }
+
+    /// The class of the outermost classified error of `err`, the caller
+    /// knowing better than the request that failed under it.
+    pub fn of(err: &anyhow::Error) -> Option<Failure> {
+        err.chain()
+            .find_map(|e| e.downcast_ref::<Classified>())
+            .map(|classified| classified.failure)
+    }
+}
+
+#[derive(Debug)]
+struct Classified {
+    failure: Failure,
+    message: String,
+}
+
+impl fmt::Display for Classified {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for Classified {}
+
+pub fn exit_code(err: &anyhow::Error) -> i32 {
+    Failure::of(err).map_or(GENERIC_EXIT_CODE, Failure::exit_code)
+}
+
+/// The exit code table shown by `xtask --help`.
+pub fn exit_codes_help() -> String {
+    let mut help = String::from("Exit codes:\n");
+    help.push_str(&format!("  {GENERIC_EXIT_CODE}  unclassified failure\n"));
+    help.push_str(&format!("  {USAGE_EXIT_CODE}  invalid command line\n"));
+    for failure in Failure::ALL {
+        help.push_str(&format!(
+            "  {}  {}\n",
+            failure.exit_code(),
+            failure.describe()
+        ));
+    }
+    help
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        anyhow::{anyhow, Context},
+        pretty_assertions::assert_eq,
+        std::collections::BTreeSet,
+    };
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let codes: BTreeSet<i32> = Failure::ALL.iter().map(|f| f.exit_code()).collect();
+        assert_eq!(codes.len(), Failure::ALL.len());
+        assert!(!codes.contains(&GENERIC_EXIT_CODE));
+        assert!(!codes.contains(&USAGE_EXIT_CODE));
+    }
+
+    #[test]
+    fn test_exit_code_survives_context() {
+        let err = Err::<(), _>(Failure::Network.error("GET https://index failed: 503"))
+            .context("failed to fetch the index")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "failed to fetch the index");
+        assert_eq!(
+            format!("{err:#}"),
+            "failed to fetch the index: GET https://index failed: 503"
+        );
+        assert_eq!(exit_code(&err), 6);
+
+        assert_eq!(exit_code(&anyhow!("something broke")), GENERIC_EXIT_CODE);
+    }
+}
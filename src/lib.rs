@@ -28,9 +28,25 @@
 //! let new = bump_version(&BumpLevel::Minor, &current).unwrap();
 //! assert_eq!(new, Version::parse("1.3.0").unwrap());
 //! ```
+//!
+//! ## Golden tests
+//!
+//! `tests/golden.rs` runs `bump-version` and `update-crate` against the fixture
+//! workspaces under `tests/golden` and diffs the rewritten manifests against
+//! expected trees. Changes to the manifest editing come with a new case
+//! directory there, see `tests/common/golden.rs` for the layout.
+//!
+//! ## Instrumentation
+//!
+//! Manifest scans, the publish graph build and every crate publish run in
+//! `tracing` spans. Without a `tracing` subscriber installed, events are
+//! forwarded to the `log` crate, which is what the `xtask` binary relies on;
+//! embedders can install a subscriber to get the structured spans instead.
 
 pub mod buildkite;
 pub mod commands;
+pub mod config;
+pub mod failure;
 pub mod utils;
 
 pub use commands::{bump_version, publish, update_crate};
@@ -0,0 +1,137 @@
+use {
+    anyhow::{anyhow, Result},
+    clap::Command,
+    std::{collections::BTreeMap, ffi::OsString},
+};
+
+/// Checks the `[aliases]` of `xtask.toml` against the subcommands of `cmd`
+/// and lists the single-word ones as visible aliases, so they show up in
+/// `--help`.
+pub fn register(mut cmd: Command, aliases: &BTreeMap<String, String>) -> Result<Command> {
+    for (alias, expansion) in aliases.iter() {
+        if cmd.find_subcommand(alias).is_some() {
+            return Err(anyhow!("alias {alias} shadows the {alias} command"));
+        }
+        let mut words = expansion.split_whitespace();
+        let target = words
+            .next()
+            .ok_or_else(|| anyhow!("alias {alias} expands to nothing"))?;
+        if cmd.find_subcommand(target).is_none() {
+            return Err(anyhow!("alias {alias} expands to unknown command {target}"));
+        }
+        if words.next().is_none() {
+            let alias = alias.clone();
+            cmd = cmd.mut_subcommand(target, |sub| sub.visible_alias(alias));
+        }
+    }
+    Ok(cmd)
+}
+
+/// Replaces an alias in the subcommand position of `args` with the words it
+/// stands for, e.g. `bvp = "bump-version patch"` turns `xtask -v bvp` into
+/// `xtask -v bump-version patch`. Like cargo aliases, only the first word
+/// after the global options is expanded, and only once.
+pub fn expand(
+    cmd: &Command,
+    args: Vec<OsString>,
+    aliases: &BTreeMap<String, String>,
+) -> Vec<OsString> {
+    // global options that take a separate value, e.g. `--scope <dir>`
+    let takes_value: Vec<String> = cmd
+        .get_arguments()
+        .filter(|arg| arg.get_action().takes_values())
+        .flat_map(|arg| {
+            arg.get_long()
+                .map(|long| format!("--{long}"))
+                .into_iter()
+                .chain(arg.get_short().map(|short| format!("-{short}")))
+        })
+        .collect();
+
+    let mut position = 1;
+    while let Some(arg) = args.get(position).and_then(|arg| arg.to_str()) {
+        if takes_value.iter().any(|flag| flag == arg) {
+            position = position.saturating_add(2);
+        } else if arg.starts_with('-') && arg != "-" {
+            position = position.saturating_add(1);
+        } else {
+            break;
+        }
+    }
+    let Some(expansion) = args
+        .get(position)
+        .and_then(|arg| arg.to_str())
+        .and_then(|arg| aliases.get(arg))
+    else {
+        return args;
+    };
+
+    let mut expanded = args[..position].to_vec();
+    expanded.extend(expansion.split_whitespace().map(OsString::from));
+    expanded.extend(args[position.saturating_add(1)..].iter().cloned());
+    expanded
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        clap::{Arg, ArgAction},
+        pretty_assertions::assert_eq,
+    };
+
+    fn command() -> Command {
+        Command::new("xtask")
+            .arg(Arg::new("verbose").short('v').action(ArgAction::SetTrue))
+            .arg(Arg::new("scope").long("scope"))
+            .subcommand(Command::new("bump-version"))
+            .subcommand(Command::new("publish"))
+    }
+
+    fn aliases(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(alias, expansion)| (alias.to_string(), expansion.to_string()))
+            .collect()
+    }
+
+    fn args(args: &[&str]) -> Vec<OsString> {
+        args.iter().map(OsString::from).collect()
+    }
+
+    #[test]
+    fn test_expand() {
+        let aliases = aliases(&[("bvp", "bump-version patch"), ("pub", "publish")]);
+        assert_eq!(
+            expand(&command(), args(&["xtask", "bvp", "--dry-run"]), &aliases),
+            args(&["xtask", "bump-version", "patch", "--dry-run"])
+        );
+        assert_eq!(
+            expand(
+                &command(),
+                args(&["xtask", "-v", "--scope", "bvp", "pub"]),
+                &aliases
+            ),
+            args(&["xtask", "-v", "--scope", "bvp", "publish"])
+        );
+        assert_eq!(
+            expand(&command(), args(&["xtask", "publish", "pub"]), &aliases),
+            args(&["xtask", "publish", "pub"])
+        );
+        assert_eq!(
+            expand(&command(), args(&["xtask"]), &aliases),
+            args(&["xtask"])
+        );
+    }
+
+    #[test]
+    fn test_register() {
+        let cmd = register(command(), &aliases(&[("pub", "publish")])).unwrap();
+        let matches = cmd.try_get_matches_from(["xtask", "pub"]).unwrap();
+        assert_eq!(matches.subcommand_name(), Some("publish"));
+
+        assert!(register(command(), &aliases(&[("publish", "bump-version")])).is_err());
+        assert!(register(command(), &aliases(&[("x", "unknown")])).is_err());
+        assert!(register(command(), &aliases(&[("x", "")])).is_err());
+    }
+}
@@ -1,9 +1,239 @@
 use {
-    anyhow::{anyhow, Result},
-    std::fs,
-    toml_edit::Document,
+    crate::{config::NetworkConfig, utils::http::apply_cargo_network_env},
+    anyhow::{anyhow, Context, Result},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+    toml_edit::{Document, DocumentMut, Item},
 };
 
+/// Dependency tables checked by manifest lints, relative to the manifest root.
+pub const DEPENDENCY_TABLES: [&str; 3] = ["dependencies", "dev-dependencies", "build-dependencies"];
+
+/// Where a dependency table sits in a manifest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableScope {
+    /// `[dependencies]`, `[dev-dependencies]` or `[build-dependencies]`.
+    Package,
+    /// `[workspace.dependencies]`.
+    Workspace,
+    /// One of the package tables under `[target.<cfg>]`.
+    Target,
+    /// `[patch.<source>]`.
+    Patch,
+}
+
+/// A dependency table of a manifest.
+#[derive(Debug)]
+pub struct DependencyTable<'a> {
+    /// Dotted name for messages, e.g. `target.cfg(unix).dev-dependencies`.
+    pub name: String,
+    /// One of [`DEPENDENCY_TABLES`], `dependencies` for workspace and patch
+    /// tables.
+    pub kind: &'static str,
+    pub scope: TableScope,
+    pub item: &'a Item,
+}
+
+/// Every dependency table of `doc`: the package's, `[workspace.dependencies]`,
+/// the per-target ones and `[patch.*]`, in that order.
+pub fn dependency_tables(doc: &DocumentMut) -> Vec<DependencyTable<'_>> {
+    let table = |name: String, kind, scope, item| DependencyTable {
+        name,
+        kind,
+        scope,
+        item,
+    };
+    let mut tables = vec![];
+    for kind in DEPENDENCY_TABLES {
+        if let Some(item) = doc.get(kind) {
+            tables.push(table(kind.to_string(), kind, TableScope::Package, item));
+        }
+    }
+    if let Some(item) = doc.get("workspace").and_then(|w| w.get("dependencies")) {
+        tables.push(table(
+            String::from("workspace.dependencies"),
+            "dependencies",
+            TableScope::Workspace,
+            item,
+        ));
+    }
+    if let Some(targets) = doc.get("target").and_then(|t| t.as_table_like()) {
+        for (target, target_item) in targets.iter() {
+            for kind in DEPENDENCY_TABLES {
+                if let Some(item) = target_item.get(kind) {
+                    tables.push(table(
+                        format!("target.{target}.{kind}"),
+                        kind,
+                        TableScope::Target,
+                        item,
+                    ));
+                }
+            }
+        }
+    }
+    if let Some(patches) = doc.get("patch").and_then(|p| p.as_table_like()) {
+        for (source, item) in patches.iter() {
+            tables.push(table(
+                format!("patch.{source}"),
+                "dependencies",
+                TableScope::Patch,
+                item,
+            ));
+        }
+    }
+    tables
+}
+
+/// The dependency tables of `doc` in `scopes`, for rewriting.
+pub fn dependency_tables_mut<'a>(
+    doc: &'a mut DocumentMut,
+    scopes: &[TableScope],
+) -> Vec<&'a mut Item> {
+    let mut tables = vec![];
+    for (key, item) in doc.iter_mut() {
+        let scope = match key.get() {
+            kind if DEPENDENCY_TABLES.contains(&kind) => TableScope::Package,
+            "workspace" => TableScope::Workspace,
+            "target" => TableScope::Target,
+            "patch" => TableScope::Patch,
+            _ => continue,
+        };
+        if !scopes.contains(&scope) {
+            continue;
+        }
+        match scope {
+            TableScope::Package => tables.push(item),
+            TableScope::Workspace => tables.extend(item.get_mut("dependencies")),
+            TableScope::Target => {
+                let Some(targets) = item.as_table_like_mut() else {
+                    continue;
+                };
+                for (_, target) in targets.iter_mut() {
+                    let Some(target) = target.as_table_like_mut() else {
+                        continue;
+                    };
+                    for (kind, item) in target.iter_mut() {
+                        if DEPENDENCY_TABLES.contains(&kind.get()) {
+                            tables.push(item);
+                        }
+                    }
+                }
+            }
+            TableScope::Patch => {
+                if let Some(patches) = item.as_table_like_mut() {
+                    tables.extend(patches.iter_mut().map(|(_, item)| item));
+                }
+            }
+        }
+    }
+    tables
+}
+
+pub fn read_manifest(path: &Path) -> Result<DocumentMut> {
+    let content = fs::read_to_string(path).context(format!("failed to read {}", path.display()))?;
+    content
+        .parse::<DocumentMut>()
+        .context(format!("failed to parse {}", path.display()))
+}
+
+/// Expands `workspace.members` (honoring `workspace.exclude`) of the workspace
+/// whose root manifest lives in `workspace_dir`.
+///
+/// Only `*` and `?` wildcards are supported, which covers every layout we use.
+pub fn expand_workspace_members(workspace_dir: &Path, doc: &DocumentMut) -> Vec<PathBuf> {
+    let excludes: Vec<PathBuf> = workspace_strings(doc, "exclude")
+        .iter()
+        .map(|e| workspace_dir.join(e))
+        .collect();
+
+    let mut members = vec![];
+    for pattern in workspace_strings(doc, "members") {
+        for candidate in expand_member_pattern(workspace_dir, &pattern) {
+            if excludes.iter().any(|e| candidate.starts_with(e)) {
+                continue;
+            }
+            if !members.contains(&candidate) {
+                members.push(candidate);
+            }
+        }
+    }
+    members.sort();
+    members
+}
+
+/// String entries of a `workspace` array such as `members` or `exclude`.
+pub fn workspace_strings(doc: &DocumentMut, key: &str) -> Vec<String> {
+    doc.get("workspace")
+        .and_then(|workspace| workspace.get(key))
+        .and_then(|item| item.as_array())
+        .map(|array| {
+            array
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Directories a single `members` entry refers to. Globs only yield
+/// directories holding a `Cargo.toml`, literal paths are returned as is.
+pub fn expand_member_pattern(workspace_dir: &Path, pattern: &str) -> Vec<PathBuf> {
+    let mut candidates = vec![workspace_dir.to_path_buf()];
+    for segment in Path::new(pattern).components() {
+        let segment = segment.as_os_str().to_string_lossy();
+        let mut next = vec![];
+        for candidate in candidates {
+            if segment.contains(['*', '?']) {
+                let Ok(entries) = fs::read_dir(&candidate) else {
+                    continue;
+                };
+                for entry in entries.filter_map(Result::ok) {
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    if entry.path().is_dir() && wildcard_match(&segment, &name) {
+                        next.push(entry.path());
+                    }
+                }
+            } else {
+                next.push(candidate.join(segment.as_ref()));
+            }
+        }
+        candidates = next;
+    }
+    let is_glob = pattern.contains(['*', '?']);
+    // globs only match directories that are actual crates
+    candidates.retain(|candidate| !is_glob || candidate.join("Cargo.toml").exists());
+    candidates.sort();
+    candidates
+}
+
+/// Matches `name` against a pattern with `*` and `?` wildcards.
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let (mut star, mut star_n) = (None, 0);
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some(p);
+            star_n = n;
+            p += 1;
+        } else if let Some(star_p) = star {
+            p = star_p + 1;
+            star_n += 1;
+            n = star_n;
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
 pub fn get_all_crates() -> Result<Vec<String>> {
     let cargo_tomls = super::fs::find_all_cargo_tomls()?;
     let mut crates = vec![];
@@ -23,8 +253,17 @@ pub fn get_all_crates() -> Result<Vec<String>> {
 }
 
 pub fn get_current_version() -> Result<String> {
-    let git_root = super::git::get_git_root_path()?;
-    let cargo_toml = git_root.join("Cargo.toml");
+    // a scoped subtree with its own workspace carries its own version
+    let scope_root = super::scope::get_scope_root()?;
+    let cargo_toml = match scope_root.join("Cargo.toml") {
+        scoped if scoped.exists() && scoped_workspace_version(&scoped) => scoped,
+        _ => super::git::get_git_root_path()?.join("Cargo.toml"),
+    };
+    workspace_version(&cargo_toml)
+}
+
+/// `[workspace.package] version` of the workspace manifest `cargo_toml`.
+pub fn workspace_version(cargo_toml: &Path) -> Result<String> {
     let content = fs::read_to_string(cargo_toml)?;
     let doc = content.parse::<Document<String>>()?;
     let Some(version) = doc
@@ -38,6 +277,116 @@ pub fn get_current_version() -> Result<String> {
     Ok(version.to_string())
 }
 
+fn scoped_workspace_version(cargo_toml: &Path) -> bool {
+    fs::read_to_string(cargo_toml)
+        .ok()
+        .and_then(|content| content.parse::<Document<String>>().ok())
+        .is_some_and(|doc| {
+            doc.get("workspace")
+                .and_then(|workspace| workspace.get("package"))
+                .and_then(|package| package.get("version"))
+                .is_some()
+        })
+}
+
+/// Reads the toolchain channel pinned by `rust-toolchain.toml` (or the legacy
+/// `rust-toolchain` file) in `dir`.
+pub fn get_toolchain_channel(dir: &Path) -> Result<Option<String>> {
+    let toml_path = dir.join("rust-toolchain.toml");
+    if toml_path.exists() {
+        let content = fs::read_to_string(&toml_path)?;
+        let doc = content.parse::<Document<String>>()?;
+        return Ok(doc
+            .get("toolchain")
+            .and_then(|toolchain| toolchain.get("channel"))
+            .and_then(|channel| channel.as_str())
+            .map(str::to_string));
+    }
+    let legacy_path = dir.join("rust-toolchain");
+    if legacy_path.exists() {
+        let content = fs::read_to_string(&legacy_path)?;
+        // the legacy file is either a bare channel name or the TOML format
+        if let Ok(doc) = content.parse::<Document<String>>() {
+            if let Some(channel) = doc
+                .get("toolchain")
+                .and_then(|toolchain| toolchain.get("channel"))
+                .and_then(|channel| channel.as_str())
+            {
+                return Ok(Some(channel.to_string()));
+            }
+        }
+        return Ok(Some(content.trim().to_string()).filter(|c| !c.is_empty()));
+    }
+    Ok(None)
+}
+
+/// Installs the rustup `target` for the toolchain used in `dir`, unless it
+/// is already there.
+pub fn ensure_target_installed(dir: &Path, target: &str) -> Result<()> {
+    let output = Command::new("rustup")
+        .current_dir(dir)
+        .args(["target", "list", "--installed"])
+        .output()
+        .map_err(|e| anyhow!("failed to run rustup: {e}"))?;
+    if String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .any(|line| line.trim() == target)
+    {
+        return Ok(());
+    }
+    let status = Command::new("rustup")
+        .current_dir(dir)
+        .args(["target", "add", target])
+        .status()
+        .map_err(|e| anyhow!("failed to run rustup: {e}"))?;
+    if !status.success() {
+        return Err(anyhow!("rustup target add {target} failed"));
+    }
+    Ok(())
+}
+
+/// Target triple of the active toolchain's host.
+pub fn host_target() -> Result<String> {
+    let output = Command::new("rustc")
+        .arg("-vV")
+        .output()
+        .map_err(|e| anyhow!("failed to run rustc: {e}"))?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("rustc -vV did not report the host target"))
+}
+
+/// Builds the library of `package` for `target`, returning the first
+/// compiler errors on failure.
+pub fn build_lib_for_target(
+    manifest_path: &str,
+    network: &NetworkConfig,
+    package: &str,
+    target: &str,
+    extra_args: &[&str],
+) -> Result<()> {
+    let mut cmd = Command::new("cargo");
+    cmd.args(["build", "--manifest-path", manifest_path, "--lib"])
+        .args(["--package", package, "--target", target])
+        .args(extra_args);
+    apply_cargo_network_env(&mut cmd, network);
+    let output = cmd
+        .output()
+        .map_err(|e| anyhow!("failed to run cargo: {e}"))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let errors: Vec<_> = stderr
+            .lines()
+            .skip_while(|line| !line.starts_with("error"))
+            .take(10)
+            .collect();
+        return Err(anyhow!("{}", errors.join("\n")));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use {super::*, pretty_assertions::assert_eq, serial_test::serial, std::collections::HashSet};
@@ -87,4 +436,65 @@ mod tests {
             assert_eq!(version, "3.1.0");
         }
     }
+
+    #[test]
+    fn test_expand_workspace_members() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let root = root_dir.path();
+        for dir in [
+            "crates/foo",
+            "crates/bar",
+            "crates/skip",
+            "crates/empty",
+            "cli",
+        ] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            if dir != "crates/empty" {
+                std::fs::write(root.join(dir).join("Cargo.toml"), "").unwrap();
+            }
+        }
+        let doc = "[workspace]\nmembers = [\"crates/*\", \"cli\", \"missing\"]\nexclude = [\"crates/skip\"]\n"
+            .parse::<DocumentMut>()
+            .unwrap();
+        assert_eq!(
+            expand_workspace_members(root, &doc),
+            vec![
+                root.join("cli"),
+                root.join("crates/bar"),
+                root.join("crates/foo"),
+                root.join("missing"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("*", "foo"));
+        assert!(wildcard_match("solana-*", "solana-sdk"));
+        assert!(wildcard_match("f?o", "foo"));
+        assert!(!wildcard_match("solana-*", "agave-sdk"));
+    }
+
+    #[test]
+    fn test_get_toolchain_channel() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let root_dir_path = root_dir.path();
+        assert_eq!(get_toolchain_channel(root_dir_path).unwrap(), None);
+
+        std::fs::write(root_dir_path.join("rust-toolchain"), "1.84.1\n").unwrap();
+        assert_eq!(
+            get_toolchain_channel(root_dir_path).unwrap(),
+            Some(String::from("1.84.1"))
+        );
+
+        std::fs::write(
+            root_dir_path.join("rust-toolchain.toml"),
+            "[toolchain]\nchannel = \"1.86.0\"\ncomponents = [\"clippy\"]\n",
+        )
+        .unwrap();
+        assert_eq!(
+            get_toolchain_channel(root_dir_path).unwrap(),
+            Some(String::from("1.86.0"))
+        );
+    }
 }
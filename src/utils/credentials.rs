@@ -0,0 +1,256 @@
+use {
+    crate::config::{Config, CredentialSource},
+    anyhow::{anyhow, Context, Result},
+    log::warn,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        env, fs,
+        process::{Command, Stdio},
+        sync::Mutex,
+    },
+};
+
+/// Tokens read so far by registry, so a password manager or keyring prompt
+/// comes up once per run rather than once per crate.
+static TOKENS: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+
+/// Where the token of `registry` comes from, `None` leaving it to cargo.
+pub fn source(registry: &str, config: &Config) -> Option<CredentialSource> {
+    if let Some(source) = config.publish.credentials.get(registry) {
+        return Some(source.clone());
+    }
+    config
+        .publish
+        .registries
+        .get(registry)
+        .and_then(|registry| registry.token_env.clone())
+        .map(CredentialSource::Env)
+}
+
+/// Variable a parent process, e.g. the host side of a hermetic release,
+/// passes the token of `registry` in once it resolved it, since the
+/// keyring or password manager it came from is out of reach.
+pub fn forwarded_env(registry: &str) -> String {
+    format!(
+        "XTASK_REGISTRY_TOKEN_{}",
+        registry.to_uppercase().replace('-', "_")
+    )
+}
+
+/// The tokens of every registry `xtask.toml` configures a source for, by
+/// the variable [`token`] reads them back from in a child process. Tokens
+/// that cannot be read are left out, the child fails if it needs them.
+pub fn forwarded(config: &Config) -> Vec<(String, String)> {
+    let registries: BTreeSet<&String> = config
+        .publish
+        .credentials
+        .keys()
+        .chain(config.publish.registries.keys())
+        .collect();
+    let mut tokens = vec![];
+    for registry in registries {
+        match token(registry, config) {
+            Ok(Some(token)) => tokens.push((forwarded_env(registry), token)),
+            Ok(None) => {}
+            Err(e) => warn!("⚠️ not forwarding a token for {registry}: {e:#}"),
+        }
+    }
+    tokens
+}
+
+/// The token of `registry` (`crates-io` for crates.io), `None` when
+/// `xtask.toml` configures no source for it.
+pub fn token(registry: &str, config: &Config) -> Result<Option<String>> {
+    if let Ok(token) = env::var(forwarded_env(registry)) {
+        return Ok(Some(token));
+    }
+    let Some(source) = source(registry, config) else {
+        return Ok(None);
+    };
+    let mut tokens = TOKENS
+        .lock()
+        .map_err(|_| anyhow!("credential cache lock poisoned"))?;
+    if let Some(token) = tokens.get(registry) {
+        return Ok(Some(token.clone()));
+    }
+    let token = read(&source, config)
+        .context(format!("failed to read the token of registry {registry}"))?;
+    tokens.insert(registry.to_string(), token.clone());
+    Ok(Some(token))
+}
+
+fn read(source: &CredentialSource, config: &Config) -> Result<String> {
+    let token = match source {
+        CredentialSource::Env(name) => env::var(name).map_err(|_| anyhow!("{name} is not set"))?,
+        CredentialSource::File(path) => {
+            let path = config.resolve(path);
+            fs::read_to_string(&path).context(format!("failed to read {}", path.display()))?
+        }
+        CredentialSource::Keyring { service, account } => run(&keyring_command(service, account))?,
+        CredentialSource::Command(command) => run(command)?,
+    };
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(anyhow!("the token is empty"));
+    }
+    Ok(token.to_string())
+}
+
+fn keyring_command(service: &str, account: &str) -> Vec<String> {
+    let command: &[&str] = if cfg!(target_os = "macos") {
+        &[
+            "security",
+            "find-generic-password",
+            "-s",
+            service,
+            "-a",
+            account,
+            "-w",
+        ]
+    } else {
+        &[
+            "secret-tool",
+            "lookup",
+            "service",
+            service,
+            "account",
+            account,
+        ]
+    };
+    command.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// Runs `command` for its stdout, leaving stdin and stderr to the terminal
+/// so it can ask for a password.
+fn run(command: &[String]) -> Result<String> {
+    let (program, arguments) = command
+        .split_first()
+        .ok_or_else(|| anyhow!("the credential command is empty"))?;
+    let output = Command::new(program)
+        .args(arguments)
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::inherit())
+        .output()
+        .map_err(|e| anyhow!("failed to run {program}: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!("{program} failed with {}", output.status));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::config::RegistryConfig, pretty_assertions::assert_eq};
+
+    #[test]
+    #[cfg(unix)] // credential commands run `echo` and `true`
+    fn test_token() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("token"), "cio-file\n").unwrap();
+        let mut config = Config {
+            root: dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        config.publish.registries.insert(
+            String::from("legacy"),
+            RegistryConfig {
+                index: None,
+                token_env: Some(String::from("XTASK_TEST_UNSET_TOKEN")),
+            },
+        );
+        for (registry, source) in [
+            ("from-file", CredentialSource::File("token".into())),
+            (
+                "from-command",
+                CredentialSource::Command(vec![String::from("echo"), String::from("cio-command")]),
+            ),
+            (
+                "empty",
+                CredentialSource::Command(vec![String::from("true")]),
+            ),
+        ] {
+            config
+                .publish
+                .credentials
+                .insert(registry.to_string(), source);
+        }
+
+        assert_eq!(
+            token("from-file", &config).unwrap().as_deref(),
+            Some("cio-file")
+        );
+        assert_eq!(
+            token("from-command", &config).unwrap().as_deref(),
+            Some("cio-command")
+        );
+        assert!(token("empty", &config).is_err());
+        assert!(token("legacy", &config).is_err());
+        assert_eq!(token("crates-io", &config).unwrap(), None);
+    }
+
+    #[test]
+    fn test_parse_sources() {
+        let config: Config = toml_edit::de::from_str(
+            r#"[publish.credentials]
+crates-io = { command = ["op", "read", "op://release/crates-io/token"] }
+internal = { env = "INTERNAL_TOKEN" }
+mirror = { keyring = { service = "cargo", account = "mirror" } }
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            config.publish.credentials["crates-io"],
+            CredentialSource::Command(
+                ["op", "read", "op://release/crates-io/token"]
+                    .map(String::from)
+                    .to_vec()
+            )
+        );
+        assert_eq!(
+            config.publish.credentials["internal"],
+            CredentialSource::Env(String::from("INTERNAL_TOKEN"))
+        );
+        assert_eq!(
+            config.publish.credentials["mirror"],
+            CredentialSource::Keyring {
+                service: String::from("cargo"),
+                account: String::from("mirror"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_forwarded() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("token"), "cio-forwarded\n").unwrap();
+        let mut config = Config {
+            root: dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        config.publish.credentials.insert(
+            String::from("xtask-test-forwarded"),
+            CredentialSource::File("token".into()),
+        );
+        config.publish.credentials.insert(
+            String::from("xtask-test-missing"),
+            CredentialSource::File("missing".into()),
+        );
+        let tokens = forwarded(&config);
+        assert_eq!(
+            tokens,
+            [(
+                String::from("XTASK_REGISTRY_TOKEN_XTASK_TEST_FORWARDED"),
+                String::from("cio-forwarded")
+            )]
+        );
+
+        // the child reads it back without the source
+        env::set_var(&tokens[0].0, "cio-forwarded");
+        assert_eq!(
+            token("xtask-test-forwarded", &Config::default())
+                .unwrap()
+                .as_deref(),
+            Some("cio-forwarded")
+        );
+    }
+}
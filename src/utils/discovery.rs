@@ -0,0 +1,119 @@
+use {
+    super::{
+        git::{git_root_of, submodule_paths},
+        portable::canonicalize,
+    },
+    crate::config::{Config, CONFIG_FILE_NAME},
+    anyhow::Result,
+    std::{
+        path::{Path, PathBuf},
+        sync::atomic::{AtomicBool, Ordering},
+    },
+};
+
+/// Whether discovery recurses into git submodules, set by
+/// `--include-submodules`.
+static INCLUDE_SUBMODULES: AtomicBool = AtomicBool::new(false);
+
+/// Makes discovery recurse into git submodules for the rest of the process;
+/// by default their manifests belong to another repository and are skipped.
+pub fn include_submodules() {
+    INCLUDE_SUBMODULES.store(true, Ordering::Relaxed);
+}
+
+/// The `[discovery]` paths of `xtask.toml` and the git submodules, applied by
+/// every manifest and lockfile search so commands agree on which crates
+/// exist.
+#[derive(Debug, Default, Clone)]
+pub struct Discovery {
+    root: PathBuf,
+    exclude: Vec<PathBuf>,
+    include: Vec<PathBuf>,
+    submodules: Vec<PathBuf>,
+}
+
+impl Discovery {
+    pub fn load() -> Result<Self> {
+        Ok(Self::from_config(&Config::load()?))
+    }
+
+    /// Discovery of the tree at `dir`, by the `xtask.toml` at the root of its
+    /// checkout (a worktree has its own) or of `dir` itself outside git.
+    pub fn for_tree(dir: &Path) -> Result<Self> {
+        let root = git_root_of(dir).unwrap_or_else(|_| dir.to_path_buf());
+        Ok(Self::from_config(&Config::load_from(
+            &root.join(CONFIG_FILE_NAME),
+        )?))
+    }
+
+    pub fn from_config(config: &Config) -> Self {
+        let root = if config.root.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            config.root.as_path()
+        };
+        let submodules = if INCLUDE_SUBMODULES.load(Ordering::Relaxed) {
+            vec![]
+        } else {
+            // outside a git repository there are no submodules to skip
+            submodule_paths(root).unwrap_or_default()
+        };
+        Discovery {
+            root: canonicalize(root).unwrap_or_else(|_| root.to_path_buf()),
+            exclude: config.discovery.exclude.clone(),
+            include: config.discovery.include.clone(),
+            submodules,
+        }
+    }
+
+    /// Whether the search skips `path`, an absolute path: it is under an
+    /// excluded tree or a submodule, and neither under nor above an included
+    /// tree.
+    pub fn skips(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        self.exclude
+            .iter()
+            .chain(self.submodules.iter())
+            .any(|e| relative.starts_with(e))
+            && !self
+                .include
+                .iter()
+                .any(|i| relative.starts_with(i) || i.starts_with(relative))
+    }
+
+    /// The included trees inside `dir`, an absolute path, which a search
+    /// honouring `.gitignore` must visit on its own.
+    pub fn included_in(&self, dir: &Path) -> Vec<PathBuf> {
+        self.include
+            .iter()
+            .map(|i| self.root.join(i))
+            .filter(|i| i.starts_with(dir) && i.is_dir())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_skips() {
+        let discovery = Discovery {
+            root: PathBuf::from("/repo"),
+            exclude: vec![PathBuf::from("vendor"), PathBuf::from("tests")],
+            include: vec![PathBuf::from("vendor/patched")],
+            submodules: vec![PathBuf::from("external/agave")],
+        };
+        assert!(discovery.skips(Path::new("/repo/external/agave/Cargo.toml")));
+        assert!(!discovery.skips(Path::new("/repo/external/Cargo.toml")));
+        assert!(discovery.skips(Path::new("/repo/tests/fixture/Cargo.toml")));
+        assert!(discovery.skips(Path::new("/repo/vendor/serde")));
+        assert!(!discovery.skips(Path::new("/repo/vendor")));
+        assert!(!discovery.skips(Path::new("/repo/vendor/patched/Cargo.toml")));
+        assert!(!discovery.skips(Path::new("/repo/testsuite/Cargo.toml")));
+        assert!(!discovery.skips(Path::new("/elsewhere/tests/Cargo.toml")));
+        assert!(!Discovery::default().skips(Path::new("/repo/Cargo.toml")));
+    }
+}
@@ -1,4 +1,24 @@
-use {anyhow::Result, std::process::Command};
+use {
+    anyhow::{anyhow, Result},
+    log::warn,
+    std::{path::PathBuf, process::Command, thread, time::Duration},
+};
+
+/// Registry errors worth retrying, matched against `docker push` stderr.
+const TRANSIENT_PUSH_ERRORS: &[&str] = &[
+    "timeout",
+    "timed out",
+    "connection reset",
+    "connection refused",
+    "tls handshake",
+    "unexpected eof",
+    "500 internal server error",
+    "502 bad gateway",
+    "503 service unavailable",
+    "504 gateway timeout",
+    "429 too many requests",
+    "toomanyrequests",
+];
 
 pub fn check_docker_available() -> Result<()> {
     let output = Command::new("docker")
@@ -14,3 +34,260 @@ pub fn check_docker_available() -> Result<()> {
     }
     Ok(())
 }
+
+/// Builder for a `docker run --rm` invocation.
+#[derive(Debug, Default)]
+pub struct DockerRun {
+    pub image: String,
+    /// `(host, container)` bind mounts.
+    pub mounts: Vec<(PathBuf, PathBuf)>,
+    pub workdir: Option<PathBuf>,
+    /// Variables forwarded by name only, so secret values never show up in
+    /// the process list or logs.
+    pub pass_env: Vec<String>,
+    /// Variables set on the docker process and forwarded by name, for
+    /// secrets resolved on the host.
+    pub secret_env: Vec<(String, String)>,
+    pub env: Vec<(String, String)>,
+    pub user: Option<String>,
+    pub extra_args: Vec<String>,
+    pub command: Vec<String>,
+}
+
+impl DockerRun {
+    pub fn new(image: impl Into<String>) -> Self {
+        DockerRun {
+            image: image.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn to_command(&self) -> Command {
+        let mut cmd = Command::new("docker");
+        cmd.args(["run", "--rm"]);
+        for (host, container) in self.mounts.iter() {
+            cmd.arg("--volume")
+                .arg(format!("{}:{}", host.display(), container.display()));
+        }
+        if let Some(workdir) = &self.workdir {
+            cmd.arg("--workdir").arg(workdir);
+        }
+        for name in self.pass_env.iter() {
+            cmd.args(["--env", name]);
+        }
+        for (name, value) in self.secret_env.iter() {
+            cmd.env(name, value).args(["--env", name]);
+        }
+        for (name, value) in self.env.iter() {
+            cmd.args(["--env", &format!("{name}={value}")]);
+        }
+        if let Some(user) = &self.user {
+            cmd.args(["--user", user]);
+        }
+        cmd.args(&self.extra_args);
+        cmd.arg(&self.image);
+        cmd.args(&self.command);
+        cmd
+    }
+}
+
+/// Returns `uid:gid` of the current user so files written into bind mounts
+/// keep their ownership. `None` on Windows, where Docker Desktop maps
+/// ownership itself.
+#[cfg(unix)]
+pub fn current_user_spec() -> Option<String> {
+    let id = |flag: &str| -> Option<String> {
+        let output = Command::new("id").arg(flag).output().ok()?;
+        output
+            .status
+            .success()
+            .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+    };
+    Some(format!("{}:{}", id("-u")?, id("-g")?))
+}
+
+#[cfg(not(unix))]
+pub fn current_user_spec() -> Option<String> {
+    None
+}
+
+/// Fails unless `image` is pinned by digest (`name@sha256:...`).
+pub fn ensure_pinned_image(image: &str) -> Result<()> {
+    match image.split_once("@sha256:") {
+        Some((name, digest))
+            if !name.is_empty()
+                && digest.len() == 64
+                && digest.chars().all(|c| c.is_ascii_hexdigit()) =>
+        {
+            Ok(())
+        }
+        _ => Err(anyhow!(
+            "docker image {image} must be pinned by digest (name@sha256:<64 hex chars>)"
+        )),
+    }
+}
+
+pub fn run_checked(cmd: &mut Command, what: &str) -> Result<()> {
+    let status = cmd
+        .status()
+        .map_err(|e| anyhow!("failed to run {what}: {e}"))?;
+    if !status.success() {
+        return Err(anyhow!("{what} failed with {status}"));
+    }
+    Ok(())
+}
+
+/// Pushes `reference` (`name:tag`), retrying transient registry failures with
+/// exponential backoff, and returns the pushed manifest digest (`sha256:...`).
+pub fn push_with_retry(
+    reference: &str,
+    attempts: u32,
+    initial_backoff: Duration,
+) -> Result<String> {
+    let mut backoff = initial_backoff;
+    let mut attempt = 1;
+    loop {
+        let output = Command::new("docker")
+            .args(["push", reference])
+            .output()
+            .map_err(|e| anyhow!("failed to run docker push: {e}"))?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if output.status.success() {
+            return match parse_push_digest(&stdout) {
+                Some(digest) => Ok(digest),
+                None => inspect_repo_digest(reference),
+            };
+        }
+        if attempt >= attempts || !is_transient_push_error(&stderr) {
+            return Err(anyhow!(
+                "docker push {reference} failed after {attempt} attempt(s): {}",
+                stderr.trim()
+            ));
+        }
+        warn!(
+            "docker push {reference} failed (attempt {attempt}/{attempts}), retrying in {backoff:?}: {}",
+            stderr.trim()
+        );
+        thread::sleep(backoff);
+        backoff = backoff.saturating_mul(2);
+        attempt = attempt.saturating_add(1);
+    }
+}
+
+pub fn is_transient_push_error(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    TRANSIENT_PUSH_ERRORS
+        .iter()
+        .any(|pattern| stderr.contains(pattern))
+}
+
+/// Extracts the digest from the final `<tag>: digest: sha256:... size: N` line.
+pub fn parse_push_digest(stdout: &str) -> Option<String> {
+    stdout.lines().rev().find_map(|line| {
+        let (_, rest) = line.split_once("digest: ")?;
+        let digest = rest.split_whitespace().next()?;
+        digest.starts_with("sha256:").then(|| digest.to_string())
+    })
+}
+
+fn inspect_repo_digest(reference: &str) -> Result<String> {
+    let output = Command::new("docker")
+        .args(["inspect", "--format", "{{json .RepoDigests}}", reference])
+        .output()
+        .map_err(|e| anyhow!("failed to run docker inspect: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "docker inspect {reference} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let repo_digests: Vec<String> = serde_json::from_slice(&output.stdout)?;
+    let name = reference
+        .rsplit_once(':')
+        .map_or(reference, |(name, _)| name);
+    repo_digests
+        .iter()
+        .find_map(|d| d.strip_prefix(&format!("{name}@")).map(str::to_string))
+        .ok_or_else(|| anyhow!("no digest recorded for {reference}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_docker_run_command() {
+        let run = DockerRun {
+            mounts: vec![(PathBuf::from("/src"), PathBuf::from("/workspace"))],
+            workdir: Some(PathBuf::from("/workspace")),
+            pass_env: vec![String::from("CARGO_REGISTRY_TOKEN")],
+            secret_env: vec![(String::from("XTASK_REGISTRY_TOKEN_X"), String::from("cio"))],
+            env: vec![(String::from("XTASK_HERMETIC"), String::from("1"))],
+            user: Some(String::from("1000:1000")),
+            command: vec![String::from("xtask"), String::from("info")],
+            ..DockerRun::new("rust:1")
+        };
+        let cmd = run.to_command();
+        let args: Vec<_> = cmd.get_args().map(|a| a.to_string_lossy()).collect();
+        assert_eq!(
+            args,
+            [
+                "run",
+                "--rm",
+                "--volume",
+                "/src:/workspace",
+                "--workdir",
+                "/workspace",
+                "--env",
+                "CARGO_REGISTRY_TOKEN",
+                "--env",
+                "XTASK_REGISTRY_TOKEN_X",
+                "--env",
+                "XTASK_HERMETIC=1",
+                "--user",
+                "1000:1000",
+                "rust:1",
+                "xtask",
+                "info"
+            ]
+        );
+        // the secret value is only in the environment of docker
+        assert!(
+            cmd.get_envs()
+                .any(|(name, value)| name == "XTASK_REGISTRY_TOKEN_X"
+                    && value == Some("cio".as_ref()))
+        );
+    }
+
+    #[test]
+    fn test_parse_push_digest() {
+        let stdout = "The push refers to repository [docker.io/anzaxyz/agave]\n\
+            5f70bf18a086: Pushed\n\
+            2.2.0: digest: sha256:0123abcd size: 1570\n";
+        assert_eq!(
+            parse_push_digest(stdout),
+            Some(String::from("sha256:0123abcd"))
+        );
+        assert_eq!(parse_push_digest("5f70bf18a086: Pushed\n"), None);
+    }
+
+    #[test]
+    fn test_is_transient_push_error() {
+        assert!(is_transient_push_error(
+            "received unexpected HTTP status: 503 Service Unavailable"
+        ));
+        assert!(is_transient_push_error("net/http: TLS handshake timeout"));
+        assert!(!is_transient_push_error(
+            "denied: requested access to the resource is denied"
+        ));
+    }
+
+    #[test]
+    fn test_ensure_pinned_image() {
+        let digest = "a".repeat(64);
+        assert!(ensure_pinned_image(&format!("rust@sha256:{digest}")).is_ok());
+        assert!(ensure_pinned_image("rust:1.86").is_err());
+        assert!(ensure_pinned_image("rust@sha256:abc").is_err());
+    }
+}
@@ -1,19 +1,38 @@
 use {
-    anyhow::Result,
+    super::{discovery::Discovery, portable::canonicalize},
+    anyhow::{Context, Result},
     ignore::WalkBuilder,
-    std::path::{Path, PathBuf},
+    sha2::{Digest, Sha256},
+    std::{
+        fs::File,
+        io::Read,
+        path::{Path, PathBuf},
+    },
+    tracing::{debug, debug_span},
     walkdir::WalkDir,
 };
 
+/// Files named `filename` under `path`, honouring `.gitignore` and the
+/// `[discovery]` paths of `discovery`, usually [`Discovery::for_tree`] of the
+/// walked tree.
 pub fn recursive_find_files(
     path: &Path,
     filename: &str,
+    discovery: &Discovery,
     filter_fn: impl Fn(&Path) -> bool,
 ) -> Result<Vec<PathBuf>> {
+    let _span = debug_span!("manifest_scan", root = %path.display(), filename).entered();
+    // matching is done on absolute paths, results keep the form of `path`
+    let root = path.to_path_buf();
+    let base = canonicalize(path).unwrap_or_else(|_| root.clone());
     let mut results = vec![];
     for result in WalkBuilder::new(path)
         .hidden(false)
         .git_ignore(true)
+        .filter_entry({
+            let (discovery, root, base) = (discovery.clone(), root.clone(), base.clone());
+            move |entry| !discovery.skips(&rebase(entry.path(), &root, &base))
+        })
         .build()
         .filter_map(Result::ok)
     {
@@ -29,27 +48,57 @@ pub fn recursive_find_files(
         }
         results.push(path.to_path_buf());
     }
+    // included trees may be gitignored, walk them without the ignore rules
+    for included in discovery.included_in(&base) {
+        for entry in WalkDir::new(&included)
+            .into_iter()
+            .filter_entry(|entry| entry.file_name() != ".git")
+            .filter_map(Result::ok)
+            .filter(|e| e.file_name() == filename)
+        {
+            let found = rebase(entry.path(), &base, &root);
+            if filter_fn(&found) && !results.contains(&found) {
+                results.push(found);
+            }
+        }
+    }
+    debug!("found {} {filename} file(s)", results.len());
     Ok(results)
 }
 
+/// `path` moved from under `from` to under `to`, unchanged when it is not
+/// under `from`.
+fn rebase(path: &Path, from: &Path, to: &Path) -> PathBuf {
+    match path.strip_prefix(from) {
+        Ok(relative) => to.join(relative),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+/// Files named `filename` under the scope root, skipping `target`, `.git`
+/// and the paths `[discovery]` of `xtask.toml` excludes.
 pub fn find_files_by_name(filename: &str) -> Result<Vec<PathBuf>> {
-    let git_root = super::git::get_git_root_path()?;
+    let root = super::scope::get_scope_root()?;
+    let _span = debug_span!("manifest_scan", root = %root.display(), filename).entered();
+    let discovery = Discovery::for_tree(&root)?;
+    let base = canonicalize(&root).unwrap_or_else(|_| root.clone());
     let mut results = vec![];
 
-    for entry in WalkDir::new(git_root)
+    for entry in WalkDir::new(&root)
         .into_iter()
         .filter_entry(|entry| {
             !entry
                 .path()
                 .components()
                 .any(|c| c.as_os_str() == "target" || c.as_os_str() == ".git")
+                && !discovery.skips(&rebase(entry.path(), &root, &base))
         })
         .filter_map(Result::ok)
         .filter(|e| e.file_name() == filename)
     {
         results.push(entry.path().to_path_buf());
     }
-
+    debug!("found {} {filename} file(s)", results.len());
     Ok(results)
 }
 
@@ -61,10 +110,48 @@ pub fn find_all_cargo_locks() -> Result<Vec<PathBuf>> {
     find_files_by_name("Cargo.lock")
 }
 
+/// Returns the hex SHA-256 of a file.
+pub fn sha256_file(path: &Path) -> Result<String> {
+    let mut file = File::open(path).context(format!("failed to open {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buffer)
+            .context(format!("failed to read {}", path.display()))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Returns the hex SHA-256 of `bytes`.
+pub fn sha256_bytes(bytes: &[u8]) -> String {
+    hex(&Sha256::digest(bytes))
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use {super::*, pretty_assertions::assert_eq, serial_test::serial, std::collections::HashSet};
 
+    #[test]
+    fn test_sha256_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("hello.txt");
+        std::fs::write(&path, "hello\n").unwrap();
+        assert_eq!(
+            sha256_file(&path).unwrap(),
+            "5891b5b522d5df086d0ff0b110fbd9d21bb4fc7163af34d08286a2e846f6be03"
+        );
+        assert_eq!(sha256_bytes(b"hello\n"), sha256_file(&path).unwrap());
+    }
+
     #[test]
     #[serial]
     fn test_find_cargo_files() {
@@ -134,4 +221,70 @@ mod tests {
             assert_eq!(expected_files, actual_files);
         }
     }
+
+    #[test]
+    #[serial]
+    fn test_discovery_config() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let root = std::fs::canonicalize(root_dir.path()).unwrap();
+        std::env::set_current_dir(&root).unwrap();
+        std::process::Command::new("git")
+            .args(["init"])
+            .output()
+            .unwrap();
+        std::fs::write(
+            root.join("xtask.toml"),
+            "[discovery]\nexclude = [\"vendor\"]\ninclude = [\"vendor/patched\", \"generated\"]\n",
+        )
+        .unwrap();
+        for dir in ["", "vendor/serde", "vendor/patched", "generated", "ignored"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::write(root.join(dir).join("Cargo.toml"), "").unwrap();
+        }
+        std::fs::write(root.join(".gitignore"), "generated/\nignored/\n").unwrap();
+
+        let discovery = Discovery::for_tree(&root).unwrap();
+        let mut files = recursive_find_files(&root, "Cargo.toml", &discovery, |_| true).unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                root.join("Cargo.toml"),
+                root.join("generated/Cargo.toml"),
+                root.join("vendor/patched/Cargo.toml"),
+            ]
+        );
+
+        let mut files = find_all_cargo_tomls().unwrap();
+        files.sort();
+        assert_eq!(
+            files,
+            vec![
+                root.join("Cargo.toml"),
+                root.join("generated/Cargo.toml"),
+                root.join("ignored/Cargo.toml"),
+                root.join("vendor/patched/Cargo.toml"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_discovery_of_a_tree_outside_git() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let root = std::fs::canonicalize(root_dir.path()).unwrap();
+        std::fs::write(
+            root.join("xtask.toml"),
+            "[discovery]\nexclude = [\"vendor\"]\n",
+        )
+        .unwrap();
+        for dir in ["", "vendor/serde"] {
+            std::fs::create_dir_all(root.join(dir)).unwrap();
+            std::fs::write(root.join(dir).join("Cargo.toml"), "").unwrap();
+        }
+        let discovery = Discovery::for_tree(&root).unwrap();
+        assert_eq!(
+            recursive_find_files(&root, "Cargo.toml", &discovery, |_| true).unwrap(),
+            vec![root.join("Cargo.toml")]
+        );
+    }
 }
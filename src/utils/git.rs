@@ -1,21 +1,296 @@
 use {
-    anyhow::{anyhow, Result},
-    std::{path::PathBuf, process::Command},
+    super::portable::{git_path, slash_path},
+    anyhow::{anyhow, Context, Result},
+    log::{debug, warn},
+    scopeguard::guard,
+    std::{
+        fs::{self, File, OpenOptions},
+        path::{Path, PathBuf},
+        process::Command,
+        sync::atomic::{AtomicUsize, Ordering},
+    },
 };
 
+/// Directory under the git dir holding xtask's temporary worktrees, named
+/// `<pid>-<n>`. The owning process holds a lock on `<pid>-<n>.lock` next to
+/// each, so worktrees whose lock can be taken were left behind and are removed.
+const WORKTREES_DIR: &str = "xtask-worktrees";
+
+static WORKTREE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
 pub fn get_git_root_path() -> Result<PathBuf> {
     let output = Command::new("git")
         .args(["rev-parse", "--show-toplevel"])
         .output()
         .map_err(|e| anyhow!("failed to get git root path, error: {e}"))?;
-    let root = String::from_utf8_lossy(&output.stdout).trim().to_string();
-    Ok(PathBuf::from(root))
+    Ok(git_path(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Top level of the repository or worktree `dir` belongs to.
+pub fn git_root_of(dir: &Path) -> Result<PathBuf> {
+    Ok(git_path(&git_in(dir, &["rev-parse", "--show-toplevel"])?))
+}
+
+pub fn get_head_commit(dir: &Path) -> Result<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .map_err(|e| anyhow!("failed to get HEAD commit, error: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "failed to get HEAD commit: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Whether the working tree has uncommitted or untracked changes.
+pub fn is_dirty(dir: &Path) -> Result<bool> {
+    let output = Command::new("git")
+        .args(["status", "--porcelain"])
+        .current_dir(dir)
+        .output()
+        .map_err(|e| anyhow!("failed to get git status, error: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "failed to get git status: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(!output.stdout.is_empty())
+}
+
+/// `git status --porcelain` lines, empty for a clean tree. Changes under
+/// `excluded`, e.g. xtask's own state files, are left out; excluded paths
+/// outside the repository of `dir` are ignored.
+pub fn status_lines(dir: &Path, excluded: &[PathBuf]) -> Result<Vec<String>> {
+    let root = git_root_of(dir)?;
+    let pathspecs: Vec<String> = excluded
+        .iter()
+        .filter_map(|path| path.strip_prefix(&root).ok())
+        .filter(|path| !path.as_os_str().is_empty())
+        .map(|path| format!(":(top,exclude){}", slash_path(path)))
+        .collect();
+    let mut args = vec!["status", "--porcelain", "--untracked-files=all", "--", ":/"];
+    args.extend(pathspecs.iter().map(String::as_str));
+    let status = git_in(dir, &args)?;
+    Ok(status.lines().map(str::to_string).collect())
+}
+
+/// The checked out branch, `None` on a detached HEAD.
+pub fn current_branch(dir: &Path) -> Result<Option<String>> {
+    Ok(git_in(dir, &["symbolic-ref", "--quiet", "--short", "HEAD"]).ok())
+}
+
+/// Local and remote branches containing HEAD, remote ones without their
+/// remote prefix.
+pub fn branches_containing_head(dir: &Path) -> Result<Vec<String>> {
+    let branches = git_in(
+        dir,
+        &[
+            "branch",
+            "--all",
+            "--contains",
+            "HEAD",
+            "--format=%(refname)",
+        ],
+    )?;
+    let mut branches: Vec<String> = branches
+        .lines()
+        .filter_map(|refname| {
+            if let Some(branch) = refname.strip_prefix("refs/heads/") {
+                return Some(branch.to_string());
+            }
+            let remote_branch = refname.strip_prefix("refs/remotes/")?;
+            let (_, branch) = remote_branch.split_once('/')?;
+            (branch != "HEAD").then(|| branch.to_string())
+        })
+        .collect();
+    branches.sort();
+    branches.dedup();
+    Ok(branches)
+}
+
+/// The commit `tag` points at, `None` when the tag does not exist.
+pub fn tag_commit(dir: &Path, tag: &str) -> Result<Option<String>> {
+    Ok(git_in(
+        dir,
+        &[
+            "rev-parse",
+            "--quiet",
+            "--verify",
+            &format!("refs/tags/{tag}^{{commit}}"),
+        ],
+    )
+    .ok())
+}
+
+/// Returns the most recent tag reachable from HEAD, if any.
+pub fn get_last_tag(dir: &Path) -> Result<Option<String>> {
+    let output = Command::new("git")
+        .args(["describe", "--tags", "--abbrev=0"])
+        .current_dir(dir)
+        .output()
+        .map_err(|e| anyhow!("failed to describe HEAD, error: {e}"))?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(Some(
+        String::from_utf8_lossy(&output.stdout).trim().to_string(),
+    ))
+}
+
+/// Runs `f` on a detached checkout of `git_ref` without touching the working tree.
+pub fn with_worktree<T>(git_ref: &str, f: impl FnOnce(&Path) -> Result<T>) -> Result<T> {
+    with_worktree_in(&get_git_root_path()?, git_ref, f)
+}
+
+/// Like [`with_worktree`] for the repository at `repo`. The worktree is removed
+/// when `f` returns, errors or panics; worktrees left behind by killed
+/// processes are cleaned up on the next call.
+pub fn with_worktree_in<T>(
+    repo: &Path,
+    git_ref: &str,
+    f: impl FnOnce(&Path) -> Result<T>,
+) -> Result<T> {
+    let worktrees_dir = git_common_dir(repo)?.join(WORKTREES_DIR);
+    fs::create_dir_all(&worktrees_dir)
+        .context(format!("failed to create {}", worktrees_dir.display()))?;
+    prune_stale_worktrees(repo, &worktrees_dir);
+
+    let path = worktrees_dir.join(format!(
+        "{}-{}",
+        std::process::id(),
+        WORKTREE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    // taken before the worktree exists, so it is never seen unlocked
+    let lock_path = worktree_lock(&path);
+    let lock =
+        File::create(&lock_path).context(format!("failed to create {}", lock_path.display()))?;
+    lock.lock()
+        .context(format!("failed to lock {}", lock_path.display()))?;
+    let _lock = guard(lock, |lock| {
+        drop(lock);
+        let _ = fs::remove_file(&lock_path);
+    });
+    git_in(
+        repo,
+        &[
+            "worktree",
+            "add",
+            "--detach",
+            "--quiet",
+            &path.to_string_lossy(),
+            git_ref,
+        ],
+    )
+    .context(format!("failed to check out {git_ref} into a worktree"))?;
+    let path = guard(path, |path| remove_worktree(repo, &path));
+    f(&path)
+}
+
+fn git_common_dir(repo: &Path) -> Result<PathBuf> {
+    let dir = git_path(&git_in(repo, &["rev-parse", "--git-common-dir"])?);
+    Ok(if dir.is_absolute() {
+        dir
+    } else {
+        repo.join(dir)
+    })
+}
+
+fn remove_worktree(repo: &Path, path: &Path) {
+    debug!("removing worktree {}", path.display());
+    if let Err(e) = git_in(
+        repo,
+        &["worktree", "remove", "--force", &path.to_string_lossy()],
+    ) {
+        warn!("failed to remove worktree {}: {e}", path.display());
+        let _ = fs::remove_dir_all(path);
+    }
+    let _ = git_in(repo, &["worktree", "prune"]);
+}
+
+fn worktree_lock(path: &Path) -> PathBuf {
+    path.with_extension("lock")
+}
+
+fn prune_stale_worktrees(repo: &Path, worktrees_dir: &Path) {
+    let Ok(entries) = fs::read_dir(worktrees_dir) else {
+        return;
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if !path.is_dir() {
+            continue;
+        }
+        let lock_path = worktree_lock(&path);
+        let Ok(lock) = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+        else {
+            continue;
+        };
+        // held by the owner until its worktree is removed
+        if lock.try_lock().is_ok() {
+            warn!("removing stale worktree {}", path.display());
+            remove_worktree(repo, &path);
+            drop(lock);
+            let _ = fs::remove_file(&lock_path);
+        }
+    }
+}
+
+/// Paths of the submodules of `repo`, relative to it, whether or not they
+/// are initialized.
+pub fn submodule_paths(repo: &Path) -> Result<Vec<PathBuf>> {
+    Ok(parse_gitlinks(&git_in(repo, &["ls-files", "--stage"])?))
+}
+
+/// Paths of the gitlink entries (mode 160000) of `git ls-files --stage`.
+fn parse_gitlinks(ls_files: &str) -> Vec<PathBuf> {
+    ls_files
+        .lines()
+        .filter(|line| line.starts_with("160000 "))
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(_, path)| PathBuf::from(path))
+        .collect()
+}
+
+/// Runs git in `repo`, returning its trimmed stdout.
+pub fn git_in(repo: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repo)
+        .output()
+        .map_err(|e| anyhow!("failed to run git: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
 }
 
 #[cfg(test)]
 mod tests {
     use {super::*, pretty_assertions::assert_eq, serial_test::serial, std::fs};
 
+    #[test]
+    fn test_parse_gitlinks() {
+        let ls_files = "100644 3b18e512dba79e4c8300dd08aeb37f8e728b8dad 0\tCargo.toml\n\
+                        160000 5f0c1a2e3b4d5c6a7b8c9d0e1f2a3b4c5d6e7f80 0\tvendor/agave\n";
+        assert_eq!(
+            parse_gitlinks(ls_files),
+            vec![PathBuf::from("vendor/agave")]
+        );
+    }
+
     #[test]
     #[serial]
     fn test_get_git_root_path() {
@@ -31,4 +306,101 @@ mod tests {
 
         assert_eq!(canonicalized_root_path, canonicalized_temp_dir_path);
     }
+
+    #[test]
+    fn test_is_dirty_and_last_tag() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@localhost"])
+                .args(args)
+                .current_dir(dir)
+                .output()
+                .unwrap()
+        };
+        git(&["init"]);
+        git(&["commit", "--allow-empty", "-m", "init"]);
+        assert!(!is_dirty(dir).unwrap());
+        assert_eq!(get_last_tag(dir).unwrap(), None);
+
+        fs::write(dir.join("file"), "").unwrap();
+        assert!(is_dirty(dir).unwrap());
+
+        git(&["tag", "v1.0.0"]);
+        assert_eq!(get_last_tag(dir).unwrap(), Some(String::from("v1.0.0")));
+
+        let root = git_root_of(dir).unwrap();
+        fs::create_dir_all(dir.join(".xtask/locks")).unwrap();
+        fs::write(dir.join(".xtask/audit.jsonl"), "").unwrap();
+        fs::write(dir.join(".xtask/locks/release.lock"), "").unwrap();
+        let excluded = [root.join(".xtask/audit.jsonl"), root.join(".xtask/locks")];
+        assert_eq!(status_lines(dir, &excluded).unwrap(), ["?? file"]);
+    }
+
+    #[test]
+    fn test_with_worktree() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@localhost"])
+                .args(args)
+                .current_dir(dir)
+                .output()
+                .unwrap()
+        };
+        git(&["init"]);
+        fs::write(dir.join("version"), "1").unwrap();
+        git(&["add", "version"]);
+        git(&["commit", "-m", "v1"]);
+        git(&["tag", "v1"]);
+        fs::write(dir.join("version"), "2").unwrap();
+
+        let worktree_path = with_worktree_in(dir, "v1", |worktree| {
+            assert_eq!(fs::read_to_string(worktree.join("version")).unwrap(), "1");
+            Ok(worktree.to_path_buf())
+        })
+        .unwrap();
+        assert!(!worktree_path.exists());
+        // the working tree is untouched
+        assert_eq!(fs::read_to_string(dir.join("version")).unwrap(), "2");
+
+        // cleaned up on error too
+        let err = with_worktree_in(dir, "v1", |_| -> Result<()> { Err(anyhow!("boom")) });
+        assert!(err.is_err());
+        let worktrees = fs::read_dir(git_common_dir(dir).unwrap().join(WORKTREES_DIR)).unwrap();
+        assert_eq!(worktrees.count(), 0);
+
+        assert!(with_worktree_in(dir, "no-such-ref", |_| Ok(())).is_err());
+    }
+
+    #[test]
+    fn test_prune_stale_worktrees() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let dir = temp_dir.path();
+        let git = |args: &[&str]| {
+            Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@localhost"])
+                .args(args)
+                .current_dir(dir)
+                .output()
+                .unwrap()
+        };
+        git(&["init"]);
+        git(&["commit", "--allow-empty", "-m", "initial"]);
+        let worktrees_dir = git_common_dir(dir).unwrap().join(WORKTREES_DIR);
+        // left by a killed process, whatever its pid is now used for
+        let stale = worktrees_dir.join(format!("{}-99", std::process::id()));
+        fs::create_dir_all(&stale).unwrap();
+
+        with_worktree_in(dir, "HEAD", |live| {
+            prune_stale_worktrees(dir, &worktrees_dir);
+            assert!(live.exists());
+            Ok(())
+        })
+        .unwrap();
+        assert!(!stale.exists());
+        assert_eq!(fs::read_dir(&worktrees_dir).unwrap().count(), 0);
+    }
 }
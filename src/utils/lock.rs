@@ -0,0 +1,579 @@
+use {
+    super::{audit::current_user, time},
+    crate::config::{Config, LockBackend},
+    anyhow::{anyhow, Context, Result},
+    log::{info, warn},
+    serde::{Deserialize, Serialize},
+    std::{
+        fs,
+        io::Write,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+};
+
+pub const LOCK_REF_PREFIX: &str = "refs/xtask/locks";
+
+/// Token of a lock held by a parent process, e.g. a pipeline that ran
+/// `xtask lock acquire`. Commands run with it re-enter the lock instead of
+/// failing on it, and leave releasing it to the holder.
+pub const LOCK_TOKEN_ENV: &str = "XTASK_LOCK_TOKEN";
+
+/// Who holds a lock, stored in the lock file or the lock ref's commit message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub scope: String,
+    pub holder: String,
+    pub acquired_at: String,
+    pub token: String,
+}
+
+impl LockHolder {
+    fn new(scope: &str) -> Self {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        LockHolder {
+            scope: scope.to_string(),
+            holder: current_user(),
+            acquired_at: time::format_rfc3339(time::unix_now()),
+            token: format!("{}-{}-{nanos}", std::process::id(), time::unix_now()),
+        }
+    }
+}
+
+/// A lock acquired by this process.
+#[derive(Debug)]
+pub struct Lock {
+    backend: Backend,
+    holder: LockHolder,
+    /// commit the lock ref points to (git-ref backend only)
+    lease: Option<String>,
+    /// Held through [`LOCK_TOKEN_ENV`] on behalf of a parent process, which
+    /// releases it.
+    reentered: bool,
+}
+
+/// What to make of a lock someone already holds.
+#[derive(Debug, PartialEq)]
+enum Existing {
+    /// Ours, handed down through [`LOCK_TOKEN_ENV`].
+    Reenter,
+    /// Older than the TTL, its holder presumably crashed.
+    Stale,
+    Held,
+}
+
+fn classify(current: &LockHolder, token: Option<&str>, ttl_secs: u64, now: u64) -> Existing {
+    if token.is_some_and(|token| token == current.token) {
+        return Existing::Reenter;
+    }
+    let expired = ttl_secs > 0
+        && time::parse_rfc3339(&current.acquired_at)
+            .is_ok_and(|acquired| acquired.saturating_add(ttl_secs) <= now);
+    if expired {
+        Existing::Stale
+    } else {
+        Existing::Held
+    }
+}
+
+/// Scopes name lock files and refs, so they must stay a single component.
+fn validate_scope(scope: &str) -> Result<()> {
+    let valid = !scope.is_empty()
+        && !scope.starts_with('.')
+        && scope
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(anyhow!(
+            "invalid lock scope '{scope}', use letters, digits, `-`, `_` and `.`"
+        ));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone)]
+enum Backend {
+    File { directory: PathBuf },
+    GitRef { repo: PathBuf, remote: String },
+}
+
+/// How a lock is acquired.
+#[derive(Debug, Clone, Default)]
+struct Acquire<'a> {
+    ttl_secs: u64,
+    /// Token handed down through [`LOCK_TOKEN_ENV`].
+    token: Option<&'a str>,
+}
+
+impl Backend {
+    fn from_config(config: &Config) -> Self {
+        match config.lock.backend {
+            LockBackend::File => Backend::File {
+                directory: config.resolve(&config.lock.directory),
+            },
+            LockBackend::GitRef => Backend::GitRef {
+                repo: config.root.clone(),
+                remote: config.lock.remote.clone(),
+            },
+        }
+    }
+}
+
+pub fn acquire(config: &Config, scope: &str) -> Result<Lock> {
+    let token = std::env::var(LOCK_TOKEN_ENV).ok();
+    acquire_with(
+        Backend::from_config(config),
+        scope,
+        &Acquire {
+            ttl_secs: config.lock.ttl_secs,
+            token: token.as_deref().filter(|t| !t.is_empty()),
+        },
+    )
+}
+
+pub fn status(config: &Config, scope: &str) -> Result<Option<LockHolder>> {
+    validate_scope(scope)?;
+    match Backend::from_config(config) {
+        Backend::File { directory } => read_lock_file(&lock_file_path(&directory, scope)),
+        Backend::GitRef { repo, remote } => {
+            Ok(read_lock_ref(&repo, &remote, scope)?.map(|(_, holder)| holder))
+        }
+    }
+}
+
+/// Releases the lock `lock acquire` took with `token`, failing when someone
+/// else holds it.
+pub fn release(config: &Config, scope: &str, token: &str) -> Result<()> {
+    validate_scope(scope)?;
+    let not_ours = |current: Option<&LockHolder>| match current {
+        Some(current) => anyhow!(
+            "lock '{scope}' is held by {} since {}, not by this token",
+            current.holder,
+            current.acquired_at
+        ),
+        None => anyhow!("lock '{scope}' is not held"),
+    };
+    match Backend::from_config(config) {
+        Backend::File { directory } => {
+            let path = lock_file_path(&directory, scope);
+            match read_lock_file(&path)? {
+                Some(current) if current.token == token => {
+                    fs::remove_file(&path).context(format!("failed to remove {}", path.display()))
+                }
+                current => Err(not_ours(current.as_ref())),
+            }
+        }
+        Backend::GitRef { repo, remote } => match read_lock_ref(&repo, &remote, scope)? {
+            Some((commit, current)) if current.token == token => {
+                delete_lock_ref(&repo, &remote, scope, &commit)
+            }
+            current => Err(not_ours(current.as_ref().map(|(_, holder)| holder))),
+        },
+    }
+}
+
+/// Releases a lock held by another process, e.g. after a crashed pipeline.
+pub fn force_release(config: &Config, scope: &str) -> Result<()> {
+    validate_scope(scope)?;
+    match Backend::from_config(config) {
+        Backend::File { directory } => {
+            let path = lock_file_path(&directory, scope);
+            if path.exists() {
+                fs::remove_file(&path).context(format!("failed to remove {}", path.display()))?;
+            }
+            Ok(())
+        }
+        Backend::GitRef { repo, remote } => match read_lock_ref(&repo, &remote, scope)? {
+            Some((commit, _)) => delete_lock_ref(&repo, &remote, scope, &commit),
+            None => Ok(()),
+        },
+    }
+}
+
+fn acquire_with(backend: Backend, scope: &str, options: &Acquire) -> Result<Lock> {
+    validate_scope(scope)?;
+    let holder = LockHolder::new(scope);
+    let held_by = |current: Option<LockHolder>| {
+        let current = current
+            .map(|h| format!("{} since {}", h.holder, h.acquired_at))
+            .unwrap_or_else(|| String::from("another process"));
+        anyhow!("lock '{scope}' is held by {current}")
+    };
+    let existing =
+        |current: &LockHolder| classify(current, options.token, options.ttl_secs, time::unix_now());
+    let reenter = |backend: Backend, current: LockHolder, lease: Option<String>| {
+        info!("🔒 re-entered lock '{scope}' held by {}", current.holder);
+        Ok(Lock {
+            backend,
+            holder: current,
+            lease,
+            reentered: true,
+        })
+    };
+
+    let lease = match &backend {
+        Backend::File { directory } => {
+            fs::create_dir_all(directory)
+                .context(format!("failed to create {}", directory.display()))?;
+            let path = lock_file_path(directory, scope);
+            let mut took_over = false;
+            // create_new makes acquisition atomic on the local filesystem
+            let mut file = loop {
+                match fs::OpenOptions::new()
+                    .write(true)
+                    .create_new(true)
+                    .open(&path)
+                {
+                    Ok(file) => break file,
+                    Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                        let current = read_lock_file(&path).ok().flatten();
+                        match current.as_ref().map(existing) {
+                            Some(Existing::Reenter) => {
+                                return reenter(backend.clone(), current.unwrap_or(holder), None)
+                            }
+                            Some(Existing::Stale) if !took_over => {
+                                warn_stale(current.as_ref());
+                                let stale = current.map(|c| c.token).unwrap_or_default();
+                                take_over_file(&path, &stale, &holder.token).map_err(&held_by)?;
+                                took_over = true;
+                            }
+                            _ => return Err(held_by(current)),
+                        }
+                    }
+                    Err(e) => return Err(anyhow!("failed to create {}: {e}", path.display())),
+                }
+            };
+            file.write_all(serde_json::to_string_pretty(&holder)?.as_bytes())?;
+            None
+        }
+        Backend::GitRef { repo, remote } => {
+            let commit = git(
+                repo,
+                &[
+                    "commit-tree",
+                    "HEAD^{tree}",
+                    "-m",
+                    &serde_json::to_string(&holder)?,
+                ],
+            )?;
+            // an empty lease only lets the push through if the ref does not
+            // exist yet, a stale lock's commit only if nobody took it over
+            let lock_ref = format!("{LOCK_REF_PREFIX}/{scope}");
+            let push = |lease: &str| {
+                git(
+                    repo,
+                    &[
+                        "push",
+                        "--quiet",
+                        &format!("--force-with-lease={lock_ref}:{lease}"),
+                        remote,
+                        &format!("{commit}:{lock_ref}"),
+                    ],
+                )
+            };
+            if push("").is_err() {
+                let current = read_lock_ref(repo, remote, scope).ok().flatten();
+                match current {
+                    Some((lease, current)) => match existing(&current) {
+                        Existing::Reenter => return reenter(backend.clone(), current, Some(lease)),
+                        Existing::Stale => {
+                            warn_stale(Some(&current));
+                            if push(&lease).is_err() {
+                                return Err(held_by(
+                                    read_lock_ref(repo, remote, scope)
+                                        .ok()
+                                        .flatten()
+                                        .map(|(_, h)| h),
+                                ));
+                            }
+                        }
+                        Existing::Held => return Err(held_by(Some(current))),
+                    },
+                    None => return Err(held_by(None)),
+                }
+            }
+            Some(commit)
+        }
+    };
+
+    info!("🔒 acquired lock '{scope}'");
+    Ok(Lock {
+        backend,
+        holder,
+        lease,
+        reentered: false,
+    })
+}
+
+/// Removes the stale lock file at `path` held with the `stale` token. The
+/// file is renamed aside first, which only one process can do, and put back
+/// if it turns out another process took the lock over in the meantime.
+fn take_over_file(path: &Path, stale: &str, token: &str) -> Result<(), Option<LockHolder>> {
+    let aside = path.with_extension(format!("lock.{token}"));
+    match fs::rename(path, &aside) {
+        Ok(()) => {}
+        // whoever removed it competes with us on create_new
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(_) => return Err(read_lock_file(path).ok().flatten()),
+    }
+    let moved = read_lock_file(&aside).ok().flatten();
+    if moved.as_ref().is_some_and(|m| m.token != stale) {
+        // a fresh lock, hard_link does not replace a lock created since
+        let _ = fs::hard_link(&aside, path);
+        let _ = fs::remove_file(&aside);
+        return Err(moved);
+    }
+    let _ = fs::remove_file(&aside);
+    Ok(())
+}
+
+fn warn_stale(current: Option<&LockHolder>) {
+    if let Some(current) = current {
+        warn!(
+            "taking over lock '{}' held by {} since {}, past lock.ttl-secs",
+            current.scope, current.holder, current.acquired_at
+        );
+    }
+}
+
+impl Lock {
+    pub fn holder(&self) -> &LockHolder {
+        &self.holder
+    }
+
+    pub fn release(self) -> Result<()> {
+        let scope = &self.holder.scope;
+        if self.reentered {
+            // the process that handed the lock down releases it
+            return Ok(());
+        }
+        match &self.backend {
+            Backend::File { directory } => {
+                let path = lock_file_path(directory, scope);
+                match read_lock_file(&path)? {
+                    Some(current) if current.token == self.holder.token => {
+                        fs::remove_file(&path)
+                            .context(format!("failed to remove {}", path.display()))?;
+                    }
+                    _ => return Err(anyhow!("lock '{scope}' is no longer held by us")),
+                }
+            }
+            Backend::GitRef { repo, remote } => {
+                let commit = self.lease.as_deref().unwrap_or_default();
+                delete_lock_ref(repo, remote, scope, commit)?;
+            }
+        }
+        info!("🔓 released lock '{scope}'");
+        Ok(())
+    }
+}
+
+/// Holds a lock for the duration of a scope, releasing it on drop.
+pub struct LockGuard(Option<Lock>);
+
+impl LockGuard {
+    pub fn acquire(config: &Config, scope: &str) -> Result<Self> {
+        Ok(LockGuard(Some(acquire(config, scope)?)))
+    }
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        if let Some(lock) = self.0.take() {
+            if let Err(e) = lock.release() {
+                warn!("failed to release lock: {e:#}");
+            }
+        }
+    }
+}
+
+fn lock_file_path(directory: &Path, scope: &str) -> PathBuf {
+    directory.join(format!("{scope}.lock"))
+}
+
+fn read_lock_file(path: &Path) -> Result<Option<LockHolder>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path).context(format!("failed to read {}", path.display()))?;
+    Ok(Some(
+        serde_json::from_str(&content).context(format!("failed to parse {}", path.display()))?,
+    ))
+}
+
+fn read_lock_ref(repo: &Path, remote: &str, scope: &str) -> Result<Option<(String, LockHolder)>> {
+    let lock_ref = format!("{LOCK_REF_PREFIX}/{scope}");
+    let listing = git(repo, &["ls-remote", remote, &lock_ref])?;
+    let Some(commit) = listing.split_whitespace().next() else {
+        return Ok(None);
+    };
+    git(repo, &["fetch", "--quiet", remote, &lock_ref])?;
+    let message = git(repo, &["log", "-1", "--format=%B", commit])?;
+    let holder =
+        serde_json::from_str(&message).context(format!("failed to parse holder of {lock_ref}"))?;
+    Ok(Some((commit.to_string(), holder)))
+}
+
+fn delete_lock_ref(repo: &Path, remote: &str, scope: &str, commit: &str) -> Result<()> {
+    let lock_ref = format!("{LOCK_REF_PREFIX}/{scope}");
+    git(
+        repo,
+        &[
+            "push",
+            "--quiet",
+            &format!("--force-with-lease={lock_ref}:{commit}"),
+            remote,
+            &format!(":{lock_ref}"),
+        ],
+    )
+    .map(|_| ())
+    .map_err(|e| anyhow!("failed to release lock '{scope}': {e}"))
+}
+
+fn git(repo: &Path, args: &[&str]) -> Result<String> {
+    let output = Command::new("git")
+        // lock commits are synthetic, don't depend on the operator's identity
+        .args(["-c", "user.name=xtask", "-c", "user.email=xtask@localhost"])
+        .args(args)
+        .current_dir(repo)
+        .output()
+        .map_err(|e| anyhow!("failed to run git: {e}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_file_lock() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let backend = Backend::File {
+            directory: temp_dir.path().to_path_buf(),
+        };
+
+        let lock = acquire_with(backend.clone(), "release", &Acquire::default()).unwrap();
+        let err = acquire_with(backend.clone(), "release", &Acquire::default()).unwrap_err();
+        assert!(err.to_string().starts_with("lock 'release' is held by"));
+
+        // other scopes are independent
+        acquire_with(backend.clone(), "docs", &Acquire::default()).unwrap();
+
+        lock.release().unwrap();
+        let lock = acquire_with(backend.clone(), "release", &Acquire::default()).unwrap();
+
+        // a child handed the token re-enters and leaves the lock in place
+        let token = lock.holder().token.clone();
+        let child = Acquire {
+            token: Some(&token),
+            ..Acquire::default()
+        };
+        let reentered = acquire_with(backend.clone(), "release", &child).unwrap();
+        assert_eq!(reentered.holder(), lock.holder());
+        reentered.release().unwrap();
+        assert!(acquire_with(backend.clone(), "release", &Acquire::default()).is_err());
+        lock.release().unwrap();
+
+        assert!(acquire_with(backend, "../release", &Acquire::default()).is_err());
+    }
+
+    #[test]
+    fn test_take_over_file() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = lock_file_path(temp_dir.path(), "release");
+        let write = |token: &str| {
+            let holder = LockHolder {
+                token: token.to_string(),
+                ..LockHolder::new("release")
+            };
+            fs::write(&path, serde_json::to_string(&holder).unwrap()).unwrap();
+        };
+
+        write("stale");
+        take_over_file(&path, "stale", "a").unwrap();
+        assert!(!path.exists());
+
+        // another process took over first, its lock stays
+        write("fresh");
+        let current = take_over_file(&path, "stale", "b").unwrap_err().unwrap();
+        assert_eq!(current.token, "fresh");
+        assert_eq!(read_lock_file(&path).unwrap().unwrap().token, "fresh");
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_classify() {
+        let holder = LockHolder {
+            scope: String::from("release"),
+            holder: String::from("ci"),
+            acquired_at: String::from("2026-01-01T00:00:00Z"),
+            token: String::from("123-456-789"),
+        };
+        let acquired = time::parse_rfc3339(&holder.acquired_at).unwrap();
+        assert_eq!(
+            classify(&holder, Some("123-456-789"), 60, acquired),
+            Existing::Reenter
+        );
+        assert_eq!(
+            classify(&holder, Some("other"), 60, acquired + 59),
+            Existing::Held
+        );
+        assert_eq!(classify(&holder, None, 60, acquired + 60), Existing::Stale);
+        assert_eq!(classify(&holder, None, 0, acquired + 600), Existing::Held);
+        for scope in ["", "a/b", "a\\b", "..", ".hidden"] {
+            assert!(validate_scope(scope).is_err(), "{scope}");
+        }
+        validate_scope("release-1.18").unwrap();
+    }
+
+    #[test]
+    fn test_git_ref_lock() {
+        let remote_dir = tempfile::tempdir().unwrap();
+        let repo_dir = tempfile::tempdir().unwrap();
+        git(remote_dir.path(), &["init", "--quiet", "--bare"]).unwrap();
+        git(repo_dir.path(), &["init", "--quiet"]).unwrap();
+        git(
+            repo_dir.path(),
+            &["commit", "--quiet", "--allow-empty", "-m", "init"],
+        )
+        .unwrap();
+        git(
+            repo_dir.path(),
+            &[
+                "remote",
+                "add",
+                "origin",
+                remote_dir.path().to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+
+        let backend = Backend::GitRef {
+            repo: repo_dir.path().to_path_buf(),
+            remote: String::from("origin"),
+        };
+        let lock = acquire_with(backend.clone(), "release", &Acquire::default()).unwrap();
+        let (_, holder) = read_lock_ref(repo_dir.path(), "origin", "release")
+            .unwrap()
+            .unwrap();
+        assert_eq!(&holder, lock.holder());
+
+        assert!(acquire_with(backend.clone(), "release", &Acquire::default()).is_err());
+        lock.release().unwrap();
+        assert_eq!(
+            read_lock_ref(repo_dir.path(), "origin", "release").unwrap(),
+            None
+        );
+        acquire_with(backend, "release", &Acquire::default()).unwrap();
+    }
+}
@@ -0,0 +1,148 @@
+use {
+    crate::{config::Config, utils::cargo::wildcard_match},
+    anyhow::{Context, Result},
+    serde::Deserialize,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        fs,
+        path::Path,
+    },
+};
+
+/// Which team owns which crates, read from `maintainers.toml`:
+///
+/// ```toml
+/// [teams.runtime]
+/// crates = ["solana-runtime", "solana-svm-*"]
+/// members = ["@anza-xyz/runtime"]
+/// webhook-env = "RUNTIME_SLACK_WEBHOOK"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Maintainers {
+    pub teams: BTreeMap<String, Team>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case", deny_unknown_fields)]
+pub struct Team {
+    /// Crate names, `*` and `?` wildcards allowed.
+    pub crates: Vec<String>,
+    /// GitHub handles or teams to contact.
+    pub members: Vec<String>,
+    /// Environment variable holding the webhook of the team's channel, so
+    /// the secret stays out of the repository.
+    pub webhook_env: Option<String>,
+}
+
+impl Maintainers {
+    /// Reads the mapping, empty when the file does not exist.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content =
+            fs::read_to_string(path).context(format!("failed to read {}", path.display()))?;
+        toml_edit::de::from_str(&content).context(format!("failed to parse {}", path.display()))
+    }
+
+    /// The mapping configured by `owners.maintainers` in `xtask.toml`.
+    pub fn load_configured(config: &Config) -> Result<Self> {
+        Self::load(&config.resolve(&config.owners.maintainers))
+    }
+
+    /// Teams owning `name`, in name order.
+    pub fn owners(&self, name: &str) -> Vec<&str> {
+        self.teams
+            .iter()
+            .filter(|(_, team)| team.crates.iter().any(|p| wildcard_match(p, name)))
+            .map(|(team, _)| team.as_str())
+            .collect()
+    }
+
+    /// Splits `crates` into the `webhook-env` variables of their owning teams
+    /// and the crates no team with a channel owns.
+    pub fn route<'a, 'c>(&'a self, crates: &[&'c str]) -> (BTreeSet<&'a str>, Vec<&'c str>) {
+        let mut variables = BTreeSet::new();
+        let mut unrouted = vec![];
+        for name in crates.iter() {
+            let owned: Vec<&str> = self
+                .owners(name)
+                .into_iter()
+                .filter_map(|team| self.teams[team].webhook_env.as_deref())
+                .collect();
+            if owned.is_empty() {
+                unrouted.push(*name);
+            }
+            variables.extend(owned);
+        }
+        (variables, unrouted)
+    }
+
+    /// `(team, pattern)` pairs matching none of `crates`, usually left behind
+    /// by a rename or removal.
+    pub fn unused_patterns<'a>(&'a self, crates: &[String]) -> Vec<(&'a str, &'a str)> {
+        let mut unused = vec![];
+        for (name, team) in self.teams.iter() {
+            for pattern in team.crates.iter() {
+                if !crates.iter().any(|c| wildcard_match(pattern, c)) {
+                    unused.push((name.as_str(), pattern.as_str()));
+                }
+            }
+        }
+        unused
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_owners() {
+        let maintainers: Maintainers = toml_edit::de::from_str(
+            r#"
+[teams.runtime]
+crates = ["solana-runtime", "solana-svm-*"]
+members = ["@anza-xyz/runtime"]
+webhook-env = "RUNTIME_WEBHOOK"
+
+[teams.svm]
+crates = ["solana-svm-*", "solana-bpf-loader-program"]
+
+[teams.cli]
+crates = ["solana-clap-utils"]
+"#,
+        )
+        .unwrap();
+        assert_eq!(maintainers.owners("solana-runtime"), vec!["runtime"]);
+        assert_eq!(
+            maintainers.owners("solana-svm-transaction"),
+            vec!["runtime", "svm"]
+        );
+        assert_eq!(maintainers.owners("solana-gossip"), Vec::<&str>::new());
+
+        let crates = vec![
+            String::from("solana-runtime"),
+            String::from("solana-svm-transaction"),
+        ];
+        assert_eq!(
+            maintainers.unused_patterns(&crates),
+            vec![
+                ("cli", "solana-clap-utils"),
+                ("svm", "solana-bpf-loader-program"),
+            ]
+        );
+
+        let (variables, unrouted) = maintainers.route(&[
+            "solana-svm-transaction",
+            "solana-clap-utils",
+            "solana-gossip",
+        ]);
+        assert_eq!(
+            variables.into_iter().collect::<Vec<_>>(),
+            vec!["RUNTIME_WEBHOOK"]
+        );
+        assert_eq!(unrouted, vec!["solana-clap-utils", "solana-gossip"]);
+    }
+}
@@ -1,9 +1,30 @@
+pub mod aliases;
+pub mod audit;
 pub mod cargo;
+pub mod credentials;
+pub mod discovery;
 pub mod docker;
 pub mod fs;
 pub mod git;
+pub mod http;
+pub mod lock;
+pub mod maintainers;
+pub mod notify;
+pub mod portable;
+pub mod prompt;
+pub mod registry;
+pub mod release_history;
+pub mod release_metadata;
+pub mod scope;
+pub mod signature;
+pub mod telemetry;
+pub mod template;
+pub mod test_history;
+pub mod time;
+pub mod vfs;
 
-pub use cargo::{get_all_crates, get_current_version};
-pub use docker::check_docker_available;
+pub use cargo::{get_all_crates, get_current_version, get_toolchain_channel};
+pub use docker::{check_docker_available, DockerRun};
 pub use fs::{find_all_cargo_locks, find_all_cargo_tomls, recursive_find_files};
-pub use git::get_git_root_path;
+pub use git::{get_git_root_path, get_head_commit, get_last_tag, is_dirty, with_worktree};
+pub use scope::get_scope_root;
@@ -0,0 +1,63 @@
+use {
+    super::{http, maintainers::Maintainers},
+    crate::config::Config,
+    anyhow::Result,
+    log::debug,
+};
+
+/// Posts `text` to the configured webhook; a no-op when none is configured.
+pub fn send(config: &Config, text: &str) -> Result<()> {
+    let Some(webhook) = config.notify.webhook() else {
+        debug!("no notify.webhook configured, not sending: {text}");
+        return Ok(());
+    };
+    post(config, &webhook, text)
+}
+
+/// Posts `text` to the channels of the teams owning `crates` (see
+/// `maintainers.toml`), falling back to the configured webhook for crates
+/// whose owners have no channel.
+pub fn send_to_owners(config: &Config, crates: &[&str], text: &str) -> Result<()> {
+    if crates.is_empty() {
+        return send(config, text);
+    }
+    let maintainers = Maintainers::load_configured(config)?;
+    let (variables, unrouted) = maintainers.route(crates);
+    let mut fallback = !unrouted.is_empty();
+    for variable in variables {
+        match std::env::var(variable).ok().filter(|v| !v.is_empty()) {
+            Some(webhook) => post(config, &webhook, text)?,
+            None => {
+                debug!("{variable} is not set, falling back to notify.webhook");
+                fallback = true;
+            }
+        }
+    }
+    if fallback {
+        send(config, text)?;
+    }
+    Ok(())
+}
+
+fn post(config: &Config, webhook: &str, text: &str) -> Result<()> {
+    let body = serde_json::json!({ "text": text }).to_string();
+    http::post_json(&config.network, webhook, &body)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, crate::config::NotifyConfig};
+
+    #[test]
+    fn test_send_keeps_the_webhook_out_of_errors() {
+        let config = Config {
+            notify: NotifyConfig {
+                webhook: Some(String::from("http://127.0.0.1:1/services/T0/B0/secret")),
+            },
+            ..Config::default()
+        };
+        let error = format!("{:#}", send(&config, "hello").unwrap_err());
+        assert!(!error.contains("secret"), "{error}");
+    }
+}
@@ -0,0 +1,149 @@
+//! Path and newline handling that also holds on Windows build agents, where
+//! git prints `/`-separated paths, `fs::canonicalize` returns `\\?\` paths
+//! and checkouts may use CRLF line endings.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf, MAIN_SEPARATOR},
+    process::Command,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    /// The line ending of `content`, taken from its first line.
+    pub fn detect(content: &str) -> Self {
+        match content.find('\n') {
+            Some(end) if content[..end].ends_with('\r') => LineEnding::CrLf,
+            _ => LineEnding::Lf,
+        }
+    }
+
+    /// `content` with every line ending replaced by this one.
+    pub fn apply(self, content: &str) -> String {
+        let lf = content.replace("\r\n", "\n");
+        match self {
+            LineEnding::Lf => lf,
+            LineEnding::CrLf => lf.replace('\n', "\r\n"),
+        }
+    }
+}
+
+/// `fs::canonicalize` without the `\\?\` verbatim prefix Windows adds, which
+/// paths from git, cargo or the user never have.
+pub fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    let canonical = fs::canonicalize(path)?;
+    Ok(PathBuf::from(strip_verbatim(&canonical.to_string_lossy())))
+}
+
+fn strip_verbatim(path: &str) -> String {
+    if let Some(share) = path.strip_prefix(r"\\?\UNC\") {
+        format!(r"\\{share}")
+    } else if let Some(local) = path.strip_prefix(r"\\?\") {
+        local.to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+/// A path printed by git, in the native form of the platform.
+pub fn git_path(output: &str) -> PathBuf {
+    PathBuf::from(native_git_path(output, cfg!(windows)))
+}
+
+/// Git for Windows prints `C:/repo`, and `/c/repo` when run from an MSYS
+/// shell.
+fn native_git_path(output: &str, windows: bool) -> String {
+    let path = output.trim();
+    if !windows {
+        return path.to_string();
+    }
+    let mut chars = path.chars();
+    let path = match (chars.next(), chars.next(), chars.next()) {
+        (Some('/'), Some(drive), Some('/') | None) if drive.is_ascii_alphabetic() => {
+            format!(
+                "{}:/{}",
+                drive.to_ascii_uppercase(),
+                path.get(3..).unwrap_or("")
+            )
+        }
+        _ => path.to_string(),
+    };
+    path.replace('/', "\\")
+}
+
+/// `path` with `/` separators, for manifests, CODEOWNERS and other files
+/// that are the same on every platform.
+pub fn slash_path(path: &Path) -> String {
+    path.to_string_lossy().replace(MAIN_SEPARATOR, "/")
+}
+
+/// Runs `script` through the platform shell, `sh -c` or `cmd /C`.
+pub fn shell(script: &str) -> Command {
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut cmd = Command::new(shell);
+    cmd.args([flag, script]);
+    cmd
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_line_ending() {
+        assert_eq!(
+            LineEnding::detect("[package]\r\nname = \"a\"\r\n"),
+            LineEnding::CrLf
+        );
+        assert_eq!(
+            LineEnding::detect("[package]\nname = \"a\"\n"),
+            LineEnding::Lf
+        );
+        assert_eq!(LineEnding::detect(""), LineEnding::Lf);
+        assert_eq!(
+            LineEnding::CrLf.apply("[package]\nname = \"a\"\r\n"),
+            "[package]\r\nname = \"a\"\r\n"
+        );
+        assert_eq!(LineEnding::Lf.apply("a\r\nb\n"), "a\nb\n");
+    }
+
+    #[test]
+    fn test_strip_verbatim() {
+        assert_eq!(
+            strip_verbatim(r"\\?\C:\repo\Cargo.toml"),
+            r"C:\repo\Cargo.toml"
+        );
+        assert_eq!(
+            strip_verbatim(r"\\?\UNC\server\share\repo"),
+            r"\\server\share\repo"
+        );
+        assert_eq!(strip_verbatim("/home/repo"), "/home/repo");
+    }
+
+    #[test]
+    fn test_native_git_path() {
+        assert_eq!(native_git_path("C:/work/repo\r\n", true), r"C:\work\repo");
+        assert_eq!(native_git_path("/c/work/repo\n", true), r"C:\work\repo");
+        assert_eq!(native_git_path("/d", true), r"D:\");
+        assert_eq!(native_git_path("/home/repo\n", false), "/home/repo");
+    }
+
+    #[test]
+    fn test_shell() {
+        let dir = std::env::temp_dir();
+        let output = shell("echo hello").current_dir(&dir).output().unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "hello");
+        let status = shell("exit 3").current_dir(&dir).status().unwrap();
+        assert_eq!(status.code(), Some(3));
+    }
+}
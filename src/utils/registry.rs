@@ -0,0 +1,201 @@
+use {
+    super::http,
+    crate::config::NetworkConfig,
+    anyhow::{anyhow, Context, Result},
+    log::debug,
+    semver::{Version, VersionReq},
+    serde::Deserialize,
+    std::{
+        thread,
+        time::{Duration, Instant},
+    },
+};
+
+pub const CRATES_IO_INDEX: &str = "https://index.crates.io";
+pub const CRATES_IO_API: &str = "https://crates.io/api/v1";
+
+/// One published version from a sparse index file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct IndexEntry {
+    pub name: String,
+    pub vers: String,
+    #[serde(default)]
+    pub yanked: bool,
+}
+
+impl IndexEntry {
+    pub fn version(&self) -> Option<Version> {
+        Version::parse(&self.vers).ok()
+    }
+}
+
+/// One published version as listed by the crates.io API, which unlike the
+/// index knows when it was published.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct PublishedVersion {
+    pub num: String,
+    pub created_at: String,
+    #[serde(default)]
+    pub yanked: bool,
+}
+
+#[derive(Deserialize)]
+struct VersionsPage {
+    versions: Vec<PublishedVersion>,
+    #[serde(default)]
+    meta: VersionsMeta,
+}
+
+#[derive(Default, Deserialize)]
+struct VersionsMeta {
+    next_page: Option<String>,
+}
+
+/// Fetches every published version of `name` from the crates.io API.
+pub fn fetch_published_versions(
+    network: &NetworkConfig,
+    name: &str,
+) -> Result<Vec<PublishedVersion>> {
+    let base = format!("{CRATES_IO_API}/crates/{name}/versions");
+    let mut url = format!("{base}?per_page=100");
+    let mut versions = vec![];
+    loop {
+        let body = http::get(network, &url, &[("User-Agent", "anza-xtask")])?;
+        let page: VersionsPage = serde_json::from_slice(&body)
+            .context(format!("failed to parse the crates.io versions of {name}"))?;
+        versions.extend(page.versions);
+        match page.meta.next_page {
+            Some(next) => url = format!("{base}{next}"),
+            None => return Ok(versions),
+        }
+    }
+}
+
+/// Path of a crate's file in a sparse index, e.g. `se/rd/serde`.
+pub fn index_path(name: &str) -> String {
+    let name = name.to_lowercase();
+    match name.len() {
+        1 => format!("1/{name}"),
+        2 => format!("2/{name}"),
+        3 => format!("3/{}/{name}", &name[..1]),
+        _ => format!("{}/{}/{name}", &name[..2], &name[2..4]),
+    }
+}
+
+/// Fetches every published version of `name`; unknown crates have none.
+/// `token` authenticates to a private index the way cargo does, crates.io
+/// takes none.
+pub fn fetch_versions(
+    network: &NetworkConfig,
+    index_url: &str,
+    token: Option<&str>,
+    name: &str,
+) -> Result<Vec<IndexEntry>> {
+    let url = format!("{}/{}", index_url.trim_end_matches('/'), index_path(name));
+    let mut headers = vec![("User-Agent", "anza-xtask")];
+    headers.extend(token.map(|token| ("Authorization", token)));
+    let body = match http::get(network, &url, &headers) {
+        Ok(body) => body,
+        Err(e) if e.to_string().contains("404") => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+    parse_index_file(&String::from_utf8_lossy(&body))
+        .context(format!("failed to parse index entry for {name}"))
+}
+
+pub fn parse_index_file(content: &str) -> Result<Vec<IndexEntry>> {
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| serde_json::from_str(line).map_err(|e| anyhow!("{e}: {line}")))
+        .collect()
+}
+
+/// Highest non-yanked stable version, optionally restricted to `req`.
+pub fn latest_version(entries: &[IndexEntry], req: Option<&VersionReq>) -> Option<Version> {
+    entries
+        .iter()
+        .filter(|entry| !entry.yanked)
+        .filter_map(IndexEntry::version)
+        .filter(|version| version.pre.is_empty())
+        .filter(|version| req.is_none_or(|req| req.matches(version)))
+        .max()
+}
+
+/// Polls the index until every `(name, version)` in `crates` is listed,
+/// failing with the missing ones after `timeout`.
+pub fn wait_for_versions(
+    network: &NetworkConfig,
+    index_url: &str,
+    token: Option<&str>,
+    crates: &[(String, String)],
+    timeout: Duration,
+    poll_interval: Duration,
+) -> Result<()> {
+    let started = Instant::now();
+    let mut pending: Vec<&(String, String)> = crates.iter().collect();
+    loop {
+        let mut still_pending = vec![];
+        for entry in pending {
+            let (name, version) = entry;
+            let listed = fetch_versions(network, index_url, token, name)
+                .map(|entries| entries.iter().any(|e| e.vers == *version))
+                .unwrap_or(false);
+            if !listed {
+                still_pending.push(entry);
+            }
+        }
+        pending = still_pending;
+        if pending.is_empty() {
+            return Ok(());
+        }
+        if started.elapsed() >= timeout {
+            return Err(anyhow!(
+                "not in the index after {}s: {}",
+                timeout.as_secs(),
+                pending
+                    .iter()
+                    .map(|(name, version)| format!("{name}@{version}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        debug!("waiting for {} crate(s) to reach the index", pending.len());
+        thread::sleep(poll_interval);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_index_path() {
+        assert_eq!(index_path("a"), "1/a");
+        assert_eq!(index_path("ab"), "2/ab");
+        assert_eq!(index_path("abc"), "3/a/abc");
+        assert_eq!(index_path("Serde"), "se/rd/serde");
+    }
+
+    #[test]
+    fn test_latest_version() {
+        let entries = parse_index_file(
+            r#"{"name":"foo","vers":"1.0.0","yanked":false}
+{"name":"foo","vers":"1.2.0","yanked":false}
+{"name":"foo","vers":"1.3.0","yanked":true}
+{"name":"foo","vers":"2.0.0-rc.1","yanked":false}
+{"name":"foo","vers":"2.1.0","yanked":false}
+"#,
+        )
+        .unwrap();
+        assert_eq!(latest_version(&entries, None), Some(Version::new(2, 1, 0)));
+        assert_eq!(
+            latest_version(&entries, Some(&VersionReq::parse("1").unwrap())),
+            Some(Version::new(1, 2, 0))
+        );
+        assert_eq!(
+            latest_version(&entries, Some(&VersionReq::parse("3").unwrap())),
+            None
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use {
+    anyhow::{Context, Result},
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, fs, path::Path},
+};
+
+/// Every release the orchestrator completed, committed as `releases.json`
+/// so dashboards and `release rollback` can look releases up without
+/// crates.io or GitHub.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseHistory {
+    #[serde(default)]
+    pub releases: Vec<ReleaseRecord>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct ReleaseRecord {
+    pub version: String,
+    /// `YYYY-MM-DD` the release was cut.
+    pub date: String,
+    pub git_commit: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub publish_duration_secs: Option<u64>,
+    /// SHA-256 (or image digest) by artifact name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub artifacts: BTreeMap<String, String>,
+}
+
+impl ReleaseHistory {
+    /// Reads the history, starting an empty one if it does not exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content =
+            fs::read_to_string(path).context(format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&content).context(format!("failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).context(format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(path, format!("{}\n", serde_json::to_string_pretty(self)?))
+            .context(format!("failed to write {}", path.display()))
+    }
+
+    /// Adds `record`, replacing an earlier record of the same version, newest
+    /// release first.
+    pub fn record(&mut self, record: ReleaseRecord) {
+        self.releases
+            .retain(|release| release.version != record.version);
+        self.releases.insert(0, record);
+    }
+
+    pub fn get(&self, version: &str) -> Option<&ReleaseRecord> {
+        self.releases
+            .iter()
+            .find(|release| release.version == version)
+    }
+
+    /// Releases matching `version` (`2.1` matches every `2.1.x`) cut on or
+    /// after `since`, a `YYYY-MM-DD` date.
+    pub fn query(&self, version: Option<&str>, since: Option<&str>) -> Vec<&ReleaseRecord> {
+        self.releases
+            .iter()
+            .filter(|release| version.is_none_or(|v| matches_version(v, &release.version)))
+            .filter(|release| since.is_none_or(|since| release.date.as_str() >= since))
+            .collect()
+    }
+}
+
+/// Whether `version` is `query` or starts with its dot-separated components.
+fn matches_version(query: &str, version: &str) -> bool {
+    let query = query.trim_start_matches('v');
+    version == query
+        || version
+            .strip_prefix(query)
+            .is_some_and(|rest| rest.starts_with(['.', '-']))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn release(version: &str, date: &str) -> ReleaseRecord {
+        ReleaseRecord {
+            version: version.to_string(),
+            date: date.to_string(),
+            git_commit: String::from("0fbec9e"),
+            ..ReleaseRecord::default()
+        }
+    }
+
+    #[test]
+    fn test_record_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("releases.json");
+        let mut history = ReleaseHistory::load(&path).unwrap();
+        history.record(release("2.1.0", "2025-01-10"));
+        history.record(release("2.1.1", "2025-02-01"));
+        let mut rerun = release("2.1.0", "2025-01-11");
+        rerun.publish_duration_secs = Some(754);
+        rerun.artifacts.insert(
+            String::from("solana-release-x86_64-unknown-linux-gnu.tar.bz2"),
+            String::from("e3b0c442"),
+        );
+        history.record(rerun.clone());
+        history.save(&path).unwrap();
+
+        let loaded = ReleaseHistory::load(&path).unwrap();
+        assert_eq!(loaded, history);
+        assert_eq!(
+            loaded
+                .releases
+                .iter()
+                .map(|r| r.version.as_str())
+                .collect::<Vec<_>>(),
+            vec!["2.1.0", "2.1.1"]
+        );
+        assert_eq!(loaded.get("2.1.0"), Some(&rerun));
+    }
+
+    #[test]
+    fn test_query() {
+        let history = ReleaseHistory {
+            releases: vec![
+                release("2.10.0", "2025-06-01"),
+                release("2.1.1", "2025-02-01"),
+                release("2.1.0", "2025-01-10"),
+                release("2.0.9", "2024-12-01"),
+            ],
+        };
+        let versions = |records: Vec<&ReleaseRecord>| {
+            records
+                .iter()
+                .map(|r| r.version.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            versions(history.query(Some("2.1"), None)),
+            vec!["2.1.1", "2.1.0"]
+        );
+        assert_eq!(versions(history.query(Some("v2.1.0"), None)), vec!["2.1.0"]);
+        assert_eq!(
+            versions(history.query(None, Some("2025-01-15"))),
+            vec!["2.10.0", "2.1.1"]
+        );
+        assert_eq!(
+            versions(history.query(Some("2"), Some("2025-02-01"))),
+            vec!["2.10.0", "2.1.1"]
+        );
+    }
+}
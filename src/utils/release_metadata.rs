@@ -0,0 +1,77 @@
+use {
+    anyhow::{Context, Result},
+    serde::{Deserialize, Serialize},
+    std::{fs, path::Path},
+};
+
+/// Provenance document describing what a release produced, consumed by
+/// deployment tooling to pin artifacts by digest.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub git_commit: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageDigest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageDigest {
+    /// `name:tag` as pushed.
+    pub reference: String,
+    pub digest: String,
+}
+
+impl ReleaseMetadata {
+    /// Reads the document, starting a new one if it does not exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content =
+            fs::read_to_string(path).context(format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&content).context(format!("failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).context(format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(path, format!("{}\n", serde_json::to_string_pretty(self)?))
+            .context(format!("failed to write {}", path.display()))
+    }
+
+    /// Records the digest of a pushed image, replacing an earlier push of the
+    /// same reference.
+    pub fn record_image(&mut self, reference: &str, digest: &str) {
+        self.images.retain(|image| image.reference != reference);
+        self.images.push(ImageDigest {
+            reference: reference.to_string(),
+            digest: digest.to_string(),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_record_image_round_trip() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("dist/release-metadata.json");
+
+        let mut metadata = ReleaseMetadata::load(&path).unwrap();
+        metadata.version = Some(String::from("2.2.0"));
+        metadata.record_image("anzaxyz/agave:2.2.0", "sha256:old");
+        metadata.record_image("anzaxyz/agave:2.2.0", "sha256:new");
+        metadata.record_image("anzaxyz/agave:latest", "sha256:new");
+        metadata.save(&path).unwrap();
+
+        let loaded = ReleaseMetadata::load(&path).unwrap();
+        assert_eq!(loaded, metadata);
+        assert_eq!(loaded.images.len(), 2);
+        assert_eq!(loaded.images[0].digest, "sha256:new");
+    }
+}
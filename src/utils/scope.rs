@@ -0,0 +1,87 @@
+use {
+    super::{git::get_git_root_path, portable::canonicalize},
+    anyhow::{anyhow, Context, Result},
+    std::{
+        path::{Path, PathBuf},
+        sync::OnceLock,
+    },
+};
+
+/// Subtree, relative to the git root, that discovery is restricted to.
+static SCOPE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Restricts manifest, lockfile and crate discovery to `path` for the rest of
+/// the process. Relative paths resolve against the current directory.
+pub fn set_scope(path: &Path) -> Result<()> {
+    let git_root = get_git_root_path()?;
+    let scope = normalize_scope(&git_root, &std::env::current_dir()?, path)?;
+    SCOPE
+        .set(scope)
+        .map_err(|_| anyhow!("scope is already set"))
+}
+
+pub fn get_scope() -> Option<&'static Path> {
+    SCOPE.get().map(PathBuf::as_path)
+}
+
+/// Directory discovery starts from: the scoped subtree, or the git root.
+pub fn get_scope_root() -> Result<PathBuf> {
+    let git_root = get_git_root_path()?;
+    Ok(match get_scope() {
+        Some(scope) => git_root.join(scope),
+        None => git_root,
+    })
+}
+
+/// Resolves a relative `--manifest-path` against the scope root when a scope is set.
+pub fn resolve_manifest_path(manifest_path: &str) -> Result<String> {
+    if get_scope().is_none() || Path::new(manifest_path).is_absolute() {
+        return Ok(manifest_path.to_string());
+    }
+    Ok(get_scope_root()?
+        .join(manifest_path)
+        .to_string_lossy()
+        .to_string())
+}
+
+fn normalize_scope(git_root: &Path, cwd: &Path, path: &Path) -> Result<PathBuf> {
+    let absolute = canonicalize(&cwd.join(path))
+        .context(format!("scope {} does not exist", path.display()))?;
+    let git_root = canonicalize(git_root)?;
+    if !absolute.is_dir() {
+        return Err(anyhow!("scope {} is not a directory", path.display()));
+    }
+    absolute
+        .strip_prefix(&git_root)
+        .map(Path::to_path_buf)
+        .map_err(|_| anyhow!("scope {} is outside the repository", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::fs};
+
+    #[test]
+    fn test_normalize_scope() {
+        let root_dir = tempfile::tempdir().unwrap();
+        let root = root_dir.path();
+        fs::create_dir_all(root.join("programs/token")).unwrap();
+        fs::write(root.join("Cargo.toml"), "").unwrap();
+
+        assert_eq!(
+            normalize_scope(root, root, Path::new("programs/token")).unwrap(),
+            PathBuf::from("programs/token")
+        );
+        assert_eq!(
+            normalize_scope(root, &root.join("programs/token"), Path::new("..")).unwrap(),
+            PathBuf::from("programs")
+        );
+        assert_eq!(
+            normalize_scope(root, root, Path::new(".")).unwrap(),
+            PathBuf::new()
+        );
+        assert!(normalize_scope(root, root, Path::new("missing")).is_err());
+        assert!(normalize_scope(root, root, Path::new("Cargo.toml")).is_err());
+        assert!(normalize_scope(&root.join("programs"), root, Path::new(".")).is_err());
+    }
+}
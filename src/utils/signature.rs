@@ -0,0 +1,125 @@
+use {
+    crate::failure::Failure,
+    anyhow::{anyhow, Result},
+    std::{
+        path::{Path, PathBuf},
+        process::Command,
+    },
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureScheme {
+    Minisign,
+    Gpg,
+}
+
+impl SignatureScheme {
+    /// Detects the scheme from the detached signature file extension.
+    pub fn from_signature_path(signature: &Path) -> Result<Self> {
+        match signature.extension().and_then(|e| e.to_str()) {
+            Some("minisig") => Ok(SignatureScheme::Minisign),
+            Some("asc") | Some("sig") | Some("gpg") => Ok(SignatureScheme::Gpg),
+            _ => Err(anyhow!(
+                "unsupported signature file {}, expected .minisig, .asc, .sig or .gpg",
+                signature.display()
+            )),
+        }
+    }
+}
+
+/// Returns the first existing detached signature next to `file`.
+pub fn find_detached_signature(file: &Path) -> Option<PathBuf> {
+    ["minisig", "asc", "sig"].iter().find_map(|ext| {
+        let mut name = file.as_os_str().to_os_string();
+        name.push(format!(".{ext}"));
+        let candidate = PathBuf::from(name);
+        candidate.exists().then_some(candidate)
+    })
+}
+
+/// Verifies `signature` over `file` against the trusted `public_key`.
+///
+/// The public key is a minisign `.pub` file or an (armored) GPG public key.
+/// GPG verification uses a throwaway keyring so the operator's own keyring
+/// never influences which keys are trusted.
+pub fn verify_detached_signature(file: &Path, signature: &Path, public_key: &Path) -> Result<()> {
+    let output = match SignatureScheme::from_signature_path(signature)? {
+        SignatureScheme::Minisign => Command::new("minisign")
+            .arg("-V")
+            .arg("-q")
+            .arg("-m")
+            .arg(file)
+            .arg("-x")
+            .arg(signature)
+            .arg("-p")
+            .arg(public_key)
+            .output()
+            .map_err(|e| anyhow!("failed to run minisign: {e}"))?,
+        SignatureScheme::Gpg => {
+            let gnupg_home = tempfile::tempdir()?;
+            let import = Command::new("gpg")
+                .arg("--homedir")
+                .arg(gnupg_home.path())
+                .args(["--batch", "--import"])
+                .arg(public_key)
+                .output()
+                .map_err(|e| anyhow!("failed to run gpg: {e}"))?;
+            if !import.status.success() {
+                return Err(anyhow!(
+                    "failed to import {}: {}",
+                    public_key.display(),
+                    String::from_utf8_lossy(&import.stderr).trim()
+                ));
+            }
+            Command::new("gpg")
+                .arg("--homedir")
+                .arg(gnupg_home.path())
+                .args(["--batch", "--verify"])
+                .arg(signature)
+                .arg(file)
+                .output()
+                .map_err(|e| anyhow!("failed to run gpg: {e}"))?
+        }
+    };
+
+    if !output.status.success() {
+        return Err(Failure::Verification.error(format!(
+            "signature verification of {} failed: {}",
+            file.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq, std::fs};
+
+    #[test]
+    fn test_signature_scheme_from_path() {
+        assert_eq!(
+            SignatureScheme::from_signature_path(Path::new("plan.json.minisig")).unwrap(),
+            SignatureScheme::Minisign
+        );
+        assert_eq!(
+            SignatureScheme::from_signature_path(Path::new("plan.json.asc")).unwrap(),
+            SignatureScheme::Gpg
+        );
+        assert!(SignatureScheme::from_signature_path(Path::new("plan.json")).is_err());
+    }
+
+    #[test]
+    fn test_find_detached_signature() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let plan = temp_dir.path().join("plan.json");
+        fs::write(&plan, "{}").unwrap();
+        assert_eq!(find_detached_signature(&plan), None);
+
+        fs::write(temp_dir.path().join("plan.json.asc"), "").unwrap();
+        assert_eq!(
+            find_detached_signature(&plan),
+            Some(temp_dir.path().join("plan.json.asc"))
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use {
+    super::{audit::append_line, http, time},
+    crate::config::{Config, TelemetryConfig},
+    anyhow::{anyhow, Result},
+    serde::Serialize,
+    serde_json::json,
+    std::{
+        net::UdpSocket,
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+    tracing::{info_span, warn},
+};
+
+/// Phases timed by the running command, drained when it finishes.
+static PHASES: Mutex<Vec<Timing>> = Mutex::new(Vec::new());
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Timing {
+    pub name: String,
+    pub millis: u64,
+    pub success: bool,
+}
+
+/// Durations of one command run, sent to the configured sinks.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Report {
+    pub timestamp: String,
+    pub command: String,
+    pub millis: u64,
+    pub success: bool,
+    pub phases: Vec<Timing>,
+}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+/// Times `f` as a phase of the running command, e.g. one release stage;
+/// `name` is relative to the command (`bump`, not `release.bump`).
+pub fn phase<T>(name: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    let _span = info_span!("phase", name).entered();
+    let started = Instant::now();
+    let result = f();
+    record(name, started.elapsed(), result.is_ok());
+    result
+}
+
+/// Records a phase timed by the caller, for phases that do not end in a
+/// `Result`.
+pub fn record(name: &str, duration: Duration, success: bool) {
+    if let Ok(mut phases) = PHASES.lock() {
+        phases.push(Timing {
+            name: name.to_string(),
+            millis: millis(duration),
+            success,
+        });
+    }
+}
+
+/// Runs a command and, when `telemetry.enabled` is set, reports its duration
+/// and the phases it timed.
+///
+/// Failing to send the report is only a warning, it never changes the
+/// outcome of the command.
+pub fn timed<T>(command: &str, f: impl FnOnce() -> Result<T>) -> Result<T> {
+    let started = Instant::now();
+    let result = f();
+    let report = Report {
+        timestamp: time::format_rfc3339(time::unix_now()),
+        command: command.to_string(),
+        millis: millis(started.elapsed()),
+        success: result.is_ok(),
+        phases: PHASES
+            .lock()
+            .map(|mut phases| phases.drain(..).collect())
+            .unwrap_or_default(),
+    };
+    let emitted = Config::load().and_then(|config| {
+        if config.telemetry.enabled {
+            emit(&config, &report)
+        } else {
+            Ok(())
+        }
+    });
+    if let Err(e) = emitted {
+        warn!("failed to report timings of {command}: {e:#}");
+    }
+    result
+}
+
+pub fn emit(config: &Config, report: &Report) -> Result<()> {
+    let telemetry = &config.telemetry;
+    if let Some(file) = &telemetry.file {
+        append_line(&config.resolve(file), &serde_json::to_string(report)?)?;
+    }
+    if let Some(address) = &telemetry.statsd {
+        let socket = UdpSocket::bind("0.0.0.0:0")
+            .map_err(|e| anyhow!("failed to open a statsd socket: {e}"))?;
+        let payload = statsd_lines(telemetry, report).join("\n");
+        socket
+            .send_to(payload.as_bytes(), address)
+            .map_err(|e| anyhow!("failed to send to statsd at {address}: {e}"))?;
+    }
+    if let Some(endpoint) = &telemetry.otlp {
+        let body = otlp_metrics(telemetry, report);
+        http::post_json(&config.network, endpoint, &body.to_string())?;
+    }
+    Ok(())
+}
+
+/// StatsD timers, `<prefix>.<command>.duration` for the run and
+/// `<prefix>.<command>.<phase>` per phase, plus a failure counter.
+pub fn statsd_lines(telemetry: &TelemetryConfig, report: &Report) -> Vec<String> {
+    let prefix = format!("{}.{}", telemetry.prefix, metric_name(&report.command));
+    let mut lines = vec![format!("{prefix}.duration:{}|ms", report.millis)];
+    for phase in report.phases.iter() {
+        lines.push(format!(
+            "{prefix}.{}:{}|ms",
+            metric_name(&phase.name),
+            phase.millis
+        ));
+    }
+    if !report.success {
+        lines.push(format!("{prefix}.failure:1|c"));
+    }
+    lines
+}
+
+/// Metric names keep to letters, digits, `_`, `-` and `.`; the `:` and `|`
+/// of the statsd line format would break it.
+fn metric_name(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.') {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// An OTLP/HTTP JSON export with one gauge per timing, for
+/// `<collector>/v1/metrics`.
+pub fn otlp_metrics(telemetry: &TelemetryConfig, report: &Report) -> serde_json::Value {
+    let time_unix_nano = time::unix_now().saturating_mul(1_000_000_000).to_string();
+    let gauge = |name: String, millis: u64, success: bool| {
+        json!({
+            "name": name,
+            "unit": "ms",
+            "gauge": {
+                "dataPoints": [{
+                    "asInt": millis.to_string(),
+                    "timeUnixNano": time_unix_nano,
+                    "attributes": [
+                        {"key": "command", "value": {"stringValue": report.command}},
+                        {"key": "success", "value": {"boolValue": success}},
+                    ],
+                }],
+            },
+        })
+    };
+    let mut metrics = vec![gauge(
+        format!("{}.duration", telemetry.prefix),
+        report.millis,
+        report.success,
+    )];
+    for phase in report.phases.iter() {
+        metrics.push(gauge(
+            format!("{}.phase.{}", telemetry.prefix, metric_name(&phase.name)),
+            phase.millis,
+            phase.success,
+        ));
+    }
+    json!({
+        "resourceMetrics": [{
+            "resource": {
+                "attributes": [{"key": "service.name", "value": {"stringValue": "xtask"}}],
+            },
+            "scopeMetrics": [{"scope": {"name": "xtask"}, "metrics": metrics}],
+        }],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    fn report(success: bool) -> Report {
+        Report {
+            timestamp: String::from("2024-01-01T00:00:00Z"),
+            command: String::from("release"),
+            millis: 1500,
+            success,
+            phases: vec![
+                Timing {
+                    name: String::from("bump"),
+                    millis: 200,
+                    success: true,
+                },
+                Timing {
+                    name: String::from("publish"),
+                    millis: 1200,
+                    success,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_statsd_lines() {
+        let telemetry = TelemetryConfig::default();
+        assert_eq!(
+            statsd_lines(&telemetry, &report(true)),
+            vec![
+                "xtask.release.duration:1500|ms",
+                "xtask.release.bump:200|ms",
+                "xtask.release.publish:1200|ms",
+            ]
+        );
+        assert_eq!(
+            statsd_lines(&telemetry, &report(false)).last().unwrap(),
+            "xtask.release.failure:1|c"
+        );
+        assert_eq!(metric_name("lint:docs rs"), "lint_docs_rs");
+    }
+
+    #[test]
+    fn test_otlp_metrics() {
+        let body = otlp_metrics(&TelemetryConfig::default(), &report(true));
+        let metrics = &body["resourceMetrics"][0]["scopeMetrics"][0]["metrics"];
+        assert_eq!(metrics[0]["name"], "xtask.duration");
+        assert_eq!(metrics[0]["gauge"]["dataPoints"][0]["asInt"], "1500");
+        assert_eq!(metrics[2]["name"], "xtask.phase.publish");
+    }
+
+    #[test]
+    fn test_phase_is_recorded() {
+        let result = phase("test.phase", || Err::<(), _>(anyhow!("boom")));
+        assert!(result.is_err());
+        let phases = PHASES.lock().unwrap();
+        assert!(phases.iter().any(|p| p.name == "test.phase" && !p.success));
+    }
+}
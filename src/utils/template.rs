@@ -0,0 +1,46 @@
+/// Replaces every `{{key}}` in `template`, the placeholder syntax of the
+/// README and release notes templates.
+///
+/// A single left-to-right pass, so `{{...}}` inside a value (a commit subject,
+/// a crate description) is copied as is rather than expanded. Unknown keys
+/// are left in place.
+pub fn render(template: &str, values: &[(&str, &str)]) -> String {
+    let mut text = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        text.push_str(&rest[..start]);
+        let after = &rest[start.saturating_add(2)..];
+        let Some(end) = after.find("}}") else {
+            rest = &rest[start..];
+            break;
+        };
+        let key = &after[..end];
+        match values.iter().find(|(k, _)| *k == key) {
+            Some((_, value)) => text.push_str(value),
+            None => text.push_str(&rest[start..start.saturating_add(end).saturating_add(4)]),
+        }
+        rest = &after[end.saturating_add(2)..];
+    }
+    text.push_str(rest);
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            render(
+                "## {{version}} - {{date}}\n{{notes}} {{unknown}} {{",
+                &[
+                    ("version", "v2.2.0"),
+                    ("date", "2024-01-01"),
+                    ("notes", "fix {{version}} parsing"),
+                ]
+            ),
+            "## v2.2.0 - 2024-01-01\nfix {{version}} parsing {{unknown}} {{"
+        );
+    }
+}
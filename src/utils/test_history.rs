@@ -0,0 +1,178 @@
+use {
+    anyhow::{Context, Result},
+    serde::{Deserialize, Serialize},
+    std::{collections::BTreeMap, fs, path::Path},
+};
+
+/// Recent durations of every test, by test binary, kept across `xtask test
+/// run` invocations. CI restores and saves the file like a cache.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TestHistory {
+    /// Seconds per run, oldest first, by test name, by binary.
+    #[serde(default)]
+    pub binaries: BTreeMap<String, BTreeMap<String, Vec<f64>>>,
+}
+
+/// A test whose latest run took much longer than it used to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regression {
+    pub binary: String,
+    pub test: String,
+    pub baseline_secs: f64,
+    pub latest_secs: f64,
+}
+
+impl TestHistory {
+    /// Reads the history, starting an empty one if it does not exist yet.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content =
+            fs::read_to_string(path).context(format!("failed to read {}", path.display()))?;
+        serde_json::from_str(&content).context(format!("failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).context(format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(path, serde_json::to_string(self)?)
+            .context(format!("failed to write {}", path.display()))
+    }
+
+    /// Takes the durations of every binary in `shard` over the ones recorded
+    /// here, shards running disjoint binaries.
+    pub fn merge(&mut self, shard: TestHistory) {
+        self.binaries.extend(shard.binaries);
+    }
+
+    /// Appends a run of `test`, keeping the newest `samples` durations.
+    pub fn record(&mut self, binary: &str, test: &str, secs: f64, samples: usize) {
+        let durations = self
+            .binaries
+            .entry(binary.to_string())
+            .or_default()
+            .entry(test.to_string())
+            .or_default();
+        durations.push(secs);
+        let excess = durations.len().saturating_sub(samples.max(1));
+        durations.drain(..excess);
+    }
+
+    /// Typical runtime of every test: the median of its recorded durations,
+    /// slowest first.
+    pub fn slowest(&self) -> Vec<(&str, &str, f64)> {
+        let mut tests: Vec<(&str, &str, f64)> = self
+            .binaries
+            .iter()
+            .flat_map(|(binary, tests)| {
+                tests.iter().map(move |(test, durations)| {
+                    (binary.as_str(), test.as_str(), median(durations))
+                })
+            })
+            .collect();
+        tests.sort_by(|a, b| {
+            b.2.total_cmp(&a.2)
+                .then_with(|| (a.0, a.1).cmp(&(b.0, b.1)))
+        });
+        tests
+    }
+
+    /// Summed typical runtime of the tests of `binary`, `None` if it never ran.
+    pub fn weight(&self, binary: &str) -> Option<f64> {
+        self.binaries
+            .get(binary)
+            .map(|tests| tests.values().map(|durations| median(durations)).sum())
+    }
+
+    /// Tests whose latest run took over `ratio` times the median of the runs
+    /// before it, ignoring tests faster than `min_secs` whose timings are
+    /// mostly noise.
+    pub fn regressions(&self, ratio: f64, min_secs: f64) -> Vec<Regression> {
+        let mut regressions = vec![];
+        for (binary, tests) in self.binaries.iter() {
+            for (test, durations) in tests.iter() {
+                let Some((latest, earlier)) = durations.split_last() else {
+                    continue;
+                };
+                if earlier.is_empty() || *latest < min_secs {
+                    continue;
+                }
+                let baseline = median(earlier);
+                if *latest > baseline * ratio {
+                    regressions.push(Regression {
+                        binary: binary.clone(),
+                        test: test.clone(),
+                        baseline_secs: baseline,
+                        latest_secs: *latest,
+                    });
+                }
+            }
+        }
+        regressions
+    }
+}
+
+fn median(durations: &[f64]) -> f64 {
+    let mut sorted = durations.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    match sorted.len() {
+        0 => 0.0,
+        len if len % 2 == 1 => sorted[len / 2],
+        len => (sorted[len / 2 - 1] + sorted[len / 2]) / 2.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_merge() {
+        let mut history = TestHistory::default();
+        history.record("a", "old", 1.0, 3);
+        history.record("b", "kept", 2.0, 3);
+        let mut shard = history.clone();
+        shard.binaries.remove("b");
+        shard.record("a", "old", 3.0, 3);
+        history.merge(shard);
+        assert_eq!(history.binaries["a"]["old"], vec![1.0, 3.0]);
+        assert_eq!(history.binaries["b"]["kept"], vec![2.0]);
+    }
+
+    #[test]
+    fn test_record_and_query() {
+        let mut history = TestHistory::default();
+        for secs in [1.0, 1.25, 0.75, 1.25] {
+            history.record("xtask/lib", "publish::tests::test_order", secs, 3);
+        }
+        history.record("xtask/update_crate", "test_update", 0.2, 3);
+        assert_eq!(
+            history.binaries["xtask/lib"]["publish::tests::test_order"],
+            vec![1.25, 0.75, 1.25]
+        );
+        assert_eq!(
+            history.slowest(),
+            vec![
+                ("xtask/lib", "publish::tests::test_order", 1.25),
+                ("xtask/update_crate", "test_update", 0.2),
+            ]
+        );
+        assert_eq!(history.weight("xtask/lib"), Some(1.25));
+        assert_eq!(history.weight("xtask/other"), None);
+        assert_eq!(history.regressions(2.0, 0.5), vec![]);
+
+        history.record("xtask/lib", "publish::tests::test_order", 3.0, 3);
+        history.record("xtask/update_crate", "test_update", 0.45, 3);
+        assert_eq!(
+            history.regressions(2.0, 0.5),
+            vec![Regression {
+                binary: String::from("xtask/lib"),
+                test: String::from("publish::tests::test_order"),
+                baseline_secs: 1.0,
+                latest_secs: 3.0,
+            }]
+        );
+    }
+}
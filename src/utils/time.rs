@@ -0,0 +1,146 @@
+use {
+    anyhow::{anyhow, Result},
+    std::time::{SystemTime, UNIX_EPOCH},
+};
+
+pub const SECONDS_PER_DAY: u64 = 86_400;
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Formats unix seconds as an RFC 3339 UTC timestamp, e.g. `2024-05-01T12:00:00Z`.
+pub fn format_rfc3339(unix_seconds: u64) -> String {
+    let days = unix_seconds / SECONDS_PER_DAY;
+    let seconds_of_day = unix_seconds % SECONDS_PER_DAY;
+    let (year, month, day) = civil_from_days(days as i64);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        seconds_of_day / 3600,
+        seconds_of_day % 3600 / 60,
+        seconds_of_day % 60
+    )
+}
+
+/// Parses `YYYY-MM-DD` or an RFC 3339 UTC timestamp (`YYYY-MM-DDTHH:MM:SSZ`,
+/// fractional seconds and numeric offsets are accepted) into unix seconds.
+pub fn parse_rfc3339(s: &str) -> Result<u64> {
+    let invalid = || anyhow!("invalid timestamp: {s}");
+    let (date, time) = s.split_once(['T', ' ']).unwrap_or((s, "00:00:00Z"));
+
+    let mut date_parts = date.splitn(3, '-').map(|p| p.parse::<i64>());
+    let (Some(Ok(year)), Some(Ok(month)), Some(Ok(day))) =
+        (date_parts.next(), date_parts.next(), date_parts.next())
+    else {
+        return Err(invalid());
+    };
+
+    let (clock, offset_seconds) = match time.find(['Z', 'z', '+', '-']) {
+        Some(idx) => {
+            let (clock, zone) = time.split_at(idx);
+            let offset = match zone {
+                "Z" | "z" => 0,
+                _ => {
+                    let sign = if zone.starts_with('-') { -1 } else { 1 };
+                    let (h, m) = zone[1..].split_once(':').ok_or_else(invalid)?;
+                    let h: i64 = h.parse().map_err(|_| invalid())?;
+                    let m: i64 = m.parse().map_err(|_| invalid())?;
+                    sign * (h * 3600 + m * 60)
+                }
+            };
+            (clock, offset)
+        }
+        None => (time, 0),
+    };
+    let clock = clock.split('.').next().unwrap_or(clock);
+    let mut clock_parts = clock.splitn(3, ':').map(|p| p.parse::<i64>());
+    let (Some(Ok(hour)), Some(Ok(minute)), Some(Ok(second))) =
+        (clock_parts.next(), clock_parts.next(), clock_parts.next())
+    else {
+        return Err(invalid());
+    };
+
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(invalid());
+    }
+    let seconds = days_from_civil(year, month, day) * SECONDS_PER_DAY as i64
+        + hour * 3600
+        + minute * 60
+        + second
+        - offset_seconds;
+    u64::try_from(seconds).map_err(|_| invalid())
+}
+
+/// Parses a `YYYY-MM-DD` date, rejecting timestamps and impossible days such
+/// as `2026-02-30`, into unix seconds at midnight UTC.
+pub fn parse_date(s: &str) -> Result<u64> {
+    parse_rfc3339(s)
+        .ok()
+        .filter(|seconds| s.len() == 10 && format_rfc3339(*seconds).starts_with(s))
+        .ok_or_else(|| anyhow!("invalid date {s:?}, expected YYYY-MM-DD"))
+}
+
+// Howard Hinnant's civil calendar algorithms.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 };
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_format_rfc3339() {
+        assert_eq!(format_rfc3339(0), "1970-01-01T00:00:00Z");
+        assert_eq!(format_rfc3339(1_709_251_199), "2024-02-29T23:59:59Z");
+    }
+
+    #[test]
+    fn test_parse_rfc3339() {
+        assert_eq!(parse_rfc3339("1970-01-01T00:00:00Z").unwrap(), 0);
+        assert_eq!(
+            parse_rfc3339("2024-02-29T23:59:59Z").unwrap(),
+            1_709_251_199
+        );
+        assert_eq!(
+            parse_rfc3339("2024-02-29T23:59:59.123+00:00").unwrap(),
+            1_709_251_199
+        );
+        assert_eq!(
+            parse_rfc3339("2024-03-01T01:59:59+02:00").unwrap(),
+            1_709_251_199
+        );
+        assert_eq!(parse_rfc3339("2024-03-01").unwrap(), 1_709_251_200);
+        assert!(parse_rfc3339("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("2024-03-01").unwrap(), 1_709_251_200);
+        assert!(parse_date("2024-3-1").is_err());
+        assert!(parse_date("2026-02-30").is_err());
+        assert!(parse_date("2024-03-01T00:00:00Z").is_err());
+    }
+}
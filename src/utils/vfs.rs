@@ -0,0 +1,145 @@
+use {
+    super::portable::LineEnding,
+    anyhow::{anyhow, Context, Result},
+    std::{
+        collections::BTreeMap,
+        fs,
+        path::{Path, PathBuf},
+        sync::Mutex,
+    },
+};
+
+/// File access of the manifest-editing logic, so it can run against an
+/// in-memory tree in tests instead of a tempdir and the current directory.
+///
+/// Both implementations keep the line endings of the file being replaced, so
+/// rewriting a manifest of a CRLF checkout only changes the edited lines.
+pub trait FileSystem {
+    fn read_to_string(&self, path: &Path) -> Result<String>;
+    fn write(&self, path: &Path, content: &str) -> Result<()>;
+}
+
+/// The real filesystem.
+pub struct RealFs;
+
+impl FileSystem for RealFs {
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        fs::read_to_string(path).context(format!("failed to read {}", path.display()))
+    }
+
+    fn write(&self, path: &Path, content: &str) -> Result<()> {
+        let existing = fs::read_to_string(path).ok();
+        fs::write(path, keep_line_endings(existing.as_deref(), content))
+            .context(format!("failed to write {}", path.display()))
+    }
+}
+
+/// Files held in memory, keyed by path; safe to share between threads.
+#[derive(Debug, Default)]
+pub struct MemoryFs {
+    files: Mutex<BTreeMap<PathBuf, String>>,
+}
+
+impl MemoryFs {
+    pub fn new<P: Into<PathBuf>, C: Into<String>>(files: impl IntoIterator<Item = (P, C)>) -> Self {
+        MemoryFs {
+            files: Mutex::new(
+                files
+                    .into_iter()
+                    .map(|(path, content)| (path.into(), content.into()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Content of `path`, `None` when it does not exist.
+    pub fn get(&self, path: impl AsRef<Path>) -> Option<String> {
+        self.files.lock().ok()?.get(path.as_ref()).cloned()
+    }
+
+    /// Every file with its content, sorted by path.
+    pub fn files(&self) -> BTreeMap<PathBuf, String> {
+        self.files
+            .lock()
+            .map(|files| files.clone())
+            .unwrap_or_default()
+    }
+
+    /// Paths of the files named `filename`, sorted.
+    pub fn paths_named(&self, filename: &str) -> Vec<PathBuf> {
+        self.files
+            .lock()
+            .map(|files| {
+                files
+                    .keys()
+                    .filter(|path| path.file_name().is_some_and(|name| name == filename))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl FileSystem for MemoryFs {
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        self.get(path)
+            .ok_or_else(|| anyhow!("failed to read {}: no such file", path.display()))
+    }
+
+    fn write(&self, path: &Path, content: &str) -> Result<()> {
+        let mut files = self
+            .files
+            .lock()
+            .map_err(|_| anyhow!("failed to write {}: poisoned", path.display()))?;
+        let content = keep_line_endings(files.get(path).map(String::as_str), content);
+        files.insert(path.to_path_buf(), content);
+        Ok(())
+    }
+}
+
+/// `content` with the line endings of `existing`, the file it replaces.
+fn keep_line_endings(existing: Option<&str>, content: &str) -> String {
+    match existing {
+        Some(existing) => LineEnding::detect(existing).apply(content),
+        None => content.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_memory_fs() {
+        let fs = MemoryFs::new([
+            ("Cargo.toml", "[workspace]\n"),
+            ("a/Cargo.toml", "[package]\n"),
+            ("a/src/lib.rs", ""),
+        ]);
+        assert_eq!(
+            fs.paths_named("Cargo.toml"),
+            vec![PathBuf::from("Cargo.toml"), PathBuf::from("a/Cargo.toml")]
+        );
+        fs.write(Path::new("a/Cargo.toml"), "[package]\nname = \"a\"\n")
+            .unwrap();
+        assert_eq!(
+            fs.read_to_string(Path::new("a/Cargo.toml")).unwrap(),
+            "[package]\nname = \"a\"\n"
+        );
+        assert!(fs.read_to_string(Path::new("b/Cargo.toml")).is_err());
+    }
+
+    #[test]
+    fn test_write_keeps_line_endings() {
+        let fs = MemoryFs::new([("Cargo.toml", "[package]\r\nname = \"a\"\r\n")]);
+        fs.write(
+            Path::new("Cargo.toml"),
+            "[package]\r\nname = \"a\"\nversion = \"1.0.0\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            fs.get("Cargo.toml").unwrap(),
+            "[package]\r\nname = \"a\"\r\nversion = \"1.0.0\"\r\n"
+        );
+    }
+}
@@ -0,0 +1,278 @@
+//! Golden tests of manifest rewriting.
+//!
+//! A case is a directory holding `case.toml`, naming the operation, an
+//! `input/` workspace tree and the `expected/` tree after the operation:
+//!
+//! ```toml
+//! command = "bump-version"
+//! level = "patch"
+//! ```
+//!
+//! ```toml
+//! command = "update-crate"
+//! package = "solana-frozen-abi"
+//! from = "3.0.0"
+//! to = "3.1.0"
+//! exclude-paths = ["f"]
+//! ```
+//!
+//! The operation runs on an in-memory copy of `input/`, so cases never touch
+//! the checkout and run in parallel. Set `XTASK_BLESS=1` to rewrite the
+//! `expected/` trees from the actual output after reviewing a change.
+
+use {
+    anyhow::{anyhow, Context, Result},
+    clap::ValueEnum,
+    serde::Deserialize,
+    std::{
+        collections::{BTreeMap, BTreeSet},
+        env, fs,
+        path::{Path, PathBuf},
+    },
+    toml_edit::DocumentMut,
+    walkdir::WalkDir,
+    xtask::{
+        commands::{
+            bump_version::{bump_manifests, bump_version, BumpLevel},
+            update_crate::{self, update_manifests},
+        },
+        utils::vfs::MemoryFs,
+    },
+};
+
+pub const BLESS_ENV: &str = "XTASK_BLESS";
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "command", rename_all = "kebab-case", deny_unknown_fields)]
+pub enum Operation {
+    BumpVersion {
+        level: String,
+    },
+    #[serde(rename_all = "kebab-case")]
+    UpdateCrate {
+        package: String,
+        from: String,
+        to: String,
+        #[serde(default)]
+        exclude_paths: Vec<PathBuf>,
+    },
+}
+
+/// Runs every case under `dir`, failing with the diffs of all cases that do
+/// not match their golden output.
+pub fn run_cases(dir: &Path) -> Result<()> {
+    let mut cases: Vec<PathBuf> = fs::read_dir(dir)
+        .context(format!("failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.join("case.toml").is_file())
+        .collect();
+    cases.sort();
+    if cases.is_empty() {
+        return Err(anyhow!("no cases found in {}", dir.display()));
+    }
+    let failures: Vec<String> = cases
+        .iter()
+        .filter_map(|case| run_case(case).err().map(|e| format!("{e:#}")))
+        .collect();
+    if !failures.is_empty() {
+        return Err(anyhow!("{}", failures.join("\n\n")));
+    }
+    Ok(())
+}
+
+/// Runs one case and compares, or with `XTASK_BLESS` set rewrites, its
+/// `expected/` tree.
+pub fn run_case(case: &Path) -> Result<()> {
+    let operation: Operation = toml_edit::de::from_str(
+        &fs::read_to_string(case.join("case.toml"))
+            .context(format!("failed to read {}/case.toml", case.display()))?,
+    )
+    .context(format!("failed to parse {}/case.toml", case.display()))?;
+    let fs = load_tree(&case.join("input"))?;
+    apply(&fs, &operation)?;
+    let actual = fs.files();
+
+    let expected_dir = case.join("expected");
+    if env::var_os(BLESS_ENV).is_some() {
+        return write_tree(&expected_dir, &actual);
+    }
+    let expected = load_tree(&expected_dir)?.files();
+    let diffs = compare(&expected, &actual);
+    if !diffs.is_empty() {
+        return Err(anyhow!(
+            "{} does not match its golden output, rerun with {BLESS_ENV}=1 to accept:\n{}",
+            case.display(),
+            diffs.join("\n")
+        ));
+    }
+    Ok(())
+}
+
+/// Every file under `dir`, keyed by its path relative to `dir`.
+pub fn load_tree(dir: &Path) -> Result<MemoryFs> {
+    let mut files = vec![];
+    for entry in WalkDir::new(dir) {
+        let entry = entry.context(format!("failed to walk {}", dir.display()))?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let content = fs::read_to_string(entry.path())
+            .context(format!("failed to read {}", entry.path().display()))?;
+        files.push((entry.path().strip_prefix(dir)?.to_path_buf(), content));
+    }
+    Ok(MemoryFs::new(files))
+}
+
+fn write_tree(dir: &Path, files: &BTreeMap<PathBuf, String>) -> Result<()> {
+    if dir.exists() {
+        fs::remove_dir_all(dir).context(format!("failed to remove {}", dir.display()))?;
+    }
+    for (path, content) in files.iter() {
+        let path = dir.join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context(format!("failed to create {}", parent.display()))?;
+        }
+        fs::write(&path, content).context(format!("failed to write {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Runs `operation` on the manifests of `fs` the way the command would on a
+/// checkout.
+pub fn apply(fs: &MemoryFs, operation: &Operation) -> Result<()> {
+    let manifests = fs.paths_named("Cargo.toml");
+    match operation {
+        Operation::BumpVersion { level } => {
+            let level = BumpLevel::from_str(level, true)
+                .map_err(|e| anyhow!("invalid bump level {level}: {e}"))?;
+            let files = fs.files();
+            let root: DocumentMut = files
+                .get(Path::new("Cargo.toml"))
+                .ok_or_else(|| anyhow!("the input has no root Cargo.toml"))?
+                .parse()?;
+            let current = root
+                .get("workspace")
+                .and_then(|w| w.get("package"))
+                .and_then(|p| p.get("version"))
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| anyhow!("the root Cargo.toml has no workspace.package.version"))?;
+            let current = semver::Version::parse(current)?;
+            let all_crates: Vec<String> = manifests
+                .iter()
+                .filter_map(|path| files.get(path)?.parse::<DocumentMut>().ok())
+                .filter_map(|doc| Some(doc.get("package")?.get("name")?.as_str()?.to_string()))
+                .collect();
+            let new = bump_version(&level, &current)?;
+            bump_manifests(fs, &manifests, &all_crates, &current, &new)
+        }
+        Operation::UpdateCrate {
+            package,
+            from,
+            to,
+            exclude_paths,
+        } => update_manifests(
+            fs,
+            &manifests,
+            &update_crate::CommandArgs {
+                root_path: PathBuf::from("."),
+                package: package.clone(),
+                from: from.clone(),
+                to: to.clone(),
+                exclude_paths: exclude_paths.clone(),
+            },
+        ),
+    }
+}
+
+/// A line diff per file that differs, missing or unexpected.
+pub fn compare(
+    expected: &BTreeMap<PathBuf, String>,
+    actual: &BTreeMap<PathBuf, String>,
+) -> Vec<String> {
+    let paths: BTreeSet<&PathBuf> = expected.keys().chain(actual.keys()).collect();
+    let mut diffs = vec![];
+    for path in paths {
+        match (expected.get(path), actual.get(path)) {
+            (Some(want), Some(got)) if want == got => {}
+            (Some(want), Some(got)) => {
+                diffs.push(format!("--- {}\n{}", path.display(), line_diff(want, got)))
+            }
+            (Some(_), None) => diffs.push(format!("--- {} is missing", path.display())),
+            (None, _) => diffs.push(format!("+++ {} is unexpected", path.display())),
+        }
+    }
+    diffs
+}
+
+/// `-`/`+` lines between `expected` and `actual` with unchanged lines
+/// indented, from their longest common subsequence.
+pub fn line_diff(expected: &str, actual: &str) -> String {
+    let want: Vec<&str> = expected.lines().collect();
+    let got: Vec<&str> = actual.lines().collect();
+    // lcs[i][j]: common lines of want[i..] and got[j..]
+    let mut lcs = vec![vec![0usize; got.len().saturating_add(1)]; want.len().saturating_add(1)];
+    for i in (0..want.len()).rev() {
+        for j in (0..got.len()).rev() {
+            lcs[i][j] = if want[i] == got[j] {
+                lcs[i.saturating_add(1)][j.saturating_add(1)].saturating_add(1)
+            } else {
+                lcs[i.saturating_add(1)][j].max(lcs[i][j.saturating_add(1)])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = String::new();
+    while i < want.len() || j < got.len() {
+        if i < want.len() && j < got.len() && want[i] == got[j] {
+            diff.push_str(&format!("  {}\n", want[i]));
+            i = i.saturating_add(1);
+            j = j.saturating_add(1);
+        } else if i < want.len()
+            && (j == got.len() || lcs[i.saturating_add(1)][j] >= lcs[i][j.saturating_add(1)])
+        {
+            diff.push_str(&format!("- {}\n", want[i]));
+            i = i.saturating_add(1);
+        } else {
+            diff.push_str(&format!("+ {}\n", got[j]));
+            j = j.saturating_add(1);
+        }
+    }
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, pretty_assertions::assert_eq};
+
+    #[test]
+    fn test_line_diff() {
+        assert_eq!(
+            line_diff(
+                "[package]\nversion = \"1.0.0\"\nedition = \"2021\"\n",
+                "[package]\nversion = \"1.0.1\"\nedition = \"2021\"\n"
+            ),
+            "  [package]\n- version = \"1.0.0\"\n+ version = \"1.0.1\"\n  edition = \"2021\"\n"
+        );
+        assert_eq!(line_diff("a\n", "a\nb\n"), "  a\n+ b\n");
+    }
+
+    #[test]
+    fn test_compare() {
+        let tree = |files: &[(&str, &str)]| {
+            files
+                .iter()
+                .map(|(path, content)| (PathBuf::from(path), content.to_string()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        assert_eq!(
+            compare(
+                &tree(&[("a/Cargo.toml", "a\n"), ("b/Cargo.toml", "b\n")]),
+                &tree(&[("a/Cargo.toml", "a\n"), ("c/Cargo.toml", "c\n")])
+            ),
+            vec![
+                "--- b/Cargo.toml is missing",
+                "+++ c/Cargo.toml is unexpected"
+            ]
+        );
+    }
+}
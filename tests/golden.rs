@@ -0,0 +1,11 @@
+mod common;
+
+use {common::golden, std::path::Path};
+
+#[test]
+fn test_golden_manifests() {
+    let cases = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    if let Err(e) = golden::run_cases(&cases) {
+        panic!("{e:#}");
+    }
+}
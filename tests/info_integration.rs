@@ -0,0 +1,21 @@
+#[test]
+fn test_info_json() {
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args(["info", "--json", "--verbose"])
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "info command should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let info: serde_json::Value =
+        serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).unwrap();
+    assert_eq!(info["crates"].as_u64().unwrap(), 1);
+    assert_eq!(info["private"][0].as_str().unwrap(), "xtask");
+    assert!(info["nested_workspaces"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|w| w.as_str().unwrap() == "tests/dummy-workspace"));
+}
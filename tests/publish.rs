@@ -165,3 +165,101 @@ fn test_publish_test() {
     assert!(stdout.contains("c published"));
     assert!(stdout.contains("d published"));
 }
+
+#[test]
+fn test_publish_plan() {
+    let current_file_path_str = file!();
+    let workspace_path = fs::canonicalize(
+        Path::new(current_file_path_str)
+            .parent()
+            .unwrap()
+            .join("dummy-workspace-publish-test"),
+    )
+    .unwrap();
+
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args([
+            "publish",
+            "--manifest-path",
+            workspace_path.join("Cargo.toml").to_str().unwrap(),
+            "plan",
+        ])
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "publish plan command should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let plan: serde_json::Value =
+        serde_json::from_str(&String::from_utf8_lossy(&output.stdout)).unwrap();
+    assert!(!plan["git_commit"].as_str().unwrap().is_empty());
+    assert_eq!(plan["levels"].as_array().unwrap().len(), 4);
+    assert_eq!(plan["levels"][3][0]["name"].as_str().unwrap(), "d");
+    assert_eq!(plan["levels"][3][0]["version"].as_str().unwrap(), "1.0.0");
+}
+
+#[test]
+fn test_publish_ref_checks_the_tag_of_the_ref() {
+    let repo = tempfile::tempdir().unwrap();
+    let git = |args: &[&str]| {
+        let output = std::process::Command::new("git")
+            .args([
+                "-c",
+                "user.name=xtask",
+                "-c",
+                "user.email=xtask@example.com",
+            ])
+            .args(args)
+            .current_dir(repo.path())
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?} failed: {output:?}");
+    };
+    let write_version = |version: &str| {
+        fs::write(
+            repo.path().join("Cargo.toml"),
+            format!(
+                "[workspace]\nmembers = [\"a\"]\nresolver = \"2\"\n\n[workspace.package]\nversion = \"{version}\"\n"
+            ),
+        )
+        .unwrap();
+    };
+    fs::create_dir_all(repo.path().join("a/src")).unwrap();
+    fs::write(
+        repo.path().join("a/Cargo.toml"),
+        "[package]\nname = \"a\"\nversion.workspace = true\nedition = \"2021\"\nlicense = \"MIT\"\ndescription = \"a\"\n",
+    )
+    .unwrap();
+    fs::write(repo.path().join("a/src/lib.rs"), "").unwrap();
+    fs::write(repo.path().join(".gitignore"), "target/\nCargo.lock\n").unwrap();
+    write_version("1.0.0");
+    git(&["init", "--quiet", "--initial-branch=main"]);
+    git(&["add", "-A"]);
+    git(&["commit", "--quiet", "-m", "1.0.0"]);
+    git(&["tag", "v1.0.0"]);
+    write_version("1.1.0");
+    git(&["commit", "--quiet", "-am", "1.1.0"]);
+
+    // an unreachable registry stops the run right after the preflight
+    let output = assert_cmd::cargo::cargo_bin_cmd!()
+        .args([
+            "publish",
+            "--ref",
+            "v1.0.0",
+            "--registry",
+            "local",
+            "execute",
+        ])
+        .current_dir(repo.path())
+        .env("RUST_LOG", "info")
+        .env("CARGO_REGISTRIES_LOCAL_INDEX", "sparse+http://127.0.0.1:1/")
+        .output()
+        .unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("publish preflight passed"),
+        "preflight should check v1.0.0 against the worktree: {stderr}"
+    );
+    assert!(!output.status.success());
+}